@@ -28,6 +28,7 @@ $FOREST_CLI_PATH chain set-head --epoch -10 --force
 
 echo "Test subcommand: info show"
 $FOREST_CLI_PATH info show
+$FOREST_CLI_PATH info
 
 echo "Test subcommand: net info"
 $FOREST_CLI_PATH net info
@@ -1,8 +1,14 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+mod registry;
+
+pub use registry::{TokenInfo, TokenRegistry, AUTH_TOKENS_FILE};
+
 use crate::key_management::KeyInfo;
 use crate::shim::crypto::SignatureType;
+use anyhow::Context as _;
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine as _};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, errors::Result as JWTResult, DecodingKey, EncodingKey, Header};
 use rand::Rng;
@@ -38,11 +44,15 @@ pub enum Error {
 
 /// Claim structure for JWT Tokens
 #[derive(Debug, Serialize, Deserialize)]
-struct Claims {
+pub struct Claims {
     #[serde(rename = "Allow")]
-    allow: Vec<String>,
+    pub allow: Vec<String>,
     // Expiration time (as UTC timestamp)
-    exp: usize,
+    pub exp: usize,
+    /// ID of the token, for the tokens that are recorded in a
+    /// [`TokenRegistry`] and can be revoked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
 }
 
 /// Create a new JWT Token
@@ -51,15 +61,51 @@ pub fn create_token(perms: Vec<String>, key: &[u8], token_exp: Duration) -> JWTR
     let payload = Claims {
         allow: perms,
         exp: exp_time.timestamp() as usize,
+        jti: None,
     };
     encode(&Header::default(), &payload, &EncodingKey::from_secret(key))
 }
 
-/// Verify JWT Token and return the allowed permissions from token
-pub fn verify_token(token: &str, key: &[u8]) -> JWTResult<Vec<String>> {
+/// Create a new JWT Token with a random ID, and the metadata to record in a
+/// [`TokenRegistry`]
+pub fn issue_token(
+    perms: Vec<String>,
+    key: &[u8],
+    token_exp: Duration,
+) -> JWTResult<(String, TokenInfo)> {
+    let now = Utc::now();
+    let info = TokenInfo {
+        id: hex::encode(rand::thread_rng().gen::<[u8; 16]>()),
+        allow: perms,
+        issued_at: now.timestamp(),
+        expires_at: (now + token_exp).timestamp(),
+    };
+    let payload = Claims {
+        allow: info.allow.clone(),
+        exp: info.expires_at as usize,
+        jti: Some(info.id.clone()),
+    };
+    let token = encode(&Header::default(), &payload, &EncodingKey::from_secret(key))?;
+    Ok((token, info))
+}
+
+/// Verify JWT Token and return its claims
+pub fn decode_token(token: &str, key: &[u8]) -> JWTResult<Claims> {
     let validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::default());
     let token = decode::<Claims>(token, &DecodingKey::from_secret(key), &validation)?;
-    Ok(token.claims.allow)
+    Ok(token.claims)
+}
+
+/// Verify JWT Token and return the allowed permissions from token
+pub fn verify_token(token: &str, key: &[u8]) -> JWTResult<Vec<String>> {
+    Ok(decode_token(token, key)?.allow)
+}
+
+/// Read the ID of a JWT Token, without verifying it
+pub fn token_id(token: &str) -> anyhow::Result<String> {
+    let payload = token.split('.').nth(1).context("malformed token")?;
+    let claims: Claims = serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(payload)?)?;
+    claims.jti.context("the token has no ID")
 }
 
 pub fn generate_priv_key() -> KeyInfo {
@@ -111,4 +157,23 @@ mod tests {
         let perms = verify_token(&token, key.private_key()).unwrap();
         assert_eq!(perms_expected, perms);
     }
+
+    #[test]
+    fn issue_and_decode_token() {
+        let key = generate_priv_key();
+        let (token, info) = issue_token(
+            vec!["read".to_owned()],
+            key.private_key(),
+            Duration::hours(1),
+        )
+        .unwrap();
+        let claims = decode_token(&token, key.private_key()).unwrap();
+        assert_eq!(claims.allow, info.allow);
+        assert_eq!(claims.exp, info.expires_at as usize);
+        assert_eq!(claims.jti.as_ref(), Some(&info.id));
+        assert_eq!(token_id(&token).unwrap(), info.id);
+
+        let token = create_token(vec![], key.private_key(), Duration::hours(1)).unwrap();
+        assert!(token_id(&token).is_err());
+    }
 }
@@ -0,0 +1,136 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::lotus_json::lotus_json_with_self;
+use anyhow::Context as _;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+/// File of the data directory the issued tokens are persisted to.
+pub const AUTH_TOKENS_FILE: &str = "auth_tokens.json";
+
+/// Metadata of a token issued by the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TokenInfo {
+    /// The `jti` claim of the token
+    pub id: String,
+    pub allow: Vec<String>,
+    /// Issuance time (as UTC timestamp)
+    pub issued_at: i64,
+    /// Expiration time (as UTC timestamp)
+    pub expires_at: i64,
+}
+
+lotus_json_with_self!(TokenInfo);
+
+impl TokenInfo {
+    fn is_expired(&self) -> bool {
+        self.expires_at < Utc::now().timestamp()
+    }
+}
+
+/// Registry of the tokens issued by the node. Tokens with an ID are only valid
+/// while they are in the registry, so removing them revokes them.
+#[derive(Debug, Default)]
+pub struct TokenRegistry {
+    /// File the registry is persisted to, if any
+    path: Option<PathBuf>,
+    tokens: BTreeMap<String, TokenInfo>,
+}
+
+impl TokenRegistry {
+    /// Loads the registry persisted to `path`, or creates an empty one if the
+    /// file does not exist.
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let tokens = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice::<Vec<TokenInfo>>(&bytes)
+                .with_context(|| format!("invalid token registry {}", path.display()))?
+                .into_iter()
+                .map(|info| (info.id.clone(), info))
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path: Some(path),
+            tokens,
+        })
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.tokens.contains_key(id)
+    }
+
+    /// Lists the tokens that have not expired yet.
+    pub fn list(&self) -> Vec<TokenInfo> {
+        self.tokens
+            .values()
+            .filter(|info| !info.is_expired())
+            .cloned()
+            .collect()
+    }
+
+    /// Records a new token, dropping the expired ones.
+    pub fn insert(&mut self, info: TokenInfo) -> anyhow::Result<()> {
+        self.tokens.retain(|_, info| !info.is_expired());
+        self.tokens.insert(info.id.clone(), info);
+        self.persist()
+    }
+
+    /// Removes a token, returns whether it was in the registry.
+    pub fn remove(&mut self, id: &str) -> anyhow::Result<bool> {
+        if self.tokens.remove(id).is_none() {
+            return Ok(false);
+        }
+        self.persist()?;
+        Ok(true)
+    }
+
+    fn persist(&self) -> anyhow::Result<()> {
+        if let Some(path) = &self.path {
+            let tokens: Vec<_> = self.tokens.values().collect();
+            std::fs::write(path, serde_json::to_vec_pretty(&tokens)?)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(id: &str, expires_at: i64) -> TokenInfo {
+        TokenInfo {
+            id: id.into(),
+            allow: vec!["read".into()],
+            issued_at: 0,
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn persisted_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(AUTH_TOKENS_FILE);
+        let valid = Utc::now().timestamp() + 3600;
+
+        let mut registry = TokenRegistry::load(path.clone()).unwrap();
+        registry.insert(token("a", valid)).unwrap();
+        registry.insert(token("b", valid)).unwrap();
+        registry.insert(token("expired", 0)).unwrap();
+        assert_eq!(registry.list(), vec![token("a", valid), token("b", valid)]);
+
+        assert!(registry.remove("a").unwrap());
+        assert!(!registry.remove("a").unwrap());
+
+        let registry = TokenRegistry::load(path).unwrap();
+        assert!(!registry.contains("a"));
+        assert!(registry.contains("b"));
+        assert_eq!(registry.list(), vec![token("b", valid)]);
+    }
+}
@@ -3,6 +3,12 @@
 
 use std::borrow::Cow;
 
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::metrics;
+
+use crate::db::{setting_keys::DRAND_ENTRY_KEY_PREFIX, SettingsStore};
 use crate::shim::clock::ChainEpoch;
 use crate::shim::version::NetworkVersion;
 use crate::utils::net::global_http_client;
@@ -10,8 +16,10 @@ use ahash::HashMap;
 use anyhow::Context as _;
 use async_trait::async_trait;
 use bls_signatures::{PublicKey, Serialize, Signature};
+use blstrs::{G1Affine, G1Projective, G2Affine};
 use byteorder::{BigEndian, WriteBytesExt};
-use parking_lot::RwLock;
+use group::prime::PrimeCurveAffine as _;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize as SerdeDeserialize, Serialize as SerdeSerialize};
 use sha2::Digest;
 
@@ -41,18 +49,23 @@ impl DrandPublic {
 #[derive(PartialEq, Eq, Clone)]
 pub enum DrandNetwork {
     Mainnet,
+    Quicknet,
     Incentinet,
 }
 
 #[derive(Clone)]
 /// Configuration used when initializing a `Drand` beacon.
 pub struct DrandConfig<'a> {
-    /// URL endpoint to send JSON HTTP requests to.
-    pub server: &'static str,
+    /// URL endpoints to send JSON HTTP requests to, in order of preference.
+    pub servers: &'static [&'static str],
     /// Info about the beacon chain, used to verify correctness of endpoint.
     pub chain_info: ChainInfo<'a>,
     /// Network type
     pub network_type: DrandNetwork,
+    /// Whether the signature of each round covers the signature of the
+    /// previous one. Unchained networks (`quicknet`) sign the round number
+    /// alone, with the public key on G2 and signatures on G1.
+    pub is_chained: bool,
 }
 
 /// Contains the vector of `BeaconPoint`, which are mappings of epoch to the
@@ -195,12 +208,90 @@ pub struct BeaconEntryJson {
     previous_signature: String,
 }
 
+/// Time an endpoint is avoided after its first failure. It doubles with each
+/// consecutive failure, up to [`MAX_ENDPOINT_BACKOFF`].
+const ENDPOINT_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_ENDPOINT_BACKOFF: Duration = Duration::from_secs(300);
+const ENDPOINT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A `drand` HTTP endpoint and its health, which is inferred from the outcome
+/// of the requests sent to it.
+struct Endpoint {
+    url: &'static str,
+    health: Mutex<EndpointHealth>,
+}
+
+#[derive(Default)]
+struct EndpointHealth {
+    consecutive_failures: u32,
+    /// Until when the endpoint is only used if the healthy ones fail.
+    retry_at: Option<Instant>,
+}
+
+impl Endpoint {
+    fn new(url: &'static str) -> Self {
+        Self {
+            url,
+            health: Default::default(),
+        }
+    }
+
+    /// `None` for healthy endpoints, which sorts them first.
+    fn retry_at(&self) -> Option<Instant> {
+        self.health
+            .lock()
+            .retry_at
+            .filter(|retry_at| *retry_at > Instant::now())
+    }
+
+    async fn fetch(&self, round: u64) -> anyhow::Result<BeaconEntry> {
+        let start = Instant::now();
+        let result = async {
+            let resp: BeaconEntryJson = global_http_client()
+                .get(format!("{}/public/{}", self.url, round))
+                .timeout(ENDPOINT_TIMEOUT)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            anyhow::Ok(BeaconEntry::new(resp.round, hex::decode(resp.signature)?))
+        }
+        .await;
+
+        let mut health = self.health.lock();
+        match &result {
+            Ok(_) => {
+                metrics::DRAND_REQUEST_TIME
+                    .with_label_values(&[self.url])
+                    .observe(start.elapsed().as_secs_f64());
+                *health = EndpointHealth::default();
+            }
+            Err(_) => {
+                metrics::DRAND_REQUEST_ERRORS
+                    .with_label_values(&[self.url])
+                    .inc();
+                health.consecutive_failures += 1;
+                let backoff = ENDPOINT_BACKOFF
+                    .saturating_mul(1 << (health.consecutive_failures - 1).min(16))
+                    .min(MAX_ENDPOINT_BACKOFF);
+                health.retry_at = Some(Instant::now() + backoff);
+            }
+        }
+        metrics::DRAND_ENDPOINT_HEALTHY
+            .with_label_values(&[self.url])
+            .set(result.is_ok() as i64);
+        result
+    }
+}
+
 /// `Drand` randomness beacon that can be used to generate randomness for the
 /// Filecoin chain. Primary use is to satisfy the [Beacon] trait.
 pub struct DrandBeacon {
-    url: &'static str,
+    endpoints: Vec<Endpoint>,
 
     pub_key: DrandPublic,
+    is_chained: bool,
     /// Interval between beacons, in seconds.
     interval: u64,
     drand_gen_time: u64,
@@ -209,6 +300,9 @@ pub struct DrandBeacon {
 
     /// Keeps track of computed beacon entries.
     local_cache: RwLock<HashMap<u64, BeaconEntry>>,
+    /// Keeps verified entries across restarts, keyed by chain hash and round.
+    persistent_cache: Option<Arc<dyn SettingsStore + Send + Sync>>,
+    chain_hash: String,
 }
 
 impl DrandBeacon {
@@ -219,7 +313,7 @@ impl DrandBeacon {
         let chain_info = &config.chain_info;
 
         if cfg!(debug_assertions) && config.network_type == DrandNetwork::Mainnet {
-            let server = config.server;
+            let server = config.servers[0];
             let remote_chain_info = std::thread::spawn(move || {
                 let rt = tokio::runtime::Runtime::new()?;
                 rt.block_on(async {
@@ -242,60 +336,143 @@ impl DrandBeacon {
         }
 
         Self {
-            url: config.server,
+            endpoints: config.servers.iter().copied().map(Endpoint::new).collect(),
             pub_key: DrandPublic {
                 coefficient: hex::decode(chain_info.public_key.as_ref())
                     .expect("invalid static encoding of drand hex public key"),
             },
+            is_chained: config.is_chained,
             interval: chain_info.period as u64,
             drand_gen_time: chain_info.genesis_time as u64,
             fil_round_time: interval,
             fil_gen_time: genesis_ts,
             local_cache: Default::default(),
+            persistent_cache: None,
+            chain_hash: chain_info.hash.to_string(),
         }
     }
+
+    /// Persists verified entries to `store`, so they are neither fetched nor
+    /// verified again.
+    pub fn with_persistent_cache(mut self, store: Arc<dyn SettingsStore + Send + Sync>) -> Self {
+        self.persistent_cache = Some(store);
+        self
+    }
+
+    fn cache_key(&self, round: u64) -> String {
+        format!("{DRAND_ENTRY_KEY_PREFIX}/{}/{round}", self.chain_hash)
+    }
+
+    fn cached_entry(&self, round: u64) -> Option<BeaconEntry> {
+        if let Some(entry) = self.local_cache.read().get(&round) {
+            return Some(entry.clone());
+        }
+        let store = self.persistent_cache.as_ref()?;
+        match store.read_bin(&self.cache_key(round)) {
+            Ok(Some(data)) => {
+                let entry = BeaconEntry::new(round, data);
+                self.local_cache.write().insert(round, entry.clone());
+                Some(entry)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                tracing::warn!("failed to read cached drand entry {round}: {e}");
+                None
+            }
+        }
+    }
+
+    pub(super) fn cache_entry(&self, entry: &BeaconEntry) {
+        self.local_cache
+            .write()
+            .insert(entry.round(), entry.clone());
+        if let Some(store) = &self.persistent_cache {
+            if let Err(e) = store.write_bin(&self.cache_key(entry.round()), entry.data()) {
+                tracing::warn!("failed to cache drand entry {}: {e}", entry.round());
+            }
+        }
+    }
+}
+
+/// Domain separation tag of the signatures of unchained `drand` networks.
+const UNCHAINED_DST: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_";
+
+/// Verifies an entry of an unchained network, whose signature is over
+/// `H(curr_round)` on G1.
+fn verify_unchained(pub_key: &[u8], entry: &BeaconEntry) -> anyhow::Result<bool> {
+    let pub_key: [u8; 96] = pub_key
+        .try_into()
+        .context("unchained drand public keys are 96 bytes")?;
+    let pub_key = Option::<G2Affine>::from(G2Affine::from_compressed(&pub_key))
+        .context("invalid drand public key")?;
+    let Ok(sig) = <[u8; 48]>::try_from(entry.data()) else {
+        return Ok(false);
+    };
+    let Some(sig) = Option::<G1Affine>::from(G1Affine::from_compressed(&sig)) else {
+        return Ok(false);
+    };
+    let digest = sha2::Sha256::digest(entry.round().to_be_bytes());
+    let point = G1Affine::from(G1Projective::hash_to_curve(&digest, UNCHAINED_DST, &[]));
+    // e(sig, g2) == e(H(m), pk)
+    Ok(blstrs::pairing(&sig, &G2Affine::generator()) == blstrs::pairing(&point, &pub_key))
 }
 
 #[async_trait]
 impl Beacon for DrandBeacon {
     fn verify_entry(&self, curr: &BeaconEntry, prev: &BeaconEntry) -> Result<bool, anyhow::Error> {
-        // TODO(forest): https://github.com/ChainSafe/forest/issues/3572
-        if prev.round() == 0 {
+        // Cached entries have been verified already.
+        if self.cached_entry(curr.round()).as_ref() == Some(curr) {
             return Ok(true);
         }
 
-        // Hash the messages
-        let mut msg: Vec<u8> = Vec::with_capacity(104);
-        msg.extend_from_slice(prev.data());
-        msg.write_u64::<BigEndian>(curr.round())?;
-        // H(prev sig | curr_round)
-        let digest = sha2::Sha256::digest(&msg);
-        // Signature
-        let sig = Signature::from_bytes(curr.data())?;
-        let sig_match = bls_signatures::verify_messages(&sig, &[&digest], &[self.pub_key.key()?]);
+        let sig_match = if self.is_chained {
+            // TODO(forest): https://github.com/ChainSafe/forest/issues/3572
+            if prev.round() == 0 {
+                return Ok(true);
+            }
+
+            // Hash the messages
+            let mut msg: Vec<u8> = Vec::with_capacity(104);
+            msg.extend_from_slice(prev.data());
+            msg.write_u64::<BigEndian>(curr.round())?;
+            // H(prev sig | curr_round)
+            let digest = sha2::Sha256::digest(&msg);
+            // Signature
+            let sig = Signature::from_bytes(curr.data())?;
+            bls_signatures::verify_messages(&sig, &[&digest], &[self.pub_key.key()?])
+        } else {
+            verify_unchained(&self.pub_key.coefficient, curr)?
+        };
 
         // Cache the result
-        let contains_curr = self.local_cache.read().contains_key(&curr.round());
-        if sig_match && !contains_curr {
-            self.local_cache.write().insert(curr.round(), curr.clone());
+        if sig_match {
+            self.cache_entry(curr);
         }
         Ok(sig_match)
     }
 
     async fn entry(&self, round: u64) -> Result<BeaconEntry, anyhow::Error> {
-        let cached: Option<BeaconEntry> = self.local_cache.read().get(&round).cloned();
-        match cached {
+        match self.cached_entry(round) {
             Some(cached_entry) => Ok(cached_entry),
             None => {
-                let client = global_http_client();
-                let resp: BeaconEntryJson = client
-                    .get(format!("{}/public/{}", self.url, round))
-                    .send()
-                    .await?
-                    .error_for_status()?
-                    .json()
-                    .await?;
-                Ok(BeaconEntry::new(resp.round, hex::decode(resp.signature)?))
+                // Healthy endpoints first, but try all of them before giving
+                // up, as block validation waits for the entry.
+                let mut endpoints = self.endpoints.iter().collect::<Vec<_>>();
+                endpoints.sort_by_key(|endpoint| endpoint.retry_at());
+                let mut last_error = None;
+                for endpoint in endpoints {
+                    match endpoint.fetch(round).await {
+                        Ok(entry) => return Ok(entry),
+                        Err(e) => {
+                            tracing::warn!(
+                                "failed to fetch drand round {round} from {}: {e}",
+                                endpoint.url
+                            );
+                            last_error = Some(e);
+                        }
+                    }
+                }
+                Err(last_error.unwrap_or_else(|| anyhow::anyhow!("no drand endpoint configured")))
             }
         }
     }
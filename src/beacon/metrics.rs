@@ -0,0 +1,69 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    core::{AtomicI64, AtomicU64, GenericCounterVec, GenericGaugeVec, Opts},
+    HistogramOpts, HistogramVec,
+};
+
+pub static DRAND_REQUEST_TIME: Lazy<Box<HistogramVec>> = Lazy::new(|| {
+    let drand_request_time = Box::new(
+        HistogramVec::new(
+            HistogramOpts {
+                common_opts: Opts::new(
+                    "drand_request_time",
+                    "Duration of successful requests to drand endpoints",
+                ),
+                buckets: vec![],
+            },
+            &[labels::ENDPOINT],
+        )
+        .expect("Defining the drand_request_time metric must succeed"),
+    );
+    prometheus::default_registry()
+        .register(drand_request_time.clone())
+        .expect("Registering the drand_request_time metric with the metrics registry must succeed");
+    drand_request_time
+});
+pub static DRAND_REQUEST_ERRORS: Lazy<Box<GenericCounterVec<AtomicU64>>> = Lazy::new(|| {
+    let drand_request_errors = Box::new(
+        GenericCounterVec::<AtomicU64>::new(
+            Opts::new(
+                "drand_request_errors",
+                "Number of failed requests to drand endpoints",
+            ),
+            &[labels::ENDPOINT],
+        )
+        .expect("Defining the drand_request_errors metric must succeed"),
+    );
+    prometheus::default_registry()
+        .register(drand_request_errors.clone())
+        .expect(
+            "Registering the drand_request_errors metric with the metrics registry must succeed",
+        );
+    drand_request_errors
+});
+pub static DRAND_ENDPOINT_HEALTHY: Lazy<Box<GenericGaugeVec<AtomicI64>>> = Lazy::new(|| {
+    let drand_endpoint_healthy = Box::new(
+        GenericGaugeVec::<AtomicI64>::new(
+            Opts::new(
+                "drand_endpoint_healthy",
+                "Whether the last request to a drand endpoint succeeded",
+            ),
+            &[labels::ENDPOINT],
+        )
+        .expect("Defining the drand_endpoint_healthy metric must succeed"),
+    );
+    prometheus::default_registry()
+        .register(drand_endpoint_healthy.clone())
+        .expect(
+            "Registering the drand_endpoint_healthy metric with the metrics registry must succeed",
+        );
+    drand_endpoint_healthy
+});
+
+pub mod labels {
+    /// URL of a drand endpoint.
+    pub const ENDPOINT: &str = "endpoint";
+}
@@ -3,6 +3,7 @@
 
 pub mod beacon_entries;
 mod drand;
+mod metrics;
 #[cfg(test)]
 pub mod mock_beacon;
 
@@ -9,13 +9,14 @@ fn new_beacon() -> DrandBeacon {
         15904451751,
         25,
         &DrandConfig {
-            server: "https://pl-us.incentinet.drand.sh",
+            servers: &["https://pl-us.incentinet.drand.sh"],
             chain_info: ChainInfo {
                 public_key: "922a2e93828ff83345bae533f5172669a26c02dc76d6bf59c80892e12ab1455c229211886f35bb56af6d5bea981024df"
                     .into(),
                 ..Default::default()
             },
             network_type: crate::beacon::DrandNetwork::Incentinet,
+            is_chained: true,
         },
     )
 }
@@ -41,3 +42,66 @@ async fn ask_and_verify_beacon_entry_fail() {
     let e3 = beacon.entry(3).await.unwrap();
     assert!(!beacon.verify_entry(&e2, &e3).unwrap());
 }
+
+#[tokio::test]
+async fn ask_and_verify_quicknet_entry() {
+    let beacon = DrandBeacon::new(
+        1598306400,
+        30,
+        &DrandConfig {
+            servers: &[
+                "https://api.drand.sh/52db9ba70e0cc0f6eaf7803dd07447a1f5477735fd3f661792ba94600c84e971",
+            ],
+            chain_info: ChainInfo {
+                public_key: "83cf0f2896adee7eb8b5f01fcad3912212c437e0073e911fb90022d3e760183c8c4b450b6a0a6c3ac6a5776a2d1064510d1fec758c921cc22b0e17e63aaf4bcb5ed66304de9cf809bd274ca73bab4af5a6e9c76a4bc09e76eae8991ef5ece45a"
+                    .into(),
+                period: 3,
+                genesis_time: 1692803367,
+                ..Default::default()
+            },
+            network_type: crate::beacon::DrandNetwork::Quicknet,
+            is_chained: false,
+        },
+    );
+
+    let e1 = beacon.entry(1000).await.unwrap();
+    let e2 = beacon.entry(1001).await.unwrap();
+    // Unchained entries don't depend on the previous one.
+    assert!(beacon.verify_entry(&e2, &e1).unwrap());
+    assert!(beacon.verify_entry(&e1, &e2).unwrap());
+    let forged = crate::beacon::BeaconEntry::new(e2.round(), e1.data().to_vec());
+    assert!(!beacon.verify_entry(&forged, &e1).unwrap());
+}
+
+#[test]
+fn verified_entries_survive_restarts() {
+    let store = std::sync::Arc::new(crate::db::MemoryDB::default());
+    let entry = crate::beacon::BeaconEntry::new(2, vec![1; 96]);
+    let prev = crate::beacon::BeaconEntry::new(1, vec![2; 96]);
+
+    let beacon = new_beacon().with_persistent_cache(store.clone());
+    // The signature is bogus, so the entry isn't cached by verification.
+    assert!(!beacon.verify_entry(&entry, &prev).unwrap_or(false));
+    beacon.cache_entry(&entry);
+
+    let restarted = new_beacon().with_persistent_cache(store);
+    assert!(restarted.verify_entry(&entry, &prev).unwrap());
+    let forged = crate::beacon::BeaconEntry::new(2, vec![3; 96]);
+    assert!(!restarted.verify_entry(&forged, &prev).unwrap_or(false));
+}
+
+#[tokio::test]
+async fn failover_to_working_endpoint() {
+    let beacon = DrandBeacon::new(
+        15904451751,
+        25,
+        &DrandConfig {
+            // Nothing listens on the discard port.
+            servers: &["http://127.0.0.1:9", "https://pl-us.incentinet.drand.sh"],
+            chain_info: ChainInfo::default(),
+            network_type: crate::beacon::DrandNetwork::Incentinet,
+            is_chained: true,
+        },
+    );
+    assert_eq!(beacon.entry(2).await.unwrap().round(), 2);
+}
@@ -34,12 +34,35 @@ pub struct TipsetKeys {
 impl TipsetKeys {
     // Special encoding to match Lotus.
     pub fn cid(&self) -> anyhow::Result<Cid> {
-        use fvm_ipld_encoding::RawBytes;
+        Ok(Cid::from_cbor_blake2b256(&self.to_raw_bytes())?)
+    }
+
+    /// Stores the object whose CID is [`TipsetKeys::cid`], so that the key can
+    /// be loaded from it.
+    pub fn save(&self, db: &impl Blockstore) -> anyhow::Result<Cid> {
+        db.put_cbor(&self.to_raw_bytes(), cid::multihash::Code::Blake2b256)
+    }
+
+    /// Loads a key stored with [`TipsetKeys::save`].
+    pub fn load(db: &impl Blockstore, cid: &Cid) -> anyhow::Result<Option<Self>> {
+        let Some(bytes) = db.get_cbor::<fvm_ipld_encoding::RawBytes>(cid)? else {
+            return Ok(None);
+        };
+        let mut reader = bytes.bytes();
+        let mut cids = vec![];
+        while !reader.is_empty() {
+            cids.push(Cid::read_bytes(&mut reader)?);
+        }
+        Ok(Some(cids.into_iter().collect()))
+    }
+
+    /// The concatenated CIDs.
+    fn to_raw_bytes(&self) -> fvm_ipld_encoding::RawBytes {
         let mut bytes = Vec::new();
         for cid in self.cids.clone() {
             bytes.append(&mut cid.to_bytes())
         }
-        Ok(Cid::from_cbor_blake2b256(&RawBytes::new(bytes))?)
+        fvm_ipld_encoding::RawBytes::new(bytes)
     }
 }
 
@@ -718,4 +741,20 @@ mod test {
     fn ensure_there_are_blocks() {
         assert_eq!(Tipset::new(vec![]).unwrap_err(), Error::NoBlocks);
     }
+
+    #[test]
+    fn keys_are_loaded_from_their_cid() {
+        let db = crate::db::MemoryDB::default();
+        let key = TipsetKeys::from_iter([
+            Cid::new_v1(DAG_CBOR, Identity.digest(&[1])),
+            Cid::new_v1(DAG_CBOR, Identity.digest(&[2])),
+        ]);
+        let cid = key.save(&db).unwrap();
+        assert_eq!(cid, key.cid().unwrap());
+        assert_eq!(TipsetKeys::load(&db, &cid).unwrap(), Some(key));
+        assert_eq!(
+            TipsetKeys::load(&db, &TipsetKeys::default().cid().unwrap()).unwrap(),
+            None
+        );
+    }
 }
@@ -59,3 +59,30 @@ pub async fn export<D: Digest>(
 
     Ok(digest)
 }
+
+/// Exports the block headers of `tipset` and the state tree they refer to,
+/// without any messages or older headers.
+pub async fn export_state(
+    db: impl Blockstore + Send + Sync + 'static,
+    tipset: &Tipset,
+    writer: impl AsyncWrite + Unpin,
+) -> anyhow::Result<()> {
+    let db = Arc::new(db);
+    let roots = tipset.key().cids.clone().into_iter().collect();
+    let mut writer = BufWriter::new(writer);
+
+    let blocks = par_buffer(
+        1024,
+        stream_chain(
+            Arc::clone(&db),
+            std::iter::once(tipset.clone()),
+            tipset.epoch() - 1,
+        )
+        .without_messages(),
+    );
+
+    let frames = forest::Encoder::compress_stream_default(blocks);
+    forest::Encoder::write(&mut writer, roots, frames).await?;
+    writer.flush().await.context("failed to flush")?;
+    Ok(())
+}
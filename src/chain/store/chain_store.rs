@@ -133,10 +133,17 @@ where
         Ok(cs)
     }
 
+    /// Settings store of the chain.
+    pub fn settings(&self) -> Arc<dyn SettingsStore + Sync + Send> {
+        self.settings.clone()
+    }
+
     /// Sets heaviest tipset within `ChainStore` and store its tipset keys in
     /// the settings store under the [`crate::db::setting_keys::HEAD_KEY`] key.
+    /// The keys are also stored in the blockstore, see [`TipsetKeys::save`].
     pub fn set_heaviest_tipset(&self, ts: Arc<Tipset>) -> Result<(), Error> {
         self.settings.write_obj(HEAD_KEY, ts.key())?;
+        ts.key().save(self.blockstore())?;
         if self.publisher.send(HeadChange::Apply(ts)).is_err() {
             debug!("did not publish head change, no active receivers");
         }
@@ -153,6 +160,7 @@ where
     /// with other compatible tracked headers.
     pub fn put_tipset(&self, ts: &Tipset) -> Result<(), Error> {
         persist_objects(self.blockstore(), ts.blocks())?;
+        ts.key().save(self.blockstore())?;
 
         // Expand tipset to include other compatible blocks at the epoch.
         let expanded = self.expand_tipset(ts.min_ticket_block().clone())?;
@@ -157,6 +157,45 @@ impl<DB: Blockstore> ChainIndex<DB> {
         })
     }
 
+    /// Returns the path from `from` to `to` through their common ancestor: the
+    /// tipsets to revert, newest first, then those to apply, oldest first.
+    /// Fails if the path is longer than `max_length` tipsets.
+    #[allow(clippy::type_complexity)]
+    pub fn path(
+        &self,
+        from: Arc<Tipset>,
+        to: Arc<Tipset>,
+        max_length: Option<usize>,
+    ) -> Result<(Vec<Arc<Tipset>>, Vec<Arc<Tipset>>), Error> {
+        let (mut left, mut right) = (from, to);
+        let (mut reverts, mut applies) = (vec![], vec![]);
+        while left.key() != right.key() {
+            if let Some(max_length) = max_length {
+                if reverts.len() + applies.len() >= max_length {
+                    return Err(Error::Other(format!(
+                        "the path is longer than {max_length} tipsets"
+                    )));
+                }
+            }
+            if left.epoch() == 0 && right.epoch() == 0 {
+                return Err(Error::Other(
+                    "the tipsets have no common ancestor".to_owned(),
+                ));
+            }
+            if left.epoch() > right.epoch() {
+                let parent = self.load_required_tipset(left.parents())?;
+                reverts.push(left);
+                left = parent;
+            } else {
+                let parent = self.load_required_tipset(right.parents())?;
+                applies.push(right);
+                right = parent;
+            }
+        }
+        applies.reverse();
+        Ok((reverts, applies))
+    }
+
     /// Finds the latest beacon entry given a tipset up to 20 tipsets behind
     pub fn latest_beacon_entry(&self, ts: &Tipset) -> Result<BeaconEntry, Error> {
         let check_for_beacon_entry = |ts: &Tipset| {
@@ -296,4 +335,37 @@ mod tests {
             &epoch2b
         );
     }
+
+    #[test]
+    fn path_reverts_then_applies() {
+        let db = Arc::new(MemoryDB::default());
+        let gen = genesis_tipset();
+        let a1 = tipset_child(&gen, 1);
+        let a2 = tipset_child(&a1, 2);
+        let b2 = tipset_child(&a1, 2);
+        let b3 = tipset_child(&b2, 3);
+        for tipset in [&gen, &a1, &a2, &b2, &b3] {
+            persist_tipset(tipset, &db);
+        }
+        let index = ChainIndex::new(db);
+        let keys = |tipsets: Vec<Arc<Tipset>>| {
+            tipsets
+                .iter()
+                .map(|tipset| tipset.key().clone())
+                .collect::<Vec<_>>()
+        };
+
+        let (reverts, applies) = index
+            .path(Arc::new(a2.clone()), Arc::new(b3.clone()), None)
+            .unwrap();
+        assert_eq!(keys(reverts), [a2.key().clone()]);
+        assert_eq!(keys(applies), [b2.key().clone(), b3.key().clone()]);
+
+        let (reverts, applies) = index
+            .path(Arc::new(b3.clone()), Arc::new(b3.clone()), None)
+            .unwrap();
+        assert!(reverts.is_empty() && applies.is_empty());
+
+        assert!(index.path(Arc::new(a2), Arc::new(b3), Some(2)).is_err());
+    }
 }
@@ -45,4 +45,18 @@ impl BadBlockCache {
     pub fn peek(&self, c: &Cid) -> Option<String> {
         self.cache.lock().peek(c).cloned()
     }
+
+    /// Removes a block `Cid` from the cache, returning the reason it was
+    /// marked bad, if it was.
+    pub fn remove(&self, c: &Cid) -> Option<String> {
+        self.cache.lock().pop(c)
+    }
+
+    /// Removes all the blocks from the cache, returning how many there were.
+    pub fn clear(&self) -> usize {
+        let mut cache = self.cache.lock();
+        let len = cache.len();
+        cache.clear();
+        len
+    }
 }
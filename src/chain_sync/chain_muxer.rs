@@ -15,6 +15,7 @@ use crate::chain::{ChainStore, Error as ChainStoreError};
 use crate::libp2p::{
     hello::HelloRequest, NetworkEvent, NetworkMessage, PeerId, PeerManager, PubsubMessage,
 };
+use crate::libp2p_bitswap::BitswapSession;
 use crate::message::SignedMessage;
 use crate::message_pool::{MessagePool, Provider};
 use crate::shim::{clock::SECONDS_IN_DAY, message::Message};
@@ -47,6 +48,7 @@ use crate::chain_sync::{
 const DEFAULT_REQUEST_WINDOW: usize = 8;
 const DEFAULT_TIPSET_SAMPLE_SIZE: usize = 5;
 const DEFAULT_RECENT_STATE_ROOTS: i64 = 2000;
+const DEFAULT_HEAD_LAG_ALERT_THRESHOLD: i64 = 10;
 
 pub(in crate::chain_sync) type WorkerState = Arc<RwLock<SyncState>>;
 
@@ -79,6 +81,7 @@ pub enum ChainMuxerError {
 /// Structure that defines syncing configuration options
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 #[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+#[serde(default)]
 pub struct SyncConfig {
     /// Request window length for tipsets during chain exchange
     #[cfg_attr(test, arbitrary(gen(|g| u32::arbitrary(g) as _)))]
@@ -90,6 +93,11 @@ pub struct SyncConfig {
     /// head is
     #[cfg_attr(test, arbitrary(gen(|g| u32::arbitrary(g) as _)))]
     pub tipset_sample_size: usize,
+    /// Number of epochs the validated head may lag behind the current epoch
+    /// before an alert is raised
+    pub head_lag_alert_threshold: i64,
+    /// URL to which head lag alerts are posted
+    pub head_lag_webhook: Option<String>,
 }
 
 impl Default for SyncConfig {
@@ -98,6 +106,8 @@ impl Default for SyncConfig {
             request_window: DEFAULT_REQUEST_WINDOW,
             recent_state_roots: DEFAULT_RECENT_STATE_ROOTS,
             tipset_sample_size: DEFAULT_TIPSET_SAMPLE_SIZE,
+            head_lag_alert_threshold: DEFAULT_HEAD_LAG_ALERT_THRESHOLD,
+            head_lag_webhook: None,
         }
     }
 }
@@ -309,18 +319,21 @@ where
             "Getting messages of gossipblock, epoch: {epoch}, block: {}",
             block.header.cid()
         );
+        // The messages of a block are likely held by the same peers
+        let session = BitswapSession::new();
+
         // Get bls_message in the store or over Bitswap
         let bls_messages: Vec<_> = block
             .bls_messages
             .into_iter()
-            .map(|m| network.bitswap_get::<Message>(m))
+            .map(|m| network.bitswap_get::<Message>(m, session.clone()))
             .collect();
 
         // Get secp_messages in the store or over Bitswap
         let secp_messages: Vec<_> = block
             .secpk_messages
             .into_iter()
-            .map(|m| network.bitswap_get::<SignedMessage>(m))
+            .map(|m| network.bitswap_get::<SignedMessage>(m, session.clone()))
             .collect();
 
         let (bls_messages, secp_messages) =
@@ -474,6 +487,12 @@ where
                     .inc();
                 return Ok(None);
             }
+            NetworkEvent::CertExchangeRequestInbound { .. } => {
+                metrics::LIBP2P_MESSAGE_TOTAL
+                    .with_label_values(&[metrics::values::CERT_EXCHANGE_REQUEST_INBOUND])
+                    .inc();
+                return Ok(None);
+            }
         };
 
         if tipset.epoch() + (SECONDS_IN_DAY / block_delay as i64)
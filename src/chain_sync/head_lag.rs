@@ -0,0 +1,152 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Alerting when the validated head falls behind the epoch expected from the
+//! wall clock, e.g. because the node lost its peers or can't keep up with
+//! validation.
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::chain::ChainStore;
+use crate::networks::ChainConfig;
+use crate::shim::clock::ChainEpoch;
+use crate::utils::net::global_http_client;
+use fvm_ipld_blockstore::Blockstore;
+use serde::Serialize;
+use tracing::{info, warn};
+use url::Url;
+
+use super::{metrics, SyncConfig};
+
+/// Body of the requests posted to the head lag webhook.
+#[derive(Debug, Serialize)]
+struct HeadLagAlert<'a> {
+    network: &'a str,
+    /// Either `lagging` or `recovered`
+    status: &'static str,
+    head_epoch: ChainEpoch,
+    expected_epoch: ChainEpoch,
+    lag: ChainEpoch,
+}
+
+/// Epoch expected at `now` (in seconds since the UNIX epoch) for a chain
+/// started at `genesis_timestamp`.
+fn expected_epoch(genesis_timestamp: u64, block_delay: u32, now: u64) -> ChainEpoch {
+    (now.saturating_sub(genesis_timestamp) / block_delay as u64) as ChainEpoch
+}
+
+/// Epoch expected now for a chain started at `genesis_timestamp`.
+pub fn expected_epoch_now(genesis_timestamp: u64, block_delay: u32) -> ChainEpoch {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time before unix epoch")
+        .as_secs();
+    expected_epoch(genesis_timestamp, block_delay, now)
+}
+
+pub struct HeadLagMonitor<DB> {
+    chain_store: Arc<ChainStore<DB>>,
+    network_name: String,
+    block_delay: u32,
+    threshold: ChainEpoch,
+    webhook: Option<Url>,
+}
+
+impl<DB: Blockstore> HeadLagMonitor<DB> {
+    pub fn new(
+        chain_store: Arc<ChainStore<DB>>,
+        chain_config: &ChainConfig,
+        network_name: String,
+        sync_config: &SyncConfig,
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            chain_config.block_delay_secs > 0,
+            "the block delay of the network must be positive"
+        );
+        let webhook = sync_config
+            .head_lag_webhook
+            .as_deref()
+            .map(Url::parse)
+            .transpose()?;
+        Ok(Self {
+            chain_store,
+            network_name,
+            block_delay: chain_config.block_delay_secs,
+            threshold: sync_config.head_lag_alert_threshold,
+            webhook,
+        })
+    }
+
+    /// Checks the head lag every epoch. Alerts are raised when the lag
+    /// exceeds the threshold and when it's back under it.
+    pub async fn run(self) -> anyhow::Result<()> {
+        let genesis_timestamp = self.chain_store.genesis().timestamp();
+        let block_delay = self.block_delay;
+        let mut interval = tokio::time::interval(Duration::from_secs(block_delay as u64));
+        let mut lagging = false;
+        loop {
+            interval.tick().await;
+            let expected_epoch = expected_epoch_now(genesis_timestamp, block_delay);
+            let head_epoch = self.chain_store.heaviest_tipset().epoch();
+            let lag = expected_epoch - head_epoch;
+            metrics::HEAD_LAG.set(lag);
+
+            let status = match (lagging, lag > self.threshold) {
+                (false, true) => {
+                    warn!(
+                        head_epoch,
+                        expected_epoch,
+                        lag,
+                        threshold = self.threshold,
+                        "Chain head is lagging behind the network"
+                    );
+                    "lagging"
+                }
+                (true, false) => {
+                    info!(head_epoch, expected_epoch, lag, "Chain head caught up");
+                    "recovered"
+                }
+                _ => continue,
+            };
+            lagging = !lagging;
+            if let Some(webhook) = &self.webhook {
+                let alert = HeadLagAlert {
+                    network: &self.network_name,
+                    status,
+                    head_epoch,
+                    expected_epoch,
+                    lag,
+                };
+                if let Err(e) = post_alert(webhook, &alert).await {
+                    warn!("Failed to post head lag alert to {webhook}: {e}");
+                }
+            }
+        }
+    }
+}
+
+async fn post_alert(webhook: &Url, alert: &HeadLagAlert<'_>) -> anyhow::Result<()> {
+    global_http_client()
+        .post(webhook.clone())
+        .json(alert)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_epoch_from_wall_clock() {
+        assert_eq!(expected_epoch(1000, 30, 1000), 0);
+        assert_eq!(expected_epoch(1000, 30, 1029), 0);
+        assert_eq!(expected_epoch(1000, 30, 1030), 1);
+        assert_eq!(expected_epoch(1000, 30, 4000), 100);
+        // Clock behind genesis
+        assert_eq!(expected_epoch(1000, 30, 10), 0);
+    }
+}
@@ -139,6 +139,19 @@ pub static LAST_VALIDATED_TIPSET_EPOCH: Lazy<Box<GenericGauge<AtomicU64>>> = Laz
         );
     last_validated_tipset_epoch
 });
+pub static HEAD_LAG: Lazy<Box<GenericGauge<AtomicI64>>> = Lazy::new(|| {
+    let head_lag = Box::new(
+        GenericGauge::new(
+            "head_lag",
+            "Number of epochs the validated head is behind the current epoch",
+        )
+        .expect("Defining the head_lag metric must succeed"),
+    );
+    prometheus::default_registry()
+        .register(head_lag.clone())
+        .expect("Registering the head_lag metric with the metrics registry must succeed");
+    head_lag
+});
 pub static PEER_TIPSET_EPOCH: Lazy<Box<GenericGaugeVec<AtomicI64>>> = Lazy::new(|| {
     let peer_tipset_epoch = Box::new(
         GenericGaugeVec::new(
@@ -231,6 +244,7 @@ pub mod values {
     pub const CHAIN_EXCHANGE_RESPONSE_INBOUND: &str = "chain_exchange_response_in";
     pub const CHAIN_EXCHANGE_REQUEST_INBOUND: &str = "chain_exchange_request_in";
     pub const CHAIN_EXCHANGE_RESPONSE_OUTBOUND: &str = "chain_exchange_response_out";
+    pub const CERT_EXCHANGE_REQUEST_INBOUND: &str = "cert_exchange_request_in";
 
     // block validation tasks
     pub const BASE_FEE_CHECK: &str = "base_fee_check";
@@ -263,6 +277,7 @@ mod tests {
         test_counter!(TIPSET_RANGE_SYNC_FAILURE_TOTAL);
         test_counter!(HEAD_EPOCH);
         test_counter!(LAST_VALIDATED_TIPSET_EPOCH);
+        test_counter!(HEAD_LAG);
         test_counter!(NETWORK_HEAD_EVALUATION_ERRORS);
         test_counter!(BOOTSTRAP_ERRORS);
         test_counter!(FOLLOW_NETWORK_INTERRUPTIONS);
@@ -4,6 +4,7 @@
 mod bad_block_cache;
 mod chain_muxer;
 pub mod consensus;
+mod head_lag;
 mod metrics;
 mod network_context;
 mod sync_state;
@@ -14,6 +15,8 @@ pub use self::{
     bad_block_cache::BadBlockCache,
     chain_muxer::{ChainMuxer, SyncConfig},
     consensus::{collect_errs, Consensus},
+    head_lag::{expected_epoch_now, HeadLagMonitor},
     sync_state::{SyncStage, SyncState},
+    tipset_syncer::pause_validations,
     validation::TipsetValidator,
 };
@@ -20,6 +20,7 @@ use crate::libp2p::{
     rpc::RequestResponseError,
     NetworkMessage, PeerId, PeerManager, BITSWAP_TIMEOUT,
 };
+use crate::libp2p_bitswap::BitswapSession;
 use anyhow::Context as _;
 use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
@@ -174,10 +175,12 @@ where
     }
 
     /// Requests that some content with a particular `Cid` get fetched over
-    /// `Bitswap` if it doesn't exist in the `BlockStore`.
+    /// `Bitswap` if it doesn't exist in the `BlockStore`. Related content
+    /// should be fetched in the same session.
     pub async fn bitswap_get<TMessage: DeserializeOwned>(
         &self,
         content: Cid,
+        session: BitswapSession,
     ) -> Result<TMessage, String> {
         // Check if what we are fetching over Bitswap already exists in the
         // database. If it does, return it, else fetch over the network.
@@ -191,6 +194,7 @@ where
             .send_async(NetworkMessage::BitswapRequest {
                 cid: content,
                 response_channel: tx,
+                session: Some(session),
             })
             .await
             .map_err(|_| "failed to send bitswap request, network receiver dropped")?;
@@ -33,8 +33,12 @@ use futures::{stream, stream::FuturesUnordered, StreamExt, TryFutureExt};
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::to_vec;
 use nonempty::{nonempty, NonEmpty};
+use once_cell::sync::Lazy;
 use thiserror::Error;
-use tokio::task::JoinSet;
+use tokio::{
+    sync::{RwLock, RwLockWriteGuard},
+    task::JoinSet,
+};
 use tracing::{debug, error, info, trace, warn};
 
 use crate::chain_sync::{
@@ -1068,10 +1072,22 @@ async fn sync_messages_check_state<DB: Blockstore + Send + Sync + 'static>(
         .await
 }
 
+/// Held for reading by the tipset validations in progress, see
+/// [`pause_validations`].
+static VALIDATIONS: Lazy<RwLock<()>> = Lazy::new(Default::default);
+
+/// Waits for the tipset validations in progress to finish, no new validation
+/// starts until the returned guard is dropped. Used on shutdown.
+pub async fn pause_validations() -> RwLockWriteGuard<'static, ()> {
+    VALIDATIONS.write().await
+}
+
 /// Validates full blocks in the tipset in parallel (since the messages are not
 /// executed), adding the successful ones to the tipset tracker, and the failed
 /// ones to the bad block cache, depending on strategy. Any bad block fails
-/// validation.
+/// validation. The Ethereum transactions of the valid blocks are indexed, see
+/// [`crate::eth`].
+#[tracing::instrument(skip_all, fields(epoch = full_tipset.epoch()))]
 async fn validate_tipset<DB: Blockstore + Send + Sync + 'static>(
     state_manager: Arc<StateManager<DB>>,
     chainstore: &ChainStore<DB>,
@@ -1084,6 +1100,7 @@ async fn validate_tipset<DB: Blockstore + Send + Sync + 'static>(
         trace!("Skipping genesis tipset validation");
         return Ok(());
     }
+    let _validating = VALIDATIONS.read().await;
 
     let epoch = full_tipset.epoch();
     let full_tipset_key = full_tipset.key().clone();
@@ -1106,6 +1123,14 @@ async fn validate_tipset<DB: Blockstore + Send + Sync + 'static>(
         match result? {
             Ok(block) => {
                 chainstore.add_to_tipset_tracker(block.header());
+                let chain_id = state_manager.chain_config().eth_chain_id.into();
+                if let Err(e) = crate::eth::index_tx_hashes(
+                    &*chainstore.settings(),
+                    chain_id,
+                    block.secp_msgs(),
+                ) {
+                    warn!("Failed to index the Ethereum transactions of EPOCH = {epoch}: {e}");
+                }
             }
             Err((cid, why)) => {
                 warn!(
@@ -1151,6 +1176,7 @@ async fn validate_tipset<DB: Blockstore + Send + Sync + 'static>(
 /// * Checking that the messages in the block correspond to the agreed upon
 ///   total ordering
 /// * That the block is a deterministic derivative of the underlying consensus
+#[tracing::instrument(skip_all, fields(epoch = block.header().epoch()))]
 async fn validate_block<DB: Blockstore + Sync + Send + 'static>(
     state_manager: Arc<StateManager<DB>>,
     block: Arc<Block>,
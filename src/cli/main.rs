@@ -10,14 +10,15 @@ use crate::rpc_client::ApiInfo;
 use crate::shim::address::{CurrentNetwork, Network};
 use clap::Parser;
 
-use super::subcommands::Subcommand;
+use super::subcommands::{InfoCommand, Subcommand};
 
 pub fn main<ArgT>(args: impl IntoIterator<Item = ArgT>) -> anyhow::Result<()>
 where
     ArgT: Into<OsString> + Clone,
 {
     // Capture Cli inputs
-    let Cli { token, cmd } = Cli::parse_from(args);
+    let Cli { token, output, cmd } = Cli::parse_from(args);
+    output.set_global();
 
     let api = ApiInfo::from_env()?.set_token(token);
 
@@ -26,7 +27,10 @@ where
         .build()
         .unwrap()
         .block_on(async {
-            logger::setup_logger(&crate::cli_shared::cli::CliOpts::default());
+            logger::setup_logger(
+                &crate::cli_shared::cli::CliOpts::default(),
+                &Default::default(),
+            );
             if let Ok(name) = api.state_network_name().await {
                 if get_actual_chain_name(&name) != "mainnet" {
                     CurrentNetwork::set_global(Network::Testnet);
@@ -39,14 +43,18 @@ where
                 Subcommand::Net(cmd) => cmd.run(api).await,
                 Subcommand::Sync(cmd) => cmd.run(api).await,
                 Subcommand::Mpool(cmd) => cmd.run(api).await,
+                Subcommand::Msg(cmd) => cmd.run(api).await,
                 Subcommand::State(cmd) => cmd.run(api).await,
                 Subcommand::Config(cmd) => cmd.run(&mut std::io::stdout()),
                 Subcommand::Send(cmd) => cmd.run(api).await,
-                Subcommand::Info(cmd) => cmd.run(api).await,
+                Subcommand::Info { command } => command.unwrap_or(InfoCommand::Show).run(api).await,
                 Subcommand::DB(cmd) => cmd.run(api).await,
+                Subcommand::Log(cmd) => cmd.run(api).await,
                 Subcommand::Snapshot(cmd) => cmd.run(api).await,
                 Subcommand::Attach(cmd) => cmd.run(api),
                 Subcommand::Shutdown(cmd) => cmd.run(api).await,
+                Subcommand::Healthcheck(cmd) => cmd.run(api).await,
+                Subcommand::WaitApi(cmd) => cmd.run(api).await,
             }
         })
 }
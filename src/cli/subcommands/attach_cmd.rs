@@ -7,6 +7,7 @@ use std::{
     str::FromStr,
 };
 
+use crate::blocks::TipsetKeys;
 use crate::chain::ChainEpochDelta;
 use crate::chain_sync::SyncStage;
 use crate::rpc_client::*;
@@ -21,10 +22,14 @@ use boa_engine::{
 use boa_interner::Interner;
 use boa_parser::Parser;
 use boa_runtime::Console;
+use cid::Cid;
 use convert_case::{Case, Casing};
 use directories::BaseDirs;
 use futures::Future;
-use rustyline::{config::Config as RustyLineConfig, history::FileHistory, EditMode, Editor};
+use rustyline::{
+    completion::Completer, config::Config as RustyLineConfig, highlight::Highlighter, hint::Hinter,
+    history::FileHistory, validate::Validator, CompletionType, EditMode, Editor, Helper,
+};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value as JsonValue;
 use tokio::time;
@@ -42,6 +47,77 @@ pub struct AttachCommand {
 
 const PRELUDE_PATH: &str = include_str!("./js/prelude.js");
 
+/// Console commands that are handled by the REPL itself rather than evaluated.
+const REPL_COMMANDS: [&str; 2] = [":quit", ":clear"];
+
+/// Completes the name under the cursor with the globals known to the console.
+struct AttachHelper {
+    names: Vec<String>,
+}
+
+impl AttachHelper {
+    fn new(mut names: Vec<String>) -> Self {
+        names.extend(REPL_COMMANDS.iter().map(|s| s.to_string()));
+        names.sort();
+        names.dedup();
+        Self { names }
+    }
+
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || matches!(c, '_' | '$' | ':'))
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(pos);
+        let word = &line[start..pos];
+        if word.is_empty() {
+            return (pos, vec![]);
+        }
+        let candidates = self
+            .names
+            .iter()
+            .filter(|name| name.starts_with(word))
+            .cloned()
+            .collect();
+        (start, candidates)
+    }
+}
+
+impl Completer for AttachHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for AttachHelper {
+    type Hint = String;
+}
+
+impl Highlighter for AttachHelper {}
+
+impl Validator for AttachHelper {}
+
+impl Helper for AttachHelper {}
+
+/// Returns the names of all the properties of the global object.
+fn global_names(context: &mut Context) -> anyhow::Result<Vec<String>> {
+    let names = context
+        .eval(Source::from_bytes("Object.getOwnPropertyNames(globalThis)"))
+        .map_err(|e| anyhow::anyhow!("{e}"))?
+        .to_json(context)
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    Ok(serde_json::from_value(names)?)
+}
+
 fn set_module(context: &mut Context) {
     let module = JsObject::default();
     module
@@ -253,6 +329,29 @@ async fn sleep(params: SleepParams, _api: ApiInfo) -> anyhow::Result<SleepResult
     Ok(())
 }
 
+async fn chain_get_block(params: (String,), api: ApiInfo) -> anyhow::Result<JsonValue> {
+    let cid = Cid::from_str(&params.0)?;
+    Ok(api.call(ApiInfo::chain_get_block_req(cid).lower()).await?)
+}
+
+async fn chain_get_message(params: (String,), api: ApiInfo) -> anyhow::Result<JsonValue> {
+    let cid = Cid::from_str(&params.0)?;
+    Ok(api
+        .call(ApiInfo::chain_get_message_req(cid).lower())
+        .await?)
+}
+
+async fn chain_read_obj(params: (String,), api: ApiInfo) -> anyhow::Result<JsonValue> {
+    let cid = Cid::from_str(&params.0)?;
+    Ok(api.call(ApiInfo::chain_read_obj_req(cid).lower()).await?)
+}
+
+async fn state_get_actor(params: (String,), api: ApiInfo) -> anyhow::Result<JsonValue> {
+    let address = Address::from_str(&params.0)?;
+    let req = ApiInfo::state_get_actor_req(address, TipsetKeys::default());
+    Ok(api.call(req.lower()).await?)
+}
+
 async fn sleep_tipsets(epochs: ChainEpochDelta, api: ApiInfo) -> anyhow::Result<()> {
     let mut epoch = None;
     loop {
@@ -299,6 +398,15 @@ impl AttachCommand {
         set_module(context);
 
         bind_request!(context, api,
+                // Chain API
+                "chain_head"                 => |()| ApiInfo::chain_head_req(),
+                "chain_get_genesis"          => |()| ApiInfo::chain_get_genesis_req(),
+                "chain_get_tipset_by_height" => |(epoch,)| ApiInfo::chain_get_tipset_by_height_req(epoch, TipsetKeys::default()),
+
+                // State API
+                "state_network_name"       => |()| ApiInfo::state_network_name_req(),
+                "state_get_network_params" => |()| ApiInfo::state_get_network_params_req(),
+
                 // Net API
                 "net_addrs_listen" => |()| ApiInfo::net_addrs_listen_req(),
                 "net_peers"        => |()| ApiInfo::net_peers_req(),
@@ -327,15 +435,22 @@ impl AttachCommand {
 
                 // Message Pool API
                 "mpool_push_message" => |(message, specs)| ApiInfo::mpool_push_message_req(message, specs),
+                "mpool_pending"      => |()| ApiInfo::mpool_pending_req(vec![]),
 
                 // Common API
                 "version" => |()| ApiInfo::version_req(),
                 "shutdown" => |()| ApiInfo::shutdown_req(),
         );
 
+        // Bind the requests that take a CID or an address as a string
+        bind_async(context, &api, "chain_get_block", chain_get_block);
+        bind_async(context, &api, "chain_get_message", chain_get_message);
+        bind_async(context, &api, "chain_read_obj", chain_read_obj);
+        bind_async(context, &api, "state_get_actor", state_get_actor);
+
         // Bind send_message, sleep, sleep_tipsets
         bind_async(context, &api, "send_message", send_message);
-        bind_async(context, &api, "seep", sleep);
+        bind_async(context, &api, "sleep", sleep);
         bind_async(context, &api, "sleep_tipsets", sleep_tipsets);
     }
 
@@ -375,9 +490,11 @@ impl AttachCommand {
         let config = RustyLineConfig::builder()
             .keyseq_timeout(1)
             .edit_mode(EditMode::Emacs)
+            .completion_type(CompletionType::List)
             .build();
 
-        let mut editor: Editor<(), FileHistory> = Editor::with_config(config)?;
+        let mut editor: Editor<AttachHelper, FileHistory> = Editor::with_config(config)?;
+        editor.set_helper(Some(AttachHelper::new(global_names(&mut context)?)));
 
         let history_path = if let Some(dirs) = BaseDirs::new() {
             let path = dirs.home_dir().join(".forest_history");
@@ -453,3 +570,31 @@ impl AttachCommand {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_global_names() {
+        let helper = AttachHelper::new(vec![
+            "chainHead".into(),
+            "chainGetBlock".into(),
+            "netPeers".into(),
+        ]);
+
+        assert_eq!(
+            helper.candidates("chainG", 6),
+            (0, vec!["chainGetBlock".to_string()])
+        );
+        assert_eq!(
+            helper.candidates("let head = chain", 16),
+            (
+                11,
+                vec!["chainGetBlock".to_string(), "chainHead".to_string()]
+            )
+        );
+        assert_eq!(helper.candidates(":q", 2), (0, vec![":quit".to_string()]));
+        assert_eq!(helper.candidates("netPeers(", 9), (9, vec![]));
+    }
+}
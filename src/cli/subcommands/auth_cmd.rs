@@ -2,20 +2,25 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use crate::auth::*;
+use crate::cli_shared::output::{self, Outcome, OutputFormat};
 use crate::rpc_client::{ApiInfo, JsonRpcError};
-use chrono::Duration;
+use chrono::{Duration, NaiveDateTime};
 use clap::Subcommand;
 use std::str::FromStr;
 
-use super::print_rpc_res_bytes;
+use super::{print_pretty_json, print_rpc_res_bytes};
 
 #[derive(Debug, Subcommand)]
 pub enum AuthCommands {
     /// Create a new Authentication token with given permission
     CreateToken {
         /// Permission to assign to the token, one of: read, write, sign, admin
-        #[arg(short, long)]
-        perm: String,
+        #[arg(short, long, required_unless_present = "allow")]
+        perm: Option<String>,
+        /// Exact set of permissions to assign to the token instead of a
+        /// permission level, e.g. `read,sign`
+        #[arg(long, value_delimiter = ',', conflicts_with = "perm")]
+        allow: Vec<String>,
         /// Token is revoked after this duration
         #[arg(long, default_value_t = humantime::Duration::from_str("2 months").expect("infallible"))]
         expire_in: humantime::Duration,
@@ -23,12 +28,23 @@ pub enum AuthCommands {
     /// Get RPC API Information
     ApiInfo {
         /// permission to assign the token, one of: read, write, sign, admin
-        #[arg(short, long)]
-        perm: String,
+        #[arg(short, long, required_unless_present = "allow")]
+        perm: Option<String>,
+        /// Exact set of permissions to assign to the token instead of a
+        /// permission level, e.g. `read,sign`
+        #[arg(long, value_delimiter = ',', conflicts_with = "perm")]
+        allow: Vec<String>,
         /// Token is revoked after this duration
         #[arg(long, default_value_t = humantime::Duration::from_str("2 months").expect("infallible"))]
         expire_in: humantime::Duration,
     },
+    /// List the unexpired tokens created with `create-token` or `api-info`
+    List,
+    /// Revoke a token created with `create-token` or `api-info`
+    Revoke {
+        /// ID of the token, as listed by `list`, or the token itself
+        token: String,
+    },
 }
 
 fn process_perms(perm: String) -> Result<Vec<String>, JsonRpcError> {
@@ -44,27 +60,99 @@ fn process_perms(perm: String) -> Result<Vec<String>, JsonRpcError> {
     .collect())
 }
 
+/// Permissions of a permission level, or an exact set of permissions
+fn token_perms(perm: Option<String>, allow: Vec<String>) -> anyhow::Result<Vec<String>> {
+    match perm {
+        Some(perm) => Ok(process_perms(perm)?),
+        None => {
+            if let Some(unknown) = allow.iter().find(|perm| !ADMIN.contains(&perm.as_str())) {
+                anyhow::bail!("unknown permission {unknown}, expected one of: {ADMIN:?}");
+            }
+            Ok(allow)
+        }
+    }
+}
+
 impl AuthCommands {
     pub async fn run(self, api: ApiInfo) -> anyhow::Result<()> {
         match self {
-            Self::CreateToken { perm, expire_in } => {
-                let perm: String = perm.parse()?;
-                let perms = process_perms(perm)?;
+            Self::CreateToken {
+                perm,
+                allow,
+                expire_in,
+            } => {
+                let perms = token_perms(perm, allow)?;
                 let token_exp = Duration::from_std(expire_in.into())?;
                 print_rpc_res_bytes(api.auth_new(perms, token_exp).await?)
             }
-            Self::ApiInfo { perm, expire_in } => {
-                let perm: String = perm.parse()?;
-                let perms = process_perms(perm)?;
+            Self::ApiInfo {
+                perm,
+                allow,
+                expire_in,
+            } => {
+                let perms = token_perms(perm, allow)?;
                 let token_exp = Duration::from_std(expire_in.into())?;
                 let token = api.auth_new(perms, token_exp).await?;
                 let new_api = ApiInfo {
                     token: Some(String::from_utf8(token)?),
                     ..api
                 };
-                println!("FULLNODE_API_INFO=\"{}\"", new_api);
+                let api_info = serde_json::json!({ "FULLNODE_API_INFO": new_api.to_string() });
+                output::print(&api_info, |_| println!("FULLNODE_API_INFO=\"{}\"", new_api))
+            }
+            Self::List => {
+                let tokens = api.auth_list().await?;
+                if OutputFormat::is_json() {
+                    return print_pretty_json(tokens);
+                }
+                for token in tokens {
+                    println!(
+                        "{}: [{}], issued at {}, expires at {}",
+                        token.id,
+                        token.allow.join(", "),
+                        format_timestamp(token.issued_at),
+                        format_timestamp(token.expires_at)
+                    );
+                }
                 Ok(())
             }
+            Self::Revoke { token } => {
+                let id = if token.contains('.') {
+                    token_id(&token)?
+                } else {
+                    token
+                };
+                let result = match api.auth_revoke(id.clone()).await? {
+                    true => Ok(()),
+                    false => Err("not a token created by the node, or expired"),
+                };
+                output::print_outcomes("revoke", &[Outcome::new(id, result)])
+            }
         }
     }
 }
+
+fn format_timestamp(timestamp: i64) -> String {
+    NaiveDateTime::from_timestamp_opt(timestamp, 0)
+        .map(|date| date.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perms() {
+        assert_eq!(
+            token_perms(Some("write".into()), vec![]).unwrap(),
+            ["read", "write"]
+        );
+        assert_eq!(
+            token_perms(None, vec!["read".into(), "sign".into()]).unwrap(),
+            ["read", "sign"]
+        );
+        assert!(token_perms(None, vec!["root".into()]).is_err());
+        assert!(token_perms(Some("root".into()), vec![]).is_err());
+    }
+}
@@ -1,34 +1,54 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use crate::blocks::{Tipset, TipsetKeys};
+use crate::blocks::{BlockHeader, Tipset, TipsetKeys};
+use crate::cli::humantoken::TokenAmountPretty as _;
+use crate::cli_shared::output::{self, OutputFormat};
 use crate::lotus_json::LotusJson;
+use crate::rpc_api::chain_api::HeadChangeType;
 use crate::rpc_client::{ApiInfo, JsonRpcError};
+use crate::shim::clock::ChainEpoch;
 use anyhow::bail;
+use chrono::NaiveDateTime;
 use cid::Cid;
 use clap::Subcommand;
+use futures::StreamExt as _;
+use serde::Serialize;
 
-use super::{print_pretty_json, print_rpc_res_cids};
+use super::db_cmd::garbage_collect;
+use super::msg_cmd::{recipient_actor, Inspection};
+use super::{maybe_confirm, print_pretty_json, print_rpc_res_cids};
 
 #[derive(Debug, Subcommand)]
 pub enum ChainCommands {
-    /// Retrieves and prints out the block specified by the given CID
+    /// Retrieves and prints out the header of the block specified by the given
+    /// CID, with its miner, parent weight, win count and base fee
     Block {
         #[arg(short)]
         cid: Cid,
+        /// Print the CBOR encoding of the header, in hex, instead
+        #[arg(long)]
+        raw_cbor: bool,
     },
 
     /// Prints out the genesis tipset
     Genesis,
 
     /// Prints out the canonical head of the chain
-    Head,
+    Head {
+        /// Keep printing the new heads as they arrive
+        #[arg(long)]
+        follow: bool,
+    },
 
     /// Reads and prints out a message referenced by the specified CID from the
-    /// chain block store
+    /// chain block store, with its method name and decoded parameters
     Message {
         #[arg(short)]
         cid: Cid,
+        /// Print the CBOR encoding of the message, in hex, instead
+        #[arg(long)]
+        raw_cbor: bool,
     },
 
     /// Reads and prints out IPLD nodes referenced by the specified CID from
@@ -38,6 +58,10 @@ pub enum ChainCommands {
         cid: Cid,
     },
 
+    /// Runs a garbage collection of the database, displaying its progress,
+    /// and prints the number of blocks kept and the disk space reclaimed
+    Prune,
+
     /// Manually set the head to the given tipset. This invalidates blocks
     /// between the desired head and the new head
     SetHead {
@@ -57,14 +81,63 @@ pub enum ChainCommands {
 impl ChainCommands {
     pub async fn run(self, api: ApiInfo) -> anyhow::Result<()> {
         match self {
-            Self::Block { cid } => print_pretty_json(api.chain_get_block(cid).await?),
+            Self::Block {
+                cid,
+                raw_cbor: true,
+            }
+            | Self::Message {
+                cid,
+                raw_cbor: true,
+            } => output::print(&api.chain_read_obj(cid).await?, |obj| println!("{obj}")),
+            Self::Block { cid, .. } if OutputFormat::is_json() => {
+                print_pretty_json(api.chain_get_block(cid).await?)
+            }
+            Self::Block { cid, .. } => {
+                print!("{}", block_text(&api.chain_get_block(cid).await?));
+                Ok(())
+            }
             Self::Genesis => print_pretty_json(LotusJson(api.chain_get_genesis().await?)),
-            Self::Head => print_rpc_res_cids(api.chain_head().await?),
-            Self::Message { cid } => print_pretty_json(api.chain_get_message(cid).await?),
-            Self::ReadObj { cid } => {
-                println!("{}", api.chain_read_obj(cid).await?);
+            Self::Head { follow } => {
+                // `--output json` prints one JSON object per head, on a single
+                // line
+                if !follow {
+                    let head = api.chain_head().await?;
+                    return match OutputFormat::get() {
+                        OutputFormat::Text => print_rpc_res_cids(head),
+                        OutputFormat::Json => {
+                            println!("{}", HeadSummary::new(None, &head).json()?);
+                            Ok(())
+                        }
+                    };
+                }
+                let mut head_changes = api.chain_notify().await?;
+                while let Some(changes) = head_changes.next().await {
+                    for change in changes? {
+                        let summary = HeadSummary::new(Some(change.r#type), &change.val);
+                        match OutputFormat::get() {
+                            OutputFormat::Text => println!("{}", summary.text()),
+                            OutputFormat::Json => println!("{}", summary.json()?),
+                        }
+                    }
+                }
+                bail!("the node closed the subscription")
+            }
+            Self::Message { cid, .. } if OutputFormat::is_json() => {
+                print_pretty_json(api.chain_get_message(cid).await?)
+            }
+            Self::Message { cid, .. } => {
+                let message = api.chain_get_message(cid).await?;
+                let actor = recipient_actor(&api, message.to).await;
+                print!("{}", Inspection::new(cid, &message, actor, None)?.text());
                 Ok(())
             }
+            Self::ReadObj { cid } => {
+                output::print(&api.chain_read_obj(cid).await?, |obj| println!("{obj}"))
+            }
+            Self::Prune => {
+                let report = garbage_collect(&api).await?;
+                output::print(&report, |report| println!("{}", report.text()))
+            }
             Self::SetHead {
                 cids,
                 epoch: Some(epoch),
@@ -90,6 +163,78 @@ impl ChainCommands {
     }
 }
 
+/// Epoch, CIDs and block count of a head, printed by `chain head`.
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct HeadSummary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    r#type: Option<HeadChangeType>,
+    epoch: ChainEpoch,
+    cids: Vec<String>,
+    blocks: usize,
+}
+
+impl HeadSummary {
+    fn new(r#type: Option<HeadChangeType>, tipset: &Tipset) -> Self {
+        Self {
+            r#type,
+            epoch: tipset.epoch(),
+            cids: tipset.cids().iter().map(Cid::to_string).collect(),
+            blocks: tipset.blocks().len(),
+        }
+    }
+
+    fn text(&self) -> String {
+        let change = match self.r#type {
+            Some(HeadChangeType::Current) | None => "current",
+            Some(HeadChangeType::Apply) => "apply",
+            Some(HeadChangeType::Revert) => "revert",
+        };
+        format!(
+            "{change}: epoch {}, {} block(s): {}",
+            self.epoch,
+            self.blocks,
+            self.cids.join(", ")
+        )
+    }
+
+    fn json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+/// Describes the fields of a block header, with the amounts in FIL.
+fn block_text(block: &BlockHeader) -> String {
+    let timestamp = NaiveDateTime::from_timestamp_opt(block.timestamp() as i64, 0)
+        .map(|date| date.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| block.timestamp().to_string());
+    let win_count = match block.election_proof() {
+        Some(proof) => proof.win_count.to_string(),
+        None => "none".into(),
+    };
+    let parents: Vec<_> = block
+        .parents()
+        .cids
+        .clone()
+        .into_iter()
+        .map(|cid| cid.to_string())
+        .collect();
+    let lines = [
+        format!("CID:               {}", block.cid()),
+        format!("Epoch:             {}", block.epoch()),
+        format!("Miner:             {}", block.miner_address()),
+        format!("Timestamp:         {timestamp}"),
+        format!("Win count:         {win_count}"),
+        format!("Parents:           {}", parents.join(", ")),
+        format!("Parent weight:     {}", block.weight()),
+        format!("Parent base fee:   {}", block.parent_base_fee().pretty()),
+        format!("Parent state root: {}", block.state_root()),
+        format!("Parent receipts:   {}", block.message_receipts()),
+        format!("Messages:          {}", block.messages()),
+    ];
+    lines.join("\n") + "\n"
+}
+
 /// If `epoch_or_offset` is negative, get the tipset that many blocks before the
 /// current head. Else treat `epoch_or_offset` as an epoch, and get that tipset.
 async fn tipset_by_epoch_or_offset(
@@ -110,17 +255,55 @@ async fn tipset_by_epoch_or_offset(
 const SET_HEAD_CONFIRMATION_MESSAGE: &str =
     "Manually setting head is an unsafe operation that could brick the node! Continue?";
 
-fn maybe_confirm(no_confirm: bool, prompt: impl Into<String>) -> anyhow::Result<()> {
-    if no_confirm {
-        return Ok(());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::ElectionProof;
+    use crate::shim::address::Address;
+    use crate::shim::econ::TokenAmount;
+
+    #[test]
+    fn block_fields() {
+        let block = BlockHeader::builder()
+            .miner_address(Address::new_id(1000))
+            .epoch(42)
+            .weight(7.into())
+            .election_proof(Some(ElectionProof {
+                win_count: 2,
+                ..Default::default()
+            }))
+            .parent_base_fee(TokenAmount::from_atto(100))
+            .build()
+            .unwrap();
+        let text = block_text(&block);
+        assert!(text.contains("Epoch:             42\n"));
+        assert!(text.contains(&format!("Miner:             {}\n", Address::new_id(1000))));
+        assert!(text.contains("Win count:         2\n"));
+        assert!(text.contains("Parent weight:     7\n"));
+        assert!(text.contains("Parent base fee:   100 attoFIL\n"));
     }
-    let should_continue = dialoguer::Confirm::new()
-        .default(false)
-        .with_prompt(prompt)
-        .wait_for_newline(true)
-        .interact()?;
-    match should_continue {
-        true => Ok(()),
-        false => bail!("Operation cancelled by user"),
+
+    #[test]
+    fn head_summary() {
+        let summary = HeadSummary {
+            r#type: Some(HeadChangeType::Apply),
+            epoch: 42,
+            cids: vec!["bafy1".into(), "bafy2".into()],
+            blocks: 2,
+        };
+        assert_eq!(summary.text(), "apply: epoch 42, 2 block(s): bafy1, bafy2");
+        assert_eq!(
+            summary.json().unwrap(),
+            r#"{"Type":"apply","Epoch":42,"Cids":["bafy1","bafy2"],"Blocks":2}"#
+        );
+
+        let summary = HeadSummary {
+            r#type: None,
+            ..summary
+        };
+        assert_eq!(
+            summary.json().unwrap(),
+            r#"{"Epoch":42,"Cids":["bafy1","bafy2"],"Blocks":2}"#
+        );
     }
 }
@@ -4,44 +4,180 @@
 use std::io::Write;
 
 use anyhow::Context as _;
-use clap::Subcommand;
+use clap::{Parser, Subcommand};
 
 use crate::cli::subcommands::Config;
+use crate::cli_shared::cli::CliOpts;
+use crate::cli_shared::output::OutputFormat;
 
 #[derive(Debug, Subcommand)]
 pub enum ConfigCommands {
-    /// Dump default configuration to standard output
-    Dump,
+    /// Dump the configuration the daemon would run with, resolved from the
+    /// defaults, the configuration file, the environment and the daemon flags
+    Dump(ResolveArgs),
+    /// Show only the settings of the resolved configuration that differ from
+    /// the defaults
+    Diff(ResolveArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ResolveArgs {
+    /// Daemon flags to resolve the configuration with, e.g.
+    /// `-- --config config.toml --chain calibnet`
+    #[arg(last = true)]
+    daemon_args: Vec<String>,
+}
+
+impl ResolveArgs {
+    /// Resolves the configuration the same way the daemon does on startup.
+    fn resolve(&self) -> anyhow::Result<Config> {
+        let opts = CliOpts::try_parse_from(
+            std::iter::once("forest").chain(self.daemon_args.iter().map(String::as_str)),
+        )?;
+        let (config, _) = opts.to_config()?;
+        Ok(config)
+    }
 }
 
 impl ConfigCommands {
     pub fn run<W: Write + Unpin>(self, sink: &mut W) -> anyhow::Result<()> {
         match self {
-            Self::Dump => writeln!(
+            Self::Dump(args) if OutputFormat::is_json() => {
+                writeln!(sink, "{}", serde_json::to_string_pretty(&args.resolve()?)?)
+                    .context("Failed to write the configuration")
+            }
+            Self::Dump(args) => writeln!(
                 sink,
                 "{}",
-                toml::to_string(&Config::default())
+                toml::to_string(&args.resolve()?)
                     .context("Could not convert configuration to TOML format")?
             )
             .context("Failed to write the configuration"),
+            Self::Diff(args) => {
+                let resolved = toml::Value::try_from(args.resolve()?)?;
+                let defaults = toml::Value::try_from(Config::default())?;
+                let mut unset = vec![];
+                let changed = diff(vec![], &resolved, &defaults, &mut unset);
+                if OutputFormat::is_json() {
+                    let diff = serde_json::json!({ "changed": changed, "unset": unset });
+                    writeln!(sink, "{}", serde_json::to_string_pretty(&diff)?)?;
+                    return Ok(());
+                }
+                for key in unset {
+                    writeln!(sink, "# `{key}` is unset")?;
+                }
+                if let Some(changed) = changed {
+                    writeln!(
+                        sink,
+                        "{}",
+                        toml::to_string(&changed)
+                            .context("Could not convert configuration to TOML format")?
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the parts of `value` that differ from `default`. The keys that are
+/// only set in `default` are collected into `unset`, since TOML has no way to
+/// represent a missing value.
+fn diff(
+    tables: Vec<&str>,
+    value: &toml::Value,
+    default: &toml::Value,
+    unset: &mut Vec<String>,
+) -> Option<toml::Value> {
+    match (value, default) {
+        (toml::Value::Table(map), toml::Value::Table(default_map)) => {
+            for key in default_map.keys().filter(|k| !map.contains_key(*k)) {
+                let mut path = tables.clone();
+                path.push(key);
+                unset.push(path.join("."));
+            }
+            let mut changed = toml::Table::new();
+            for (key, v) in map.iter() {
+                let mut path = tables.clone();
+                path.push(key);
+                let entry = match default_map.get(key) {
+                    Some(default_v) => diff(path, v, default_v, unset),
+                    None => Some(v.clone()),
+                };
+                if let Some(entry) = entry {
+                    changed.insert(key.clone(), entry);
+                }
+            }
+            (!changed.is_empty()).then_some(toml::Value::Table(changed))
         }
+        _ => (value != default).then(|| value.clone()),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::networks::NetworkChain;
+
+    fn resolve_args(args: &[&str]) -> ResolveArgs {
+        ResolveArgs {
+            daemon_args: args.iter().map(|s| s.to_string()).collect(),
+        }
+    }
 
     #[tokio::test]
     async fn given_default_configuration_should_print_valid_toml() {
-        let expected_config = Config::default();
         let mut sink = std::io::BufWriter::new(Vec::new());
 
-        ConfigCommands::Dump.run(&mut sink).unwrap();
+        ConfigCommands::Dump(resolve_args(&["--chain", "calibnet"]))
+            .run(&mut sink)
+            .unwrap();
 
         let actual_config: Config = toml::from_str(std::str::from_utf8(sink.buffer()).unwrap())
             .expect("Invalid configuration!");
 
-        assert_eq!(expected_config, actual_config);
+        assert_eq!(actual_config.chain, NetworkChain::Calibnet);
+    }
+
+    #[test]
+    fn diff_keeps_only_deviations() {
+        let default = Config::default();
+        let mut config = default.clone();
+        config.chain = NetworkChain::Calibnet;
+        config.network.target_peer_count = default.network.target_peer_count + 1;
+
+        let mut unset = vec![];
+        let changed = diff(
+            vec![],
+            &toml::Value::try_from(&config).unwrap(),
+            &toml::Value::try_from(&default).unwrap(),
+            &mut unset,
+        )
+        .unwrap();
+
+        let changed = changed.as_table().unwrap();
+        assert_eq!(changed.len(), 2);
+        assert_eq!(
+            changed["chain"],
+            toml::Value::try_from(NetworkChain::Calibnet).unwrap()
+        );
+        assert_eq!(
+            changed["network"].as_table().unwrap().clone(),
+            toml::Table::from_iter([(
+                "target_peer_count".to_string(),
+                toml::Value::from(config.network.target_peer_count),
+            )])
+        );
+        assert!(unset.is_empty());
+
+        assert_eq!(
+            diff(
+                vec![],
+                &toml::Value::try_from(&default).unwrap(),
+                &toml::Value::try_from(&default).unwrap(),
+                &mut unset,
+            ),
+            None
+        );
     }
 }
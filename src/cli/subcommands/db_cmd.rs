@@ -3,11 +3,15 @@
 
 use std::sync::Arc;
 
+use crate::cli_shared::output::{self, OutputFormat};
 use crate::rpc_api::progress_api::GetProgressType;
 use crate::rpc_client::ApiInfo;
 use crate::utils::io::ProgressBar;
 use chrono::Utc;
 use clap::Subcommand;
+use human_repr::HumanCount as _;
+use is_terminal::IsTerminal as _;
+use serde::Serialize;
 
 #[derive(Debug, Subcommand)]
 pub enum DBCommands {
@@ -19,45 +23,133 @@ impl DBCommands {
     pub async fn run(self, api: ApiInfo) -> anyhow::Result<()> {
         match self {
             Self::GC => {
-                let start = Utc::now();
-
-                let bar = Arc::new(tokio::sync::Mutex::new({
-                    let bar = ProgressBar::new(0);
-                    bar.message("Running database garbage collection | blocks ");
-                    bar
-                }));
-                tokio::spawn({
-                    let bar = bar.clone();
-                    let api = api.clone();
-                    async move {
-                        let mut interval =
-                            tokio::time::interval(tokio::time::Duration::from_secs(1));
-                        loop {
-                            interval.tick().await;
-                            if let Ok((progress, total)) = api
-                                .get_progress(GetProgressType::DatabaseGarbageCollection)
-                                .await
-                            {
-                                let bar = bar.lock().await;
-                                if bar.is_finish() {
-                                    break;
-                                }
-                                bar.set_total(total);
-                                bar.set(progress);
-                            }
-                        }
-                    }
-                });
+                let report = garbage_collect(&api).await?;
+                output::print(&report, |report| println!("{}", report.text()))
+            }
+        }
+    }
+}
+
+/// Summary of a garbage collection, printed by `db gc` and `chain prune`.
+#[derive(Debug, Serialize)]
+pub(super) struct GcReport {
+    /// Number of blocks walked from the head, which are kept
+    blocks_scanned: u64,
+    /// Size of the database before the collection, in bytes, if known
+    size_before: Option<u64>,
+    /// Size of the database after the collection, in bytes, if known
+    size_after: Option<u64>,
+    duration_secs: i64,
+}
+
+impl GcReport {
+    fn reclaimed_bytes(&self) -> Option<u64> {
+        Some(self.size_before?.saturating_sub(self.size_after?))
+    }
+
+    pub(super) fn text(&self) -> String {
+        let reclaimed = match self.reclaimed_bytes() {
+            Some(bytes) => bytes.human_count_bytes().to_string(),
+            None => "unknown".into(),
+        };
+        format!(
+            "Database garbage collection completed. took {}s, blocks scanned: {}, reclaimed: {reclaimed}",
+            self.duration_secs, self.blocks_scanned,
+        )
+    }
+}
 
-                api.db_gc().await?;
+/// Interval at which the progress is reported when it isn't displayed as a
+/// progress bar.
+const STATUS_INTERVAL_SECS: u64 = 10;
 
-                bar.lock().await.finish_println(&format!(
-                    "Database garbage collection completed. took {}s",
-                    (Utc::now() - start).num_seconds()
-                ));
+/// Runs a garbage collection of the node database, reporting its progress
+/// while it runs: as a progress bar on a terminal, as status lines otherwise.
+pub(super) async fn garbage_collect(api: &ApiInfo) -> anyhow::Result<GcReport> {
+    let start = Utc::now();
+    let size_before = api.db_size().await.ok();
 
-                Ok(())
+    let show_bar = std::io::stdout().is_terminal() && !OutputFormat::is_json();
+    let bar = Arc::new(tokio::sync::Mutex::new({
+        let bar = ProgressBar::new(0);
+        bar.message("Running database garbage collection | blocks ");
+        bar
+    }));
+    let progress_task = tokio::spawn({
+        let bar = bar.clone();
+        let api = api.clone();
+        async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
+            let mut ticks = 0;
+            loop {
+                interval.tick().await;
+                ticks += 1;
+                if let Ok((progress, total)) = api
+                    .get_progress(GetProgressType::DatabaseGarbageCollection)
+                    .await
+                {
+                    if !show_bar {
+                        if ticks % STATUS_INTERVAL_SECS == 0 {
+                            output::status(format!(
+                                "Running database garbage collection: {progress}/{total} blocks"
+                            ));
+                        }
+                        continue;
+                    }
+                    let bar = bar.lock().await;
+                    if bar.is_finish() {
+                        break;
+                    }
+                    bar.set_total(total);
+                    bar.set(progress);
+                }
             }
         }
+    });
+
+    let result = api.db_gc().await;
+    progress_task.abort();
+    if show_bar {
+        bar.lock().await.finish();
+    }
+    result?;
+
+    let (blocks_scanned, _) = api
+        .get_progress(GetProgressType::DatabaseGarbageCollection)
+        .await?;
+    Ok(GcReport {
+        blocks_scanned,
+        size_before,
+        size_after: api.db_size().await.ok(),
+        duration_secs: (Utc::now() - start).num_seconds(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reclaimed_bytes() {
+        let report = GcReport {
+            blocks_scanned: 10,
+            size_before: Some(1000),
+            size_after: Some(400),
+            duration_secs: 1,
+        };
+        assert_eq!(report.reclaimed_bytes(), Some(600));
+
+        // The database may grow while the collection runs
+        let report = GcReport {
+            size_after: Some(1200),
+            ..report
+        };
+        assert_eq!(report.reclaimed_bytes(), Some(0));
+
+        let report = GcReport {
+            size_before: None,
+            ..report
+        };
+        assert_eq!(report.reclaimed_bytes(), None);
     }
 }
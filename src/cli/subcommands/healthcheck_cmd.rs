@@ -0,0 +1,117 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::chain_sync::expected_epoch_now;
+use crate::cli_shared::output;
+use crate::rpc_client::ApiInfo;
+use crate::shim::clock::ChainEpoch;
+use anyhow::{bail, Context as _};
+use serde::Serialize;
+
+#[derive(Debug, clap::Args)]
+pub struct HealthcheckCommand {
+    /// Maximum number of epochs the head can lag behind the network
+    #[arg(long, default_value_t = 5)]
+    max_epochs_behind: ChainEpoch,
+    /// Minimum number of connected peers
+    #[arg(long, default_value_t = 1)]
+    min_peers: usize,
+}
+
+impl HealthcheckCommand {
+    /// Runs the checks, and fails if any of them does, so that the command
+    /// exits with a non-zero code.
+    pub async fn run(self, api: ApiInfo) -> anyhow::Result<()> {
+        let checks = [
+            ("rpc", check_rpc(&api).await),
+            ("sync", check_synced(&api, self.max_epochs_behind).await),
+            ("peers", self.check_peers(&api).await),
+        ];
+        let checks: Vec<_> = checks
+            .into_iter()
+            .map(|(name, result)| Check {
+                name,
+                healthy: result.is_ok(),
+                details: result.unwrap_or_else(|e| format!("{e:#}")),
+            })
+            .collect();
+        output::print(&checks, |checks| {
+            for check in checks {
+                let mark = if check.healthy { '+' } else { '!' };
+                println!("[{mark}] {}: {}", check.name, check.details);
+            }
+        })?;
+        if !checks.iter().all(|check| check.healthy) {
+            bail!("the node is unhealthy");
+        }
+        Ok(())
+    }
+
+    async fn check_peers(&self, api: &ApiInfo) -> anyhow::Result<String> {
+        let peers = api.net_info().await?.num_peers;
+        if peers < self.min_peers {
+            bail!(
+                "{peers} peers connected, expected at least {}",
+                self.min_peers
+            );
+        }
+        Ok(format!("{peers} peers connected"))
+    }
+}
+
+/// Result of a check, printed by `healthcheck`.
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    healthy: bool,
+    details: String,
+}
+
+/// Checks that the head of the node lags at most `max_epochs_behind` epochs
+/// behind the epoch the network is expected to be at, given the genesis
+/// timestamp and the block delay.
+pub(super) async fn check_synced(
+    api: &ApiInfo,
+    max_epochs_behind: ChainEpoch,
+) -> anyhow::Result<String> {
+    let (head, genesis, params) = tokio::try_join!(
+        api.chain_head(),
+        api.chain_get_genesis(),
+        api.state_get_network_params(),
+    )?;
+    let genesis = genesis.context("the node has no genesis")?;
+    let expected_epoch =
+        expected_epoch_now(genesis.min_timestamp(), params.block_delay_secs as u32);
+    sync_lag(head.epoch(), expected_epoch, max_epochs_behind)
+}
+
+async fn check_rpc(api: &ApiInfo) -> anyhow::Result<String> {
+    let version = api.version().await?;
+    Ok(format!("{} is up", version.version))
+}
+
+fn sync_lag(
+    head_epoch: ChainEpoch,
+    expected_epoch: ChainEpoch,
+    max_epochs_behind: ChainEpoch,
+) -> anyhow::Result<String> {
+    let lag = expected_epoch - head_epoch;
+    if lag > max_epochs_behind {
+        bail!("head at epoch {head_epoch} lags {lag} epochs behind the network (maximum: {max_epochs_behind})");
+    }
+    Ok(format!("head at epoch {head_epoch}, {lag} epochs behind"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_lag_threshold() {
+        assert!(sync_lag(100, 100, 5).is_ok());
+        assert!(sync_lag(95, 100, 5).is_ok());
+        assert!(sync_lag(94, 100, 5).is_err());
+        // A head ahead of the clock isn't lagging
+        assert!(sync_lag(101, 100, 5).is_ok());
+    }
+}
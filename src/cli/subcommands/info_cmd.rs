@@ -2,12 +2,15 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use crate::blocks::Tipset;
+use crate::cli_shared::output;
 use crate::rpc_client::ApiInfo;
 use crate::shim::econ::TokenAmount;
 use chrono::{DateTime, Utc};
 use clap::Subcommand;
+use serde::Serialize;
 
 use crate::shim::clock::{ChainEpoch, BLOCKS_PER_EPOCH, EPOCH_DURATION_SECONDS};
+use human_repr::HumanCount as _;
 use humantime::format_duration;
 use num::BigInt;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -16,10 +19,11 @@ use crate::cli::humantoken::TokenAmountPretty;
 
 #[derive(Debug, Subcommand)]
 pub enum InfoCommand {
+    /// Print a summary of the node status, the default
     Show,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct NodeStatusInfo {
     /// How far behind the node is with respect to syncing to head in seconds
     pub lag: i64,
@@ -27,20 +31,25 @@ pub struct NodeStatusInfo {
     /// an average of 5 blocks per tipset in the last couple of
     /// hours. The number of blocks per tipset is non-deterministic
     /// but averaging at 5 is considered healthy.
-    pub health: f64,
+    pub health: Option<f64>,
     /// epoch the node is currently at
     pub epoch: ChainEpoch,
     /// Base fee is the set price per unit of gas (measured in attoFIL/gas unit) to be burned (sent to an unrecoverable address) for every message execution
+    #[serde(with = "crate::lotus_json")]
     pub base_fee: TokenAmount,
     pub sync_status: SyncStatus,
     /// Start time of the node
-    pub start_time: DateTime<Utc>,
-    pub network: String,
+    pub start_time: Option<DateTime<Utc>>,
+    pub network: Option<String>,
+    /// Number of connected peers
+    pub peers: Option<usize>,
+    /// Size of the database on disk, in bytes
+    pub db_size: Option<u64>,
     pub default_wallet_address: Option<String>,
     pub default_wallet_address_balance: Option<String>,
 }
 
-#[derive(Debug, strum::Display, PartialEq)]
+#[derive(Debug, strum::Display, PartialEq, Serialize)]
 pub enum SyncStatus {
     Ok,
     Slow,
@@ -49,14 +58,11 @@ pub enum SyncStatus {
 }
 
 impl NodeStatusInfo {
+    /// Status of the node at `head`, with the optional information left out.
     pub fn new(
         cur_duration: Duration,
-        blocks_per_tipset_last_finality: f64,
+        blocks_per_tipset_last_finality: Option<f64>,
         head: &Tipset,
-        start_time: DateTime<Utc>,
-        network: String,
-        default_wallet_address: Option<String>,
-        default_wallet_address_balance: Option<String>,
     ) -> NodeStatusInfo {
         let ts = head.min_timestamp() as i64;
         let cur_duration_secs = cur_duration.as_secs() as i64;
@@ -77,7 +83,8 @@ impl NodeStatusInfo {
         let base_fee = head.min_ticket_block().parent_base_fee().clone();
 
         // blocks_per_tipset_last_finality = no of blocks till head / chain finality
-        let health = 100. * blocks_per_tipset_last_finality / BLOCKS_PER_EPOCH as f64;
+        let health = blocks_per_tipset_last_finality
+            .map(|blocks_per_tipset| 100. * blocks_per_tipset / BLOCKS_PER_EPOCH as f64);
 
         Self {
             lag,
@@ -85,26 +92,31 @@ impl NodeStatusInfo {
             epoch: head.epoch(),
             base_fee,
             sync_status,
-            start_time,
-            network,
-            default_wallet_address,
-            default_wallet_address_balance,
+            start_time: None,
+            network: None,
+            peers: None,
+            db_size: None,
+            default_wallet_address: None,
+            default_wallet_address_balance: None,
         }
     }
 
     fn format(&self, now: DateTime<Utc>) -> String {
-        let network = format!("Network: {}", self.network);
-
-        let uptime = {
-            let uptime = (now - self.start_time)
-                .to_std()
-                .expect("failed converting to std duration");
-            let uptime = Duration::from_secs(uptime.as_secs());
-            let fmt_uptime = format_duration(uptime);
-            format!(
-                "Uptime: {fmt_uptime} (Started at: {})",
-                self.start_time.with_timezone(&chrono::offset::Local)
-            )
+        let network = format!("Network: {}", or_unknown(self.network.as_ref()));
+
+        let uptime = match self.start_time {
+            Some(start_time) => {
+                let uptime = (now - start_time)
+                    .to_std()
+                    .expect("failed converting to std duration");
+                let uptime = Duration::from_secs(uptime.as_secs());
+                let fmt_uptime = format_duration(uptime);
+                format!(
+                    "Uptime: {fmt_uptime} (Started at: {})",
+                    start_time.with_timezone(&chrono::offset::Local)
+                )
+            }
+            None => format!("Uptime: {UNKNOWN}"),
         };
 
         let chain = {
@@ -122,7 +134,17 @@ impl NodeStatusInfo {
             )
         };
 
-        let chain_health = format!("Chain health: {:.2}%\n\n", self.health);
+        let chain_health = match self.health {
+            Some(health) => format!("Chain health: {health:.2}%"),
+            None => format!("Chain health: {UNKNOWN}"),
+        };
+
+        let peers = format!("Peers: {}", or_unknown(self.peers.as_ref()));
+
+        let db_size = format!(
+            "Database size: {}\n",
+            or_unknown(self.db_size.map(|size| size.human_count_bytes()).as_ref())
+        );
 
         let wallet_info = {
             let wallet_address = self
@@ -149,45 +171,63 @@ impl NodeStatusInfo {
             )
         };
 
-        [network, uptime, chain, chain_health, wallet_info].join("\n")
+        [
+            network,
+            uptime,
+            chain,
+            chain_health,
+            peers,
+            db_size,
+            wallet_info,
+        ]
+        .join("\n")
     }
 }
 
 impl InfoCommand {
     pub async fn run(self, api: ApiInfo) -> anyhow::Result<()> {
-        let (node_status, head, network, start_time, default_wallet_address) = tokio::try_join!(
+        // Only the head is required, the rest of the information is reported
+        // as unknown if it can't be fetched, e.g. with a read-only token.
+        let head = api.chain_head().await?;
+        let (node_status, network, start_time, net_info, db_size, default_wallet_address) = tokio::join!(
             api.node_status(),
-            api.chain_head(),
             api.state_network_name(),
             api.start_time(),
+            api.net_info(),
+            api.db_size(),
             api.wallet_default_address(),
-        )?;
+        );
+        let default_wallet_address = default_wallet_address.ok().flatten();
+        let default_wallet_address_balance = match &default_wallet_address {
+            Some(def_addr) => api.wallet_balance(def_addr.clone()).await.ok(),
+            None => None,
+        };
 
         let cur_duration: Duration = SystemTime::now().duration_since(UNIX_EPOCH)?;
-        let blocks_per_tipset_last_finality =
-            node_status.chain_status.blocks_per_tipset_last_finality;
-
-        let default_wallet_address_balance = if let Some(def_addr) = &default_wallet_address {
-            let balance = api.wallet_balance(def_addr.clone()).await?;
-            Some(balance)
-        } else {
-            None
+        let blocks_per_tipset_last_finality = node_status
+            .ok()
+            .map(|status| status.chain_status.blocks_per_tipset_last_finality);
+
+        let node_status_info = NodeStatusInfo {
+            start_time: start_time.ok(),
+            network: network.ok(),
+            peers: net_info.ok().map(|info| info.num_peers),
+            db_size: db_size.ok(),
+            default_wallet_address,
+            default_wallet_address_balance,
+            ..NodeStatusInfo::new(cur_duration, blocks_per_tipset_last_finality, &head)
         };
 
-        let node_status_info = NodeStatusInfo::new(
-            cur_duration,
-            blocks_per_tipset_last_finality,
-            &head,
-            start_time,
-            network,
-            default_wallet_address.clone(),
-            default_wallet_address_balance,
-        );
+        output::print(&node_status_info, |info| {
+            println!("{}", info.format(Utc::now()))
+        })
+    }
+}
 
-        println!("{}", node_status_info.format(Utc::now()));
+const UNKNOWN: &str = "unknown";
 
-        Ok(())
-    }
+fn or_unknown(value: Option<&impl ToString>) -> String {
+    value.map_or_else(|| UNKNOWN.to_string(), ToString::to_string)
 }
 
 fn balance(bal: &str) -> Result<String, anyhow::Error> {
@@ -220,27 +260,25 @@ mod tests {
     fn mock_node_status() -> NodeStatusInfo {
         NodeStatusInfo {
             lag: 0,
-            health: 90.,
+            health: Some(90.),
             epoch: i64::MAX,
             base_fee: TokenAmount::from_whole(1),
             sync_status: SyncStatus::Ok,
-            start_time: DateTime::<chrono::Utc>::MIN_UTC,
-            network: "calibnet".to_string(),
+            start_time: Some(DateTime::<chrono::Utc>::MIN_UTC),
+            network: Some("calibnet".to_string()),
+            peers: Some(10),
+            db_size: Some(1 << 30),
             default_wallet_address: Some("-".to_string()),
             default_wallet_address_balance: None,
         }
     }
 
     fn node_status(duration: Duration, tipset: &Tipset) -> NodeStatusInfo {
-        NodeStatusInfo::new(
-            duration,
-            20.,
-            tipset,
-            DateTime::<chrono::Utc>::MIN_UTC,
-            "calibnet".to_string(),
-            None,
-            None,
-        )
+        NodeStatusInfo {
+            start_time: Some(DateTime::<chrono::Utc>::MIN_UTC),
+            network: Some("calibnet".to_string()),
+            ..NodeStatusInfo::new(duration, Some(20.), tipset)
+        }
     }
 
     #[quickcheck]
@@ -259,7 +297,7 @@ mod tests {
         let tipset = mock_tipset_at(duration.as_secs().saturating_sub(200));
         let status = node_status(duration, tipset.as_ref());
 
-        assert!(status.health.is_finite());
+        assert!(status.health.unwrap().is_finite());
         assert_ne!(status.sync_status, SyncStatus::Ok);
         assert_ne!(status.sync_status, SyncStatus::Slow);
     }
@@ -273,7 +311,7 @@ mod tests {
                 .saturating_sub(EPOCH_DURATION_SECONDS as u64 * 4),
         );
         let status = node_status(duration, tipset.as_ref());
-        assert!(status.health.is_finite());
+        assert!(status.health.unwrap().is_finite());
         assert_ne!(status.sync_status, SyncStatus::Behind);
         assert_ne!(status.sync_status, SyncStatus::Ok);
     }
@@ -318,4 +356,23 @@ mod tests {
             .format(DateTime::<chrono::Utc>::MIN_UTC)
             .contains(&expected_status_fmt));
     }
+
+    #[test]
+    fn unknown_values() {
+        let status = NodeStatusInfo::new(Duration::from_secs(60), None, &mock_tipset_at(50));
+        let formatted = status.format(DateTime::<chrono::Utc>::MIN_UTC);
+        for line in [
+            "Network: unknown",
+            "Uptime: unknown",
+            "Chain health: unknown",
+            "Peers: unknown",
+            "Database size: unknown",
+        ] {
+            assert!(formatted.contains(line), "{line} not in {formatted}");
+        }
+
+        let formatted = mock_node_status().format(DateTime::<chrono::Utc>::MIN_UTC);
+        assert!(formatted.contains("Peers: 10"));
+        assert!(formatted.contains("Chain health: 90.00%"));
+    }
 }
@@ -0,0 +1,41 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::cli_shared::output;
+use crate::rpc_client::ApiInfo;
+use clap::Subcommand;
+
+#[derive(Debug, Subcommand)]
+pub enum LogCommands {
+    /// List the log filter directives in effect
+    List,
+    /// Set the maximum level logged for a target
+    SetLevel {
+        /// Module path to filter, e.g. `forest_filecoin::chain_sync`
+        target: String,
+        /// One of `trace`, `debug`, `info`, `warn`, `error` or `off`
+        level: String,
+    },
+    /// Restore the log filter directives the node was started with
+    Reset,
+}
+
+impl LogCommands {
+    pub async fn run(self, api: ApiInfo) -> anyhow::Result<()> {
+        match self {
+            Self::List => output::print(&api.log_list().await?, |directives| {
+                for directive in directives {
+                    println!("{directive}");
+                }
+            }),
+            Self::SetLevel { target, level } => {
+                api.log_set_level(target, level).await?;
+                Ok(())
+            }
+            Self::Reset => {
+                api.log_reset().await?;
+                Ok(())
+            }
+        }
+    }
+}
@@ -11,21 +11,27 @@ mod auth_cmd;
 mod chain_cmd;
 mod config_cmd;
 mod db_cmd;
+mod healthcheck_cmd;
 mod info_cmd;
+mod log_cmd;
 mod mpool_cmd;
+mod msg_cmd;
 mod net_cmd;
 pub(crate) mod send_cmd;
 mod shutdown_cmd;
 mod snapshot_cmd;
 mod state_cmd;
 mod sync_cmd;
+mod wait_api_cmd;
 
 use std::io::Write;
 
 use crate::blocks::Tipset;
 pub(crate) use crate::cli_shared::cli::Config;
 use crate::cli_shared::cli::HELP_MESSAGE;
+use crate::cli_shared::output::OutputFormat;
 use crate::utils::version::FOREST_VERSION_STRING;
+use anyhow::bail;
 use cid::Cid;
 use clap::Parser;
 use serde::Serialize;
@@ -33,11 +39,12 @@ use tracing::error;
 
 pub(super) use self::{
     attach_cmd::AttachCommand, auth_cmd::AuthCommands, chain_cmd::ChainCommands,
-    config_cmd::ConfigCommands, db_cmd::DBCommands, mpool_cmd::MpoolCommands, net_cmd::NetCommands,
-    send_cmd::SendCommand, shutdown_cmd::ShutdownCommand, snapshot_cmd::SnapshotCommands,
-    state_cmd::StateCommands, sync_cmd::SyncCommands,
+    config_cmd::ConfigCommands, db_cmd::DBCommands, healthcheck_cmd::HealthcheckCommand,
+    info_cmd::InfoCommand, log_cmd::LogCommands, mpool_cmd::MpoolCommands, msg_cmd::MsgCommands,
+    net_cmd::NetCommands, send_cmd::SendCommand, shutdown_cmd::ShutdownCommand,
+    snapshot_cmd::SnapshotCommands, state_cmd::StateCommands, sync_cmd::SyncCommands,
+    wait_api_cmd::WaitApiCommand,
 };
-use crate::cli::subcommands::info_cmd::InfoCommand;
 
 /// CLI structure generated when interacting with Forest binary
 #[derive(Parser)]
@@ -47,6 +54,9 @@ pub struct Cli {
     /// Client JWT token to use for JSON-RPC authentication
     #[arg(short, long)]
     pub token: Option<String>,
+    /// Format of the printed results
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
     #[command(subcommand)]
     pub cmd: Subcommand,
 }
@@ -74,6 +84,10 @@ pub enum Subcommand {
     #[command(subcommand)]
     Mpool(MpoolCommands),
 
+    /// Decode and inspect messages
+    #[command(subcommand)]
+    Msg(MsgCommands),
+
     /// Interact with and query Filecoin chain state
     #[command(subcommand)]
     State(StateCommands),
@@ -90,18 +104,32 @@ pub enum Subcommand {
     Send(SendCommand),
 
     /// Print node info
-    #[command(subcommand)]
-    Info(InfoCommand),
+    Info {
+        #[command(subcommand)]
+        command: Option<InfoCommand>,
+    },
 
     /// Database management
     #[command(subcommand)]
     DB(DBCommands),
 
+    /// Inspect or change the node's log filters
+    #[command(subcommand)]
+    Log(LogCommands),
+
     /// Attach to daemon via a JavaScript console
     Attach(AttachCommand),
 
     /// Shutdown Forest
     Shutdown(ShutdownCommand),
+
+    /// Check that the node is up, in sync and connected to peers, exiting with
+    /// a non-zero code otherwise
+    Healthcheck(HealthcheckCommand),
+
+    /// Wait until the node API is reachable and, optionally, the node is in
+    /// sync
+    WaitApi(WaitApiCommand),
 }
 
 /// Format a vector to a prettified string
@@ -116,6 +144,22 @@ pub fn cli_error_and_die(msg: impl AsRef<str>, code: i32) -> ! {
     std::process::exit(code);
 }
 
+/// Asks the user to confirm an operation, unless `no_confirm` is set
+pub(super) fn maybe_confirm(no_confirm: bool, prompt: impl Into<String>) -> anyhow::Result<()> {
+    if no_confirm {
+        return Ok(());
+    }
+    let should_continue = dialoguer::Confirm::new()
+        .default(false)
+        .with_prompt(prompt)
+        .wait_for_newline(true)
+        .interact()?;
+    match should_continue {
+        true => Ok(()),
+        false => bail!("Operation cancelled by user"),
+    }
+}
+
 /// Prints a pretty HTTP JSON-RPC response result
 pub(super) fn print_pretty_json<T: Serialize>(obj: T) -> anyhow::Result<()> {
     println!("{}", serde_json::to_string_pretty(&obj)?);
@@ -139,8 +183,7 @@ pub(super) fn print_rpc_res_cids(tipset: Tipset) -> anyhow::Result<()> {
 
 /// Prints a bytes HTTP JSON-RPC response result
 pub(super) fn print_rpc_res_bytes(obj: Vec<u8>) -> anyhow::Result<()> {
-    println!("{}", String::from_utf8(obj)?);
-    Ok(())
+    crate::cli_shared::output::print(&String::from_utf8(obj)?, |s| println!("{s}"))
 }
 
 pub fn prompt_confirm() -> bool {
@@ -4,6 +4,8 @@
 use std::str::FromStr;
 
 use crate::blocks::Tipset;
+use crate::cli_shared::output::{self, OutputFormat};
+use crate::lotus_json::LotusJson;
 use crate::message::SignedMessage;
 use crate::rpc_client::ApiInfo;
 use crate::shim::address::StrictAddress;
@@ -13,6 +15,7 @@ use crate::shim::{address::Address, econ::TokenAmount};
 use ahash::{HashMap, HashSet};
 use clap::Subcommand;
 use num::BigInt;
+use serde::Serialize;
 
 #[derive(Debug, Subcommand)]
 pub enum MpoolCommands {
@@ -84,12 +87,12 @@ async fn get_actor_sequence(message: &Message, tipset: &Tipset, api: &ApiInfo) -
             if let Some(state) = maybe_actor {
                 state
             } else {
-                println!("{}, actor state not found", address);
+                output::status(format!("{address}, actor state not found"));
                 return None;
             }
         }
         Err(err) => {
-            println!("{}, err: {}", address, err);
+            output::status(format!("{address}, err: {err}"));
             return None;
         }
     };
@@ -99,17 +102,72 @@ async fn get_actor_sequence(message: &Message, tipset: &Tipset, api: &ApiInfo) -
 
 type StatBucket = HashMap<u64, Message>;
 
-#[derive(Debug, Default, Eq, PartialEq)]
+/// Percentiles of the gas premiums printed by `mpool stat`.
+const GAS_PREMIUM_PERCENTILES: [u8; 5] = [10, 25, 50, 75, 90];
+
+#[derive(Debug, Default, Eq, PartialEq, Serialize)]
 struct MpStat {
     address: String,
     past: u64,
     current: u64,
     future: u64,
+    /// Sequences missing between the actor sequence and the highest pending
+    /// one, which block the messages after them.
+    gaps: u64,
     below_current: u64,
     below_past: u64,
+    #[serde(serialize_with = "crate::lotus_json::stringify::serialize")]
     gas_limit: BigInt,
 }
 
+/// Gas premium of the pending messages at a percentile, in attoFIL.
+#[derive(Debug, Serialize)]
+struct GasPremiumPercentile {
+    percentile: u8,
+    #[serde(serialize_with = "crate::lotus_json::stringify::serialize")]
+    premium: BigInt,
+}
+
+/// Result of `mpool stat`: the statistics of each address and their total.
+#[derive(Debug, Serialize)]
+struct MpStats {
+    basefee_lookback: u32,
+    addresses: Vec<MpStat>,
+    total: MpStat,
+    gas_premiums: Vec<GasPremiumPercentile>,
+}
+
+impl MpStats {
+    fn new(addresses: Vec<MpStat>, basefee_lookback: u32, premiums: &[(u8, TokenAmount)]) -> Self {
+        let mut total = MpStat {
+            address: "total".into(),
+            ..Default::default()
+        };
+        for stat in addresses.iter() {
+            total.past += stat.past;
+            total.current += stat.current;
+            total.future += stat.future;
+            total.gaps += stat.gaps;
+            total.below_current += stat.below_current;
+            total.below_past += stat.below_past;
+            total.gas_limit += &stat.gas_limit;
+        }
+        let gas_premiums = premiums
+            .iter()
+            .map(|(percentile, premium)| GasPremiumPercentile {
+                percentile: *percentile,
+                premium: premium.atto().clone(),
+            })
+            .collect();
+        Self {
+            basefee_lookback,
+            addresses,
+            total,
+            gas_premiums,
+        }
+    }
+}
+
 fn compute_stats(
     messages: &[Message],
     actor_sequences: HashMap<Address, u64>,
@@ -134,8 +192,14 @@ fn compute_stats(
             curr_sequence += 1;
         }
 
+        let max_sequence = bucket.keys().copied().max().unwrap_or_default();
+        let pending_from_actor_sequence = bucket
+            .keys()
+            .filter(|sequence| **sequence >= actor_sequence)
+            .count() as u64;
         let mut stat = MpStat {
             address: address.to_string(),
+            gaps: (max_sequence + 1).saturating_sub(actor_sequence) - pending_from_actor_sequence,
             ..Default::default()
         };
 
@@ -165,43 +229,55 @@ fn compute_stats(
     stats
 }
 
-fn print_stats(stats: &[MpStat], basefee_lookback: u32) {
-    let mut total = MpStat::default();
-
-    for stat in stats {
-        total.past += stat.past;
-        total.current += stat.current;
-        total.future += stat.future;
-        total.below_current += stat.below_current;
-        total.below_past += stat.below_past;
-        total.gas_limit += &stat.gas_limit;
-
-        println!(
-            "{}: Nonce past: {}, cur: {}, future: {}; FeeCap cur: {}, min-{}: {}, gasLimit: {}",
-            stat.address,
-            stat.past,
-            stat.current,
-            stat.future,
-            stat.below_current,
-            basefee_lookback,
-            stat.below_past,
-            stat.gas_limit
-        );
+/// Gas premiums of the messages at [`GAS_PREMIUM_PERCENTILES`], by the
+/// nearest-rank method. Empty without messages.
+fn gas_premium_percentiles(messages: &[Message]) -> Vec<(u8, TokenAmount)> {
+    let mut premiums: Vec<_> = messages.iter().map(|msg| &msg.gas_premium).collect();
+    if premiums.is_empty() {
+        return vec![];
     }
+    premiums.sort();
+    GAS_PREMIUM_PERCENTILES
+        .iter()
+        .map(|&percentile| {
+            let rank = (percentile as usize * premiums.len()).div_ceil(100);
+            (percentile, premiums[rank.max(1) - 1].clone())
+        })
+        .collect()
+}
 
-    println!("-----");
+fn print_stat(stat: &MpStat, basefee_lookback: u32) {
     println!(
-        "total: Nonce past: {}, cur: {}, future: {}; FeeCap cur: {}, min-{}: {}, gasLimit: {}",
-        total.past,
-        total.current,
-        total.future,
-        total.below_current,
+        "{}: Nonce past: {}, cur: {}, future: {}, gaps: {}; FeeCap cur: {}, min-{}: {}, gasLimit: {}",
+        stat.address,
+        stat.past,
+        stat.current,
+        stat.future,
+        stat.gaps,
+        stat.below_current,
         basefee_lookback,
-        total.below_past,
-        total.gas_limit
+        stat.below_past,
+        stat.gas_limit
     );
 }
 
+fn print_stats(stats: &MpStats) {
+    for stat in stats.addresses.iter() {
+        print_stat(stat, stats.basefee_lookback);
+    }
+
+    println!("-----");
+    print_stat(&stats.total, stats.basefee_lookback);
+    if !stats.gas_premiums.is_empty() {
+        let percentiles: Vec<_> = stats
+            .gas_premiums
+            .iter()
+            .map(|p| format!("p{}: {}", p.percentile, p.premium))
+            .collect();
+        println!("GasPremium (attoFIL) {}", percentiles.join(", "));
+    }
+}
+
 impl MpoolCommands {
     pub async fn run(self, api: ApiInfo) -> anyhow::Result<()> {
         match self {
@@ -222,15 +298,22 @@ impl MpoolCommands {
 
                 let filtered_messages = filter_messages(messages, local_addrs, &to, &from)?;
 
+                if cids {
+                    let cids = filtered_messages
+                        .iter()
+                        .map(|msg| msg.cid().map(|cid| cid.to_string()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    return output::print(&cids, |cids| {
+                        for cid in cids {
+                            println!("{cid}");
+                        }
+                    });
+                }
+                if OutputFormat::is_json() {
+                    return super::print_pretty_json(LotusJson(filtered_messages));
+                }
                 for msg in filtered_messages {
-                    if cids {
-                        println!("{}", msg.cid().unwrap());
-                    } else {
-                        println!(
-                            "{}",
-                            serde_json::to_string_pretty(&crate::lotus_json::LotusJson(msg))?
-                        );
-                    }
+                    println!("{}", serde_json::to_string_pretty(&LotusJson(msg))?);
                 }
 
                 Ok(())
@@ -266,11 +349,16 @@ impl MpoolCommands {
                     }
                 }
 
+                // Messages whose actor isn't found are left out
+                let messages: Vec<Message> = messages
+                    .into_iter()
+                    .filter(|msg| actor_sequences.contains_key(&msg.from))
+                    .collect();
                 let stats = compute_stats(&messages, actor_sequences, curr_base_fee, min_base_fee);
 
-                print_stats(&stats, basefee_lookback);
-
-                Ok(())
+                let stats =
+                    MpStats::new(stats, basefee_lookback, &gas_premium_percentiles(&messages));
+                output::print(&stats, print_stats)
             }
         }
     }
@@ -477,6 +565,7 @@ mod tests {
                 past: 0,
                 current: 1,
                 future: 0,
+                gaps: 0,
                 below_current: 0,
                 below_past: 0,
                 gas_limit: 25201703.into(),
@@ -486,6 +575,7 @@ mod tests {
                 past: 2,
                 current: 0,
                 future: 0,
+                gaps: 0,
                 below_current: 0,
                 below_past: 0,
                 gas_limit: 133944296.into(),
@@ -494,4 +584,55 @@ mod tests {
 
         assert_eq!(stats, expected);
     }
+
+    #[test]
+    fn nonce_gaps_and_premiums() {
+        let from = Address::new_id(1000);
+        let message = |sequence: u64, gas_premium: u64| crate::shim::message::Message {
+            from,
+            sequence,
+            gas_premium: TokenAmount::from_atto(gas_premium),
+            ..Default::default()
+        };
+        // 11 and 13 are missing
+        let messages = [
+            message(10, 400),
+            message(12, 100),
+            message(14, 300),
+            message(9, 200),
+        ];
+        let actor_sequences = HashMap::from_iter([(from, 10)]);
+        let stats = compute_stats(
+            &messages,
+            actor_sequences,
+            TokenAmount::default(),
+            TokenAmount::default(),
+        );
+        assert_eq!(
+            (
+                stats[0].past,
+                stats[0].current,
+                stats[0].future,
+                stats[0].gaps
+            ),
+            (1, 1, 2, 2)
+        );
+
+        let premiums = gas_premium_percentiles(&messages);
+        assert_eq!(
+            premiums,
+            [10, 25, 50, 75, 90]
+                .into_iter()
+                .zip([100, 100, 200, 300, 400].map(TokenAmount::from_atto))
+                .collect::<Vec<_>>()
+        );
+        assert!(gas_premium_percentiles(&[]).is_empty());
+
+        let stats = MpStats::new(stats, 60, &premiums);
+        assert_eq!((stats.total.past, stats.total.future), (1, 2));
+        assert_eq!(
+            serde_json::to_value(&stats.gas_premiums[0]).unwrap(),
+            serde_json::json!({ "percentile": 10, "premium": "100" })
+        );
+    }
 }
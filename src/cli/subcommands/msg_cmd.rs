@@ -0,0 +1,296 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::str::FromStr as _;
+
+use crate::blocks::TipsetKeys;
+use crate::cli::humantoken::TokenAmountPretty as _;
+use crate::cli_shared::output;
+use crate::lotus_json::LotusJson;
+use crate::message::SignedMessage;
+use crate::rpc_client::ApiInfo;
+use crate::shim::actor_methods;
+use crate::shim::address::Address;
+use crate::shim::econ::TokenAmount;
+use crate::shim::machine::{ActorVersion, BuiltinActor, BuiltinActorManifest};
+use crate::shim::message::{Message, MethodNum, METHOD_SEND};
+use anyhow::Context as _;
+use base64::{prelude::BASE64_STANDARD, Engine as _};
+use cid::Cid;
+use clap::Subcommand;
+use serde::Serialize;
+
+#[derive(Debug, Subcommand)]
+pub enum MsgCommands {
+    /// Decode a message and print its fields, with the method name and the
+    /// parameters resolved from the builtin actor of the recipient
+    Inspect {
+        /// CID of a message known to the node, a message in JSON, or a
+        /// CBOR-encoded message in hex or base64. Signed messages are
+        /// accepted too.
+        message: String,
+    },
+}
+
+impl MsgCommands {
+    pub async fn run(self, api: ApiInfo) -> anyhow::Result<()> {
+        match self {
+            Self::Inspect { message } => {
+                let (cid, message, signature) = match parse_message(&message)? {
+                    MessageInput::Cid(cid) => (cid, api.chain_get_message(cid).await?, None),
+                    MessageInput::Unsigned(message) => (message.cid()?, message, None),
+                    MessageInput::Signed(smsg) => {
+                        let cid = smsg.cid()?;
+                        let valid = smsg
+                            .signature
+                            .verify(&smsg.message.cid()?.to_bytes(), &smsg.message.from)
+                            .is_ok();
+                        let signature = SignatureCheck {
+                            r#type: format!("{:?}", smsg.signature.signature_type()),
+                            valid,
+                        };
+                        (cid, smsg.message, Some(signature))
+                    }
+                };
+                let actor = recipient_actor(&api, message.to).await;
+                let inspection = Inspection::new(cid, &message, actor, signature)?;
+                output::print(&inspection, |inspection| print!("{}", inspection.text()))
+            }
+        }
+    }
+}
+
+/// A message given on the command line.
+#[derive(Debug, PartialEq)]
+enum MessageInput {
+    Cid(Cid),
+    Unsigned(Message),
+    Signed(SignedMessage),
+}
+
+/// Parses a message CID, a (signed) message in Lotus JSON, or a (signed)
+/// message encoded in CBOR, as hex or base64.
+fn parse_message(input: &str) -> anyhow::Result<MessageInput> {
+    let input = input.trim();
+    if let Ok(cid) = Cid::from_str(input) {
+        return Ok(MessageInput::Cid(cid));
+    }
+    if input.starts_with('{') {
+        if let Ok(LotusJson(smsg)) = serde_json::from_str(input) {
+            return Ok(MessageInput::Signed(smsg));
+        }
+        let LotusJson(message) = serde_json::from_str(input).context("invalid JSON message")?;
+        return Ok(MessageInput::Unsigned(message));
+    }
+    let hex = input.strip_prefix("0x").unwrap_or(input);
+    let bytes = match hex::decode(hex) {
+        Ok(bytes) => bytes,
+        Err(_) => BASE64_STANDARD
+            .decode(input)
+            .context("expected a message CID, a JSON message, or a message in hex or base64")?,
+    };
+    if let Ok(message) = fvm_ipld_encoding::from_slice(&bytes) {
+        return Ok(MessageInput::Unsigned(message));
+    }
+    let smsg = fvm_ipld_encoding::from_slice(&bytes).context("invalid CBOR message")?;
+    Ok(MessageInput::Signed(smsg))
+}
+
+/// The builtin actor of `address` and its version at the head, if the node
+/// knows them.
+pub(super) async fn recipient_actor(
+    api: &ApiInfo,
+    address: Address,
+) -> Option<(BuiltinActor, ActorVersion)> {
+    let actor = api
+        .state_get_actor(address, TipsetKeys::default())
+        .await
+        .ok()??;
+    let name = api
+        .state_builtin_actor(actor.code, TipsetKeys::default())
+        .await
+        .ok()?;
+    let network_version = api
+        .state_network_version(TipsetKeys::default())
+        .await
+        .ok()?;
+    Some((
+        BuiltinActorManifest::builtin_actor_named(&name)?,
+        ActorVersion::for_network_version(network_version)?,
+    ))
+}
+
+/// Signature of a signed message, and whether it matches the sender.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub(super) struct SignatureCheck {
+    r#type: String,
+    valid: bool,
+}
+
+/// Decoded fields of a message, printed by `msg inspect`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub(super) struct Inspection {
+    #[serde(with = "crate::lotus_json")]
+    cid: Cid,
+    from: String,
+    to: String,
+    /// Builtin actor of the recipient, if known
+    actor: Option<&'static str>,
+    #[serde(with = "crate::lotus_json")]
+    value: TokenAmount,
+    nonce: u64,
+    method: MethodNum,
+    method_name: Option<&'static str>,
+    /// Decoded parameters, or their raw bytes in base64 if they can't be
+    /// decoded
+    params: serde_json::Value,
+    gas_limit: u64,
+    #[serde(with = "crate::lotus_json")]
+    gas_fee_cap: TokenAmount,
+    #[serde(with = "crate::lotus_json")]
+    gas_premium: TokenAmount,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<SignatureCheck>,
+}
+
+impl Inspection {
+    pub(super) fn new(
+        cid: Cid,
+        message: &Message,
+        actor: Option<(BuiltinActor, ActorVersion)>,
+        signature: Option<SignatureCheck>,
+    ) -> anyhow::Result<Self> {
+        let method_name = match actor {
+            Some((actor, version)) => {
+                actor_methods::method(actor, version, message.method_num).map(|m| m.name)
+            }
+            // Every actor can receive funds
+            None => (message.method_num == METHOD_SEND).then_some("Send"),
+        };
+        let params = match actor.and_then(|(actor, version)| {
+            actor_methods::decode_params(actor, version, message.method_num, &message.params).ok()
+        }) {
+            Some(params) => params,
+            None if message.params.bytes().is_empty() => serde_json::Value::Null,
+            None => serde_json::to_value(LotusJson(message.params.clone()))?,
+        };
+        Ok(Self {
+            cid,
+            from: message.from.to_string(),
+            to: message.to.to_string(),
+            actor: actor.map(|(actor, _)| actor.name()),
+            value: message.value.clone(),
+            nonce: message.sequence,
+            method: message.method_num,
+            method_name,
+            params,
+            gas_limit: message.gas_limit,
+            gas_fee_cap: message.gas_fee_cap.clone(),
+            gas_premium: message.gas_premium.clone(),
+            signature,
+        })
+    }
+
+    pub(super) fn text(&self) -> String {
+        let mut lines = vec![
+            format!("CID:         {}", self.cid),
+            format!("From:        {}", self.from),
+            format!(
+                "To:          {} ({})",
+                self.to,
+                self.actor.unwrap_or("unknown actor")
+            ),
+            format!("Value:       {}", self.value.pretty()),
+            format!("Nonce:       {}", self.nonce),
+            format!(
+                "Method:      {} ({})",
+                self.method,
+                self.method_name.unwrap_or("unknown method")
+            ),
+            format!("Gas limit:   {}", self.gas_limit),
+            format!("Gas fee cap: {}", self.gas_fee_cap.pretty()),
+            format!("Gas premium: {}", self.gas_premium.pretty()),
+            format!(
+                "Maximum fee: {}",
+                (&self.gas_fee_cap * self.gas_limit).pretty()
+            ),
+        ];
+        if let Some(signature) = &self.signature {
+            let validity = if signature.valid { "valid" } else { "invalid" };
+            lines.push(format!("Signature:   {} ({validity})", signature.r#type));
+        }
+        match &self.params {
+            serde_json::Value::Null => lines.push("Params:      none".into()),
+            params => {
+                lines.push("Params:".into());
+                let pretty = serde_json::to_string_pretty(params).unwrap_or_default();
+                lines.extend(pretty.lines().map(|line| format!("  {line}")));
+            }
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fvm_ipld_encoding::RawBytes;
+
+    fn message() -> Message {
+        Message {
+            from: Address::new_id(1000),
+            to: Address::new_id(1001),
+            sequence: 7,
+            method_num: 3,
+            params: RawBytes::new(
+                fvm_ipld_encoding::to_vec(&(7_u64, RawBytes::default())).unwrap(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_message_inputs() {
+        let message = message();
+        let cbor = fvm_ipld_encoding::to_vec(&message).unwrap();
+        let expected = MessageInput::Unsigned(message.clone());
+
+        assert_eq!(parse_message(&hex::encode(&cbor)).unwrap(), expected);
+        assert_eq!(
+            parse_message(&format!("0x{}", hex::encode(&cbor))).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_message(&BASE64_STANDARD.encode(&cbor)).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_message(&serde_json::to_string(&LotusJson(message.clone())).unwrap()).unwrap(),
+            expected
+        );
+        let cid = message.cid().unwrap();
+        assert_eq!(
+            parse_message(&cid.to_string()).unwrap(),
+            MessageInput::Cid(cid)
+        );
+        assert!(parse_message("not a message").is_err());
+    }
+
+    #[test]
+    fn resolve_method_and_params() {
+        let message = message();
+        let cid = message.cid().unwrap();
+
+        let actor = Some((BuiltinActor::Multisig, ActorVersion::V12));
+        let inspection = Inspection::new(cid, &message, actor, None).unwrap();
+        assert_eq!(inspection.method_name, Some("Approve"));
+        assert!(inspection.params.get("ID").is_some());
+
+        // Without the actor, the parameters are left encoded
+        let inspection = Inspection::new(cid, &message, None, None).unwrap();
+        assert_eq!(inspection.method_name, None);
+        assert!(inspection.params.is_string());
+    }
+}
@@ -1,15 +1,17 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use crate::libp2p::{Multiaddr, Protocol};
+use std::time::Duration;
+
+use crate::cli_shared::output::{self, Outcome};
+use crate::libp2p::{Multiaddr, PeerId, Protocol};
 use crate::rpc_api::data_types::AddrInfo;
 use crate::rpc_client::ApiInfo;
 use ahash::HashSet;
-use cid::multibase;
+use anyhow::Context as _;
 use clap::Subcommand;
 use itertools::Itertools;
-
-use crate::cli::subcommands::cli_error_and_die;
+use serde::Serialize;
 
 #[derive(Debug, Subcommand)]
 pub enum NetCommands {
@@ -18,17 +20,64 @@ pub enum NetCommands {
     /// Lists `libp2p` swarm network info
     Info,
     /// Lists `libp2p` swarm peers
-    Peers,
-    /// Connects to a peer by its peer ID and multi-addresses
+    Peers {
+        /// Print the agent version of each peer
+        #[arg(short, long)]
+        agent: bool,
+    },
+    /// Connects to peers by their multi-addresses, or by their peer IDs, whose
+    /// addresses are then looked up in the DHT
     Connect {
-        /// Multi-address (with `/p2p/` protocol)
-        address: String,
+        /// Multi-addresses (with `/p2p/` protocol) or peer IDs
+        #[arg(required = true)]
+        addresses: Vec<String>,
     },
-    /// Disconnects from a peer by it's peer ID
+    /// Disconnects from peers by their peer IDs
     Disconnect {
-        /// Peer ID to disconnect from
+        /// Peer IDs to disconnect from
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Prints the reachability of the node from the public internet
+    Reachability,
+    /// Prints the gossipsub scores of the connected peers
+    Scores,
+    /// Looks up the addresses of a peer, in the DHT if it's not connected
+    FindPeer {
+        /// Peer ID
         id: String,
     },
+    /// Prints the details of a connected peer
+    PeerInfo {
+        /// Peer ID
+        id: String,
+    },
+    /// Bans a peer, disconnecting from it
+    Ban {
+        /// Peer ID to ban
+        id: String,
+        /// Duration of the ban, e.g. `1h`. The ban lasts until lifted or until
+        /// the node restarts if omitted.
+        #[arg(long)]
+        duration: Option<humantime::Duration>,
+    },
+    /// Lifts the ban of a peer
+    Unban {
+        /// Peer ID to unban
+        id: String,
+    },
+    /// Protects peers from being banned, lists the protected peers if none is
+    /// given
+    Protect {
+        /// Peer IDs to protect
+        ids: Vec<String>,
+    },
+    /// Removes the protection of peers
+    Unprotect {
+        /// Peer IDs to unprotect
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
 }
 
 impl NetCommands {
@@ -41,11 +90,9 @@ impl NetCommands {
                     .iter()
                     .map(|addr| format!("{}/p2p/{}", addr, info.id))
                     .collect();
-                println!("{}", addresses.join("\n"));
-                Ok(())
+                output::print(&addresses, |addresses| println!("{}", addresses.join("\n")))
             }
-            Self::Info => {
-                let info = api.net_info().await?;
+            Self::Info => output::print(&api.net_info().await?, |info| {
                 println!("forest libp2p swarm info:");
                 println!("num peers: {}", info.num_peers);
                 println!("num connections: {}", info.num_connections);
@@ -53,69 +100,210 @@ impl NetCommands {
                 println!("num pending incoming: {}", info.num_pending_incoming);
                 println!("num pending outgoing: {}", info.num_pending_outgoing);
                 println!("num established: {}", info.num_established);
-                Ok(())
-            }
-            Self::Peers => {
-                let addrs = api.net_peers().await?;
-                let output: Vec<String> = addrs
-                    .into_iter()
-                    .filter_map(|info| {
-                        let addresses: Vec<String> = info
-                            .addrs
-                            .into_iter()
-                            .filter(|addr| match addr.iter().next().unwrap() {
-                                Protocol::Ip4(ip_addr) => !ip_addr.is_loopback(),
-                                Protocol::Ip6(ip_addr) => !ip_addr.is_loopback(),
-                                _ => true,
-                            })
-                            .map(|addr| addr.to_string())
-                            .unique()
-                            .collect();
-                        if addresses.is_empty() {
-                            return None;
+            }),
+            Self::Peers { agent } => {
+                let mut peers = vec![];
+                for info in api.net_peers().await? {
+                    let addresses: Vec<String> = info
+                        .addrs
+                        .into_iter()
+                        .filter(|addr| match addr.iter().next() {
+                            Some(Protocol::Ip4(ip_addr)) => !ip_addr.is_loopback(),
+                            Some(Protocol::Ip6(ip_addr)) => !ip_addr.is_loopback(),
+                            _ => true,
+                        })
+                        .map(|addr| addr.to_string())
+                        .unique()
+                        .collect();
+                    if addresses.is_empty() {
+                        continue;
+                    }
+                    let agent = if agent {
+                        Some(
+                            api.net_agent_version(info.id.clone())
+                                .await
+                                .unwrap_or_else(|_| "<unknown>".into()),
+                        )
+                    } else {
+                        None
+                    };
+                    peers.push(Peer {
+                        id: info.id,
+                        addresses,
+                        agent,
+                    });
+                }
+                output::print(&peers, |peers| {
+                    for peer in peers {
+                        match &peer.agent {
+                            Some(agent) => {
+                                println!("{}, [{}], {agent}", peer.id, peer.addresses.join(", "))
+                            }
+                            None => println!("{}, [{}]", peer.id, peer.addresses.join(", ")),
                         }
-                        Some(format!("{}, [{}]", info.id, addresses.join(", ")))
-                    })
-                    .collect();
-                println!("{}", output.join("\n"));
-                Ok(())
+                    }
+                })
             }
-            Self::Connect { address } => {
-                let addr: Multiaddr = address
-                    .parse()
-                    .map_err(|e| {
-                        cli_error_and_die(format!("Error parsing multiaddr. Error was: {e}"), 1);
-                    })
-                    .expect("Parse provided multiaddr from string");
-
-                let mut id = "".to_owned();
-
-                for protocol in addr.iter() {
-                    if let Protocol::P2p(p2p) = protocol {
-                        id = multibase::encode(multibase::Base::Base58Btc, p2p.to_bytes());
-                        id = id.split_off(1);
+            Self::Connect { addresses } => {
+                let mut peers: Vec<AddrInfo> = vec![];
+                for address in addresses {
+                    let (id, addr) = parse_peer_address(&address)?;
+                    match addr {
+                        Some(addr) => match peers.iter_mut().find(|peer| peer.id == id) {
+                            Some(peer) => {
+                                peer.addrs.insert(addr);
+                            }
+                            None => peers.push(AddrInfo {
+                                id,
+                                addrs: HashSet::from_iter([addr]),
+                            }),
+                        },
+                        None => peers.push(
+                            api.net_find_peer(id.clone())
+                                .await
+                                .with_context(|| format!("failed to find the addresses of {id}"))?,
+                        ),
                     }
                 }
-
-                if id.is_empty() {
-                    cli_error_and_die("Needs a /p2p/ protocol present in multiaddr", 1)
+                let mut outcomes = vec![];
+                for addr_info in peers {
+                    let id = addr_info.id.clone();
+                    let result = api.net_connect(addr_info).await.map_err(|e| e.message);
+                    outcomes.push(Outcome::new(id, result));
                 }
-
-                let addrs = HashSet::from_iter([addr]);
-                let addr_info = AddrInfo {
-                    id: id.clone(),
-                    addrs,
+                output::print_outcomes("connect", &outcomes)
+            }
+            Self::Disconnect { ids } => {
+                let mut outcomes = vec![];
+                for id in ids {
+                    let result = api.net_disconnect(id.clone()).await.map_err(|e| e.message);
+                    outcomes.push(Outcome::new(id, result));
+                }
+                output::print_outcomes("disconnect", &outcomes)
+            }
+            Self::Reachability => output::print(&api.net_reachability().await?, |result| {
+                println!("AutoNAT status: {:?}", result.reachability);
+                for addr in result.public_addrs.iter() {
+                    println!("Public address: {addr}");
+                }
+            }),
+            Self::Scores => output::print(&api.net_pubsub_scores().await?, |scores| {
+                for score in scores {
+                    println!("{}, {}", score.id, score.score.score);
+                }
+            }),
+            Self::FindPeer { id } => {
+                let info = api.net_find_peer(id).await?;
+                let addresses: Vec<String> = info.addrs.iter().map(|a| a.to_string()).collect();
+                output::print(&addresses, |addresses| {
+                    for addr in addresses {
+                        println!("{addr}");
+                    }
+                })
+            }
+            Self::PeerInfo { id } => output::print(&api.net_peer_info(id).await?, |info| {
+                println!("Peer ID: {}", info.id);
+                println!("Agent: {}", info.agent);
+                println!("Direction: {:?}", info.direction);
+                if let Some(latency) = info.latency {
+                    println!(
+                        "Latency: {}",
+                        humantime::format_duration(Duration::from_nanos(latency))
+                    );
+                }
+                println!("Protected: {}", info.protected);
+                println!("Addresses:");
+                for addr in info.addrs.iter() {
+                    println!("  {addr}");
+                }
+                println!("Protocols:");
+                for protocol in info.protocols.iter() {
+                    println!("  {protocol}");
+                }
+            }),
+            Self::Ban { id, duration } => {
+                api.net_ban_peer(id.clone(), duration.map(|d| d.as_secs()))
+                    .await?;
+                output::print_outcomes("ban", &[Outcome::new(id, Ok::<_, String>(()))])
+            }
+            Self::Unban { id } => {
+                let result = match api.net_unban_peer(id.clone()).await? {
+                    true => Ok(()),
+                    false => Err("not banned"),
                 };
-
-                api.net_connect(addr_info).await?;
-                println!("connect {id}: success");
-                Ok(())
+                output::print_outcomes("unban", &[Outcome::new(id, result)])
+            }
+            Self::Protect { ids } => {
+                if ids.is_empty() {
+                    return output::print(&api.net_protect_list().await?, |ids| {
+                        for id in ids {
+                            println!("{id}");
+                        }
+                    });
+                }
+                api.net_protect_add(ids.clone()).await?;
+                let outcomes: Vec<_> = ids
+                    .into_iter()
+                    .map(|id| Outcome::new(id, Ok::<_, String>(())))
+                    .collect();
+                output::print_outcomes("protect", &outcomes)
             }
-            Self::Disconnect { id } => {
-                api.net_disconnect(id.to_owned()).await?;
-                println!("disconnect {id}: success");
-                Ok(())
+            Self::Unprotect { ids } => {
+                api.net_protect_remove(ids.clone()).await?;
+                let outcomes: Vec<_> = ids
+                    .into_iter()
+                    .map(|id| Outcome::new(id, Ok::<_, String>(())))
+                    .collect();
+                output::print_outcomes("unprotect", &outcomes)
             }
         }
     }
 }
+
+/// A connected peer, printed by `net peers`.
+#[derive(Debug, Serialize)]
+struct Peer {
+    id: String,
+    addresses: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    agent: Option<String>,
+}
+
+/// Parses a multi-address with a `/p2p/` protocol, or a bare peer ID, into the
+/// peer ID and the address, if any.
+fn parse_peer_address(address: &str) -> anyhow::Result<(String, Option<Multiaddr>)> {
+    if let Ok(id) = address.parse::<PeerId>() {
+        return Ok((id.to_string(), None));
+    }
+    let addr: Multiaddr = address
+        .parse()
+        .with_context(|| format!("{address} is neither a multi-address nor a peer ID"))?;
+    let id = addr
+        .iter()
+        .find_map(|protocol| match protocol {
+            Protocol::P2p(id) => Some(id.to_string()),
+            _ => None,
+        })
+        .with_context(|| format!("{address} has no /p2p/ protocol"))?;
+    Ok((id, Some(addr)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peer_addresses() {
+        let id = PeerId::random().to_string();
+        assert_eq!(parse_peer_address(&id).unwrap(), (id.clone(), None));
+
+        let address = format!("/ip4/1.2.3.4/tcp/1234/p2p/{id}");
+        assert_eq!(
+            parse_peer_address(&address).unwrap(),
+            (id, Some(address.parse().unwrap()))
+        );
+
+        assert!(parse_peer_address("/ip4/1.2.3.4/tcp/1234").is_err());
+        assert!(parse_peer_address("not an address").is_err());
+    }
+}
@@ -3,15 +3,23 @@
 
 use std::str::FromStr as _;
 
+use crate::blocks::TipsetKeys;
+use crate::cli::humantoken::TokenAmountPretty as _;
+use crate::cli_shared::output;
+use crate::message::SignedMessage;
 use crate::rpc_client::ApiInfo;
 use crate::shim::address::{Address, StrictAddress};
 use crate::shim::econ::TokenAmount;
-use crate::shim::message::{Message, METHOD_SEND};
+use crate::shim::message::{Message, MethodNum, METHOD_SEND};
 use anyhow::Context as _;
+use base64::{prelude::BASE64_STANDARD, Engine as _};
+use fvm_ipld_encoding::RawBytes;
 use num::Zero as _;
 
 use crate::cli::humantoken;
 
+use super::maybe_confirm;
+
 #[derive(Debug, clap::Args)]
 pub struct SendCommand {
     /// optionally specify the account to send funds from (otherwise the default
@@ -21,13 +29,29 @@ pub struct SendCommand {
     target_address: String,
     #[arg(value_parser = humantoken::parse)]
     amount: TokenAmount,
+    /// Estimated if zero
     #[arg(long, value_parser = humantoken::parse, default_value_t = TokenAmount::zero())]
     gas_feecap: TokenAmount,
-    /// In milliGas
+    /// In milliGas. Estimated if zero
     #[arg(long, default_value_t = 0)]
     gas_limit: i64,
+    /// Estimated if zero
     #[arg(long, value_parser = humantoken::parse, default_value_t = TokenAmount::zero())]
     gas_premium: TokenAmount,
+    /// Nonce of the message (otherwise the next nonce of the sender will be
+    /// used)
+    #[arg(long)]
+    nonce: Option<u64>,
+    /// Number of the actor method to invoke
+    #[arg(long, default_value_t = METHOD_SEND)]
+    method: MethodNum,
+    /// Parameters of the method as JSON, encoded to DAG-CBOR as is: the
+    /// parameters of most methods are tuples, written as JSON arrays
+    #[arg(long)]
+    params_json: Option<String>,
+    /// Send the message without asking for confirmation
+    #[arg(short, long)]
+    force: bool,
 }
 
 impl SendCommand {
@@ -40,23 +64,78 @@ impl SendCommand {
                     "No default wallet address selected. Please set a default address.",
                 )?)?
             };
+        let params = match &self.params_json {
+            Some(json) => params_from_json(json)?,
+            None => RawBytes::default(),
+        };
 
         let message = Message {
             from,
             to: StrictAddress::from_str(&self.target_address)?.into(),
             value: self.amount.clone(),
-            method_num: METHOD_SEND,
+            method_num: self.method,
+            params,
             gas_limit: self.gas_limit as u64,
             gas_fee_cap: self.gas_feecap.clone(),
             gas_premium: self.gas_premium.clone(),
             // JANK(aatifsyed): Why are we using a testing build of fvm_shared?
             ..Default::default()
         };
+        let mut message = api
+            .gas_estimate_message_gas(message, None, TipsetKeys::default())
+            .await?;
+
+        print_preview(&message);
+        maybe_confirm(self.force, "Send the message?")?;
+
+        let cid = match self.nonce {
+            Some(nonce) => {
+                message.sequence = nonce;
+                let data = BASE64_STANDARD.encode(message.cid()?.to_bytes());
+                let signature = api.wallet_sign(message.from, data.into_bytes()).await?;
+                api.mpool_push(SignedMessage::new_unchecked(message, signature))
+                    .await?
+            }
+            None => api.mpool_push_message(message, None).await?.cid()?,
+        };
 
-        let signed_msg = api.mpool_push_message(message, None).await?;
+        let sent = serde_json::json!({ "cid": cid.to_string() });
+        output::print(&sent, |_| println!("{cid}"))
+    }
+}
+
+/// Encodes method parameters given as JSON to DAG-CBOR.
+fn params_from_json(json: &str) -> anyhow::Result<RawBytes> {
+    let value: serde_json::Value = serde_json::from_str(json).context("Invalid JSON parameters")?;
+    Ok(RawBytes::new(serde_ipld_dagcbor::to_vec(&value)?))
+}
+
+/// Prints the message before it is sent, on `stderr` with `--output json`.
+fn print_preview(message: &Message) {
+    output::status(format!("From:        {}", message.from));
+    output::status(format!("To:          {}", message.to));
+    output::status(format!("Value:       {}", message.value.pretty()));
+    output::status(format!("Method:      {}", message.method_num));
+    output::status(format!("Gas limit:   {}", message.gas_limit));
+    output::status(format!("Gas fee cap: {}", message.gas_fee_cap.pretty()));
+    output::status(format!("Gas premium: {}", message.gas_premium.pretty()));
+    output::status(format!(
+        "Maximum fee: {}",
+        (&message.gas_fee_cap * message.gas_limit).pretty()
+    ));
+}
 
-        println!("{}", signed_msg.cid().unwrap());
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(())
+    #[test]
+    fn json_params() {
+        // A tuple of an integer and a string
+        assert_eq!(
+            params_from_json(r#"[1, "a"]"#).unwrap(),
+            RawBytes::new(vec![0x82, 0x01, 0x61, b'a'])
+        );
+        assert!(params_from_json("[1,").is_err());
     }
 }
@@ -1,6 +1,7 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use crate::cli_shared::output;
 use crate::rpc_client::ApiInfo;
 
 use crate::cli::subcommands::prompt_confirm;
@@ -14,9 +15,9 @@ pub struct ShutdownCommand {
 
 impl ShutdownCommand {
     pub async fn run(self, api: ApiInfo) -> anyhow::Result<()> {
-        println!("Shutting down Forest node");
+        output::status("Shutting down Forest node");
         if !self.force && !prompt_confirm() {
-            println!("Aborted.");
+            output::status("Aborted.");
             return Ok(());
         }
         api.shutdown().await?;
@@ -4,16 +4,16 @@
 use super::*;
 use crate::blocks::TipsetKeys;
 use crate::chain_sync::SyncConfig;
-use crate::cli_shared::snapshot::{self, TrustedVendor};
+use crate::cli_shared::output::{self, OutputFormat};
+use crate::cli_shared::snapshot::{self, save_checksum, TrustedVendor};
 use crate::rpc_api::chain_api::ChainExportParams;
 use crate::rpc_client::ApiInfo;
 use anyhow::Context as _;
 use chrono::NaiveDateTime;
 use clap::Subcommand;
 use human_repr::HumanCount;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use tempfile::NamedTempFile;
-use tokio::io::AsyncWriteExt;
 
 #[derive(Debug, Subcommand)]
 pub enum SnapshotCommands {
@@ -90,6 +90,10 @@ impl SnapshotCommands {
                     let tmp_file = temp_path.to_owned();
                     let output_path = output_path.clone();
                     async move {
+                        // The progress is only displayed to humans
+                        if OutputFormat::is_json() {
+                            return;
+                        }
                         let mut interval =
                             tokio::time::interval(tokio::time::Duration::from_secs_f32(0.25));
                         println!("Getting ready to export...");
@@ -121,32 +125,11 @@ impl SnapshotCommands {
                 if let Some(hash) = hash_result {
                     save_checksum(&output_path, hash).await?;
                 }
-                temp_path.persist(output_path)?;
+                temp_path.persist(&output_path)?;
 
-                println!("Export completed.");
-                Ok(())
+                let exported = serde_json::json!({ "path": output_path, "epoch": epoch });
+                output::print(&exported, |_| println!("Export completed."))
             }
         }
     }
 }
-
-/// Prints hex-encoded representation of SHA-256 checksum and saves it to a file
-/// with the same name but with a `.sha256sum` extension.
-async fn save_checksum(source: &Path, encoded_hash: String) -> anyhow::Result<()> {
-    let checksum_file_content = format!(
-        "{encoded_hash} {}\n",
-        source
-            .file_name()
-            .and_then(std::ffi::OsStr::to_str)
-            .context("Failed to retrieve file name while saving checksum")?
-    );
-
-    let checksum_path = PathBuf::from(source).with_extension("sha256sum");
-
-    let mut checksum_file = tokio::fs::File::create(&checksum_path).await?;
-    checksum_file
-        .write_all(checksum_file_content.as_bytes())
-        .await?;
-    checksum_file.flush().await?;
-    Ok(())
-}
@@ -2,12 +2,23 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::path::PathBuf;
+use std::str::FromStr as _;
 
+use crate::blocks::{Tipset, TipsetKeys};
+use crate::cli::humantoken::TokenAmountPretty as _;
+use crate::cli_shared::output::{self, OutputFormat};
 use crate::rpc_client::ApiInfo;
+use crate::shim::address::{Address, StrictAddress};
 use crate::shim::clock::ChainEpoch;
 use crate::shim::econ::TokenAmount;
+use crate::shim::state_tree::ActorState;
+use crate::state_manager::{BlockRewards, ComputeStateOutput, InvocResult};
+use ahash::HashMap;
+use anyhow::Context as _;
 use cid::Cid;
 use clap::Subcommand;
+use serde::Serialize;
+use serde_json::Value;
 use serde_tuple::{self, Deserialize_tuple, Serialize_tuple};
 
 #[derive(Serialize_tuple, Deserialize_tuple, Clone, Debug)]
@@ -29,15 +40,469 @@ pub enum StateCommands {
         #[arg(short, long)]
         save_to_file: Option<PathBuf>,
     },
+    /// Break down the rewards, gas burns and penalties of a tipset by block
+    Rewards {
+        /// Epoch of the tipset. Defaults to the parent of the current head, as
+        /// the messages of the head have not been executed yet.
+        #[arg(long)]
+        epoch: Option<ChainEpoch>,
+    },
+    /// Recompute the state of a tipset and print the results of its messages
+    ComputeState {
+        /// Epoch of the tipset whose messages are executed. Defaults to the
+        /// current head.
+        #[arg(long)]
+        epoch: Option<ChainEpoch>,
+        /// Epoch seen by the virtual machine, after running the migrations
+        /// from the tipset epoch. Defaults to the tipset epoch.
+        #[arg(long)]
+        vm_height: Option<ChainEpoch>,
+        /// Print the execution trace of each message as a call tree
+        #[arg(long)]
+        show_trace: bool,
+        /// Print the output, with the traces, as an HTML page
+        #[arg(long)]
+        html: bool,
+    },
+    /// List the actors of the state of a tipset, a page at a time
+    ListActors {
+        /// Epoch of the tipset. Defaults to the current head.
+        #[arg(long)]
+        epoch: Option<ChainEpoch>,
+        /// Only list the actors with this code CID
+        #[arg(long)]
+        code: Option<Cid>,
+        /// Address of the first actor of the page, as printed at the end of
+        /// the previous page. Defaults to the first actor of the state.
+        #[arg(long)]
+        from: Option<String>,
+        /// Maximum number of actors in the page
+        #[arg(long, default_value_t = 100)]
+        limit: u64,
+    },
+    /// Print the balance, nonce, code CID and type of an actor
+    GetActor {
+        address: String,
+        /// Epoch of the tipset. Defaults to the current head.
+        #[arg(long)]
+        epoch: Option<ChainEpoch>,
+    },
 }
 
 impl StateCommands {
     pub async fn run(self, api: ApiInfo) -> anyhow::Result<()> {
         match self {
             Self::Fetch { root, save_to_file } => {
-                println!("{}", api.state_fetch_root(root, save_to_file).await?);
+                output::print(&api.state_fetch_root(root, save_to_file).await?, |s| {
+                    println!("{s}")
+                })?;
+            }
+            Self::Rewards { epoch } => {
+                let head = api.chain_head().await?;
+                let epoch = epoch.unwrap_or(head.epoch() - 1);
+                let tipset = api
+                    .chain_get_tipset_by_height(epoch, head.key().clone())
+                    .await?;
+                let rewards = api.state_tipset_rewards(tipset.key().clone()).await?;
+                if OutputFormat::is_json() {
+                    super::print_pretty_json(rewards)?;
+                } else {
+                    print_rewards(tipset.epoch(), &rewards);
+                }
+            }
+            Self::ComputeState {
+                epoch,
+                vm_height,
+                show_trace,
+                html,
+            } => {
+                let tipset = tipset_at(&api, epoch).await?;
+                let vm_height = vm_height.unwrap_or(tipset.epoch());
+                // Tracing slows the computation down, only ask for the traces
+                // when they are printed
+                let output = if show_trace || html || OutputFormat::is_json() {
+                    api.state_compute_traced(vm_height, vec![], tipset.key().clone())
+                        .await?
+                } else {
+                    api.state_compute(vm_height, vec![], tipset.key().clone())
+                        .await?
+                };
+                if html {
+                    print!("{}", compute_state_html(&output));
+                } else if OutputFormat::is_json() {
+                    super::print_pretty_json(output)?;
+                } else {
+                    print!("{}", compute_state_text(&output, show_trace));
+                }
+            }
+            Self::ListActors {
+                epoch,
+                code,
+                from,
+                limit,
+            } => {
+                let from = from
+                    .map(|from| StrictAddress::from_str(&from).map(Address::from))
+                    .transpose()?;
+                let tipset = tipset_at(&api, epoch).await?;
+                let page = api
+                    .state_list_actors_page(tipset.key().clone(), from, limit, code)
+                    .await?;
+                let mut types = ActorTypes::new(tipset.key().clone());
+                let mut actors = Vec::with_capacity(page.actors.len());
+                for entry in page.actors {
+                    let r#type = types.get(&api, entry.actor.code).await;
+                    actors.push(ActorInfo::new(entry.address, &entry.actor, r#type));
+                }
+                let list = ActorList {
+                    actors,
+                    next: page.next,
+                };
+                output::print(&list, |list| {
+                    for actor in &list.actors {
+                        println!("{}", actor.summary());
+                    }
+                })?;
+                if let Some(next) = list.next {
+                    output::status(format!("More actors with --from {next}"));
+                }
+            }
+            Self::GetActor { address, epoch } => {
+                let address = StrictAddress::from_str(&address)?.into();
+                let tipset = tipset_at(&api, epoch).await?;
+                let actor = api
+                    .state_get_actor(address, tipset.key().clone())
+                    .await?
+                    .with_context(|| format!("actor {address} not found"))?;
+                let r#type = ActorTypes::new(tipset.key().clone())
+                    .get(&api, actor.code)
+                    .await;
+                let info = ActorInfo::new(address, &actor, r#type);
+                output::print(&info, |info| print!("{}", info.text()))?;
             }
         }
         Ok(())
     }
 }
+
+/// Loads the tipset at `epoch`, or the head if `epoch` is `None`.
+async fn tipset_at(api: &ApiInfo, epoch: Option<ChainEpoch>) -> anyhow::Result<Tipset> {
+    let head = api.chain_head().await?;
+    Ok(match epoch {
+        Some(epoch) => {
+            api.chain_get_tipset_by_height(epoch, head.key().clone())
+                .await?
+        }
+        None => head,
+    })
+}
+
+/// Names of the builtin actors of the code CIDs, in the manifest of the actors
+/// of a tipset, fetched once per code.
+struct ActorTypes {
+    tipset: TipsetKeys,
+    names: HashMap<Cid, Option<String>>,
+}
+
+impl ActorTypes {
+    fn new(tipset: TipsetKeys) -> Self {
+        Self {
+            tipset,
+            names: HashMap::default(),
+        }
+    }
+
+    async fn get(&mut self, api: &ApiInfo, code: Cid) -> Option<String> {
+        if let Some(name) = self.names.get(&code) {
+            return name.clone();
+        }
+        let name = api
+            .state_builtin_actor(code, self.tipset.clone())
+            .await
+            .ok();
+        self.names.insert(code, name.clone());
+        name
+    }
+}
+
+/// An actor, printed by `state get-actor` and `state list-actors`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ActorInfo {
+    #[serde(with = "crate::lotus_json")]
+    address: Address,
+    /// Name of the builtin actor of the code, if known
+    r#type: Option<String>,
+    #[serde(with = "crate::lotus_json")]
+    code: Cid,
+    #[serde(with = "crate::lotus_json")]
+    balance: TokenAmount,
+    nonce: u64,
+}
+
+impl ActorInfo {
+    fn new(address: Address, actor: &ActorState, r#type: Option<String>) -> Self {
+        Self {
+            address,
+            r#type,
+            code: actor.code,
+            balance: TokenAmount::from(&actor.balance),
+            nonce: actor.sequence,
+        }
+    }
+
+    fn type_name(&self) -> &str {
+        self.r#type.as_deref().unwrap_or("unknown")
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "Address: {}\nType:    {}\nCode:    {}\nBalance: {}\nNonce:   {}\n",
+            self.address,
+            self.type_name(),
+            self.code,
+            self.balance.pretty(),
+            self.nonce
+        )
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{}\t{}\t{}\tnonce {}",
+            self.address,
+            self.type_name(),
+            self.balance.pretty(),
+            self.nonce
+        )
+    }
+}
+
+/// A page of actors, printed by `state list-actors`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ActorList {
+    actors: Vec<ActorInfo>,
+    /// Address of the first actor of the next page, if there are more actors
+    #[serde(with = "crate::lotus_json")]
+    next: Option<Address>,
+}
+
+fn print_rewards(epoch: ChainEpoch, rewards: &[BlockRewards]) {
+    println!("Epoch: {epoch}");
+    let mut total = BlockRewards::default();
+    for block in rewards {
+        println!(
+            "Block: {} (miner {}, wins {})",
+            block.block, block.miner, block.win_count
+        );
+        print_amounts(block);
+        total.block_reward += block.block_reward.clone();
+        total.messages += block.messages;
+        total.miner_tip += block.miner_tip.clone();
+        total.base_fee_burn += block.base_fee_burn.clone();
+        total.over_estimation_burn += block.over_estimation_burn.clone();
+        total.penalty += block.penalty.clone();
+    }
+    println!("Total:");
+    print_amounts(&total);
+}
+
+fn print_amounts(rewards: &BlockRewards) {
+    println!("  Messages:             {}", rewards.messages);
+    println!("  Block reward:         {}", rewards.block_reward.pretty());
+    println!("  Miner tip:            {}", rewards.miner_tip.pretty());
+    println!(
+        "  Total reward:         {}",
+        rewards.total_reward().pretty()
+    );
+    println!("  Base fee burn:        {}", rewards.base_fee_burn.pretty());
+    println!(
+        "  Over-estimation burn: {}",
+        rewards.over_estimation_burn.pretty()
+    );
+    println!("  Penalty:              {}", rewards.penalty.pretty());
+    println!("  Total burn:           {}", rewards.total_burn().pretty());
+}
+
+fn compute_state_text(output: &ComputeStateOutput, show_trace: bool) -> String {
+    let mut text = format!("Computed state root: {}\n", output.root);
+    for result in &output.trace {
+        text.push_str(&format!(
+            "{}: {}\n",
+            result.msg_cid,
+            message_summary(result)
+        ));
+        if show_trace {
+            if let Some(trace) = &result.execution_trace {
+                push_call_tree(&mut text, trace, 1);
+            }
+        }
+    }
+    text
+}
+
+fn message_summary(result: &InvocResult) -> String {
+    let msg = &result.msg;
+    let mut summary = format!(
+        "{} -> {} (method {}, value {})",
+        msg.from,
+        msg.to,
+        msg.method_num,
+        msg.value.pretty()
+    );
+    if let Some(receipt) = &result.msg_rct {
+        summary.push_str(&format!(
+            ", exit code {}, gas used {}",
+            receipt.exit_code().value(),
+            receipt.gas_used()
+        ));
+    }
+    if let Some(error) = &result.error {
+        summary.push_str(&format!(", error: {error}"));
+    }
+    summary
+}
+
+/// Summarizes a call of a Lotus-style `ExecutionTrace`.
+fn call_summary(trace: &Value) -> String {
+    let field = |path: &[&str]| {
+        let value = path.iter().fold(trace, |value, key| &value[key]);
+        match value {
+            Value::String(s) => s.clone(),
+            Value::Null => "?".into(),
+            other => other.to_string(),
+        }
+    };
+    format!(
+        "{} -> {} (method {}, value {} attoFIL), exit code {}",
+        field(&["Msg", "From"]),
+        field(&["Msg", "To"]),
+        field(&["Msg", "Method"]),
+        field(&["Msg", "Value"]),
+        field(&["MsgRct", "ExitCode"]),
+    )
+}
+
+fn subcalls(trace: &Value) -> &[Value] {
+    trace["Subcalls"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn push_call_tree(text: &mut String, trace: &Value, depth: usize) {
+    text.push_str(&format!("{}{}\n", "  ".repeat(depth), call_summary(trace)));
+    for subcall in subcalls(trace) {
+        push_call_tree(text, subcall, depth + 1);
+    }
+}
+
+fn compute_state_html(output: &ComputeStateOutput) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>State computation</title></head>\n<body>\n",
+    );
+    html.push_str(&format!("<h1>Computed state root: {}</h1>\n", output.root));
+    for result in &output.trace {
+        html.push_str(&format!(
+            "<details open>\n<summary><code>{}</code>: {}</summary>\n",
+            result.msg_cid,
+            html_escape(&message_summary(result))
+        ));
+        if let Some(trace) = &result.execution_trace {
+            html.push_str("<ul>\n");
+            push_call_tree_html(&mut html, trace);
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</details>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn push_call_tree_html(html: &mut String, trace: &Value) {
+    html.push_str(&format!("<li>{}", html_escape(&call_summary(trace))));
+    let subcalls = subcalls(trace);
+    if !subcalls.is_empty() {
+        html.push_str("\n<ul>\n");
+        for subcall in subcalls {
+            push_call_tree_html(html, subcall);
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</li>\n");
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(from: &str, to: &str, exit_code: u32, subcalls: Vec<Value>) -> Value {
+        json!({
+            "Msg": { "From": from, "To": to, "Value": "0", "Method": 2 },
+            "MsgRct": { "ExitCode": exit_code },
+            "Subcalls": subcalls,
+        })
+    }
+
+    #[test]
+    fn call_tree() {
+        let trace = call(
+            "f0100",
+            "f01000",
+            0,
+            vec![
+                call("f01000", "f05", 16, vec![]),
+                call("f01000", "f099", 0, vec![]),
+            ],
+        );
+        let mut text = String::new();
+        push_call_tree(&mut text, &trace, 1);
+        assert_eq!(
+            text,
+            "  f0100 -> f01000 (method 2, value 0 attoFIL), exit code 0\n\
+             \x20   f01000 -> f05 (method 2, value 0 attoFIL), exit code 16\n\
+             \x20   f01000 -> f099 (method 2, value 0 attoFIL), exit code 0\n"
+        );
+
+        let mut html = String::new();
+        push_call_tree_html(&mut html, &trace);
+        assert_eq!(html.matches("<li>").count(), 3);
+        assert_eq!(html.matches("<ul>").count(), 1);
+        assert!(html.contains("f0100 -&gt; f01000"));
+    }
+
+    #[test]
+    fn actor_info_text() {
+        let actor = ActorState::new_empty(Cid::default(), None);
+        let info = ActorInfo::new(Address::new_id(1000), &actor, Some("multisig".into()));
+        assert_eq!(
+            info.text(),
+            format!(
+                "Address: {}\nType:    multisig\nCode:    {}\nBalance: 0 FIL\nNonce:   0\n",
+                Address::new_id(1000),
+                Cid::default()
+            )
+        );
+
+        let info = ActorInfo::new(Address::new_id(1000), &actor, None);
+        assert_eq!(
+            info.summary(),
+            format!("{}\tunknown\t0 FIL\tnonce 0", Address::new_id(1000))
+        );
+    }
+
+    #[test]
+    fn call_tree_without_subcalls() {
+        let mut trace = call("f0100", "f01000", 0, vec![]);
+        trace["Subcalls"] = Value::Null;
+        assert!(subcalls(&trace).is_empty());
+    }
+}
@@ -7,9 +7,12 @@ use std::{
 };
 
 use crate::chain_sync::SyncStage;
+use crate::cli_shared::output::{self, OutputFormat};
 use crate::rpc_client::*;
+use crate::shim::clock::ChainEpoch;
 use cid::Cid;
 use clap::Subcommand;
+use serde::Serialize;
 use ticker::Ticker;
 
 use crate::cli::subcommands::format_vec_pretty;
@@ -36,6 +39,15 @@ pub enum SyncCommands {
         #[arg(short)]
         cid: String,
     },
+    /// Unmark a bad block, so that the node syncs it again
+    UnmarkBad {
+        /// The block CID to unmark
+        #[arg(short, required_unless_present = "all")]
+        cid: Option<String>,
+        /// Unmark all the bad blocks
+        #[arg(long, conflicts_with = "cid")]
+        all: bool,
+    },
 }
 
 impl SyncCommands {
@@ -61,6 +73,21 @@ impl SyncCommands {
                         0
                     };
 
+                    if OutputFormat::is_json() {
+                        // One object per line, without the cursor movements
+                        let progress = serde_json::json!({
+                            "base": base_height,
+                            "target": target_height,
+                            "stage": state.stage().to_string(),
+                            "epoch": state.epoch(),
+                        });
+                        println!("{progress}");
+                        if state.stage() == SyncStage::Complete && !watch {
+                            break;
+                        }
+                        continue;
+                    }
+
                     println!(
                         "Worker: 0; Base: {}; Target: {}; (diff: {})",
                         base_height,
@@ -100,49 +127,85 @@ impl SyncCommands {
 
                 let (target_cids, target_height) = if let Some(tipset) = target {
                     let cid_vec = tipset.cids().iter().map(|cid| cid.to_string()).collect();
-                    (format_vec_pretty(cid_vec), tipset.epoch())
+                    (cid_vec, tipset.epoch())
                 } else {
-                    ("[]".to_string(), 0)
+                    (vec![], 0)
                 };
 
                 let (base_cids, base_height) = if let Some(tipset) = base {
                     let cid_vec = tipset.cids().iter().map(|cid| cid.to_string()).collect();
-                    (format_vec_pretty(cid_vec), tipset.epoch())
+                    (cid_vec, tipset.epoch())
                 } else {
-                    ("[]".to_string(), 0)
+                    (vec![], 0)
                 };
 
-                let height_diff = base_height - target_height;
+                let status = SyncStatus {
+                    base: base_cids,
+                    target: target_cids,
+                    target_height,
+                    height_diff: (base_height - target_height).abs(),
+                    stage: state.stage().to_string(),
+                    height: state.epoch(),
+                    elapsed_secs: elapsed_time.map(|duration| duration.num_seconds()),
+                };
 
-                println!("sync status:");
-                println!("Base:\t{base_cids}");
-                println!("Target:\t{target_cids} ({target_height})");
-                println!("Height diff:\t{}", height_diff.abs());
-                println!("Stage:\t{}", state.stage());
-                println!("Height:\t{}", state.epoch());
+                output::print(&status, |status| {
+                    println!("sync status:");
+                    println!("Base:\t{}", format_vec_pretty(status.base.clone()));
+                    println!(
+                        "Target:\t{} ({})",
+                        format_vec_pretty(status.target.clone()),
+                        status.target_height
+                    );
+                    println!("Height diff:\t{}", status.height_diff);
+                    println!("Stage:\t{}", status.stage);
+                    println!("Height:\t{}", status.height);
 
-                if let Some(duration) = elapsed_time {
-                    println!("Elapsed time:\t{}s", duration.num_seconds());
-                }
-                Ok(())
+                    if let Some(secs) = status.elapsed_secs {
+                        println!("Elapsed time:\t{secs}s");
+                    }
+                })
             }
             Self::CheckBad { cid } => {
                 let cid: Cid = cid.parse()?;
                 let response = api.sync_check_bad(cid).await?;
+                let reason = (!response.is_empty()).then_some(response);
 
-                if response.is_empty() {
-                    println!("Block \"{cid}\" is not marked as a bad block");
-                } else {
-                    println!("response");
-                }
-                Ok(())
+                output::print(&reason, |reason| match reason {
+                    None => println!("Block \"{cid}\" is not marked as a bad block"),
+                    Some(reason) => println!("{reason}"),
+                })
             }
             Self::MarkBad { cid } => {
                 let cid: Cid = cid.parse()?;
                 api.sync_mark_bad(cid).await?;
-                println!("OK");
+                output::status("OK");
+                Ok(())
+            }
+            Self::UnmarkBad { cid: Some(cid), .. } => {
+                let cid: Cid = cid.parse()?;
+                api.sync_unmark_bad(cid).await?;
+                output::status("OK");
+                Ok(())
+            }
+            Self::UnmarkBad { cid: None, .. } => {
+                api.sync_unmark_all_bad().await?;
+                output::status("OK");
                 Ok(())
             }
         }
     }
 }
+
+/// State of the active sync, printed by `sync status`.
+#[derive(Debug, Serialize)]
+struct SyncStatus {
+    base: Vec<String>,
+    target: Vec<String>,
+    target_height: ChainEpoch,
+    height_diff: ChainEpoch,
+    stage: String,
+    height: ChainEpoch,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_secs: Option<i64>,
+}
@@ -0,0 +1,109 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::str::FromStr as _;
+use std::time::Duration;
+
+use crate::cli::subcommands::healthcheck_cmd::check_synced;
+use crate::cli_shared::output;
+use crate::rpc_client::ApiInfo;
+use crate::shim::clock::ChainEpoch;
+use anyhow::{bail, Context as _};
+use humantime::format_duration;
+use tokio::time::Instant;
+
+#[derive(Debug, clap::Args)]
+pub struct WaitApiCommand {
+    /// Maximum time to wait for the node to be ready
+    #[arg(long, default_value_t = humantime::Duration::from_str("1m").expect("infallible"))]
+    timeout: humantime::Duration,
+    /// Also wait until the head of the node is within `--max-epochs-behind`
+    /// epochs of the network
+    #[arg(long)]
+    synced: bool,
+    /// Maximum number of epochs the head can lag behind the network with
+    /// `--synced`
+    #[arg(long, default_value_t = 5, requires = "synced")]
+    max_epochs_behind: ChainEpoch,
+}
+
+impl WaitApiCommand {
+    pub async fn run(self, api: ApiInfo) -> anyhow::Result<()> {
+        let timeout: Duration = self.timeout.into();
+        let deadline = Instant::now() + timeout;
+        let mut backoff = Backoff::default();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let result = match tokio::time::timeout(remaining, self.check(&api)).await {
+                Ok(result) => result,
+                Err(_) => bail!("timed out after {}", format_duration(timeout)),
+            };
+            let error = match result {
+                Ok(details) => {
+                    output::status(format!("Ready: {details}"));
+                    return Ok(());
+                }
+                Err(e) => e,
+            };
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                bail!("timed out after {}: {error:#}", format_duration(timeout));
+            }
+            let delay = backoff.next().min(remaining);
+            output::status(format!(
+                "Not ready: {error:#}, retrying in {}",
+                format_duration(delay)
+            ));
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Checks once whether the node is ready.
+    async fn check(&self, api: &ApiInfo) -> anyhow::Result<String> {
+        let version = api.version().await.context("the API is not reachable")?;
+        if self.synced {
+            check_synced(api, self.max_epochs_behind).await
+        } else {
+            Ok(format!("{} is up", version.version))
+        }
+    }
+}
+
+/// Exponentially growing delays between the readiness checks.
+struct Backoff {
+    delay: Duration,
+}
+
+impl Backoff {
+    const INITIAL: Duration = Duration::from_millis(500);
+    const MAX: Duration = Duration::from_secs(10);
+
+    fn next(&mut self) -> Duration {
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(Self::MAX);
+        delay
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            delay: Self::INITIAL,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let mut backoff = Backoff::default();
+        let delays: Vec<_> = std::iter::from_fn(|| Some(backoff.next()))
+            .take(7)
+            .map(|d| d.as_millis())
+            .collect();
+        assert_eq!(delays, [500, 1000, 2000, 4000, 8000, 10000, 10000]);
+    }
+}
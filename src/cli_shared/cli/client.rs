@@ -34,6 +34,54 @@ impl Default for BufferSize {
     }
 }
 
+/// Builtin-actors bundle CAR file to use for a network version, instead of the
+/// bundle which is defined for it in Forest.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+pub struct ActorBundlePath {
+    pub network_version: u32,
+    pub path: PathBuf,
+}
+
+impl FromStr for ActorBundlePath {
+    type Err = anyhow::Error;
+
+    /// Parses `<network version>=<path>`, e.g., `22=./builtin-actors-devnet.car`.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (network_version, path) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("expected <network version>=<path>, got {s}"))?;
+        Ok(ActorBundlePath {
+            network_version: network_version.trim().parse()?,
+            path: path.trim().into(),
+        })
+    }
+}
+
+/// Cross-origin requests allowed by the RPC server, for browser-based clients.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+pub struct RpcCorsConfig {
+    /// Origins allowed, e.g. `https://app.example.com`, or `*` for any origin.
+    /// No CORS headers are sent if empty.
+    pub allowed_origins: Vec<String>,
+    /// Methods allowed in preflight responses
+    pub allowed_methods: Vec<String>,
+    /// Request headers allowed in preflight responses
+    pub allowed_headers: Vec<String>,
+}
+
+impl Default for RpcCorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec![],
+            allowed_methods: vec!["GET".into(), "POST".into(), "OPTIONS".into()],
+            allowed_headers: vec!["Authorization".into(), "Content-Type".into()],
+        }
+    }
+}
+
 #[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(default)]
@@ -66,6 +114,13 @@ pub struct Client {
     pub metrics_address: SocketAddr,
     /// RPC bind, e.g. 127.0.0.1:1234
     pub rpc_address: SocketAddr,
+    /// Maximum number of requests in a JSON-RPC batch
+    #[cfg_attr(test, arbitrary(gen(|g| u32::arbitrary(g) as _)))]
+    pub rpc_max_batch_size: usize,
+    pub rpc_cors: RpcCorsConfig,
+    pub enable_health_check: bool,
+    /// Health check bind, e.g. 127.0.0.1:2346
+    pub health_check_address: SocketAddr,
     // Period of validity for JWT in seconds. Defaults to 60 days.
     #[serde_as(as = "DurationSeconds<i64>")]
     #[cfg_attr(test, arbitrary(gen(
@@ -76,6 +131,15 @@ pub struct Client {
     pub show_progress_bars: ProgressBarVisibility,
     /// Load actors from the bundle file (possibly generating it if it doesn't exist)
     pub load_actors: bool,
+    /// Local actor bundles overriding the ones of their network versions.
+    pub actor_bundles: Vec<ActorBundlePath>,
+    /// Time given to the daemon to shut down gracefully, in seconds, after
+    /// which it exits anyway.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    #[cfg_attr(test, arbitrary(gen(
+        |g| std::time::Duration::from_secs(u32::arbitrary(g).into())
+    )))]
+    pub shutdown_timeout: std::time::Duration,
 }
 
 impl Default for Client {
@@ -98,9 +162,33 @@ impl Default for Client {
             encrypt_keystore: true,
             metrics_address: FromStr::from_str("0.0.0.0:6116").unwrap(),
             rpc_address: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), DEFAULT_PORT),
+            rpc_max_batch_size: 100,
+            rpc_cors: RpcCorsConfig::default(),
+            enable_health_check: true,
+            health_check_address: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 2346),
             token_exp: Duration::seconds(5184000), // 60 Days = 5184000 Seconds
             show_progress_bars: Default::default(),
             load_actors: true,
+            actor_bundles: vec![],
+            shutdown_timeout: std::time::Duration::from_secs(30),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_actor_bundle_path() {
+        assert_eq!(
+            ActorBundlePath::from_str("22=/tmp/bundle.car").unwrap(),
+            ActorBundlePath {
+                network_version: 22,
+                path: "/tmp/bundle.car".into(),
+            }
+        );
+        assert!(ActorBundlePath::from_str("/tmp/bundle.car").is_err());
+        assert!(ActorBundlePath::from_str("nv22=/tmp/bundle.car").is_err());
+    }
+}
@@ -4,8 +4,11 @@
 use crate::db::db_engine::DbConfig;
 use crate::libp2p::Libp2pConfig;
 use crate::{chain_sync::SyncConfig, networks::NetworkChain};
+use ahash::HashMap;
 use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use super::client::Client;
 
@@ -36,6 +39,174 @@ impl Default for DaemonConfig {
     }
 }
 
+/// Format of the log lines.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+/// Period after which a log file is rotated.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Minutely,
+    #[default]
+    Hourly,
+    Daily,
+    Never,
+}
+
+/// Logging configuration, see also the `RUST_LOG` environment variable.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Default)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+#[serde(default)]
+pub struct LogConfig {
+    /// Format of the logs, on the console and in the log files.
+    pub format: LogFormat,
+    /// Directory the log files are written to, no log files if unset.
+    pub dir: Option<PathBuf>,
+    /// Period after which a log file is rotated. Ignored if `max_file_size`
+    /// is set.
+    pub rotation: LogRotation,
+    /// Size in bytes after which a log file is rotated.
+    #[cfg_attr(test, arbitrary(gen(
+        |g| Option::<u32>::arbitrary(g).map(u64::from)
+    )))]
+    pub max_file_size: Option<u64>,
+    /// Number of rotated log files kept, all of them if unset.
+    #[cfg_attr(test, arbitrary(gen(
+        |g| Option::<u32>::arbitrary(g).map(|max_files| max_files as _)
+    )))]
+    pub max_files: Option<usize>,
+}
+
+/// Export of spans to an OpenTelemetry collector, e.g. Jaeger or Tempo.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+#[serde(default)]
+pub struct TracingConfig {
+    /// Whether spans are exported over OTLP.
+    pub otlp: bool,
+    /// gRPC endpoint of the OTLP collector.
+    pub otlp_endpoint: String,
+    /// Name of the service the spans are reported under.
+    pub service_name: String,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            otlp: false,
+            otlp_endpoint: "http://127.0.0.1:4317".into(),
+            service_name: "forest".into(),
+        }
+    }
+}
+
+/// Periodic snapshot exports.
+#[serde_as]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+#[serde(default)]
+pub struct SnapshotExportSchedule {
+    /// Whether snapshots are exported periodically.
+    pub enabled: bool,
+    /// Time between two exports, in seconds.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    #[cfg_attr(test, arbitrary(gen(
+        |g| Duration::from_secs(u32::arbitrary(g).into())
+    )))]
+    pub interval: Duration,
+    /// Directory the snapshots are written to, `snapshots` in the data
+    /// directory if unset.
+    pub dir: Option<PathBuf>,
+    /// Number of snapshots kept, the oldest ones are deleted. Zero keeps them
+    /// all.
+    #[cfg_attr(test, arbitrary(gen(|g| u32::arbitrary(g) as _)))]
+    pub keep: usize,
+    /// Executable run with the paths of each new snapshot and of its checksum
+    /// file, e.g. to upload them.
+    pub upload_hook: Option<PathBuf>,
+}
+
+impl Default for SnapshotExportSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: Duration::from_secs(24 * 60 * 60),
+            dir: None,
+            keep: 3,
+            upload_hook: None,
+        }
+    }
+}
+
+/// Periodic database garbage collection, during quiet hours.
+#[serde_as]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+#[serde(default)]
+pub struct GcSchedule {
+    /// Whether the garbage collection runs on schedule, instead of whenever
+    /// the database grows too big.
+    pub enabled: bool,
+    /// Minimum time between two collections, in seconds.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    #[cfg_attr(test, arbitrary(gen(
+        |g| Duration::from_secs(u32::arbitrary(g).into())
+    )))]
+    pub interval: Duration,
+    /// Hour (UTC) the quiet hours start at.
+    pub quiet_hours_start: u8,
+    /// Hour (UTC) the quiet hours end at, excluded. The quiet hours span
+    /// midnight if it's lower than the start hour.
+    pub quiet_hours_end: u8,
+}
+
+impl Default for GcSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: Duration::from_secs(24 * 60 * 60),
+            quiet_hours_start: 2,
+            quiet_hours_end: 6,
+        }
+    }
+}
+
+/// Maintenance jobs run periodically by the daemon.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Default, Debug, Clone)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+#[serde(default)]
+pub struct SchedulerConfig {
+    pub snapshot_export: SnapshotExportSchedule,
+    pub gc: GcSchedule,
+}
+
+/// Token-bucket rate limits of the RPC server, keyed by client IP. Requests
+/// over a limit are rejected with HTTP status 429.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Default, Debug, Clone)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+#[serde(default)]
+pub struct RpcRateLimitConfig {
+    /// Requests per second allowed for each client, all methods included. No
+    /// limit if unset.
+    pub per_ip: Option<u32>,
+    /// Requests per second allowed for each client, by method name. The
+    /// methods not listed are only subject to `per_ip`.
+    #[cfg_attr(test, arbitrary(gen(
+        |g| HashMap::from_iter(Vec::<(String, u32)>::arbitrary(g))
+    )))]
+    pub per_method: HashMap<String, u32>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Default, Debug, Clone)]
 #[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
 #[serde(default)]
@@ -46,6 +217,10 @@ pub struct Config {
     pub network: Libp2pConfig,
     pub sync: SyncConfig,
     pub daemon: DaemonConfig,
+    pub tracing: TracingConfig,
+    pub log: LogConfig,
+    pub scheduler: SchedulerConfig,
+    pub rpc_rate_limit: RpcRateLimitConfig,
 }
 
 impl Config {
@@ -9,7 +9,7 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::networks::NetworkChain;
+use crate::networks::custom::ChainArg;
 use crate::utils::{
     io::{read_file_to_string, read_toml, ProgressBarVisibility},
     misc::LoggingColor,
@@ -51,6 +51,13 @@ pub struct CliOpts {
     /// Disable Metrics endpoint
     #[arg(short, long)]
     pub no_metrics: bool,
+    /// Disable the health check endpoints
+    #[arg(long)]
+    pub no_health_check: bool,
+    /// Address used for the health check endpoints. By default binds on
+    /// localhost on port 2346.
+    #[arg(long)]
+    pub health_check_address: Option<SocketAddr>,
     /// Client JWT token to use for JSON-RPC authentication
     #[arg(short, long)]
     pub token: Option<String>,
@@ -105,9 +112,10 @@ pub struct CliOpts {
     /// Encrypt the key-store (default: true)
     #[arg(long)]
     pub encrypt_keystore: Option<bool>,
-    /// Choose network chain to sync to
+    /// Choose network chain to sync to: `mainnet`, `calibnet`, a devnet name,
+    /// or `custom:<path>` for a network defined in a TOML file
     #[arg(long)]
-    pub chain: Option<NetworkChain>,
+    pub chain: Option<ChainArg>,
     /// Daemonize Forest process
     #[arg(long)]
     pub detach: bool,
@@ -122,7 +130,8 @@ pub struct CliOpts {
     /// TTY.
     #[arg(long)]
     pub show_progress_bars: Option<ProgressBarVisibility>,
-    /// Turn on tokio-console support for debugging
+    /// Turn on tokio-console support for debugging, requires the
+    /// `tokio-console` feature
     #[arg(long)]
     pub tokio_console: bool,
     /// Send telemetry to `grafana loki`
@@ -131,7 +140,8 @@ pub struct CliOpts {
     /// Endpoint of `grafana loki`
     #[arg(long, default_value = "http://127.0.0.1:3100")]
     pub loki_endpoint: String,
-    /// Specify a directory into which rolling log files should be appended
+    /// Specify a directory into which rolling log files should be appended,
+    /// overrides `log.dir` of the configuration file
     #[arg(long)]
     pub log_dir: Option<PathBuf>,
     /// Exit after basic daemon initialization
@@ -152,6 +162,18 @@ pub struct CliOpts {
     /// Skip loading actors from the actors bundle.
     #[arg(long)]
     pub skip_load_actors: bool,
+    /// Produce blocks for the given miner actor. The miner's worker key must
+    /// be in the keystore. Only supported on devnets with mocked proofs.
+    #[arg(long, value_name = "MINER_ADDRESS")]
+    pub produce_blocks: Option<String>,
+    /// Remove the chain data of a test network that was reset, i.e. relaunched
+    /// from a new genesis, and resync.
+    #[arg(long)]
+    pub auto_reset_network: bool,
+    /// Start without the libp2p service nor any outbound network access, the
+    /// RPC API is served from the local database
+    #[arg(long, conflicts_with_all = ["auto_download_snapshot", "produce_blocks"])]
+    pub offline: bool,
 }
 
 impl CliOpts {
@@ -168,12 +190,16 @@ impl CliOpts {
         };
 
         if let Some(chain) = &self.chain {
-            cfg.chain = chain.clone();
+            cfg.chain = chain.resolve()?;
         }
 
         if let Some(genesis_file) = &self.genesis {
             cfg.client.genesis_file = Some(genesis_file.to_owned());
         }
+        if cfg.client.genesis_file.is_none() {
+            cfg.client.genesis_file = crate::networks::custom::genesis_file(&cfg.chain)
+                .map(|path| path.display().to_string());
+        }
         if self.rpc.unwrap_or(cfg.client.enable_rpc) {
             cfg.client.enable_rpc = true;
             if let Some(rpc_address) = self.rpc_address {
@@ -196,6 +222,12 @@ impl CliOpts {
             }
         }
 
+        if self.no_health_check {
+            cfg.client.enable_health_check = false;
+        } else if let Some(health_check_address) = self.health_check_address {
+            cfg.client.health_check_address = health_check_address;
+        }
+
         if self.import_snapshot.is_some() && self.import_chain.is_some() {
             anyhow::bail!("Can't set import_snapshot and import_chain at the same time!")
         } else if self.import_snapshot.is_some() && self.consume_snapshot.is_some() {
@@ -0,0 +1,165 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use tracing::{level_filters::LevelFilter, span, Metadata, Subscriber};
+use tracing_subscriber::{
+    filter::Directive,
+    layer::{Context, Filter},
+    registry::LookupSpan,
+    EnvFilter,
+};
+
+static LOG_FILTER: Lazy<LogFilter> = Lazy::new(|| LogFilter::new(super::env_directives()));
+
+/// The filter shared by the log outputs of the process, it can be changed at
+/// runtime, e.g. through the `Forest.LogSetLevel` RPC method.
+pub fn log_filter() -> &'static LogFilter {
+    &LOG_FILTER
+}
+
+/// An [`EnvFilter`] whose directives can be changed while it's in use.
+#[derive(Clone)]
+pub struct LogFilter {
+    inner: Arc<RwLock<Inner>>,
+}
+
+struct Inner {
+    initial: Vec<String>,
+    directives: Vec<String>,
+    filter: EnvFilter,
+}
+
+impl LogFilter {
+    fn new(directives: Vec<String>) -> Self {
+        let filter = build_filter(&directives).unwrap_or_default();
+        Self {
+            inner: Arc::new(RwLock::new(Inner {
+                initial: directives.clone(),
+                directives,
+                filter,
+            })),
+        }
+    }
+
+    /// Directives currently in effect, as in `RUST_LOG`.
+    pub fn directives(&self) -> Vec<String> {
+        self.inner.read().directives.clone()
+    }
+
+    /// Sets the maximum level logged for a target, e.g.
+    /// `forest_filecoin::chain_sync`, replacing any directive for that target.
+    pub fn set_level(&self, target: &str, level: &str) -> anyhow::Result<()> {
+        let directive = format!("{target}={level}");
+        directive
+            .parse::<Directive>()
+            .with_context(|| format!("invalid log directive {directive}"))?;
+        let mut directives = self.directives();
+        match directives
+            .iter_mut()
+            .find(|d| directive_target(d) == target)
+        {
+            Some(existing) => *existing = directive,
+            None => directives.push(directive),
+        }
+        self.update(directives)
+    }
+
+    /// Restores the directives the process was started with.
+    pub fn reset(&self) -> anyhow::Result<()> {
+        let initial = self.inner.read().initial.clone();
+        self.update(initial)
+    }
+
+    fn update(&self, directives: Vec<String>) -> anyhow::Result<()> {
+        let filter = build_filter(&directives)?;
+        {
+            let mut inner = self.inner.write();
+            inner.directives = directives;
+            inner.filter = filter;
+        }
+        // Callsites cache whether they are enabled, they have to be asked again.
+        tracing::callsite::rebuild_interest_cache();
+        Ok(())
+    }
+}
+
+fn build_filter(directives: &[String]) -> anyhow::Result<EnvFilter> {
+    Ok(EnvFilter::try_new(directives.join(","))?)
+}
+
+/// Target of a directive such as `target[span{field=value}]=level`.
+fn directive_target(directive: &str) -> &str {
+    directive
+        .split(['[', '='])
+        .next()
+        .unwrap_or_default()
+        .trim()
+}
+
+impl<S> Filter<S> for LogFilter
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn enabled(&self, meta: &Metadata<'_>, cx: &Context<'_, S>) -> bool {
+        Filter::<S>::enabled(&self.inner.read().filter, meta, cx)
+    }
+
+    fn callsite_enabled(&self, meta: &'static Metadata<'static>) -> tracing::subscriber::Interest {
+        Filter::<S>::callsite_enabled(&self.inner.read().filter, meta)
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Filter::<S>::max_level_hint(&self.inner.read().filter)
+    }
+
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        Filter::<S>::on_new_span(&self.inner.read().filter, attrs, id, ctx)
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        Filter::<S>::on_record(&self.inner.read().filter, id, values, ctx)
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        Filter::<S>::on_enter(&self.inner.read().filter, id, ctx)
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        Filter::<S>::on_exit(&self.inner.read().filter, id, ctx)
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        Filter::<S>::on_close(&self.inner.read().filter, id, ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_level_replaces_target_directive() {
+        let filter = LogFilter::new(vec!["info".into(), "libp2p_gossipsub=error".into()]);
+        filter.set_level("libp2p_gossipsub", "debug").unwrap();
+        filter
+            .set_level("forest_filecoin::chain_sync", "trace")
+            .unwrap();
+        assert_eq!(
+            filter.directives(),
+            [
+                "info",
+                "libp2p_gossipsub=debug",
+                "forest_filecoin::chain_sync=trace"
+            ]
+        );
+        assert!(filter.set_level("chain_sync", "loud").is_err());
+
+        filter.reset().unwrap();
+        assert_eq!(filter.directives(), ["info", "libp2p_gossipsub=error"]);
+    }
+}
@@ -1,14 +1,47 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+mod filter;
+mod rotation;
+
+use std::path::Path;
+
+use opentelemetry::trace::TracerProvider as _;
+use tracing_appender::rolling::Rotation;
 use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
-use tracing_subscriber::{filter::LevelFilter, prelude::*, EnvFilter};
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::{
+    filter::LevelFilter, fmt::writer::BoxMakeWriter, prelude::*, reload, EnvFilter, Registry,
+};
 
-use crate::cli_shared::cli::CliOpts;
+use crate::cli_shared::cli::{CliOpts, LogConfig, LogFormat, LogRotation, TracingConfig};
 use crate::utils::misc::LoggingColor;
+use crate::utils::monitoring::otlp_tracer_provider;
+
+use self::rotation::{SizeRotatingFile, TimeRotatingFile};
+
+pub use self::filter::{log_filter, LogFilter};
+
+const LOG_FILE_NAME: &str = "forest.log";
 
-pub fn setup_logger(opts: &CliOpts) -> (Option<tracing_loki::BackgroundTask>, Option<FlushGuard>) {
+/// Handle to the OTLP export layer, which is only set once a tokio runtime is
+/// running, see [`start_otlp_export`].
+pub type OtlpLayerHandle = reload::Handle<
+    Option<OpenTelemetryLayer<Registry, opentelemetry_sdk::trace::Tracer>>,
+    Registry,
+>;
+
+pub fn setup_logger(
+    opts: &CliOpts,
+    log: &LogConfig,
+) -> (
+    Option<tracing_loki::BackgroundTask>,
+    Option<FlushGuard>,
+    OtlpLayerHandle,
+) {
+    let (otlp_layer, otlp_handle) = reload::Layer::new(None);
     let mut loki_task = None;
+    #[cfg(feature = "tokio-console")]
     let tracing_tokio_console = if opts.tokio_console {
         Some(
             console_subscriber::ConsoleLayer::builder()
@@ -18,6 +51,8 @@ pub fn setup_logger(opts: &CliOpts) -> (Option<tracing_loki::BackgroundTask>, Op
     } else {
         None
     };
+    #[cfg(not(feature = "tokio-console"))]
+    let tracing_tokio_console: Option<tracing_subscriber::layer::Identity> = None;
     let tracing_loki = if opts.loki {
         let (layer, task) = tracing_loki::layer(
             tracing_loki::url::Url::parse(&opts.loki_endpoint)
@@ -41,16 +76,55 @@ pub fn setup_logger(opts: &CliOpts) -> (Option<tracing_loki::BackgroundTask>, Op
     } else {
         None
     };
-    let tracing_rolling_file = if let Some(log_dir) = &opts.log_dir {
-        let file_appender = tracing_appender::rolling::hourly(log_dir, "forest.log");
-        Some(
-            tracing_subscriber::fmt::Layer::new()
-                .with_ansi(false)
-                .with_writer(file_appender)
-                .with_filter(get_env_filter(default_env_filter())),
-        )
-    } else {
-        None
+    let file_writer = opts
+        .log_dir
+        .as_deref()
+        .or(log.dir.as_deref())
+        .and_then(|dir| match log_file_writer(dir, log) {
+            Ok(writer) => Some(writer),
+            Err(e) => {
+                eprintln!("Unable to write log files to {}: {e}", dir.display());
+                None
+            }
+        });
+    let (text_file, json_file) = match (file_writer, log.format) {
+        (Some(writer), LogFormat::Text) => (
+            Some(
+                tracing_subscriber::fmt::Layer::new()
+                    .with_ansi(false)
+                    .with_writer(writer)
+                    .with_filter(log_filter().clone()),
+            ),
+            None,
+        ),
+        (Some(writer), LogFormat::Json) => (
+            None,
+            Some(
+                tracing_subscriber::fmt::Layer::new()
+                    .json()
+                    .with_writer(writer)
+                    .with_filter(log_filter().clone()),
+            ),
+        ),
+        (None, _) => (None, None),
+    };
+    let (text_stdout, json_stdout) = match log.format {
+        LogFormat::Text => (
+            Some(
+                tracing_subscriber::fmt::Layer::new()
+                    .with_ansi(opts.color.coloring_enabled())
+                    .with_filter(log_filter().clone()),
+            ),
+            None,
+        ),
+        LogFormat::Json => (
+            None,
+            Some(
+                tracing_subscriber::fmt::Layer::new()
+                    .json()
+                    .with_filter(log_filter().clone()),
+            ),
+        ),
     };
 
     // Go to <https://ui.perfetto.dev> to browse trace files.
@@ -65,17 +139,54 @@ pub fn setup_logger(opts: &CliOpts) -> (Option<tracing_loki::BackgroundTask>, Op
         };
 
     tracing_subscriber::registry()
+        .with(otlp_layer.with_filter(log_filter().clone()))
         .with(tracing_tokio_console)
         .with(tracing_loki)
-        .with(tracing_rolling_file)
+        .with(text_file)
+        .with(json_file)
         .with(chrome_layer)
-        .with(
-            tracing_subscriber::fmt::Layer::new()
-                .with_ansi(opts.color.coloring_enabled())
-                .with_filter(get_env_filter(default_env_filter())),
-        )
+        .with(text_stdout)
+        .with(json_stdout)
         .init();
-    (loki_task, flush_guard)
+    #[cfg(not(feature = "tokio-console"))]
+    if opts.tokio_console {
+        tracing::warn!("tokio-console support requires building with the tokio-console feature");
+    }
+    (loki_task, flush_guard, otlp_handle)
+}
+
+/// Log files rotated by size if a maximum size is configured, by time
+/// otherwise. The writers of `tracing` are only implemented for the standard
+/// mutex.
+#[allow(clippy::disallowed_types)]
+fn log_file_writer(dir: &Path, log: &LogConfig) -> anyhow::Result<BoxMakeWriter> {
+    if let Some(max_size) = log.max_file_size {
+        let file = SizeRotatingFile::new(dir, LOG_FILE_NAME, max_size, log.max_files)?;
+        return Ok(BoxMakeWriter::new(std::sync::Mutex::new(file)));
+    }
+    let rotation = match log.rotation {
+        LogRotation::Minutely => Rotation::MINUTELY,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+    let file = TimeRotatingFile::new(dir, LOG_FILE_NAME, rotation, log.max_files)?;
+    Ok(BoxMakeWriter::new(std::sync::Mutex::new(file)))
+}
+
+/// Starts exporting spans to an OTLP collector. Must be called within a tokio
+/// runtime, the spans are exported in batches by a background task.
+pub fn start_otlp_export(handle: &OtlpLayerHandle, config: &TracingConfig) -> anyhow::Result<()> {
+    let provider = otlp_tracer_provider(&config.otlp_endpoint, &config.service_name)?;
+    let tracer = provider.tracer("forest");
+    opentelemetry::global::set_tracer_provider(provider);
+    handle.reload(Some(tracing_opentelemetry::layer().with_tracer(tracer)))?;
+    Ok(())
+}
+
+/// Flushes the spans that are yet to be exported.
+pub fn stop_otlp_export() {
+    opentelemetry::global::shutdown_tracer_provider();
 }
 
 // Log warnings to stderr
@@ -91,7 +202,7 @@ pub fn setup_minimal_logger() {
 }
 
 /// Returns an [`EnvFilter`] according to the `RUST_LOG` environment variable, or a default
-/// - see [`default_env_filter`] and [`default_tool_filter`]
+/// - see [`default_tool_filter`]
 ///
 /// Note that [`tracing_subscriber::filter::Builder`] only allows a single default directive,
 /// whereas we want to provide multiple.
@@ -108,22 +219,37 @@ fn get_env_filter(def: EnvFilter) -> EnvFilter {
     }
 }
 
-fn default_env_filter() -> EnvFilter {
-    let default_directives = [
-        "info",
-        "bellperson::groth16::aggregate::verify=warn",
-        "axum=warn",
-        "filecoin_proofs=warn",
-        "libp2p_bitswap=off",
-        "libp2p_gossipsub=error",
-        "libp2p_kad=error",
-        "rpc=error",
-        "storage_proofs_core=warn",
-        "tracing_loki=off",
-    ];
-    EnvFilter::try_new(default_directives.join(",")).unwrap()
+/// Directives of the `RUST_LOG` environment variable, or the default ones.
+fn env_directives() -> Vec<String> {
+    use std::env::{
+        self,
+        VarError::{NotPresent, NotUnicode},
+    };
+    match env::var(tracing_subscriber::EnvFilter::DEFAULT_ENV) {
+        Ok(s) => s
+            .split(',')
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(String::from)
+            .collect(),
+        Err(NotPresent) => DEFAULT_ENV_DIRECTIVES.iter().map(|&d| d.into()).collect(),
+        Err(NotUnicode(_)) => vec!["error".into()],
+    }
 }
 
+const DEFAULT_ENV_DIRECTIVES: &[&str] = &[
+    "info",
+    "bellperson::groth16::aggregate::verify=warn",
+    "axum=warn",
+    "filecoin_proofs=warn",
+    "libp2p_bitswap=off",
+    "libp2p_gossipsub=error",
+    "libp2p_kad=error",
+    "rpc=error",
+    "storage_proofs_core=warn",
+    "tracing_loki=off",
+];
+
 fn default_tool_filter() -> EnvFilter {
     let default_directives = [
         "warn",
@@ -137,5 +263,5 @@ fn default_tool_filter() -> EnvFilter {
 
 #[test]
 fn test_default_env_filter() {
-    let _did_not_panic = default_env_filter();
+    let _did_not_panic = EnvFilter::try_new(DEFAULT_ENV_DIRECTIVES.join(",")).unwrap();
 }
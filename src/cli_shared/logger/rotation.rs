@@ -0,0 +1,205 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+/// How often the log files rotated by time are pruned. Files are rotated at
+/// most every minute.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Log file rotated once it reaches a maximum size. The rotated files are
+/// renamed `<name>.1`, `<name>.2`... from the most recent to the oldest, and
+/// only the `max_files` most recent ones are kept.
+pub struct SizeRotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: Option<usize>,
+    file: File,
+    size: u64,
+}
+
+impl SizeRotatingFile {
+    pub fn new(
+        dir: &Path,
+        file_name: &str,
+        max_size: u64,
+        max_files: Option<usize>,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(file_name);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let mut last = 1;
+        while self.rotated_path(last).exists() {
+            last += 1;
+        }
+        for index in (1..last).rev() {
+            let path = self.rotated_path(index);
+            if self.max_files.is_some_and(|max| index >= max) {
+                fs::remove_file(path)?;
+            } else {
+                fs::rename(path, self.rotated_path(index + 1))?;
+            }
+        }
+        if self.max_files == Some(0) {
+            fs::remove_file(&self.path)?;
+        } else {
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Log file rotated by time. The rotated files are suffixed with their date,
+/// and only the `max_files` most recent ones are kept.
+pub struct TimeRotatingFile {
+    appender: RollingFileAppender,
+    dir: PathBuf,
+    file_name: String,
+    max_files: Option<usize>,
+    last_pruned: Option<Instant>,
+}
+
+impl TimeRotatingFile {
+    pub fn new(
+        dir: &Path,
+        file_name: &str,
+        rotation: Rotation,
+        max_files: Option<usize>,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            appender: RollingFileAppender::new(rotation, dir, file_name),
+            dir: dir.to_owned(),
+            file_name: file_name.to_owned(),
+            max_files,
+            last_pruned: None,
+        })
+    }
+
+    fn prune(&self, max_files: usize) -> io::Result<()> {
+        let prefix = format!("{}.", self.file_name);
+        let mut rotated = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&prefix))
+            {
+                rotated.push(path);
+            }
+        }
+        // Dates sort in chronological order.
+        rotated.sort_unstable();
+        let excess = rotated.len().saturating_sub(max_files);
+        for path in &rotated[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for TimeRotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.appender.write(buf)?;
+        if let Some(max_files) = self.max_files {
+            if self
+                .last_pruned
+                .map_or(true, |last| last.elapsed() >= PRUNE_INTERVAL)
+            {
+                self.last_pruned = Some(Instant::now());
+                self.prune(max_files)?;
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.appender.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_are_rotated_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = SizeRotatingFile::new(dir.path(), "forest.log", 10, Some(2)).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(read("forest.log"), "fourth\n");
+        assert_eq!(read("forest.log.1"), "third\n");
+        assert_eq!(read("forest.log.2"), "second\n");
+        assert!(!dir.path().join("forest.log.3").exists());
+    }
+
+    #[test]
+    fn old_files_are_pruned_by_time() {
+        let dir = tempfile::tempdir().unwrap();
+        for date in ["2023-01-01", "2023-01-02", "2023-01-03"] {
+            fs::write(dir.path().join(format!("forest.log.{date}")), date).unwrap();
+        }
+        let mut file =
+            TimeRotatingFile::new(dir.path(), "forest.log", Rotation::DAILY, Some(2)).unwrap();
+        file.write_all(b"today\n").unwrap();
+        file.flush().unwrap();
+
+        let mut names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 2);
+        assert!(!names.contains(&"forest.log.2023-01-01".to_owned()));
+        assert!(!names.contains(&"forest.log.2023-01-02".to_owned()));
+    }
+}
@@ -3,6 +3,7 @@
 
 pub mod cli;
 pub mod logger;
+pub mod output;
 
 use std::path::PathBuf;
 
@@ -0,0 +1,113 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Printing of the command results, either for humans or for scripts.
+//!
+//! The format is selected once per process with [`OutputFormat::set_global`].
+//! In JSON mode, the result of a command is printed as a JSON document on
+//! `stdout`, or as one JSON object per line for the commands that keep
+//! printing, while progress and status messages go to `stderr` so that the
+//! output stays parseable.
+
+use std::fmt::Display;
+
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+
+static OUTPUT_FORMAT: OnceCell<OutputFormat> = OnceCell::new();
+
+/// Format of the results printed by the command line tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable text.
+    #[default]
+    Text,
+    /// Pretty-printed JSON.
+    Json,
+}
+
+impl OutputFormat {
+    /// Sets the output format of the process. Only the first call has an
+    /// effect.
+    pub fn set_global(self) {
+        let _ = OUTPUT_FORMAT.set(self);
+    }
+
+    /// Returns the output format of the process, [`OutputFormat::Text`] if
+    /// none was set.
+    pub fn get() -> Self {
+        OUTPUT_FORMAT.get().copied().unwrap_or_default()
+    }
+
+    pub fn is_json() -> bool {
+        Self::get() == OutputFormat::Json
+    }
+}
+
+/// Prints `value` as JSON in JSON mode, or calls `text` to print it otherwise.
+pub fn print<T: Serialize>(value: &T, text: impl FnOnce(&T)) -> anyhow::Result<()> {
+    match OutputFormat::get() {
+        OutputFormat::Text => text(value),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+    }
+    Ok(())
+}
+
+/// Prints a status message that is not part of the result of a command: on
+/// `stdout` in text mode, on `stderr` in JSON mode.
+pub fn status(message: impl Display) {
+    match OutputFormat::get() {
+        OutputFormat::Text => println!("{message}"),
+        OutputFormat::Json => eprintln!("{message}"),
+    }
+}
+
+/// Outcome of an operation applied to one of several targets, e.g. a peer.
+#[derive(Debug, Serialize)]
+pub struct Outcome {
+    pub target: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Outcome {
+    pub fn new<E: Display>(target: impl Display, result: Result<(), E>) -> Self {
+        Self {
+            target: target.to_string(),
+            success: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        }
+    }
+}
+
+/// Prints one line per outcome, e.g. `connect <peer>: success`.
+pub fn print_outcomes(action: &str, outcomes: &[Outcome]) -> anyhow::Result<()> {
+    print(&outcomes, |outcomes| {
+        for outcome in outcomes.iter() {
+            match &outcome.error {
+                None => println!("{action} {}: success", outcome.target),
+                Some(error) => println!("{action} {}: failure: {error}", outcome.target),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outcome_serialization() {
+        let ok = Outcome::new("a", Ok::<(), String>(()));
+        let failed = Outcome::new("b", Err("unreachable"));
+
+        assert_eq!(
+            serde_json::to_value([ok, failed]).unwrap(),
+            serde_json::json!([
+                { "target": "a", "success": true },
+                { "target": "b", "success": false, "error": "unreachable" },
+            ])
+        );
+    }
+}
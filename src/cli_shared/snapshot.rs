@@ -13,6 +13,7 @@ use crate::{
 };
 use anyhow::{bail, Context as _};
 use chrono::NaiveDate;
+use tokio::io::AsyncWriteExt as _;
 use tracing::event;
 use url::Url;
 
@@ -61,6 +62,27 @@ pub fn filename(
     .to_string()
 }
 
+/// Saves the hex-encoded representation of a SHA-256 checksum to a file with
+/// the same name but with a `.sha256sum` extension.
+pub async fn save_checksum(source: &Path, encoded_hash: String) -> anyhow::Result<()> {
+    let checksum_file_content = format!(
+        "{encoded_hash} {}\n",
+        source
+            .file_name()
+            .and_then(std::ffi::OsStr::to_str)
+            .context("Failed to retrieve file name while saving checksum")?
+    );
+
+    let checksum_path = PathBuf::from(source).with_extension("sha256sum");
+
+    let mut checksum_file = tokio::fs::File::create(&checksum_path).await?;
+    checksum_file
+        .write_all(checksum_file_content.as_bytes())
+        .await?;
+    checksum_file.flush().await?;
+    Ok(())
+}
+
 /// Returns the path to the downloaded file.
 pub async fn fetch(
     directory: &Path,
@@ -173,7 +195,10 @@ pub fn stable_url(vendor: TrustedVendor, chain: &NetworkChain) -> anyhow::Result
         (TrustedVendor::Forest, NetworkChain::Calibnet) => FOREST_CALIBNET_COMPRESSED,
         (TrustedVendor::Filops, NetworkChain::Mainnet) => FILOPS_MAINNET_COMPRESSED,
         (TrustedVendor::Filops, NetworkChain::Calibnet) => FILOPS_CALIBNET_COMPRESSED,
-        (TrustedVendor::Forest | TrustedVendor::Filops, NetworkChain::Devnet(_)) => {
+        (
+            TrustedVendor::Forest | TrustedVendor::Filops,
+            NetworkChain::Butterflynet | NetworkChain::Devnet(_),
+        ) => {
             bail!("unsupported chain {chain}")
         }
     };
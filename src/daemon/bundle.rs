@@ -2,15 +2,21 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use crate::{
-    networks::{ActorBundleInfo, NetworkChain, ACTOR_BUNDLES},
+    cli_shared::cli::ActorBundlePath,
+    networks::{ActorBundleInfo, ChainConfig, NetworkChain, ACTOR_BUNDLES},
+    shim::version::NetworkVersion,
     utils::{db::car_util::load_car, net::http_get},
 };
-use anyhow::ensure;
+use anyhow::{ensure, Context as _};
 use futures::{stream::FuturesUnordered, TryStreamExt};
 use fvm_ipld_blockstore::Blockstore;
 use std::io::Cursor;
 use std::mem::discriminant;
-use tracing::warn;
+use tracing::{info, warn};
+
+/// Comma-separated list of `<network version>=<path>` actor bundles, in
+/// addition to the ones from the configuration file.
+pub const ACTOR_BUNDLES_ENV: &str = "FOREST_ACTOR_BUNDLES";
 
 /// Tries to load the missing actor bundles to the blockstore. If the bundle is
 /// not present, it will be downloaded.
@@ -53,3 +59,60 @@ pub async fn load_actor_bundles(
 
     Ok(())
 }
+
+/// Loads the local actor bundles of the configuration and of the
+/// [`ACTOR_BUNDLES_ENV`] environment variable, and makes every height of their
+/// network versions use them. Later entries take precedence.
+pub async fn load_local_actor_bundles(
+    db: &impl Blockstore,
+    bundles: &[ActorBundlePath],
+    chain_config: &mut ChainConfig,
+) -> anyhow::Result<()> {
+    let mut bundles = bundles.to_vec();
+    if let Ok(env) = std::env::var(ACTOR_BUNDLES_ENV) {
+        for bundle in env.split(',').filter(|s| !s.trim().is_empty()) {
+            bundles.push(
+                bundle
+                    .parse()
+                    .context(format!("invalid {ACTOR_BUNDLES_ENV}"))?,
+            );
+        }
+    }
+
+    for ActorBundlePath {
+        network_version,
+        path,
+    } in bundles
+    {
+        let file = tokio::fs::File::open(&path)
+            .await
+            .with_context(|| format!("failed to open actor bundle {}", path.display()))?;
+        let header = load_car(db, tokio::io::BufReader::new(file)).await?;
+        ensure!(
+            header.roots.len() == 1,
+            "actor bundle {} must have a single root",
+            path.display()
+        );
+        let manifest = header.roots[0];
+        let mut used = false;
+        for info in chain_config.height_infos.iter_mut().filter(|info| {
+            NetworkVersion::from(info.height) == NetworkVersion(network_version.into())
+        }) {
+            info.bundle = Some(manifest);
+            used = true;
+        }
+        if used {
+            info!(
+                "Using actor bundle {manifest} from {} for network version {network_version}",
+                path.display()
+            );
+        } else {
+            warn!(
+                "Network version {network_version} is not scheduled, ignoring actor bundle {}",
+                path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
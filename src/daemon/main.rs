@@ -18,7 +18,7 @@ use raw_sync_2::{
 };
 use std::ffi::OsString;
 use std::{fs::File, process, time::Duration};
-use tracing::info;
+use tracing::{info, warn};
 
 // Initiate an Event object in shared memory.
 fn create_ipc_lock() -> anyhow::Result<()> {
@@ -88,7 +88,7 @@ where
     // Run forest as a daemon if no other subcommands are used. Otherwise, run the
     // subcommand.
 
-    let (loki_task, _chrome_flush_guard) = logger::setup_logger(&opts);
+    let (loki_task, _chrome_flush_guard, otlp_handle) = logger::setup_logger(&opts, &cfg.log);
     ProgressBar::set_progress_bars_visibility(cfg.client.show_progress_bars);
 
     if let Some(path) = &path {
@@ -132,7 +132,15 @@ where
             if let Some(loki_task) = loki_task {
                 rt.spawn(loki_task);
             }
+            if cfg.tracing.otlp {
+                let _guard = rt.enter();
+                match logger::start_otlp_export(&otlp_handle, &cfg.tracing) {
+                    Ok(()) => info!("Exporting spans to {}", cfg.tracing.otlp_endpoint),
+                    Err(e) => warn!("Failed to start the OTLP export: {e}"),
+                }
+            }
             let ret = rt.block_on(super::start_interruptable(opts, cfg));
+            logger::stop_otlp_export();
             info!("Shutting down tokio...");
             rt.shutdown_timeout(Duration::from_secs_f32(0.5));
             info!("Forest finish shutdown");
@@ -4,11 +4,17 @@
 pub mod bundle;
 mod db_util;
 pub mod main;
+mod scheduler;
+mod sd_notify;
+mod shutdown;
 
-use crate::auth::{create_token, generate_priv_key, ADMIN, JWT_IDENTIFIER};
+use crate::auth::{
+    create_token, generate_priv_key, TokenRegistry, ADMIN, AUTH_TOKENS_FILE, JWT_IDENTIFIER,
+};
 use crate::blocks::Tipset;
 use crate::chain::ChainStore;
-use crate::chain_sync::ChainMuxer;
+use crate::chain_sync::consensus::Proposer as _;
+use crate::chain_sync::{ChainMuxer, HeadLagMonitor};
 use crate::cli_shared::snapshot;
 use crate::cli_shared::{
     chain_path,
@@ -16,19 +22,24 @@ use crate::cli_shared::{
 };
 
 use crate::daemon::db_util::{import_chain_as_forest_car, load_all_forest_cars};
+use crate::daemon::shutdown::{graceful_shutdown, Shutdown};
 use crate::db::car::ManyCar;
 use crate::db::db_engine::{db_root, open_proxy_db};
 use crate::db::rolling::DbGarbageCollector;
-use crate::genesis::{get_network_name_from_genesis, read_genesis_header};
+use crate::fil_cns::DevnetProposer;
+use crate::genesis::{
+    check_snapshot_genesis, check_stored_genesis, get_network_name_from_genesis,
+    read_genesis_header,
+};
 use crate::key_management::{
     KeyStore, KeyStoreConfig, ENCRYPTED_KEYSTORE_NAME, FOREST_KEYSTORE_PHRASE_ENV,
 };
 use crate::libp2p::{Libp2pConfig, Libp2pService, PeerManager};
 use crate::message_pool::{MessagePool, MpoolConfig, MpoolRpcProvider};
-use crate::networks::ChainConfig;
+use crate::networks::{butterflynet, upgrade_overrides_from_env, ChainConfig, NetworkChain};
 use crate::rpc::start_rpc;
 use crate::rpc_api::data_types::RPCState;
-use crate::shim::address::{CurrentNetwork, Network};
+use crate::shim::address::{Address, CurrentNetwork, Network};
 use crate::shim::clock::ChainEpoch;
 use crate::shim::version::NetworkVersion;
 use crate::state_manager::StateManager;
@@ -37,7 +48,8 @@ use crate::utils::{
     version::FOREST_VERSION_STRING,
 };
 use anyhow::{bail, Context as _};
-use bundle::load_actor_bundles;
+use bundle::{load_actor_bundles, load_local_actor_bundles};
+use cid::Cid;
 use dialoguer::console::Term;
 use dialoguer::theme::ColorfulTheme;
 use futures::{select, Future, FutureExt};
@@ -45,7 +57,9 @@ use once_cell::sync::Lazy;
 use raw_sync_2::events::{Event, EventInit as _, EventState};
 use shared_memory::ShmemConf;
 use std::path::Path;
-use std::{cell::RefCell, net::TcpListener, path::PathBuf, sync::Arc};
+use std::pin::Pin;
+use std::time::Duration;
+use std::{cell::RefCell, net::TcpListener, path::PathBuf, str::FromStr, sync::Arc};
 use tempfile::{Builder, TempPath};
 use tokio::{
     signal::{
@@ -107,37 +121,68 @@ fn maybe_increase_fd_limit() -> anyhow::Result<()> {
     Ok(())
 }
 
-// Start the daemon and abort if we're interrupted by ctrl-c, SIGTERM, or `forest-cli shutdown`.
+// Start the daemon and shut it down if we're interrupted by ctrl-c, SIGTERM, or `forest-cli shutdown`.
 pub async fn start_interruptable(opts: CliOpts, config: Config) -> anyhow::Result<()> {
     let mut terminate = signal(SignalKind::terminate())?;
     let (shutdown_send, mut shutdown_recv) = mpsc::channel(1);
+    let shutdown_timeout = config.client.shutdown_timeout;
+    let shutdown = Shutdown::default();
+    let daemon = start(opts, config, shutdown_send, shutdown.clone());
+    tokio::pin!(daemon);
 
-    let result = tokio::select! {
-        ret = start(opts, config, shutdown_send) => ret,
+    let ret = tokio::select! {
+        ret = &mut daemon => Some(ret),
         _ = ctrl_c() => {
             info!("Keyboard interrupt.");
-            Ok(())
+            None
         },
         _ = terminate.recv() => {
             info!("Received SIGTERM.");
-            Ok(())
+            None
         },
         _ = shutdown_recv.recv() => {
             info!("Client requested a shutdown.");
-            Ok(())
+            None
         },
     };
+    let result = match ret {
+        Some(ret) => ret,
+        None => shut_down(daemon, &shutdown, shutdown_timeout).await,
+    };
     crate::utils::io::terminal_cleanup();
     result
 }
 
+/// Requests a graceful shutdown of the daemon and waits for it, for at most
+/// `timeout`.
+async fn shut_down(
+    daemon: Pin<&mut impl Future<Output = anyhow::Result<()>>>,
+    shutdown: &Shutdown,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    sd_notify::notify(sd_notify::STOPPING);
+    if !shutdown.is_ready() {
+        return Ok(());
+    }
+    shutdown.request();
+    match tokio::time::timeout(timeout, daemon).await {
+        Ok(ret) => ret,
+        Err(_) => {
+            warn!("Graceful shutdown timed out after {}s", timeout.as_secs());
+            Ok(())
+        }
+    }
+}
+
 /// Starts daemon process
 pub(super) async fn start(
     opts: CliOpts,
     config: Config,
     shutdown_send: mpsc::Sender<()>,
+    shutdown: Shutdown,
 ) -> anyhow::Result<()> {
-    let chain_config = Arc::new(ChainConfig::from_chain(&config.chain));
+    let mut chain_config = ChainConfig::from_chain(&config.chain);
+    chain_config.override_upgrades(upgrade_overrides_from_env()?)?;
     if chain_config.is_testnet() {
         CurrentNetwork::set_global(Network::Testnet);
     }
@@ -164,6 +209,36 @@ pub(super) async fn start(
 
     let chain_data_path = chain_path(&config);
 
+    // Butterflynet is reset for every upgrade rehearsal, so its genesis is
+    // fetched from Lotus.
+    let (fetched_genesis, expected_genesis) =
+        match (&chain_config.network, &config.client.genesis_file) {
+            (NetworkChain::Butterflynet, None) if opts.offline => {
+                bail!("A genesis file is required to run offline on butterflynet")
+            }
+            (NetworkChain::Butterflynet, None) => {
+                let (genesis, bytes) = butterflynet::fetch_genesis().await?;
+                (Some(bytes), Some(genesis))
+            }
+            (NetworkChain::Calibnet, None) => (
+                None,
+                chain_config
+                    .genesis_cid
+                    .as_deref()
+                    .map(Cid::from_str)
+                    .transpose()?,
+            ),
+            _ => (None, None),
+        };
+    if let Some(genesis) = &expected_genesis {
+        crate::networks::handle_reset(
+            &chain_data_path,
+            &chain_config.network,
+            genesis,
+            opts.auto_reset_network,
+        )?;
+    }
+
     // Try to migrate the database if needed. In case the migration fails, we fallback to creating a new database
     // to avoid breaking the node.
     let db_migration = crate::db::migration::DbMigration::new(chain_data_path.clone());
@@ -172,6 +247,7 @@ pub(super) async fn start(
     }
 
     let db_root_dir = db_root(&chain_data_path)?;
+    crate::state_migration::set_checkpoint_dir(chain_data_path.join("migration_checkpoints"));
     let db = Arc::new(ManyCar::new(Arc::new(open_proxy_db(
         db_root_dir.clone(),
         config.db_config().clone(),
@@ -179,11 +255,19 @@ pub(super) async fn start(
     let forest_car_db_dir = db_root_dir.join("car_db");
     load_all_forest_cars(&db, &forest_car_db_dir)?;
 
-    if config.client.load_actors {
+    if config.client.load_actors && !opts.offline {
         load_actor_bundles(&db, &config.chain).await?;
     }
+    load_local_actor_bundles(&db, &config.client.actor_bundles, &mut chain_config).await?;
+    let chain_config = Arc::new(chain_config);
 
     let mut services = JoinSet::new();
+    // Tasks stopped first on shutdown
+    let mut intake = vec![];
+
+    if let Some(interval) = sd_notify::watchdog_interval() {
+        services.spawn(sd_notify::watchdog_loop(interval));
+    }
 
     if opts.track_peak_rss {
         let mem_stats_tracker = MemStatsTracker::default();
@@ -216,10 +300,12 @@ pub(super) async fn start(
     //   initialized
     let genesis_header = read_genesis_header(
         config.client.genesis_file.as_ref(),
-        chain_config.genesis_bytes(),
+        fetched_genesis.as_deref().or(chain_config.genesis_bytes()),
         &db,
+        &chain_config,
     )
     .await?;
+    check_stored_genesis(db.writer(), genesis_header.cid(), &chain_config.network)?;
 
     // Initialize ChainStore
     let chain_store = Arc::new(ChainStore::new(
@@ -241,7 +327,8 @@ pub(super) async fn start(
         ))
     };
 
-    if !opts.no_gc {
+    // The scheduled garbage collection replaces the automatic one
+    if !opts.no_gc && !config.scheduler.gc.enabled {
         services.spawn({
             let db_garbage_collector = db_garbage_collector.clone();
             async move { db_garbage_collector.collect_loop_passive().await }
@@ -270,8 +357,11 @@ pub(super) async fn start(
     let (tipset_sink, tipset_stream) = flume::bounded(20);
 
     // if bootstrap peers are not set, set them
-    let config = if config.network.bootstrap_peers.is_empty() {
-        let bootstrap_peers = chain_config.bootstrap_peers.clone();
+    let config = if config.network.bootstrap_peers.is_empty() && !opts.offline {
+        let bootstrap_peers = match chain_config.network {
+            NetworkChain::Butterflynet => butterflynet::fetch_bootstrap_peers().await?,
+            _ => chain_config.bootstrap_peers.clone(),
+        };
 
         Config {
             network: Libp2pConfig {
@@ -293,23 +383,30 @@ pub(super) async fn start(
     let peer_manager = Arc::new(PeerManager::default());
     services.spawn(peer_manager.clone().peer_operation_event_loop_task());
     let genesis_cid = *genesis_header.cid();
-    // Libp2p service setup
-    let p2p_service = Libp2pService::new(
-        config.network.clone(),
-        Arc::clone(&chain_store),
-        peer_manager.clone(),
-        net_keypair,
-        &network_name,
-        genesis_cid,
-    )
-    .await?;
-
-    let network_rx = p2p_service.network_receiver();
-    let network_send = p2p_service.network_sender();
+    // Libp2p service setup. Offline, messages to the network fail as there is
+    // no receiver.
+    let (p2p_service, network_send, network_rx) = if opts.offline {
+        info!("Running offline, the libp2p service is disabled");
+        let (network_send, _) = flume::unbounded();
+        (None, network_send, None)
+    } else {
+        let p2p_service = Libp2pService::new(
+            config.network.clone(),
+            Arc::clone(&chain_store),
+            peer_manager.clone(),
+            net_keypair,
+            &network_name,
+            genesis_cid,
+        )
+        .await?;
+        let network_rx = p2p_service.network_receiver();
+        let network_send = p2p_service.network_sender();
+        (Some(p2p_service), network_send, Some(network_rx))
+    };
 
     // Initialize mpool
     let provider = MpoolRpcProvider::new(publisher.clone(), Arc::clone(&state_manager));
-    let mpool = MessagePool::new(
+    let mut mpool = MessagePool::new(
         provider,
         network_name.clone(),
         network_send.clone(),
@@ -317,23 +414,106 @@ pub(super) async fn start(
         state_manager.chain_config().clone(),
         &mut services,
     )?;
+    if let Err(e) = mpool.load_saved_local(db.writer().as_ref()) {
+        warn!("Failed to load the local messages of the memory pool: {e}");
+    }
 
     let mpool = Arc::new(mpool);
 
-    // Initialize ChainMuxer
-    let chain_muxer = ChainMuxer::new(
-        Arc::clone(&state_manager),
-        peer_manager,
-        mpool.clone(),
-        network_send.clone(),
-        network_rx,
-        Arc::new(Tipset::from(genesis_header)),
-        tipset_sink,
-        tipset_stream,
-    )?;
-    let bad_blocks = chain_muxer.bad_blocks_cloned();
-    let sync_state = chain_muxer.sync_state_cloned();
-    services.spawn(async { Err(anyhow::anyhow!("{}", chain_muxer.await)) });
+    // Initialize ChainMuxer, there is nothing to sync offline
+    let (bad_blocks, sync_state) = if let Some(network_rx) = network_rx {
+        let chain_muxer = ChainMuxer::new(
+            Arc::clone(&state_manager),
+            peer_manager,
+            mpool.clone(),
+            network_send.clone(),
+            network_rx,
+            Arc::new(Tipset::from(&genesis_header)),
+            tipset_sink.clone(),
+            tipset_stream,
+        )?;
+
+        if let Some(miner) = &opts.produce_blocks {
+            let miner = Address::from_str(miner)
+                .with_context(|| format!("invalid miner address {miner}"))?;
+            DevnetProposer::new(
+                miner,
+                Arc::clone(&keystore),
+                network_name.clone(),
+                network_send.clone(),
+                tipset_sink.clone(),
+            )
+            .spawn(Arc::clone(&state_manager), mpool.clone(), &mut services)
+            .await?;
+        }
+        let bad_blocks = chain_muxer.bad_blocks_cloned();
+        let sync_state = chain_muxer.sync_state_cloned();
+        services.spawn(async { Err(anyhow::anyhow!("{}", chain_muxer.await)) });
+        services.spawn(
+            HeadLagMonitor::new(
+                Arc::clone(&chain_store),
+                &chain_config,
+                network_name.clone(),
+                &config.sync,
+            )?
+            .run(),
+        );
+        (bad_blocks, sync_state)
+    } else {
+        (Default::default(), Default::default())
+    };
+
+    if config.scheduler.snapshot_export.enabled {
+        let dir = config
+            .scheduler
+            .snapshot_export
+            .dir
+            .clone()
+            .unwrap_or_else(|| config.client.data_dir.join("snapshots"));
+        services.spawn(scheduler::snapshot_export_loop(
+            Arc::clone(&chain_store),
+            get_actual_chain_name(&network_name).to_string(),
+            dir,
+            config.scheduler.snapshot_export.clone(),
+            config.sync.recent_state_roots,
+        ));
+    }
+    if config.scheduler.gc.enabled {
+        services.spawn(scheduler::gc_loop(
+            db_garbage_collector.get_tx(),
+            config.scheduler.gc.clone(),
+        ));
+    }
+
+    if config.client.enable_health_check {
+        let health_check_listener = TcpListener::bind(config.client.health_check_address).context(
+            format!("could not bind to {}", config.client.health_check_address),
+        )?;
+        info!(
+            "Health check endpoints started at {}",
+            config.client.health_check_address
+        );
+        let health_state = crate::metrics::health::HealthState {
+            start_time,
+            chain_store: Arc::clone(&chain_store),
+            block_delay: chain_config.block_delay_secs,
+            rpc_address: config
+                .client
+                .enable_rpc
+                .then_some(config.client.rpc_address),
+            // The head doesn't move offline
+            sync_lag_threshold: if opts.offline {
+                ChainEpoch::MAX
+            } else {
+                config.sync.head_lag_alert_threshold
+            },
+        };
+        services.spawn(async {
+            crate::metrics::health::init_health_check(health_check_listener, health_state)
+                .await
+                .context("Failed to start the health check server")
+        });
+    }
 
     // Start services
     if config.client.enable_rpc {
@@ -346,36 +526,45 @@ pub(super) async fn start(
 
         let rpc_state_manager = Arc::clone(&state_manager);
         let rpc_chain_store = Arc::clone(&chain_store);
+        let rpc_mpool = Arc::clone(&mpool);
 
         let gc_event_tx = db_garbage_collector.get_tx();
-        services.spawn(async move {
+        let db_directory = db_root(&chain_path(&config))?;
+        let token_registry = Arc::new(parking_lot::RwLock::new(TokenRegistry::load(
+            config.client.data_dir.join(AUTH_TOKENS_FILE),
+        )?));
+        let rpc_rate_limit = config.rpc_rate_limit.clone();
+        let rpc_cors = config.client.rpc_cors.clone();
+        intake.push(services.spawn(async move {
             info!("JSON-RPC endpoint started at {}", config.client.rpc_address);
-            let beacon = Arc::new(
-                rpc_state_manager
-                    .chain_config()
-                    .get_beacon_schedule(chain_store.genesis().timestamp()),
-            );
+            let beacon = rpc_state_manager.beacon_schedule();
             start_rpc(
                 Arc::new(RPCState {
                     state_manager: Arc::clone(&rpc_state_manager),
                     keystore: keystore_rpc,
-                    mpool,
+                    token_registry,
+                    mpool: rpc_mpool,
                     bad_blocks,
                     sync_state,
                     network_send,
+                    tipset_sink,
                     network_name,
                     start_time,
                     beacon,
                     chain_store: rpc_chain_store,
                     gc_event_tx,
+                    db_directory,
                 }),
                 rpc_listen,
+                config.client.rpc_max_batch_size,
+                rpc_rate_limit,
+                rpc_cors,
                 FOREST_VERSION_STRING.as_str(),
                 shutdown_send,
             )
             .await
             .map_err(|err| anyhow::anyhow!("{:?}", serde_json::to_string(&err)))
-        });
+        }));
     } else {
         debug!("RPC disabled.");
     };
@@ -392,7 +581,7 @@ pub(super) async fn start(
 
     // Sets the latest snapshot if needed for downloading later
     let mut config = config;
-    if config.client.snapshot_path.is_none() {
+    if config.client.snapshot_path.is_none() && !opts.offline {
         set_snapshot_path_if_needed(
             &mut config,
             &chain_config,
@@ -406,6 +595,9 @@ pub(super) async fn start(
     // Import chain if needed
     if !opts.skip_load.unwrap_or_default() {
         if let Some(path) = &config.client.snapshot_path {
+            if opts.offline && url::Url::parse(&path.display().to_string()).is_ok() {
+                bail!("Can't download snapshot {} offline", path.display());
+            }
             let (car_db_path, ts) = import_chain_as_forest_car(
                 path,
                 &forest_car_db_dir,
@@ -414,6 +606,8 @@ pub(super) async fn start(
             .await?;
             db.read_only_files(std::iter::once(car_db_path.clone()))?;
             debug!("Loaded car DB at {}", car_db_path.display());
+            check_snapshot_genesis(&ts, &db, &genesis_header, &chain_config.network)
+                .with_context(|| format!("invalid snapshot {}", path.display()))?;
             state_manager
                 .chain_store()
                 .set_heaviest_tipset(Arc::new(ts))?;
@@ -445,14 +639,23 @@ pub(super) async fn start(
         return Ok(());
     }
 
-    ensure_params_downloaded().await?;
-    services.spawn(p2p_service.run());
+    if let Some(p2p_service) = p2p_service {
+        ensure_params_downloaded().await?;
+        intake.push(services.spawn(p2p_service.run()));
+    }
 
-    // blocking until any of the services returns an error,
-    propagate_error(&mut services)
-        .await
-        .context("services failure")
-        .map(|_| {})
+    shutdown.set_ready();
+    sd_notify::notify(sd_notify::READY);
+
+    // blocking until any of the services returns an error, or a shutdown is
+    // requested
+    tokio::select! {
+        result = propagate_error(&mut services) => {
+            return result.context("services failure").map(|_| {});
+        }
+        _ = shutdown.requested() => {}
+    }
+    graceful_shutdown(&mut services, &intake, &mpool, db.writer().as_ref()).await
 }
 
 /// If our current chain is below a supported height, we need a snapshot to bring it up
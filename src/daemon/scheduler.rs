@@ -0,0 +1,302 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Maintenance jobs run periodically by the daemon, configured in the
+//! `[scheduler]` section: snapshot exports and database garbage collection.
+//! The outcome of each run is exported as metrics, labelled by job.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::chain::ChainStore;
+use crate::cid_collections::CidHashSet;
+use crate::cli_shared::cli::{GcSchedule, SnapshotExportSchedule};
+use crate::cli_shared::snapshot::{self, save_checksum, TrustedVendor};
+use anyhow::Context as _;
+use chrono::{NaiveDateTime, Timelike as _, Utc};
+use fvm_ipld_blockstore::Blockstore;
+use hex::ToHex as _;
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, IntGaugeVec, Opts};
+use sha2::Sha256;
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+const JOB_SNAPSHOT_EXPORT: &str = "snapshot_export";
+const JOB_SNAPSHOT_UPLOAD: &str = "snapshot_upload";
+const JOB_GC: &str = "gc";
+
+/// How often the quiet hours are checked for.
+const GC_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+static JOB_RUNS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let job_runs = IntCounterVec::new(
+        Opts::new("scheduler_job_runs_total", "Runs of the scheduled jobs"),
+        &["job", "result"],
+    )
+    .expect("Defining the scheduler_job_runs_total metric must succeed");
+    prometheus::default_registry()
+        .register(Box::new(job_runs.clone()))
+        .expect("Registering the scheduler_job_runs_total metric must succeed");
+    job_runs
+});
+static JOB_LAST_SUCCESS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let last_success = IntGaugeVec::new(
+        Opts::new(
+            "scheduler_job_last_success_timestamp_seconds",
+            "Unix timestamp of the last successful run of the scheduled jobs",
+        ),
+        &["job"],
+    )
+    .expect("Defining the scheduler_job_last_success_timestamp_seconds metric must succeed");
+    prometheus::default_registry()
+        .register(Box::new(last_success.clone()))
+        .expect("Registering the scheduler_job_last_success_timestamp_seconds metric must succeed");
+    last_success
+});
+static JOB_LAST_DURATION: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let last_duration = IntGaugeVec::new(
+        Opts::new(
+            "scheduler_job_last_duration_seconds",
+            "Duration of the last run of the scheduled jobs",
+        ),
+        &["job"],
+    )
+    .expect("Defining the scheduler_job_last_duration_seconds metric must succeed");
+    prometheus::default_registry()
+        .register(Box::new(last_duration.clone()))
+        .expect("Registering the scheduler_job_last_duration_seconds metric must succeed");
+    last_duration
+});
+
+fn record_run<T>(job: &str, started: Instant, result: &anyhow::Result<T>) {
+    let outcome = if result.is_ok() { "success" } else { "failure" };
+    JOB_RUNS.with_label_values(&[job, outcome]).inc();
+    JOB_LAST_DURATION
+        .with_label_values(&[job])
+        .set(started.elapsed().as_secs() as i64);
+    match result {
+        Ok(_) => JOB_LAST_SUCCESS
+            .with_label_values(&[job])
+            .set(Utc::now().timestamp()),
+        Err(e) => warn!("Scheduled {job} failed: {e:#}"),
+    }
+}
+
+/// Exports a snapshot of the chain every `schedule.interval`, keeping the
+/// `schedule.keep` most recent ones.
+pub async fn snapshot_export_loop<DB>(
+    chain_store: Arc<ChainStore<DB>>,
+    chain_name: String,
+    dir: PathBuf,
+    schedule: SnapshotExportSchedule,
+    recent_roots: i64,
+) -> anyhow::Result<()>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    anyhow::ensure!(
+        !schedule.interval.is_zero(),
+        "the interval between snapshot exports must not be zero"
+    );
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .with_context(|| format!("could not create {}", dir.display()))?;
+    info!(
+        "Exporting a snapshot to {} every {}s",
+        dir.display(),
+        schedule.interval.as_secs()
+    );
+    let start = tokio::time::Instant::now() + schedule.interval;
+    let mut interval = tokio::time::interval_at(start, schedule.interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let started = Instant::now();
+        let result = export_snapshot(&chain_store, &chain_name, &dir, recent_roots).await;
+        record_run(JOB_SNAPSHOT_EXPORT, started, &result);
+        let Ok(path) = result else {
+            continue;
+        };
+        info!("Exported snapshot {}", path.display());
+        if let Err(e) = prune_snapshots(&dir, &chain_name, schedule.keep) {
+            warn!("Failed to delete old snapshots: {e:#}");
+        }
+        if let Some(hook) = &schedule.upload_hook {
+            let started = Instant::now();
+            let result = run_upload_hook(hook, &path).await;
+            record_run(JOB_SNAPSHOT_UPLOAD, started, &result);
+        }
+    }
+}
+
+async fn export_snapshot<DB>(
+    chain_store: &ChainStore<DB>,
+    chain_name: &str,
+    dir: &Path,
+    recent_roots: i64,
+) -> anyhow::Result<PathBuf>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    let head = chain_store.heaviest_tipset();
+    let date = NaiveDateTime::from_timestamp_opt(head.min_ticket_block().timestamp() as i64, 0)
+        .unwrap_or_default()
+        .into();
+    let path = dir.join(snapshot::filename(
+        TrustedVendor::Forest,
+        chain_name,
+        date,
+        head.epoch(),
+        true,
+    ));
+    let temp_path = tempfile::NamedTempFile::new_in(dir)?.into_temp_path();
+    let file = tokio::fs::File::create(&temp_path).await?;
+    let checksum = crate::chain::export::<Sha256>(
+        Arc::clone(&chain_store.db),
+        &head,
+        recent_roots,
+        file,
+        CidHashSet::default(),
+        false,
+    )
+    .await?;
+    if let Some(hash) = checksum {
+        save_checksum(&path, hash.encode_hex()).await?;
+    }
+    temp_path.persist(&path)?;
+    Ok(path)
+}
+
+/// Deletes the snapshots exported for the chain, along with their checksum
+/// files, except the `keep` most recent ones.
+fn prune_snapshots(dir: &Path, chain_name: &str, keep: usize) -> anyhow::Result<()> {
+    if keep == 0 {
+        return Ok(());
+    }
+    let prefix = format!("{}_snapshot_{chain_name}_", TrustedVendor::Forest);
+    let mut snapshots = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(&prefix) && name.ends_with(".forest.car.zst") {
+            let modified = entry
+                .metadata()?
+                .modified()
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            snapshots.push((modified, name, entry.path()));
+        }
+    }
+    // Most recent first
+    snapshots.sort_unstable_by(|a, b| b.cmp(a));
+    for (_, _, path) in snapshots.into_iter().skip(keep) {
+        info!("Deleting old snapshot {}", path.display());
+        std::fs::remove_file(&path)?;
+        let checksum_path = path.with_extension("sha256sum");
+        if checksum_path.exists() {
+            std::fs::remove_file(checksum_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs the hook with the paths of the snapshot and of its checksum file.
+async fn run_upload_hook(hook: &Path, snapshot: &Path) -> anyhow::Result<()> {
+    let status = tokio::process::Command::new(hook)
+        .arg(snapshot)
+        .arg(snapshot.with_extension("sha256sum"))
+        .status()
+        .await
+        .with_context(|| format!("could not run {}", hook.display()))?;
+    anyhow::ensure!(status.success(), "{} failed: {status}", hook.display());
+    Ok(())
+}
+
+/// Triggers the database garbage collection at most once per
+/// `schedule.interval`, during the quiet hours.
+pub async fn gc_loop(
+    gc_tx: flume::Sender<flume::Sender<anyhow::Result<()>>>,
+    schedule: GcSchedule,
+) -> anyhow::Result<()> {
+    info!(
+        "Running the database garbage collection between {}:00 and {}:00 UTC",
+        schedule.quiet_hours_start, schedule.quiet_hours_end
+    );
+    let mut last_run: Option<Instant> = None;
+    loop {
+        tokio::time::sleep(GC_CHECK_INTERVAL).await;
+        let hour = Utc::now().hour();
+        if !in_quiet_hours(hour, schedule.quiet_hours_start, schedule.quiet_hours_end)
+            || last_run.is_some_and(|last_run| last_run.elapsed() < schedule.interval)
+        {
+            continue;
+        }
+        last_run = Some(Instant::now());
+        let started = Instant::now();
+        let (tx, rx) = flume::bounded(1);
+        gc_tx
+            .send_async(tx)
+            .await
+            .context("the garbage collector is gone")?;
+        let result = rx
+            .recv_async()
+            .await
+            .context("the garbage collector is gone")?;
+        record_run(JOB_GC, started, &result);
+    }
+}
+
+/// Whether `hour` is within `start..end`, which spans midnight if `end` is
+/// lower than `start`. Equal bounds cover the whole day.
+fn in_quiet_hours(hour: u32, start: u8, end: u8) -> bool {
+    let (start, end) = (u32::from(start), u32::from(end));
+    match start.cmp(&end) {
+        std::cmp::Ordering::Less => (start..end).contains(&hour),
+        std::cmp::Ordering::Greater => hour >= start || hour < end,
+        std::cmp::Ordering::Equal => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quiet_hours() {
+        assert!(in_quiet_hours(2, 2, 6));
+        assert!(in_quiet_hours(5, 2, 6));
+        assert!(!in_quiet_hours(6, 2, 6));
+        assert!(in_quiet_hours(23, 22, 4));
+        assert!(in_quiet_hours(0, 22, 4));
+        assert!(!in_quiet_hours(12, 22, 4));
+        assert!(in_quiet_hours(12, 0, 0));
+    }
+
+    #[test]
+    fn old_snapshots_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let date = chrono::NaiveDate::from_ymd_opt(2023, 10, 1).unwrap();
+        let names: Vec<_> = (100..103)
+            .map(|height| snapshot::filename(TrustedVendor::Forest, "calibnet", date, height, true))
+            .collect();
+        for name in &names {
+            let path = dir.path().join(name);
+            std::fs::write(&path, []).unwrap();
+            std::fs::write(path.with_extension("sha256sum"), []).unwrap();
+        }
+        let other = dir.path().join("notes.txt");
+        std::fs::write(&other, []).unwrap();
+
+        prune_snapshots(dir.path(), "calibnet", 2).unwrap();
+        assert!(!dir.path().join(&names[0]).exists());
+        assert!(!dir
+            .path()
+            .join(&names[0])
+            .with_extension("sha256sum")
+            .exists());
+        assert!(dir.path().join(&names[1]).exists());
+        assert!(dir.path().join(&names[2]).exists());
+        assert!(other.exists());
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Notifications to the `systemd` service manager, for services of
+//! `Type=notify`, see <https://www.freedesktop.org/software/systemd/man/sd_notify.html>.
+
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::{ffi::OsStrExt as _, net::UnixDatagram};
+use std::time::Duration;
+
+use tracing::warn;
+
+/// Socket of the service manager, only set when running under `systemd`.
+const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+
+/// The daemon is started and serving requests.
+pub const READY: &str = "READY=1";
+/// The daemon is shutting down.
+pub const STOPPING: &str = "STOPPING=1";
+/// Keep-alive ping, see [`watchdog_interval`].
+pub const WATCHDOG: &str = "WATCHDOG=1";
+
+/// Sends a state to the service manager, does nothing when not running under
+/// `systemd`.
+pub fn notify(state: &str) {
+    let Some(socket_path) = std::env::var_os(NOTIFY_SOCKET_ENV) else {
+        return;
+    };
+    if let Err(e) = send(&socket_path, state) {
+        warn!("Failed to notify the service manager of {state}: {e}");
+    }
+}
+
+fn send(socket_path: &OsStr, state: &str) -> io::Result<()> {
+    let socket = UnixDatagram::unbound()?;
+    match socket_path.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt as _;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "abstract sockets are only supported on Linux",
+            ))
+        }
+        None => {
+            socket.send_to(state.as_bytes(), socket_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Interval within which the service manager expects [`WATCHDOG`] pings, if
+/// its watchdog is enabled for this process (`WatchdogSec=`).
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+    Some(Duration::from_micros(usec)).filter(|interval| !interval.is_zero())
+}
+
+/// Pings the watchdog twice per interval, as recommended.
+pub async fn watchdog_loop(interval: Duration) -> anyhow::Result<()> {
+    let mut ticker = tokio::time::interval(interval / 2);
+    loop {
+        ticker.tick().await;
+        notify(WATCHDOG);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn states_are_sent() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("notify.sock");
+        let listener = UnixDatagram::bind(&socket_path).unwrap();
+        send(socket_path.as_os_str(), READY).unwrap();
+        let mut buf = [0; 64];
+        let n = listener.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], READY.as_bytes());
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::chain_sync::pause_validations;
+use crate::db::SettingsStore;
+use crate::message_pool::{MessagePool, Provider};
+use tokio::task::{AbortHandle, JoinSet};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Shutdown request sent to the daemon once it's ready. A daemon that is
+/// still initializing is stopped right away instead.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    ready: Arc<AtomicBool>,
+    requested: CancellationToken,
+}
+
+impl Shutdown {
+    pub fn set_ready(&self) {
+        self.ready.store(true, Ordering::Release);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    pub fn request(&self) {
+        self.requested.cancel();
+    }
+
+    pub async fn requested(&self) {
+        self.requested.cancelled().await
+    }
+}
+
+/// Shuts the daemon down in phases:
+/// 1. stops the intake of new work, i.e. the RPC server and the libp2p service,
+/// 2. saves the messages pushed to the memory pool through this node,
+/// 3. waits for the tipset validations in progress,
+/// 4. stops the remaining services, which releases the database. It's flushed
+///    once closed.
+pub async fn graceful_shutdown<T, DB>(
+    services: &mut JoinSet<anyhow::Result<()>>,
+    intake: &[AbortHandle],
+    mpool: &MessagePool<T>,
+    settings: &DB,
+) -> anyhow::Result<()>
+where
+    T: Provider,
+    DB: SettingsStore,
+{
+    info!("Shutting down: stopping the RPC server and the libp2p service");
+    for task in intake {
+        task.abort();
+    }
+    info!("Shutting down: saving local messages of the memory pool");
+    if let Err(e) = mpool.save_local(settings) {
+        warn!("Failed to save local messages: {e}");
+    }
+    info!("Shutting down: waiting for the tipset validations in progress");
+    let _paused = pause_validations().await;
+    info!("Shutting down: stopping services and flushing the database");
+    services.shutdown().await;
+    Ok(())
+}
@@ -17,10 +17,37 @@ use std::sync::Arc;
 pub mod setting_keys {
     /// Key used to store the heaviest tipset in the settings store. This is expected to be a [`crate::blocks::TipsetKeys`]
     pub const HEAD_KEY: &str = "head";
+    /// Key used to store the CID of the genesis the database was initialized
+    /// with.
+    pub const GENESIS_KEY: &str = "/genesis";
     /// Estimated number of IPLD records in the database. This is expected to be a `usize`
     pub const ESTIMATED_RECORDS_KEY: &str = "estimated_reachable_records";
     /// Key used to store the memory pool configuration in the settings store.
     pub const MPOOL_CONFIG_KEY: &str = "/mpool/config";
+    /// Key used to store the messages pushed to the memory pool through this
+    /// node, so that they survive a restart.
+    pub const MPOOL_LOCAL_MESSAGES_KEY: &str = "/mpool/local";
+    /// Key used to store the payment channels tracked by this node, listed by
+    /// `Filecoin.PaychList`.
+    pub const PAYCH_CHANNELS_KEY: &str = "/paych/channels";
+    /// Prefix of the keys of verified `drand` entries, followed by
+    /// `/<chain hash>/<round>`. The values are the entry signatures.
+    pub const DRAND_ENTRY_KEY_PREFIX: &str = "/drand";
+    /// Prefix of the keys of F3 finality certificates, followed by
+    /// `/<instance>`.
+    pub const F3_CERT_KEY_PREFIX: &str = "/f3/certs";
+    /// Key used to store the instance of the latest F3 finality certificate.
+    pub const F3_LATEST_CERT_KEY: &str = "/f3/latest";
+    /// Prefix of the keys of the Ethereum transaction hashes, followed by
+    /// `/<hash>`. The values are the CIDs of the messages, see
+    /// [`crate::eth`].
+    pub const ETH_TX_HASH_KEY_PREFIX: &str = "/eth/txhash";
+    /// Key used to store the peers known to the node, see
+    /// [`crate::libp2p::Peerstore`].
+    pub const PEERSTORE_KEY: &str = "/libp2p/peerstore";
+    /// Key written by the health checks to verify that the database is
+    /// writable.
+    pub const HEALTH_CHECK_KEY: &str = "/health_check";
 }
 
 /// Interface used to store and retrieve settings from the database.
@@ -178,6 +178,7 @@ impl Blockstore for ParityDb {
         }
     }
 
+    #[tracing::instrument(level = "debug", skip_all)]
     fn put_many_keyed<D, I>(&self, blocks: I) -> anyhow::Result<()>
     where
         Self: Sized,
@@ -0,0 +1,234 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Ethereum transactions of the messages signed with a delegated key, i.e.
+//! sent from an `f410` address by an Ethereum wallet.
+//!
+//! The hash of such a transaction is the Keccak-256 hash of the RLP-encoded
+//! EIP-1559 transaction, which can't be derived from the message CID. The
+//! hashes of the messages seen by the node are kept in the settings store
+//! under [`ETH_TX_HASH_KEY_PREFIX`], so that the transactions can be looked
+//! up by hash.
+//!
+//! [`ETH_TX_HASH_KEY_PREFIX`]: crate::db::setting_keys::ETH_TX_HASH_KEY_PREFIX
+
+mod rlp;
+
+use crate::db::setting_keys::ETH_TX_HASH_KEY_PREFIX;
+use crate::db::SettingsStore;
+use crate::message::{ChainMessage, SignedMessage};
+use crate::rpc_api::eth_api::{
+    EthAddress, EthHash, EAM_CREATE_EXTERNAL_METHOD, EIP_1559_TX_TYPE, EVM_INVOKE_CONTRACT_METHOD,
+};
+use crate::shim::address::Address;
+use crate::shim::crypto::Signature;
+use crate::shim::message::{Message, METHOD_SEND};
+use anyhow::Context as _;
+use cid::Cid;
+use fvm_ipld_encoding::{BytesDe, RawBytes};
+use sha3::{Digest as _, Keccak256};
+
+pub use self::rlp::Rlp;
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// The hash of the Ethereum transaction of a message signed with a delegated
+/// key, the digest of the message CID otherwise.
+pub fn tx_hash(msg: &ChainMessage, chain_id: u64) -> anyhow::Result<EthHash> {
+    match msg {
+        ChainMessage::Signed(smsg) if smsg.is_delegated() => eth_tx_hash(smsg, chain_id),
+        _ => Ok(EthHash::from_cid(&msg.cid()?)),
+    }
+}
+
+/// Hash of the EIP-1559 transaction a message signed with a delegated key was
+/// built from.
+pub fn eth_tx_hash(msg: &SignedMessage, chain_id: u64) -> anyhow::Result<EthHash> {
+    let message = msg.message();
+    let (to, input) = to_and_input(message)?;
+    let (v, r, s) = signature_values(msg.signature())?;
+    let tx = Rlp::List(vec![
+        Rlp::uint(&chain_id.to_be_bytes()),
+        Rlp::uint(&message.sequence.to_be_bytes()),
+        Rlp::uint(&message.gas_premium.atto().to_bytes_be().1),
+        Rlp::uint(&message.gas_fee_cap.atto().to_bytes_be().1),
+        Rlp::uint(&message.gas_limit.to_be_bytes()),
+        Rlp::Bytes(to.map(|to| to.0.to_vec()).unwrap_or_default()),
+        Rlp::uint(&message.value.atto().to_bytes_be().1),
+        Rlp::Bytes(input),
+        // Access list
+        Rlp::List(vec![]),
+        Rlp::uint(v),
+        Rlp::uint(r),
+        Rlp::uint(s),
+    ]);
+    let mut encoded = vec![EIP_1559_TX_TYPE as u8];
+    encoded.extend(tx.encode());
+    Ok(EthHash(keccak256(&encoded)))
+}
+
+/// The `v`, `r` and `s` values of a delegated signature, which is `r || s ||
+/// v`.
+pub fn signature_values(sig: &Signature) -> anyhow::Result<(&[u8], &[u8], &[u8])> {
+    let bytes = sig.bytes();
+    anyhow::ensure!(
+        bytes.len() == 65,
+        "delegated signatures are 65 bytes long, got {}",
+        bytes.len()
+    );
+    Ok((&bytes[64..], &bytes[..32], &bytes[32..64]))
+}
+
+/// Contracts are created through the Ethereum Address Manager actor, the
+/// other transactions are contract invocations or plain transfers.
+fn to_and_input(msg: &Message) -> anyhow::Result<(Option<EthAddress>, Vec<u8>)> {
+    if msg.to == Address::ETHEREUM_ACCOUNT_MANAGER_ACTOR {
+        anyhow::ensure!(
+            msg.method_num == EAM_CREATE_EXTERNAL_METHOD,
+            "method {} of the Ethereum Address Manager isn't a contract creation",
+            msg.method_num
+        );
+        return Ok((None, decode_bytes_params(&msg.params)?));
+    }
+    let to = EthAddress::from_filecoin_address(&msg.to)?;
+    let input = match msg.method_num {
+        EVM_INVOKE_CONTRACT_METHOD => decode_bytes_params(&msg.params)?,
+        METHOD_SEND if msg.params.is_empty() => vec![],
+        method => anyhow::bail!("method {method} has no Ethereum equivalent"),
+    };
+    Ok((Some(to), input))
+}
+
+/// The input of Ethereum transactions is passed as CBOR-encoded bytes.
+fn decode_bytes_params(params: &RawBytes) -> anyhow::Result<Vec<u8>> {
+    if params.is_empty() {
+        return Ok(vec![]);
+    }
+    let BytesDe(bytes) = fvm_ipld_encoding::from_slice(params.bytes())
+        .context("the parameters aren't CBOR-encoded bytes")?;
+    Ok(bytes)
+}
+
+/// Records the hashes of the Ethereum transactions of the messages signed
+/// with a delegated key.
+pub fn index_tx_hashes<'a, S>(
+    settings: &S,
+    chain_id: u64,
+    msgs: impl IntoIterator<Item = &'a SignedMessage>,
+) -> anyhow::Result<()>
+where
+    S: SettingsStore + ?Sized,
+{
+    for msg in msgs.into_iter().filter(|msg| msg.is_delegated()) {
+        let hash = eth_tx_hash(msg, chain_id)?;
+        settings.write_bin(&tx_hash_key(&hash), &msg.cid()?.to_bytes())?;
+    }
+    Ok(())
+}
+
+/// The CID of the message of an indexed Ethereum transaction.
+pub fn message_cid<S>(settings: &S, hash: &EthHash) -> anyhow::Result<Option<Cid>>
+where
+    S: SettingsStore + ?Sized,
+{
+    settings
+        .read_bin(&tx_hash_key(hash))?
+        .map(|bytes| Ok(Cid::try_from(bytes.as_slice())?))
+        .transpose()
+}
+
+fn tx_hash_key(hash: &EthHash) -> String {
+    format!("{ETH_TX_HASH_KEY_PREFIX}/{hash}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+    use crate::shim::crypto::SignatureType;
+    use crate::shim::econ::TokenAmount;
+    use fvm_ipld_encoding::BytesSer;
+
+    fn delegated_message(to: Address, method_num: u64, params: RawBytes) -> SignedMessage {
+        let from = Address::new_delegated(10, &[1; 20]).unwrap();
+        let message = Message {
+            from,
+            to,
+            sequence: 3,
+            value: TokenAmount::from_atto(1000),
+            method_num,
+            params,
+            gas_limit: 21000,
+            gas_fee_cap: TokenAmount::from_atto(200),
+            gas_premium: TokenAmount::from_atto(100),
+            ..Default::default()
+        };
+        let mut sig = vec![0x11; 32];
+        sig.extend([0x22; 32]);
+        sig.push(1);
+        SignedMessage::new_unchecked(message, Signature::new(SignatureType::Delegated, sig))
+    }
+
+    #[test]
+    fn keccak() {
+        assert_eq!(
+            hex::encode(keccak256(&[])),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+    }
+
+    #[test]
+    fn tx_hash_of_a_transfer() {
+        let to = Address::new_delegated(10, &[2; 20]).unwrap();
+        let msg = delegated_message(to, METHOD_SEND, RawBytes::default());
+        let mut expected = vec![0x02, 0xf8, 0x67, 0x82, 0x01, 0x3a, 0x03, 0x64, 0x81, 0xc8];
+        expected.extend([0x82, 0x52, 0x08, 0x94]);
+        expected.extend([2; 20]);
+        expected.extend([0x82, 0x03, 0xe8, 0x80, 0xc0, 0x01, 0xa0]);
+        expected.extend([0x11; 32]);
+        expected.push(0xa0);
+        expected.extend([0x22; 32]);
+        assert_eq!(
+            eth_tx_hash(&msg, 314).unwrap(),
+            EthHash(keccak256(&expected))
+        );
+        assert_ne!(
+            tx_hash(&ChainMessage::Signed(msg.clone()), 314).unwrap(),
+            EthHash::from_cid(&msg.cid().unwrap())
+        );
+    }
+
+    #[test]
+    fn contract_creations_have_no_recipient() {
+        let params = RawBytes::serialize(BytesSer(&[0x60, 0x80])).unwrap();
+        let msg = delegated_message(
+            Address::ETHEREUM_ACCOUNT_MANAGER_ACTOR,
+            EAM_CREATE_EXTERNAL_METHOD,
+            params,
+        );
+        let (to, input) = to_and_input(msg.message()).unwrap();
+        assert_eq!(to, None);
+        assert_eq!(input, [0x60, 0x80]);
+
+        let other_method = delegated_message(
+            Address::ETHEREUM_ACCOUNT_MANAGER_ACTOR,
+            2,
+            RawBytes::default(),
+        );
+        assert!(eth_tx_hash(&other_method, 314).is_err());
+    }
+
+    #[test]
+    fn hashes_are_indexed() {
+        let db = MemoryDB::default();
+        let to = Address::new_delegated(10, &[2; 20]).unwrap();
+        let msg = delegated_message(to, METHOD_SEND, RawBytes::default());
+        let hash = eth_tx_hash(&msg, 314).unwrap();
+        assert_eq!(message_cid(&db, &hash).unwrap(), None);
+
+        index_tx_hashes(&db, 314, [&msg]).unwrap();
+        assert_eq!(message_cid(&db, &hash).unwrap(), Some(msg.cid().unwrap()));
+    }
+}
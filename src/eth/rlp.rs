@@ -0,0 +1,81 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Recursive Length Prefix encoding, see
+//! <https://ethereum.org/en/developers/docs/data-structures-and-encoding/rlp/>.
+
+/// An RLP item, either a byte string or a list of items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rlp {
+    Bytes(Vec<u8>),
+    List(Vec<Rlp>),
+}
+
+impl Rlp {
+    /// Big-endian unsigned integer, without leading zeros.
+    pub fn uint(be_bytes: &[u8]) -> Self {
+        Self::Bytes(strip_leading_zeros(be_bytes).to_vec())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Bytes(bytes) if bytes.len() == 1 && bytes[0] < 0x80 => bytes.clone(),
+            Self::Bytes(bytes) => with_prefix(0x80, bytes),
+            Self::List(items) => {
+                let payload: Vec<u8> = items.iter().flat_map(Rlp::encode).collect();
+                with_prefix(0xc0, &payload)
+            }
+        }
+    }
+}
+
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// Payloads longer than 55 bytes are prefixed with their length.
+fn with_prefix(offset: u8, payload: &[u8]) -> Vec<u8> {
+    let mut encoded = if payload.len() <= 55 {
+        vec![offset + payload.len() as u8]
+    } else {
+        let len = payload.len().to_be_bytes();
+        let len = strip_leading_zeros(&len);
+        let mut prefix = vec![offset + 55 + len.len() as u8];
+        prefix.extend_from_slice(len);
+        prefix
+    };
+    encoded.extend_from_slice(payload);
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(s: &str) -> Rlp {
+        Rlp::Bytes(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(bytes("dog").encode(), b"\x83dog");
+        assert_eq!(
+            Rlp::List(vec![bytes("cat"), bytes("dog")]).encode(),
+            b"\xc8\x83cat\x83dog"
+        );
+        assert_eq!(bytes("").encode(), [0x80]);
+        assert_eq!(Rlp::List(vec![]).encode(), [0xc0]);
+        assert_eq!(Rlp::uint(&0u64.to_be_bytes()).encode(), [0x80]);
+        assert_eq!(Rlp::uint(&15u64.to_be_bytes()).encode(), [0x0f]);
+        assert_eq!(
+            Rlp::uint(&1024u64.to_be_bytes()).encode(),
+            [0x82, 0x04, 0x00]
+        );
+
+        let lorem = "Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+        let mut expected = vec![0xb8, 0x38];
+        expected.extend_from_slice(lorem.as_bytes());
+        assert_eq!(bytes(lorem).encode(), expected);
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Storage of F3 finality certificates.
+//!
+//! Certificates are validated by the `go-f3` sidecar before it hands them to
+//! the node, so the store only checks that instances are contiguous. They are
+//! kept in the settings store under [`F3_CERT_KEY_PREFIX`], CBOR encoded.
+//!
+//! [`F3_CERT_KEY_PREFIX`]: crate::db::setting_keys::F3_CERT_KEY_PREFIX
+
+use std::sync::Arc;
+
+use crate::blocks::TipsetKeys;
+use crate::db::setting_keys::{F3_CERT_KEY_PREFIX, F3_LATEST_CERT_KEY};
+use crate::db::SettingsStore;
+use crate::shim::clock::ChainEpoch;
+use crate::shim::sector::StoragePower;
+use anyhow::{bail, Context as _};
+use cid::Cid;
+use serde::{Deserialize, Serialize};
+
+/// A tipset finalized by an F3 instance.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ECTipSet {
+    #[serde(with = "crate::lotus_json")]
+    pub key: TipsetKeys,
+    pub epoch: ChainEpoch,
+    /// CID of the power table used by the instance following this tipset.
+    #[serde(with = "crate::lotus_json")]
+    pub power_table: Cid,
+    #[serde(with = "crate::lotus_json::base64_standard")]
+    pub commitments: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SupplementalData {
+    #[serde(with = "crate::lotus_json::base64_standard")]
+    pub commitments: Vec<u8>,
+    #[serde(with = "crate::lotus_json")]
+    pub power_table: Cid,
+}
+
+/// Change of the power of one participant between two instances.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PowerTableDelta {
+    #[serde(rename = "ParticipantID")]
+    pub participant_id: u64,
+    #[serde(with = "crate::lotus_json::stringify")]
+    pub power_delta: StoragePower,
+    #[serde(with = "crate::lotus_json::base64_standard")]
+    pub signing_key: Vec<u8>,
+}
+
+/// Proof that a chain prefix was finalized by a GPBFT instance.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct FinalityCertificate {
+    #[serde(rename = "GPBFTInstance")]
+    pub gpbft_instance: u64,
+    /// The finalized tipsets, starting with the base that was already final.
+    #[serde(rename = "ECChain")]
+    pub ec_chain: Vec<ECTipSet>,
+    pub supplemental_data: SupplementalData,
+    /// Indices of the signers in the power table of the instance.
+    pub signers: Vec<u64>,
+    /// Aggregated BLS signature of the signers.
+    #[serde(with = "crate::lotus_json::base64_standard")]
+    pub signature: Vec<u8>,
+    pub power_table_delta: Vec<PowerTableDelta>,
+}
+
+crate::lotus_json::lotus_json_with_self!(FinalityCertificate);
+
+/// Persistent store of the finality certificates of consecutive instances.
+#[derive(Clone)]
+pub struct CertStore {
+    settings: Arc<dyn SettingsStore + Send + Sync>,
+}
+
+impl CertStore {
+    pub fn new(settings: Arc<dyn SettingsStore + Send + Sync>) -> Self {
+        Self { settings }
+    }
+
+    fn key(instance: u64) -> String {
+        format!("{F3_CERT_KEY_PREFIX}/{instance}")
+    }
+
+    /// Returns the certificate of an instance, if stored.
+    pub fn get(&self, instance: u64) -> anyhow::Result<Option<FinalityCertificate>> {
+        match self.settings.read_bin(&Self::key(instance))? {
+            Some(bytes) => Ok(Some(fvm_ipld_encoding::from_slice(&bytes).with_context(
+                || format!("corrupted finality certificate for instance {instance}"),
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the instance of the latest stored certificate.
+    pub fn latest_instance(&self) -> anyhow::Result<Option<u64>> {
+        match self.settings.read_bin(F3_LATEST_CERT_KEY)? {
+            Some(bytes) => Ok(Some(fvm_ipld_encoding::from_slice(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the latest stored certificate.
+    pub fn latest(&self) -> anyhow::Result<Option<FinalityCertificate>> {
+        match self.latest_instance()? {
+            Some(instance) => self.get(instance),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns up to `limit` consecutive certificates starting at `first`.
+    pub fn range(&self, first: u64, limit: u64) -> anyhow::Result<Vec<FinalityCertificate>> {
+        let mut certs = Vec::new();
+        for instance in first..first.saturating_add(limit) {
+            match self.get(instance)? {
+                Some(cert) => certs.push(cert),
+                None => break,
+            }
+        }
+        Ok(certs)
+    }
+
+    /// Stores the certificate of the instance following the latest one. The
+    /// first certificate stored can be of any instance. Storing a certificate
+    /// again is a no-op.
+    pub fn put(&self, cert: &FinalityCertificate) -> anyhow::Result<()> {
+        let instance = cert.gpbft_instance;
+        if let Some(latest) = self.latest_instance()? {
+            if instance <= latest {
+                if self.get(instance)?.as_ref() == Some(cert) {
+                    return Ok(());
+                }
+                bail!("a different certificate is already stored for instance {instance}");
+            }
+            if instance != latest + 1 {
+                bail!(
+                    "certificate for instance {instance} doesn't follow the latest one ({latest})"
+                );
+            }
+        }
+        self.settings
+            .write_bin(&Self::key(instance), &fvm_ipld_encoding::to_vec(cert)?)?;
+        self.settings
+            .write_bin(F3_LATEST_CERT_KEY, &fvm_ipld_encoding::to_vec(&instance)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+
+    fn cert(instance: u64) -> FinalityCertificate {
+        FinalityCertificate {
+            gpbft_instance: instance,
+            ec_chain: vec![ECTipSet {
+                key: TipsetKeys::default(),
+                epoch: instance as ChainEpoch * 10,
+                power_table: Cid::default(),
+                commitments: vec![0; 32],
+            }],
+            supplemental_data: SupplementalData {
+                commitments: vec![0; 32],
+                power_table: Cid::default(),
+            },
+            signers: vec![0, 2],
+            signature: vec![1, 2, 3],
+            power_table_delta: vec![PowerTableDelta {
+                participant_id: 1000,
+                power_delta: (-2048).into(),
+                signing_key: vec![],
+            }],
+        }
+    }
+
+    #[test]
+    fn certificates_are_stored_in_sequence() {
+        let store = CertStore::new(Arc::new(MemoryDB::default()));
+        assert!(store.latest().unwrap().is_none());
+
+        store.put(&cert(5)).unwrap();
+        store.put(&cert(6)).unwrap();
+        // Same certificate again
+        store.put(&cert(6)).unwrap();
+        // Gap
+        assert!(store.put(&cert(8)).is_err());
+        // Conflict
+        let mut other = cert(5);
+        other.signers.clear();
+        assert!(store.put(&other).is_err());
+
+        assert_eq!(store.latest().unwrap(), Some(cert(6)));
+        assert_eq!(store.get(5).unwrap(), Some(cert(5)));
+        assert_eq!(store.get(7).unwrap(), None);
+        assert_eq!(store.range(5, 10).unwrap(), vec![cert(5), cert(6)]);
+        assert_eq!(store.range(6, 0).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn certificate_json() {
+        let json = serde_json::to_value(cert(1)).unwrap();
+        assert_eq!(json["GPBFTInstance"], 1);
+        assert_eq!(json["ECChain"][0]["Epoch"], 10);
+        assert_eq!(json["PowerTableDelta"][0]["ParticipantID"], 1000);
+        assert_eq!(json["PowerTableDelta"][0]["PowerDelta"], "-2048");
+        assert_eq!(
+            serde_json::from_value::<FinalityCertificate>(json).unwrap(),
+            cert(1)
+        );
+    }
+}
@@ -0,0 +1,242 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Participation in F3, the fast finality protocol of Filecoin
+//! ([FIP-0086](https://github.com/filecoin-project/FIPs/blob/master/FIPS/fip-0086.md)).
+//!
+//! GPBFT itself runs in a sidecar process (`go-f3`). The node provides it with
+//! what only the node can compute:
+//! - the [`F3Manifest`] of the network,
+//! - the power table of a tipset, see [`power_table`],
+//! - signatures of GPBFT messages by the worker keys of the participating
+//!   miners, see [`sign`].
+//!
+//! Those are served over the `Filecoin.F3*` RPC methods. In return, the sidecar
+//! hands over the finality certificates it validated, which the node stores
+//! and serves to its peers, see [`certs`].
+
+pub mod certs;
+
+use crate::blocks::Tipset;
+use crate::interpreter::resolve_to_key_addr;
+use crate::key_management::{find_key, KeyStore};
+use crate::networks::ChainConfig;
+use crate::shim::{
+    address::{Address, Payload},
+    clock::ChainEpoch,
+    crypto::{Signature, SignatureType},
+    sector::StoragePower,
+    state_tree::StateTree,
+};
+use crate::state_manager::StateManager;
+use anyhow::{bail, Context as _};
+use fil_actor_interface::{miner, power};
+use fvm_ipld_blockstore::Blockstore;
+use num_traits::Signed as _;
+use serde::{Deserialize, Serialize};
+
+/// Number of epochs the power table lags behind the instance it is used for.
+const DEFAULT_COMMITTEE_LOOKBACK: u64 = 10;
+
+/// Parameters of the F3 instance run by a network, in the format of the
+/// `go-f3` manifest.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct F3Manifest {
+    pub network_name: String,
+    pub bootstrap_epoch: ChainEpoch,
+    pub initial_instance: u64,
+    pub committee_lookback: u64,
+    pub pause: bool,
+}
+
+crate::lotus_json::lotus_json_with_self!(F3Manifest);
+
+impl F3Manifest {
+    /// Returns the manifest of the network, or `None` if F3 is not enabled on
+    /// it.
+    pub fn new(chain_config: &ChainConfig, network_name: &str) -> Option<Self> {
+        if chain_config.f3_bootstrap_epoch < 0 {
+            return None;
+        }
+        Some(Self {
+            network_name: network_name.to_owned(),
+            bootstrap_epoch: chain_config.f3_bootstrap_epoch,
+            initial_instance: 0,
+            committee_lookback: DEFAULT_COMMITTEE_LOOKBACK,
+            pause: false,
+        })
+    }
+}
+
+/// A participant of F3 with its share of the network power.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PowerEntry {
+    /// Actor ID of the miner.
+    #[serde(rename = "ID")]
+    pub id: u64,
+    /// Quality adjusted power of the miner.
+    #[serde(with = "crate::lotus_json::stringify")]
+    pub power: StoragePower,
+    /// BLS public key of the miner's worker.
+    #[serde(with = "crate::lotus_json::base64_standard")]
+    pub pub_key: Vec<u8>,
+}
+
+crate::lotus_json::lotus_json_with_self!(PowerEntry);
+
+/// Derives the F3 power table from the parent state of `ts`: all miners that
+/// meet the consensus minimum power, aren't in a consensus fault and have a
+/// BLS worker key. Entries are sorted by decreasing power, then by ID.
+pub fn power_table<DB>(
+    state_manager: &StateManager<DB>,
+    ts: &Tipset,
+) -> anyhow::Result<Vec<PowerEntry>>
+where
+    DB: Blockstore,
+{
+    let db = state_manager.blockstore();
+    let policy = &state_manager.chain_config().policy;
+    let state = StateTree::new_from_root(state_manager.blockstore_owned(), ts.parent_state())?;
+    let power_actor = state
+        .get_actor(&Address::POWER_ACTOR)?
+        .context("power actor not found")?;
+    let power_state = power::State::load(db, power_actor.code, power_actor.state)?;
+
+    let mut entries = Vec::new();
+    for (address, power) in miner_claims(db, &power_state)? {
+        if !power.is_positive()
+            || !power_state.miner_nominal_power_meets_consensus_minimum(
+                policy,
+                db,
+                &address.into(),
+            )?
+        {
+            continue;
+        }
+        let actor = state
+            .get_actor(&address)?
+            .with_context(|| format!("miner actor {address} not found"))?;
+        let info = miner::State::load(db, actor.code, actor.state)?.info(db)?;
+        if ts.epoch() <= info.consensus_fault_elapsed {
+            continue;
+        }
+        let worker = resolve_to_key_addr(&state, db, &info.worker().into())?;
+        let Payload::BLS(pub_key) = worker.into_payload() else {
+            continue;
+        };
+        entries.push(PowerEntry {
+            id: address.id()?,
+            power,
+            pub_key: pub_key.to_vec(),
+        });
+    }
+    sort_power_entries(&mut entries);
+    Ok(entries)
+}
+
+/// Lists the miners that have a claim in the power actor, with their quality
+/// adjusted power.
+fn miner_claims<DB: Blockstore>(
+    db: &DB,
+    power_state: &power::State,
+) -> anyhow::Result<Vec<(Address, StoragePower)>> {
+    macro_rules! collect_claims {
+        ($state:expr, $version:ident) => {{
+            let claims = fil_actors_shared::$version::make_map_with_root_and_bitwidth::<
+                _,
+                fil_actor_power_state::$version::Claim,
+            >(
+                &$state.claims,
+                db,
+                fil_actors_shared::$version::builtin::HAMT_BIT_WIDTH,
+            )?;
+            let mut out = Vec::new();
+            claims.for_each(|key, claim| {
+                out.push((
+                    Address::from_bytes(&key.0)?,
+                    claim.quality_adj_power.clone(),
+                ));
+                Ok(())
+            })?;
+            out
+        }};
+    }
+    Ok(match power_state {
+        power::State::V8(st) => collect_claims!(st, v8),
+        power::State::V9(st) => collect_claims!(st, v9),
+        power::State::V10(st) => collect_claims!(st, v10),
+        power::State::V11(st) => collect_claims!(st, v11),
+        power::State::V12(st) => collect_claims!(st, v12),
+    })
+}
+
+fn sort_power_entries(entries: &mut [PowerEntry]) {
+    entries.sort_by(|a, b| b.power.cmp(&a.power).then(a.id.cmp(&b.id)));
+}
+
+/// Signs an F3 payload with the BLS key of `signer`, which must be in the
+/// keystore.
+pub fn sign(keystore: &KeyStore, signer: &Address, payload: &[u8]) -> anyhow::Result<Signature> {
+    let key = find_key(signer, keystore)
+        .with_context(|| format!("key {signer} is not in the keystore"))?;
+    if *key.key_info.key_type() != SignatureType::Bls {
+        bail!("F3 messages must be signed with a BLS key, {signer} is not one");
+    }
+    Ok(crate::key_management::sign(
+        SignatureType::Bls,
+        key.key_info.private_key(),
+        payload,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_management::{generate_key, KeyStoreConfig};
+    use crate::shim::crypto::verify_bls_sig;
+
+    #[test]
+    fn manifest_follows_chain_config() {
+        assert!(F3Manifest::new(&ChainConfig::mainnet(), "testnetnet").is_none());
+        let manifest = F3Manifest::new(&ChainConfig::devnet(), "localnet").unwrap();
+        assert_eq!(manifest.bootstrap_epoch, 1000);
+        let json = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(json["NetworkName"], "localnet");
+        assert_eq!(json["BootstrapEpoch"], 1000);
+    }
+
+    #[test]
+    fn power_entries_are_sorted_by_power_then_id() {
+        let entry = |id: u64, power: u64| PowerEntry {
+            id,
+            power: power.into(),
+            pub_key: vec![],
+        };
+        let mut entries = vec![entry(3, 10), entry(1, 20), entry(2, 10)];
+        sort_power_entries(&mut entries);
+        let ids: Vec<_> = entries.iter().map(|e| e.id).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        let json = serde_json::to_value(&entries[0]).unwrap();
+        assert_eq!(json["ID"], 1);
+        assert_eq!(json["Power"], "20");
+    }
+
+    #[test]
+    fn sign_requires_bls_key() {
+        let mut keystore = KeyStore::new(KeyStoreConfig::Memory).unwrap();
+        let bls = generate_key(SignatureType::Bls).unwrap();
+        let secp = generate_key(SignatureType::Secp256k1).unwrap();
+        for key in [&bls, &secp] {
+            keystore
+                .put(&format!("wallet-{}", key.address), key.key_info.clone())
+                .unwrap();
+        }
+
+        let sig = sign(&keystore, &bls.address, b"payload").unwrap();
+        verify_bls_sig(sig.bytes(), b"payload", &bls.address).unwrap();
+        assert!(sign(&keystore, &secp.address, b"payload").is_err());
+        assert!(sign(&keystore, &Address::new_id(1), b"payload").is_err());
+    }
+}
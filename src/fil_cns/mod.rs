@@ -13,9 +13,12 @@ use nonempty::NonEmpty;
 use thiserror::Error;
 
 mod metrics;
+mod proposer;
 mod validation;
 mod weight;
 
+pub use proposer::DevnetProposer;
+
 #[derive(Debug, Error)]
 pub enum FilecoinConsensusError {
     #[error("Block must have an election proof included in tipset")]
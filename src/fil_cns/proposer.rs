@@ -0,0 +1,413 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! A minimal block producer for local devnets.
+//!
+//! [`DevnetProposer`] mines on top of the heaviest tipset on behalf of a single
+//! miner actor whose worker key is present in the node keystore. Generating
+//! winning PoSt proofs is not supported, so blocks can only be produced on
+//! networks that run with mocked proofs (see [`ChainConfig::mock_proofs`]).
+//!
+//! [`ChainConfig::mock_proofs`]: crate::networks::ChainConfig::mock_proofs
+
+use std::{
+    borrow::Cow,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::blocks::{
+    Block, BlockHeader, ElectionProof, GossipBlock, Ticket, Tipset, TipsetKeys, VRFProof,
+};
+use crate::chain::{compute_base_fee, persist_objects, ChainStore};
+use crate::chain_sync::consensus::{MessagePoolApi, Proposer};
+use crate::chain_sync::TipsetValidator;
+use crate::key_management::{find_key, Key, KeyStore};
+use crate::libp2p::{NetworkMessage, Topic, PUBSUB_BLOCK_STR};
+use crate::message::SignedMessage;
+use crate::networks::Height;
+use crate::shim::{
+    address::Address,
+    clock::ChainEpoch,
+    crypto::{Signature, SignatureType, TICKET_RANDOMNESS_LOOKBACK},
+    message::Message,
+    sector::{PoStProof, RegisteredPoStProofV3},
+};
+use crate::state_manager::{chain_rand::draw_randomness, StateManager};
+use anyhow::{bail, Context as _};
+use async_trait::async_trait;
+use bls_signatures::Serialize as _;
+use fil_actors_shared::v10::runtime::DomainSeparationTag;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::to_vec;
+use tokio::{sync::RwLock, task::JoinSet};
+use tracing::{debug, info, warn};
+
+/// Proof bytes accepted in place of a winning PoSt on mock-proof networks.
+const MOCK_WINNING_POST: &[u8] = b"valid proof";
+
+/// Produces blocks for a single miner on a local devnet.
+pub struct DevnetProposer {
+    miner: Address,
+    keystore: Arc<RwLock<KeyStore>>,
+    network_name: String,
+    network_send: flume::Sender<NetworkMessage>,
+    tipset_sink: flume::Sender<Arc<Tipset>>,
+}
+
+impl DevnetProposer {
+    pub fn new(
+        miner: Address,
+        keystore: Arc<RwLock<KeyStore>>,
+        network_name: String,
+        network_send: flume::Sender<NetworkMessage>,
+        tipset_sink: flume::Sender<Arc<Tipset>>,
+    ) -> Self {
+        Self {
+            miner,
+            keystore,
+            network_name,
+            network_send,
+            tipset_sink,
+        }
+    }
+
+    async fn run<DB, MP>(
+        self,
+        state_manager: Arc<StateManager<DB>>,
+        mpool: Arc<MP>,
+    ) -> anyhow::Result<()>
+    where
+        DB: Blockstore + Sync + Send + 'static,
+        MP: MessagePoolApi + Sync + Send + 'static,
+    {
+        let block_delay = state_manager.chain_config().block_delay_secs as u64;
+        let mut last_attempt: Option<(TipsetKeys, ChainEpoch)> = None;
+        loop {
+            let base = state_manager.chain_store().heaviest_tipset();
+            let mut round = next_round(base.epoch(), base.min_timestamp(), block_delay, now());
+            // Never try the same round on the same base twice, move on to the
+            // next (null) round instead.
+            if let Some((key, last_round)) = &last_attempt {
+                if key == base.key() {
+                    round = round.max(last_round + 1);
+                }
+            }
+
+            let mine_at = round_timestamp(&base, round, block_delay);
+            if let Some(wait) = mine_at.checked_sub(now()) {
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+            }
+            if state_manager.chain_store().heaviest_tipset().key() != base.key() {
+                // A new head arrived while waiting, mine on top of it instead.
+                continue;
+            }
+            last_attempt = Some((base.key().clone(), round));
+
+            match self
+                .create_block(&state_manager, mpool.as_ref(), &base, round)
+                .await
+            {
+                Ok(Some(block)) => {
+                    info!(
+                        "Mined block {} at epoch {} with {} messages",
+                        block.cid(),
+                        round,
+                        block.bls_msgs().len() + block.secp_msgs().len()
+                    );
+                    self.publish(&state_manager, block).await?;
+                }
+                Ok(None) => debug!("Miner {} did not win round {round}", self.miner),
+                Err(e) => warn!("Failed to mine block at epoch {round}: {e:#}"),
+            }
+        }
+    }
+
+    /// Creates a signed block for `round` on top of `base`, or returns `None`
+    /// if the miner did not win the election.
+    async fn create_block<DB, MP>(
+        &self,
+        state_manager: &Arc<StateManager<DB>>,
+        mpool: &MP,
+        base: &Arc<Tipset>,
+        round: ChainEpoch,
+    ) -> anyhow::Result<Option<Block>>
+    where
+        DB: Blockstore + Sync + Send + 'static,
+        MP: MessagePoolApi,
+    {
+        let chain_store = state_manager.chain_store();
+        let chain_config = state_manager.chain_config();
+
+        let (lookback_tipset, lookback_state) = ChainStore::get_lookback_tipset_for_round(
+            chain_store.chain_index.clone(),
+            chain_config.clone(),
+            base.clone(),
+            round,
+        )?;
+        if !state_manager.eligible_to_mine(&self.miner, base, &lookback_tipset)? {
+            return Ok(None);
+        }
+
+        let worker = state_manager.get_miner_work_addr(lookback_state, &self.miner)?;
+        let key = find_key(&worker, &*self.keystore.read().await)
+            .with_context(|| format!("worker key {worker} is not in the keystore"))?;
+        if *key.key_info.key_type() != SignatureType::Bls {
+            bail!("worker key {worker} must be a BLS key");
+        }
+
+        let prev_beacon = chain_store.chain_index.latest_beacon_entry(base)?;
+        let beacon_entries = state_manager
+            .beacon_schedule()
+            .beacon_entries_for_block(
+                state_manager.get_network_version(round),
+                round,
+                base.epoch(),
+                &prev_beacon,
+            )
+            .await?;
+        let rbase = beacon_entries.last().unwrap_or(&prev_beacon);
+        let miner_address_buf = to_vec(&self.miner)?;
+
+        let election_rand = draw_randomness(
+            rbase.data(),
+            DomainSeparationTag::ElectionProofProduction as i64,
+            round,
+            &miner_address_buf,
+        )?;
+        let mut election_proof = ElectionProof {
+            win_count: 0,
+            vrfproof: VRFProof::new(sign_with(&key, &election_rand)?.bytes().to_vec()),
+        };
+        let (mpow, tpow) = state_manager
+            .get_power(&lookback_state, Some(&self.miner))?
+            .context("miner power not available")?;
+        election_proof.win_count =
+            election_proof.compute_win_count(&mpow.quality_adj_power, &tpow.quality_adj_power);
+        if election_proof.win_count < 1 {
+            return Ok(None);
+        }
+
+        let mut ticket_entropy = miner_address_buf;
+        if round > chain_config.epoch(Height::Smoke) {
+            let min_ticket = base.min_ticket().context("base tipset has no ticket")?;
+            ticket_entropy.extend_from_slice(min_ticket.vrfproof.as_bytes());
+        }
+        let ticket_rand = draw_randomness(
+            rbase.data(),
+            DomainSeparationTag::TicketProduction as i64,
+            round - TICKET_RANDOMNESS_LOOKBACK,
+            &ticket_entropy,
+        )?;
+        let ticket = Ticket::new(VRFProof::new(
+            sign_with(&key, &ticket_rand)?.bytes().to_vec(),
+        ));
+
+        let winning_post_proof = vec![PoStProof::new(
+            RegisteredPoStProofV3::StackedDRGWinning2KiBV1.into(),
+            MOCK_WINNING_POST.to_vec(),
+        )];
+
+        let (bls_signed, secp_messages): (Vec<SignedMessage>, Vec<SignedMessage>) = mpool
+            .select_signed(state_manager, base)?
+            .into_iter()
+            .map(Cow::into_owned)
+            .partition(SignedMessage::is_bls);
+        let bls_aggregate = aggregate_bls(&bls_signed)?;
+        let bls_messages: Vec<Message> = bls_signed.into_iter().map(|m| m.message).collect();
+
+        let (state_root, message_receipts) = state_manager.tipset_state(base).await?;
+        let messages = TipsetValidator::compute_msg_root(
+            chain_store.blockstore(),
+            &bls_messages,
+            &secp_messages,
+        )?;
+        let weight = super::weight(chain_store.blockstore(), base)?;
+        let parent_base_fee = compute_base_fee(
+            chain_store.blockstore(),
+            base,
+            chain_config.epoch(Height::Smoke),
+        )?;
+
+        let mut header = BlockHeader::builder()
+            .parents(base.key().clone())
+            .weight(weight)
+            .epoch(round)
+            .beacon_entries(beacon_entries)
+            .winning_post_proof(winning_post_proof)
+            .miner_address(self.miner)
+            .messages(messages)
+            .message_receipts(message_receipts)
+            .state_root(state_root)
+            .election_proof(Some(election_proof))
+            .timestamp(round_timestamp(
+                base,
+                round,
+                chain_config.block_delay_secs as u64,
+            ))
+            .ticket(Some(ticket))
+            .bls_aggregate(Some(bls_aggregate))
+            .parent_base_fee(parent_base_fee)
+            .build()?;
+        header.signature = Some(sign_with(&key, &header.to_signing_bytes())?);
+
+        Ok(Some(Block {
+            header,
+            bls_messages,
+            secp_messages,
+        }))
+    }
+
+    /// Stores the block, gossips it to peers and hands it to the local
+    /// synchronizer so that this node follows its own chain.
+    async fn publish<DB>(
+        &self,
+        state_manager: &StateManager<DB>,
+        block: Block,
+    ) -> anyhow::Result<()>
+    where
+        DB: Blockstore,
+    {
+        let db = state_manager.blockstore();
+        persist_objects(db, &[block.header()])?;
+        persist_objects(db, block.bls_msgs())?;
+        persist_objects(db, block.secp_msgs())?;
+
+        let gossip = GossipBlock {
+            header: block.header.clone(),
+            bls_messages: block
+                .bls_msgs()
+                .iter()
+                .map(Message::cid)
+                .collect::<Result<_, _>>()?,
+            secpk_messages: block
+                .secp_msgs()
+                .iter()
+                .map(SignedMessage::cid)
+                .collect::<Result<_, _>>()?,
+        };
+        self.network_send
+            .send_async(NetworkMessage::PubsubMessage {
+                topic: Topic::new(format!("{PUBSUB_BLOCK_STR}/{}", self.network_name)),
+                message: to_vec(&gossip)?,
+            })
+            .await
+            .context("network receiver dropped")?;
+        self.tipset_sink
+            .send_async(Arc::new(Tipset::from(block.header)))
+            .await
+            .context("tipset processor dropped")?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Proposer for DevnetProposer {
+    async fn spawn<DB, MP>(
+        self,
+        state_manager: Arc<StateManager<DB>>,
+        mpool: Arc<MP>,
+        services: &mut JoinSet<anyhow::Result<()>>,
+    ) -> anyhow::Result<()>
+    where
+        DB: Blockstore + Sync + Send + 'static,
+        MP: MessagePoolApi + Sync + Send + 'static,
+    {
+        if !state_manager.chain_config().mock_proofs {
+            bail!("block production is only supported on networks with mocked proofs");
+        }
+        info!("Producing blocks for miner {}", self.miner);
+        services.spawn(self.run(state_manager, mpool));
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Retrieved system time before UNIX epoch")
+        .as_secs()
+}
+
+/// Returns the first round on top of a tipset at `base_epoch` whose slot has
+/// not ended yet at time `now`. Rounds that were missed are null rounds.
+fn next_round(
+    base_epoch: ChainEpoch,
+    base_timestamp: u64,
+    block_delay: u64,
+    now: u64,
+) -> ChainEpoch {
+    let elapsed = now.saturating_sub(base_timestamp) / block_delay.max(1);
+    base_epoch + (elapsed as ChainEpoch).max(1)
+}
+
+/// The timestamp a block mined at `round` on top of `base` must carry.
+fn round_timestamp(base: &Tipset, round: ChainEpoch, block_delay: u64) -> u64 {
+    base.min_timestamp() + block_delay * (round - base.epoch()) as u64
+}
+
+fn sign_with(key: &Key, msg: &[u8]) -> anyhow::Result<Signature> {
+    Ok(crate::key_management::sign(
+        *key.key_info.key_type(),
+        key.key_info.private_key(),
+        msg,
+    )?)
+}
+
+/// Aggregates the signatures of the BLS messages included in a block. An
+/// empty block carries the aggregate of no signatures, the point at infinity.
+fn aggregate_bls(messages: &[SignedMessage]) -> anyhow::Result<Signature> {
+    if messages.is_empty() {
+        let mut infinity = vec![0; 96];
+        infinity[0] = 0xc0;
+        return Ok(Signature::new_bls(infinity));
+    }
+    let signatures = messages
+        .iter()
+        .map(|m| bls_signatures::Signature::from_bytes(m.signature().bytes()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Signature::new_bls(
+        bls_signatures::aggregate(&signatures)?.as_bytes(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shim::crypto::verify_bls_sig;
+
+    #[test]
+    fn next_round_skips_missed_slots() {
+        // Right after the base, the next epoch is mined.
+        assert_eq!(next_round(10, 1000, 30, 1000), 11);
+        assert_eq!(next_round(10, 1000, 30, 1029), 11);
+        assert_eq!(next_round(10, 1000, 30, 1030), 11);
+        // Slots that passed without a block become null rounds.
+        assert_eq!(next_round(10, 1000, 30, 1060), 12);
+        assert_eq!(next_round(10, 1000, 30, 1095), 13);
+        // A clock behind the base doesn't underflow.
+        assert_eq!(next_round(10, 1000, 30, 900), 11);
+    }
+
+    #[test]
+    fn vrf_signatures_verify_against_worker() {
+        let key = crate::key_management::generate_key(SignatureType::Bls).unwrap();
+        let rand = draw_randomness(
+            b"beacon",
+            DomainSeparationTag::TicketProduction as i64,
+            42,
+            &to_vec(&Address::new_id(1000)).unwrap(),
+        )
+        .unwrap();
+        let proof = sign_with(&key, &rand).unwrap();
+        verify_bls_sig(proof.bytes(), &rand, &key.address).unwrap();
+    }
+
+    #[test]
+    fn empty_bls_aggregate_is_infinity() {
+        let sig = aggregate_bls(&[]).unwrap();
+        assert_eq!(sig.signature_type(), SignatureType::Bls);
+        assert_eq!(sig.bytes().len(), 96);
+        assert_eq!(sig.bytes()[0], 0xc0);
+    }
+}
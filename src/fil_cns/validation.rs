@@ -157,18 +157,20 @@ pub(in crate::fil_cns) async fn validate_block<DB: Blockstore + Sync + Send + 's
     }));
 
     // Winning PoSt proof validation
-    let v_block = block.clone();
-    let v_prev_beacon = Arc::clone(&prev_beacon);
-    validations.push(tokio::task::spawn_blocking(move || {
-        verify_winning_post_proof::<_>(
-            &state_manager,
-            win_p_nv,
-            v_block.header(),
-            &v_prev_beacon,
-            &lookback_state,
-        )?;
-        Ok(())
-    }));
+    if !state_manager.chain_config().mock_proofs {
+        let v_block = block.clone();
+        let v_prev_beacon = Arc::clone(&prev_beacon);
+        validations.push(tokio::task::spawn_blocking(move || {
+            verify_winning_post_proof::<_>(
+                &state_manager,
+                win_p_nv,
+                v_block.header(),
+                &v_prev_beacon,
+                &lookback_state,
+            )?;
+            Ok(())
+        }));
+    }
 
     // Collect the errors from the async validations
     collect_errs(validations).await
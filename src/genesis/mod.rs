@@ -1,11 +1,18 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use crate::blocks::BlockHeader;
+use std::sync::Arc;
+
+use crate::blocks::{BlockHeader, Tipset, TxMeta};
+use crate::db::{setting_keys::GENESIS_KEY, SettingsStore};
+use crate::networks::{ChainConfig, NetworkChain};
+use crate::shim::{address::Address, state_tree::StateTree};
 use crate::state_manager::StateManager;
 use crate::utils::db::car_util::load_car;
-use anyhow::Context as _;
+use anyhow::{bail, ensure, Context as _};
+use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore as _;
 use tokio::{fs::File, io::AsyncBufRead, io::BufReader};
 use tracing::{debug, info};
 
@@ -18,19 +25,30 @@ pub async fn read_genesis_header<DB>(
     genesis_fp: Option<&String>,
     genesis_bytes: Option<&[u8]>,
     db: &DB,
+    chain_config: &ChainConfig,
 ) -> Result<BlockHeader, anyhow::Error>
 where
     DB: Blockstore,
 {
     let genesis = match genesis_fp {
         Some(path) => {
-            let file = File::open(path).await?;
+            let file = File::open(path)
+                .await
+                .with_context(|| format!("couldn't open genesis file {path}"))?;
             let reader = BufReader::new(file);
-            process_car(reader, db).await?
+            let genesis = process_car(reader, db)
+                .await
+                .with_context(|| format!("invalid genesis file {path}"))?;
+            validate_genesis(&genesis, db, chain_config)
+                .with_context(|| format!("invalid genesis file {path}"))?;
+            genesis
         }
         None => {
             debug!("No specified genesis in config. Using default genesis.");
-            let genesis_bytes = genesis_bytes.context("No default genesis.")?;
+            // Devnets are seeded locally, e.g. with the `devgen.car` written by
+            // `lotus daemon --lotus-make-genesis`.
+            let genesis_bytes = genesis_bytes
+                .context("No default genesis for this network. Pass one with --genesis.")?;
             process_car(genesis_bytes, db).await?
         }
     };
@@ -59,14 +77,165 @@ where
     BS: Blockstore,
 {
     // Load genesis state into the database and get the Cid
-    let header = load_car(db, reader).await?;
-    if header.roots.len() != 1 {
-        panic!("Invalid Genesis. Genesis Tipset must have only 1 Block.");
-    }
+    let header = load_car(db, reader)
+        .await
+        .context("not a readable CAR file")?;
+    ensure!(
+        header.roots.len() == 1,
+        "expected the genesis block as the only root, found {} roots",
+        header.roots.len()
+    );
 
-    let genesis_block = BlockHeader::load(db, header.roots[0])?.ok_or_else(|| {
-        anyhow::anyhow!("Could not find genesis block despite being loaded using a genesis file")
-    })?;
+    let genesis_block = BlockHeader::load(db, header.roots[0])
+        .context("the root is not a block header")?
+        .with_context(|| format!("the genesis block {} is missing", header.roots[0]))?;
 
     Ok(genesis_block)
 }
+
+/// Network name stored in the genesis state of the builtin networks.
+fn expected_network_name(network: &NetworkChain) -> Option<&'static str> {
+    match network {
+        NetworkChain::Mainnet => Some("testnetnet"),
+        NetworkChain::Calibnet => Some("calibrationnet"),
+        NetworkChain::Butterflynet => Some("butterflynet"),
+        NetworkChain::Devnet(_) => None,
+    }
+}
+
+/// Checks that a user-supplied genesis is a genesis block of `chain_config`
+/// with a complete state, so that a wrong file is reported at startup rather
+/// than when syncing.
+pub fn validate_genesis<DB: Blockstore>(
+    genesis: &BlockHeader,
+    db: &DB,
+    chain_config: &ChainConfig,
+) -> anyhow::Result<()> {
+    ensure!(
+        genesis.epoch() == 0,
+        "block {} is at epoch {}, not a genesis block. Snapshots are imported with --import-snapshot",
+        genesis.cid(),
+        genesis.epoch()
+    );
+    if let Some(expected) = &chain_config.genesis_cid {
+        ensure!(
+            genesis.cid().to_string() == *expected,
+            "genesis {} doesn't match the {} genesis {expected}. Check the --chain option",
+            genesis.cid(),
+            chain_config.network
+        );
+    }
+    db.get_cbor::<TxMeta>(genesis.messages())
+        .ok()
+        .flatten()
+        .context("the messages of the genesis block are missing")?;
+
+    let state_tree = StateTree::new_from_root(Arc::new(db), genesis.state_root())
+        .with_context(|| format!("couldn't load the genesis state {}", genesis.state_root()))?;
+    for (name, address) in [
+        ("system", Address::SYSTEM_ACTOR),
+        ("init", Address::INIT_ACTOR),
+        ("reward", Address::REWARD_ACTOR),
+        ("cron", Address::CRON_ACTOR),
+        ("power", Address::POWER_ACTOR),
+        ("market", Address::MARKET_ACTOR),
+        ("verified registry", Address::VERIFIED_REGISTRY_ACTOR),
+        ("burnt funds", Address::BURNT_FUNDS_ACTOR),
+    ] {
+        if state_tree.get_actor(&address)?.is_none() {
+            bail!("the {name} actor ({address}) is missing from the genesis state");
+        }
+    }
+
+    let init = state_tree
+        .get_actor(&Address::INIT_ACTOR)?
+        .context("missing init actor")?;
+    let network_name = fil_actor_interface::init::State::load(db, init.code, init.state)
+        .context("couldn't load the init actor state")?
+        .into_network_name();
+    if let Some(expected) = expected_network_name(&chain_config.network) {
+        ensure!(
+            network_name == expected,
+            "the genesis is for network {network_name}, not {}. Check the --chain option",
+            chain_config.network
+        );
+    }
+    Ok(())
+}
+
+/// Checks that the database was initialized with `genesis`, so that the chain
+/// data of different networks is never mixed. The genesis is recorded the
+/// first time.
+pub fn check_stored_genesis(
+    settings: &(impl SettingsStore + ?Sized),
+    genesis: &Cid,
+    network: &NetworkChain,
+) -> anyhow::Result<()> {
+    match settings.read_bin(GENESIS_KEY)? {
+        Some(bytes) => {
+            let stored = Cid::try_from(bytes).context("corrupted genesis CID in the database")?;
+            ensure!(
+                stored == *genesis,
+                "the database was initialized with genesis {stored}, but the {network} genesis \
+                 is {genesis}. Check the --chain and --genesis options, or use another data \
+                 directory. The database can be removed with `forest-tool db destroy`"
+            );
+            Ok(())
+        }
+        None => settings.write_bin(GENESIS_KEY, &genesis.to_bytes()),
+    }
+}
+
+/// Checks that the head of an imported snapshot descends from `genesis`.
+pub fn check_snapshot_genesis(
+    head: &Tipset,
+    db: impl Blockstore,
+    genesis: &BlockHeader,
+    network: &NetworkChain,
+) -> anyhow::Result<()> {
+    let snapshot_genesis = head
+        .genesis(db)
+        .context("couldn't find the genesis of the snapshot")?;
+    ensure!(
+        snapshot_genesis.cid() == genesis.cid(),
+        "the snapshot is of the chain with genesis {}, but the {network} genesis is {}. \
+         Check the --chain option and the snapshot",
+        snapshot_genesis.cid(),
+        genesis.cid()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+
+    #[tokio::test]
+    async fn reject_non_genesis() {
+        let db = MemoryDB::default();
+        let header = process_car(EXPORT_SR_40, &db).await.unwrap();
+        let err = validate_genesis(&header, &db, &ChainConfig::calibnet()).unwrap_err();
+        assert!(err.to_string().contains("not a genesis block"), "{err}");
+    }
+
+    #[test]
+    fn stored_genesis_must_match() {
+        let db = MemoryDB::default();
+        let network = NetworkChain::Calibnet;
+        let genesis = *crate::networks::calibnet::GENESIS_CID;
+        check_stored_genesis(&db, &genesis, &network).unwrap();
+        check_stored_genesis(&db, &genesis, &network).unwrap();
+        let err = check_stored_genesis(&db, &Cid::default(), &NetworkChain::Mainnet).unwrap_err();
+        assert!(
+            err.to_string().contains("initialized with genesis"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn reject_garbage() {
+        let db = MemoryDB::default();
+        assert!(process_car(&b"not a car"[..], &db).await.is_err());
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Lotus-style `ExecutionTrace`s, built from the [`ExecutionEvent`]s of an
+//! applied message: the tree of calls with their gas charges, subcalls and
+//! return data.
+
+use std::collections::VecDeque;
+
+use crate::{
+    lotus_json::LotusJson,
+    shim::{
+        address::Address,
+        error::ExitCode,
+        gas::GasCharge,
+        kernel::{ErrorNumber, SyscallError},
+        trace::{Call, CallReturn, ExecutionEvent},
+    },
+};
+use fvm_ipld_encoding::{ipld_block::IpldBlock, RawBytes};
+use itertools::Either;
+use serde_json::json;
+
+/// Returns the `ExecutionTrace` of the given events as JSON, or [`None`] if
+/// the message made no call, e.g. if it was not traced.
+pub fn json(events: Vec<ExecutionEvent>) -> anyhow::Result<Option<serde_json::Value>> {
+    Ok(parse_events(events)?.map(CallTree::json))
+}
+
+/// Construct a single [`CallTree`]s from a linear array of [`ExecutionEvent`](fvm3::trace::ExecutionEvent)s.
+///
+/// This function is so-called because it similar to the parse step in a traditional compiler:
+/// ```text
+/// text --lex-->     tokens     --parse-->   AST
+///               ExecutionEvent --parse--> CallTree
+/// ```
+///
+/// This function is notable in that [`GasCharge`](fvm3::gas::GasCharge)s which precede a [`CallTree`] at the root level
+/// are attributed to that node.
+///
+/// We call this "front loading", and is copied from [this (rather obscure) code in `filecoin-ffi`](https://github.com/filecoin-project/filecoin-ffi/blob/v1.23.0/rust/src/fvm/machine.rs#L209)
+///
+/// ```text
+/// GasCharge GasCharge Call GasCharge Call CallError CallReturn
+/// ────┬──── ────┬──── ─┬── ────┬──── ─┬── ───┬───── ────┬─────
+///     │         │      │       │      │      │          │
+///     │         │      │       │      └─(T)──┘          │
+///     │         │      └───────┴───(T)───┴──────────────┘
+///     └─────────┴──────────────────►│
+///     ("front loaded" GasCharges)   │
+///                                  (T)
+///
+/// (T): a CallTree node
+/// ```
+///
+/// Multiple call trees and trailing gas will be warned and ignored.
+/// If no call tree is found, returns [`Ok(None)`]
+fn parse_events(events: Vec<ExecutionEvent>) -> Result<Option<CallTree>, BuildCallTreeError> {
+    let mut events = VecDeque::from(events);
+    let mut front_load_me = vec![];
+    let mut call_trees = vec![];
+
+    // we don't use a `for` loop so we can pass events them to inner parsers
+    while let Some(event) = events.pop_front() {
+        match event {
+            ExecutionEvent::GasCharge(gc) => front_load_me.push(gc),
+            ExecutionEvent::Call(call) => call_trees.push(CallTree::parse(call, {
+                // if CallTree::parse took impl Iterator<Item = ExecutionEvent>
+                // the compiler would infinitely recurse trying to resolve
+                // &mut &mut &mut ..: Iterator
+                // so use a VecDeque instead
+                for gc in front_load_me.drain(..).rev() {
+                    events.push_front(ExecutionEvent::GasCharge(gc))
+                }
+                &mut events
+            })?),
+            ExecutionEvent::CallReturn(_)
+            | ExecutionEvent::CallAbort(_)
+            | ExecutionEvent::CallError(_) => return Err(BuildCallTreeError::UnexpectedReturn),
+            ExecutionEvent::Log(_ignored) => {}
+            ExecutionEvent::Unknown(u) => {
+                return Err(BuildCallTreeError::UnrecognisedEvent(Box::new(u)))
+            }
+        }
+    }
+
+    if !front_load_me.is_empty() {
+        tracing::warn!(
+            "vm tracing: ignoring {} trailing gas charges",
+            front_load_me.len()
+        );
+    }
+
+    match call_trees.len() {
+        0 => Ok(None),
+        1 => Ok(Some(call_trees.remove(0))),
+        many => {
+            tracing::warn!(
+                "vm tracing: ignoring {} call trees at the root level",
+                many - 1
+            );
+            Ok(Some(call_trees.remove(0)))
+        }
+    }
+}
+
+struct CallTree {
+    call: Call,
+    gas_charges: Vec<GasCharge>,
+    sub_calls: Vec<CallTree>,
+    r#return: CallTreeReturn,
+}
+
+impl CallTree {
+    fn json(self) -> serde_json::Value {
+        use fvm_shared3::error::ExitCode;
+
+        let Self {
+            call:
+                Call {
+                    from,
+                    to,
+                    method_num,
+                    params,
+                    value,
+                    gas_limit: _,
+                    read_only: _,
+                },
+            gas_charges,
+            sub_calls,
+            r#return,
+        } = self;
+
+        fn params_to_codec_and_data(params: Either<RawBytes, Option<IpldBlock>>) -> (u64, Vec<u8>) {
+            params
+                .map_either(
+                    // This is more of a guess than anything
+                    |raw_bytes| (fvm_ipld_encoding::IPLD_RAW, Vec::from(raw_bytes)),
+                    |maybe_ipld| {
+                        let IpldBlock { codec, data } = maybe_ipld.unwrap_or_default();
+                        (codec, data)
+                    },
+                )
+                .into_inner()
+        }
+
+        let (codec, data) = params_to_codec_and_data(params);
+        let (return_code, return_data, return_codec) = match r#return {
+            CallTreeReturn::Return(CallReturn { exit_code, data }) => {
+                let (codec, data) = params_to_codec_and_data(data);
+                (
+                    exit_code.map(|it| it.value()).unwrap_or_default(),
+                    data,
+                    codec,
+                )
+            }
+            CallTreeReturn::Abort(exit_code) => (exit_code.value(), vec![], 0),
+            CallTreeReturn::Error(SyscallError { message: _, number }) => {
+                // Ported from: https://github.com/filecoin-project/filecoin-ffi/blob/v1.23.0/rust/src/fvm/machine.rs#L440
+                let code = match number {
+                    ErrorNumber::InsufficientFunds => ExitCode::SYS_INSUFFICIENT_FUNDS.value(),
+                    ErrorNumber::NotFound => ExitCode::SYS_INVALID_RECEIVER.value(),
+                    _ => ExitCode::SYS_ASSERTION_FAILED.value(),
+                };
+                (code, vec![], 0)
+            }
+        };
+
+        json!({
+            "Msg": {
+                "From": LotusJson(Address::new_id(from)),
+                "To": LotusJson(to),
+                "Value": LotusJson(value),
+                "Method": LotusJson(method_num),
+                "Params": LotusJson(data),
+                "ParamsCodec": LotusJson(codec)
+            },
+            // "MsgRct" might suggest that this is the right place to use LotusJson<crate::shim::executor::Receipt>
+            // But this is actually different information - e.g "GasUsed" isn't shown by Lotus
+            // And contructing a Receipt requires RawBytes, which is _not_ the same as the IpldBlock in CallTreeReturn::Return
+            "MsgRct": {
+                "ExitCode": LotusJson(return_code),
+                "Return": LotusJson(return_data),
+                "ReturnCodec": LotusJson(return_codec),
+            },
+            "GasCharges": LotusJson(gas_charges.into_iter().map(gas_charge_json).collect::<Vec<_>>()),
+            "Subcalls": LotusJson(sub_calls.into_iter().map(Self::json).collect::<Vec<_>>())
+        })
+    }
+
+    /// ```text
+    ///    events: GasCharge Call CallError CallReturn ...
+    ///            ────┬──── ─┬── ───┬───── ────┬─────
+    ///                │      │      │          │
+    /// ┌──────┐       │      └─(T)──┘          │
+    /// │ Call ├───────┴───(T)───┴──────────────┘
+    /// └──────┘            |                   ▲
+    ///                     ▼                   │
+    ///              Returned CallTree          │
+    ///                                     parsing end
+    /// ```
+    fn parse(
+        call: Call,
+        events: &mut VecDeque<ExecutionEvent>,
+    ) -> Result<Self, BuildCallTreeError> {
+        let mut gas_charges = vec![];
+        let mut sub_calls = vec![];
+
+        // we don't use a for loop over `events` so we can pass them to recursive calls
+        while let Some(event) = events.pop_front() {
+            let found_return = match event {
+                ExecutionEvent::GasCharge(gc) => {
+                    gas_charges.push(gc);
+                    None
+                }
+                ExecutionEvent::Call(call) => {
+                    sub_calls.push(Self::parse(call, events)?);
+                    None
+                }
+                ExecutionEvent::CallReturn(ret) => Some(CallTreeReturn::Return(ret)),
+                ExecutionEvent::CallAbort(ab) => Some(CallTreeReturn::Abort(ab)),
+                ExecutionEvent::CallError(e) => Some(CallTreeReturn::Error(e)),
+                ExecutionEvent::Log(_ignored) => None,
+                // RUST: This should be caught at compile time with #[deny(non_exhaustive_omitted_patterns)]
+                //       So that BuildCallTreeError::UnrecognisedEvent is never constructed
+                //       But that lint is not yet stabilised: https://github.com/rust-lang/rust/issues/89554
+                ExecutionEvent::Unknown(u) => {
+                    return Err(BuildCallTreeError::UnrecognisedEvent(Box::new(u)))
+                }
+            };
+
+            // commonise the return branch
+            if let Some(r#return) = found_return {
+                return Ok(Self {
+                    call,
+                    gas_charges,
+                    sub_calls,
+                    r#return,
+                });
+            }
+        }
+
+        Err(BuildCallTreeError::NoReturn)
+    }
+}
+
+fn gas_charge_json(gc: GasCharge) -> serde_json::Value {
+    json!({
+        "Name": gc.name(),
+        // total gas
+        "tg": gc.total().round_up(),
+        "cg": gc.compute_gas().round_up(),
+        "sg": gc.other_gas().round_up(),
+        "tt": null,
+    })
+}
+
+enum CallTreeReturn {
+    Return(CallReturn),
+    Abort(ExitCode),
+    Error(SyscallError),
+}
+
+#[derive(Debug, thiserror::Error)]
+enum BuildCallTreeError {
+    #[error("every ExecutionEvent::Return | ExecutionEvent::CallError should be preceded by an ExecutionEvent::Call, but this one wasn't")]
+    UnexpectedReturn,
+    #[error("every ExecutionEvent::Call should have a corresponding ExecutionEvent::Return, but this one didn't")]
+    NoReturn,
+    #[error("unrecognised ExecutionEvent variant: {0:?}")]
+    UnrecognisedEvent(Box<dyn std::fmt::Debug + Send + Sync + 'static>),
+}
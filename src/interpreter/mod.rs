@@ -2,9 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 mod errors;
+pub mod execution_trace;
 mod fvm2;
 pub mod fvm3;
 mod fvm4;
+pub mod state_overrides;
+pub mod trace_sink;
 mod vm;
 
 use crate::shim::{
@@ -0,0 +1,118 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Overrides of the state of actors, to simulate calls as if their balance,
+//! nonce or state were different. The overridden state tree is only written to
+//! an [`OverlayBlockstore`], never to the database.
+
+use std::sync::Arc;
+
+use crate::db::MemoryDB;
+use crate::lotus_json::lotus_json_with_self;
+use crate::shim::{address::Address, econ::TokenAmount, state_tree::StateTree};
+use anyhow::{ensure, Context as _};
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use serde::{Deserialize, Serialize};
+
+/// Block store whose writes are kept in memory, on top of a read-only one.
+pub struct OverlayBlockstore<DB> {
+    base: DB,
+    overlay: MemoryDB,
+}
+
+impl<DB> OverlayBlockstore<DB> {
+    pub fn new(base: DB) -> Self {
+        Self {
+            base,
+            overlay: MemoryDB::default(),
+        }
+    }
+}
+
+impl<DB: Blockstore> Blockstore for OverlayBlockstore<DB> {
+    fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+        match self.overlay.get(k)? {
+            Some(block) => Ok(Some(block)),
+            None => self.base.get(k),
+        }
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+        self.overlay.put_keyed(k, block)
+    }
+
+    fn has(&self, k: &Cid) -> anyhow::Result<bool> {
+        Ok(self.overlay.has(k)? || self.base.has(k)?)
+    }
+}
+
+/// Replaces some fields of an existing actor, the others are kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ActorOverride {
+    #[serde(with = "crate::lotus_json")]
+    pub address: Address,
+    #[serde(with = "crate::lotus_json", default)]
+    pub balance: Option<TokenAmount>,
+    #[serde(default)]
+    pub nonce: Option<u64>,
+    /// Head of the actor state, which must be in the database.
+    #[serde(with = "crate::lotus_json", default)]
+    pub state: Option<Cid>,
+}
+
+lotus_json_with_self!(ActorOverride);
+
+/// Applies the overrides to the state tree at `state_root`, returns the root of
+/// the overridden tree.
+pub fn apply_state_overrides<DB: Blockstore>(
+    store: &Arc<OverlayBlockstore<DB>>,
+    state_root: Cid,
+    overrides: &[ActorOverride],
+) -> anyhow::Result<Cid> {
+    if overrides.is_empty() {
+        return Ok(state_root);
+    }
+    let mut state_tree = StateTree::new_from_root(Arc::clone(store), &state_root)?;
+    for actor_override in overrides {
+        let address = &actor_override.address;
+        let mut actor = state_tree
+            .get_actor(address)?
+            .with_context(|| format!("actor {address} not found"))?;
+        if let Some(balance) = &actor_override.balance {
+            actor.balance = balance.clone().into();
+        }
+        if let Some(nonce) = actor_override.nonce {
+            actor.sequence = nonce;
+        }
+        if let Some(state) = actor_override.state {
+            ensure!(
+                store.has(&state)?,
+                "state {state} of actor {address} not found"
+            );
+            actor.state = state;
+        }
+        state_tree.set_actor(address, actor)?;
+    }
+    state_tree.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::db::CborStoreExt;
+
+    #[test]
+    fn overlay_writes_stay_in_memory() {
+        let base = Arc::new(MemoryDB::default());
+        let in_base = base.put_cbor_default(&"in base").unwrap();
+
+        let store = OverlayBlockstore::new(Arc::clone(&base));
+        let in_overlay = store.put_cbor_default(&"in overlay").unwrap();
+
+        assert!(store.has(&in_base).unwrap());
+        assert!(store.has(&in_overlay).unwrap());
+        assert!(!base.has(&in_overlay).unwrap());
+    }
+}
@@ -0,0 +1,489 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Destinations for VM execution traces.
+//!
+//! Traces are handed to a [`TraceSink`] one message at a time, as soon as the
+//! message has been applied, so that replaying long ranges of the chain does
+//! not require keeping every trace in memory.
+
+#[cfg(test)]
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use crate::shim::address::Address;
+use crate::shim::clock::ChainEpoch;
+use crate::shim::trace::ExecutionEvent;
+use crate::utils::monitoring::otlp_tracer_provider;
+use anyhow::Context as _;
+use cid::Cid;
+use opentelemetry::trace::{
+    SpanBuilder, SpanContext, SpanId, Status, TraceContextExt as _, TraceFlags, TraceId,
+    TraceState, Tracer as _, TracerProvider as _,
+};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::{CalledAt, MessageCallbackCtx};
+
+/// Receives the execution trace of every applied message.
+///
+/// Messages of different tipsets may be applied in parallel, so sinks must be
+/// safe to share between threads.
+pub trait TraceSink: Send + Sync {
+    fn record(&self, trace: MessageTrace) -> anyhow::Result<()>;
+
+    /// Makes sure that all recorded traces have reached their destination.
+    fn flush(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Execution trace of a single message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MessageTrace {
+    /// Epoch of the tipset whose messages are applied.
+    pub epoch: ChainEpoch,
+    #[serde(with = "crate::lotus_json")]
+    pub cid: Cid,
+    /// One of `applied`, `reward` or `cron`.
+    pub kind: String,
+    pub exit_code: u32,
+    pub gas_used: u64,
+    pub events: Vec<TraceEvent>,
+}
+
+impl MessageTrace {
+    pub fn new(epoch: ChainEpoch, ctx: &MessageCallbackCtx) -> Self {
+        let receipt = ctx.apply_ret.msg_receipt();
+        MessageTrace {
+            epoch,
+            cid: ctx.cid,
+            kind: match ctx.at {
+                CalledAt::Applied => "applied",
+                CalledAt::Reward => "reward",
+                CalledAt::Cron => "cron",
+            }
+            .into(),
+            exit_code: receipt.exit_code().value(),
+            gas_used: receipt.gas_used(),
+            events: ctx
+                .apply_ret
+                .exec_trace()
+                .into_iter()
+                .filter_map(TraceEvent::from_event)
+                .collect(),
+        }
+    }
+}
+
+/// A serializable [`ExecutionEvent`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "Type", rename_all = "PascalCase")]
+pub enum TraceEvent {
+    GasCharge {
+        name: String,
+        milligas: u64,
+    },
+    Call {
+        from: u64,
+        to: String,
+        method: u64,
+        value: String,
+    },
+    Return {
+        exit_code: Option<u32>,
+    },
+    Abort {
+        exit_code: u32,
+    },
+    Error {
+        message: String,
+    },
+    Log {
+        message: String,
+    },
+}
+
+impl TraceEvent {
+    fn from_event(event: ExecutionEvent) -> Option<Self> {
+        Some(match event {
+            ExecutionEvent::GasCharge(charge) => TraceEvent::GasCharge {
+                name: charge.name().into(),
+                milligas: charge.total().as_milligas(),
+            },
+            ExecutionEvent::Call(call) => TraceEvent::Call {
+                from: call.from,
+                to: call.to.to_string(),
+                method: call.method_num,
+                value: call.value.to_string(),
+            },
+            ExecutionEvent::CallReturn(ret) => TraceEvent::Return {
+                exit_code: ret.exit_code.map(|code| code.value()),
+            },
+            ExecutionEvent::CallAbort(code) => TraceEvent::Abort {
+                exit_code: code.value(),
+            },
+            ExecutionEvent::CallError(e) => TraceEvent::Error {
+                message: e.to_string(),
+            },
+            ExecutionEvent::Log(message) => TraceEvent::Log { message },
+            ExecutionEvent::Unknown(_) => return None,
+        })
+    }
+}
+
+/// Writes one JSON object per message.
+pub struct JsonlTraceSink<W> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write> JsonlTraceSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+}
+
+impl JsonlTraceSink<BufWriter<File>> {
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("couldn't create trace file {}", path.display()))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write + Send> TraceSink for JsonlTraceSink<W> {
+    fn record(&self, trace: MessageTrace) -> anyhow::Result<()> {
+        let mut writer = self.writer.lock();
+        serde_json::to_writer(&mut *writer, &trace)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    fn flush(&self) -> anyhow::Result<()> {
+        Ok(self.writer.lock().flush()?)
+    }
+}
+
+/// Keeps the most recent traces in memory.
+#[cfg(test)]
+pub struct MemoryTraceSink {
+    capacity: usize,
+    traces: Mutex<VecDeque<MessageTrace>>,
+}
+
+#[cfg(test)]
+impl MemoryTraceSink {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            traces: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Returns the retained traces, oldest first.
+    pub fn traces(&self) -> Vec<MessageTrace> {
+        self.traces.lock().iter().cloned().collect()
+    }
+
+    /// Returns the retained traces of the message `cid`.
+    pub fn find(&self, cid: &Cid) -> Vec<MessageTrace> {
+        self.traces
+            .lock()
+            .iter()
+            .filter(|trace| trace.cid == *cid)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl TraceSink for MemoryTraceSink {
+    fn record(&self, trace: MessageTrace) -> anyhow::Result<()> {
+        let mut traces = self.traces.lock();
+        if traces.len() == self.capacity {
+            traces.pop_front();
+        }
+        if self.capacity > 0 {
+            traces.push_back(trace);
+        }
+        Ok(())
+    }
+}
+
+/// Exports every call in a trace as a span to an OpenTelemetry collector
+/// over OTLP. All calls of a message share a trace ID derived from the
+/// message CID.
+pub struct OtlpTraceSink {
+    tracer: Tracer,
+    provider: Option<TracerProvider>,
+    // The exporter runs on its own runtime, so that flushing doesn't depend
+    // on the runtime of the caller.
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl OtlpTraceSink {
+    /// `endpoint` is the gRPC endpoint of the collector, e.g.
+    /// `http://localhost:4317`.
+    pub fn new(endpoint: &str) -> anyhow::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let provider = {
+            let _guard = runtime.enter();
+            otlp_tracer_provider(endpoint, "forest")?
+        };
+        Ok(Self {
+            tracer: provider.tracer("forest.vm"),
+            provider: Some(provider),
+            runtime: Some(runtime),
+        })
+    }
+}
+
+impl TraceSink for OtlpTraceSink {
+    fn record(&self, trace: MessageTrace) -> anyhow::Result<()> {
+        let trace_id = otlp_trace_id(&trace.cid);
+        for CallSpan { builder, parent } in otlp_spans(&trace, SystemTime::now()) {
+            let parent = match parent {
+                Some(span_id) => Context::new().with_remote_span_context(SpanContext::new(
+                    trace_id,
+                    span_id,
+                    TraceFlags::SAMPLED,
+                    true,
+                    TraceState::default(),
+                )),
+                None => Context::new(),
+            };
+            // The span is exported when it is dropped.
+            self.tracer.build_with_context(builder, &parent);
+        }
+        Ok(())
+    }
+
+    /// Waits for the recorded spans to be exported.
+    fn flush(&self) -> anyhow::Result<()> {
+        if let Some(provider) = &self.provider {
+            for result in provider.force_flush() {
+                result?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for OtlpTraceSink {
+    fn drop(&mut self) {
+        // Exports the pending spans before the runtime goes away.
+        drop(self.provider.take());
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// A call of a message trace, as a span.
+struct CallSpan {
+    builder: SpanBuilder,
+    parent: Option<SpanId>,
+}
+
+// The trace ID of a message is the tail of its CID.
+fn otlp_trace_id(cid: &Cid) -> TraceId {
+    let cid = cid.to_bytes();
+    let tail = &cid[cid.len().saturating_sub(16)..];
+    let mut bytes = [0; 16];
+    bytes[16 - tail.len()..].copy_from_slice(tail);
+    TraceId::from_bytes(bytes)
+}
+
+// Converts the calls of a message trace to spans, in the order the calls
+// return. The trace carries no timing information, so all spans are stamped
+// with `now`.
+fn otlp_spans(trace: &MessageTrace, now: SystemTime) -> Vec<CallSpan> {
+    let trace_id = otlp_trace_id(&trace.cid);
+    let mut spans = vec![];
+    // Calls that haven't returned yet, with the gas charged to them.
+    let mut stack: Vec<(CallSpan, u64)> = vec![];
+    let mut next_id = 1u64;
+    let finish =
+        |spans: &mut Vec<CallSpan>, stack: &mut Vec<(CallSpan, u64)>, exit_code: Option<u32>| {
+            if let Some((mut span, milligas)) = stack.pop() {
+                let attributes = span.builder.attributes.get_or_insert_with(Vec::new);
+                attributes.push(KeyValue::new("gas.milligas", milligas as i64));
+                if let Some(exit_code) = exit_code {
+                    attributes.push(KeyValue::new("exit_code", i64::from(exit_code)));
+                    if exit_code != 0 {
+                        span.builder.status = Status::error(format!("exit code {exit_code}"));
+                    }
+                }
+                spans.push(span);
+            }
+        };
+    for event in &trace.events {
+        match event {
+            TraceEvent::Call {
+                from,
+                to,
+                method,
+                value,
+            } => {
+                let span_id = SpanId::from_bytes(next_id.to_be_bytes());
+                next_id += 1;
+                let builder = SpanBuilder::from_name(format!("{to}/{method}"))
+                    .with_trace_id(trace_id)
+                    .with_span_id(span_id)
+                    .with_start_time(now)
+                    .with_end_time(now)
+                    .with_attributes([
+                        KeyValue::new("epoch", trace.epoch),
+                        KeyValue::new("message", trace.cid.to_string()),
+                        KeyValue::new("from", Address::new_id(*from).to_string()),
+                        KeyValue::new("value", value.clone()),
+                    ]);
+                let parent = stack.last().and_then(|(parent, _)| parent.builder.span_id);
+                stack.push((CallSpan { builder, parent }, 0));
+            }
+            TraceEvent::GasCharge { milligas, .. } => {
+                if let Some((_, total)) = stack.last_mut() {
+                    *total += milligas;
+                }
+            }
+            TraceEvent::Return { exit_code } => finish(&mut spans, &mut stack, *exit_code),
+            TraceEvent::Abort { exit_code } => finish(&mut spans, &mut stack, Some(*exit_code)),
+            TraceEvent::Error { .. } => finish(&mut spans, &mut stack, None),
+            TraceEvent::Log { .. } => {}
+        }
+    }
+    while !stack.is_empty() {
+        finish(&mut spans, &mut stack, None);
+    }
+    spans
+}
+
+/// Configuration of a [`TraceSink`], in the form `jsonl:<path>` or
+/// `otlp:<endpoint>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceSinkConfig {
+    Jsonl(PathBuf),
+    Otlp(String),
+}
+
+impl FromStr for TraceSinkConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("jsonl", path)) if !path.is_empty() => Ok(Self::Jsonl(path.into())),
+            Some(("otlp", endpoint)) if !endpoint.is_empty() => Ok(Self::Otlp(endpoint.into())),
+            _ => anyhow::bail!("expected `jsonl:<path>` or `otlp:<endpoint>`, got `{s}`"),
+        }
+    }
+}
+
+impl TraceSinkConfig {
+    pub fn build(&self) -> anyhow::Result<Box<dyn TraceSink>> {
+        Ok(match self {
+            Self::Jsonl(path) => Box::new(JsonlTraceSink::create(path)?),
+            Self::Otlp(endpoint) => Box::new(OtlpTraceSink::new(endpoint)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(epoch: ChainEpoch) -> MessageTrace {
+        MessageTrace {
+            epoch,
+            cid: Cid::default(),
+            kind: "applied".into(),
+            exit_code: 0,
+            gas_used: 10,
+            events: vec![
+                TraceEvent::Call {
+                    from: 100,
+                    to: "f01".into(),
+                    method: 2,
+                    value: "0".into(),
+                },
+                TraceEvent::GasCharge {
+                    name: "OnMethodInvocation".into(),
+                    milligas: 10_000,
+                },
+                TraceEvent::Call {
+                    from: 1,
+                    to: "f02".into(),
+                    method: 3,
+                    value: "0".into(),
+                },
+                TraceEvent::Abort { exit_code: 16 },
+                TraceEvent::Return { exit_code: None },
+            ],
+        }
+    }
+
+    #[test]
+    fn jsonl_roundtrip() {
+        let sink = JsonlTraceSink::new(vec![]);
+        sink.record(trace(1)).unwrap();
+        sink.record(trace(2)).unwrap();
+        let written = sink.writer.into_inner();
+        let traces = String::from_utf8(written)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<MessageTrace>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(traces, vec![trace(1), trace(2)]);
+    }
+
+    #[test]
+    fn memory_sink_keeps_latest() {
+        let sink = MemoryTraceSink::new(2);
+        for epoch in 0..5 {
+            sink.record(trace(epoch)).unwrap();
+        }
+        let epochs = sink.traces().iter().map(|t| t.epoch).collect::<Vec<_>>();
+        assert_eq!(epochs, vec![3, 4]);
+        assert_eq!(sink.find(&Cid::default()).len(), 2);
+    }
+
+    #[test]
+    fn otlp_spans_follow_calls() {
+        let spans = otlp_spans(&trace(1), SystemTime::now());
+        assert_eq!(spans.len(), 2);
+        // The inner call finishes first.
+        assert_eq!(spans[0].builder.name, "f02/3");
+        assert_eq!(spans[0].parent, spans[1].builder.span_id);
+        assert!(matches!(spans[0].builder.status, Status::Error { .. }));
+        assert!(spans[1].parent.is_none());
+    }
+
+    #[test]
+    fn parse_config() {
+        assert_eq!(
+            "jsonl:/tmp/traces.jsonl"
+                .parse::<TraceSinkConfig>()
+                .unwrap(),
+            TraceSinkConfig::Jsonl("/tmp/traces.jsonl".into())
+        );
+        assert_eq!(
+            "otlp:http://localhost:4317"
+                .parse::<TraceSinkConfig>()
+                .unwrap(),
+            TraceSinkConfig::Otlp("http://localhost:4317".into())
+        );
+        assert!("stdout".parse::<TraceSinkConfig>().is_err());
+    }
+}
@@ -17,12 +17,11 @@ use crate::networks::{ChainConfig, NetworkChain};
 use crate::shim::{
     address::Address,
     econ::TokenAmount,
-    executor::{ApplyRet, Receipt},
+    executor::{ApplyRet, Receipt, StampedEvent},
     externs::{Rand, RandWrapper},
-    machine::MultiEngine,
+    machine::{FvmVersion, MultiEngine},
     message::{Message, Message_v3},
     state_tree::ActorState,
-    version::NetworkVersion,
 };
 use ahash::{HashMap, HashMapExt, HashSet};
 use anyhow::bail;
@@ -181,85 +180,89 @@ where
         enable_tracing: VMTrace,
     ) -> Result<Self, anyhow::Error> {
         let network_version = chain_config.network_version(epoch);
-        if network_version >= NetworkVersion::V21 {
-            let mut config = NetworkConfig_v4::new(network_version.into());
-            // ChainId defines the chain ID used in the Ethereum JSON-RPC endpoint.
-            config.chain_id((chain_config.eth_chain_id as u64).into());
-            if let NetworkChain::Devnet(_) = chain_config.network {
-                config.enable_actor_debugging();
-            }
+        match FvmVersion::for_network_version(network_version) {
+            FvmVersion::V4 => {
+                let mut config = NetworkConfig_v4::new(network_version.into());
+                // ChainId defines the chain ID used in the Ethereum JSON-RPC endpoint.
+                config.chain_id((chain_config.eth_chain_id as u64).into());
+                if let NetworkChain::Devnet(_) = chain_config.network {
+                    config.enable_actor_debugging();
+                }
 
-            let engine = multi_engine.v4.get(&config)?;
-            let mut context = config.for_epoch(epoch, timestamp, state_tree_root);
-            context.set_base_fee(base_fee.into());
-            context.set_circulating_supply(circ_supply.into());
-            context.tracing = enable_tracing.is_traced();
-
-            let fvm: ForestMachineV4<DB> = ForestMachineV4::new(
-                &context,
-                Arc::clone(&chain_index.db),
-                ForestExternsV4::new(
-                    RandWrapper::from(rand),
-                    heaviest_tipset,
-                    epoch,
-                    state_tree_root,
-                    chain_index,
-                    chain_config,
-                ),
-            )?;
-            let exec: ForestExecutorV4<DB> = DefaultExecutor_v4::new(engine, fvm)?;
-            Ok(VM::VM4(exec))
-        } else if network_version >= NetworkVersion::V18 {
-            let mut config = NetworkConfig_v3::new(network_version.into());
-            // ChainId defines the chain ID used in the Ethereum JSON-RPC endpoint.
-            config.chain_id((chain_config.eth_chain_id as u64).into());
-            if let NetworkChain::Devnet(_) = chain_config.network {
-                config.enable_actor_debugging();
+                let engine = multi_engine.v4.get(&config)?;
+                let mut context = config.for_epoch(epoch, timestamp, state_tree_root);
+                context.set_base_fee(base_fee.into());
+                context.set_circulating_supply(circ_supply.into());
+                context.tracing = enable_tracing.is_traced();
+
+                let fvm: ForestMachineV4<DB> = ForestMachineV4::new(
+                    &context,
+                    Arc::clone(&chain_index.db),
+                    ForestExternsV4::new(
+                        RandWrapper::from(rand),
+                        heaviest_tipset,
+                        epoch,
+                        state_tree_root,
+                        chain_index,
+                        chain_config,
+                    ),
+                )?;
+                let exec: ForestExecutorV4<DB> = DefaultExecutor_v4::new(engine, fvm)?;
+                Ok(VM::VM4(exec))
             }
+            FvmVersion::V3 => {
+                let mut config = NetworkConfig_v3::new(network_version.into());
+                // ChainId defines the chain ID used in the Ethereum JSON-RPC endpoint.
+                config.chain_id((chain_config.eth_chain_id as u64).into());
+                if let NetworkChain::Devnet(_) = chain_config.network {
+                    config.enable_actor_debugging();
+                }
 
-            let engine = multi_engine.v3.get(&config)?;
-            let mut context = config.for_epoch(epoch, timestamp, state_tree_root);
-            context.set_base_fee(base_fee.into());
-            context.set_circulating_supply(circ_supply.into());
-            context.tracing = enable_tracing.is_traced();
-
-            let fvm: ForestMachineV3<DB> = ForestMachineV3::new(
-                &context,
-                Arc::clone(&chain_index.db),
-                ForestExternsV3::new(
-                    RandWrapper::from(rand),
-                    heaviest_tipset,
-                    epoch,
-                    state_tree_root,
-                    chain_index,
-                    chain_config,
-                ),
-            )?;
-            let exec: ForestExecutorV3<DB> = DefaultExecutor_v3::new(engine, fvm)?;
-            Ok(VM::VM3(exec))
-        } else {
-            let config = NetworkConfig_v2::new(network_version.into());
-            let engine = multi_engine.v2.get(&config)?;
-            let mut context = config.for_epoch(epoch, state_tree_root);
-            context.set_base_fee(base_fee.into());
-            context.set_circulating_supply(circ_supply.into());
-            context.tracing = enable_tracing.is_traced();
-
-            let fvm: ForestMachineV2<DB> = ForestMachineV2::new(
-                &engine,
-                &context,
-                Arc::clone(&chain_index.db),
-                ForestExternsV2::new(
-                    RandWrapper::from(rand),
-                    heaviest_tipset,
-                    epoch,
-                    state_tree_root,
-                    chain_index,
-                    chain_config,
-                ),
-            )?;
-            let exec: ForestExecutorV2<DB> = DefaultExecutor_v2::new(fvm);
-            Ok(VM::VM2(exec))
+                let engine = multi_engine.v3.get(&config)?;
+                let mut context = config.for_epoch(epoch, timestamp, state_tree_root);
+                context.set_base_fee(base_fee.into());
+                context.set_circulating_supply(circ_supply.into());
+                context.tracing = enable_tracing.is_traced();
+
+                let fvm: ForestMachineV3<DB> = ForestMachineV3::new(
+                    &context,
+                    Arc::clone(&chain_index.db),
+                    ForestExternsV3::new(
+                        RandWrapper::from(rand),
+                        heaviest_tipset,
+                        epoch,
+                        state_tree_root,
+                        chain_index,
+                        chain_config,
+                    ),
+                )?;
+                let exec: ForestExecutorV3<DB> = DefaultExecutor_v3::new(engine, fvm)?;
+                Ok(VM::VM3(exec))
+            }
+            FvmVersion::V2 => {
+                let config = NetworkConfig_v2::new(network_version.into());
+                let engine = multi_engine.v2.get(&config)?;
+                let mut context = config.for_epoch(epoch, state_tree_root);
+                context.set_base_fee(base_fee.into());
+                context.set_circulating_supply(circ_supply.into());
+                context.tracing = enable_tracing.is_traced();
+
+                let fvm: ForestMachineV2<DB> = ForestMachineV2::new(
+                    &engine,
+                    &context,
+                    Arc::clone(&chain_index.db),
+                    ForestExternsV2::new(
+                        RandWrapper::from(rand),
+                        heaviest_tipset,
+                        epoch,
+                        state_tree_root,
+                        chain_index,
+                        chain_config,
+                    ),
+                )?;
+                let exec: ForestExecutorV2<DB> = DefaultExecutor_v2::new(fvm);
+                Ok(VM::VM2(exec))
+            }
         }
     }
 
@@ -340,7 +343,7 @@ where
     }
 
     /// Apply block messages from a Tipset.
-    /// Returns the receipts from the transactions.
+    /// Returns the receipts from the transactions, and the events they emitted.
     pub fn apply_block_messages(
         &mut self,
         messages: &[BlockMessages],
@@ -348,8 +351,9 @@ where
         // note: we take &MessageCallbackCtx rather than MessageCallbackCtx<'_>
         //       because I'm not smart enough to make the second one work
         mut callback: Option<impl FnMut(&MessageCallbackCtx) -> anyhow::Result<()>>,
-    ) -> Result<Vec<Receipt>, anyhow::Error> {
+    ) -> Result<(Vec<Receipt>, Vec<Vec<StampedEvent>>), anyhow::Error> {
         let mut receipts = Vec::new();
+        let mut events = Vec::new();
         let mut processed = HashSet::<Cid>::default();
 
         for block in messages.iter() {
@@ -378,6 +382,7 @@ where
                 penalty += ret.penalty();
                 let msg_receipt = ret.msg_receipt();
                 receipts.push(msg_receipt.clone());
+                events.push(ret.events());
 
                 // Add processed Cid to set of processed messages
                 processed.insert(cid);
@@ -389,25 +394,9 @@ where
             }
 
             // Generate reward transaction for the miner of the block
-            if let Some(rew_msg) =
-                self.reward_message(epoch, block.miner, block.win_count, penalty, gas_reward)?
+            if let Some((rew_msg, ret)) =
+                self.apply_block_reward(epoch, block, penalty, gas_reward)?
             {
-                let ret = self.apply_implicit_message(&rew_msg)?;
-                if let Some(err) = ret.failure_info() {
-                    anyhow::bail!(
-                        "failed to apply reward message for miner {}: {}",
-                        block.miner,
-                        err
-                    );
-                }
-                // This is more of a sanity check, this should not be able to be hit.
-                if !ret.msg_receipt().exit_code().is_success() {
-                    anyhow::bail!(
-                        "reward application message failed (exit: {:?})",
-                        ret.msg_receipt().exit_code()
-                    );
-                }
-
                 if let Some(callback) = &mut callback {
                     callback(&MessageCallbackCtx {
                         cid: rew_msg.cid()?,
@@ -423,7 +412,48 @@ where
             tracing::error!("End of epoch cron failed to run: {}", e);
         }
 
-        Ok(receipts)
+        Ok((receipts, events))
+    }
+
+    /// Applies the messages of a tipset, along with the block rewards, until
+    /// the message matching `target` is reached, and returns its result
+    /// together with the state roots right before and after it. End of epoch
+    /// cron is not run.
+    pub fn apply_block_messages_until(
+        &mut self,
+        messages: &[BlockMessages],
+        epoch: ChainEpoch,
+        target: impl Fn(&ChainMessage) -> bool,
+    ) -> anyhow::Result<ReplayedMessage> {
+        let mut processed = HashSet::<Cid>::default();
+
+        for block in messages.iter() {
+            let mut penalty = TokenAmount::zero();
+            let mut gas_reward = TokenAmount::zero();
+
+            for message in block.messages.iter() {
+                if !processed.insert(message.cid()?) {
+                    continue;
+                }
+                if target(message) {
+                    let pre_state = self.flush()?;
+                    let apply_ret = self.apply_message(message)?;
+                    let post_state = self.flush()?;
+                    return Ok(ReplayedMessage {
+                        message: message.clone(),
+                        apply_ret,
+                        pre_state,
+                        post_state,
+                    });
+                }
+                let ret = self.apply_message(message)?;
+                gas_reward += ret.miner_tip();
+                penalty += ret.penalty();
+            }
+
+            self.apply_block_reward(epoch, block, penalty, gas_reward)?;
+        }
+        bail!("message not found in the tipset")
     }
 
     /// Applies single message through VM and returns result from execution.
@@ -519,6 +549,37 @@ where
         Ok(ret)
     }
 
+    /// Pays the reward of a block to its miner.
+    fn apply_block_reward(
+        &mut self,
+        epoch: ChainEpoch,
+        block: &BlockMessages,
+        penalty: TokenAmount,
+        gas_reward: TokenAmount,
+    ) -> anyhow::Result<Option<(Message, ApplyRet)>> {
+        let Some(rew_msg) =
+            self.reward_message(epoch, block.miner, block.win_count, penalty, gas_reward)?
+        else {
+            return Ok(None);
+        };
+        let ret = self.apply_implicit_message(&rew_msg)?;
+        if let Some(err) = ret.failure_info() {
+            anyhow::bail!(
+                "failed to apply reward message for miner {}: {}",
+                block.miner,
+                err
+            );
+        }
+        // This is more of a sanity check, this should not be able to be hit.
+        if !ret.msg_receipt().exit_code().is_success() {
+            anyhow::bail!(
+                "reward application message failed (exit: {:?})",
+                ret.msg_receipt().exit_code()
+            );
+        }
+        Ok(Some((rew_msg, ret)))
+    }
+
     fn reward_message(
         &self,
         epoch: ChainEpoch,
@@ -550,6 +611,16 @@ where
     }
 }
 
+/// A message re-executed by [`VM::apply_block_messages_until`].
+pub struct ReplayedMessage {
+    pub message: ChainMessage,
+    pub apply_ret: ApplyRet,
+    /// State root right before the message was applied.
+    pub pre_state: Cid,
+    /// State root right after the message was applied.
+    pub post_state: Cid,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct MessageCallbackCtx<'a> {
     pub cid: Cid,
@@ -286,6 +286,7 @@ pin_project! {
         seen: CidHashSet,
         stateroot_limit: ChainEpoch,
         fail_on_dead_links: bool,
+        include_messages: bool,
     }
 }
 
@@ -294,6 +295,15 @@ impl<DB, T> ChainStream<DB, T> {
         ChainStream { seen, ..self }
     }
 
+    /// Skip the messages of the streamed tipsets, leaving only block headers
+    /// and state trees.
+    pub fn without_messages(self) -> Self {
+        ChainStream {
+            include_messages: false,
+            ..self
+        }
+    }
+
     #[allow(dead_code)]
     pub fn into_seen(self) -> CidHashSet {
         self.seen
@@ -323,6 +333,7 @@ pub fn stream_chain<DB: Blockstore, T: Iterator<Item = Tipset> + Unpin>(
         seen: CidHashSet::default(),
         stateroot_limit,
         fail_on_dead_links: true,
+        include_messages: true,
     }
 }
 
@@ -340,6 +351,7 @@ pub fn stream_graph<DB: Blockstore, T: Iterator<Item = Tipset> + Unpin>(
         seen: CidHashSet::default(),
         stateroot_limit,
         fail_on_dead_links: false,
+        include_messages: true,
     }
 }
 
@@ -358,6 +370,7 @@ impl<DB: Blockstore, T: Iterator<Item = Tipset> + Unpin> Stream for ChainStream<
         };
 
         let stateroot_limit = *this.stateroot_limit;
+        let include_messages = *this.include_messages;
         loop {
             while let Some(task) = this.dfs.front_mut() {
                 match task {
@@ -418,7 +431,7 @@ impl<DB: Blockstore, T: Iterator<Item = Tipset> + Unpin> Stream for ChainStream<
                         }
 
                         // Process block messages.
-                        if block.epoch() > stateroot_limit {
+                        if include_messages && block.epoch() > stateroot_limit {
                             this.dfs.push_back(Iterate(
                                 DfsIter::from(*block.messages())
                                     .filter_map(ipld_to_cid)
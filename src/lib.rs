@@ -41,6 +41,8 @@ mod cli_shared;
 mod daemon;
 mod db;
 mod documentation;
+mod eth;
+mod f3;
 mod fil_cns;
 mod genesis;
 mod interpreter;
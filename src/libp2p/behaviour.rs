@@ -1,29 +1,36 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use crate::libp2p_bitswap::BitswapBehaviour;
+use std::time::Duration;
+
+use crate::libp2p_bitswap::{BitswapBehaviour, BitswapServerConfig};
 use crate::utils::{encoding::blake2b_256, version::FOREST_VERSION_STRING};
 use ahash::{HashMap, HashSet};
+use futures::channel::oneshot::Sender as OneShotSender;
 use libp2p::{
-    allow_block_list, connection_limits,
+    allow_block_list, autonat, connection_limits, dcutr,
     gossipsub::{
-        self, IdentTopic as Topic, MessageAuthenticity, MessageId, PublishError, SubscriptionError,
-        ValidationMode,
+        self, IdentTopic as Topic, MessageAcceptance, MessageAuthenticity, MessageId, PublishError,
+        SubscriptionError, ValidationMode,
     },
     identity::{Keypair, PeerId},
     kad::QueryId,
+    memory_connection_limits,
     metrics::{Metrics, Recorder},
-    ping,
-    swarm::NetworkBehaviour,
+    ping, relay,
+    swarm::{behaviour::toggle::Toggle, NetworkBehaviour},
     Multiaddr,
 };
 use tracing::{info, warn};
 
 use crate::libp2p::{
+    certexchange::CertExchangeBehaviour,
     chain_exchange::ChainExchangeBehaviour,
     config::Libp2pConfig,
-    discovery::{DiscoveryBehaviour, DiscoveryConfig},
-    gossip_params::{build_peer_score_params, build_peer_score_threshold},
+    discovery::{DiscoveryBehaviour, DiscoveryConfig, PeerDetails},
+    gossip_params::{
+        build_peer_score_params, build_peer_score_threshold, BOOTSTRAP_PEER_APP_SCORE,
+    },
     hello::HelloBehaviour,
 };
 
@@ -37,9 +44,13 @@ pub(in crate::libp2p) struct ForestBehaviour {
     discovery: DiscoveryBehaviour,
     ping: ping::Behaviour,
     connection_limits: connection_limits::Behaviour,
+    memory_connection_limits: Toggle<memory_connection_limits::Behaviour>,
+    relay: relay::client::Behaviour,
+    dcutr: dcutr::Behaviour,
     pub(super) blocked_peers: allow_block_list::Behaviour<allow_block_list::BlockedPeers>,
     pub(super) hello: HelloBehaviour,
     pub(super) chain_exchange: ChainExchangeBehaviour,
+    pub(super) cert_exchange: CertExchangeBehaviour,
     pub(super) bitswap: BitswapBehaviour,
 }
 
@@ -48,6 +59,7 @@ impl Recorder<ForestBehaviourEvent> for Metrics {
         match event {
             ForestBehaviourEvent::Gossipsub(e) => self.record(e),
             ForestBehaviourEvent::Ping(ping_event) => self.record(ping_event),
+            ForestBehaviourEvent::Dcutr(e) => self.record(e),
             ForestBehaviourEvent::Discovery(DiscoveryEvent::Discovery(e)) => match e.as_ref() {
                 DerivedDiscoveryBehaviourEvent::Identify(e) => self.record(e),
                 DerivedDiscoveryBehaviourEvent::Kademlia(e) => self.record(e),
@@ -63,10 +75,14 @@ impl ForestBehaviour {
         local_key: &Keypair,
         config: &Libp2pConfig,
         network_name: &str,
+        relay: relay::client::Behaviour,
     ) -> anyhow::Result<Self> {
         let mut gs_config_builder = gossipsub::ConfigBuilder::default();
         gs_config_builder.max_transmit_size(1 << 20);
         gs_config_builder.validation_mode(ValidationMode::Strict);
+        // Messages are only forwarded once reported valid, see
+        // `report_message_validation_result`
+        gs_config_builder.validate_messages();
         gs_config_builder.message_id_fn(|msg: &gossipsub::Message| {
             let s = blake2b_256(&msg.data);
             MessageId::from(s)
@@ -94,7 +110,12 @@ impl ForestBehaviour {
                 "/chain/ipfs/bitswap",
             ],
             Default::default(),
-        );
+        )
+        .with_server_config(BitswapServerConfig {
+            enabled: config.bitswap_server,
+            max_requests_per_peer: config.bitswap_max_requests_per_peer,
+            max_served_bytes: config.bitswap_max_served_bytes,
+        });
         if let Err(err) = crate::libp2p_bitswap::register_metrics(prometheus::default_registry()) {
             warn!("Fail to register prometheus metrics for libp2p_bitswap: {err}");
         }
@@ -102,7 +123,15 @@ impl ForestBehaviour {
         let discovery = DiscoveryConfig::new(local_key.public(), network_name)
             .with_mdns(config.mdns)
             .with_kademlia(config.kademlia)
+            .with_kademlia_mode(config.kademlia_mode)
+            .with_kademlia_parallelism(config.kademlia_parallelism)
+            .with_kademlia_bootstrap_interval(config.kademlia_bootstrap_interval)
+            .with_announce_addresses(
+                config.announce_addresses.clone(),
+                config.no_announce_addresses.clone(),
+            )
             .with_user_defined(config.bootstrap_peers.clone())?
+            .with_static_peers(config.static_peers.clone())?
             .target_peer_count(config.target_peer_count as u64)
             .finish()?;
 
@@ -110,10 +139,13 @@ impl ForestBehaviour {
             connection_limits::ConnectionLimits::default()
                 .with_max_pending_incoming(Some(4096))
                 .with_max_pending_outgoing(Some(8192))
-                .with_max_established_incoming(Some(8192))
-                .with_max_established_outgoing(Some(8192))
-                .with_max_established_per_peer(Some(5)),
+                .with_max_established_incoming(Some(config.max_inbound_connections))
+                .with_max_established_outgoing(Some(config.max_outbound_connections))
+                .with_max_established_per_peer(Some(config.max_connections_per_peer)),
         );
+        let memory_connection_limits = config.max_memory_bytes.map(|max_bytes| {
+            memory_connection_limits::Behaviour::with_max_bytes(max_bytes as usize)
+        });
 
         info!("libp2p Forest version: {}", FOREST_VERSION_STRING.as_str());
         Ok(ForestBehaviour {
@@ -121,10 +153,14 @@ impl ForestBehaviour {
             discovery,
             ping: Default::default(),
             connection_limits,
+            memory_connection_limits: memory_connection_limits.into(),
+            relay,
+            dcutr: dcutr::Behaviour::new(local_key.public().to_peer_id()),
             blocked_peers: Default::default(),
             bitswap,
             hello: HelloBehaviour::default(),
             chain_exchange: ChainExchangeBehaviour::default(),
+            cert_exchange: CertExchangeBehaviour::new(network_name),
         })
     }
 
@@ -142,11 +178,76 @@ impl ForestBehaviour {
         self.gossipsub.publish(topic, data)
     }
 
+    /// Reports whether a gossip message could be decoded. Peers sending
+    /// invalid messages are penalized by peer scoring.
+    pub fn report_message_validation_result(
+        &mut self,
+        message_id: &MessageId,
+        source: &PeerId,
+        acceptance: MessageAcceptance,
+    ) {
+        if let Err(e) = self
+            .gossipsub
+            .report_message_validation_result(message_id, source, acceptance)
+        {
+            warn!("Failed to report gossip message validation result: {e}");
+        }
+    }
+
+    /// Gives a high application score to bootstrap and static peers once
+    /// connected.
+    pub fn on_peer_connected(&mut self, peer_id: &PeerId) {
+        if self.discovery.is_seed_peer(peer_id) || self.discovery.is_static_peer(peer_id) {
+            self.gossipsub
+                .set_application_score(peer_id, BOOTSTRAP_PEER_APP_SCORE);
+        }
+    }
+
+    /// Returns the gossip scores of the connected peers.
+    pub fn peer_scores(&self) -> Vec<(PeerId, f64)> {
+        self.gossipsub
+            .all_peers()
+            .filter_map(|(peer_id, _)| Some((*peer_id, self.gossipsub.peer_score(peer_id)?)))
+            .collect()
+    }
+
+    /// Returns the number of connected peers subscribed to each gossip topic.
+    pub fn topic_peer_counts(&self) -> HashMap<String, usize> {
+        let mut counts = HashMap::default();
+        for (_, topics) in self.gossipsub.all_peers() {
+            for topic in topics {
+                *counts.entry(topic.to_string()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Returns the number of connected peers supporting each protocol, as
+    /// advertised over `identify`.
+    pub fn protocol_peer_counts(&self) -> HashMap<String, usize> {
+        let mut counts = HashMap::default();
+        for peer_id in self.peers() {
+            for protocol in self
+                .peer_details(peer_id)
+                .into_iter()
+                .flat_map(|details| &details.protocols)
+            {
+                *counts.entry(protocol.to_string()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
     /// Subscribe to a gossip topic.
     pub fn subscribe(&mut self, topic: &Topic) -> Result<bool, SubscriptionError> {
         self.gossipsub.subscribe(topic)
     }
 
+    /// Returns the reachability of the node from the public internet
+    pub fn nat_status(&self) -> autonat::NatStatus {
+        self.discovery.nat_status()
+    }
+
     /// Returns a set of peer ids
     pub fn peers(&self) -> &HashSet<PeerId> {
         self.discovery.peers()
@@ -156,4 +257,28 @@ impl ForestBehaviour {
     pub fn peer_addresses(&mut self) -> &HashMap<PeerId, HashSet<Multiaddr>> {
         self.discovery.peer_addresses()
     }
+
+    /// Looks up the addresses of a peer, see [`DiscoveryBehaviour::find_peer`]
+    pub fn find_peer(
+        &mut self,
+        peer_id: PeerId,
+        response_channel: OneShotSender<Option<HashSet<Multiaddr>>>,
+    ) {
+        self.discovery.find_peer(peer_id, response_channel)
+    }
+
+    /// Adds the addresses of a peer known from a previous run and dials it
+    pub fn add_known_peer(&mut self, peer_id: PeerId, addresses: Vec<Multiaddr>) {
+        self.discovery.add_known_peer(peer_id, addresses)
+    }
+
+    /// Returns the details of a connected peer
+    pub fn peer_details(&self, peer_id: &PeerId) -> Option<&PeerDetails> {
+        self.discovery.peer_details(peer_id)
+    }
+
+    /// Records the round-trip time of a successful ping
+    pub fn record_latency(&mut self, peer_id: &PeerId, rtt: Duration) {
+        self.discovery.record_latency(peer_id, rtt)
+    }
 }
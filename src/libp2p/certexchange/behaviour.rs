@@ -0,0 +1,119 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use libp2p::{
+    request_response::{self, ProtocolSupport, ResponseChannel},
+    swarm::{derive_prelude::*, NetworkBehaviour, THandlerOutEvent},
+    PeerId,
+};
+
+use super::*;
+
+type InnerBehaviour = request_response::Behaviour<CertExchangeCodec>;
+
+/// Serves the finality certificates of the node to its peers. Certificates are
+/// fetched from peers by the `go-f3` sidecar, so no requests are sent.
+pub struct CertExchangeBehaviour {
+    inner: InnerBehaviour,
+}
+
+impl CertExchangeBehaviour {
+    pub fn new(network_name: &str) -> Self {
+        Self {
+            inner: InnerBehaviour::new(
+                [(
+                    cert_exchange_protocol_name(network_name),
+                    ProtocolSupport::Inbound,
+                )],
+                Default::default(),
+            ),
+        }
+    }
+
+    pub fn send_response(
+        &mut self,
+        channel: ResponseChannel<CertExchangeResponse>,
+        response: CertExchangeResponse,
+    ) -> Result<(), CertExchangeResponse> {
+        self.inner.send_response(channel, response)
+    }
+}
+
+impl NetworkBehaviour for CertExchangeBehaviour {
+    type ConnectionHandler = <InnerBehaviour as NetworkBehaviour>::ConnectionHandler;
+
+    type ToSwarm = <InnerBehaviour as NetworkBehaviour>::ToSwarm;
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &libp2p::Multiaddr,
+        remote_addr: &libp2p::Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &libp2p::Multiaddr,
+        role_override: libp2p::core::Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner
+            .handle_established_outbound_connection(connection_id, peer, addr, role_override)
+    }
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &libp2p::Multiaddr,
+        remote_addr: &libp2p::Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[libp2p::Multiaddr],
+        effective_role: libp2p::core::Endpoint,
+    ) -> Result<Vec<libp2p::Multiaddr>, ConnectionDenied> {
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm<Self::ConnectionHandler>) {
+        self.inner.on_swarm_event(event)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+        params: &mut impl PollParameters,
+    ) -> std::task::Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        self.inner.poll(cx, params)
+    }
+}
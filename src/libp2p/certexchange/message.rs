@@ -0,0 +1,23 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::f3::certs::FinalityCertificate;
+use serde_tuple::{self, Deserialize_tuple, Serialize_tuple};
+
+/// Request for the certificates of consecutive instances.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct CertExchangeRequest {
+    /// Instance of the first certificate requested.
+    pub first_instance: u64,
+    /// Maximum number of certificates requested.
+    pub limit: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct CertExchangeResponse {
+    /// The first instance the responding peer has no certificate for.
+    pub pending_instance: u64,
+    /// Certificates starting at the requested instance, possibly fewer than
+    /// requested.
+    pub payload: Vec<FinalityCertificate>,
+}
@@ -0,0 +1,21 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Exchange of F3 finality certificates between peers, see
+//! [`crate::f3::certs`].
+
+mod behaviour;
+mod message;
+mod provider;
+pub use behaviour::*;
+
+pub use self::{message::*, provider::*};
+use super::rpc::CborRequestResponse;
+
+/// Libp2p protocol name for the certificate exchange of a network.
+pub fn cert_exchange_protocol_name(network_name: &str) -> String {
+    format!("/f3/certexch/get/1/{network_name}")
+}
+
+/// Certificate exchange protocol codec to be used within the RPC service.
+pub type CertExchangeCodec = CborRequestResponse<String, CertExchangeRequest, CertExchangeResponse>;
@@ -0,0 +1,82 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::f3::certs::CertStore;
+use tracing::warn;
+
+use super::{CertExchangeRequest, CertExchangeResponse};
+
+/// Maximum number of certificates sent in a single response.
+pub const MAX_CERTS_PER_RESPONSE: u64 = 256;
+
+/// Builds certificate exchange response out of the certificate store.
+pub fn make_cert_exchange_response(
+    store: &CertStore,
+    request: &CertExchangeRequest,
+) -> CertExchangeResponse {
+    let inner = || -> anyhow::Result<CertExchangeResponse> {
+        let pending_instance = store.latest_instance()?.map_or(0, |latest| latest + 1);
+        let payload = store.range(
+            request.first_instance,
+            request.limit.min(MAX_CERTS_PER_RESPONSE),
+        )?;
+        Ok(CertExchangeResponse {
+            pending_instance,
+            payload,
+        })
+    };
+
+    inner().unwrap_or_else(|e| {
+        warn!("Failed to serve finality certificates: {e}");
+        CertExchangeResponse {
+            pending_instance: 0,
+            payload: vec![],
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::db::MemoryDB;
+    use crate::f3::certs::{FinalityCertificate, SupplementalData};
+    use cid::Cid;
+
+    fn cert(instance: u64) -> FinalityCertificate {
+        FinalityCertificate {
+            gpbft_instance: instance,
+            ec_chain: vec![],
+            supplemental_data: SupplementalData {
+                commitments: vec![],
+                power_table: Cid::default(),
+            },
+            signers: vec![],
+            signature: vec![],
+            power_table_delta: vec![],
+        }
+    }
+
+    #[test]
+    fn responses_are_capped_and_report_pending_instance() {
+        let store = CertStore::new(Arc::new(MemoryDB::default()));
+        let request = |first_instance, limit| CertExchangeRequest {
+            first_instance,
+            limit,
+        };
+
+        let response = make_cert_exchange_response(&store, &request(0, 10));
+        assert_eq!(response.pending_instance, 0);
+        assert!(response.payload.is_empty());
+
+        for instance in 0..300 {
+            store.put(&cert(instance)).unwrap();
+        }
+        let response = make_cert_exchange_response(&store, &request(0, u64::MAX));
+        assert_eq!(response.pending_instance, 300);
+        assert_eq!(response.payload.len() as u64, MAX_CERTS_PER_RESPONSE);
+        let response = make_cert_exchange_response(&store, &request(298, 10));
+        assert_eq!(response.payload, vec![cert(298), cert(299)]);
+    }
+}
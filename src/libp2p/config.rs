@@ -1,19 +1,35 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::{num::NonZeroUsize, time::Duration};
+
 use libp2p::Multiaddr;
 use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
 #[cfg(test)]
 use std::net::Ipv4Addr;
 
+/// Role of the node in the Kademlia DHT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+pub enum KademliaMode {
+    /// Answers the DHT queries of other peers.
+    #[default]
+    Server,
+    /// Only issues queries, for resource-constrained nodes.
+    Client,
+}
+
 /// Libp2p configuration for the Forest node.
+#[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(default)]
 #[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
 pub struct Libp2pConfig {
-    /// Local addresses. TCP and WebSocket with DNS are supported. By making it
-    /// empty, the libp2p node will not be capable of working as a callee but
-    /// can still work as a dialer
+    /// Local addresses. TCP and QUIC (`/udp/<port>/quic-v1`, if enabled) with
+    /// DNS are supported. By making it empty, the libp2p node will not be
+    /// capable of working as a callee but can still work as a dialer
     #[cfg_attr(test, arbitrary(gen(
         |g: &mut quickcheck::Gen| {
             let addr = Ipv4Addr::arbitrary(&mut *g);
@@ -22,27 +38,105 @@ pub struct Libp2pConfig {
         }
     )))]
     pub listening_multiaddrs: Vec<Multiaddr>,
+    /// Addresses advertised to other peers, instead of the listening and
+    /// observed ones. Useful when the node is reachable at addresses it
+    /// doesn't bind to, e.g. behind a load balancer.
+    #[cfg_attr(test, arbitrary(gen(
+        |g| vec![Ipv4Addr::arbitrary(g).into()]
+    )))]
+    pub announce_addresses: Vec<Multiaddr>,
+    /// Addresses never advertised to other peers.
+    #[cfg_attr(test, arbitrary(gen(
+        |g| vec![Ipv4Addr::arbitrary(g).into()]
+    )))]
+    pub no_announce_addresses: Vec<Multiaddr>,
     /// Bootstrap peer list.
     #[cfg_attr(test, arbitrary(gen(
         |g| vec![Ipv4Addr::arbitrary(g).into()]
     )))]
     pub bootstrap_peers: Vec<Multiaddr>,
+    /// Peers that are always connected to and never banned, with a
+    /// `/p2p/<peer id>` suffix.
+    #[cfg_attr(test, arbitrary(gen(
+        |g| vec![Ipv4Addr::arbitrary(g).into()]
+    )))]
+    pub static_peers: Vec<Multiaddr>,
+    /// Whether the known peers are saved in the database, to be dialed first
+    /// after a restart.
+    pub persist_peerstore: bool,
     /// MDNS discovery enabled.
     pub mdns: bool,
     /// Kademlia discovery enabled.
     pub kademlia: bool,
+    /// Kademlia mode, `server` or `client`.
+    pub kademlia_mode: KademliaMode,
+    /// Number of peers queried in parallel by Kademlia lookups.
+    #[cfg_attr(test, arbitrary(gen(
+        |g| NonZeroUsize::MIN.saturating_add(u32::arbitrary(g) as usize)
+    )))]
+    pub kademlia_parallelism: NonZeroUsize,
+    /// Interval in seconds between Kademlia bootstraps, which refresh the
+    /// routing table. Zero disables the periodic bootstraps.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    #[cfg_attr(test, arbitrary(gen(
+        |g| Duration::from_secs(u32::arbitrary(g).into())
+    )))]
+    pub kademlia_bootstrap_interval: Duration,
     /// Target peer count.
     pub target_peer_count: u32,
+    /// QUIC transport enabled, for both listening and dialing.
+    pub quic: bool,
+    /// Maximum number of established inbound connections.
+    pub max_inbound_connections: u32,
+    /// Maximum number of established outbound connections.
+    pub max_outbound_connections: u32,
+    /// Maximum number of established connections with a single peer.
+    pub max_connections_per_peer: u32,
+    /// Maximum number of concurrent streams on a single connection.
+    pub max_streams_per_connection: u32,
+    /// Process memory usage in bytes above which new connections are denied.
+    /// Unlimited if unset.
+    #[cfg_attr(test, arbitrary(gen(
+        |g| Option::<u32>::arbitrary(g).map(u64::from)
+    )))]
+    pub max_memory_bytes: Option<u64>,
+    /// Whether blocks are served to peers over bitswap.
+    pub bitswap_server: bool,
+    /// Maximum number of bitswap want-list entries answered per peer and
+    /// second.
+    pub bitswap_max_requests_per_peer: u32,
+    /// Maximum number of bytes served over bitswap per second.
+    #[cfg_attr(test, arbitrary(gen(|g| u32::arbitrary(g) as _)))]
+    pub bitswap_max_served_bytes: u64,
 }
 
 impl Default for Libp2pConfig {
     fn default() -> Self {
         Self {
-            listening_multiaddrs: vec!["/ip4/0.0.0.0/tcp/0".parse().expect("Infallible")],
+            listening_multiaddrs: vec![
+                "/ip4/0.0.0.0/tcp/0".parse().expect("Infallible"),
+                "/ip4/0.0.0.0/udp/0/quic-v1".parse().expect("Infallible"),
+            ],
+            announce_addresses: vec![],
+            no_announce_addresses: vec![],
             bootstrap_peers: vec![],
+            static_peers: vec![],
+            persist_peerstore: true,
             mdns: false,
             kademlia: true,
+            kademlia_mode: KademliaMode::Server,
+            kademlia_parallelism: NonZeroUsize::new(3).expect("Infallible"),
+            kademlia_bootstrap_interval: Duration::from_secs(300),
             target_peer_count: 75,
+            quic: true,
+            max_inbound_connections: 8192,
+            max_outbound_connections: 8192,
+            max_connections_per_peer: 5,
+            max_streams_per_connection: 512,
+            max_memory_bytes: None,
+            bitswap_server: true,
+            bitswap_max_requests_per_peer: 500,
+            bitswap_max_served_bytes: 16 * 1024 * 1024,
         }
     }
 }
@@ -4,14 +4,16 @@
 use std::{
     cmp,
     collections::VecDeque,
+    num::NonZeroUsize,
     task::{Context, Poll},
     time::Duration,
 };
 
+use ::futures::channel::oneshot::Sender as OneShotSender;
 use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use libp2p::{
     autonat,
-    core::Multiaddr,
+    core::{Endpoint, Multiaddr},
     identify,
     identity::{PeerId, PublicKey},
     kad::{self, record::store::MemoryStore},
@@ -21,15 +23,19 @@ use libp2p::{
         behaviour::toggle::Toggle,
         derive_prelude::*,
         dial_opts::{DialOpts, PeerCondition},
-        NetworkBehaviour, PollParameters, ToSwarm,
+        ListenOpts, NetworkBehaviour, PollParameters, ToSwarm,
     },
     StreamProtocol,
 };
 use tokio::time::Interval;
 use tracing::{debug, trace, warn};
 
+use crate::libp2p::{metrics, KademliaMode};
 use crate::utils::version::FOREST_VERSION_STRING;
 
+/// Interval at which the disconnected static peers are dialed.
+const STATIC_PEERS_DIAL_INTERVAL: Duration = Duration::from_secs(30);
+
 #[derive(NetworkBehaviour)]
 pub struct DerivedDiscoveryBehaviour {
     /// Kademlia discovery.
@@ -65,9 +71,15 @@ pub struct DiscoveryConfig<'a> {
     local_peer_id: PeerId,
     local_public_key: PublicKey,
     user_defined: Vec<(PeerId, Multiaddr)>,
+    static_peers: Vec<(PeerId, Multiaddr)>,
     target_peer_count: u64,
     enable_mdns: bool,
     enable_kademlia: bool,
+    kademlia_mode: KademliaMode,
+    kademlia_parallelism: Option<NonZeroUsize>,
+    kademlia_bootstrap_interval: Option<Duration>,
+    announce_addresses: Vec<Multiaddr>,
+    no_announce_addresses: Vec<Multiaddr>,
     network_name: &'a str,
 }
 
@@ -78,9 +90,15 @@ impl<'a> DiscoveryConfig<'a> {
             local_peer_id: local_public_key.to_peer_id(),
             local_public_key,
             user_defined: Vec::new(),
+            static_peers: Vec::new(),
             target_peer_count: std::u64::MAX,
             enable_mdns: false,
             enable_kademlia: true,
+            kademlia_mode: KademliaMode::Server,
+            kademlia_parallelism: None,
+            kademlia_bootstrap_interval: None,
+            announce_addresses: vec![],
+            no_announce_addresses: vec![],
             network_name,
         }
     }
@@ -96,12 +114,20 @@ impl<'a> DiscoveryConfig<'a> {
         mut self,
         user_defined: impl IntoIterator<Item = Multiaddr>,
     ) -> anyhow::Result<Self> {
-        for mut addr in user_defined.into_iter() {
-            if let Some(Protocol::P2p(peer_id)) = addr.pop() {
-                self.user_defined.push((peer_id, addr))
-            } else {
-                anyhow::bail!("Failed to parse peer id from {addr}")
-            }
+        for addr in user_defined.into_iter() {
+            self.user_defined.push(split_peer_id(addr)?)
+        }
+        Ok(self)
+    }
+
+    /// Set peers that are always connected to, they are redialed when
+    /// disconnected.
+    pub fn with_static_peers(
+        mut self,
+        static_peers: impl IntoIterator<Item = Multiaddr>,
+    ) -> anyhow::Result<Self> {
+        for addr in static_peers.into_iter() {
+            self.static_peers.push(split_peer_id(addr)?)
         }
         Ok(self)
     }
@@ -118,15 +144,53 @@ impl<'a> DiscoveryConfig<'a> {
         self
     }
 
+    /// Configures the Kademlia mode.
+    pub fn with_kademlia_mode(mut self, mode: KademliaMode) -> Self {
+        self.kademlia_mode = mode;
+        self
+    }
+
+    /// Configures the number of peers queried in parallel by Kademlia.
+    pub fn with_kademlia_parallelism(mut self, parallelism: NonZeroUsize) -> Self {
+        self.kademlia_parallelism = Some(parallelism);
+        self
+    }
+
+    /// Configures the interval of the periodic Kademlia bootstraps, none if
+    /// zero.
+    pub fn with_kademlia_bootstrap_interval(mut self, interval: Duration) -> Self {
+        self.kademlia_bootstrap_interval = (!interval.is_zero()).then_some(interval);
+        self
+    }
+
+    /// Configures the addresses advertised to other peers. If `announce` is
+    /// empty, the listening and confirmed external addresses are advertised
+    /// except for those in `no_announce`.
+    pub fn with_announce_addresses(
+        mut self,
+        announce: Vec<Multiaddr>,
+        no_announce: Vec<Multiaddr>,
+    ) -> Self {
+        self.announce_addresses = announce;
+        self.no_announce_addresses = no_announce;
+        self
+    }
+
     /// Create a `DiscoveryBehaviour` from this configuration.
     pub fn finish(self) -> anyhow::Result<DiscoveryBehaviour> {
         let DiscoveryConfig {
             local_peer_id,
             local_public_key,
             user_defined,
+            static_peers,
             target_peer_count,
             enable_mdns,
             enable_kademlia,
+            kademlia_mode,
+            kademlia_parallelism,
+            kademlia_bootstrap_interval,
+            announce_addresses,
+            no_announce_addresses,
             network_name,
         } = self;
 
@@ -140,6 +204,9 @@ impl<'a> DiscoveryConfig<'a> {
             cfg.set_protocol_names(vec![StreamProtocol::try_from_owned(format!(
                 "/fil/kad/{network_name}/kad/1.0.0"
             ))?]);
+            if let Some(parallelism) = kademlia_parallelism {
+                cfg.set_parallelism(parallelism);
+            }
             cfg
         };
 
@@ -149,11 +216,17 @@ impl<'a> DiscoveryConfig<'a> {
             // but it should not be required as the behaviour should automatically switch to server mode
             // according to the doc. It might be a bug in `libp2p`.
             // We should fix the bug or report with a minimal reproduction.
-            kademlia.set_mode(Some(kad::Mode::Server));
+            kademlia.set_mode(Some(match kademlia_mode {
+                KademliaMode::Server => kad::Mode::Server,
+                KademliaMode::Client => kad::Mode::Client,
+            }));
             for (peer_id, addr) in &user_defined {
                 kademlia.add_address(peer_id, addr.clone());
                 peers.insert(*peer_id);
             }
+            for (peer_id, addr) in &static_peers {
+                kademlia.add_address(peer_id, addr.clone());
+            }
             if let Err(e) = kademlia.bootstrap() {
                 warn!("Kademlia bootstrap failed: {}", e);
             }
@@ -181,17 +254,41 @@ impl<'a> DiscoveryConfig<'a> {
             },
             next_kad_random_query: tokio::time::interval(Duration::from_secs(1)),
             duration_to_next_kad: Duration::from_secs(1),
+            next_kad_bootstrap: kademlia_bootstrap_interval.map(|period| {
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+            }),
+            pending_find_peer: HashMap::new(),
             pending_events: VecDeque::new(),
             n_node_connected: 0,
             peers,
             peer_addresses,
+            peer_details: HashMap::new(),
             target_peer_count,
             custom_seed_peers: user_defined,
+            static_peers,
+            next_static_peers_dial: tokio::time::interval(STATIC_PEERS_DIAL_INTERVAL),
             pending_dial_opts: VecDeque::new(),
+            pending_listen_addrs: VecDeque::new(),
+            listening_via_relays: false,
+            announce_addresses,
+            no_announce_addresses,
         })
     }
 }
 
+/// What is known of a connected peer.
+#[derive(Debug, Clone)]
+pub struct PeerDetails {
+    /// Role of the node on the first connection with the peer.
+    pub endpoint: Endpoint,
+    /// Agent version advertised over `identify`.
+    pub agent_version: Option<String>,
+    /// Protocols advertised over `identify`.
+    pub protocols: Vec<StreamProtocol>,
+    /// Round-trip time of the latest ping.
+    pub latency: Option<Duration>,
+}
+
 /// Implementation of `NetworkBehaviour` that discovers the nodes on the
 /// network.
 pub struct DiscoveryBehaviour {
@@ -203,6 +300,12 @@ pub struct DiscoveryBehaviour {
     /// After `next_kad_random_query` triggers, the next one triggers after this
     /// duration.
     duration_to_next_kad: Duration,
+    /// Stream that fires when the Kademlia routing table needs to be
+    /// refreshed.
+    next_kad_bootstrap: Option<Interval>,
+    /// `NetFindPeer` queries in progress.
+    #[allow(clippy::type_complexity)]
+    pending_find_peer: HashMap<kad::QueryId, (PeerId, OneShotSender<Option<HashSet<Multiaddr>>>)>,
     /// Events to return in priority when polled.
     pending_events: VecDeque<DiscoveryEvent>,
     /// Number of nodes we're currently connected to.
@@ -211,12 +314,27 @@ pub struct DiscoveryBehaviour {
     peers: HashSet<PeerId>,
     /// Keeps hash map of peers and their multi-addresses
     peer_addresses: HashMap<PeerId, HashSet<Multiaddr>>,
+    /// Details of the connected peers
+    peer_details: HashMap<PeerId, PeerDetails>,
     /// Number of connected peers to pause discovery on.
     target_peer_count: u64,
     /// Seed peers
     custom_seed_peers: Vec<(PeerId, Multiaddr)>,
+    /// Peers that are always connected to
+    static_peers: Vec<(PeerId, Multiaddr)>,
+    /// Stream that fires when the disconnected static peers are dialed.
+    next_static_peers_dial: Interval,
     /// Options to configure dials to known peers.
     pending_dial_opts: VecDeque<DialOpts>,
+    /// Relayed addresses to listen on.
+    pending_listen_addrs: VecDeque<Multiaddr>,
+    /// Whether the node listens through the seed peers acting as relays,
+    /// which it does once it's known to be behind a NAT.
+    listening_via_relays: bool,
+    /// Addresses advertised to other peers, if not empty.
+    announce_addresses: Vec<Multiaddr>,
+    /// Addresses never advertised to other peers.
+    no_announce_addresses: Vec<Multiaddr>,
 }
 
 impl DiscoveryBehaviour {
@@ -230,6 +348,80 @@ impl DiscoveryBehaviour {
         &self.peer_addresses
     }
 
+    /// Returns the details of a connected peer.
+    pub fn peer_details(&self, peer_id: &PeerId) -> Option<&PeerDetails> {
+        self.peer_details.get(peer_id)
+    }
+
+    /// Records the round-trip time of a successful ping.
+    pub fn record_latency(&mut self, peer_id: &PeerId, rtt: Duration) {
+        if let Some(details) = self.peer_details.get_mut(peer_id) {
+            details.latency = Some(rtt);
+        }
+    }
+
+    /// Looks up the addresses of a peer, in the DHT if it's not connected.
+    /// `None` is sent if it can't be found.
+    pub fn find_peer(
+        &mut self,
+        peer_id: PeerId,
+        response_channel: OneShotSender<Option<HashSet<Multiaddr>>>,
+    ) {
+        if let Some(addrs) = self.peer_addresses.get(&peer_id) {
+            let _ = response_channel.send(Some(addrs.clone()));
+        } else if let Some(kademlia) = self.discovery.kademlia.as_mut() {
+            let query_id = kademlia.get_closest_peers(peer_id);
+            self.pending_find_peer
+                .insert(query_id, (peer_id, response_channel));
+        } else {
+            let _ = response_channel.send(None);
+        }
+    }
+
+    /// Returns the addresses of a peer in the Kademlia routing table.
+    fn kad_addresses(&mut self, peer_id: &PeerId) -> Option<HashSet<Multiaddr>> {
+        let kademlia = self.discovery.kademlia.as_mut()?;
+        for bucket in kademlia.kbuckets() {
+            for entry in bucket.iter() {
+                if entry.node.key.preimage() == peer_id {
+                    return Some(entry.node.value.iter().cloned().collect());
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns whether a peer is one of the custom seed peers.
+    pub fn is_seed_peer(&self, peer_id: &PeerId) -> bool {
+        self.custom_seed_peers.iter().any(|(id, _)| id == peer_id)
+    }
+
+    /// Returns whether a peer is one of the static peers.
+    pub fn is_static_peer(&self, peer_id: &PeerId) -> bool {
+        self.static_peers.iter().any(|(id, _)| id == peer_id)
+    }
+
+    /// Adds the addresses of a peer known from a previous run and dials it.
+    pub fn add_known_peer(&mut self, peer_id: PeerId, addresses: Vec<Multiaddr>) {
+        if let Some(kademlia) = self.discovery.kademlia.as_mut() {
+            for addr in &addresses {
+                kademlia.add_address(&peer_id, addr.clone());
+            }
+        }
+        self.pending_dial_opts.push_back(
+            DialOpts::peer_id(peer_id)
+                .condition(PeerCondition::Disconnected)
+                .addresses(addresses)
+                .build(),
+        );
+    }
+
+    /// Reachability of the node from the public internet, as determined by
+    /// `AutoNAT`.
+    pub fn nat_status(&self) -> autonat::NatStatus {
+        self.discovery.autonat.nat_status()
+    }
+
     /// Bootstrap Kademlia network
     pub fn bootstrap(&mut self) -> Result<kad::QueryId, String> {
         if let Some(active_kad) = self.discovery.kademlia.as_mut() {
@@ -322,6 +514,15 @@ impl NetworkBehaviour for DiscoveryBehaviour {
                 if e.other_established == 0 {
                     self.n_node_connected += 1;
                     self.peers.insert(e.peer_id);
+                    self.peer_details.insert(
+                        e.peer_id,
+                        PeerDetails {
+                            endpoint: e.endpoint.to_endpoint(),
+                            agent_version: None,
+                            protocols: vec![],
+                            latency: None,
+                        },
+                    );
                     self.pending_events
                         .push_back(DiscoveryEvent::PeerConnected(e.peer_id));
                 }
@@ -331,10 +532,21 @@ impl NetworkBehaviour for DiscoveryBehaviour {
                     self.n_node_connected -= 1;
                     self.peers.remove(&e.peer_id);
                     self.peer_addresses.remove(&e.peer_id);
+                    self.peer_details.remove(&e.peer_id);
+                    metrics::remove_peer(&e.peer_id);
                     self.pending_events
                         .push_back(DiscoveryEvent::PeerDisconnected(e.peer_id));
                 }
             }
+            // `identify`, Kademlia and mDNS advertise the addresses they learn from
+            // these events, hide the ones that must not be announced.
+            FromSwarm::NewListenAddr(NewListenAddr { addr, .. })
+            | FromSwarm::ExternalAddrConfirmed(ExternalAddrConfirmed { addr }) => {
+                if !should_announce(addr, &self.announce_addresses, &self.no_announce_addresses) {
+                    trace!("Not announcing {addr}");
+                    return;
+                }
+            }
             _ => {}
         };
         self.discovery.on_swarm_event(event)
@@ -366,6 +578,38 @@ impl NetworkBehaviour for DiscoveryBehaviour {
             return Poll::Ready(ToSwarm::Dial { opts });
         }
 
+        // Listen via relays
+        if let Some(addr) = self.pending_listen_addrs.pop_front() {
+            return Poll::Ready(ToSwarm::ListenOn {
+                opts: ListenOpts::new(addr),
+            });
+        }
+
+        // Reconnect to the static peers.
+        while self.next_static_peers_dial.poll_tick(cx).is_ready() {
+            for (peer_id, address) in &self.static_peers {
+                if !self.peers.contains(peer_id) {
+                    self.pending_dial_opts.push_back(
+                        DialOpts::peer_id(*peer_id)
+                            .condition(PeerCondition::Disconnected)
+                            .addresses(vec![address.clone()])
+                            .build(),
+                    );
+                }
+            }
+        }
+
+        // Refresh the Kademlia routing table.
+        if let Some(next_kad_bootstrap) = self.next_kad_bootstrap.as_mut() {
+            while next_kad_bootstrap.poll_tick(cx).is_ready() {
+                if let Some(kademlia) = self.discovery.kademlia.as_mut() {
+                    if let Err(e) = kademlia.bootstrap() {
+                        debug!("Kademlia bootstrap failed: {e}");
+                    }
+                }
+            }
+        }
+
         // Poll the stream that fires when we need to start a random Kademlia query.
         while self.next_kad_random_query.poll_tick(cx).is_ready() {
             if self.n_node_connected < self.target_peer_count {
@@ -397,6 +641,11 @@ impl NetworkBehaviour for DiscoveryBehaviour {
                     match &ev {
                         DerivedDiscoveryBehaviourEvent::Identify(ev) => {
                             if let identify::Event::Received { peer_id, info } = ev {
+                                metrics::set_peer_agent(*peer_id, &info.agent_version);
+                                if let Some(details) = self.peer_details.get_mut(peer_id) {
+                                    details.agent_version = Some(info.agent_version.clone());
+                                    details.protocols = info.protocols.clone();
+                                }
                                 if let Some(kademlia) = self.discovery.kademlia.as_mut() {
                                     for address in &info.listen_addrs {
                                         kademlia.add_address(peer_id, address.clone());
@@ -404,7 +653,27 @@ impl NetworkBehaviour for DiscoveryBehaviour {
                                 }
                             }
                         }
-                        DerivedDiscoveryBehaviourEvent::Autonat(_) => {}
+                        DerivedDiscoveryBehaviourEvent::Autonat(ev) => {
+                            if let autonat::Event::StatusChanged {
+                                new: autonat::NatStatus::Private,
+                                ..
+                            } = ev
+                            {
+                                if !self.listening_via_relays {
+                                    // Peers behind a NAT can only be reached through relays,
+                                    // from where `DCUtR` upgrades to direct connections.
+                                    debug!("Node is behind a NAT, listening via relays");
+                                    self.listening_via_relays = true;
+                                    self.pending_listen_addrs.extend(
+                                        self.custom_seed_peers.iter().map(|(peer_id, addr)| {
+                                            addr.clone()
+                                                .with(Protocol::P2p(*peer_id))
+                                                .with(Protocol::P2pCircuit)
+                                        }),
+                                    );
+                                }
+                            }
+                        }
                         DerivedDiscoveryBehaviourEvent::Kademlia(ev) => match ev {
                             // Adding to Kademlia buckets is automatic with our config,
                             // no need to do manually.
@@ -413,6 +682,18 @@ impl NetworkBehaviour for DiscoveryBehaviour {
                             kad::Event::PendingRoutablePeer { .. } => {
                                 // Intentionally ignore
                             }
+                            kad::Event::OutboundQueryProgressed {
+                                id,
+                                result: kad::QueryResult::GetClosestPeers(_),
+                                step,
+                                ..
+                            } if step.last => {
+                                if let Some((peer_id, response_channel)) =
+                                    self.pending_find_peer.remove(id)
+                                {
+                                    let _ = response_channel.send(self.kad_addresses(&peer_id));
+                                }
+                            }
                             other => {
                                 trace!("Libp2p => Unhandled Kademlia event: {:?}", other)
                             }
@@ -482,6 +763,23 @@ impl NetworkBehaviour for DiscoveryBehaviour {
     }
 }
 
+/// Splits a `/p2p/<peer id>` address into the peer id and its address.
+fn split_peer_id(mut addr: Multiaddr) -> anyhow::Result<(PeerId, Multiaddr)> {
+    if let Some(Protocol::P2p(peer_id)) = addr.pop() {
+        Ok((peer_id, addr))
+    } else {
+        anyhow::bail!("Failed to parse peer id from {addr}")
+    }
+}
+
+/// Whether an address of the node may be advertised to other peers.
+fn should_announce(addr: &Multiaddr, announce: &[Multiaddr], no_announce: &[Multiaddr]) -> bool {
+    if no_announce.contains(addr) {
+        return false;
+    }
+    announce.is_empty() || announce.contains(addr)
+}
+
 #[cfg(test)]
 mod tests {
     use libp2p::{identity::Keypair, swarm::SwarmEvent, Swarm};
@@ -489,6 +787,23 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn announced_addresses() {
+        let public: Multiaddr = "/ip4/1.2.3.4/tcp/1234".parse().unwrap();
+        let private: Multiaddr = "/ip4/10.0.0.1/tcp/1234".parse().unwrap();
+
+        assert!(should_announce(&public, &[], &[]));
+        assert!(should_announce(&private, &[], &[]));
+        assert!(!should_announce(&private, &[], &[private.clone()]));
+        assert!(should_announce(&public, &[public.clone()], &[]));
+        assert!(!should_announce(&private, &[public.clone()], &[]));
+        assert!(!should_announce(
+            &public,
+            &[public.clone()],
+            &[public.clone()]
+        ));
+    }
+
     #[tokio::test]
     async fn kademlia_test() {
         fn new_discovery(
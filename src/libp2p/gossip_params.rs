@@ -10,10 +10,14 @@ use libp2p::gossipsub::{
 use crate::libp2p::{PUBSUB_BLOCK_STR, PUBSUB_MSG_STR};
 
 // All these parameters are copied from what Lotus has set for their Topic
-// scores. They are currently unused because enabling them causes GossipSub
-// blocks to come delayed usually by 1 second compared to when we have these
-// parameters disabled. Leaving these here so that we can enable and fix these
-// parameters when they are needed.
+// scores, see
+// <https://github.com/filecoin-project/lotus/blob/master/node/modules/lp2p/pubsub.go>.
+// Invalid messages are the ones that can't be decoded, which are reported to
+// GossipSub when handling its events.
+
+/// Application specific score of the bootstrap peers, so that they are never
+/// pruned from the mesh.
+pub(in crate::libp2p) const BOOTSTRAP_PEER_APP_SCORE: f64 = 2500.0;
 
 fn build_msg_topic_config() -> TopicScoreParams {
     TopicScoreParams {
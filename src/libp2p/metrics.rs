@@ -1,8 +1,11 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use ahash::HashMap;
+use libp2p::PeerId;
 use once_cell::sync::Lazy;
-use prometheus::core::{AtomicU64, GenericCounter, GenericGauge};
+use parking_lot::RwLock;
+use prometheus::core::{AtomicU64, GenericCounter, GenericCounterVec, GenericGauge, Opts};
 
 pub static PEER_FAILURE_TOTAL: Lazy<Box<GenericCounter<AtomicU64>>> = Lazy::new(|| {
     let peer_failure_total = Box::new(
@@ -40,3 +43,104 @@ pub static BAD_PEERS: Lazy<Box<GenericGauge<AtomicU64>>> = Lazy::new(|| {
         .expect("Registering the bad_peers metric with the metrics registry must succeed");
     bad_peers
 });
+
+pub static GOSSIPSUB_MESSAGES: Lazy<Box<GenericCounterVec<AtomicU64>>> = Lazy::new(|| {
+    let gossipsub_messages = Box::new(
+        GenericCounterVec::<AtomicU64>::new(
+            Opts::new(
+                "gossipsub_messages_total",
+                "Number of gossip messages received, by topic and peer bucket",
+            ),
+            &[labels::TOPIC, labels::PEER_BUCKET],
+        )
+        .expect("Defining the gossipsub_messages_total metric must succeed"),
+    );
+    prometheus::default_registry()
+        .register(gossipsub_messages.clone())
+        .expect(
+            "Registering the gossipsub_messages_total metric with the metrics registry must succeed",
+        );
+    gossipsub_messages
+});
+pub static GOSSIPSUB_VALIDATIONS: Lazy<Box<GenericCounterVec<AtomicU64>>> = Lazy::new(|| {
+    let gossipsub_validations = Box::new(
+        GenericCounterVec::<AtomicU64>::new(
+            Opts::new(
+                "gossipsub_validations_total",
+                "Number of gossip messages validated, by topic and result",
+            ),
+            &[labels::TOPIC, labels::RESULT],
+        )
+        .expect("Defining the gossipsub_validations_total metric must succeed"),
+    );
+    prometheus::default_registry()
+        .register(gossipsub_validations.clone())
+        .expect(
+            "Registering the gossipsub_validations_total metric with the metrics registry must succeed",
+        );
+    gossipsub_validations
+});
+
+pub mod labels {
+    pub const TOPIC: &str = "topic";
+    pub const RESULT: &str = "result";
+    pub const PEER_BUCKET: &str = "peer_bucket";
+}
+
+pub mod values {
+    pub const BLOCKS_TOPIC: &str = "blocks";
+    pub const MESSAGES_TOPIC: &str = "msgs";
+    pub const UNKNOWN_TOPIC: &str = "unknown";
+
+    pub const ACCEPT: &str = "accept";
+    pub const REJECT: &str = "reject";
+    pub const IGNORE: &str = "ignore";
+}
+
+/// Buckets of the connected peers, see [`peer_bucket`].
+static PEER_BUCKETS: Lazy<RwLock<HashMap<PeerId, &'static str>>> = Lazy::new(Default::default);
+
+/// Records the agent version a peer advertised over `identify`.
+pub(crate) fn set_peer_agent(peer: PeerId, agent_version: &str) {
+    PEER_BUCKETS
+        .write()
+        .insert(peer, agent_bucket(agent_version));
+}
+
+pub(crate) fn remove_peer(peer: &PeerId) {
+    PEER_BUCKETS.write().remove(peer);
+}
+
+/// Coarse classification of a peer by implementation, to label metrics with
+/// without one series per peer. Peers that haven't been identified yet are
+/// `unknown`.
+pub(crate) fn peer_bucket(peer: &PeerId) -> &'static str {
+    PEER_BUCKETS.read().get(peer).copied().unwrap_or("unknown")
+}
+
+fn agent_bucket(agent_version: &str) -> &'static str {
+    let agent_version = agent_version.to_lowercase();
+    ["lotus", "forest", "venus", "boost"]
+        .into_iter()
+        .find(|implementation| agent_version.starts_with(implementation))
+        .unwrap_or("other")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peer_buckets() {
+        assert_eq!(agent_bucket("lotus-1.23.3+mainnet+git.2c6f1ed"), "lotus");
+        assert_eq!(agent_bucket("forest-0.16.0"), "forest");
+        assert_eq!(agent_bucket("go-ipfs/0.4.0"), "other");
+
+        let peer = PeerId::random();
+        assert_eq!(peer_bucket(&peer), "unknown");
+        set_peer_agent(peer, "venus-1.14.0");
+        assert_eq!(peer_bucket(&peer), "venus");
+        remove_peer(&peer);
+        assert_eq!(peer_bucket(&peer), "unknown");
+    }
+}
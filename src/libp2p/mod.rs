@@ -2,14 +2,16 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 mod behaviour;
+pub mod certexchange;
 pub mod chain_exchange;
 mod config;
 mod discovery;
 mod gossip_params;
 pub mod hello;
 pub mod keypair;
-mod metrics;
+pub(crate) mod metrics;
 mod peer_manager;
+mod peerstore;
 pub mod rpc;
 mod service;
 
@@ -21,7 +23,7 @@ pub use libp2p::{
 };
 
 pub(in crate::libp2p) use self::behaviour::*;
-pub use self::{config::*, peer_manager::*, service::*};
+pub use self::{config::*, peer_manager::*, peerstore::*, service::*};
 #[cfg(test)]
 mod tests {
     mod decode_test;
@@ -12,7 +12,7 @@ use ahash::{HashMap, HashSet};
 use flume::{Receiver, Sender};
 use rand::seq::SliceRandom;
 use tokio::sync::RwLock;
-use tracing::{debug, trace, warn};
+use tracing::{debug, info, trace, warn};
 
 use crate::libp2p::*;
 
@@ -75,6 +75,8 @@ pub struct PeerManager {
     peer_ops_rx: Receiver<PeerOperation>,
     /// Peer ban list, key is peer id, value is expiration time
     peer_ban_list: RwLock<HashMap<PeerId, Option<Instant>>>,
+    /// Peers that are never banned
+    protected_peers: RwLock<HashSet<PeerId>>,
 }
 
 impl Default for PeerManager {
@@ -86,6 +88,7 @@ impl Default for PeerManager {
             peer_ops_tx,
             peer_ops_rx,
             peer_ban_list: Default::default(),
+            protected_peers: Default::default(),
         }
     }
 }
@@ -203,8 +206,12 @@ impl PeerManager {
     }
 
     /// Removes a peer from the set and returns true if the value was present
-    /// previously
+    /// previously. Protected peers are kept.
     pub async fn mark_peer_bad(&self, peer_id: PeerId) -> bool {
+        if self.is_protected(&peer_id).await {
+            debug!("not marking protected peer {peer_id} bad");
+            return false;
+        }
         let mut peers = self.peers.write().await;
         let removed = remove_peer(&mut peers, &peer_id);
         if removed {
@@ -236,13 +243,21 @@ impl PeerManager {
         &self.peer_ops_rx
     }
 
-    /// Bans a peer with an optional duration
+    /// Bans a peer with an optional duration. Protected peers are not banned,
+    /// in which case `false` is returned.
     pub async fn ban_peer(
         &self,
         peer: PeerId,
         reason: impl Into<String>,
         duration: Option<Duration>,
-    ) {
+    ) -> bool {
+        if self.is_protected(&peer).await {
+            info!(
+                "Not banning protected peer {peer}, reason: {}",
+                reason.into()
+            );
+            return false;
+        }
         let mut locked = self.peer_ban_list.write().await;
         locked.insert(peer, duration.and_then(|d| Instant::now().checked_add(d)));
         if let Err(e) = self
@@ -252,6 +267,44 @@ impl PeerManager {
         {
             warn!("ban_peer err: {e}");
         }
+        true
+    }
+
+    /// Lifts the ban of a peer, returns `false` if it wasn't banned.
+    pub async fn unban_peer(&self, peer: PeerId) -> bool {
+        if self.peer_ban_list.write().await.remove(&peer).is_none() {
+            return false;
+        }
+        if let Err(e) = self
+            .peer_ops_tx
+            .send_async(PeerOperation::Unban(peer))
+            .await
+        {
+            warn!("unban_peer err: {e}");
+        }
+        true
+    }
+
+    /// Protects a peer from being banned, lifting its current ban if any.
+    pub async fn protect_peer(&self, peer: PeerId) {
+        self.protected_peers.write().await.insert(peer);
+        self.unban_peer(peer).await;
+    }
+
+    /// Removes the protection of a peer, returns `false` if it wasn't
+    /// protected.
+    pub async fn unprotect_peer(&self, peer: &PeerId) -> bool {
+        self.protected_peers.write().await.remove(peer)
+    }
+
+    /// Returns whether a peer is protected from being banned.
+    pub async fn is_protected(&self, peer: &PeerId) -> bool {
+        self.protected_peers.read().await.contains(peer)
+    }
+
+    /// Returns the protected peers.
+    pub async fn protected_peers(&self) -> HashSet<PeerId> {
+        self.protected_peers.read().await.clone()
     }
 
     pub async fn peer_operation_event_loop_task(self: Arc<Self>) -> anyhow::Result<()> {
@@ -315,3 +368,37 @@ pub enum PeerOperation {
     Ban(PeerId, String),
     Unban(PeerId),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn protected_peers_are_not_banned() {
+        let peer_manager = PeerManager::default();
+        let peer = PeerId::random();
+
+        assert!(peer_manager.ban_peer(peer, "test", None).await);
+        assert!(matches!(
+            peer_manager.peer_ops_rx().try_recv(),
+            Ok(PeerOperation::Ban(p, _)) if p == peer
+        ));
+
+        // Protecting a banned peer lifts its ban
+        peer_manager.protect_peer(peer).await;
+        assert!(matches!(
+            peer_manager.peer_ops_rx().try_recv(),
+            Ok(PeerOperation::Unban(p)) if p == peer
+        ));
+        assert!(!peer_manager.ban_peer(peer, "test", None).await);
+        assert!(peer_manager.peer_ops_rx().is_empty());
+        assert!(!peer_manager.mark_peer_bad(peer).await);
+        assert!(peer_manager.is_peer_new(&peer).await);
+
+        assert!(peer_manager.unprotect_peer(&peer).await);
+        assert!(peer_manager.protected_peers().await.is_empty());
+        assert!(peer_manager.ban_peer(peer, "test", None).await);
+        assert!(peer_manager.unban_peer(peer).await);
+        assert!(!peer_manager.unban_peer(peer).await);
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Peers the node has been connected to, persisted in the settings store under
+//! [`PEERSTORE_KEY`] so that connectivity is regained quickly after a restart.
+//!
+//! [`PEERSTORE_KEY`]: crate::db::setting_keys::PEERSTORE_KEY
+
+use std::time::Duration;
+
+use crate::db::{setting_keys::PEERSTORE_KEY, SettingsStore, SettingsStoreExt as _};
+use ahash::HashMap;
+use libp2p::{Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr, DurationMilliSeconds};
+
+/// Number of latency samples kept per peer.
+const LATENCY_HISTORY: usize = 10;
+/// Number of peers kept, the ones seen the longest time ago are dropped first.
+const MAX_PEERS: usize = 1000;
+
+/// What is remembered of a peer.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerRecord {
+    /// Addresses the peer was connected at.
+    pub addrs: Vec<Multiaddr>,
+    /// Latest round-trip times, oldest first.
+    #[serde_as(as = "Vec<DurationMilliSeconds<u64>>")]
+    pub latencies: Vec<Duration>,
+    /// Unix timestamp of the last time the peer was connected.
+    pub last_seen: i64,
+}
+
+impl PeerRecord {
+    /// Average of the recorded round-trip times.
+    pub fn average_latency(&self) -> Option<Duration> {
+        let n = u32::try_from(self.latencies.len())
+            .ok()
+            .filter(|n| *n > 0)?;
+        Some(self.latencies.iter().sum::<Duration>() / n)
+    }
+}
+
+/// Known peers with their addresses and latency history.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Peerstore {
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    peers: HashMap<PeerId, PeerRecord>,
+}
+
+impl Peerstore {
+    /// Loads the peerstore, empty if it was never saved.
+    pub fn load(settings: &dyn SettingsStore) -> anyhow::Result<Self> {
+        Ok(settings.read_obj(PEERSTORE_KEY)?.unwrap_or_default())
+    }
+
+    /// Saves the peerstore, keeping only the most recently seen peers.
+    pub fn save(&mut self, settings: &dyn SettingsStore) -> anyhow::Result<()> {
+        if self.peers.len() > MAX_PEERS {
+            let mut last_seen: Vec<_> = self.peers.values().map(|r| r.last_seen).collect();
+            last_seen.sort_unstable_by(|a, b| b.cmp(a));
+            let oldest_kept = last_seen[MAX_PEERS - 1];
+            self.peers.retain(|_, r| r.last_seen >= oldest_kept);
+        }
+        settings.write_obj(PEERSTORE_KEY, self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    #[cfg(test)]
+    pub fn get(&self, peer: &PeerId) -> Option<&PeerRecord> {
+        self.peers.get(peer)
+    }
+
+    /// Records that a peer is connected at the given addresses, with its latest
+    /// round-trip time if known.
+    pub fn record_peer<'a>(
+        &mut self,
+        peer: PeerId,
+        addrs: impl IntoIterator<Item = &'a Multiaddr>,
+        latency: Option<Duration>,
+        now: i64,
+    ) {
+        let record = self.peers.entry(peer).or_default();
+        for addr in addrs {
+            if !record.addrs.contains(addr) {
+                record.addrs.push(addr.clone());
+            }
+        }
+        if let Some(latency) = latency {
+            if record.latencies.len() == LATENCY_HISTORY {
+                record.latencies.remove(0);
+            }
+            record.latencies.push(latency);
+        }
+        record.last_seen = now;
+    }
+
+    /// Forgets a peer, e.g. because it got banned.
+    pub fn remove_peer(&mut self, peer: &PeerId) -> bool {
+        self.peers.remove(peer).is_some()
+    }
+
+    /// Returns up to `n` peers to dial, the fastest ones first. Peers without
+    /// latency samples come last, the most recently seen first.
+    pub fn best_peers(&self, n: usize) -> Vec<(PeerId, Vec<Multiaddr>)> {
+        let mut peers: Vec<_> = self
+            .peers
+            .iter()
+            .filter(|(_, r)| !r.addrs.is_empty())
+            .collect();
+        peers.sort_by_key(|(_, r)| {
+            (
+                r.average_latency().unwrap_or(Duration::MAX),
+                std::cmp::Reverse(r.last_seen),
+            )
+        });
+        peers
+            .into_iter()
+            .take(n)
+            .map(|(peer, r)| (*peer, r.addrs.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+
+    #[test]
+    fn peerstore_roundtrip() {
+        let db = MemoryDB::default();
+        assert!(Peerstore::load(&db).unwrap().is_empty());
+
+        let (fast, slow, unknown) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let addr: Multiaddr = "/ip4/127.0.0.1/tcp/1234".parse().unwrap();
+        let mut store = Peerstore::default();
+        store.record_peer(unknown, [&addr], None, 3);
+        store.record_peer(slow, [&addr], Some(Duration::from_millis(300)), 1);
+        store.record_peer(fast, [&addr], Some(Duration::from_millis(100)), 1);
+        for _ in 0..LATENCY_HISTORY {
+            store.record_peer(slow, [&addr], Some(Duration::from_millis(200)), 2);
+        }
+        let record = store.get(&slow).unwrap();
+        assert_eq!(record.addrs.len(), 1);
+        assert_eq!(record.latencies.len(), LATENCY_HISTORY);
+        assert_eq!(record.average_latency(), Some(Duration::from_millis(200)));
+
+        let best: Vec<_> = store.best_peers(3).into_iter().map(|(p, _)| p).collect();
+        assert_eq!(best, [fast, slow, unknown]);
+
+        store.save(&db).unwrap();
+        assert_eq!(Peerstore::load(&db).unwrap(), store);
+    }
+}
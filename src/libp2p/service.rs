@@ -6,16 +6,22 @@ use std::{
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use crate::blocks::GossipBlock;
+use crate::db::SettingsStore;
+use crate::f3::certs::CertStore;
 use crate::libp2p_bitswap::{
-    request_manager::BitswapRequestManager, BitswapStoreRead, BitswapStoreReadWrite,
+    request_manager::BitswapRequestManager, BitswapSession, BitswapStoreRead, BitswapStoreReadWrite,
 };
 use crate::message::SignedMessage;
-use crate::{blocks::GossipBlock, rpc_api::net_api::NetInfoResult};
+use crate::rpc_api::net_api::{
+    ConnectionDirection, ExtendedPeerInfo, NatReachability, NetInfoResult, NetReachabilityResult,
+};
 use crate::{chain::ChainStore, utils::encoding::from_slice_with_fallback};
 use ahash::{HashMap, HashSet};
 use anyhow::Context as _;
 use cid::Cid;
 use flume::Sender;
+use futures::future::Either;
 use futures::stream::StreamExt;
 use futures::{channel::oneshot::Sender as OneShotSender, select};
 use fvm_ipld_blockstore::Blockstore;
@@ -23,12 +29,18 @@ use libp2p::connection_limits::Exceeded;
 pub use libp2p::gossipsub::{IdentTopic, Topic};
 use libp2p::swarm::DialError;
 use libp2p::{
-    core::{self, muxing::StreamMuxerBox, transport::Boxed, Multiaddr},
-    gossipsub,
+    autonat,
+    core::{
+        self,
+        muxing::StreamMuxerBox,
+        transport::{Boxed, OrTransport},
+        Multiaddr,
+    },
+    gossipsub::{self, MessageAcceptance},
     identity::Keypair,
     metrics::{Metrics, Recorder},
     multiaddr::Protocol,
-    noise, ping,
+    noise, ping, relay,
     request_response::{self, RequestId, ResponseChannel},
     swarm::{self, SwarmEvent},
     yamux, PeerId, Swarm, Transport,
@@ -37,15 +49,18 @@ use tokio_stream::wrappers::IntervalStream;
 use tracing::{debug, error, info, trace, warn};
 
 use super::{
+    certexchange::{make_cert_exchange_response, CertExchangeRequest, CertExchangeResponse},
     chain_exchange::{make_chain_exchange_response, ChainExchangeRequest, ChainExchangeResponse},
     ForestBehaviour, ForestBehaviourEvent, Libp2pConfig,
 };
 use crate::libp2p::{
+    certexchange::CertExchangeBehaviour,
     chain_exchange::ChainExchangeBehaviour,
     discovery::DiscoveryEvent,
     hello::{HelloBehaviour, HelloRequest, HelloResponse},
+    metrics as gossip_metrics,
     rpc::RequestResponseError,
-    PeerManager, PeerOperation,
+    PeerManager, PeerOperation, Peerstore,
 };
 
 pub(in crate::libp2p) mod metrics {
@@ -70,7 +85,28 @@ pub(in crate::libp2p) mod metrics {
         })
     };
 
+    pub static CONNECTIONS: Lazy<Box<GenericGaugeVec<AtomicU64>>> = Lazy::new(|| {
+        let connections = Box::new(
+            GenericGaugeVec::<AtomicU64>::new(
+                Opts::new("libp2p_connections", "Number of libp2p connections"),
+                &[labels::KIND],
+            )
+            .expect("Defining the libp2p_connections metric must succeed"),
+        );
+        prometheus::default_registry()
+            .register(connections.clone())
+            .expect(
+                "Registering the libp2p_connections metric with the metrics registry must succeed",
+            );
+        connections
+    });
+
     pub mod values {
+        pub const ESTABLISHED_INBOUND: &str = "established_inbound";
+        pub const ESTABLISHED_OUTBOUND: &str = "established_outbound";
+        pub const PENDING_INBOUND: &str = "pending_inbound";
+        pub const PENDING_OUTBOUND: &str = "pending_outbound";
+
         pub const HELLO_REQUEST_TABLE: &str = "hello_request_table";
         pub const CHAIN_EXCHANGE_REQUEST_TABLE: &str = "cx_request_table";
     }
@@ -91,6 +127,9 @@ pub const BITSWAP_TIMEOUT: Duration = Duration::from_secs(10);
 
 const BAN_PEER_DURATION: Duration = Duration::from_secs(60 * 60); //1h
 
+/// Interval at which the known peers are saved.
+const PEERSTORE_SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
 /// Events emitted by this Service.
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
@@ -125,6 +164,9 @@ pub enum NetworkEvent {
     ChainExchangeResponseOutbound {
         request_id: RequestId,
     },
+    CertExchangeRequestInbound {
+        request_id: RequestId,
+    },
     PeerConnected(PeerId),
     PeerDisconnected(PeerId),
 }
@@ -159,12 +201,20 @@ pub enum NetworkMessage {
     BitswapRequest {
         cid: Cid,
         response_channel: flume::Sender<bool>,
+        session: Option<BitswapSession>,
     },
     JSONRPCRequest {
         method: NetRPCMethods,
     },
 }
 
+/// Number of connected peers by gossip topic and by protocol.
+#[derive(Debug, Default)]
+pub struct PeerCounts {
+    pub by_topic: HashMap<String, usize>,
+    pub by_protocol: HashMap<String, usize>,
+}
+
 /// Network RPC API methods used to gather data from libp2p node.
 #[derive(Debug)]
 pub enum NetRPCMethods {
@@ -173,6 +223,17 @@ pub enum NetRPCMethods {
     Info(OneShotSender<NetInfoResult>),
     Connect(OneShotSender<bool>, PeerId, HashSet<Multiaddr>),
     Disconnect(OneShotSender<()>, PeerId),
+    Reachability(OneShotSender<NetReachabilityResult>),
+    PubsubScores(OneShotSender<Vec<(PeerId, f64)>>),
+    PeerInfo(OneShotSender<Option<ExtendedPeerInfo>>, PeerId),
+    AgentVersion(OneShotSender<Option<String>>, PeerId),
+    BanPeer(OneShotSender<bool>, PeerId, Option<Duration>),
+    UnbanPeer(OneShotSender<bool>, PeerId),
+    ProtectPeers(OneShotSender<()>, HashSet<PeerId>),
+    UnprotectPeers(OneShotSender<()>, HashSet<PeerId>),
+    ProtectedPeers(OneShotSender<HashSet<PeerId>>),
+    FindPeer(OneShotSender<Option<HashSet<Multiaddr>>>, PeerId),
+    PeerCounts(OneShotSender<PeerCounts>),
 }
 
 /// The `Libp2pService` listens to events from the libp2p swarm.
@@ -186,6 +247,8 @@ pub struct Libp2pService<DB> {
     network_sender_out: Sender<NetworkEvent>,
     network_name: String,
     genesis_cid: Cid,
+    /// Known peers, saved in the settings store if enabled.
+    peerstore: Option<Peerstore>,
 }
 
 impl<DB> Libp2pService<DB>
@@ -202,12 +265,18 @@ where
     ) -> anyhow::Result<Self> {
         let peer_id = PeerId::from(net_keypair.public());
 
-        let transport =
-            build_transport(net_keypair.clone()).expect("Failed to build libp2p transport");
+        let (relay_transport, relay_behaviour) = relay::client::new(peer_id);
+        let transport = build_transport(
+            net_keypair.clone(),
+            config.quic,
+            config.max_streams_per_connection,
+            relay_transport,
+        )
+        .expect("Failed to build libp2p transport");
 
         let mut swarm = Swarm::new(
             transport,
-            ForestBehaviour::new(&net_keypair, &config, network_name)?,
+            ForestBehaviour::new(&net_keypair, &config, network_name, relay_behaviour)?,
             peer_id,
             swarm::Config::with_tokio_executor()
                 .with_notify_handler_buffer_size(std::num::NonZeroUsize::new(20).expect("Not zero"))
@@ -231,6 +300,10 @@ where
 
         // Listen on network endpoints before being detached and connecting to any peers.
         for addr in &config.listening_multiaddrs {
+            if !config.quic && addr.iter().any(|p| matches!(p, Protocol::QuicV1)) {
+                warn!("QUIC is disabled, not listening on {addr}");
+                continue;
+            }
             match swarm.listen_on(addr.clone()) {
                 Ok(id) => loop {
                     if let SwarmEvent::NewListenAddr {
@@ -252,6 +325,32 @@ where
             anyhow::bail!("p2p peer failed to listen on any network endpoints");
         }
 
+        for addr in &config.announce_addresses {
+            info!("p2p peer is announcing: {addr}");
+            swarm.add_external_address(addr.clone());
+        }
+
+        for addr in &config.static_peers {
+            if let Some(Protocol::P2p(peer_id)) = addr.iter().last() {
+                peer_manager.protect_peer(peer_id).await;
+            }
+        }
+
+        let peerstore = if config.persist_peerstore {
+            let peerstore = Peerstore::load(cs.settings().as_ref()).unwrap_or_else(|e| {
+                warn!("Failed to load the peerstore: {e}");
+                Peerstore::default()
+            });
+            let known_peers = peerstore.best_peers(config.target_peer_count as usize);
+            info!("Dialing {} peers from the peerstore", known_peers.len());
+            for (peer_id, addrs) in known_peers {
+                swarm.behaviour_mut().add_known_peer(peer_id, addrs);
+            }
+            Some(peerstore)
+        } else {
+            None
+        };
+
         Ok(Libp2pService {
             swarm,
             cs,
@@ -262,6 +361,7 @@ where
             network_sender_out,
             network_name: network_name.into(),
             genesis_cid,
+            peerstore,
         })
     }
 
@@ -276,10 +376,14 @@ where
         }
 
         let bitswap_request_manager = self.swarm.behaviour().bitswap.request_manager();
+        let settings = self.cs.settings();
+        let mut peerstore = self.peerstore.take();
         let mut swarm_stream = self.swarm.fuse();
         let mut network_stream = self.network_receiver_in.stream().fuse();
         let mut interval =
             IntervalStream::new(tokio::time::interval(Duration::from_secs(15))).fuse();
+        let mut peerstore_save_interval =
+            IntervalStream::new(tokio::time::interval(PEERSTORE_SAVE_INTERVAL)).fuse();
         let pubsub_block_str = format!("{}/{}", PUBSUB_BLOCK_STR, self.network_name);
         let pubsub_msg_str = format!("{}/{}", PUBSUB_MSG_STR, self.network_name);
 
@@ -322,6 +426,7 @@ where
                             swarm_stream.get_mut(),
                             self.cs.clone(),
                             bitswap_request_manager.clone(),
+                            &self.peer_manager,
                             message,
                             &self.network_sender_out).await;
                     }
@@ -330,6 +435,13 @@ where
                 interval_event = interval.next() => if interval_event.is_some() {
                     // Print peer count on an interval.
                     debug!("Peers connected: {}", swarm_stream.get_mut().behaviour_mut().peers().len());
+                    update_connection_metrics(swarm_stream.get_mut());
+                    if let Some(peerstore) = peerstore.as_mut() {
+                        update_peerstore(swarm_stream.get_mut(), peerstore);
+                    }
+                },
+                save_event = peerstore_save_interval.next() => if save_event.is_some() {
+                    save_peerstore(peerstore.as_mut(), settings.as_ref());
                 },
                 cs_pair_opt = cx_response_rx_stream.next() => {
                     if let Some((_request_id, channel, cx_response)) = cs_pair_opt {
@@ -347,11 +459,12 @@ where
                 }
                 peer_ops_opt = peer_ops_rx_stream.next() => {
                     if let Some(peer_ops) = peer_ops_opt {
-                        handle_peer_ops(swarm_stream.get_mut(), peer_ops);
+                        handle_peer_ops(swarm_stream.get_mut(), peer_ops, peerstore.as_mut());
                     }
                 },
             };
         }
+        save_peerstore(peerstore.as_mut(), settings.as_ref());
         Ok(())
     }
 
@@ -366,12 +479,71 @@ where
     }
 }
 
-fn handle_peer_ops(swarm: &mut Swarm<ForestBehaviour>, peer_ops: PeerOperation) {
+fn update_connection_metrics(swarm: &Swarm<ForestBehaviour>) {
+    let info = swarm.network_info();
+    let counters = info.connection_counters();
+    for (kind, count) in [
+        (
+            metrics::values::ESTABLISHED_INBOUND,
+            counters.num_established_incoming(),
+        ),
+        (
+            metrics::values::ESTABLISHED_OUTBOUND,
+            counters.num_established_outgoing(),
+        ),
+        (
+            metrics::values::PENDING_INBOUND,
+            counters.num_pending_incoming(),
+        ),
+        (
+            metrics::values::PENDING_OUTBOUND,
+            counters.num_pending_outgoing(),
+        ),
+    ] {
+        metrics::CONNECTIONS
+            .with_label_values(&[kind])
+            .set(count as u64);
+    }
+}
+
+fn save_peerstore(peerstore: Option<&mut Peerstore>, settings: &dyn SettingsStore) {
+    if let Some(peerstore) = peerstore {
+        match peerstore.save(settings) {
+            Ok(()) => debug!("Saved {} peers to the peerstore", peerstore.len()),
+            Err(e) => warn!("Failed to save the peerstore: {e}"),
+        }
+    }
+}
+
+/// Records the addresses and latency of the connected peers.
+fn update_peerstore(swarm: &mut Swarm<ForestBehaviour>, peerstore: &mut Peerstore) {
+    let now = chrono::Utc::now().timestamp();
+    let behaviour = swarm.behaviour_mut();
+    let latencies: Vec<_> = behaviour
+        .peers()
+        .iter()
+        .map(|peer| (*peer, behaviour.peer_details(peer).and_then(|d| d.latency)))
+        .collect();
+    let addresses = behaviour.peer_addresses();
+    for (peer, latency) in latencies {
+        let addrs = addresses.get(&peer).into_iter().flatten();
+        peerstore.record_peer(peer, addrs, latency, now);
+    }
+}
+
+fn handle_peer_ops(
+    swarm: &mut Swarm<ForestBehaviour>,
+    peer_ops: PeerOperation,
+    peerstore: Option<&mut Peerstore>,
+) {
     use PeerOperation::*;
     match peer_ops {
         Ban(peer_id, reason) => {
             warn!("Banning {peer_id}, reason: {reason}");
             swarm.behaviour_mut().blocked_peers.block_peer(peer_id);
+            if let Some(peerstore) = peerstore {
+                peerstore.remove_peer(&peer_id);
+            }
         }
         Unban(peer_id) => {
             info!("Unbanning {peer_id}");
@@ -384,6 +556,7 @@ async fn handle_network_message(
     swarm: &mut Swarm<ForestBehaviour>,
     store: Arc<impl BitswapStoreReadWrite>,
     bitswap_request_manager: Arc<BitswapRequestManager>,
+    peer_manager: &Arc<PeerManager>,
     message: NetworkMessage,
     network_sender_out: &Sender<NetworkEvent>,
 ) {
@@ -428,8 +601,15 @@ async fn handle_network_message(
         NetworkMessage::BitswapRequest {
             cid,
             response_channel,
+            session,
         } => {
-            bitswap_request_manager.get_block(store, cid, BITSWAP_TIMEOUT, Some(response_channel));
+            bitswap_request_manager.get_block(
+                store,
+                cid,
+                BITSWAP_TIMEOUT,
+                Some(response_channel),
+                session,
+            );
         }
         NetworkMessage::JSONRPCRequest { method } => {
             match method {
@@ -492,6 +672,126 @@ async fn handle_network_message(
                         warn!("Failed to disconnect from a peer");
                     }
                 }
+                NetRPCMethods::Reachability(response_channel) => {
+                    let result = match swarm.behaviour().nat_status() {
+                        autonat::NatStatus::Public(addr) => NetReachabilityResult {
+                            reachability: NatReachability::Public,
+                            public_addrs: vec![addr],
+                        },
+                        autonat::NatStatus::Private => NetReachabilityResult {
+                            reachability: NatReachability::Private,
+                            public_addrs: vec![],
+                        },
+                        autonat::NatStatus::Unknown => NetReachabilityResult {
+                            reachability: NatReachability::Unknown,
+                            public_addrs: vec![],
+                        },
+                    };
+                    if response_channel.send(result).is_err() {
+                        warn!("Failed to get Libp2p reachability");
+                    }
+                }
+                NetRPCMethods::PubsubScores(response_channel) => {
+                    if response_channel
+                        .send(swarm.behaviour().peer_scores())
+                        .is_err()
+                    {
+                        warn!("Failed to get gossipsub peer scores");
+                    }
+                }
+                NetRPCMethods::FindPeer(response_channel, peer_id) => {
+                    swarm.behaviour_mut().find_peer(peer_id, response_channel);
+                }
+                NetRPCMethods::PeerCounts(response_channel) => {
+                    let behaviour = swarm.behaviour();
+                    let counts = PeerCounts {
+                        by_topic: behaviour.topic_peer_counts(),
+                        by_protocol: behaviour.protocol_peer_counts(),
+                    };
+                    if response_channel.send(counts).is_err() {
+                        warn!("Failed to get Libp2p peer counts");
+                    }
+                }
+                NetRPCMethods::PeerInfo(response_channel, peer_id) => {
+                    let protected = peer_manager.is_protected(&peer_id).await;
+                    let behaviour = swarm.behaviour_mut();
+                    let info =
+                        behaviour
+                            .peer_details(&peer_id)
+                            .cloned()
+                            .map(|details| ExtendedPeerInfo {
+                                id: peer_id.to_string(),
+                                agent: details.agent_version.unwrap_or_default(),
+                                addrs: behaviour
+                                    .peer_addresses()
+                                    .get(&peer_id)
+                                    .into_iter()
+                                    .flatten()
+                                    .map(|addr| addr.to_string())
+                                    .collect(),
+                                protocols: details
+                                    .protocols
+                                    .iter()
+                                    .map(|p| p.to_string())
+                                    .collect(),
+                                direction: match details.endpoint {
+                                    core::Endpoint::Dialer => ConnectionDirection::Outbound,
+                                    core::Endpoint::Listener => ConnectionDirection::Inbound,
+                                },
+                                latency: details.latency.map(|rtt| rtt.as_nanos() as u64),
+                                protected,
+                            });
+                    if response_channel.send(info).is_err() {
+                        warn!("Failed to get Libp2p peer info");
+                    }
+                }
+                NetRPCMethods::AgentVersion(response_channel, peer_id) => {
+                    let agent = swarm
+                        .behaviour_mut()
+                        .peer_details(&peer_id)
+                        .map(|details| details.agent_version.clone().unwrap_or_default());
+                    if response_channel.send(agent).is_err() {
+                        warn!("Failed to get Libp2p peer agent version");
+                    }
+                }
+                NetRPCMethods::BanPeer(response_channel, peer_id, duration) => {
+                    let banned = peer_manager
+                        .ban_peer(peer_id, "Banned through RPC", duration)
+                        .await;
+                    if response_channel.send(banned).is_err() {
+                        warn!("Failed to ban a peer");
+                    }
+                }
+                NetRPCMethods::UnbanPeer(response_channel, peer_id) => {
+                    let unbanned = peer_manager.unban_peer(peer_id).await;
+                    if response_channel.send(unbanned).is_err() {
+                        warn!("Failed to unban a peer");
+                    }
+                }
+                NetRPCMethods::ProtectPeers(response_channel, peer_ids) => {
+                    for peer_id in peer_ids {
+                        peer_manager.protect_peer(peer_id).await;
+                    }
+                    if response_channel.send(()).is_err() {
+                        warn!("Failed to protect peers");
+                    }
+                }
+                NetRPCMethods::UnprotectPeers(response_channel, peer_ids) => {
+                    for peer_id in &peer_ids {
+                        peer_manager.unprotect_peer(peer_id).await;
+                    }
+                    if response_channel.send(()).is_err() {
+                        warn!("Failed to unprotect peers");
+                    }
+                }
+                NetRPCMethods::ProtectedPeers(response_channel) => {
+                    if response_channel
+                        .send(peer_manager.protected_peers().await)
+                        .is_err()
+                    {
+                        warn!("Failed to get protected peers");
+                    }
+                }
             }
         }
     }
@@ -515,6 +815,7 @@ async fn handle_discovery_event(
 }
 
 async fn handle_gossip_event(
+    behaviour: &mut ForestBehaviour,
     e: gossipsub::Event,
     network_sender_out: &Sender<NetworkEvent>,
     pubsub_block_str: &str,
@@ -523,46 +824,60 @@ async fn handle_gossip_event(
     if let gossipsub::Event::Message {
         propagation_source: source,
         message,
-        message_id: _,
+        message_id,
     } = e
     {
         let topic = message.topic.as_str();
         let message = message.data;
         trace!("Got a Gossip Message from {:?}", source);
-        if topic == pubsub_block_str {
-            match from_slice_with_fallback::<GossipBlock>(&message) {
-                Ok(b) => {
-                    emit_event(
-                        network_sender_out,
-                        NetworkEvent::PubsubMessage {
-                            source,
-                            message: PubsubMessage::Block(b),
-                        },
-                    )
-                    .await;
-                }
-                Err(e) => {
-                    warn!("Gossip Block from peer {source:?} could not be deserialized: {e}",);
-                }
-            }
+        let topic_label = if topic == pubsub_block_str {
+            gossip_metrics::values::BLOCKS_TOPIC
         } else if topic == pubsub_msg_str {
-            match from_slice_with_fallback::<SignedMessage>(&message) {
-                Ok(m) => {
-                    emit_event(
-                        network_sender_out,
-                        NetworkEvent::PubsubMessage {
-                            source,
-                            message: PubsubMessage::Message(m),
-                        },
-                    )
-                    .await;
-                }
-                Err(e) => {
-                    warn!("Gossip Message from peer {source:?} could not be deserialized: {e}");
-                }
-            }
+            gossip_metrics::values::MESSAGES_TOPIC
+        } else {
+            gossip_metrics::values::UNKNOWN_TOPIC
+        };
+        gossip_metrics::GOSSIPSUB_MESSAGES
+            .with_label_values(&[topic_label, gossip_metrics::peer_bucket(&source)])
+            .inc();
+        let report = |behaviour: &mut ForestBehaviour, acceptance: MessageAcceptance| {
+            let result = match acceptance {
+                MessageAcceptance::Accept => gossip_metrics::values::ACCEPT,
+                MessageAcceptance::Reject => gossip_metrics::values::REJECT,
+                MessageAcceptance::Ignore => gossip_metrics::values::IGNORE,
+            };
+            gossip_metrics::GOSSIPSUB_VALIDATIONS
+                .with_label_values(&[topic_label, result])
+                .inc();
+            behaviour.report_message_validation_result(&message_id, &source, acceptance);
+        };
+        let pubsub_message = if topic == pubsub_block_str {
+            from_slice_with_fallback::<GossipBlock>(&message)
+                .map(PubsubMessage::Block)
+                .map_err(|e| {
+                    warn!("Gossip Block from peer {source:?} could not be deserialized: {e}",)
+                })
+        } else if topic == pubsub_msg_str {
+            from_slice_with_fallback::<SignedMessage>(&message)
+                .map(PubsubMessage::Message)
+                .map_err(|e| {
+                    warn!("Gossip Message from peer {source:?} could not be deserialized: {e}")
+                })
         } else {
             warn!("Getting gossip messages from unknown topic: {topic}");
+            report(behaviour, MessageAcceptance::Ignore);
+            return;
+        };
+        match pubsub_message {
+            Ok(message) => {
+                report(behaviour, MessageAcceptance::Accept);
+                emit_event(
+                    network_sender_out,
+                    NetworkEvent::PubsubMessage { source, message },
+                )
+                .await;
+            }
+            Err(()) => report(behaviour, MessageAcceptance::Reject),
         }
     }
 }
@@ -664,9 +979,14 @@ async fn handle_hello_event(
     }
 }
 
-async fn handle_ping_event(ping_event: ping::Event, peer_manager: &Arc<PeerManager>) {
+async fn handle_ping_event(
+    behaviour: &mut ForestBehaviour,
+    ping_event: ping::Event,
+    peer_manager: &Arc<PeerManager>,
+) {
     match ping_event.result {
         Ok(rtt) => {
+            behaviour.record_latency(&ping_event.peer, rtt);
             trace!(
                 "PingSuccess::Ping rtt to {} is {} ms",
                 ping_event.peer.to_base58(),
@@ -771,6 +1091,51 @@ async fn handle_chain_exchange_event<DB>(
     }
 }
 
+async fn handle_cert_exchange_event<DB>(
+    cert_exchange: &mut CertExchangeBehaviour,
+    event: request_response::Event<CertExchangeRequest, CertExchangeResponse>,
+    db: &Arc<ChainStore<DB>>,
+    network_sender_out: &Sender<NetworkEvent>,
+) where
+    DB: Blockstore + Sync + Send + 'static,
+{
+    match event {
+        request_response::Event::Message { peer, message } => match message {
+            request_response::Message::Request {
+                request,
+                channel,
+                request_id,
+            } => {
+                trace!("Received cert exchange request from {peer}: {request:?}");
+                emit_event(
+                    network_sender_out,
+                    NetworkEvent::CertExchangeRequestInbound { request_id },
+                )
+                .await;
+                // Certificates are small and read from the settings store, so the response is
+                // sent immediately.
+                let response =
+                    make_cert_exchange_response(&CertStore::new(db.settings()), &request);
+                if let Err(e) = cert_exchange.send_response(channel, response) {
+                    debug!("Failed to send CertExchangeResponse: {e:?}");
+                }
+            }
+            request_response::Message::Response { .. } => {
+                debug!("Ignoring unsolicited CertExchangeResponse from {peer}");
+            }
+        },
+        request_response::Event::OutboundFailure { .. } => (),
+        request_response::Event::InboundFailure {
+            peer,
+            error,
+            request_id: _,
+        } => {
+            debug!("CertExchange inbound error (peer: {peer:?}): {error:?}");
+        }
+        request_response::Event::ResponseSent { .. } => (),
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn handle_forest_behaviour_event<DB>(
     swarm: &mut Swarm<ForestBehaviour>,
@@ -792,10 +1157,20 @@ async fn handle_forest_behaviour_event<DB>(
 {
     match event {
         ForestBehaviourEvent::Discovery(discovery_out) => {
+            if let DiscoveryEvent::PeerConnected(peer_id) = &discovery_out {
+                swarm.behaviour_mut().on_peer_connected(peer_id);
+            }
             handle_discovery_event(discovery_out, network_sender_out).await
         }
         ForestBehaviourEvent::Gossipsub(e) => {
-            handle_gossip_event(e, network_sender_out, pubsub_block_str, pubsub_msg_str).await
+            handle_gossip_event(
+                swarm.behaviour_mut(),
+                e,
+                network_sender_out,
+                pubsub_block_str,
+                pubsub_msg_str,
+            )
+            .await
         }
         ForestBehaviourEvent::Hello(rr_event) => {
             handle_hello_event(
@@ -816,8 +1191,13 @@ async fn handle_forest_behaviour_event<DB>(
                 warn!("bitswap: {e}");
             }
         }
-        ForestBehaviourEvent::Ping(ping_event) => handle_ping_event(ping_event, peer_manager).await,
+        ForestBehaviourEvent::Ping(ping_event) => {
+            handle_ping_event(swarm.behaviour_mut(), ping_event, peer_manager).await
+        }
         ForestBehaviourEvent::ConnectionLimits(_) => {}
+        ForestBehaviourEvent::MemoryConnectionLimits(_) => {}
+        ForestBehaviourEvent::Relay(e) => debug!("Relay client: {e:?}"),
+        ForestBehaviourEvent::Dcutr(e) => debug!("Hole punching: {e:?}"),
         ForestBehaviourEvent::BlockedPeers(_) => {}
         ForestBehaviourEvent::ChainExchange(ce_event) => {
             handle_chain_exchange_event(
@@ -829,6 +1209,15 @@ async fn handle_forest_behaviour_event<DB>(
             )
             .await
         }
+        ForestBehaviourEvent::CertExchange(event) => {
+            handle_cert_exchange_event(
+                &mut swarm.behaviour_mut().cert_exchange,
+                event,
+                db,
+                network_sender_out,
+            )
+            .await
+        }
     }
 }
 
@@ -838,23 +1227,49 @@ async fn emit_event(sender: &Sender<NetworkEvent>, event: NetworkEvent) {
     }
 }
 
-/// Builds the transport stack that libp2p will communicate over. When support
-/// of other protocols like `udp`, `quic`, `http` are added, remember to update
+/// Builds the transport stack that libp2p will communicate over: TCP and
+/// relayed connections with `noise` and `yamux` and, if `quic` is set, QUIC.
+/// When support of other protocols like `http` is added, remember to update
 /// code comment in [`Libp2pConfig`].
 ///
 /// As a reference `lotus` uses the default `go-libp2p` transport builder which
 /// has all above protocols enabled.
-pub fn build_transport(local_key: Keypair) -> anyhow::Result<Boxed<(PeerId, StreamMuxerBox)>> {
-    let build_tcp = || libp2p::tcp::tokio::Transport::new(libp2p::tcp::Config::new().nodelay(true));
-    let build_dns_tcp = || libp2p::dns::tokio::Transport::system(build_tcp());
-    let transport = build_dns_tcp()?;
-
+pub fn build_transport(
+    local_key: Keypair,
+    quic: bool,
+    max_streams_per_connection: u32,
+    relay: relay::client::Transport,
+) -> anyhow::Result<Boxed<(PeerId, StreamMuxerBox)>> {
     let auth_config = noise::Config::new(&local_key).context("Noise key generation failed")?;
-
-    Ok(transport
+    let tcp = libp2p::dns::tokio::Transport::system(libp2p::tcp::tokio::Transport::new(
+        libp2p::tcp::Config::new().nodelay(true),
+    ))?;
+    let tcp_or_relay = OrTransport::new(relay, tcp)
         .upgrade(core::upgrade::Version::V1)
         .authenticate(auth_config)
-        .multiplex(yamux::Config::default())
-        .timeout(Duration::from_secs(20))
-        .boxed())
+        .multiplex({
+            let mut yamux_config = yamux::Config::default();
+            yamux_config.set_max_num_streams(max_streams_per_connection as usize);
+            yamux_config
+        })
+        .timeout(Duration::from_secs(20));
+
+    Ok(if quic {
+        // QUIC comes with its own encryption and multiplexing
+        let mut quic_config = libp2p::quic::Config::new(&local_key);
+        quic_config.max_concurrent_stream_limit = max_streams_per_connection;
+        let quic = libp2p::dns::tokio::Transport::system(libp2p::quic::tokio::Transport::new(
+            quic_config,
+        ))?;
+        OrTransport::new(quic, tcp_or_relay)
+            .map(|output, _| match output {
+                Either::Left((peer_id, connection)) => (peer_id, StreamMuxerBox::new(connection)),
+                Either::Right((peer_id, muxer)) => (peer_id, StreamMuxerBox::new(muxer)),
+            })
+            .boxed()
+    } else {
+        tcp_or_relay
+            .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+            .boxed()
+    })
 }
@@ -9,7 +9,7 @@ use libp2p::{
     PeerId,
 };
 
-use crate::libp2p_bitswap::{codec::*, request_manager::*, *};
+use crate::libp2p_bitswap::{codec::*, request_manager::*, server::BitswapServer, *};
 
 /// `libp2p` swarm network behavior event of `bitswap`
 pub type BitswapBehaviourEvent = request_response::Event<Vec<BitswapMessage>, ()>;
@@ -19,6 +19,7 @@ pub type BitswapBehaviourEvent = request_response::Event<Vec<BitswapMessage>, ()
 pub struct BitswapBehaviour {
     inner: request_response::Behaviour<BitswapRequestResponseCodec>,
     request_manager: Arc<BitswapRequestManager>,
+    server: BitswapServer,
 }
 
 impl BitswapBehaviour {
@@ -33,9 +34,20 @@ impl BitswapBehaviour {
         BitswapBehaviour {
             inner: request_response::Behaviour::new(protocols, cfg),
             request_manager: Default::default(),
+            server: Default::default(),
         }
     }
 
+    /// Sets the limits on serving the want-lists of peers
+    pub fn with_server_config(mut self, config: BitswapServerConfig) -> Self {
+        self.server = BitswapServer::new(config);
+        self
+    }
+
+    pub(in crate::libp2p_bitswap) fn server_mut(&mut self) -> &mut BitswapServer {
+        &mut self.server
+    }
+
     /// Gets mutable borrow of the inner [`request_response::Behaviour`]
     pub fn inner_mut(&mut self) -> &mut request_response::Behaviour<BitswapRequestResponseCodec> {
         &mut self.inner
@@ -45,10 +57,17 @@ impl BitswapBehaviour {
     pub fn send_request(&mut self, peer: &PeerId, request: BitswapRequest) -> RequestId {
         if request.cancel {
             metrics::message_counter_outbound_request_cancel().inc();
+            metrics::peer_request("cancel", peer).inc();
         } else {
             match request.ty {
-                RequestType::Have => metrics::message_counter_outbound_request_have().inc(),
-                RequestType::Block => metrics::message_counter_outbound_request_block().inc(),
+                RequestType::Have => {
+                    metrics::message_counter_outbound_request_have().inc();
+                    metrics::peer_request("have", peer).inc();
+                }
+                RequestType::Block => {
+                    metrics::message_counter_outbound_request_block().inc();
+                    metrics::peer_request("block", peer).inc();
+                }
             }
         }
         self.inner
@@ -5,7 +5,7 @@ use std::sync::Arc;
 
 use libp2p::{request_response, PeerId};
 
-use crate::libp2p_bitswap::{request_manager::*, *};
+use crate::libp2p_bitswap::{request_manager::*, server::BitswapServer, *};
 
 #[derive(Debug, Clone)]
 pub enum BitswapInboundResponseEvent {
@@ -33,7 +33,12 @@ pub fn handle_event_impl<S: BitswapStoreRead>(
                 for message in request {
                     match message {
                         BitswapMessage::Request(request) => {
-                            if let Some(response) = handle_inbound_request(store, &request) {
+                            if !bitswap.server_mut().allow_request(&peer) {
+                                continue;
+                            }
+                            if let Some(response) =
+                                handle_inbound_request(bitswap.server_mut(), store, &request)
+                            {
                                 bitswap.send_response(&peer, (request.cid, response));
                             }
                         }
@@ -69,6 +74,7 @@ pub fn handle_event_impl<S: BitswapStoreRead>(
 }
 
 fn handle_inbound_request<S: BitswapStoreRead>(
+    server: &mut BitswapServer,
     store: &S,
     request: &BitswapRequest,
 ) -> Option<BitswapResponse> {
@@ -89,7 +95,7 @@ fn handle_inbound_request<S: BitswapStoreRead>(
         RequestType::Block => {
             metrics::message_counter_inbound_request_block().inc();
             let block = store.get(&request.cid).ok().unwrap_or_default();
-            if let Some(data) = block {
+            if let Some(data) = block.filter(|data| server.allow_block(data.len())) {
                 Some(BitswapResponse::Block(data))
             } else if request.send_dont_have {
                 Some(BitswapResponse::Have(false))
@@ -1,6 +1,8 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use crate::libp2p::metrics::peer_bucket;
+use libp2p::PeerId;
 use once_cell::sync::Lazy;
 use prometheus::{core::*, *};
 
@@ -37,6 +39,49 @@ pub(in crate::libp2p_bitswap) static GET_BLOCK_TIME: Lazy<Histogram> = Lazy::new
     })
     .expect("Infallible")
 });
+static PEER_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "bitswap_peer_requests_total",
+            "Number of bitswap requests sent, by peer bucket",
+        ),
+        &["type", "peer_bucket"],
+    )
+    .expect("Infallible")
+});
+static PEER_RESPONSES: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "bitswap_peer_responses_total",
+            "Number of bitswap responses received, by peer bucket",
+        ),
+        &["type", "peer_bucket"],
+    )
+    .expect("Infallible")
+});
+static PEER_TIMEOUTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "bitswap_peer_block_timeouts_total",
+            "Number of block requests a peer didn't answer in time, by peer bucket",
+        ),
+        &["peer_bucket"],
+    )
+    .expect("Infallible")
+});
+static PEER_RESPONSE_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    HistogramVec::new(
+        HistogramOpts {
+            common_opts: Opts::new(
+                "bitswap_peer_block_response_time",
+                "Time for a peer to answer a block request, by peer bucket",
+            ),
+            buckets: vec![0.01, 0.025, 0.05, 0.1, 0.2, 0.3, 0.5, 0.75, 1.0, 2.0, 5.0],
+        },
+        &["peer_bucket"],
+    )
+    .expect("Infallible")
+});
 
 /// Register bitswap metrics
 pub fn register_metrics(registry: &Registry) -> anyhow::Result<()> {
@@ -44,6 +89,10 @@ pub fn register_metrics(registry: &Registry) -> anyhow::Result<()> {
     registry.register(Box::new(MESSAGE_COUNTER.clone()))?;
     registry.register(Box::new(CONTAINER_CAPACITIES.clone()))?;
     registry.register(Box::new(GET_BLOCK_TIME.clone()))?;
+    registry.register(Box::new(PEER_REQUESTS.clone()))?;
+    registry.register(Box::new(PEER_RESPONSES.clone()))?;
+    registry.register(Box::new(PEER_TIMEOUTS.clone()))?;
+    registry.register(Box::new(PEER_RESPONSE_TIME.clone()))?;
 
     Ok(())
 }
@@ -56,6 +105,10 @@ pub(in crate::libp2p_bitswap) fn outbound_bytes() -> GenericCounter<AtomicU64> {
     MESSAGE_SIZE.with_label_values(&["outbound_bytes"])
 }
 
+pub(in crate::libp2p_bitswap) fn served_bytes() -> GenericCounter<AtomicU64> {
+    MESSAGE_SIZE.with_label_values(&["served_bytes"])
+}
+
 pub(in crate::libp2p_bitswap) fn inbound_stream_count() -> GenericCounter<AtomicU64> {
     MESSAGE_COUNTER.with_label_values(&["inbound_stream_count"])
 }
@@ -82,6 +135,16 @@ pub(in crate::libp2p_bitswap) fn message_counter_inbound_request_block() -> Gene
     MESSAGE_COUNTER.with_label_values(&["inbound_request_block"])
 }
 
+pub(in crate::libp2p_bitswap) fn message_counter_inbound_request_throttled(
+) -> GenericCounter<AtomicU64> {
+    MESSAGE_COUNTER.with_label_values(&["inbound_request_throttled"])
+}
+
+pub(in crate::libp2p_bitswap) fn message_counter_inbound_request_over_budget(
+) -> GenericCounter<AtomicU64> {
+    MESSAGE_COUNTER.with_label_values(&["inbound_request_over_budget"])
+}
+
 pub(in crate::libp2p_bitswap) fn message_counter_outbound_request_cancel(
 ) -> GenericCounter<AtomicU64> {
     MESSAGE_COUNTER.with_label_values(&["outbound_request_cancel"])
@@ -149,3 +212,25 @@ pub(in crate::libp2p_bitswap) fn peer_container_capacity() -> GenericGauge<Atomi
 pub(in crate::libp2p_bitswap) fn response_channel_container_capacity() -> GenericGauge<AtomicU64> {
     CONTAINER_CAPACITIES.with_label_values(&["response_channel_container_capacity"])
 }
+
+pub(in crate::libp2p_bitswap) fn peer_request(
+    ty: &str,
+    peer: &PeerId,
+) -> GenericCounter<AtomicU64> {
+    PEER_REQUESTS.with_label_values(&[ty, peer_bucket(peer)])
+}
+
+pub(in crate::libp2p_bitswap) fn peer_response(
+    ty: &str,
+    peer: &PeerId,
+) -> GenericCounter<AtomicU64> {
+    PEER_RESPONSES.with_label_values(&[ty, peer_bucket(peer)])
+}
+
+pub(in crate::libp2p_bitswap) fn peer_block_timeout(peer: &PeerId) -> GenericCounter<AtomicU64> {
+    PEER_TIMEOUTS.with_label_values(&[peer_bucket(peer)])
+}
+
+pub(in crate::libp2p_bitswap) fn peer_block_response_time(peer: &PeerId) -> Histogram {
+    PEER_RESPONSE_TIME.with_label_values(&[peer_bucket(peer)])
+}
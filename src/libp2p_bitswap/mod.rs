@@ -41,6 +41,12 @@ pub use metrics::register_metrics;
 
 pub mod request_manager;
 
+mod server;
+pub use server::BitswapServerConfig;
+
+mod session;
+pub use session::BitswapSession;
+
 mod store;
 pub use store::*;
 
@@ -10,7 +10,7 @@ use std::{
 };
 
 use crate::cid_collections::CidHashMap;
-use ahash::{HashSet, HashSetExt};
+use ahash::{HashMap, HashSet, HashSetExt};
 use flume::TryRecvError;
 use libipld::{Block, Cid};
 use libp2p::PeerId;
@@ -18,12 +18,40 @@ use parking_lot::RwLock;
 
 use crate::libp2p_bitswap::{event_handlers::*, *};
 
+/// Time given to a peer to send a block before it's requested from another
+/// one.
 const BITSWAP_BLOCK_REQUEST_INTERVAL: Duration = Duration::from_millis(500);
+/// Interval at which `have` responses are checked while waiting for a block.
+const BITSWAP_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 #[derive(Debug, Clone)]
 struct ResponseChannels {
     block_have: flume::Sender<PeerId>,
-    block_received: flume::Sender<Option<Vec<u8>>>,
+    block_received: flume::Sender<(PeerId, Option<Vec<u8>>)>,
+}
+
+/// Past performance of a peer at sending blocks.
+#[derive(Debug, Default, Clone, Copy)]
+struct PeerStats {
+    /// Number of blocks received.
+    blocks: u32,
+    /// Number of `want-block` requests left unanswered in time.
+    timeouts: u32,
+    /// Average time to receive a block.
+    average_time: Duration,
+}
+
+impl PeerStats {
+    /// Lower is better. Unknown peers are assumed to answer in
+    /// [`BITSWAP_BLOCK_REQUEST_INTERVAL`].
+    fn cost(&self) -> f64 {
+        let average_time = if self.blocks == 0 {
+            BITSWAP_BLOCK_REQUEST_INTERVAL
+        } else {
+            self.average_time
+        };
+        average_time.as_secs_f64() * f64::from(self.timeouts + 1) / f64::from(self.blocks + 1)
+    }
 }
 
 /// Request manager implementation that is optimized for Filecoin network
@@ -33,6 +61,7 @@ pub struct BitswapRequestManager {
     outbound_request_tx: flume::Sender<(PeerId, BitswapRequest)>,
     outbound_request_rx: flume::Receiver<(PeerId, BitswapRequest)>,
     peers: RwLock<HashSet<PeerId>>,
+    peer_stats: RwLock<HashMap<PeerId, PeerStats>>,
     response_channels: RwLock<CidHashMap<ResponseChannels>>,
 }
 
@@ -53,6 +82,7 @@ impl Default for BitswapRequestManager {
             outbound_request_tx,
             outbound_request_rx,
             peers: RwLock::new(HashSet::new()),
+            peer_stats: Default::default(),
             response_channels: RwLock::new(CidHashMap::new()),
         }
     }
@@ -72,6 +102,9 @@ impl BitswapRequestManager {
     /// Gets a block, writing it to the given block store that implements
     /// [`BitswapStoreReadWrite`] and respond to the channel. Note: this
     /// method is a non-blocking, it is intended to return immediately.
+    ///
+    /// Requests of a [`BitswapSession`] ask the peers that provided the
+    /// previous blocks of the session first.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn get_block(
         self: Arc<Self>,
@@ -79,6 +112,7 @@ impl BitswapRequestManager {
         cid: Cid,
         timeout: Duration,
         responder: Option<flume::Sender<bool>>,
+        session: Option<BitswapSession>,
     ) {
         let start = Instant::now();
         let timer = metrics::GET_BLOCK_TIME.start_timer();
@@ -87,10 +121,11 @@ impl BitswapRequestManager {
             let mut success = store.contains(&cid).unwrap_or_default();
             if !success {
                 let deadline = start.checked_add(timeout).expect("Infallible");
-                success =
-                    task::spawn_blocking(move || self.get_block_sync(store_cloned, cid, deadline))
-                        .await
-                        .unwrap_or_default();
+                success = task::spawn_blocking(move || {
+                    self.get_block_sync(store_cloned, cid, deadline, session)
+                })
+                .await
+                .unwrap_or_default();
                 // Spin check db when `get_block_sync` fails fast,
                 // which means there is other task actually processing the same `cid`
                 while !success && Instant::now() < deadline {
@@ -120,6 +155,7 @@ impl BitswapRequestManager {
         store: Arc<impl BitswapStoreReadWrite>,
         cid: Cid,
         deadline: Instant,
+        session: Option<BitswapSession>,
     ) -> bool {
         // Fail fast here when the given `cid` is being processed by other tasks
         if self.response_channels.read().contains_key(&cid) {
@@ -136,37 +172,70 @@ impl BitswapRequestManager {
             self.response_channels.write().insert(cid, channels);
         }
 
-        let have_request = BitswapRequest::new_have(cid).send_dont_have(false);
-        for &peer in self.peers.read().iter() {
-            if let Err(e) = self.outbound_request_tx.send((peer, have_request.clone())) {
-                warn!("{e}");
+        // Peers of the session are presumed to have the block, the others are
+        // probed with `want-have` requests.
+        let mut candidates: Vec<PeerId> = {
+            let peers = self.peers.read();
+            let candidates: Vec<_> = session
+                .iter()
+                .flat_map(|session| session.providers())
+                .filter(|peer| peers.contains(peer))
+                .collect();
+            let have_request = BitswapRequest::new_have(cid).send_dont_have(false);
+            for &peer in peers.iter().filter(|peer| !candidates.contains(peer)) {
+                if let Err(e) = self.outbound_request_tx.send((peer, have_request.clone())) {
+                    warn!("{e}");
+                }
             }
-        }
+            candidates
+        };
 
+        // Only one peer at a time is asked for the block, to avoid receiving it
+        // multiple times. The next candidate is asked when it takes too long.
         let mut success = false;
         let mut block_data = None;
+        let mut asked = HashSet::new();
+        let mut pending: Option<(PeerId, Instant)> = None;
         let block_request = BitswapRequest::new_block(cid).send_dont_have(false);
-        while !success && Instant::now() < deadline {
-            match block_have_rx.try_recv() {
-                Ok(peer) => {
-                    _ = self.outbound_request_tx.send((peer, block_request.clone()));
+        'outer: while !success && Instant::now() < deadline {
+            loop {
+                match block_have_rx.try_recv() {
+                    Ok(peer) => {
+                        if !asked.contains(&peer) && !candidates.contains(&peer) {
+                            candidates.push(peer);
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => break 'outer,
                 }
-                Err(TryRecvError::Empty) => {}
-                Err(TryRecvError::Disconnected) => {
-                    break;
+            }
+
+            let timed_out = pending
+                .map(|(_, sent)| sent.elapsed() >= BITSWAP_BLOCK_REQUEST_INTERVAL)
+                .unwrap_or(true);
+            if timed_out && !candidates.is_empty() {
+                if let Some((peer, _)) = pending.take() {
+                    self.peer_stats.write().entry(peer).or_default().timeouts += 1;
+                    metrics::peer_block_timeout(&peer).inc();
                 }
+                let peer = self.pop_best_candidate(&mut candidates);
+                asked.insert(peer);
+                _ = self.outbound_request_tx.send((peer, block_request.clone()));
+                pending = Some((peer, Instant::now()));
             }
 
-            if let Ok(data) = block_saved_rx.recv_timeout(BITSWAP_BLOCK_REQUEST_INTERVAL) {
+            if let Ok((peer, data)) = block_saved_rx.recv_timeout(BITSWAP_POLL_INTERVAL) {
                 success = true;
                 block_data = data;
+                self.on_block_received(peer, pending, session.as_ref());
             }
         }
 
         if !success {
-            if let Ok(data) = block_saved_rx.recv_deadline(deadline) {
+            if let Ok((peer, data)) = block_saved_rx.recv_deadline(deadline) {
                 success = true;
                 block_data = data;
+                self.on_block_received(peer, pending, session.as_ref());
             }
         }
 
@@ -204,6 +273,41 @@ impl BitswapRequestManager {
         success
     }
 
+    /// Removes and returns the candidate with the best past performance.
+    fn pop_best_candidate(&self, candidates: &mut Vec<PeerId>) -> PeerId {
+        let stats = self.peer_stats.read();
+        let cost = |peer: &PeerId| stats.get(peer).copied().unwrap_or_default().cost();
+        let (best, _) = candidates
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| cost(a).total_cmp(&cost(b)))
+            .expect("candidates must not be empty");
+        candidates.remove(best)
+    }
+
+    fn on_block_received(
+        &self,
+        peer: PeerId,
+        pending: Option<(PeerId, Instant)>,
+        session: Option<&BitswapSession>,
+    ) {
+        if let Some(session) = session {
+            session.record_block(peer);
+        }
+        let mut peer_stats = self.peer_stats.write();
+        let stats = peer_stats.entry(peer).or_default();
+        if let Some((_, sent)) = pending.filter(|(pending_peer, _)| *pending_peer == peer) {
+            let elapsed = sent.elapsed();
+            metrics::peer_block_response_time(&peer).observe(elapsed.as_secs_f64());
+            stats.average_time = if stats.blocks == 0 {
+                elapsed
+            } else {
+                (stats.average_time * 4 + elapsed) / 5
+            };
+        }
+        stats.blocks += 1;
+    }
+
     pub(in crate::libp2p_bitswap) fn on_inbound_response_event<S: BitswapStoreRead>(
         &self,
         store: &S,
@@ -213,19 +317,21 @@ impl BitswapRequestManager {
 
         match response {
             HaveBlock(peer, cid) => {
+                metrics::peer_response("have", &peer).inc();
                 if let Some(chans) = self.response_channels.read().get(&cid) {
                     _ = chans.block_have.send(peer);
                 }
             }
-            DataBlock(_peer, cid, data) => {
+            DataBlock(peer, cid, data) => {
+                metrics::peer_response("block", &peer).inc();
                 if let Some(chans) = self.response_channels.read().get(&cid) {
                     if let Ok(true) = store.contains(&cid) {
                         // Avoid duplicate writes, still notify the receiver
                         metrics::message_counter_inbound_response_block_already_exists_in_db()
                             .inc();
-                        _ = chans.block_received.send(None);
+                        _ = chans.block_received.send((peer, None));
                     } else {
-                        _ = chans.block_received.send(Some(data));
+                        _ = chans.block_received.send((peer, Some(data)));
                     }
 
                     // <https://github.com/ipfs/go-libipfs/tree/main/bitswap#background>
@@ -260,6 +366,7 @@ impl BitswapRequestManager {
     pub(in crate::libp2p_bitswap) fn on_peer_disconnected(&self, peer: &PeerId) -> bool {
         let mut peers = self.peers.write();
         let success = peers.remove(peer);
+        self.peer_stats.write().remove(peer);
         if success {
             metrics::peer_container_capacity().set(peers.capacity() as _);
         }
@@ -0,0 +1,150 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Limits on how the want-lists of peers are served from the local store.
+
+use std::time::{Duration, Instant};
+
+use ahash::HashMap;
+use libp2p::PeerId;
+
+use crate::libp2p_bitswap::metrics;
+
+/// Length of the window the limits apply to.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Configuration of the blocks served to peers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitswapServerConfig {
+    /// Whether want-lists of peers are answered at all.
+    pub enabled: bool,
+    /// Maximum number of want-list entries answered per peer and second.
+    pub max_requests_per_peer: u32,
+    /// Maximum number of block bytes served per second, to all peers.
+    pub max_served_bytes: u64,
+}
+
+impl Default for BitswapServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_requests_per_peer: 500,
+            max_served_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Keeps track of the requests answered and of the bytes served in the
+/// current window.
+#[derive(Debug)]
+pub(in crate::libp2p_bitswap) struct BitswapServer {
+    config: BitswapServerConfig,
+    window_start: Instant,
+    requests: HashMap<PeerId, u32>,
+    served_bytes: u64,
+}
+
+impl BitswapServer {
+    pub fn new(config: BitswapServerConfig) -> Self {
+        Self {
+            config,
+            window_start: Instant::now(),
+            requests: HashMap::default(),
+            served_bytes: 0,
+        }
+    }
+
+    fn refresh(&mut self, now: Instant) {
+        if now.duration_since(self.window_start) >= WINDOW {
+            self.window_start = now;
+            self.requests.clear();
+            self.served_bytes = 0;
+        }
+    }
+
+    /// Returns whether a want-list entry of `peer` may be answered.
+    pub fn allow_request(&mut self, peer: &PeerId) -> bool {
+        self.allow_request_at(peer, Instant::now())
+    }
+
+    fn allow_request_at(&mut self, peer: &PeerId, now: Instant) -> bool {
+        if !self.config.enabled {
+            return false;
+        }
+        self.refresh(now);
+        let requests = self.requests.entry(*peer).or_default();
+        if *requests >= self.config.max_requests_per_peer {
+            metrics::message_counter_inbound_request_throttled().inc();
+            return false;
+        }
+        *requests += 1;
+        true
+    }
+
+    /// Returns whether a block of `size` bytes fits in the budget, in which
+    /// case it's deducted from it.
+    pub fn allow_block(&mut self, size: usize) -> bool {
+        self.allow_block_at(size, Instant::now())
+    }
+
+    fn allow_block_at(&mut self, size: usize, now: Instant) -> bool {
+        self.refresh(now);
+        let served_bytes = self.served_bytes.saturating_add(size as u64);
+        if served_bytes > self.config.max_served_bytes {
+            metrics::message_counter_inbound_request_over_budget().inc();
+            return false;
+        }
+        self.served_bytes = served_bytes;
+        metrics::served_bytes().inc_by(size as u64);
+        true
+    }
+}
+
+impl Default for BitswapServer {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_limited_per_peer() {
+        let mut server = BitswapServer::new(BitswapServerConfig {
+            max_requests_per_peer: 2,
+            ..Default::default()
+        });
+        let (a, b) = (PeerId::random(), PeerId::random());
+        let now = Instant::now();
+        assert!(server.allow_request_at(&a, now));
+        assert!(server.allow_request_at(&a, now));
+        assert!(!server.allow_request_at(&a, now));
+        assert!(server.allow_request_at(&b, now));
+        // The limit is reset in the next window
+        assert!(server.allow_request_at(&a, now + WINDOW));
+    }
+
+    #[test]
+    fn served_bytes_are_limited() {
+        let mut server = BitswapServer::new(BitswapServerConfig {
+            max_served_bytes: 100,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        assert!(server.allow_block_at(60, now));
+        assert!(!server.allow_block_at(60, now));
+        assert!(server.allow_block_at(40, now));
+        assert!(server.allow_block_at(100, now + WINDOW));
+    }
+
+    #[test]
+    fn disabled_server_answers_nothing() {
+        let mut server = BitswapServer::new(BitswapServerConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        assert!(!server.allow_request(&PeerId::random()));
+    }
+}
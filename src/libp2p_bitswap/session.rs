@@ -0,0 +1,51 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::sync::Arc;
+
+use ahash::HashMap;
+use libp2p::PeerId;
+use parking_lot::Mutex;
+
+/// Groups related block requests, e.g. the messages of a block or the nodes of
+/// a state tree. Peers that provided some of the blocks of a session likely
+/// have the others, so they're asked for them directly, before the other
+/// peers are probed with `want-have` requests.
+#[derive(Debug, Clone, Default)]
+pub struct BitswapSession {
+    /// Number of blocks received from each peer
+    providers: Arc<Mutex<HashMap<PeerId, u32>>>,
+}
+
+impl BitswapSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(in crate::libp2p_bitswap) fn record_block(&self, peer: PeerId) {
+        *self.providers.lock().entry(peer).or_default() += 1;
+    }
+
+    /// Returns the peers that provided blocks, the most prolific first.
+    pub(in crate::libp2p_bitswap) fn providers(&self) -> Vec<PeerId> {
+        let providers = self.providers.lock();
+        let mut peers: Vec<_> = providers.iter().map(|(&peer, &n)| (peer, n)).collect();
+        peers.sort_by(|(_, a), (_, b)| b.cmp(a));
+        peers.into_iter().map(|(peer, _)| peer).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn providers_are_sorted_by_blocks() {
+        let session = BitswapSession::new();
+        let (a, b) = (PeerId::random(), PeerId::random());
+        session.record_block(a);
+        session.clone().record_block(b);
+        session.record_block(b);
+        assert_eq!(session.providers(), vec![b, a]);
+    }
+}
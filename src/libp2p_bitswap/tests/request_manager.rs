@@ -74,6 +74,7 @@ mod tests {
                 *block_not_exist.cid(),
                 TIMEOUT,
                 Some(request_tx),
+                None,
             );
             // Use a small timeout here
             tokio::task::spawn_blocking(move || request_rx.recv_timeout(Duration::from_secs(1)))
@@ -92,6 +93,7 @@ mod tests {
                 *block_exist.cid(),
                 TIMEOUT,
                 Some(request_tx),
+                None,
             );
             let success =
                 tokio::task::spawn_blocking(move || request_rx.recv_timeout(TIMEOUT)).await??;
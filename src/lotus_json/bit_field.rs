@@ -0,0 +1,95 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::*;
+use fil_actors_shared::fvm_ipld_bitfield::iter::Ranges;
+use fil_actors_shared::fvm_ipld_bitfield::BitField;
+use std::ops::Range;
+#[cfg(test)]
+use {itertools::Itertools as _, pretty_assertions::assert_eq};
+
+#[test]
+fn snapshots() {
+    assert_all_snapshots::<BitField>();
+}
+
+#[test]
+fn empty_runs() {
+    let bit_field = BitField::from_lotus_json(BitFieldLotusJson(vec![1, 2, 0, 1, 0, 0]));
+    assert_eq!(bit_field.ranges().exactly_one().ok(), Some(1..4));
+}
+
+#[test]
+fn overflowing_runs() {
+    let bit_field = BitField::from_lotus_json(BitFieldLotusJson(vec![u64::MAX - 1, 1, 2]));
+    assert_eq!(
+        bit_field.ranges().exactly_one().ok(),
+        Some(u64::MAX - 1..u64::MAX)
+    );
+}
+
+#[cfg(test)]
+quickcheck! {
+    fn quickcheck(bits: Vec<u16>) -> () {
+        let mut bit_field = BitField::new();
+        for bit in bits {
+            bit_field.set(bit.into());
+        }
+        assert_unchanged_via_json(bit_field)
+    }
+}
+
+/// Lengths of the alternating runs of unset and set bits, starting with unset
+/// bits, as in Lotus.
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BitFieldLotusJson(Vec<u64>);
+
+impl HasLotusJson for BitField {
+    type LotusJson = BitFieldLotusJson;
+
+    fn snapshots() -> Vec<(serde_json::Value, Self)> {
+        let mut bit_field = BitField::new();
+        for bit in [0, 1, 5, 6, 7] {
+            bit_field.set(bit);
+        }
+        vec![
+            (json!([0]), BitField::new()),
+            (json!([0, 2, 3, 3]), bit_field),
+        ]
+    }
+
+    fn into_lotus_json(self) -> Self::LotusJson {
+        let mut runs = vec![];
+        let mut end = 0;
+        for range in self.ranges() {
+            runs.push(range.start - end);
+            runs.push(range.end - range.start);
+            end = range.end;
+        }
+        if runs.is_empty() {
+            runs.push(0);
+        }
+        BitFieldLotusJson(runs)
+    }
+
+    fn from_lotus_json(BitFieldLotusJson(runs): Self::LotusJson) -> Self {
+        let mut ranges: Vec<Range<u64>> = vec![];
+        let mut start = 0u64;
+        for (i, len) in runs.into_iter().enumerate() {
+            // Bits past `u64::MAX` can't be set
+            let Some(end) = start.checked_add(len) else {
+                break;
+            };
+            if i % 2 == 1 && len > 0 {
+                match ranges.last_mut() {
+                    // Runs of set bits separated by an empty run of unset bits
+                    Some(last) if last.end == start => last.end = end,
+                    _ => ranges.push(start..end),
+                }
+            }
+            start = end;
+        }
+        BitField::from_ranges(Ranges::new(ranges))
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::shim::econ::TokenAmount;
+use crate::state_manager::CirculatingSupply;
+
+use super::*;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CirculatingSupplyLotusJson {
+    fil_vested: LotusJson<TokenAmount>,
+    fil_mined: LotusJson<TokenAmount>,
+    fil_burnt: LotusJson<TokenAmount>,
+    fil_locked: LotusJson<TokenAmount>,
+    fil_circulating: LotusJson<TokenAmount>,
+    fil_reserve_disbursed: LotusJson<TokenAmount>,
+}
+
+impl HasLotusJson for CirculatingSupply {
+    type LotusJson = CirculatingSupplyLotusJson;
+
+    fn snapshots() -> Vec<(serde_json::Value, Self)> {
+        vec![(
+            json!({
+                "FilVested": "1",
+                "FilMined": "2",
+                "FilBurnt": "3",
+                "FilLocked": "4",
+                "FilCirculating": "5",
+                "FilReserveDisbursed": "6",
+            }),
+            CirculatingSupply {
+                fil_vested: TokenAmount::from_atto(1),
+                fil_mined: TokenAmount::from_atto(2),
+                fil_burnt: TokenAmount::from_atto(3),
+                fil_locked: TokenAmount::from_atto(4),
+                fil_circulating: TokenAmount::from_atto(5),
+                fil_reserve_disbursed: TokenAmount::from_atto(6),
+            },
+        )]
+    }
+
+    fn into_lotus_json(self) -> Self::LotusJson {
+        let Self {
+            fil_vested,
+            fil_mined,
+            fil_burnt,
+            fil_locked,
+            fil_circulating,
+            fil_reserve_disbursed,
+        } = self;
+        Self::LotusJson {
+            fil_vested: fil_vested.into(),
+            fil_mined: fil_mined.into(),
+            fil_burnt: fil_burnt.into(),
+            fil_locked: fil_locked.into(),
+            fil_circulating: fil_circulating.into(),
+            fil_reserve_disbursed: fil_reserve_disbursed.into(),
+        }
+    }
+
+    fn from_lotus_json(lotus_json: Self::LotusJson) -> Self {
+        let Self::LotusJson {
+            fil_vested,
+            fil_mined,
+            fil_burnt,
+            fil_locked,
+            fil_circulating,
+            fil_reserve_disbursed,
+        } = lotus_json;
+        Self {
+            fil_vested: fil_vested.into_inner(),
+            fil_mined: fil_mined.into_inner(),
+            fil_burnt: fil_burnt.into_inner(),
+            fil_locked: fil_locked.into_inner(),
+            fil_circulating: fil_circulating.into_inner(),
+            fil_reserve_disbursed: fil_reserve_disbursed.into_inner(),
+        }
+    }
+}
@@ -124,6 +124,7 @@
 use crate::ipld::{json::IpldJson, Ipld};
 use derive_more::From;
 use fil_actor_interface::power::Claim;
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::json;
 use std::{fmt::Display, str::FromStr};
@@ -179,6 +180,7 @@ decl_and_test!(
     address for crate::shim::address::Address,
     beacon_entry for crate::beacon::BeaconEntry,
     big_int for num::BigInt,
+    circulating_supply for crate::state_manager::CirculatingSupply,
     election_proof for crate::blocks::ElectionProof,
     gossip_block for crate::blocks::GossipBlock,
     key_info for crate::key_management::KeyInfo,
@@ -198,6 +200,7 @@ decl_and_test!(
     vrf_proof for crate::blocks::VRFProof,
 );
 
+mod bit_field; // fvm_ipld_bitfield::BitField: !quickcheck::Arbitrary
 mod cid; // can't make snapshots of generic type
 mod opt; // can't make snapshots of generic type
 mod raw_bytes; // fvm_ipld_encoding::RawBytes: !quickcheck::Arbitrary
@@ -345,6 +348,25 @@ impl<T> LotusJson<Option<T>> {
     }
 }
 
+/// The schema of a domain struct is the one of its lotus JSON representation.
+impl<T> JsonSchema for LotusJson<T>
+where
+    T: HasLotusJson,
+    T::LotusJson: JsonSchema,
+{
+    fn is_referenceable() -> bool {
+        T::LotusJson::is_referenceable()
+    }
+
+    fn schema_name() -> String {
+        T::LotusJson::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        T::LotusJson::json_schema(gen)
+    }
+}
+
 /// A struct that is (de) serialized through its [`Display`] and [`FromStr`] implementations.
 #[derive(Serialize, Deserialize, From, Default)]
 #[serde(bound = "T: Display + FromStr, T::Err: Display")]
@@ -356,6 +378,20 @@ impl<T> Stringify<T> {
     }
 }
 
+impl<T> JsonSchema for Stringify<T> {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        String::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
 macro_rules! lotus_json_with_self {
     ($($domain_ty:ty),* $(,)?) => {
         $(
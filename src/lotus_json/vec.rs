@@ -51,6 +51,24 @@ where
     }
 }
 
+// An empty vec is `null`
+impl<T> JsonSchema for VecLotusJson<T>
+where
+    T: JsonSchema,
+{
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        Option::<Vec<T>>::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        Option::<Vec<T>>::json_schema(gen)
+    }
+}
+
 impl<'de, T> Deserialize<'de> for VecLotusJson<T>
 where
     T: Deserialize<'de>,
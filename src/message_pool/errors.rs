@@ -30,6 +30,8 @@ pub enum Error {
     DuplicateSequence,
     #[error("Validation Error: {0}")]
     SoftValidationFailure(String),
+    #[error("Unfulfilled nonce gap")]
+    NonceGap,
     #[error("Too many pending messages from actor {0} (trusted: {1})")]
     TooManyPendingMessages(String, bool),
     #[error("{0}")]
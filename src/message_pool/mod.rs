@@ -11,7 +11,7 @@ pub use self::{
     config::*,
     errors::*,
     msgpool::{
-        msg_pool::MessagePool,
+        msg_pool::{MessagePool, MpoolUpdate},
         provider::{MpoolRpcProvider, Provider},
         *,
     },
@@ -22,13 +22,14 @@ use cid::Cid;
 use fvm_ipld_encoding::to_vec;
 use lru::LruCache;
 use parking_lot::{Mutex, RwLock as SyncRwLock};
+use tokio::sync::broadcast;
 use tracing::error;
 use utils::{get_base_fee_lower_bound, recover_sig};
 
 use super::errors::Error;
 use crate::message_pool::{
     msg_chain::{create_message_chains, Chains},
-    msg_pool::{add_helper, remove, MsgSet},
+    msg_pool::{add_helper, remove, MpoolUpdate, MsgSet},
     provider::Provider,
 };
 
@@ -212,6 +213,7 @@ pub async fn head_change<T>(
     repub_trigger: Arc<flume::Sender<()>>,
     republished: &SyncRwLock<HashSet<Cid>>,
     pending: &SyncRwLock<HashMap<Address, MsgSet>>,
+    updates: &broadcast::Sender<MpoolUpdate>,
     cur_tipset: &Mutex<Arc<Tipset>>,
     revert: Vec<Tipset>,
     apply: Vec<Tipset>,
@@ -248,6 +250,7 @@ where
                 remove_from_selected_msgs(
                     &msg.from(),
                     pending,
+                    updates,
                     msg.sequence(),
                     rmsgs.borrow_mut(),
                 )?;
@@ -256,7 +259,13 @@ where
                 }
             }
             for msg in msgs {
-                remove_from_selected_msgs(&msg.from, pending, msg.sequence, rmsgs.borrow_mut())?;
+                remove_from_selected_msgs(
+                    &msg.from,
+                    pending,
+                    updates,
+                    msg.sequence,
+                    rmsgs.borrow_mut(),
+                )?;
                 if !repub && republished.write().insert(msg.cid()?) {
                     repub = true;
                 }
@@ -273,7 +282,7 @@ where
     for (_, hm) in rmsgs {
         for (_, msg) in hm {
             let sequence = get_state_sequence(api, &msg.from(), &cur_tipset.lock().clone())?;
-            if let Err(e) = add_helper(api, bls_sig_cache, pending, msg, sequence) {
+            if let Err(e) = add_helper(api, bls_sig_cache, pending, updates, msg, sequence, true) {
                 error!("Failed to read message from reorg to mpool: {}", e);
             }
         }
@@ -287,6 +296,7 @@ where
 pub(in crate::message_pool) fn remove_from_selected_msgs(
     from: &Address,
     pending: &SyncRwLock<HashMap<Address, MsgSet>>,
+    updates: &broadcast::Sender<MpoolUpdate>,
     sequence: u64,
     rmsgs: &mut HashMap<Address, HashMap<u64, SignedMessage>>,
 ) -> Result<(), Error> {
@@ -294,10 +304,10 @@ pub(in crate::message_pool) fn remove_from_selected_msgs(
         if temp.get_mut(&sequence).is_some() {
             temp.remove(&sequence);
         } else {
-            remove(from, pending, sequence, true)?;
+            remove(from, pending, updates, sequence, true)?;
         }
     } else {
-        remove(from, pending, sequence, true)?;
+        remove(from, pending, updates, sequence, true)?;
     }
     Ok(())
 }
@@ -377,6 +387,80 @@ pub mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_local_messages_are_saved() {
+        let keystore = KeyStore::new(KeyStoreConfig::Memory).unwrap();
+        let mut wallet = Wallet::new(keystore);
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let db = crate::db::MemoryDB::default();
+        let new_mpool = |services: &mut JoinSet<_>| {
+            let tma = TestApi::default();
+            tma.set_state_sequence(&sender, 0);
+            let (tx, rx) = flume::bounded(50);
+            let mpool = MessagePool::new(
+                tma,
+                "mptest".to_string(),
+                tx,
+                Default::default(),
+                Arc::default(),
+                services,
+            )
+            .unwrap();
+            (mpool, rx)
+        };
+
+        let mut services = JoinSet::new();
+        let (mpool, _rx) = new_mpool(&mut services);
+        for i in 0..2 {
+            let msg = create_smsg(&target, &sender, wallet.borrow_mut(), i, 1000000, 1);
+            mpool.push(msg).await.unwrap();
+        }
+        mpool.save_local(&db).unwrap();
+
+        let (mut mpool, _rx) = new_mpool(&mut services);
+        assert_eq!(mpool.get_sequence(&sender).unwrap(), 0);
+        mpool.load_saved_local(&db).unwrap();
+        assert_eq!(mpool.get_sequence(&sender).unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_push_untrusted() {
+        let keystore = KeyStore::new(KeyStoreConfig::Memory).unwrap();
+        let mut wallet = Wallet::new(keystore);
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let tma = TestApi::default();
+        tma.set_state_sequence(&sender, 0);
+        // Enough for one message, not two
+        tma.set_state_balance_raw(&sender, TokenAmount::from_atto(150_000_000));
+
+        let (tx, _rx) = flume::bounded(50);
+        let mut services = JoinSet::new();
+        let mpool = MessagePool::new(
+            tma,
+            "mptest".to_string(),
+            tx,
+            Default::default(),
+            Arc::default(),
+            &mut services,
+        )
+        .unwrap();
+
+        let gapped = create_smsg(&target, &sender, wallet.borrow_mut(), 1, 1000000, 1);
+        assert_eq!(mpool.push_untrusted(gapped).await, Err(Error::NonceGap));
+
+        let first = create_smsg(&target, &sender, wallet.borrow_mut(), 0, 1000000, 1);
+        mpool.push_untrusted(first).await.unwrap();
+        let second = create_smsg(&target, &sender, wallet.borrow_mut(), 1, 1000000, 1);
+        assert!(matches!(
+            mpool.push_untrusted(second.clone()).await,
+            Err(Error::SoftValidationFailure(_))
+        ));
+        // Trusted pushes only check the funds for the message itself
+        mpool.push(second).await.unwrap();
+    }
+
     pub fn create_smsg(
         to: &Address,
         from: &Address,
@@ -454,16 +538,24 @@ pub mod tests {
             smsg_vec.push(msg);
         }
 
+        let mut updates = mpool.updates.subscribe();
         mpool.api.inner.lock().set_state_sequence(&sender, 0);
         assert_eq!(mpool.get_sequence(&sender).unwrap(), 0);
         mpool.add(smsg_vec[0].clone()).unwrap();
         assert_eq!(mpool.get_sequence(&sender).unwrap(), 1);
         mpool.add(smsg_vec[1].clone()).unwrap();
         assert_eq!(mpool.get_sequence(&sender).unwrap(), 2);
+        for msg in &smsg_vec {
+            assert_eq!(updates.try_recv().unwrap(), MpoolUpdate::Add(msg.clone()));
+        }
 
         let a = mock_block(1, 1);
 
-        mpool.api.inner.lock().set_block_messages(&a, smsg_vec);
+        mpool
+            .api
+            .inner
+            .lock()
+            .set_block_messages(&a, smsg_vec.clone());
         let api = mpool.api.clone();
         let bls_sig_cache = mpool.bls_sig_cache.clone();
         let pending = mpool.pending.clone();
@@ -476,6 +568,7 @@ pub mod tests {
             repub_trigger,
             republished.as_ref(),
             pending.as_ref(),
+            &mpool.updates,
             cur_tipset.as_ref(),
             Vec::new(),
             vec![Tipset::from(a)],
@@ -484,6 +577,9 @@ pub mod tests {
         .unwrap();
 
         assert_eq!(mpool.get_sequence(&sender).unwrap(), 2);
+        for msg in smsg_vec {
+            assert_eq!(updates.try_recv().unwrap(), MpoolUpdate::Remove(msg));
+        }
     }
 
     #[tokio::test]
@@ -544,6 +640,7 @@ pub mod tests {
             repub_trigger.clone(),
             republished.as_ref(),
             pending.as_ref(),
+            &mpool.updates,
             cur_tipset.as_ref(),
             Vec::new(),
             vec![Tipset::from(a)],
@@ -566,6 +663,7 @@ pub mod tests {
             repub_trigger.clone(),
             republished.as_ref(),
             pending.as_ref(),
+            &mpool.updates,
             cur_tipset.as_ref(),
             Vec::new(),
             vec![Tipset::from(&b)],
@@ -583,6 +681,7 @@ pub mod tests {
             repub_trigger.clone(),
             republished.as_ref(),
             pending.as_ref(),
+            &mpool.updates,
             cur_tipset.as_ref(),
             vec![Tipset::from(b)],
             Vec::new(),
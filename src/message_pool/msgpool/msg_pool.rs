@@ -10,8 +10,7 @@ use std::{num::NonZeroUsize, sync::Arc, time::Duration};
 
 use crate::blocks::{BlockHeader, Tipset};
 use crate::chain::{HeadChange, MINIMUM_BASE_FEE};
-#[cfg(test)]
-use crate::db::SettingsStore;
+use crate::db::{setting_keys::MPOOL_LOCAL_MESSAGES_KEY, SettingsStore};
 use crate::libp2p::{NetworkMessage, Topic, PUBSUB_MSG_STR};
 use crate::message::{valid_for_block_inclusion, ChainMessage, Message, SignedMessage};
 use crate::networks::{ChainConfig, NEWEST_NETWORK_VERSION};
@@ -31,7 +30,11 @@ use lru::LruCache;
 use nonzero_ext::nonzero;
 use num::BigInt;
 use parking_lot::{Mutex, RwLock as SyncRwLock};
-use tokio::{sync::broadcast::error::RecvError, task::JoinSet, time::interval};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinSet,
+    time::interval,
+};
 use tracing::warn;
 
 use crate::message_pool::{
@@ -52,6 +55,8 @@ const SIG_VAL_CACHE_SIZE: NonZeroUsize = nonzero!(32000usize);
 
 pub const MAX_ACTOR_PENDING_MESSAGES: u64 = 1000;
 pub const MAX_UNTRUSTED_ACTOR_PENDING_MESSAGES: u64 = 10;
+/// Updates buffered for each subscriber of [`MessagePool::updates`].
+const MPOOL_UPDATES_CAP: usize = 256;
 
 /// Simple structure that contains a hash-map of messages where k: a message
 /// from address, v: a message which corresponds to that address.
@@ -83,8 +88,8 @@ impl MsgSet {
 
     /// Add a signed message to the `MsgSet`. Increase `next_sequence` if the
     /// message has a sequence greater than any existing message sequence.
-    /// Use this method when pushing a message coming from untrusted sources.
-    #[allow(dead_code)]
+    /// Use this method when pushing a message coming from untrusted sources,
+    /// which may not leave a gap in the sequences of the sender.
     pub fn add_untrusted<T>(&mut self, api: &T, m: SignedMessage) -> Result<(), Error>
     where
         T: Provider,
@@ -102,6 +107,10 @@ impl MsgSet {
             api.max_untrusted_actor_pending_messages()
         };
 
+        if !trusted && m.sequence() > self.next_sequence {
+            return Err(Error::NonceGap);
+        }
+
         if self.msgs.is_empty() || m.sequence() >= self.next_sequence {
             self.next_sequence = m.sequence() + 1;
         }
@@ -132,18 +141,29 @@ impl MsgSet {
         Ok(())
     }
 
-    /// Removes message with the given sequence. If applied, update the set's
-    /// next sequence.
-    pub fn rm(&mut self, sequence: u64, applied: bool) {
-        if self.msgs.remove(&sequence).is_none() {
+    /// Funds required by the pending messages, except the one with the given
+    /// sequence, which is about to be replaced.
+    fn required_funds(&self, except_sequence: u64) -> TokenAmount {
+        self.msgs
+            .iter()
+            .filter(|(sequence, _)| **sequence != except_sequence)
+            .fold(TokenAmount::default(), |funds, (_, m)| {
+                funds + m.required_funds()
+            })
+    }
+
+    /// Removes message with the given sequence, and returns it. If applied,
+    /// update the set's next sequence.
+    pub fn rm(&mut self, sequence: u64, applied: bool) -> Option<SignedMessage> {
+        let Some(removed) = self.msgs.remove(&sequence) else {
             if applied && sequence >= self.next_sequence {
                 self.next_sequence = sequence + 1;
                 while self.msgs.get(&self.next_sequence).is_some() {
                     self.next_sequence += 1;
                 }
             }
-            return;
-        }
+            return None;
+        };
         metrics::MPOOL_MESSAGE_TOTAL.dec();
 
         // adjust next sequence
@@ -153,16 +173,24 @@ impl MsgSet {
             if sequence >= self.next_sequence {
                 self.next_sequence = sequence + 1;
             }
-            return;
+            return Some(removed);
         }
         // we removed a message because it was pruned
         // we have to adjust the sequence if it creates a gap or rewinds state
         if sequence < self.next_sequence {
             self.next_sequence = sequence;
         }
+        Some(removed)
     }
 }
 
+/// Change of the pending messages of the [`MessagePool`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MpoolUpdate {
+    Add(SignedMessage),
+    Remove(SignedMessage),
+}
+
 /// This contains all necessary information needed for the message pool.
 /// Keeps track of messages to apply, as well as context needed for verifying
 /// transactions.
@@ -195,6 +223,9 @@ pub struct MessagePool<T> {
     pub config: MpoolConfig,
     /// Chain configuration
     pub chain_config: Arc<ChainConfig>,
+    /// Sender of the changes of the pending messages, there may be no
+    /// receivers
+    pub updates: broadcast::Sender<MpoolUpdate>,
 }
 
 impl<T> MessagePool<T>
@@ -211,10 +242,22 @@ where
     /// Push a signed message to the `MessagePool`. Additionally performs basic
     /// checks on the validity of a message.
     pub async fn push(&self, msg: SignedMessage) -> Result<Cid, Error> {
+        self.push_internal(msg, true).await
+    }
+
+    /// Push a signed message coming from an untrusted source to the
+    /// `MessagePool`. On top of the checks of [`MessagePool::push`], the
+    /// sender must afford all its pending messages, may not leave a gap in
+    /// its sequences and may only have a few pending messages.
+    pub async fn push_untrusted(&self, msg: SignedMessage) -> Result<Cid, Error> {
+        self.push_internal(msg, false).await
+    }
+
+    async fn push_internal(&self, msg: SignedMessage, trusted: bool) -> Result<Cid, Error> {
         self.check_message(&msg)?;
         let cid = msg.cid().map_err(|err| Error::Other(err.to_string()))?;
         let cur_ts = self.cur_tipset.lock().clone();
-        let publish = self.add_tipset(msg.clone(), &cur_ts, true)?;
+        let publish = self.add_tipset(msg.clone(), &cur_ts, true, trusted)?;
         let msg_ser = to_vec(&msg)?;
         self.add_local(msg)?;
         if publish {
@@ -250,7 +293,7 @@ where
 
         let tip = self.cur_tipset.lock().clone();
 
-        self.add_tipset(msg, &tip, false)?;
+        self.add_tipset(msg, &tip, false, true)?;
         Ok(())
     }
 
@@ -274,7 +317,13 @@ where
     /// Verify the `state_sequence` and balance for the sender of the message
     /// given then call `add_locked` to finish adding the `signed_message`
     /// to pending.
-    fn add_tipset(&self, msg: SignedMessage, cur_ts: &Tipset, local: bool) -> Result<bool, Error> {
+    fn add_tipset(
+        &self,
+        msg: SignedMessage,
+        cur_ts: &Tipset,
+        local: bool,
+        trusted: bool,
+    ) -> Result<bool, Error> {
         let sequence = self.get_state_sequence(&msg.from(), cur_ts)?;
 
         if sequence > msg.message().sequence {
@@ -299,7 +348,20 @@ where
         if balance < msg_balance {
             return Err(Error::NotEnoughFunds);
         }
-        self.add_helper(msg)?;
+        if !trusted {
+            let pending_balance = self
+                .pending
+                .read()
+                .get(&msg.from())
+                .map(|mset| mset.required_funds(msg.sequence()))
+                .unwrap_or_default();
+            if balance < msg_balance + pending_balance {
+                return Err(Error::SoftValidationFailure(
+                    "not enough funds including pending messages".to_owned(),
+                ));
+            }
+        }
+        self.add_helper(msg, trusted)?;
         Ok(publish)
     }
 
@@ -307,15 +369,17 @@ where
     /// hash-map. If an entry in the hash-map does not yet exist, create a
     /// new `mset` that will correspond to the from message and push it to
     /// the pending hash-map.
-    fn add_helper(&self, msg: SignedMessage) -> Result<(), Error> {
+    fn add_helper(&self, msg: SignedMessage, trusted: bool) -> Result<(), Error> {
         let from = msg.from();
         let cur_ts = self.cur_tipset.lock().clone();
         add_helper(
             self.api.as_ref(),
             self.bls_sig_cache.as_ref(),
             self.pending.as_ref(),
+            &self.updates,
             msg,
             self.get_state_sequence(&from, &cur_ts)?,
+            trusted,
         )
     }
 
@@ -372,6 +436,19 @@ where
         Ok((out, cur_ts))
     }
 
+    /// Return the number of pending messages and the number of their senders.
+    pub fn pending_count(&self) -> (usize, usize) {
+        let pending = self.pending.read();
+        let messages = pending.values().map(|mset| mset.msgs.len()).sum();
+        (messages, pending.len())
+    }
+
+    /// Return the number of messages pushed to the pool through this node
+    /// that are still pending.
+    pub fn local_message_count(&self) -> usize {
+        self.local_msgs.read().len()
+    }
+
     /// Return a Vector of signed messages for a given from address. This vector
     /// will be sorted by each `messsage`'s sequence. If no corresponding
     /// messages found, return None result type.
@@ -420,6 +497,25 @@ where
         Ok(())
     }
 
+    /// Saves the messages pushed through this node, they are loaded back by
+    /// [`MessagePool::load_saved_local`].
+    pub fn save_local<DB: SettingsStore>(&self, store: &DB) -> anyhow::Result<()> {
+        let local_msgs: Vec<_> = self.local_msgs.read().iter().cloned().collect();
+        store.write_bin(MPOOL_LOCAL_MESSAGES_KEY, &to_vec(&local_msgs)?)
+    }
+
+    /// Adds the messages saved by [`MessagePool::save_local`] to the pool.
+    pub fn load_saved_local<DB: SettingsStore>(&mut self, store: &DB) -> anyhow::Result<()> {
+        if let Some(bytes) = store.read_bin(MPOOL_LOCAL_MESSAGES_KEY)? {
+            let local_msgs: Vec<SignedMessage> = fvm_ipld_encoding::from_slice(&bytes)?;
+            for msg in local_msgs {
+                self.add_local(msg)?;
+            }
+            self.load_local()?;
+        }
+        Ok(())
+    }
+
     #[cfg(test)]
     pub fn get_config(&self) -> &MpoolConfig {
         &self.config
@@ -485,6 +581,7 @@ where
         let block_delay = chain_config.block_delay_secs;
 
         let (repub_trigger, repub_trigger_rx) = flume::bounded::<()>(4);
+        let (updates, _) = broadcast::channel(MPOOL_UPDATES_CAP);
         let mut mp = MessagePool {
             local_addrs,
             pending,
@@ -500,6 +597,7 @@ where
             network_sender,
             repub_trigger,
             chain_config: Arc::clone(&chain_config),
+            updates,
         };
 
         mp.load_local()?;
@@ -513,6 +611,7 @@ where
 
         let cur_tipset = mp.cur_tipset.clone();
         let repub_trigger = Arc::new(mp.repub_trigger.clone());
+        let updates = mp.updates.clone();
 
         // Reacts to new HeadChanges
         services.spawn(async move {
@@ -532,6 +631,7 @@ where
                             repub_trigger.clone(),
                             republished.as_ref(),
                             pending.as_ref(),
+                            &updates,
                             cur.as_ref(),
                             rev,
                             app,
@@ -596,8 +696,10 @@ pub(in crate::message_pool) fn add_helper<T>(
     api: &T,
     bls_sig_cache: &Mutex<LruCache<Cid, Signature>>,
     pending: &SyncRwLock<HashMap<Address, MsgSet>>,
+    updates: &broadcast::Sender<MpoolUpdate>,
     msg: SignedMessage,
     sequence: u64,
+    trusted: bool,
 ) -> Result<(), Error>
 where
     T: Provider,
@@ -619,15 +721,21 @@ where
 
     let mut pending = pending.write();
     let msett = pending.get_mut(&msg.from());
+    let add = |mset: &mut MsgSet, msg| match trusted {
+        true => mset.add_trusted(api, msg),
+        false => mset.add_untrusted(api, msg),
+    };
     match msett {
-        Some(mset) => mset.add_trusted(api, msg)?,
+        Some(mset) => add(mset, msg.clone())?,
         None => {
             let mut mset = MsgSet::new(sequence);
             let from = msg.from();
-            mset.add_trusted(api, msg)?;
+            add(&mut mset, msg.clone())?;
             pending.insert(from, mset);
         }
     }
+    // There may be no subscribers
+    let _ = updates.send(MpoolUpdate::Add(msg));
 
     Ok(())
 }
@@ -662,6 +770,7 @@ fn verify_msg_before_add(
 pub fn remove(
     from: &Address,
     pending: &SyncRwLock<HashMap<Address, MsgSet>>,
+    updates: &broadcast::Sender<MpoolUpdate>,
     sequence: u64,
     applied: bool,
 ) -> Result<(), Error> {
@@ -672,7 +781,9 @@ pub fn remove(
         return Ok(());
     };
 
-    mset.rm(sequence, applied);
+    if let Some(removed) = mset.rm(sequence, applied) {
+        let _ = updates.send(MpoolUpdate::Remove(removed));
+    }
 
     if mset.msgs.is_empty() {
         pending.remove(from);
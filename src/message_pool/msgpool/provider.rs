@@ -97,6 +97,11 @@ where
             .blockstore()
             .put_cbor_default(msg)
             .map_err(|err| Error::Other(err.to_string()))?;
+        if let ChainMessage::Signed(smsg) = msg {
+            let chain_id = self.sm.chain_config().eth_chain_id.into();
+            crate::eth::index_tx_hashes(&*self.sm.chain_store().settings(), chain_id, [smsg])
+                .map_err(|err| Error::Other(err.to_string()))?;
+        }
         Ok(cid)
     }
 
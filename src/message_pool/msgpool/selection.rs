@@ -480,6 +480,7 @@ where
         run_head_change(
             self.api.as_ref(),
             &self.pending,
+            &self.updates,
             cur_ts.clone(),
             ts.clone(),
             &mut result,
@@ -631,6 +632,7 @@ fn merge_and_trim(
 pub(in crate::message_pool) fn run_head_change<T>(
     api: &T,
     pending: &RwLock<HashMap<Address, MsgSet>>,
+    updates: &tokio::sync::broadcast::Sender<super::msg_pool::MpoolUpdate>,
     from: Tipset,
     to: Tipset,
     rmsgs: &mut HashMap<Address, HashMap<u64, SignedMessage>>,
@@ -672,12 +674,19 @@ where
                 remove_from_selected_msgs(
                     &msg.from(),
                     pending,
+                    updates,
                     msg.sequence(),
                     rmsgs.borrow_mut(),
                 )?;
             }
             for msg in msgs {
-                remove_from_selected_msgs(&msg.from, pending, msg.sequence, rmsgs.borrow_mut())?;
+                remove_from_selected_msgs(
+                    &msg.from,
+                    pending,
+                    updates,
+                    msg.sequence,
+                    rmsgs.borrow_mut(),
+                )?;
             }
         }
     }
@@ -747,6 +756,7 @@ mod test_selection {
             repub_trigger.clone(),
             republished.as_ref(),
             pending.as_ref(),
+            &mpool.updates,
             cur_tipset.as_ref(),
             Vec::new(),
             vec![Tipset::from(b1)],
@@ -805,6 +815,7 @@ mod test_selection {
             repub_trigger.clone(),
             republished.as_ref(),
             pending.as_ref(),
+            &mpool.updates,
             cur_tipset.as_ref(),
             Vec::new(),
             vec![Tipset::from(b2)],
@@ -909,6 +920,7 @@ mod test_selection {
             repub_trigger.clone(),
             republished.as_ref(),
             pending.as_ref(),
+            &mpool.updates,
             cur_tipset.as_ref(),
             Vec::new(),
             vec![Tipset::from(b1)],
@@ -989,6 +1001,7 @@ mod test_selection {
             repub_trigger.clone(),
             republished.as_ref(),
             pending.as_ref(),
+            &mpool.updates,
             cur_tipset.as_ref(),
             Vec::new(),
             vec![Tipset::from(b1)],
@@ -1083,6 +1096,7 @@ mod test_selection {
             repub_trigger.clone(),
             republished.as_ref(),
             pending.as_ref(),
+            &mpool.updates,
             cur_tipset.as_ref(),
             Vec::new(),
             vec![Tipset::from(b1)],
@@ -1162,6 +1176,7 @@ mod test_selection {
             repub_trigger.clone(),
             republished.as_ref(),
             pending.as_ref(),
+            &mpool.updates,
             cur_tipset.as_ref(),
             Vec::new(),
             vec![Tipset::from(b1)],
@@ -1279,6 +1294,7 @@ mod test_selection {
             repub_trigger.clone(),
             republished.as_ref(),
             pending.as_ref(),
+            &mpool.updates,
             cur_tipset.as_ref(),
             Vec::new(),
             vec![Tipset::from(block)],
@@ -0,0 +1,206 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Kubernetes-style health check endpoints:
+//! - `/livez`, the process is up and serving requests,
+//! - `/readyz`, the RPC server accepts connections and the database is
+//!   writable,
+//! - `/healthz`, the node is ready and its head isn't lagging behind the
+//!   network.
+//!
+//! They answer `200 OK` when all checks pass and `503 Service Unavailable`
+//! otherwise. With the `verbose` query parameter the result of each check is
+//! listed.
+
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::chain::ChainStore;
+use crate::chain_sync::expected_epoch_now;
+use crate::db::setting_keys::HEALTH_CHECK_KEY;
+use crate::shim::clock::ChainEpoch;
+use ahash::HashMap;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use fvm_ipld_blockstore::Blockstore;
+
+/// Time given to the RPC server to accept a connection.
+const RPC_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub struct HealthState<DB> {
+    pub start_time: DateTime<Utc>,
+    pub chain_store: Arc<ChainStore<DB>>,
+    pub block_delay: u32,
+    /// Address of the RPC server, if enabled.
+    pub rpc_address: Option<SocketAddr>,
+    /// Maximum number of epochs the head can lag behind the network.
+    pub sync_lag_threshold: ChainEpoch,
+}
+
+type Params = Query<HashMap<String, String>>;
+
+pub async fn init_health_check<DB>(
+    listener: TcpListener,
+    state: HealthState<DB>,
+) -> anyhow::Result<()>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    let app = Router::new()
+        .route("/livez", get(livez::<DB>))
+        .route("/readyz", get(readyz::<DB>))
+        .route("/healthz", get(healthz::<DB>))
+        .with_state(Arc::new(state));
+    let server = axum::Server::from_tcp(listener)?.serve(app.into_make_service());
+    Ok(server.await?)
+}
+
+#[allow(clippy::unused_async)]
+async fn livez<DB>(State(state): State<Arc<HealthState<DB>>>, params: Params) -> impl IntoResponse {
+    let mut checks = Checks::default();
+    checks.push("uptime", Ok(uptime(&state)));
+    checks.into_response(params.contains_key("verbose"))
+}
+
+async fn readyz<DB: Blockstore>(
+    State(state): State<Arc<HealthState<DB>>>,
+    params: Params,
+) -> impl IntoResponse {
+    let mut checks = Checks::default();
+    check_ready(&state, &mut checks).await;
+    checks.into_response(params.contains_key("verbose"))
+}
+
+async fn healthz<DB: Blockstore>(
+    State(state): State<Arc<HealthState<DB>>>,
+    params: Params,
+) -> impl IntoResponse {
+    let mut checks = Checks::default();
+    checks.push("uptime", Ok(uptime(&state)));
+    check_ready(&state, &mut checks).await;
+    checks.push("sync", check_sync(&state));
+    checks.into_response(params.contains_key("verbose"))
+}
+
+fn uptime<DB>(state: &HealthState<DB>) -> String {
+    let uptime = (Utc::now() - state.start_time).num_seconds().max(0);
+    format!("up for {uptime}s")
+}
+
+async fn check_ready<DB: Blockstore>(state: &HealthState<DB>, checks: &mut Checks) {
+    checks.push("rpc_server", check_rpc_server(state.rpc_address).await);
+    checks.push("db_writable", check_db_writable(&state.chain_store));
+}
+
+async fn check_rpc_server(rpc_address: Option<SocketAddr>) -> Result<String, String> {
+    let Some(address) = rpc_address else {
+        return Ok("disabled".into());
+    };
+    match tokio::time::timeout(RPC_CONNECT_TIMEOUT, tokio::net::TcpStream::connect(address)).await {
+        Ok(Ok(_)) => Ok(format!("listening on {address}")),
+        Ok(Err(e)) => Err(format!("can't connect to {address}: {e}")),
+        Err(_) => Err(format!("connection to {address} timed out")),
+    }
+}
+
+fn check_db_writable<DB: Blockstore>(chain_store: &ChainStore<DB>) -> Result<String, String> {
+    let now = Utc::now().timestamp().to_be_bytes();
+    chain_store
+        .settings()
+        .write_bin(HEALTH_CHECK_KEY, &now)
+        .map(|()| "ok".into())
+        .map_err(|e| format!("write failed: {e}"))
+}
+
+fn check_sync<DB: Blockstore>(state: &HealthState<DB>) -> Result<String, String> {
+    let expected_epoch =
+        expected_epoch_now(state.chain_store.genesis().timestamp(), state.block_delay);
+    let head_epoch = state.chain_store.heaviest_tipset().epoch();
+    let lag = expected_epoch - head_epoch;
+    if lag > state.sync_lag_threshold {
+        Err(format!(
+            "head at epoch {head_epoch} lags {lag} epochs behind the network (threshold: {})",
+            state.sync_lag_threshold
+        ))
+    } else {
+        Ok(format!("head at epoch {head_epoch}, {lag} epochs behind"))
+    }
+}
+
+/// Results of the health checks, with a description of each.
+#[derive(Default)]
+struct Checks(Vec<(&'static str, Result<String, String>)>);
+
+impl Checks {
+    fn push(&mut self, name: &'static str, result: Result<String, String>) {
+        self.0.push((name, result));
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.0.iter().all(|(_, result)| result.is_ok())
+    }
+
+    fn report(&self) -> String {
+        let mut report = String::new();
+        for (name, result) in &self.0 {
+            match result {
+                Ok(msg) => report.push_str(&format!("[+] {name} ok: {msg}\n")),
+                Err(msg) => report.push_str(&format!("[-] {name} failed: {msg}\n")),
+            }
+        }
+        report
+    }
+
+    fn into_response(self, verbose: bool) -> impl IntoResponse {
+        let (status, summary) = if self.is_healthy() {
+            (StatusCode::OK, "OK")
+        } else {
+            (StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable")
+        };
+        let body = if verbose {
+            format!("{}{summary}\n", self.report())
+        } else {
+            format!("{summary}\n")
+        };
+        (
+            status,
+            [("content-type", "text/plain; charset=utf-8")],
+            body,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_report() {
+        let mut checks = Checks::default();
+        checks.push("rpc_server", Ok("listening".into()));
+        assert!(checks.is_healthy());
+        checks.push("sync", Err("lagging".into()));
+        assert!(!checks.is_healthy());
+        assert_eq!(
+            checks.report(),
+            "[+] rpc_server ok: listening\n[-] sync failed: lagging\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn rpc_server_check() {
+        assert!(check_rpc_server(None).await.is_ok());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        assert!(check_rpc_server(Some(address)).await.is_ok());
+        drop(listener);
+        assert!(check_rpc_server(Some(address)).await.is_err());
+    }
+}
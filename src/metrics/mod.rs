@@ -2,6 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 pub mod db;
+pub mod health;
+#[cfg(tokio_unstable)]
+pub mod runtime;
 
 use crate::db::DBStatistics;
 use ahash::{HashMap, HashMapExt};
@@ -61,6 +64,12 @@ where
     let db_collector = crate::metrics::db::DBCollector::new(db_directory);
     registry.register(Box::new(db_collector))?;
 
+    // Add the tokio runtime metrics, they are only available with `--cfg=tokio_unstable`
+    #[cfg(tokio_unstable)]
+    registry.register(Box::new(crate::metrics::runtime::RuntimeCollector::new(
+        tokio::runtime::Handle::current(),
+    )))?;
+
     // Create an configure HTTP server
     let app = Router::new()
         .route("/metrics", get(collect_prometheus_metrics))
@@ -126,4 +135,6 @@ pub mod values {
     pub const TIPSET: &str = "tipset";
     /// tipset cache in state manager
     pub const STATE_MANAGER_TIPSET: &str = "sm_tipset";
+    /// actor cache in state manager
+    pub const STATE_MANAGER_ACTOR: &str = "sm_actor";
 }
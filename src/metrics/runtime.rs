@@ -0,0 +1,181 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Metrics of the tokio runtime, they require the `tokio_unstable` cfg flag.
+
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use prometheus::{
+    core::{Collector, Desc},
+    proto, GaugeVec, IntGauge, IntGaugeVec, Opts,
+};
+use tokio::runtime::Handle;
+
+pub struct RuntimeCollector {
+    handle: Handle,
+    descs: Vec<Desc>,
+    workers: IntGauge,
+    worker_utilization: GaugeVec,
+    worker_local_queue_depth: IntGaugeVec,
+    global_queue_depth: IntGauge,
+    active_tasks: IntGauge,
+    blocking_threads: IntGauge,
+    idle_blocking_threads: IntGauge,
+    blocking_queue_depth: IntGauge,
+    /// Busy duration of each worker at the last collection.
+    last_busy: Mutex<(Instant, Vec<Duration>)>,
+}
+
+impl RuntimeCollector {
+    pub fn new(handle: Handle) -> Self {
+        let int_gauge = |name: &str, help: &str| {
+            IntGauge::with_opts(Opts::new(name, help))
+                .unwrap_or_else(|e| panic!("Creating {name} gauge must succeed: {e}"))
+        };
+        let workers = int_gauge("tokio_workers", "Number of runtime worker threads");
+        let worker_utilization = GaugeVec::new(
+            Opts::new(
+                "tokio_worker_utilization",
+                "Share of time a worker was busy since the previous scrape",
+            ),
+            &["worker"],
+        )
+        .expect("Creating tokio_worker_utilization gauge must succeed");
+        let worker_local_queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "tokio_worker_local_queue_depth",
+                "Number of tasks in the local queue of a worker",
+            ),
+            &["worker"],
+        )
+        .expect("Creating tokio_worker_local_queue_depth gauge must succeed");
+        let global_queue_depth = int_gauge(
+            "tokio_global_queue_depth",
+            "Number of tasks in the runtime global queue",
+        );
+        let active_tasks = int_gauge("tokio_active_tasks", "Number of tasks alive");
+        let blocking_threads = int_gauge(
+            "tokio_blocking_threads",
+            "Number of threads of the blocking pool",
+        );
+        let idle_blocking_threads = int_gauge(
+            "tokio_idle_blocking_threads",
+            "Number of idle threads of the blocking pool",
+        );
+        let blocking_queue_depth = int_gauge(
+            "tokio_blocking_queue_depth",
+            "Number of tasks waiting for a thread of the blocking pool",
+        );
+
+        let mut descs: Vec<Desc> = vec![];
+        descs.extend(workers.desc().into_iter().cloned());
+        descs.extend(worker_utilization.desc().into_iter().cloned());
+        descs.extend(worker_local_queue_depth.desc().into_iter().cloned());
+        descs.extend(global_queue_depth.desc().into_iter().cloned());
+        descs.extend(active_tasks.desc().into_iter().cloned());
+        descs.extend(blocking_threads.desc().into_iter().cloned());
+        descs.extend(idle_blocking_threads.desc().into_iter().cloned());
+        descs.extend(blocking_queue_depth.desc().into_iter().cloned());
+
+        let last_busy = Mutex::new((Instant::now(), busy_durations(&handle)));
+        Self {
+            handle,
+            descs,
+            workers,
+            worker_utilization,
+            worker_local_queue_depth,
+            global_queue_depth,
+            active_tasks,
+            blocking_threads,
+            idle_blocking_threads,
+            blocking_queue_depth,
+            last_busy,
+        }
+    }
+}
+
+fn busy_durations(handle: &Handle) -> Vec<Duration> {
+    let metrics = handle.metrics();
+    (0..metrics.num_workers())
+        .map(|worker| metrics.worker_total_busy_duration(worker))
+        .collect()
+}
+
+/// Share of `elapsed` spent between the `before` and `after` busy durations.
+fn utilization(before: Duration, after: Duration, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() {
+        return 0.0;
+    }
+    (after.saturating_sub(before).as_secs_f64() / elapsed.as_secs_f64()).min(1.0)
+}
+
+impl Collector for RuntimeCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.descs.iter().collect()
+    }
+
+    fn collect(&self) -> Vec<proto::MetricFamily> {
+        let metrics = self.handle.metrics();
+        let now = Instant::now();
+        let busy = busy_durations(&self.handle);
+        {
+            let mut last_busy = self.last_busy.lock();
+            let (last_time, last_durations) = &*last_busy;
+            let elapsed = now.duration_since(*last_time);
+            for (worker, (before, after)) in last_durations.iter().zip(&busy).enumerate() {
+                self.worker_utilization
+                    .with_label_values(&[&worker.to_string()])
+                    .set(utilization(*before, *after, elapsed));
+            }
+            *last_busy = (now, busy);
+        }
+        for worker in 0..metrics.num_workers() {
+            self.worker_local_queue_depth
+                .with_label_values(&[&worker.to_string()])
+                .set(metrics.worker_local_queue_depth(worker) as i64);
+        }
+        self.workers.set(metrics.num_workers() as i64);
+        self.global_queue_depth
+            .set(metrics.injection_queue_depth() as i64);
+        self.active_tasks.set(metrics.active_tasks_count() as i64);
+        self.blocking_threads
+            .set(metrics.num_blocking_threads() as i64);
+        self.idle_blocking_threads
+            .set(metrics.num_idle_blocking_threads() as i64);
+        self.blocking_queue_depth
+            .set(metrics.blocking_queue_depth() as i64);
+
+        let mut metric_families = vec![];
+        metric_families.extend(self.workers.collect());
+        metric_families.extend(self.worker_utilization.collect());
+        metric_families.extend(self.worker_local_queue_depth.collect());
+        metric_families.extend(self.global_queue_depth.collect());
+        metric_families.extend(self.active_tasks.collect());
+        metric_families.extend(self.blocking_threads.collect());
+        metric_families.extend(self.idle_blocking_threads.collect());
+        metric_families.extend(self.blocking_queue_depth.collect());
+        metric_families
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utilization_is_a_share_of_elapsed_time() {
+        let secs = Duration::from_secs;
+        assert_eq!(utilization(secs(1), secs(2), secs(4)), 0.25);
+        assert_eq!(utilization(secs(1), secs(9), secs(4)), 1.0);
+        assert_eq!(utilization(secs(1), secs(2), Duration::ZERO), 0.0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn runtime_metrics_are_collected() {
+        let collector = RuntimeCollector::new(Handle::current());
+        let families = collector.collect();
+        assert_eq!(families.len(), 8);
+        assert_eq!(collector.workers.get(), 2);
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Butterflynet is the testnet on which upgrades are rehearsed. It is reset
+//! for every rehearsal, so its genesis and bootstrap peers are fetched from
+//! Lotus rather than compiled in.
+
+use std::io;
+
+use anyhow::Context as _;
+use cid::Cid;
+use libp2p::Multiaddr;
+use once_cell::sync::Lazy;
+use reqwest::Url;
+
+use super::{drand::DRAND_MAINNET, parse_bootstrap_peers, DrandPoint, Height, HeightInfo};
+use crate::utils::db::car_stream::CarStream;
+use crate::utils::net::http_get;
+
+/// Genesis of the current incarnation of the network.
+pub static GENESIS_URL: Lazy<Url> = Lazy::new(|| {
+    "https://github.com/filecoin-project/lotus/raw/master/build/genesis/butterflynet.car.zst"
+        .parse()
+        .unwrap()
+});
+
+/// Bootstrap peers of the current incarnation of the network.
+pub static BOOTSTRAP_URL: Lazy<Url> = Lazy::new(|| {
+    "https://github.com/filecoin-project/lotus/raw/master/build/bootstrap/butterflynet.pi"
+        .parse()
+        .unwrap()
+});
+
+// https://github.com/ethereum-lists/chains/blob/6b1e3ccad1cfcaae5aa1ab917960258f0ef1a6b6/_data/chains/eip155-3141592.json
+pub const ETH_CHAIN_ID: u64 = 3141592;
+
+/// Height epochs. The network is launched at the latest network version, so
+/// all upgrades happened before genesis, in order.
+pub static HEIGHT_INFOS: Lazy<[HeightInfo; 22]> = Lazy::new(|| {
+    [
+        HeightInfo {
+            height: Height::Breeze,
+            epoch: -22,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Smoke,
+            epoch: -21,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Ignition,
+            epoch: -20,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::ActorsV2,
+            epoch: -19,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Tape,
+            epoch: -18,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Liftoff,
+            epoch: -17,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Kumquat,
+            epoch: -16,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Calico,
+            epoch: -15,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Persian,
+            epoch: -14,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Orange,
+            epoch: -13,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Trust,
+            epoch: -12,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Norwegian,
+            epoch: -11,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Turbo,
+            epoch: -10,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Hyperdrive,
+            epoch: -9,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Chocolate,
+            epoch: -8,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::OhSnap,
+            epoch: -7,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Skyr,
+            epoch: -6,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Shark,
+            epoch: -5,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Hygge,
+            epoch: -4,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Lightning,
+            epoch: -3,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Thunder,
+            epoch: -2,
+            bundle: None,
+        },
+        HeightInfo {
+            height: Height::Watermelon,
+            epoch: -1,
+            bundle: Some(
+                Cid::try_from("bafy2bzaceabeegs5mhrxwqodyt7u2ulxr2jg6eh5g3545ganqzkncnxzserue")
+                    .unwrap(),
+            ),
+        },
+    ]
+});
+
+pub(super) static DRAND_SCHEDULE: [DrandPoint<'static>; 1] = [DrandPoint {
+    height: 0,
+    config: &DRAND_MAINNET,
+}];
+
+/// Downloads the genesis CAR of the network, returning its CID and bytes.
+pub async fn fetch_genesis() -> anyhow::Result<(Cid, Vec<u8>)> {
+    let bytes = http_get(&GENESIS_URL)
+        .await?
+        .bytes()
+        .await
+        .context("couldn't download the butterflynet genesis")?
+        .to_vec();
+    let header = CarStream::new(io::Cursor::new(&bytes)).await?.header;
+    let genesis = *header
+        .roots
+        .first()
+        .context("the butterflynet genesis has no root")?;
+    Ok((genesis, bytes))
+}
+
+pub async fn fetch_bootstrap_peers() -> anyhow::Result<Vec<Multiaddr>> {
+    let peers = http_get(&BOOTSTRAP_URL).await?.text().await?;
+    Ok(parse_bootstrap_peers(&peers))
+}
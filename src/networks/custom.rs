@@ -0,0 +1,369 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Networks defined in a TOML file rather than compiled into Forest, selected
+//! with `--chain custom:<path>`. A definition looks like:
+//!
+//! ```toml
+//! name = "localnet"
+//! # Relative paths are resolved against the directory of the definition.
+//! genesis = "genesis.car"
+//! bootstrap_peers = ["/ip4/10.0.0.2/tcp/1347/p2p/12D3KooWQ4fDBqHWEB36zBN2RR5FTQHbtHFhW3YqDWSg3tHu1EWC"]
+//! block_delay_secs = 4
+//! propagation_delay_secs = 1
+//! eth_chain_id = 31415926
+//!
+//! [[upgrades]]
+//! height = "Breeze"
+//! epoch = -1
+//!
+//! [[upgrades]]
+//! height = "Watermelon"
+//! epoch = 200
+//! bundle = "bafy2bzaceau5i7eanhvss22z5ixmyrihilfniqn22tvkecjj56akz4xj7fvku"
+//!
+//! [[drand_schedule]]
+//! height = 0
+//! network = "mainnet"
+//! ```
+//!
+//! Upgrades that are not listed are never scheduled, nor can the upgrades past
+//! [`NEWEST_MIGRATED_NETWORK_VERSION`](super::NEWEST_MIGRATED_NETWORK_VERSION)
+//! be listed. The consensus policy
+//! (sector sizes, minimum power, ...) is the one of devnets, and the network is
+//! handled as a [`NetworkChain::Devnet`] named after the definition.
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Context as _};
+use cid::Cid;
+use libp2p::Multiaddr;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use strum::IntoEnumIterator as _;
+
+use super::drand::{DRAND_INCENTINET, DRAND_MAINNET, DRAND_QUICKNET};
+use super::{devnet, ChainConfig, DrandPoint, Height, HeightInfo, NetworkChain};
+use crate::shim::clock::ChainEpoch;
+
+/// Prefix of the `--chain` values that select a custom network definition.
+pub const CUSTOM_CHAIN_PREFIX: &str = "custom:";
+
+static CUSTOM_NETWORK: OnceCell<CustomNetwork> = OnceCell::new();
+
+/// A `--chain` value: a network known to Forest, or the path of a custom
+/// network definition prefixed with [`CUSTOM_CHAIN_PREFIX`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainArg {
+    Network(NetworkChain),
+    Custom(PathBuf),
+}
+
+impl FromStr for ChainArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix(CUSTOM_CHAIN_PREFIX) {
+            Some(path) if !path.is_empty() => Ok(Self::Custom(path.into())),
+            Some(_) => bail!("expected `{CUSTOM_CHAIN_PREFIX}<path>`"),
+            None => Ok(Self::Network(s.parse()?)),
+        }
+    }
+}
+
+impl ChainArg {
+    /// Returns the selected network, loading and registering its definition
+    /// if it is a custom one.
+    pub fn resolve(&self) -> anyhow::Result<NetworkChain> {
+        match self {
+            Self::Network(chain) => Ok(chain.clone()),
+            Self::Custom(path) => CustomNetwork::load(path)?.register(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomNetwork {
+    pub name: String,
+    /// CAR file with the genesis state of the network.
+    pub genesis: Option<PathBuf>,
+    #[serde(default)]
+    pub bootstrap_peers: Vec<Multiaddr>,
+    #[serde(default = "default_block_delay_secs")]
+    pub block_delay_secs: u32,
+    #[serde(default = "default_propagation_delay_secs")]
+    pub propagation_delay_secs: u32,
+    #[serde(default = "default_eth_chain_id")]
+    pub eth_chain_id: u32,
+    #[serde(default)]
+    pub upgrades: Vec<CustomUpgrade>,
+    #[serde(default = "default_drand_schedule")]
+    pub drand_schedule: Vec<CustomDrandPoint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomUpgrade {
+    pub height: Height,
+    pub epoch: ChainEpoch,
+    /// Manifest CID of the actor bundle introduced by the upgrade.
+    pub bundle: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomDrandPoint {
+    pub height: ChainEpoch,
+    /// One of `mainnet`, `quicknet` or `incentinet`.
+    pub network: String,
+}
+
+fn default_block_delay_secs() -> u32 {
+    ChainConfig::devnet().block_delay_secs
+}
+
+fn default_propagation_delay_secs() -> u32 {
+    ChainConfig::devnet().propagation_delay_secs
+}
+
+fn default_eth_chain_id() -> u32 {
+    devnet::ETH_CHAIN_ID as u32
+}
+
+fn default_drand_schedule() -> Vec<CustomDrandPoint> {
+    vec![CustomDrandPoint {
+        height: 0,
+        network: "mainnet".into(),
+    }]
+}
+
+impl CustomNetwork {
+    /// Reads and checks the definition at `path`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("couldn't read network definition {}", path.display()))?;
+        let mut network = Self::from_toml(&toml)
+            .with_context(|| format!("invalid network definition {}", path.display()))?;
+        if let Some(genesis) = &mut network.genesis {
+            if genesis.is_relative() {
+                *genesis = path.parent().unwrap_or(Path::new(".")).join(&*genesis);
+            }
+        }
+        Ok(network)
+    }
+
+    pub fn from_toml(toml: &str) -> anyhow::Result<Self> {
+        let network: Self = toml::from_str(toml)?;
+        // The name is used as the directory of the chain data.
+        if network.name.is_empty()
+            || !network
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("invalid network name {:?}", network.name);
+        }
+        if matches!(
+            network.name.as_str(),
+            "mainnet" | "calibnet" | "calibrationnet" | "butterflynet" | devnet::DEVNET_2K_NAME
+        ) {
+            bail!("{} is a builtin network", network.name);
+        }
+        if network.block_delay_secs == 0 {
+            bail!("the block delay must be positive");
+        }
+        for upgrade in &network.upgrades {
+            if let Some(bundle) = &upgrade.bundle {
+                Cid::try_from(bundle.as_str()).with_context(|| {
+                    format!("invalid bundle CID for the {} upgrade", upgrade.height)
+                })?;
+            }
+        }
+        network.drand_points()?;
+        super::check_upgrades_migrated(&network.chain_config().height_infos)?;
+        Ok(network)
+    }
+
+    /// Makes this the network returned by [`get`]. Only one custom network may
+    /// be registered by a process.
+    pub fn register(self) -> anyhow::Result<NetworkChain> {
+        let chain = NetworkChain::Devnet(self.name.clone());
+        let registered = CUSTOM_NETWORK.get_or_init(|| self.clone());
+        if *registered != self {
+            bail!(
+                "a different custom network ({}) is already in use",
+                registered.name
+            );
+        }
+        Ok(chain)
+    }
+
+    pub fn chain_config(&self) -> ChainConfig {
+        // Keep the order of `Height`, which state migrations index by.
+        let height_infos = Height::iter()
+            .map(|height| {
+                match self
+                    .upgrades
+                    .iter()
+                    .find(|upgrade| upgrade.height == height)
+                {
+                    Some(upgrade) => HeightInfo {
+                        height,
+                        epoch: upgrade.epoch,
+                        bundle: upgrade
+                            .bundle
+                            .as_deref()
+                            .map(|bundle| Cid::try_from(bundle).expect("checked when loaded")),
+                    },
+                    None => HeightInfo {
+                        height,
+                        epoch: ChainEpoch::MAX,
+                        bundle: None,
+                    },
+                }
+            })
+            .collect();
+        ChainConfig {
+            network: NetworkChain::Devnet(self.name.clone()),
+            genesis_cid: None,
+            bootstrap_peers: self.bootstrap_peers.clone(),
+            block_delay_secs: self.block_delay_secs,
+            propagation_delay_secs: self.propagation_delay_secs,
+            height_infos,
+            eth_chain_id: self.eth_chain_id,
+            ..ChainConfig::devnet()
+        }
+    }
+
+    pub(super) fn drand_points(&self) -> anyhow::Result<Vec<DrandPoint<'static>>> {
+        self.drand_schedule
+            .iter()
+            .map(|point| {
+                let config = match point.network.as_str() {
+                    "mainnet" => &DRAND_MAINNET,
+                    "quicknet" => &DRAND_QUICKNET,
+                    "incentinet" => &DRAND_INCENTINET,
+                    other => bail!("unknown drand network {other}"),
+                };
+                Ok(DrandPoint {
+                    height: point.height,
+                    config,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The registered custom network called `name`, if any.
+pub fn get(name: &str) -> Option<&'static CustomNetwork> {
+    CUSTOM_NETWORK.get().filter(|network| network.name == name)
+}
+
+/// Genesis CAR of `chain`, if it is a custom network that defines one.
+pub fn genesis_file(chain: &NetworkChain) -> Option<PathBuf> {
+    match chain {
+        NetworkChain::Devnet(name) => get(name).and_then(|network| network.genesis.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shim::version::NetworkVersion;
+
+    const DEFINITION: &str = r#"
+        name = "localnet"
+        genesis = "genesis.car"
+        block_delay_secs = 2
+
+        [[upgrades]]
+        height = "Breeze"
+        epoch = -1
+
+        [[upgrades]]
+        height = "Hygge"
+        epoch = -1
+
+        [[upgrades]]
+        height = "Watermelon"
+        epoch = 100
+        bundle = "bafy2bzaceau5i7eanhvss22z5ixmyrihilfniqn22tvkecjj56akz4xj7fvku"
+    "#;
+
+    #[test]
+    fn chain_config_from_definition() {
+        let network = CustomNetwork::from_toml(DEFINITION).unwrap();
+        let config = network.chain_config();
+        assert_eq!(config.network, NetworkChain::Devnet("localnet".into()));
+        assert_eq!(config.block_delay_secs, 2);
+        assert_eq!(config.propagation_delay_secs, 1);
+        assert_eq!(config.network_version(50), NetworkVersion::V18);
+        assert_eq!(config.network_version(101), NetworkVersion::V21);
+        assert_eq!(config.epoch(Height::Watermelon), 100);
+        assert_eq!(config.epoch(Height::Dragon), ChainEpoch::MAX);
+        assert_eq!(network.drand_points().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn invalid_definitions() {
+        assert!(CustomNetwork::from_toml(r#"name = "mainnet""#).is_err());
+        assert!(CustomNetwork::from_toml(r#"name = "../localnet""#).is_err());
+        assert!(CustomNetwork::from_toml(
+            r#"name = "localnet"
+            unknown = 1"#
+        )
+        .is_err());
+        assert!(CustomNetwork::from_toml(
+            r#"name = "localnet"
+            [[upgrades]]
+            height = "Hygge"
+            epoch = 0
+            bundle = "not a cid""#
+        )
+        .is_err());
+        assert!(CustomNetwork::from_toml(
+            r#"name = "localnet"
+            [[drand_schedule]]
+            height = 0
+            network = "testnet""#
+        )
+        .is_err());
+        assert!(CustomNetwork::from_toml(
+            r#"name = "localnet"
+            block_delay_secs = 0"#
+        )
+        .is_err());
+        assert!(CustomNetwork::from_toml(
+            r#"name = "localnet"
+            [[upgrades]]
+            height = "Dragon"
+            epoch = 300"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse_chain_arg() {
+        assert_eq!(
+            "calibnet".parse::<ChainArg>().unwrap(),
+            ChainArg::Network(NetworkChain::Calibnet)
+        );
+        assert_eq!(
+            "custom:localnet.toml".parse::<ChainArg>().unwrap(),
+            ChainArg::Custom("localnet.toml".into())
+        );
+        assert!("custom:".parse::<ChainArg>().is_err());
+    }
+
+    #[test]
+    fn genesis_relative_to_definition() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("localnet.toml");
+        std::fs::write(&path, DEFINITION).unwrap();
+        let network = CustomNetwork::load(&path).unwrap();
+        assert_eq!(network.genesis, Some(dir.path().join("genesis.car")));
+    }
+}
@@ -6,6 +6,9 @@ use once_cell::sync::Lazy;
 
 use super::{drand::DRAND_MAINNET, DrandPoint, Height, HeightInfo};
 
+/// Name of the devnet profile matching the `2k` build of Lotus.
+pub const DEVNET_2K_NAME: &str = "devnet-2k";
+
 // https://github.com/ethereum-lists/chains/blob/6b1e3ccad1cfcaae5aa1ab917960258f0ef1a6b6/_data/chains/eip155-31415926.json
 pub const ETH_CHAIN_ID: u64 = 31415926;
 
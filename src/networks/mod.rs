@@ -1,15 +1,18 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use std::{fmt::Display, str::FromStr};
+use std::{fmt::Display, str::FromStr, sync::Arc};
 
+use anyhow::Context as _;
 use cid::Cid;
 use fil_actors_shared::v10::runtime::Policy;
 use libp2p::Multiaddr;
 use serde::{Deserialize, Serialize};
-use strum_macros::Display;
+use strum::IntoEnumIterator as _;
+use strum_macros::{Display, EnumIter};
 
-use crate::beacon::{BeaconPoint, BeaconSchedule, DrandBeacon, DrandConfig};
+use crate::beacon::{BeaconPoint, BeaconSchedule, DrandBeacon, DrandConfig, DrandNetwork};
+use crate::db::SettingsStore;
 use crate::shim::clock::{ChainEpoch, EPOCH_DURATION_SECONDS};
 use crate::shim::sector::{RegisteredPoStProofV3, RegisteredSealProofV3};
 use crate::shim::version::NetworkVersion;
@@ -18,14 +21,28 @@ mod actors_bundle;
 pub use actors_bundle::{generate_actor_bundle, ActorBundleInfo, ACTOR_BUNDLES};
 
 mod drand;
+use drand::DRAND_QUICKNET;
 
+pub mod butterflynet;
 pub mod calibnet;
+pub mod custom;
 pub mod devnet;
 pub mod mainnet;
+mod reset;
+pub use reset::handle_reset;
+
+/// Prefix of the environment variables that move an upgrade of a test network,
+/// e.g. `FOREST_OVERRIDE_UPGRADE_NV22=200`.
+pub const UPGRADE_OVERRIDE_ENV_PREFIX: &str = "FOREST_OVERRIDE_UPGRADE_NV";
 
 /// Newest network version for all networks
 pub const NEWEST_NETWORK_VERSION: NetworkVersion = NetworkVersion::V17;
 
+/// Newest network version Forest migrates the state to. The upgrades to later
+/// versions can't be scheduled, the chain would cross them without migrating
+/// its state.
+pub const NEWEST_MIGRATED_NETWORK_VERSION: NetworkVersion = NetworkVersion::V21;
+
 /// Forest builtin `filecoin` network chains. In general only `mainnet` and its
 /// chain information should be considered stable.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -35,6 +52,7 @@ pub enum NetworkChain {
     #[default]
     Mainnet,
     Calibnet,
+    Butterflynet,
     Devnet(String),
 }
 
@@ -45,6 +63,7 @@ impl FromStr for NetworkChain {
         match s {
             "mainnet" => Ok(NetworkChain::Mainnet),
             "calibnet" | "calibrationnet" => Ok(NetworkChain::Calibnet),
+            "butterflynet" => Ok(NetworkChain::Butterflynet),
             name => Ok(NetworkChain::Devnet(name.to_owned())),
         }
     }
@@ -55,6 +74,7 @@ impl Display for NetworkChain {
         match self {
             NetworkChain::Mainnet => write!(f, "mainnet"),
             NetworkChain::Calibnet => write!(f, "calibnet"),
+            NetworkChain::Butterflynet => write!(f, "butterflynet"),
             NetworkChain::Devnet(name) => write!(f, "{name}"),
         }
     }
@@ -89,7 +109,7 @@ impl NetworkChain {
 }
 
 /// Defines the meaningful heights of the protocol.
-#[derive(Debug, Display, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Display, EnumIter, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
 pub enum Height {
     Breeze,
@@ -115,6 +135,12 @@ pub enum Height {
     Thunder,
     Watermelon,
     WatermelonFix,
+    Dragon,
+    /// Switch of the randomness beacon to `drand` quicknet, shortly after
+    /// [`Height::Dragon`]. No network schedules it until the `NV22` state
+    /// migration exists, see [`NEWEST_MIGRATED_NETWORK_VERSION`].
+    Phoenix,
+    Waffle,
 }
 
 impl Default for Height {
@@ -149,6 +175,9 @@ impl From<Height> for NetworkVersion {
             Height::Thunder => NetworkVersion::V20,
             Height::Watermelon => NetworkVersion::V21,
             Height::WatermelonFix => NetworkVersion::V21,
+            Height::Dragon => NetworkVersion::V22,
+            Height::Phoenix => NetworkVersion::V22,
+            Height::Waffle => NetworkVersion::V23,
         }
     }
 }
@@ -195,6 +224,12 @@ pub struct ChainConfig {
     #[serde(default = "default_policy")]
     pub policy: Policy,
     pub eth_chain_id: u32,
+    /// Accept blocks without verifying their winning PoSt proofs. Only meant
+    /// for local networks whose miners seal mock sectors.
+    pub mock_proofs: bool,
+    /// Epoch at which F3 (fast finality) starts, negative if F3 is not
+    /// enabled on the network.
+    pub f3_bootstrap_epoch: ChainEpoch,
 }
 
 impl ChainConfig {
@@ -209,6 +244,8 @@ impl ChainConfig {
             height_infos: HEIGHT_INFOS.to_vec(),
             policy: Policy::mainnet(),
             eth_chain_id: ETH_CHAIN_ID as u32,
+            mock_proofs: false,
+            f3_bootstrap_epoch: -1,
         }
     }
 
@@ -223,6 +260,37 @@ impl ChainConfig {
             height_infos: HEIGHT_INFOS.to_vec(),
             policy: Policy::calibnet(),
             eth_chain_id: ETH_CHAIN_ID as u32,
+            mock_proofs: false,
+            f3_bootstrap_epoch: -1,
+        }
+    }
+
+    pub fn butterflynet() -> Self {
+        use butterflynet::*;
+        let mut policy = Policy::mainnet();
+        policy.minimum_consensus_power = (2 << 30).into();
+        policy.pre_commit_challenge_delay = 150;
+
+        #[allow(clippy::disallowed_types)]
+        let allowed_proof_types = std::collections::HashSet::from_iter(vec![
+            RegisteredSealProofV3::StackedDRG512MiBV1,
+            RegisteredSealProofV3::StackedDRG32GiBV1,
+            RegisteredSealProofV3::StackedDRG64GiBV1,
+        ]);
+        policy.valid_pre_commit_proof_type = allowed_proof_types;
+
+        Self {
+            network: NetworkChain::Butterflynet,
+            genesis_cid: None,
+            // Fetched at startup, as they change with every reset.
+            bootstrap_peers: Vec::new(),
+            block_delay_secs: EPOCH_DURATION_SECONDS as u32,
+            propagation_delay_secs: 6,
+            height_infos: HEIGHT_INFOS.to_vec(),
+            policy,
+            eth_chain_id: ETH_CHAIN_ID as u32,
+            mock_proofs: false,
+            f3_bootstrap_epoch: -1,
         }
     }
 
@@ -255,6 +323,31 @@ impl ChainConfig {
             height_infos: HEIGHT_INFOS.to_vec(),
             policy,
             eth_chain_id: ETH_CHAIN_ID as u32,
+            mock_proofs: false,
+            f3_bootstrap_epoch: 1000,
+        }
+    }
+
+    /// A devnet matching the `2k` build of Lotus: 2 KiB sectors only, and mock
+    /// proofs so that a localnet seeded by `lotus-seed` can be followed without
+    /// the proof parameters of its sectors.
+    pub fn devnet_2k() -> Self {
+        let mut config = Self::devnet();
+        let policy = &mut config.policy;
+        #[allow(clippy::disallowed_types)]
+        let allowed_proof_types =
+            std::collections::HashSet::from_iter(vec![RegisteredSealProofV3::StackedDRG2KiBV1]);
+        policy.valid_pre_commit_proof_type = allowed_proof_types;
+        #[allow(clippy::disallowed_types)]
+        let allowed_proof_types = std::collections::HashSet::from_iter(vec![
+            RegisteredPoStProofV3::StackedDRGWindow2KiBV1,
+        ]);
+        policy.valid_post_proof_type = allowed_proof_types;
+
+        Self {
+            network: NetworkChain::Devnet(devnet::DEVNET_2K_NAME.to_string()),
+            mock_proofs: true,
+            ..config
         }
     }
 
@@ -262,9 +355,14 @@ impl ChainConfig {
         match network_chain {
             NetworkChain::Mainnet => Self::mainnet(),
             NetworkChain::Calibnet => Self::calibnet(),
-            NetworkChain::Devnet(name) => Self {
-                network: NetworkChain::Devnet(name.clone()),
-                ..Self::devnet()
+            NetworkChain::Butterflynet => Self::butterflynet(),
+            NetworkChain::Devnet(name) if name == devnet::DEVNET_2K_NAME => Self::devnet_2k(),
+            NetworkChain::Devnet(name) => match custom::get(name) {
+                Some(network) => network.chain_config(),
+                None => Self {
+                    network: NetworkChain::Devnet(name.clone()),
+                    ..Self::devnet()
+                },
             },
         }
     }
@@ -281,26 +379,77 @@ impl ChainConfig {
     }
 
     pub fn get_beacon_schedule(&self, genesis_ts: u64) -> BeaconSchedule {
-        let ds_iter = match self.network {
-            NetworkChain::Mainnet => mainnet::DRAND_SCHEDULE.iter(),
-            NetworkChain::Calibnet => calibnet::DRAND_SCHEDULE.iter(),
-            NetworkChain::Devnet(_) => devnet::DRAND_SCHEDULE.iter(),
-        };
+        self.beacon_schedule(genesis_ts, None)
+    }
+
+    /// Like [`ChainConfig::get_beacon_schedule`], with the verified beacon
+    /// entries kept in `store`.
+    pub fn get_beacon_schedule_with_cache(
+        &self,
+        genesis_ts: u64,
+        store: Arc<dyn SettingsStore + Send + Sync>,
+    ) -> BeaconSchedule {
+        self.beacon_schedule(genesis_ts, Some(store))
+    }
 
+    fn beacon_schedule(
+        &self,
+        genesis_ts: u64,
+        store: Option<Arc<dyn SettingsStore + Send + Sync>>,
+    ) -> BeaconSchedule {
         BeaconSchedule(
-            ds_iter
-                .map(|dc| BeaconPoint {
-                    height: dc.height,
-                    beacon: Box::new(DrandBeacon::new(
-                        genesis_ts,
-                        self.block_delay_secs as u64,
-                        dc.config,
-                    )),
+            self.drand_points()
+                .into_iter()
+                .map(|dc| {
+                    let beacon =
+                        DrandBeacon::new(genesis_ts, self.block_delay_secs as u64, dc.config);
+                    BeaconPoint {
+                        height: dc.height,
+                        beacon: Box::new(match &store {
+                            Some(store) => beacon.with_persistent_cache(store.clone()),
+                            None => beacon,
+                        }),
+                    }
                 })
                 .collect(),
         )
     }
 
+    fn drand_points(&self) -> Vec<DrandPoint<'static>> {
+        match &self.network {
+            NetworkChain::Mainnet => mainnet::DRAND_SCHEDULE.to_vec(),
+            NetworkChain::Calibnet => calibnet::DRAND_SCHEDULE.to_vec(),
+            NetworkChain::Butterflynet => butterflynet::DRAND_SCHEDULE.to_vec(),
+            NetworkChain::Devnet(name) => {
+                let mut points = match custom::get(name) {
+                    Some(network) => network
+                        .drand_points()
+                        .expect("checked when the network was loaded"),
+                    None => devnet::DRAND_SCHEDULE.to_vec(),
+                };
+                // Like Lotus devnets, switch to quicknet at the (movable)
+                // Phoenix upgrade, unless the schedule already does.
+                let phoenix = self
+                    .height_infos
+                    .iter()
+                    .find(|info| info.height == Height::Phoenix && info.epoch != ChainEpoch::MAX);
+                if let Some(phoenix) = phoenix {
+                    if !points
+                        .iter()
+                        .any(|point| point.config.network_type == DrandNetwork::Quicknet)
+                    {
+                        points.push(DrandPoint {
+                            height: phoenix.epoch,
+                            config: &DRAND_QUICKNET,
+                        });
+                        points.sort_by_key(|point| point.height);
+                    }
+                }
+                points
+            }
+        }
+    }
+
     pub fn epoch(&self, height: Height) -> ChainEpoch {
         sort_by_epoch(&self.height_infos)
             .iter()
@@ -313,13 +462,127 @@ impl ChainConfig {
         match self.network {
             NetworkChain::Mainnet => Some(mainnet::DEFAULT_GENESIS),
             NetworkChain::Calibnet => Some(calibnet::DEFAULT_GENESIS),
-            NetworkChain::Devnet(_) => None,
+            NetworkChain::Butterflynet | NetworkChain::Devnet(_) => None,
         }
     }
 
     pub fn is_testnet(&self) -> bool {
         self.network.is_testnet()
     }
+
+    /// Moves the upgrades to the given network versions to the given epochs.
+    /// When several heights share a network version, the one introducing it is
+    /// moved. Network versions that are not scheduled are added.
+    pub fn override_upgrades(
+        &mut self,
+        overrides: impl IntoIterator<Item = (u32, ChainEpoch)>,
+    ) -> anyhow::Result<()> {
+        let mut height_infos = self.height_infos.clone();
+        for (network_version, epoch) in overrides {
+            anyhow::ensure!(
+                self.is_testnet(),
+                "upgrade epochs can't be overridden on {}",
+                self.network
+            );
+            let version = NetworkVersion(network_version.into());
+            match height_infos
+                .iter_mut()
+                .find(|info| NetworkVersion::from(info.height) == version)
+            {
+                Some(info) => {
+                    tracing::warn!(
+                        "Overriding the epoch of the {} upgrade (network version {network_version}): {} -> {epoch}",
+                        info.height,
+                        info.epoch
+                    );
+                    info.epoch = epoch;
+                }
+                None => {
+                    let height = Height::iter()
+                        .find(|height| NetworkVersion::from(*height) == version)
+                        .with_context(|| format!("unknown network version {network_version}"))?;
+                    tracing::warn!(
+                        "Scheduling the {height} upgrade (network version {network_version}) at {epoch}"
+                    );
+                    // Keep the heights in the order of `Height`.
+                    let index = height_infos
+                        .iter()
+                        .position(|info| info.height as usize > height as usize)
+                        .unwrap_or(height_infos.len());
+                    height_infos.insert(
+                        index,
+                        HeightInfo {
+                            height,
+                            epoch,
+                            bundle: None,
+                        },
+                    );
+                }
+            }
+        }
+        check_upgrade_order(&height_infos)?;
+        check_upgrades_migrated(&height_infos)?;
+        self.height_infos = height_infos;
+        Ok(())
+    }
+}
+
+// Upgrades after genesis must happen in the order of their heights. Upgrades
+// before genesis, and unscheduled ones, may be in any order.
+fn check_upgrade_order(height_infos: &[HeightInfo]) -> anyhow::Result<()> {
+    let mut infos = height_infos
+        .iter()
+        .filter(|info| info.epoch != ChainEpoch::MAX)
+        .collect::<Vec<_>>();
+    infos.sort_by_key(|info| info.height as usize);
+    for pair in infos.windows(2) {
+        let (before, after) = (pair[0], pair[1]);
+        anyhow::ensure!(
+            before.epoch < 0 || before.epoch <= after.epoch,
+            "the {} upgrade at {} must not happen before the {} upgrade at {}",
+            after.height,
+            after.epoch,
+            before.height,
+            before.epoch
+        );
+    }
+    Ok(())
+}
+
+/// Fails if an upgrade past [`NEWEST_MIGRATED_NETWORK_VERSION`] is scheduled.
+pub(super) fn check_upgrades_migrated(height_infos: &[HeightInfo]) -> anyhow::Result<()> {
+    match height_infos.iter().find(|info| {
+        info.epoch != ChainEpoch::MAX
+            && NetworkVersion::from(info.height) > NEWEST_MIGRATED_NETWORK_VERSION
+    }) {
+        Some(info) => anyhow::bail!(
+            "the {} upgrade can't be scheduled, Forest has no state migration to network version {}",
+            info.height,
+            *NetworkVersion::from(info.height)
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Upgrade overrides set with [`UPGRADE_OVERRIDE_ENV_PREFIX`] variables.
+pub fn upgrade_overrides_from_env() -> anyhow::Result<Vec<(u32, ChainEpoch)>> {
+    std::env::vars()
+        .filter_map(|(key, value)| {
+            let network_version = key.strip_prefix(UPGRADE_OVERRIDE_ENV_PREFIX)?.to_owned();
+            Some((key, network_version, value))
+        })
+        .map(|(key, network_version, value)| {
+            Ok((
+                network_version
+                    .parse()
+                    .with_context(|| format!("invalid network version in {key}"))?,
+                value
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("invalid epoch in {key}: {value}"))?,
+            ))
+        })
+        .collect()
 }
 
 impl Default for ChainConfig {
@@ -343,3 +606,107 @@ pub(crate) fn parse_bootstrap_peers(bootstrap_peer_list: &str) -> Vec<Multiaddr>
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devnet_switches_to_quicknet_at_phoenix() {
+        let mut config = ChainConfig::devnet();
+        assert_eq!(config.drand_points().len(), 1);
+        config.height_infos.push(HeightInfo {
+            height: Height::Phoenix,
+            epoch: 100,
+            bundle: None,
+        });
+        let points = config.drand_points();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].height, 100);
+        assert!(points[1].config.network_type == DrandNetwork::Quicknet);
+    }
+
+    #[test]
+    fn phoenix_is_inert_on_builtin_networks() {
+        for config in [
+            ChainConfig::mainnet(),
+            ChainConfig::calibnet(),
+            ChainConfig::butterflynet(),
+            ChainConfig::devnet(),
+        ] {
+            assert!(check_upgrades_migrated(&config.height_infos).is_ok());
+            assert!(config
+                .height_infos
+                .iter()
+                .all(|info| info.height != Height::Phoenix));
+            assert!(config
+                .drand_points()
+                .iter()
+                .all(|point| point.config.network_type != DrandNetwork::Quicknet));
+        }
+    }
+
+    #[test]
+    fn devnet_2k_profile() {
+        let config = ChainConfig::from_chain(&"devnet-2k".parse().unwrap());
+        assert!(config.mock_proofs);
+        assert_eq!(config.block_delay_secs, 4);
+        assert_eq!(config.policy.valid_pre_commit_proof_type.len(), 1);
+        assert!(!ChainConfig::from_chain(&"devnet".parse().unwrap()).mock_proofs);
+    }
+
+    #[test]
+    fn f3_is_only_scheduled_on_devnets() {
+        assert!(ChainConfig::mainnet().f3_bootstrap_epoch < 0);
+        assert!(ChainConfig::calibnet().f3_bootstrap_epoch < 0);
+        assert_eq!(ChainConfig::devnet().f3_bootstrap_epoch, 1000);
+    }
+
+    #[test]
+    fn butterflynet() {
+        let chain: NetworkChain = "butterflynet".parse().unwrap();
+        assert_eq!(chain, NetworkChain::Butterflynet);
+        assert_eq!(chain.to_string(), "butterflynet");
+        let config = ChainConfig::from_chain(&chain);
+        assert!(config.is_testnet());
+        assert!(config.genesis_bytes().is_none());
+        assert_eq!(config.network_version(0), NetworkVersion::V21);
+        let bundle = config.height_infos[Height::Watermelon as usize]
+            .bundle
+            .unwrap();
+        assert!(ACTOR_BUNDLES
+            .iter()
+            .any(|info| info.manifest == bundle && info.network == chain));
+    }
+
+    #[test]
+    fn override_upgrades() {
+        let mut config = ChainConfig::devnet();
+        config.override_upgrades([(21, 200)]).unwrap();
+        assert_eq!(config.epoch(Height::Watermelon), 200);
+        assert_eq!(config.network_version(200), NetworkVersion::V20);
+        assert_eq!(config.network_version(201), NetworkVersion::V21);
+
+        // Tape introduced network version 5, not Liftoff.
+        config.override_upgrades([(5, -100)]).unwrap();
+        assert_eq!(config.epoch(Height::Tape), -100);
+
+        assert!(config.override_upgrades([(99, 1)]).is_err());
+
+        // Upgrades can't be moved before the previous ones.
+        let mut config = ChainConfig::devnet();
+        config.override_upgrades([(21, 200)]).unwrap();
+        assert!(config.override_upgrades([(20, 300)]).is_err());
+        assert_eq!(config.epoch(Height::Watermelon), 200);
+        // Nor can the upgrades that have no state migration be scheduled.
+        assert!(config.override_upgrades([(22, 300)]).is_err());
+        assert!(config.override_upgrades([(23, 300)]).is_err());
+        assert!(config
+            .height_infos
+            .iter()
+            .all(|info| info.height != Height::Dragon));
+        assert!(ChainConfig::mainnet()
+            .override_upgrades([(21, 200)])
+            .is_err());
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Test networks are occasionally relaunched from a new genesis. Chain data
+//! synced before such a reset can't be reconciled with the new chain, so it
+//! has to be detected before the database is opened.
+
+use std::io;
+use std::path::Path;
+
+use anyhow::bail;
+use cid::Cid;
+use tracing::warn;
+
+use super::NetworkChain;
+
+/// File of the chain directory holding the genesis CID the chain data belongs
+/// to.
+const GENESIS_CID_FILE: &str = "genesis_cid";
+
+/// Detects a reset of `network` by comparing `genesis` with the genesis the
+/// chain data in `chain_path` was synced from. The data of a previous
+/// incarnation is only removed if `auto_reset` is set, otherwise an error
+/// explaining how to recover is returned.
+pub fn handle_reset(
+    chain_path: &Path,
+    network: &NetworkChain,
+    genesis: &Cid,
+    auto_reset: bool,
+) -> anyhow::Result<()> {
+    let genesis_file = chain_path.join(GENESIS_CID_FILE);
+    match std::fs::read_to_string(&genesis_file) {
+        Ok(previous) if previous.trim() == genesis.to_string() => return Ok(()),
+        Ok(previous) => {
+            let previous = previous.trim();
+            if !auto_reset {
+                let hint = match network {
+                    NetworkChain::Butterflynet => {
+                        " Butterflynet is relaunched for every upgrade rehearsal, consider \
+                         always running it with --auto-reset-network."
+                    }
+                    _ => "",
+                };
+                bail!(
+                    "{network} was reset: the chain data in {} belongs to genesis {previous}, \
+                     but the network now starts at {genesis}. Restart with --auto-reset-network \
+                     to remove it and resync, or remove it manually.{hint}",
+                    chain_path.display()
+                );
+            }
+            warn!(
+                "{network} was reset (genesis {previous} replaced by {genesis}), removing the chain data in {}",
+                chain_path.display()
+            );
+            std::fs::remove_dir_all(chain_path)?;
+        }
+        // Chain data written before the genesis was recorded is assumed to
+        // belong to the current genesis.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    std::fs::create_dir_all(chain_path)?;
+    std::fs::write(genesis_file, genesis.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn second_genesis() -> Cid {
+        "bafy2bzacecyaggy24wol5ruvs6qm73gjibs2l2iyhcqmvi7r7a4ph7zx3yqd4"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn chain_data_removed_on_reset() {
+        let dir = tempfile::tempdir().unwrap();
+        let chain_path = dir.path().join("butterflynet");
+        let network = NetworkChain::Butterflynet;
+        let first = Cid::default();
+
+        handle_reset(&chain_path, &network, &first, false).unwrap();
+        std::fs::write(chain_path.join("data"), "").unwrap();
+        handle_reset(&chain_path, &network, &first, false).unwrap();
+        assert!(chain_path.join("data").exists());
+
+        let error = handle_reset(&chain_path, &network, &second_genesis(), false).unwrap_err();
+        assert!(error.to_string().contains("upgrade rehearsal"));
+        assert!(chain_path.join("data").exists());
+
+        handle_reset(&chain_path, &network, &second_genesis(), true).unwrap();
+        assert!(!chain_path.join("data").exists());
+        assert_eq!(
+            std::fs::read_to_string(chain_path.join(GENESIS_CID_FILE)).unwrap(),
+            second_genesis().to_string()
+        );
+    }
+
+    #[test]
+    fn calibnet_reset_requires_opt_in() {
+        let dir = tempfile::tempdir().unwrap();
+        let chain_path = dir.path().join("calibnet");
+        let network = NetworkChain::Calibnet;
+
+        handle_reset(&chain_path, &network, &Cid::default(), false).unwrap();
+        std::fs::write(chain_path.join("data"), "").unwrap();
+
+        assert!(handle_reset(&chain_path, &network, &second_genesis(), false).is_err());
+        assert!(chain_path.join("data").exists());
+
+        handle_reset(&chain_path, &network, &second_genesis(), true).unwrap();
+        assert!(!chain_path.join("data").exists());
+    }
+}
@@ -0,0 +1,280 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+#![allow(clippy::unused_async)]
+
+use std::collections::BTreeMap;
+
+use crate::blocks::Tipset;
+use crate::chain::index::ResolveNullTipset;
+use crate::lotus_json::LotusJson;
+use crate::rpc_api::{
+    actor_events_api::{ActorEvent, ActorEventBlock, ActorEventFilter, ApiEventEntry},
+    chain_api::{ApiHeadChange, HeadChangeType},
+    data_types::RPCState,
+};
+use crate::shim::{
+    address::Address,
+    executor::{EventEntry, Receipt_v3, StampedEvent},
+};
+use anyhow::{ensure, Context as _};
+use fil_actors_shared::fvm_ipld_amt::{Amt, Amtv0};
+use fvm_ipld_blockstore::Blockstore;
+use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
+use tracing::debug;
+
+/// Largest range of epochs of a `GetActorEventsRaw` call, a day of epochs.
+const MAX_EPOCH_RANGE: i64 = 2880;
+
+/// [`ActorEventFilter`] with the emitters resolved to actor IDs.
+#[derive(Debug, Default)]
+pub struct EventFilter {
+    emitters: Vec<u64>,
+    fields: BTreeMap<String, Vec<ActorEventBlock>>,
+}
+
+impl EventFilter {
+    fn matches(&self, emitter: u64, entries: &[EventEntry]) -> bool {
+        (self.emitters.is_empty() || self.emitters.contains(&emitter))
+            && self.fields.iter().all(|(key, values)| {
+                entries.iter().any(|entry| {
+                    &entry.key == key
+                        && values
+                            .iter()
+                            .any(|value| value.codec == entry.codec && value.value == entry.value)
+                })
+            })
+    }
+}
+
+/// Events of the chain, for the WebSocket subscriptions that aren't generic
+/// over the database.
+pub trait ActorEventSource: Send + Sync {
+    /// Resolves the emitters of a filter at the current head.
+    fn event_filter(&self, filter: &ActorEventFilter) -> anyhow::Result<EventFilter>;
+
+    /// Events emitted by the messages of the parent of `child`, which holds
+    /// their receipts.
+    fn parent_events(
+        &self,
+        child: &Tipset,
+        filter: &EventFilter,
+        reverted: bool,
+    ) -> anyhow::Result<Vec<ActorEvent>>;
+}
+
+impl<DB: Blockstore + Send + Sync + 'static> ActorEventSource for RPCState<DB> {
+    fn event_filter(&self, filter: &ActorEventFilter) -> anyhow::Result<EventFilter> {
+        let head = self.chain_store.heaviest_tipset();
+        let emitters = filter
+            .addresses
+            .iter()
+            .map(|address| {
+                let id = self
+                    .state_manager
+                    .lookup_id(address, &head)?
+                    .with_context(|| format!("actor {address} not found"))?;
+                Ok(id.id()?)
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(EventFilter {
+            emitters,
+            fields: filter.fields.clone(),
+        })
+    }
+
+    fn parent_events(
+        &self,
+        child: &Tipset,
+        filter: &EventFilter,
+        reverted: bool,
+    ) -> anyhow::Result<Vec<ActorEvent>> {
+        let db = self.state_manager.blockstore();
+        let parent = self.chain_store.load_required_tipset(child.parents())?;
+        let messages = self.chain_store.messages_for_tipset(&parent)?;
+        // The receipts have no events root before FVM v3
+        let Ok(receipts) =
+            Amtv0::<Receipt_v3, _>::load(child.min_ticket_block().message_receipts(), db)
+        else {
+            return Ok(vec![]);
+        };
+
+        let mut actor_events = vec![];
+        for (index, message) in messages.iter().enumerate() {
+            let Some(events_root) = receipts.get(index as u64)?.and_then(|r| r.events_root) else {
+                continue;
+            };
+            let msg_cid = message.cid()?;
+            let events = match Amt::<StampedEvent, _>::load(&events_root, db) {
+                Ok(events) => events,
+                Err(e) => {
+                    // The events are only stored by the nodes that computed the state
+                    debug!("Events {events_root} of message {msg_cid} not found: {e}");
+                    continue;
+                }
+            };
+            events.for_each(|_, event| {
+                let emitter = event.emitter();
+                let entries = event.entries();
+                if !filter.matches(emitter, &entries) {
+                    return Ok(());
+                }
+                let id = Address::new_id(emitter);
+                let emitter = self
+                    .state_manager
+                    .get_actor(&id, *child.parent_state())?
+                    .and_then(|actor| actor.delegated_address)
+                    .map(Address::from)
+                    .unwrap_or(id);
+                actor_events.push(ActorEvent {
+                    entries: entries.into_iter().map(From::from).collect(),
+                    emitter,
+                    reverted,
+                    height: parent.epoch(),
+                    tipset_key: parent.key().clone(),
+                    msg_cid,
+                });
+                Ok(())
+            })?;
+        }
+        Ok(actor_events)
+    }
+}
+
+impl From<EventEntry> for ApiEventEntry {
+    fn from(entry: EventEntry) -> Self {
+        ApiEventEntry {
+            flags: entry.flags,
+            key: entry.key,
+            codec: entry.codec,
+            value: entry.value,
+        }
+    }
+}
+
+/// returns all user-programmed and built-in actor events that match the given
+/// filter, from the latest executed tipset if no epochs nor tipset are given.
+pub(in crate::rpc) async fn get_actor_events_raw<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((filter,))): Params<LotusJson<(Option<ActorEventFilter>,)>>,
+) -> Result<Vec<ActorEvent>, JsonRpcError> {
+    Ok(actor_events(&data, &filter.unwrap_or_default())?)
+}
+
+fn actor_events<DB: Blockstore + Send + Sync + 'static>(
+    data: &RPCState<DB>,
+    filter: &ActorEventFilter,
+) -> anyhow::Result<Vec<ActorEvent>> {
+    let event_filter = data.event_filter(filter)?;
+    let head = data.chain_store.heaviest_tipset();
+
+    let (from, to) = match &filter.tipset_key {
+        Some(key) => {
+            ensure!(
+                filter.from_height.is_none() && filter.to_height.is_none(),
+                "the tipset key and the heights of a filter are exclusive"
+            );
+            let tipset = data.chain_store.load_required_tipset(key)?;
+            (tipset.epoch(), tipset.epoch())
+        }
+        None => {
+            let latest = head.epoch() - 1;
+            let from = filter.from_height.unwrap_or(latest);
+            let to = filter.to_height.unwrap_or(latest).min(latest);
+            ensure!(from <= to, "the range of epochs {from}..={to} is empty");
+            ensure!(
+                to - from < MAX_EPOCH_RANGE,
+                "the range of epochs {from}..={to} is larger than {MAX_EPOCH_RANGE} epochs"
+            );
+            (from, to)
+        }
+    };
+
+    // The receipts of the messages of a tipset are in its child
+    let mut child = if to + 1 < head.epoch() {
+        data.chain_store
+            .chain_index
+            .tipset_by_height(to + 1, head, ResolveNullTipset::TakeNewer)?
+    } else {
+        head
+    };
+    let mut events_by_tipset = vec![];
+    while child.epoch() > 0 {
+        let parent = data.chain_store.load_required_tipset(child.parents())?;
+        if parent.epoch() < from {
+            break;
+        }
+        if filter
+            .tipset_key
+            .as_ref()
+            .map_or(true, |key| key == parent.key())
+        {
+            events_by_tipset.push(data.parent_events(&child, &event_filter, false)?);
+        }
+        child = parent;
+    }
+    ensure!(
+        filter.tipset_key.is_none() || !events_by_tipset.is_empty(),
+        "the tipset is not executed on the current chain"
+    );
+    Ok(events_by_tipset.into_iter().rev().flatten().collect())
+}
+
+/// Events of the messages whose tipsets are applied or reverted by head
+/// changes.
+pub(in crate::rpc) fn head_change_events(
+    source: &dyn ActorEventSource,
+    changes: &[ApiHeadChange],
+    filter: &EventFilter,
+) -> anyhow::Result<Vec<ActorEvent>> {
+    let mut events = vec![];
+    for change in changes {
+        let reverted = match change.r#type {
+            HeadChangeType::Apply => false,
+            HeadChangeType::Revert => true,
+            HeadChangeType::Current => continue,
+        };
+        events.extend(source.parent_events(&change.val, filter, reverted)?);
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: &str, value: &[u8]) -> EventEntry {
+        EventEntry {
+            flags: 0,
+            key: key.into(),
+            codec: 0x55,
+            value: value.to_vec(),
+        }
+    }
+
+    #[test]
+    fn filter_matches() {
+        let entries = [entry("t1", b"a"), entry("t2", b"b")];
+        assert!(EventFilter::default().matches(1000, &entries));
+
+        let by_emitter = EventFilter {
+            emitters: vec![1000, 1001],
+            ..Default::default()
+        };
+        assert!(by_emitter.matches(1001, &entries));
+        assert!(!by_emitter.matches(1002, &entries));
+
+        let block = |value: &[u8]| ActorEventBlock {
+            codec: 0x55,
+            value: value.to_vec(),
+        };
+        let by_fields = EventFilter {
+            fields: BTreeMap::from([
+                ("t1".into(), vec![block(b"x"), block(b"a")]),
+                ("t2".into(), vec![block(b"b")]),
+            ]),
+            ..Default::default()
+        };
+        assert!(by_fields.matches(1000, &entries));
+        assert!(!by_fields.matches(1000, &entries[..1]));
+    }
+}
@@ -15,7 +15,8 @@ pub(in crate::rpc) async fn auth_new<DB: Blockstore>(
     let auth_params: AuthNewParams = params;
     let ks = data.keystore.read().await;
     let ki = ks.get(JWT_IDENTIFIER)?;
-    let token = create_token(auth_params.perms, ki.private_key(), auth_params.token_exp)?;
+    let (token, info) = issue_token(auth_params.perms, ki.private_key(), auth_params.token_exp)?;
+    data.token_registry.write().insert(info)?;
     Ok(LotusJson(token.as_bytes().to_vec()))
 }
 
@@ -30,6 +31,27 @@ where
     let ks = data.keystore.read().await;
     let token = header_raw.trim_start_matches("Bearer ");
     let ki = ks.get(JWT_IDENTIFIER)?;
-    let perms = verify_token(token, ki.private_key())?;
-    Ok(perms)
+    let claims = decode_token(token, ki.private_key())?;
+    if let Some(id) = &claims.jti {
+        if !data.token_registry.read().contains(id) {
+            return Err(format!("token {id} has been revoked").into());
+        }
+    }
+    Ok(claims.allow)
+}
+
+/// lists the unexpired tokens issued through RPC
+pub(in crate::rpc) async fn auth_list<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> Result<Vec<TokenInfo>, JsonRpcError> {
+    Ok(data.token_registry.read().list())
+}
+
+/// revokes a token issued through RPC by its ID, returns whether the token
+/// existed
+pub(in crate::rpc) async fn auth_revoke<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((id,))): Params<LotusJson<(String,)>>,
+) -> Result<bool, JsonRpcError> {
+    Ok(data.token_registry.write().remove(&id)?)
 }
@@ -8,6 +8,7 @@ use crate::blocks::{BlockHeader, Tipset, TipsetKeys};
 use crate::chain::index::ResolveNullTipset;
 use crate::cid_collections::CidHashSet;
 use crate::lotus_json::LotusJson;
+use crate::rpc::pubsub::head_changes_of_path;
 use crate::rpc_api::data_types::ApiMessage;
 use crate::rpc_api::{
     chain_api::*,
@@ -23,7 +24,7 @@ use hex::ToHex;
 use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
 use once_cell::sync::Lazy;
 use sha2::Sha256;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, MutexGuard};
 
 pub(in crate::rpc) async fn chain_get_message<DB: Blockstore>(
     data: Data<RPCState<DB>>,
@@ -68,6 +69,34 @@ pub(crate) async fn chain_get_messages_in_tipset<DB: Blockstore>(
     Ok(LotusJson(messages))
 }
 
+pub(in crate::rpc) const EXPORT_IN_PROGRESS: &str = "Another chain export job is still in progress";
+
+/// Only one export runs at a time, whether to a file or over HTTP.
+pub(in crate::rpc) fn try_lock_export() -> Option<MutexGuard<'static, ()>> {
+    static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+    LOCK.try_lock().ok()
+}
+
+/// Checks the depth of an export and finds the tipset it starts from.
+pub(in crate::rpc) fn export_start_tipset<DB: Blockstore>(
+    data: &RPCState<DB>,
+    epoch: ChainEpoch,
+    recent_roots: i64,
+    tsk: &TipsetKeys,
+) -> anyhow::Result<Arc<Tipset>> {
+    let chain_finality = data.state_manager.chain_config().policy.chain_finality;
+    anyhow::ensure!(
+        recent_roots >= chain_finality,
+        "recent-stateroots must be greater than {chain_finality}"
+    );
+
+    let head = data.chain_store.load_required_tipset(tsk)?;
+    Ok(data
+        .chain_store
+        .chain_index
+        .tipset_by_height(epoch, head, ResolveNullTipset::TakeOlder)?)
+}
+
 pub(in crate::rpc) async fn chain_export<DB>(
     data: Data<RPCState<DB>>,
     Params(ChainExportParams {
@@ -82,28 +111,13 @@ pub(in crate::rpc) async fn chain_export<DB>(
 where
     DB: Blockstore + Send + Sync + 'static,
 {
-    static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
-
-    let _locked = LOCK.try_lock();
-    if _locked.is_err() {
+    let Some(_locked) = try_lock_export() else {
         return Err(JsonRpcError::Provided {
             code: http::StatusCode::SERVICE_UNAVAILABLE.as_u16() as _,
-            message: "Another chain export job is still in progress",
+            message: EXPORT_IN_PROGRESS,
         });
-    }
-
-    let chain_finality = data.state_manager.chain_config().policy.chain_finality;
-    if recent_roots < chain_finality {
-        Err(&format!(
-            "recent-stateroots must be greater than {chain_finality}"
-        ))?;
-    }
-
-    let head = data.chain_store.load_required_tipset(&tsk)?;
-    let start_ts =
-        data.chain_store
-            .chain_index
-            .tipset_by_height(epoch, head, ResolveNullTipset::TakeOlder)?;
+    };
+    let start_ts = export_start_tipset(&data, epoch, recent_roots, &tsk)?;
 
     match if dry_run {
         crate::chain::export::<Sha256>(
@@ -234,6 +248,20 @@ pub(in crate::rpc) async fn chain_get_tipset<DB: Blockstore>(
     Ok((*ts).clone().into())
 }
 
+/// returns the head changes leading from one tipset to another: the reverts of
+/// the tipsets after their common ancestor, newest first, then the applies of
+/// the tipsets up to the second tipset, oldest first.
+pub(in crate::rpc) async fn chain_get_path<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((from, to))): Params<LotusJson<(TipsetKeys, TipsetKeys)>>,
+) -> Result<Vec<ApiHeadChange>, JsonRpcError> {
+    let chain_store = data.state_manager.chain_store();
+    let from = chain_store.load_required_tipset(&from)?;
+    let to = chain_store.load_required_tipset(&to)?;
+    let (reverts, applies) = chain_store.chain_index.path(from, to, None)?;
+    Ok(head_changes_of_path(reverts, applies))
+}
+
 // This is basically a port of the reference implementation at
 // https://github.com/filecoin-project/lotus/blob/v1.23.0/node/impl/full/chain.go#L321
 pub(in crate::rpc) async fn chain_set_head<DB: Blockstore>(
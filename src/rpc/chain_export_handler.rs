@@ -0,0 +1,110 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Snapshot export streamed over HTTP, for clients that can't read the files
+//! written by [`Filecoin.ChainExport`](crate::rpc_api::chain_api::CHAIN_EXPORT)
+//! on the node.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use crate::blocks::TipsetKeys;
+use crate::cid_collections::CidHashSet;
+use crate::rpc_api::{
+    chain_api::CHAIN_EXPORT,
+    data_types::{JsonRpcServerState, RPCState},
+};
+use crate::shim::clock::ChainEpoch;
+use axum::{
+    body::StreamBody,
+    extract::{ConnectInfo, Query},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use fvm_ipld_blockstore::Blockstore;
+use http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use sha2::Sha256;
+use tokio_util::io::ReaderStream;
+use tracing::{info, warn};
+
+use crate::rpc::chain_api::{export_start_tipset, try_lock_export, EXPORT_IN_PROGRESS};
+use crate::rpc::rate_limit::{ClientRateLimit, RateLimiter};
+use crate::rpc::rpc_util::{check_permissions, get_auth_header};
+
+/// Size of the buffer between the export and the response body.
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct ChainExportQuery {
+    /// Epoch the snapshot starts from, the head by default
+    epoch: Option<ChainEpoch>,
+    /// Number of state roots included, the chain finality by default
+    recent_roots: Option<i64>,
+}
+
+/// Streams a `.forest.car.zst` snapshot. The checksum isn't computed, and a
+/// failed export ends the body early.
+pub async fn chain_export_handler<DB>(
+    headers: HeaderMap,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    axum::extract::State(rpc_server): axum::extract::State<JsonRpcServerState>,
+    Extension(data): Extension<Arc<RPCState<DB>>>,
+    Extension(limiter): Extension<Arc<RateLimiter>>,
+    Query(ChainExportQuery {
+        epoch,
+        recent_roots,
+    }): Query<ChainExportQuery>,
+) -> Response
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    if let Err(e) = check_permissions(rpc_server, CHAIN_EXPORT, get_auth_header(headers)).await {
+        return e.into_response();
+    }
+    let rate_limit = ClientRateLimit {
+        limiter,
+        ip: client.ip(),
+    };
+    if let Err(e) = rate_limit.check(CHAIN_EXPORT) {
+        return e.into_response();
+    }
+
+    let Some(locked) = try_lock_export() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, EXPORT_IN_PROGRESS).into_response();
+    };
+    let head = data.chain_store.heaviest_tipset();
+    let epoch = epoch.unwrap_or(head.epoch());
+    let recent_roots =
+        recent_roots.unwrap_or(data.state_manager.chain_config().policy.chain_finality);
+    let start_ts = match export_start_tipset(&data, epoch, recent_roots, &TipsetKeys::default()) {
+        Ok(start_ts) => start_ts,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    info!(
+        "Streaming a snapshot at epoch {} to {client}",
+        start_ts.epoch()
+    );
+    let (writer, reader) = tokio::io::duplex(BUFFER_SIZE);
+    tokio::spawn(async move {
+        let _locked = locked;
+        if let Err(e) = crate::chain::export::<Sha256>(
+            Arc::clone(&data.chain_store.db),
+            &start_ts,
+            recent_roots,
+            writer,
+            CidHashSet::default(),
+            true,
+        )
+        .await
+        {
+            warn!("Failed to stream a snapshot to {client}: {e}");
+        }
+    });
+
+    (
+        [("content-type", "application/octet-stream")],
+        StreamBody::new(ReaderStream::new(reader)),
+    )
+        .into_response()
+}
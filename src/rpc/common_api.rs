@@ -2,7 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 #![allow(clippy::unused_async)]
 
-use crate::rpc_api::data_types::{APIVersion, RPCState, Version};
+use crate::rpc_api::{
+    data_types::{APIVersion, DiscoverResult, RPCState, Version},
+    openrpc,
+};
 use fvm_ipld_blockstore::Blockstore;
 use jsonrpc_v2::{Data, Error as JsonRpcError};
 use semver::Version as SemVer;
@@ -20,6 +23,13 @@ pub(in crate::rpc) async fn version(
     })
 }
 
+/// describes the RPC methods, in the OpenRPC format
+pub(in crate::rpc) async fn discover(
+    forest_version: &'static str,
+) -> Result<DiscoverResult, JsonRpcError> {
+    Ok(openrpc::document(forest_version))
+}
+
 pub(in crate::rpc) async fn shutdown(shutdown_send: Sender<()>) -> Result<(), JsonRpcError> {
     // Trigger graceful shutdown
     if let Err(err) = shutdown_send.send(()).await {
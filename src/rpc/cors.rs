@@ -0,0 +1,134 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Cross-origin resource sharing, so that browser-based clients can call the
+//! RPC server.
+
+use std::sync::Arc;
+
+use crate::cli_shared::cli::RpcCorsConfig;
+use axum::{
+    extract::State,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{
+    header::{
+        ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+        ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+    },
+    HeaderValue, Method, Request, StatusCode,
+};
+
+/// Time browsers may cache the preflight responses, in seconds.
+const PREFLIGHT_MAX_AGE: u64 = 600;
+
+/// [`RpcCorsConfig`] checked and turned into header values.
+#[derive(Debug)]
+pub struct Cors {
+    any_origin: bool,
+    allowed_origins: Vec<HeaderValue>,
+    allowed_methods: HeaderValue,
+    allowed_headers: HeaderValue,
+}
+
+impl Cors {
+    pub fn new(config: &RpcCorsConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            any_origin: config.allowed_origins.iter().any(|origin| origin == "*"),
+            allowed_origins: config
+                .allowed_origins
+                .iter()
+                .map(|origin| HeaderValue::from_str(origin))
+                .collect::<Result<_, _>>()?,
+            allowed_methods: HeaderValue::from_str(&config.allowed_methods.join(", "))?,
+            allowed_headers: HeaderValue::from_str(&config.allowed_headers.join(", "))?,
+        })
+    }
+
+    /// Value of the `Access-Control-Allow-Origin` header for a request from
+    /// `origin`, if it's allowed.
+    fn allow_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        if self.any_origin {
+            Some(HeaderValue::from_static("*"))
+        } else {
+            self.allowed_origins
+                .contains(origin)
+                .then(|| origin.clone())
+        }
+    }
+}
+
+/// Answers the preflight requests and adds the CORS headers to the responses
+/// to allowed origins.
+pub async fn cors<B>(
+    State(cors): State<Arc<Cors>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let allowed_origin = request
+        .headers()
+        .get(ORIGIN)
+        .and_then(|origin| cors.allow_origin(origin));
+    let preflight = request.method() == Method::OPTIONS
+        && request
+            .headers()
+            .contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+
+    let mut response = match (preflight, &allowed_origin) {
+        (true, Some(_)) => StatusCode::NO_CONTENT.into_response(),
+        (true, None) => StatusCode::FORBIDDEN.into_response(),
+        (false, _) => next.run(request).await,
+    };
+    if let Some(origin) = allowed_origin {
+        let headers = response.headers_mut();
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.append(VARY, HeaderValue::from_static("Origin"));
+        if preflight {
+            headers.insert(ACCESS_CONTROL_ALLOW_METHODS, cors.allowed_methods.clone());
+            headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, cors.allowed_headers.clone());
+            headers.insert(ACCESS_CONTROL_MAX_AGE, PREFLIGHT_MAX_AGE.into());
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cors(allowed_origins: &[&str]) -> Cors {
+        Cors::new(&RpcCorsConfig {
+            allowed_origins: allowed_origins.iter().map(|o| o.to_string()).collect(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn allowed_origins() {
+        let origin = HeaderValue::from_static("https://app.example.com");
+        let other = HeaderValue::from_static("https://evil.example.com");
+
+        assert_eq!(cors(&[]).allow_origin(&origin), None);
+
+        let listed = cors(&["https://app.example.com"]);
+        assert_eq!(listed.allow_origin(&origin), Some(origin.clone()));
+        assert_eq!(listed.allow_origin(&other), None);
+
+        let any = cors(&["*"]);
+        assert_eq!(
+            any.allow_origin(&other),
+            Some(HeaderValue::from_static("*"))
+        );
+    }
+
+    #[test]
+    fn invalid_config() {
+        assert!(Cors::new(&RpcCorsConfig {
+            allowed_headers: vec!["Authorization\n".into()],
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -13,3 +13,11 @@ pub(in crate::rpc) async fn db_gc<DB: Blockstore>(
     rx.recv_async().await??;
     Ok(())
 }
+
+/// returns the size of the database on disk, in bytes
+pub(in crate::rpc) async fn db_size<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> Result<u64, JsonRpcError> {
+    let db_directory = data.db_directory.clone();
+    Ok(tokio::task::spawn_blocking(move || fs_extra::dir::get_size(db_directory)).await??)
+}
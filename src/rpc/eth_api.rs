@@ -0,0 +1,715 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+#![allow(clippy::unused_async)]
+
+//! Read-only subset of the Ethereum JSON-RPC API. The transactions signed
+//! with an Ethereum key are looked up by their Keccak-256 hash, see
+//! [`crate::eth`], the other ones by the digest of their message CID.
+
+use std::sync::Arc;
+
+use crate::blocks::{Tipset, TipsetKeys};
+use crate::chain::index::ResolveNullTipset;
+use crate::eth::{keccak256, Rlp};
+use crate::message::ChainMessage;
+use crate::rpc_api::data_types::RPCState;
+use crate::rpc_api::eth_api::*;
+use crate::shim::address::{Address, Protocol};
+use crate::shim::clock::ChainEpoch;
+use crate::shim::econ::{TokenAmount, BLOCK_GAS_LIMIT};
+use crate::shim::executor::{Receipt, StampedEvent};
+use crate::shim::message::{Message, METHOD_SEND};
+use crate::state_manager::{StateManager, LOOKBACK_NO_LIMIT};
+use anyhow::Context as _;
+use cid::Cid;
+use fil_actor_interface::evm::State as EvmState;
+use fil_actors_shared::fvm_ipld_amt::{Amt, Amtv0};
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{BytesDe, BytesSer, RawBytes, IPLD_RAW};
+use num::BigInt;
+use tracing::debug;
+
+use crate::rpc::gas_api::{estimate_gas_limit, estimate_gas_premium};
+use crate::rpc::*;
+
+/// Forest doesn't hold Ethereum keys.
+pub(in crate::rpc) async fn eth_accounts() -> RpcResult<Vec<EthAddress>> {
+    Ok(vec![])
+}
+
+pub(in crate::rpc) async fn eth_chain_id<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> RpcResult<EthUint64> {
+    Ok(EthUint64(
+        data.state_manager.chain_config().eth_chain_id.into(),
+    ))
+}
+
+pub(in crate::rpc) async fn eth_net_version<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> RpcResult<String> {
+    Ok(data.state_manager.chain_config().eth_chain_id.to_string())
+}
+
+pub(in crate::rpc) async fn eth_block_number<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> RpcResult<EthUint64> {
+    let head = data.state_manager.chain_store().heaviest_tipset();
+    Ok(EthUint64(head.epoch().try_into()?))
+}
+
+/// Base fee of the head, plus the estimated premium.
+pub(in crate::rpc) async fn eth_gas_price<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> RpcResult<EthBigInt> {
+    let head = data.state_manager.chain_store().heaviest_tipset();
+    let base_fee = head.blocks()[0].parent_base_fee().clone();
+    let premium = estimate_gas_premium(&data, 10).await?;
+    Ok(EthBigInt((base_fee + premium).atto().clone()))
+}
+
+pub(in crate::rpc) async fn eth_get_balance<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params((address, block)): Params<(EthAddress, BlockNumberOrPredefined)>,
+) -> RpcResult<EthBigInt> {
+    let state_manager = &data.state_manager;
+    let ts = tipset_by_block_number(state_manager, block)?;
+    let (state_root, _) = state_manager.tipset_state(&ts).await?;
+    let balance = state_manager
+        .get_actor(&address.to_filecoin_address()?, state_root)?
+        .map(|actor| actor.balance.atto().clone())
+        .unwrap_or_default();
+    Ok(EthBigInt(balance))
+}
+
+pub(in crate::rpc) async fn eth_get_transaction_count<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params((address, block)): Params<(EthAddress, BlockNumberOrPredefined)>,
+) -> RpcResult<EthUint64> {
+    let address = address.to_filecoin_address()?;
+    if block == BlockNumberOrPredefined::Pending {
+        return Ok(EthUint64(data.mpool.get_sequence(&address)?));
+    }
+    let state_manager = &data.state_manager;
+    let ts = tipset_by_block_number(state_manager, block)?;
+    let (state_root, _) = state_manager.tipset_state(&ts).await?;
+    let nonce = state_manager
+        .get_actor(&address, state_root)?
+        .map(|actor| actor.sequence)
+        .unwrap_or_default();
+    Ok(EthUint64(nonce))
+}
+
+/// Returns `null` for the null rounds.
+pub(in crate::rpc) async fn eth_get_block_by_number<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params((block, full_transactions)): Params<(BlockNumberOrPredefined, bool)>,
+) -> RpcResult<Option<EthBlock>> {
+    let state_manager = &data.state_manager;
+    let ts = tipset_by_block_number(state_manager, block)?;
+    if let BlockNumberOrPredefined::Number(EthUint64(number)) = block {
+        if u64::try_from(ts.epoch()) != Ok(number) {
+            return Ok(None);
+        }
+    }
+    Ok(Some(
+        eth_block(state_manager, &ts, full_transactions).await?,
+    ))
+}
+
+/// Returns `null` for unknown blocks.
+pub(in crate::rpc) async fn eth_get_block_by_hash<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params((hash, full_transactions)): Params<(EthHash, bool)>,
+) -> RpcResult<Option<EthBlock>> {
+    let state_manager = &data.state_manager;
+    let Some(key) = TipsetKeys::load(state_manager.blockstore(), &hash.to_cid())? else {
+        return Ok(None);
+    };
+    let ts = state_manager.chain_store().load_required_tipset(&key)?;
+    Ok(Some(
+        eth_block(state_manager, &ts, full_transactions).await?,
+    ))
+}
+
+/// Returns `null` for unknown transactions.
+pub(in crate::rpc) async fn eth_get_transaction_by_hash<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params((hash,)): Params<(EthHash,)>,
+) -> RpcResult<Option<EthTx>> {
+    let state_manager = &data.state_manager;
+    let cid = message_cid(state_manager, &hash)?;
+    let Ok(msg) = crate::chain::get_chain_message(state_manager.blockstore(), &cid) else {
+        return Ok(None);
+    };
+    let tx = match locate_message(state_manager, cid)? {
+        Some(location) => eth_tx(state_manager, &msg, &location.ts, Some(location.index))?,
+        // Still in the message pool
+        None => {
+            let head = state_manager.chain_store().heaviest_tipset();
+            eth_tx(state_manager, &msg, &head, None)?
+        }
+    };
+    Ok(Some(tx))
+}
+
+/// Returns `null` for the transactions that aren't executed yet.
+pub(in crate::rpc) async fn eth_get_transaction_receipt<DB>(
+    data: Data<RPCState<DB>>,
+    Params((hash,)): Params<(EthHash,)>,
+) -> RpcResult<Option<EthTxReceipt>>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    let state_manager = &data.state_manager;
+    let cid = message_cid(state_manager, &hash)?;
+    let Ok(msg) = crate::chain::get_chain_message(state_manager.blockstore(), &cid) else {
+        return Ok(None);
+    };
+    let Some(location) = locate_message(state_manager, cid)? else {
+        return Ok(None);
+    };
+    let tx = eth_tx(state_manager, &msg, &location.ts, Some(location.index))?;
+    let db = state_manager.blockstore();
+    let receipts = Amtv0::<Receipt, _>::load(
+        location.executed_in.min_ticket_block().message_receipts(),
+        db,
+    )?;
+    let mut cumulative_gas_used = 0;
+    receipts.for_each_while(|index, receipt| {
+        cumulative_gas_used += receipt.gas_used();
+        Ok(index < location.index as u64)
+    })?;
+    let receipt = &location.receipt;
+    let message = msg.message();
+    let succeeded = receipt.exit_code().value() == 0;
+    let contract_address = if succeeded && tx.to.is_none() {
+        created_contract_address(&receipt.return_data())
+    } else {
+        None
+    };
+    let base_fee = location.ts.blocks()[0].parent_base_fee();
+    // The premium is capped by the fee cap
+    let effective_gas_price = (base_fee + &message.gas_premium).min(message.gas_fee_cap.clone());
+    let mut logs = vec![];
+    let mut logs_bloom = [0; 256];
+    let state_root = location.executed_in.parent_state();
+    let message_logs = message_logs(state_manager, receipt, state_root)?
+        .context("the events of the message aren't stored, the state must be recomputed")?;
+    for (address, topics, data) in message_logs {
+        add_to_bloom(&mut logs_bloom, &address.0);
+        for topic in &topics {
+            add_to_bloom(&mut logs_bloom, &topic.0);
+        }
+        logs.push(EthLog {
+            address,
+            data: EthBytes(data),
+            topics,
+            removed: false,
+            log_index: EthUint64(logs.len() as u64),
+            transaction_index: EthUint64(location.index as u64),
+            transaction_hash: tx.hash,
+            block_hash: EthHash::from_cid(&location.ts.key().cid()?),
+            block_number: EthUint64(location.ts.epoch().try_into()?),
+        });
+    }
+    Ok(Some(EthTxReceipt {
+        transaction_hash: tx.hash,
+        transaction_index: EthUint64(location.index as u64),
+        block_hash: EthHash::from_cid(&location.ts.key().cid()?),
+        block_number: EthUint64(location.ts.epoch().try_into()?),
+        from: tx.from,
+        to: tx.to,
+        status: EthUint64(succeeded.into()),
+        contract_address,
+        cumulative_gas_used: EthUint64(cumulative_gas_used),
+        gas_used: EthUint64(receipt.gas_used()),
+        effective_gas_price: EthBigInt(effective_gas_price.atto().clone()),
+        logs_bloom: EthBytes(logs_bloom.to_vec()),
+        logs,
+        r#type: EthUint64(EIP_1559_TX_TYPE),
+    }))
+}
+
+/// Returns no code for the actors that aren't contracts.
+pub(in crate::rpc) async fn eth_get_code<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params((address, block)): Params<(EthAddress, BlockNumberOrPredefined)>,
+) -> RpcResult<EthBytes> {
+    let state_manager = &data.state_manager;
+    let ts = tipset_by_block_number(state_manager, block)?;
+    let (state_root, _) = state_manager.tipset_state(&ts).await?;
+    let Some(actor) = state_manager.get_actor(&address.to_filecoin_address()?, state_root)? else {
+        return Ok(EthBytes::default());
+    };
+    let db = state_manager.blockstore();
+    let Ok(state) = EvmState::load(db, actor.code, actor.state) else {
+        return Ok(EthBytes::default());
+    };
+    let bytecode = match state {
+        EvmState::V10(state) => state.bytecode,
+        EvmState::V11(state) => state.bytecode,
+        EvmState::V12(state) => state.bytecode,
+    };
+    let code = db
+        .get(&bytecode)?
+        .with_context(|| format!("bytecode {bytecode} not found"))?;
+    Ok(EthBytes(code))
+}
+
+/// Returns the 32-byte value of a storage slot, zero for unknown actors.
+pub(in crate::rpc) async fn eth_get_storage_at<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params((address, EthBytes(position), block)): Params<(
+        EthAddress,
+        EthBytes,
+        BlockNumberOrPredefined,
+    )>,
+) -> RpcResult<EthBytes> {
+    let state_manager = &data.state_manager;
+    let ts = tipset_by_block_number(state_manager, block)?;
+    let (state_root, _) = state_manager.tipset_state(&ts).await?;
+    let to = address.to_filecoin_address()?;
+    if state_manager.get_actor(&to, state_root)?.is_none() {
+        return Ok(EthBytes(vec![0; 32]));
+    }
+    let mut msg = Message {
+        from: Address::SYSTEM_ACTOR,
+        to,
+        method_num: EVM_GET_STORAGE_AT_METHOD,
+        params: RawBytes::serialize((BytesSer(&left_pad_32(&position)?),))?,
+        ..Default::default()
+    };
+    let res = state_manager.call(&mut msg, Some(ts))?;
+    let receipt = res.msg_rct.context("no receipt for the call")?;
+    if receipt.exit_code().value() != 0 {
+        return Err(anyhow::anyhow!(
+            "failed to read the storage: exit {}, reason: {}",
+            receipt.exit_code().value(),
+            res.error.unwrap_or_default()
+        )
+        .into());
+    }
+    let BytesDe(value) = fvm_ipld_encoding::from_slice(receipt.return_data().bytes())?;
+    Ok(EthBytes(left_pad_32(&value)?.to_vec()))
+}
+
+/// Returns the return data of the contract.
+pub(in crate::rpc) async fn eth_call<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params((tx, block)): Params<(EthCallMessage, BlockNumberOrPredefined)>,
+) -> RpcResult<EthBytes> {
+    let state_manager = &data.state_manager;
+    let ts = tipset_by_block_number(state_manager, block)?;
+    let mut msg = filecoin_message(tx)?;
+    let res = state_manager.call(&mut msg, Some(ts))?;
+    let receipt = res.msg_rct.context("no receipt for the call")?;
+    if receipt.exit_code().value() != 0 {
+        return Err(anyhow::anyhow!(
+            "message execution failed: exit {}, reason: {}",
+            receipt.exit_code().value(),
+            res.error.unwrap_or_default()
+        )
+        .into());
+    }
+    Ok(EthBytes(return_data(&msg, receipt.return_data())))
+}
+
+pub(in crate::rpc) async fn eth_estimate_gas<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(params): Params<EthEstimateGasParams>,
+) -> RpcResult<EthUint64> {
+    let (tx, block) = match params {
+        EthEstimateGasParams::AtBlock(tx, block) => (tx, block),
+        EthEstimateGasParams::Latest((tx,)) => (tx, BlockNumberOrPredefined::Latest),
+    };
+    let msg = filecoin_message(tx)?;
+    let ts = tipset_by_block_number(&data.state_manager, block)?;
+    let gas_limit = estimate_gas_limit(&data, msg, ts.key().clone()).await?;
+    Ok(EthUint64(gas_limit as u64))
+}
+
+fn tipset_by_block_number<DB: Blockstore>(
+    state_manager: &StateManager<DB>,
+    block: BlockNumberOrPredefined,
+) -> anyhow::Result<Arc<Tipset>> {
+    let chain_store = state_manager.chain_store();
+    let head = chain_store.heaviest_tipset();
+    let epoch = match block {
+        BlockNumberOrPredefined::Earliest => {
+            return Ok(Arc::new(Tipset::from(chain_store.genesis())))
+        }
+        BlockNumberOrPredefined::Latest | BlockNumberOrPredefined::Pending => return Ok(head),
+        BlockNumberOrPredefined::Safe => (head.epoch() - SAFE_EPOCH_DELAY).max(0),
+        BlockNumberOrPredefined::Finalized => {
+            (head.epoch() - state_manager.chain_config().policy.chain_finality).max(0)
+        }
+        BlockNumberOrPredefined::Number(EthUint64(number)) => {
+            let epoch = ChainEpoch::try_from(number)?;
+            anyhow::ensure!(epoch <= head.epoch(), "block {number} is ahead of the head");
+            epoch
+        }
+    };
+    Ok(chain_store
+        .chain_index
+        .tipset_by_height(epoch, head, ResolveNullTipset::TakeOlder)?)
+}
+
+/// The CID of the message of a transaction, see [`crate::eth`].
+fn message_cid<DB: Blockstore>(
+    state_manager: &StateManager<DB>,
+    hash: &EthHash,
+) -> anyhow::Result<Cid> {
+    let settings = state_manager.chain_store().settings();
+    Ok(crate::eth::message_cid(&*settings, hash)?.unwrap_or_else(|| hash.to_cid()))
+}
+
+/// Where an executed message is included.
+struct MessageLocation {
+    /// The tipset including the message
+    ts: Arc<Tipset>,
+    /// The position of the message in `ts`
+    index: usize,
+    /// The child of `ts`, which has the receipt
+    executed_in: Arc<Tipset>,
+    receipt: Receipt,
+}
+
+/// Returns `None` for the messages that aren't executed yet.
+fn locate_message<DB: Blockstore + Send + Sync + 'static>(
+    state_manager: &StateManager<DB>,
+    cid: Cid,
+) -> anyhow::Result<Option<MessageLocation>> {
+    let head = state_manager.chain_store().heaviest_tipset();
+    let Some((executed_in, receipt, _)) =
+        state_manager.search_for_message(head, cid, LOOKBACK_NO_LIMIT, false)?
+    else {
+        return Ok(None);
+    };
+    let ts = state_manager
+        .chain_store()
+        .load_required_tipset(executed_in.parents())?;
+    let messages = state_manager.chain_store().messages_for_tipset(&ts)?;
+    let index = messages
+        .iter()
+        .position(|m| m.cid().ok() == Some(cid))
+        .context("the message isn't in its tipset")?;
+    Ok(Some(MessageLocation {
+        ts,
+        index,
+        executed_in,
+        receipt,
+    }))
+}
+
+/// The address, topics and data of the logs of a message. Only the events of
+/// the EVM actors, whose entries are raw topics `t1` to `t4` and data `d`, are
+/// logs. `state_root` is the state the message was executed into.
+///
+/// Returns `None` if the events aren't stored, they are only stored by the
+/// nodes that computed the state.
+#[allow(clippy::type_complexity)]
+fn message_logs<DB: Blockstore + Send + Sync + 'static>(
+    state_manager: &StateManager<DB>,
+    receipt: &Receipt,
+    state_root: &Cid,
+) -> anyhow::Result<Option<Vec<(EthAddress, Vec<EthHash>, Vec<u8>)>>> {
+    let Some(events_root) = receipt.events_root() else {
+        return Ok(Some(vec![]));
+    };
+    let Ok(events) = Amt::<StampedEvent, _>::load(&events_root, state_manager.blockstore()) else {
+        return Ok(None);
+    };
+    let mut logs = vec![];
+    events.for_each(|_, event| {
+        let mut topics = vec![];
+        let mut data = vec![];
+        for entry in event.entries() {
+            if entry.codec != IPLD_RAW {
+                return Ok(());
+            }
+            match entry.key.as_str() {
+                "t1" | "t2" | "t3" | "t4" => match <[u8; 32]>::try_from(entry.value) {
+                    Ok(topic) => topics.push(EthHash(topic)),
+                    Err(_) => return Ok(()),
+                },
+                "d" => data = entry.value,
+                _ => return Ok(()),
+            }
+        }
+        let id = Address::new_id(event.emitter());
+        let address = match state_manager
+            .get_actor(&id, *state_root)?
+            .and_then(|actor| actor.delegated_address)
+        {
+            Some(delegated) => EthAddress::from_filecoin_address(&Address::from(delegated))?,
+            None => EthAddress::from_filecoin_address(&id)?,
+        };
+        logs.push((address, topics, data));
+        Ok(())
+    })?;
+    Ok(Some(logs))
+}
+
+/// Sets the 3 bits of `data` in a 2048-bit bloom filter, as in the Ethereum
+/// yellow paper.
+fn add_to_bloom(bloom: &mut [u8; 256], data: &[u8]) {
+    let hash = keccak256(data);
+    for i in [0, 2, 4] {
+        let bit = (usize::from(hash[i]) << 8 | usize::from(hash[i + 1])) & 2047;
+        bloom[255 - bit / 8] |= 1 << (bit % 8);
+    }
+}
+
+/// The Ethereum address of a contract created through the Ethereum Address
+/// Manager actor, whose return value is the ID, the robust address and the
+/// Ethereum address of the contract.
+fn created_contract_address(return_data: &RawBytes) -> Option<EthAddress> {
+    let (_, _, BytesDe(address)) =
+        fvm_ipld_encoding::from_slice::<(u64, Option<Address>, BytesDe)>(return_data).ok()?;
+    Some(EthAddress(address.try_into().ok()?))
+}
+
+fn left_pad_32(bytes: &[u8]) -> anyhow::Result<[u8; 32]> {
+    anyhow::ensure!(
+        bytes.len() <= 32,
+        "{} bytes don't fit in a word",
+        bytes.len()
+    );
+    let mut word = [0; 32];
+    word[32 - bytes.len()..].copy_from_slice(bytes);
+    Ok(word)
+}
+
+/// The `f410` address of an actor if it has one, its masked ID address
+/// otherwise.
+fn eth_address<DB: Blockstore + Send + Sync + 'static>(
+    state_manager: &StateManager<DB>,
+    addr: &Address,
+    ts: &Tipset,
+) -> anyhow::Result<EthAddress> {
+    if addr.protocol() == Protocol::Delegated {
+        return EthAddress::from_filecoin_address(addr);
+    }
+    let id = state_manager
+        .lookup_id(addr, ts)?
+        .with_context(|| format!("actor {addr} not found"))?;
+    let delegated = state_manager
+        .get_actor(&id, *ts.parent_state())?
+        .and_then(|actor| actor.delegated_address)
+        .map(Address::from);
+    match delegated {
+        Some(delegated) => EthAddress::from_filecoin_address(&delegated),
+        None => EthAddress::from_filecoin_address(&id),
+    }
+}
+
+async fn eth_block<DB: Blockstore + Send + Sync + 'static>(
+    state_manager: &Arc<StateManager<DB>>,
+    ts: &Arc<Tipset>,
+    full_transactions: bool,
+) -> anyhow::Result<EthBlock> {
+    let messages = state_manager.chain_store().messages_for_tipset(ts)?;
+    let chain_id = state_manager.chain_config().eth_chain_id.into();
+    let transactions = if full_transactions {
+        EthBlockTransactions::Full(
+            messages
+                .iter()
+                .enumerate()
+                .map(|(index, msg)| eth_tx(state_manager, msg, ts, Some(index)))
+                .collect::<anyhow::Result<_>>()?,
+        )
+    } else {
+        EthBlockTransactions::Hashes(
+            messages
+                .iter()
+                .map(|msg| crate::eth::tx_hash(msg, chain_id))
+                .collect::<anyhow::Result<_>>()?,
+        )
+    };
+    let (state_root, receipts_root) = state_manager.tipset_state(ts).await?;
+    let receipts = Amtv0::<Receipt, _>::load(&receipts_root, state_manager.blockstore())?;
+    let mut gas_used = 0;
+    let mut logs_bloom = [0; 256];
+    receipts.for_each(|_, receipt| {
+        gas_used += receipt.gas_used();
+        let Some(logs) = message_logs(state_manager, receipt, &state_root)? else {
+            debug!("Events of a message of tipset {} not found", ts.key());
+            return Ok(());
+        };
+        for (address, topics, _) in logs {
+            add_to_bloom(&mut logs_bloom, &address.0);
+            for topic in &topics {
+                add_to_bloom(&mut logs_bloom, &topic.0);
+            }
+        }
+        Ok(())
+    })?;
+    // Hash of an empty list and root of an empty trie
+    let empty_uncles_hash = EthHash(keccak256(&Rlp::List(vec![]).encode()));
+    let empty_root = EthHash(keccak256(&Rlp::Bytes(vec![]).encode()));
+    let first_block = ts.min_ticket_block();
+    Ok(EthBlock {
+        hash: EthHash::from_cid(&ts.key().cid()?),
+        parent_hash: EthHash::from_cid(&ts.parents().cid()?),
+        sha3_uncles: empty_uncles_hash,
+        miner: eth_address(state_manager, first_block.miner_address(), ts)?,
+        state_root: EthHash::from_cid(&state_root),
+        transactions_root: empty_root,
+        receipts_root: empty_root,
+        logs_bloom: EthBytes(logs_bloom.to_vec()),
+        difficulty: EthUint64::default(),
+        total_difficulty: EthUint64::default(),
+        number: EthUint64(ts.epoch().try_into()?),
+        gas_limit: EthUint64(BLOCK_GAS_LIMIT),
+        gas_used: EthUint64(gas_used),
+        timestamp: EthUint64(ts.min_timestamp()),
+        extra_data: EthBytes::default(),
+        mix_hash: EthHash::default(),
+        nonce: EthBytes(vec![0; 8]),
+        base_fee_per_gas: EthBigInt(first_block.parent_base_fee().atto().clone()),
+        size: EthUint64::default(),
+        transactions,
+        uncles: vec![],
+    })
+}
+
+/// `index` is the position of the message in the tipset `ts`, if it's
+/// included in a tipset.
+fn eth_tx<DB: Blockstore + Send + Sync + 'static>(
+    state_manager: &StateManager<DB>,
+    msg: &ChainMessage,
+    ts: &Tipset,
+    index: Option<usize>,
+) -> anyhow::Result<EthTx> {
+    let message = msg.message();
+    let (block_hash, block_number, transaction_index) = match index {
+        Some(index) => (
+            Some(EthHash::from_cid(&ts.key().cid()?)),
+            Some(EthUint64(ts.epoch().try_into()?)),
+            Some(EthUint64(index.try_into()?)),
+        ),
+        None => (None, None, None),
+    };
+    // Contracts are created through the Ethereum Address Manager actor
+    let to = if message.to == Address::ETHEREUM_ACCOUNT_MANAGER_ACTOR {
+        None
+    } else {
+        eth_address(state_manager, &message.to, ts).ok()
+    };
+    let input = if message.method_num == EVM_INVOKE_CONTRACT_METHOD {
+        fvm_ipld_encoding::from_slice::<BytesDe>(message.params.bytes())
+            .map(|BytesDe(bytes)| bytes)
+            .unwrap_or_else(|_| message.params.to_vec())
+    } else {
+        message.params.to_vec()
+    };
+    let chain_id = state_manager.chain_config().eth_chain_id.into();
+    let (v, r, s) = match msg {
+        ChainMessage::Signed(smsg) if smsg.is_delegated() => {
+            let (v, r, s) = crate::eth::signature_values(smsg.signature())?;
+            let to_big_int =
+                |bytes| EthBigInt(BigInt::from_bytes_be(num::bigint::Sign::Plus, bytes));
+            (to_big_int(v), to_big_int(r), to_big_int(s))
+        }
+        _ => Default::default(),
+    };
+    Ok(EthTx {
+        hash: crate::eth::tx_hash(msg, chain_id)?,
+        nonce: EthUint64(message.sequence),
+        block_hash,
+        block_number,
+        transaction_index,
+        from: eth_address(state_manager, &message.from, ts)?,
+        to,
+        value: EthBigInt(message.value.atto().clone()),
+        r#type: EthUint64(EIP_1559_TX_TYPE),
+        input: EthBytes(input),
+        gas: EthUint64(message.gas_limit),
+        max_fee_per_gas: EthBigInt(message.gas_fee_cap.atto().clone()),
+        max_priority_fee_per_gas: EthBigInt(message.gas_premium.atto().clone()),
+        chain_id: EthUint64(chain_id),
+        v,
+        r,
+        s,
+    })
+}
+
+/// A call with input data invokes the contract, others are plain transfers.
+fn filecoin_message(tx: EthCallMessage) -> anyhow::Result<Message> {
+    let from = match tx.from {
+        Some(from) => from.to_filecoin_address()?,
+        None => EthAddress::default().to_filecoin_address()?,
+    };
+    let to = tx
+        .to
+        .context("the creation of contracts isn't supported")?
+        .to_filecoin_address()?;
+    let (method_num, params) = match tx.data {
+        Some(EthBytes(data)) if !data.is_empty() => (
+            EVM_INVOKE_CONTRACT_METHOD,
+            RawBytes::serialize(BytesSer(&data))?,
+        ),
+        _ => (METHOD_SEND, RawBytes::default()),
+    };
+    Ok(Message {
+        from,
+        to,
+        value: tx
+            .value
+            .map(|EthBigInt(value)| TokenAmount::from_atto(value))
+            .unwrap_or_default(),
+        method_num,
+        params,
+        gas_limit: tx.gas.map(|EthUint64(gas)| gas).unwrap_or_default(),
+        gas_fee_cap: tx
+            .gas_price
+            .map(|EthBigInt(price)| TokenAmount::from_atto(price))
+            .unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+/// Contracts return CBOR-encoded bytes.
+fn return_data(msg: &Message, data: RawBytes) -> Vec<u8> {
+    if msg.method_num != EVM_INVOKE_CONTRACT_METHOD || data.is_empty() {
+        return data.to_vec();
+    }
+    fvm_ipld_encoding::from_slice::<BytesDe>(&data)
+        .map(|BytesDe(bytes)| bytes)
+        .unwrap_or_else(|_| data.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::Zero as _;
+
+    #[test]
+    fn call_input_is_cbor_encoded() {
+        let to: EthAddress = "0xd4c5fb16488aa48081296299d54b0c648c9333da"
+            .parse()
+            .unwrap();
+        let msg = filecoin_message(EthCallMessage {
+            to: Some(to),
+            data: Some(EthBytes(vec![0xde, 0xad])),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(msg.method_num, EVM_INVOKE_CONTRACT_METHOD);
+        assert_eq!(msg.params.bytes(), [0x42, 0xde, 0xad]);
+        assert_eq!(msg.to, to.to_filecoin_address().unwrap());
+        assert!(msg.value.is_zero());
+
+        let ret = RawBytes::serialize(BytesSer(&[1, 2, 3])).unwrap();
+        assert_eq!(return_data(&msg, ret), [1, 2, 3]);
+
+        let transfer = filecoin_message(EthCallMessage {
+            to: Some(to),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(transfer.method_num, METHOD_SEND);
+        assert!(filecoin_message(EthCallMessage::default()).is_err());
+    }
+}
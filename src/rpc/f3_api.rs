@@ -0,0 +1,68 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+#![allow(clippy::unused_async)]
+
+use crate::blocks::TipsetKeys;
+use crate::f3::{
+    self,
+    certs::{CertStore, FinalityCertificate},
+    F3Manifest, PowerEntry,
+};
+use crate::lotus_json::LotusJson;
+use crate::rpc_api::data_types::RPCState;
+use crate::shim::{address::Address, crypto::Signature};
+use fvm_ipld_blockstore::Blockstore;
+use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
+
+/// Returns the F3 manifest of the network the node is running
+pub(in crate::rpc) async fn f3_get_manifest<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> Result<F3Manifest, JsonRpcError> {
+    F3Manifest::new(data.state_manager.chain_config(), &data.network_name)
+        .ok_or_else(|| "F3 is not enabled on this network".into())
+}
+
+/// Returns the F3 power table derived from the parent state of a tipset
+pub(in crate::rpc) async fn f3_get_power_table<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((tsk,))): Params<LotusJson<(TipsetKeys,)>>,
+) -> Result<Vec<PowerEntry>, JsonRpcError> {
+    let ts = data.chain_store.load_required_tipset(&tsk)?;
+    Ok(f3::power_table(&data.state_manager, &ts)?)
+}
+
+/// Signs an F3 payload with a BLS key from the wallet
+pub(in crate::rpc) async fn f3_sign<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((signer, payload))): Params<LotusJson<(Address, Vec<u8>)>>,
+) -> Result<LotusJson<Signature>, JsonRpcError> {
+    let keystore = data.keystore.read().await;
+    Ok(f3::sign(&keystore, &signer, &payload)?.into())
+}
+
+/// Returns the finality certificate of a GPBFT instance
+pub(in crate::rpc) async fn f3_get_certificate<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((instance,))): Params<LotusJson<(u64,)>>,
+) -> Result<FinalityCertificate, JsonRpcError> {
+    CertStore::new(data.chain_store.settings())
+        .get(instance)?
+        .ok_or_else(|| format!("no finality certificate for instance {instance}").into())
+}
+
+/// Returns the finality certificate of the latest finalized GPBFT instance
+pub(in crate::rpc) async fn f3_get_latest_certificate<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> Result<FinalityCertificate, JsonRpcError> {
+    CertStore::new(data.chain_store.settings())
+        .latest()?
+        .ok_or_else(|| "no finality certificate stored yet".into())
+}
+
+/// Stores a finality certificate validated by the F3 sidecar
+pub(in crate::rpc) async fn f3_put_certificate<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((cert,))): Params<LotusJson<(FinalityCertificate,)>>,
+) -> Result<(), JsonRpcError> {
+    Ok(CertStore::new(data.chain_store.settings()).put(&cert)?)
+}
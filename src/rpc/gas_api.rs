@@ -10,13 +10,17 @@ use crate::rpc_api::data_types::{MessageSendSpec, RPCState};
 use crate::shim::address::Address;
 use crate::shim::econ::BLOCK_GAS_LIMIT;
 use crate::shim::{econ::TokenAmount, message::Message};
+use crate::state_migration::has_state_migration;
 use fvm_ipld_blockstore::Blockstore;
+use fvm_shared4::error::ExitCode;
 use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
 use num::BigInt;
 use num_traits::{FromPrimitive, Zero};
 use rand_distr::{Distribution, Normal};
 
-const MIN_GAS_PREMIUM: f64 = 100000.0;
+const MIN_GAS_PREMIUM: u64 = 100000;
+/// Most a message sent without a `MaxFee` can cost, 0.07 FIL, as in Lotus.
+const DEFAULT_MAX_FEE_NANO: u64 = 70_000_000;
 
 /// Estimate the fee cap
 pub(in crate::rpc) async fn gas_estimate_fee_cap<DB: Blockstore>(
@@ -48,7 +52,7 @@ fn estimate_fee_cap<DB: Blockstore>(
     Ok(out)
 }
 
-/// Estimate the fee cap
+/// Estimate the gas premium
 pub(in crate::rpc) async fn gas_estimate_gas_premium<DB: Blockstore>(
     data: Data<RPCState<DB>>,
     Params(params): Params<LotusJson<(u64, Address, i64, TipsetKeys)>>,
@@ -59,7 +63,7 @@ pub(in crate::rpc) async fn gas_estimate_gas_premium<DB: Blockstore>(
         .map(|n| TokenAmount::to_string(&n))
 }
 
-async fn estimate_gas_premium<DB: Blockstore>(
+pub(in crate::rpc) async fn estimate_gas_premium<DB: Blockstore>(
     data: &Data<RPCState<DB>>,
     mut nblocksincl: u64,
 ) -> Result<TokenAmount, JsonRpcError> {
@@ -67,11 +71,6 @@ async fn estimate_gas_premium<DB: Blockstore>(
         nblocksincl = 1;
     }
 
-    struct GasMeta {
-        pub price: TokenAmount,
-        pub limit: u64,
-    }
-
     let mut prices: Vec<GasMeta> = Vec::new();
     let mut blocks = 0;
 
@@ -88,44 +87,24 @@ async fn estimate_gas_premium<DB: Blockstore>(
         blocks += pts.blocks().len();
         let msgs = crate::chain::messages_for_tipset(data.state_manager.blockstore_owned(), &pts)?;
 
-        prices.append(
-            &mut msgs
-                .iter()
-                .map(|msg| GasMeta {
-                    price: msg.message().gas_premium(),
-                    limit: msg.message().gas_limit(),
-                })
-                .collect(),
-        );
+        prices.extend(msgs.iter().map(|msg| GasMeta {
+            price: msg.message().gas_premium(),
+            limit: msg.message().gas_limit(),
+        }));
         ts = pts;
     }
 
-    prices.sort_by(|a, b| b.price.cmp(&a.price));
-    let mut at = BLOCK_GAS_TARGET * blocks as u64 / 2;
-    let mut prev = TokenAmount::zero();
-    let mut premium = TokenAmount::zero();
-
-    for price in prices {
-        at -= price.limit;
-        if at > 0 {
-            prev = price.price;
-            continue;
-        }
-        if prev == TokenAmount::zero() {
-            let ret: TokenAmount = price.price + TokenAmount::from_atto(1);
-            return Ok(ret);
-        }
-        premium = (&price.price + &prev).div_floor(2) + TokenAmount::from_atto(1)
-    }
+    let mut premium = median_gas_premium(prices, blocks);
 
-    if premium == TokenAmount::zero() {
+    if premium < TokenAmount::from_atto(MIN_GAS_PREMIUM) {
         premium = TokenAmount::from_atto(match nblocksincl {
-            1 => (MIN_GAS_PREMIUM * 2.0) as u64,
-            2 => (MIN_GAS_PREMIUM * 1.5) as u64,
-            _ => MIN_GAS_PREMIUM as u64,
+            1 => MIN_GAS_PREMIUM * 2,
+            2 => MIN_GAS_PREMIUM * 3 / 2,
+            _ => MIN_GAS_PREMIUM,
         });
     }
 
+    // add some noise to normalize behaviour of message selection
     let precision = 32;
 
     // mean 1, stddev 0.005 => 95% within +-1%
@@ -134,12 +113,44 @@ async fn estimate_gas_premium<DB: Blockstore>(
         .sample(&mut rand::thread_rng());
 
     premium *= BigInt::from_f64(noise * (1i64 << precision) as f64)
-        .ok_or("failed to converrt gas premium f64 to bigint")?;
+        .ok_or("failed to converrt gas premium f64 to bigint")?
+        + 1;
     premium = premium.div_floor(1i64 << precision);
 
     Ok(premium)
 }
 
+struct GasMeta {
+    price: TokenAmount,
+    limit: u64,
+}
+
+/// Returns the premium of the messages filling the blocks up to their gas
+/// target, and 5% further, when ordered by decreasing premium.
+fn median_gas_premium(mut prices: Vec<GasMeta>, blocks: usize) -> TokenAmount {
+    prices.sort_by(|a, b| b.price.cmp(&a.price));
+    // 50th percentile
+    let mut at = BLOCK_GAS_TARGET as i64 * blocks as i64 / 2;
+    // move 5% further
+    at += BLOCK_GAS_TARGET as i64 * blocks as i64 / (2 * 20);
+
+    let mut prev1 = TokenAmount::zero();
+    let mut prev2 = TokenAmount::zero();
+    for price in prices {
+        prev2 = std::mem::replace(&mut prev1, price.price);
+        at -= price.limit as i64;
+        if at < 0 {
+            break;
+        }
+    }
+
+    if prev2.is_zero() {
+        prev1
+    } else {
+        (prev1 + &prev2).div_floor(2)
+    }
+}
+
 /// Estimate the gas limit
 pub(in crate::rpc) async fn gas_estimate_gas_limit<DB>(
     data: Data<RPCState<DB>>,
@@ -151,10 +162,13 @@ where
     estimate_gas_limit::<DB>(&data, msg, tsk).await
 }
 
-async fn estimate_gas_limit<DB>(
+/// Returns the gas used by the message when applied on top of the tipset
+/// `tsk`, after the pending messages of its sender. Fails if the message
+/// cannot be applied successfully.
+pub(in crate::rpc) async fn estimate_gas_limit<DB>(
     data: &Data<RPCState<DB>>,
     msg: Message,
-    _: TipsetKeys,
+    tsk: TipsetKeys,
 ) -> Result<i64, JsonRpcError>
 where
     DB: Blockstore + Send + Sync + 'static,
@@ -164,34 +178,50 @@ where
     msg.set_gas_fee_cap(TokenAmount::from_atto(MINIMUM_BASE_FEE + 1));
     msg.set_gas_premium(TokenAmount::from_atto(1));
 
-    let curr_ts = data.state_manager.chain_store().heaviest_tipset();
+    let curr_ts = data
+        .state_manager
+        .chain_store()
+        .load_required_tipset(&tsk)?;
     let from_a = data
         .state_manager
         .resolve_to_key_addr(&msg.from, &curr_ts)
         .await?;
 
-    let pending = data.mpool.pending_for(&from_a);
+    let pending = data.mpool.pending_for(&from_a).unwrap_or_default();
     let prior_messages: Vec<ChainMessage> = pending
-        .map(|s| s.into_iter().map(ChainMessage::Signed).collect::<Vec<_>>())
-        .unwrap_or_default();
+        .into_iter()
+        .take_while(|m| m.sequence() != msg.sequence)
+        .map(ChainMessage::Signed)
+        .collect();
 
-    let ts = data.mpool.cur_tipset.lock().clone();
+    // Migrations are not run by the simulation, so the message is applied
+    // before the next one instead.
+    let mut ts = curr_ts;
+    while ts.epoch() > 0
+        && has_state_migration::<DB>(ts.epoch() + 1, data.state_manager.chain_config())
+    {
+        ts = data
+            .state_manager
+            .chain_store()
+            .load_required_tipset(ts.parents())?;
+    }
     let res = data
         .state_manager
         .call_with_gas(&mut ChainMessage::Unsigned(msg), &prior_messages, Some(ts))
         .await?;
-    match res.msg_rct {
-        Some(rct) => {
-            if rct.exit_code().value() != 0 {
-                return Ok(-1);
-            }
-            // TODO(forest): https://github.com/ChainSafe/forest/issues/901
-            //               Figure out why we always under estimate the gas
-            //               calculation so we dont need to add 200000
-            Ok(rct.gas_used() as i64 + 200000)
-        }
-        None => Ok(-1),
+    let rct = res.msg_rct.ok_or("the message has no receipt")?;
+    if rct.exit_code().value() == ExitCode::SYS_OUT_OF_GAS.value() {
+        return Err("out of gas".into());
     }
+    if rct.exit_code().value() != 0 {
+        return Err(format!(
+            "message execution failed: exit {}, reason: {}",
+            rct.exit_code().value(),
+            res.error.unwrap_or_default()
+        )
+        .into());
+    }
+    Ok(rct.gas_used() as i64)
 }
 
 /// Estimates the gas parameters for a given message
@@ -212,7 +242,7 @@ where
 pub(in crate::rpc) async fn estimate_message_gas<DB>(
     data: &Data<RPCState<DB>>,
     msg: Message,
-    _spec: Option<MessageSendSpec>,
+    spec: Option<MessageSendSpec>,
     tsk: TipsetKeys,
 ) -> Result<Message, JsonRpcError>
 where
@@ -221,7 +251,9 @@ where
     let mut msg = msg;
     if msg.gas_limit == 0 {
         let gl = estimate_gas_limit::<DB>(data, msg.clone(), tsk.clone()).await?;
-        msg.set_gas_limit(gl as u64);
+        let gl = gl as f64 * data.mpool.config.gas_limit_overestimation;
+        // Overestimating may exceed the block gas limit
+        msg.set_gas_limit((gl as u64).min(BLOCK_GAS_LIMIT));
     }
     if msg.gas_premium.is_zero() {
         let gp = estimate_gas_premium(data, 10).await?;
@@ -231,8 +263,61 @@ where
         let gfp = estimate_fee_cap(data, msg.clone(), 20, tsk)?;
         msg.set_gas_fee_cap(gfp);
     }
-    // TODO(forest): https://github.com/ChainSafe/forest/issues/901
-    //               Figure out why we always under estimate the gas
-    //               calculation so we dont need to add 200000
+    cap_gas_fee(&mut msg, spec.map(|spec| spec.max_fee));
     Ok(msg)
 }
+
+/// Lowers the fee cap so that the message costs at most `max_fee`, or
+/// [`DEFAULT_MAX_FEE_NANO`], and the premium so that it does not exceed the fee cap.
+fn cap_gas_fee(msg: &mut Message, max_fee: Option<TokenAmount>) {
+    let max_fee = max_fee
+        .filter(|max_fee| !max_fee.is_zero())
+        .unwrap_or_else(|| TokenAmount::from_nano(DEFAULT_MAX_FEE_NANO));
+    let total_fee = &msg.gas_fee_cap * msg.gas_limit;
+    if total_fee > max_fee {
+        msg.gas_fee_cap = max_fee.div_floor(msg.gas_limit);
+    }
+    msg.gas_premium = msg.gas_premium.clone().min(msg.gas_fee_cap.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_premium() {
+        let meta = |price, limit| GasMeta {
+            price: TokenAmount::from_atto(price),
+            limit,
+        };
+        // Half of the gas target of two blocks, and 5%, is reached by the
+        // second message
+        let prices = vec![
+            meta(10, BLOCK_GAS_TARGET / 2),
+            meta(30, BLOCK_GAS_TARGET),
+            meta(20, BLOCK_GAS_TARGET),
+        ];
+        assert_eq!(median_gas_premium(prices, 2), TokenAmount::from_atto(25));
+        assert!(median_gas_premium(vec![], 1).is_zero());
+        // A single message does not fill the blocks
+        let prices = vec![meta(10, 1)];
+        assert_eq!(median_gas_premium(prices, 1), TokenAmount::from_atto(10));
+    }
+
+    #[test]
+    fn capped_gas_fee() {
+        let mut msg = Message {
+            gas_limit: 1_000_000,
+            gas_fee_cap: TokenAmount::from_nano(1),
+            gas_premium: TokenAmount::from_nano(2),
+            ..Default::default()
+        };
+        cap_gas_fee(&mut msg, None);
+        assert_eq!(msg.gas_fee_cap, TokenAmount::from_nano(1));
+        assert_eq!(msg.gas_premium, TokenAmount::from_nano(1));
+
+        cap_gas_fee(&mut msg, Some(TokenAmount::from_atto(500_000_000)));
+        assert_eq!(msg.gas_fee_cap, TokenAmount::from_atto(500));
+        assert_eq!(msg.gas_premium, TokenAmount::from_atto(500));
+    }
+}
@@ -0,0 +1,28 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+#![allow(clippy::unused_async)]
+
+use crate::cli_shared::logger::log_filter;
+use crate::rpc_api::log_api::{LogListResult, LogSetLevelParams};
+
+use crate::rpc::*;
+use tracing::info;
+
+pub(in crate::rpc) async fn log_list() -> RpcResult<LogListResult> {
+    Ok(log_filter().directives())
+}
+
+pub(in crate::rpc) async fn log_set_level(
+    Params((target, level)): Params<LogSetLevelParams>,
+) -> RpcResult<()> {
+    log_filter().set_level(&target, &level)?;
+    info!("Log level of {target} set to {level}");
+    Ok(())
+}
+
+pub(in crate::rpc) async fn log_reset() -> RpcResult<()> {
+    log_filter().reset()?;
+    info!("Log filter reset");
+    Ok(())
+}
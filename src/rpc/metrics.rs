@@ -0,0 +1,74 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    core::{AtomicU64, GenericCounterVec, Opts},
+    HistogramOpts, HistogramVec,
+};
+
+pub static RPC_METHOD_TIME: Lazy<Box<HistogramVec>> = Lazy::new(|| {
+    let rpc_method_time = Box::new(
+        HistogramVec::new(
+            HistogramOpts {
+                common_opts: Opts::new("rpc_method_time", "Duration of RPC method calls"),
+                buckets: vec![],
+            },
+            &[labels::METHOD],
+        )
+        .expect("Defining the rpc_method_time metric must succeed"),
+    );
+    prometheus::default_registry()
+        .register(rpc_method_time.clone())
+        .expect("Registering the rpc_method_time metric with the metrics registry must succeed");
+    rpc_method_time
+});
+pub static RPC_METHOD_PARAMS_SIZE: Lazy<Box<HistogramVec>> = Lazy::new(|| {
+    let rpc_method_params_size = Box::new(
+        HistogramVec::new(
+            HistogramOpts {
+                common_opts: Opts::new(
+                    "rpc_method_params_size",
+                    "Size of the parameters of RPC method calls, in bytes",
+                ),
+                buckets: prometheus::exponential_buckets(64.0, 4.0, 8)
+                    .expect("Defining the rpc_method_params_size buckets must succeed"),
+            },
+            &[labels::METHOD],
+        )
+        .expect("Defining the rpc_method_params_size metric must succeed"),
+    );
+    prometheus::default_registry()
+        .register(rpc_method_params_size.clone())
+        .expect(
+            "Registering the rpc_method_params_size metric with the metrics registry must succeed",
+        );
+    rpc_method_params_size
+});
+pub static RPC_METHOD_CALLS: Lazy<Box<GenericCounterVec<AtomicU64>>> = Lazy::new(|| {
+    let rpc_method_calls = Box::new(
+        GenericCounterVec::<AtomicU64>::new(
+            Opts::new("rpc_method_calls", "Number of RPC method calls"),
+            &[labels::METHOD, labels::OUTCOME],
+        )
+        .expect("Defining the rpc_method_calls metric must succeed"),
+    );
+    prometheus::default_registry()
+        .register(rpc_method_calls.clone())
+        .expect("Registering the rpc_method_calls metric with the metrics registry must succeed");
+    rpc_method_calls
+});
+
+pub mod labels {
+    /// Name of an RPC method.
+    pub const METHOD: &str = "method";
+    /// Whether an RPC method call succeeded.
+    pub const OUTCOME: &str = "outcome";
+}
+
+pub mod values {
+    /// RPC method call that returned a result.
+    pub const OK: &str = "ok";
+    /// RPC method call that returned an error.
+    pub const ERROR: &str = "error";
+}
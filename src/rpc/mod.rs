@@ -1,16 +1,26 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+mod actor_events_api;
 mod auth_api;
 mod beacon_api;
 mod chain_api;
+mod chain_export_handler;
 mod common_api;
+mod cors;
 mod db_api;
+mod eth_api;
+mod f3_api;
 mod gas_api;
+mod log_api;
+mod metrics;
 mod mpool_api;
 mod net_api;
 mod node_api;
+mod paych_api;
 mod progress_api;
+mod pubsub;
+mod rate_limit;
 mod rpc_http_handler;
 mod rpc_util;
 mod rpc_ws_handler;
@@ -18,32 +28,51 @@ mod state_api;
 mod sync_api;
 mod wallet_api;
 
-use std::{net::TcpListener, sync::Arc};
+use std::{
+    net::{SocketAddr, TcpListener},
+    sync::Arc,
+};
 
+use crate::cli_shared::cli::{RpcCorsConfig, RpcRateLimitConfig};
 use crate::rpc_api::{
-    auth_api::*, beacon_api::*, chain_api::*, common_api::*, data_types::RPCState, db_api::*,
-    gas_api::*, mpool_api::*, net_api::*, node_api::NODE_STATUS, progress_api::GET_PROGRESS,
-    state_api::*, sync_api::*, wallet_api::*,
+    actor_events_api::*, auth_api::*, beacon_api::*, chain_api::*, common_api::*,
+    data_types::RPCState, db_api::*, eth_api::*, f3_api::*, gas_api::*, log_api::*, mpool_api::*,
+    net_api::*, node_api::NODE_STATUS, paych_api::*, progress_api::GET_PROGRESS, state_api::*,
+    sync_api::*, wallet_api::*,
+};
+use axum::{
+    routing::{get, post},
+    Extension,
 };
-use axum::routing::{get, post};
 use fvm_ipld_blockstore::Blockstore;
 use jsonrpc_v2::{Data, Error as JSONRPCError, Params, Server};
 use tokio::sync::mpsc::Sender;
 use tracing::info;
 
 use crate::rpc::{
+    actor_events_api::ActorEventSource,
     beacon_api::beacon_get_entry,
-    common_api::{shutdown, start_time, version},
+    chain_export_handler::chain_export_handler,
+    common_api::{discover, shutdown, start_time, version},
+    cors::Cors,
+    pubsub::ChainNotifyHub,
+    rate_limit::RateLimiter,
     rpc_http_handler::rpc_http_handler,
+    rpc_util::MaxBatchSize,
     rpc_ws_handler::rpc_ws_handler,
     state_api::*,
 };
 
+pub(crate) use pubsub::XRPC_CHANNEL_VALUE;
+
 pub type RpcResult<T> = Result<T, JSONRPCError>;
 
 pub async fn start_rpc<DB>(
     state: Arc<RPCState<DB>>,
     rpc_endpoint: TcpListener,
+    max_batch_size: usize,
+    rate_limit: RpcRateLimitConfig,
+    cors_config: RpcCorsConfig,
     forest_version: &'static str,
     shutdown_send: Sender<()>,
 ) -> Result<(), JSONRPCError>
@@ -58,12 +87,19 @@ where
     use wallet_api::*;
 
     let block_delay = state.state_manager.chain_config().block_delay_secs as u64;
+    let chain_store = state.chain_store.clone();
+    let chain_notify = Arc::new(ChainNotifyHub::new(chain_store.heaviest_tipset()));
+    let export_state = state.clone();
+    let event_source = state.clone() as Arc<dyn ActorEventSource>;
+    let mpool_updates = state.mpool.updates.clone();
     let rpc_server = Arc::new(
         Server::new()
             .with_data(Data(state))
             // Auth API
             .with_method(AUTH_NEW, auth_new::<DB>)
             .with_method(AUTH_VERIFY, auth_verify::<DB>)
+            .with_method(AUTH_LIST, auth_list::<DB>)
+            .with_method(AUTH_REVOKE, auth_revoke::<DB>)
             // Beacon API
             .with_method(BEACON_GET_ENTRY, beacon_get_entry::<DB>)
             // Chain API
@@ -75,6 +111,7 @@ where
             .with_method(CHAIN_GET_TIPSET_BY_HEIGHT, chain_get_tipset_by_height::<DB>)
             .with_method(CHAIN_GET_GENESIS, chain_get_genesis::<DB>)
             .with_method(CHAIN_GET_TIPSET, chain_get_tipset::<DB>)
+            .with_method(CHAIN_GET_PATH, chain_get_path::<DB>)
             .with_method(CHAIN_HEAD, chain_head::<DB>)
             .with_method(CHAIN_GET_BLOCK, chain_api::chain_get_block::<DB>)
             .with_method(CHAIN_SET_HEAD, chain_api::chain_set_head::<DB>)
@@ -90,10 +127,16 @@ where
             .with_method(MPOOL_PENDING, mpool_pending::<DB>)
             .with_method(MPOOL_PUSH, mpool_push::<DB>)
             .with_method(MPOOL_PUSH_MESSAGE, mpool_push_message::<DB>)
+            .with_method(MPOOL_PUSH_UNTRUSTED, mpool_push_untrusted::<DB>)
+            .with_method(MPOOL_BATCH_PUSH, mpool_batch_push::<DB>)
+            .with_method(MPOOL_BATCH_PUSH_UNTRUSTED, mpool_batch_push_untrusted::<DB>)
             // Sync API
             .with_method(SYNC_CHECK_BAD, sync_check_bad::<DB>)
             .with_method(SYNC_MARK_BAD, sync_mark_bad::<DB>)
+            .with_method(SYNC_UNMARK_BAD, sync_unmark_bad::<DB>)
+            .with_method(SYNC_UNMARK_ALL_BAD, sync_unmark_all_bad::<DB>)
             .with_method(SYNC_STATE, sync_state::<DB>)
+            .with_method(SYNC_SUBMIT_BLOCK, sync_submit_block::<DB>)
             // Wallet API
             .with_method(WALLET_BALANCE, wallet_balance::<DB>)
             .with_method(WALLET_DEFAULT_ADDRESS, wallet_default_address::<DB>)
@@ -108,15 +151,62 @@ where
             .with_method(WALLET_DELETE, wallet_delete::<DB>)
             // State API
             .with_method(STATE_CALL, state_call::<DB>)
+            .with_method(STATE_CALL_WITH_OVERRIDES, state_call_with_overrides::<DB>)
             .with_method(STATE_REPLAY, state_replay::<DB>)
+            .with_method(STATE_REPLAY_EX, state_replay_ex::<DB>)
+            .with_method(STATE_COMPUTE, state_compute::<DB>)
+            .with_method(STATE_COMPUTE_TRACED, state_compute_traced::<DB>)
+            .with_method(MSIG_GET_AVAILABLE_BALANCE, msig_get_available_balance::<DB>)
+            .with_method(MSIG_GET_VESTED, msig_get_vested::<DB>)
+            .with_method(MSIG_GET_PENDING, msig_get_pending::<DB>)
             .with_method(STATE_NETWORK_NAME, state_network_name::<DB>)
             .with_method(STATE_NETWORK_VERSION, state_get_network_version::<DB>)
+            .with_method(STATE_GET_NETWORK_PARAMS, state_get_network_params::<DB>)
+            .with_method(STATE_NETWORK_VERSIONS, state_network_versions::<DB>)
+            .with_method(STATE_BASE_FEE_PARAMS, state_base_fee_params::<DB>)
             .with_method(STATE_GET_ACTOR, state_get_actor::<DB>)
             .with_method(STATE_MARKET_BALANCE, state_market_balance::<DB>)
             .with_method(STATE_MARKET_DEALS, state_market_deals::<DB>)
+            .with_method(STATE_MARKET_DEALS_PAGE, state_market_deals_page::<DB>)
+            .with_method(STATE_MARKET_STORAGE_DEAL, state_market_storage_deal::<DB>)
             .with_method(STATE_GET_RECEIPT, state_get_receipt::<DB>)
             .with_method(STATE_WAIT_MSG, state_wait_msg::<DB>)
+            .with_method(STATE_SEARCH_MSG, state_search_msg::<DB>)
             .with_method(STATE_FETCH_ROOT, state_fetch_root::<DB>)
+            .with_method(
+                STATE_GET_RANDOMNESS_FROM_BEACON,
+                state_get_randomness_from_beacon::<DB>,
+            )
+            .with_method(
+                STATE_GET_RANDOMNESS_FROM_TICKETS,
+                state_get_randomness_from_tickets::<DB>,
+            )
+            .with_method(STATE_TIPSET_REWARDS, state_tipset_rewards::<DB>)
+            .with_method(STATE_MINER_ACTIVE_SECTORS, state_miner_active_sectors::<DB>)
+            .with_method(STATE_MINER_SECTORS, state_miner_sectors::<DB>)
+            .with_method(STATE_MINER_PARTITIONS, state_miner_partitions::<DB>)
+            .with_method(STATE_SECTOR_GET_INFO, state_sector_get_info::<DB>)
+            .with_method(
+                STATE_SECTOR_PRE_COMMIT_INFO,
+                state_sector_pre_commit_info::<DB>,
+            )
+            .with_method(STATE_SECTOR_EXPIRATION, state_sector_expiration::<DB>)
+            .with_method(STATE_DECODE_PARAMS, state_decode_params::<DB>)
+            .with_method(STATE_ENCODE_PARAMS, state_encode_params::<DB>)
+            .with_method(STATE_BUILTIN_ACTOR, state_builtin_actor::<DB>)
+            .with_method(STATE_LIST_ACTORS_PAGE, state_list_actors_page::<DB>)
+            .with_method(
+                STATE_VERIFIED_CLIENT_STATUS,
+                state_verified_client_status::<DB>,
+            )
+            .with_method(STATE_VERIFIER_STATUS, state_verifier_status::<DB>)
+            .with_method(STATE_GET_ALLOCATION, state_get_allocation::<DB>)
+            .with_method(STATE_GET_CLAIM, state_get_claim::<DB>)
+            .with_method(STATE_CIRCULATING_SUPPLY, state_circulating_supply::<DB>)
+            .with_method(
+                STATE_VM_CIRCULATING_SUPPLY_INTERNAL,
+                state_vm_circulating_supply_internal::<DB>,
+            )
             // Gas API
             .with_method(GAS_ESTIMATE_FEE_CAP, gas_estimate_fee_cap::<DB>)
             .with_method(GAS_ESTIMATE_GAS_LIMIT, gas_estimate_gas_limit::<DB>)
@@ -125,6 +215,8 @@ where
             // Common API
             .with_method(VERSION, move || version(block_delay, forest_version))
             .with_method(SHUTDOWN, move || shutdown(shutdown_send.clone()))
+            .with_method(DISCOVER, move || discover(forest_version))
+            .with_method(RPC_DISCOVER, move || discover(forest_version))
             .with_method(START_TIME, start_time::<DB>)
             // Net API
             .with_method(NET_ADDRS_LISTEN, net_api::net_addrs_listen::<DB>)
@@ -132,23 +224,103 @@ where
             .with_method(NET_INFO, net_api::net_info::<DB>)
             .with_method(NET_CONNECT, net_api::net_connect::<DB>)
             .with_method(NET_DISCONNECT, net_api::net_disconnect::<DB>)
+            .with_method(NET_REACHABILITY, net_api::net_reachability::<DB>)
+            .with_method(NET_PUBSUB_SCORES, net_api::net_pubsub_scores::<DB>)
+            .with_method(NET_PEER_INFO, net_api::net_peer_info::<DB>)
+            .with_method(NET_AGENT_VERSION, net_api::net_agent_version::<DB>)
+            .with_method(NET_BAN_PEER, net_api::net_ban_peer::<DB>)
+            .with_method(NET_UNBAN_PEER, net_api::net_unban_peer::<DB>)
+            .with_method(NET_PROTECT_ADD, net_api::net_protect_add::<DB>)
+            .with_method(NET_PROTECT_REMOVE, net_api::net_protect_remove::<DB>)
+            .with_method(NET_PROTECT_LIST, net_api::net_protect_list::<DB>)
+            .with_method(NET_FIND_PEER, net_api::net_find_peer::<DB>)
             // DB API
             .with_method(DB_GC, db_api::db_gc::<DB>)
+            .with_method(DB_SIZE, db_api::db_size::<DB>)
             // Progress API
             .with_method(GET_PROGRESS, progress_api::get_progress)
             // Node API
             .with_method(NODE_STATUS, node_api::node_status::<DB>)
+            // F3 API
+            .with_method(F3_GET_MANIFEST, f3_api::f3_get_manifest::<DB>)
+            .with_method(F3_GET_POWER_TABLE, f3_api::f3_get_power_table::<DB>)
+            .with_method(F3_SIGN, f3_api::f3_sign::<DB>)
+            .with_method(F3_GET_CERTIFICATE, f3_api::f3_get_certificate::<DB>)
+            .with_method(
+                F3_GET_LATEST_CERTIFICATE,
+                f3_api::f3_get_latest_certificate::<DB>,
+            )
+            .with_method(F3_PUT_CERTIFICATE, f3_api::f3_put_certificate::<DB>)
+            // Log API
+            .with_method(LOG_LIST, log_api::log_list)
+            .with_method(LOG_SET_LEVEL, log_api::log_set_level)
+            .with_method(LOG_RESET, log_api::log_reset)
+            // Payment channel API
+            .with_method(PAYCH_LIST, paych_api::paych_list::<DB>)
+            .with_method(PAYCH_STATUS, paych_api::paych_status::<DB>)
+            .with_method(
+                STATE_PAYCH_VOUCHERS_CHECK,
+                paych_api::state_paych_vouchers_check::<DB>,
+            )
+            // Actor events API
+            .with_method(
+                GET_ACTOR_EVENTS_RAW,
+                actor_events_api::get_actor_events_raw::<DB>,
+            )
+            // Eth API
+            .with_method(ETH_ACCOUNTS, eth_api::eth_accounts)
+            .with_method(ETH_BLOCK_NUMBER, eth_api::eth_block_number::<DB>)
+            .with_method(ETH_CHAIN_ID, eth_api::eth_chain_id::<DB>)
+            .with_method(ETH_NET_VERSION, eth_api::eth_net_version::<DB>)
+            .with_method(ETH_GAS_PRICE, eth_api::eth_gas_price::<DB>)
+            .with_method(ETH_GET_BALANCE, eth_api::eth_get_balance::<DB>)
+            .with_method(
+                ETH_GET_TRANSACTION_COUNT,
+                eth_api::eth_get_transaction_count::<DB>,
+            )
+            .with_method(
+                ETH_GET_BLOCK_BY_NUMBER,
+                eth_api::eth_get_block_by_number::<DB>,
+            )
+            .with_method(ETH_GET_BLOCK_BY_HASH, eth_api::eth_get_block_by_hash::<DB>)
+            .with_method(
+                ETH_GET_TRANSACTION_BY_HASH,
+                eth_api::eth_get_transaction_by_hash::<DB>,
+            )
+            .with_method(
+                ETH_GET_TRANSACTION_RECEIPT,
+                eth_api::eth_get_transaction_receipt::<DB>,
+            )
+            .with_method(ETH_GET_CODE, eth_api::eth_get_code::<DB>)
+            .with_method(ETH_GET_STORAGE_AT, eth_api::eth_get_storage_at::<DB>)
+            .with_method(ETH_CALL, eth_api::eth_call::<DB>)
+            .with_method(ETH_ESTIMATE_GAS, eth_api::eth_estimate_gas::<DB>)
             .finish_unwrapped(),
     );
 
     let app = axum::Router::new()
         .route("/rpc/v0", get(rpc_ws_handler))
         .route("/rpc/v0", post(rpc_http_handler))
+        .route("/chain-export", get(chain_export_handler::<DB>))
+        .layer(Extension(export_state))
+        .layer(Extension(chain_notify.clone()))
+        .layer(Extension(event_source))
+        .layer(Extension(mpool_updates))
+        .layer(Extension(MaxBatchSize(max_batch_size)))
+        .layer(Extension(Arc::new(RateLimiter::new(rate_limit))))
+        .layer(axum::middleware::from_fn_with_state(
+            Arc::new(Cors::new(&cors_config)?),
+            cors::cors,
+        ))
         .with_state(rpc_server);
 
     info!("Ready for RPC connections");
-    let server = axum::Server::from_tcp(rpc_endpoint)?.serve(app.into_make_service());
-    server.await?;
+    let server = axum::Server::from_tcp(rpc_endpoint)?
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>());
+    tokio::select! {
+        result = server => result?,
+        () = chain_notify.run(chain_store) => {}
+    }
 
     info!("Stopped accepting RPC connections");
 
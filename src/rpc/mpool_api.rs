@@ -91,6 +91,53 @@ where
     Ok(cid.into())
 }
 
+/// Add `SignedMessage` from an untrusted source to `mpool`, return message CID
+pub(in crate::rpc) async fn mpool_push_untrusted<DB>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((signed_message,))): Params<LotusJson<(SignedMessage,)>>,
+) -> Result<LotusJson<Cid>, JsonRpcError>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    let cid = data.mpool.as_ref().push_untrusted(signed_message).await?;
+
+    Ok(cid.into())
+}
+
+/// Add a batch of `SignedMessage`s to `mpool`, return their CIDs. Stops at the
+/// first message that is rejected.
+pub(in crate::rpc) async fn mpool_batch_push<DB>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((signed_messages,))): Params<LotusJson<(Vec<SignedMessage>,)>>,
+) -> Result<LotusJson<Vec<Cid>>, JsonRpcError>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    let mut cids = Vec::with_capacity(signed_messages.len());
+    for signed_message in signed_messages {
+        cids.push(data.mpool.as_ref().push(signed_message).await?);
+    }
+
+    Ok(cids.into())
+}
+
+/// Add a batch of `SignedMessage`s from an untrusted source to `mpool`, return
+/// their CIDs. Stops at the first message that is rejected.
+pub(in crate::rpc) async fn mpool_batch_push_untrusted<DB>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((signed_messages,))): Params<LotusJson<(Vec<SignedMessage>,)>>,
+) -> Result<LotusJson<Vec<Cid>>, JsonRpcError>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    let mut cids = Vec::with_capacity(signed_messages.len());
+    for signed_message in signed_messages {
+        cids.push(data.mpool.as_ref().push_untrusted(signed_message).await?);
+    }
+
+    Ok(cids.into())
+}
+
 /// Sign given `UnsignedMessage` and add it to `mpool`, return `SignedMessage`
 pub(in crate::rpc) async fn mpool_push_message<DB>(
     data: Data<RPCState<DB>>,
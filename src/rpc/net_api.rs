@@ -1,13 +1,14 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 
 use crate::libp2p::{NetRPCMethods, NetworkMessage, PeerId};
 use crate::rpc_api::{
     data_types::{AddrInfo, RPCState},
     net_api::*,
 };
+use ahash::HashSet;
 use cid::multibase;
 use futures::channel::oneshot;
 use fvm_ipld_blockstore::Blockstore;
@@ -104,3 +105,174 @@ pub(in crate::rpc) async fn net_disconnect<DB: Blockstore>(
 
     Ok(())
 }
+
+pub(in crate::rpc) async fn net_reachability<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> Result<NetReachabilityResult, JsonRpcError> {
+    let (tx, rx) = oneshot::channel();
+    let req = NetworkMessage::JSONRPCRequest {
+        method: NetRPCMethods::Reachability(tx),
+    };
+
+    data.network_send.send_async(req).await?;
+    Ok(rx.await?)
+}
+
+pub(in crate::rpc) async fn net_pubsub_scores<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> Result<Vec<PubsubScore>, JsonRpcError> {
+    let (tx, rx) = oneshot::channel();
+    let req = NetworkMessage::JSONRPCRequest {
+        method: NetRPCMethods::PubsubScores(tx),
+    };
+
+    data.network_send.send_async(req).await?;
+    let mut scores: Vec<_> = rx
+        .await?
+        .into_iter()
+        .map(|(peer_id, score)| PubsubScore {
+            id: peer_id.to_string(),
+            score: PeerScoreSnapshot { score },
+        })
+        .collect();
+    scores.sort_by(|a, b| b.score.score.total_cmp(&a.score.score));
+    Ok(scores)
+}
+
+pub(in crate::rpc) async fn net_peer_info<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params((id,)): Params<(String,)>,
+) -> Result<ExtendedPeerInfo, JsonRpcError> {
+    let peer_id = PeerId::from_str(&id)?;
+
+    let (tx, rx) = oneshot::channel();
+    let req = NetworkMessage::JSONRPCRequest {
+        method: NetRPCMethods::PeerInfo(tx, peer_id),
+    };
+
+    data.network_send.send_async(req).await?;
+    Ok(rx
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("peer {peer_id} is not connected"))?)
+}
+
+pub(in crate::rpc) async fn net_agent_version<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params((id,)): Params<(String,)>,
+) -> Result<String, JsonRpcError> {
+    let peer_id = PeerId::from_str(&id)?;
+
+    let (tx, rx) = oneshot::channel();
+    let req = NetworkMessage::JSONRPCRequest {
+        method: NetRPCMethods::AgentVersion(tx, peer_id),
+    };
+
+    data.network_send.send_async(req).await?;
+    Ok(rx
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("peer {peer_id} is not connected"))?)
+}
+
+pub(in crate::rpc) async fn net_ban_peer<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params((id, duration_secs)): Params<(String, Option<u64>)>,
+) -> Result<(), JsonRpcError> {
+    let peer_id = PeerId::from_str(&id)?;
+
+    let (tx, rx) = oneshot::channel();
+    let req = NetworkMessage::JSONRPCRequest {
+        method: NetRPCMethods::BanPeer(tx, peer_id, duration_secs.map(Duration::from_secs)),
+    };
+
+    data.network_send.send_async(req).await?;
+    if !rx.await? {
+        return Err(anyhow::anyhow!("peer {peer_id} is protected").into());
+    }
+    Ok(())
+}
+
+pub(in crate::rpc) async fn net_unban_peer<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params((id,)): Params<(String,)>,
+) -> Result<bool, JsonRpcError> {
+    let peer_id = PeerId::from_str(&id)?;
+
+    let (tx, rx) = oneshot::channel();
+    let req = NetworkMessage::JSONRPCRequest {
+        method: NetRPCMethods::UnbanPeer(tx, peer_id),
+    };
+
+    data.network_send.send_async(req).await?;
+    Ok(rx.await?)
+}
+
+fn parse_peer_ids(ids: &[String]) -> anyhow::Result<HashSet<PeerId>> {
+    Ok(ids
+        .iter()
+        .map(|id| PeerId::from_str(id))
+        .collect::<Result<_, _>>()?)
+}
+
+pub(in crate::rpc) async fn net_protect_add<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params((ids,)): Params<(Vec<String>,)>,
+) -> Result<(), JsonRpcError> {
+    let peer_ids = parse_peer_ids(&ids)?;
+
+    let (tx, rx) = oneshot::channel();
+    let req = NetworkMessage::JSONRPCRequest {
+        method: NetRPCMethods::ProtectPeers(tx, peer_ids),
+    };
+
+    data.network_send.send_async(req).await?;
+    rx.await?;
+    Ok(())
+}
+
+pub(in crate::rpc) async fn net_protect_remove<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params((ids,)): Params<(Vec<String>,)>,
+) -> Result<(), JsonRpcError> {
+    let peer_ids = parse_peer_ids(&ids)?;
+
+    let (tx, rx) = oneshot::channel();
+    let req = NetworkMessage::JSONRPCRequest {
+        method: NetRPCMethods::UnprotectPeers(tx, peer_ids),
+    };
+
+    data.network_send.send_async(req).await?;
+    rx.await?;
+    Ok(())
+}
+
+pub(in crate::rpc) async fn net_protect_list<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> Result<Vec<String>, JsonRpcError> {
+    let (tx, rx) = oneshot::channel();
+    let req = NetworkMessage::JSONRPCRequest {
+        method: NetRPCMethods::ProtectedPeers(tx),
+    };
+
+    data.network_send.send_async(req).await?;
+    let mut peers: Vec<_> = rx.await?.iter().map(|id| id.to_string()).collect();
+    peers.sort();
+    Ok(peers)
+}
+
+pub(in crate::rpc) async fn net_find_peer<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params((id,)): Params<(String,)>,
+) -> Result<AddrInfo, JsonRpcError> {
+    let peer_id = PeerId::from_str(&id)?;
+
+    let (tx, rx) = oneshot::channel();
+    let req = NetworkMessage::JSONRPCRequest {
+        method: NetRPCMethods::FindPeer(tx, peer_id),
+    };
+
+    data.network_send.send_async(req).await?;
+    let addrs = rx
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("peer {peer_id} not found"))?;
+    Ok(AddrInfo { id, addrs })
+}
@@ -4,11 +4,16 @@
 
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::rpc_api::{data_types::RPCState, node_api::NodeStatusResult};
+use crate::libp2p::{NetRPCMethods, NetworkMessage, PeerCounts, PUBSUB_BLOCK_STR, PUBSUB_MSG_STR};
+use crate::rpc_api::{
+    data_types::RPCState,
+    node_api::{NodeMpoolStatus, NodePeerStatus, NodeStatusResult},
+};
+use futures::channel::oneshot;
 use fvm_ipld_blockstore::Blockstore;
 use jsonrpc_v2::{Data, Error as JsonRpcError};
 
-pub(in crate::rpc) async fn node_status<DB: Blockstore>(
+pub(in crate::rpc) async fn node_status<DB: Blockstore + Send + Sync + 'static>(
     data: Data<RPCState<DB>>,
 ) -> Result<NodeStatusResult, JsonRpcError> {
     let mut node_status = NodeStatusResult::default();
@@ -31,6 +36,19 @@ pub(in crate::rpc) async fn node_status<DB: Blockstore>(
     node_status.sync_status.epoch = head.epoch() as u64;
     node_status.sync_status.behind = behind;
 
+    // The peers are unknown when running offline
+    if let Some(peer_counts) = peer_counts(&data).await {
+        node_status.peer_status = peer_status(peer_counts);
+    }
+
+    let (pending_messages, pending_senders) = data.mpool.pending_count();
+    node_status.mpool_status = NodeMpoolStatus {
+        pending_messages: pending_messages as u64,
+        pending_senders: pending_senders as u64,
+        local_messages: data.mpool.local_message_count() as u64,
+        size_limit: data.mpool.config.size_limit_high.max(0) as u64,
+    };
+
     if head.epoch() > chain_finality {
         let mut block_count = 0;
         let mut ts = head;
@@ -55,3 +73,59 @@ pub(in crate::rpc) async fn node_status<DB: Blockstore>(
 
     Ok(node_status)
 }
+
+async fn peer_counts<DB: Blockstore>(data: &RPCState<DB>) -> Option<PeerCounts> {
+    let (tx, rx) = oneshot::channel();
+    let req = NetworkMessage::JSONRPCRequest {
+        method: NetRPCMethods::PeerCounts(tx),
+    };
+    data.network_send.send_async(req).await.ok()?;
+    rx.await.ok()
+}
+
+fn peer_status(
+    PeerCounts {
+        by_topic,
+        by_protocol,
+    }: PeerCounts,
+) -> NodePeerStatus {
+    // Topics are suffixed with the network name
+    let topic_peers = |prefix: &str| {
+        by_topic
+            .iter()
+            .filter(|(topic, _)| topic.starts_with(&format!("{prefix}/")))
+            .map(|(_, count)| *count as u32)
+            .sum()
+    };
+    NodePeerStatus {
+        peers_to_publish_msgs: topic_peers(PUBSUB_MSG_STR),
+        peers_to_publish_blocks: topic_peers(PUBSUB_BLOCK_STR),
+        peers_by_protocol: by_protocol
+            .into_iter()
+            .map(|(protocol, count)| (protocol, count as u32))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peer_status_by_topic() {
+        let counts = PeerCounts {
+            by_topic: [
+                ("/fil/msgs/calibrationnet".to_owned(), 3),
+                ("/fil/blocks/calibrationnet".to_owned(), 5),
+                ("/f3/granite/0.0.1/calibrationnet".to_owned(), 2),
+            ]
+            .into_iter()
+            .collect(),
+            by_protocol: [("/fil/hello/1.0.0".to_owned(), 4)].into_iter().collect(),
+        };
+        let status = peer_status(counts);
+        assert_eq!(status.peers_to_publish_msgs, 3);
+        assert_eq!(status.peers_to_publish_blocks, 5);
+        assert_eq!(status.peers_by_protocol["/fil/hello/1.0.0"], 4);
+    }
+}
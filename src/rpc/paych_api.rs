@@ -0,0 +1,299 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+#![allow(clippy::unused_async)]
+
+use crate::blocks::{Tipset, TipsetKeys};
+use crate::db::{setting_keys::PAYCH_CHANNELS_KEY, SettingsStore, SettingsStoreExt as _};
+use crate::lotus_json::LotusJson;
+use crate::rpc::state_api::builtin_actor;
+use crate::rpc_api::{
+    data_types::RPCState,
+    paych_api::{ApiSignedVoucher, PaychDirection, PaychStatus, PaychVoucherCheck},
+};
+use crate::shim::{
+    address::Address,
+    econ::TokenAmount,
+    machine::BuiltinActor,
+    paych::{self, LaneState, SignedVoucher},
+    state_tree::ActorState,
+};
+use ahash::{HashMap, HashMapExt};
+use anyhow::{anyhow, ensure, Context as _};
+use fvm_ipld_blockstore::Blockstore;
+use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
+
+/// lists the payment channels tracked by the node: the channels with a party
+/// in the wallet of the node that were given to `PaychStatus` or
+/// `StatePaychVouchersCheck`.
+pub(in crate::rpc) async fn paych_list<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+) -> Result<LotusJson<Vec<Address>>, JsonRpcError> {
+    Ok(LotusJson(tracked_channels(&*data.chain_store.settings())?))
+}
+
+/// returns the wallet address of the party of the node in a payment channel,
+/// and whether the channel pays to or from it.
+pub(in crate::rpc) async fn paych_status<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((channel,))): Params<LotusJson<(Address,)>>,
+) -> Result<PaychStatus, JsonRpcError> {
+    let head = data.chain_store.heaviest_tipset();
+    let (_, state) = load_paych(&data, &channel, &head)?;
+    let parties = local_parties(&data, &head).await?;
+    track_local_channel(&data, &channel, &state, &parties)?;
+    if let Some(&control_addr) = parties.get(&state.from) {
+        return Ok(PaychStatus {
+            control_addr,
+            direction: PaychDirection::Outbound,
+        });
+    }
+    if let Some(&control_addr) = parties.get(&state.to) {
+        return Ok(PaychStatus {
+            control_addr,
+            direction: PaychDirection::Inbound,
+        });
+    }
+    Err(anyhow!("payment channel {channel} has no party in the wallet").into())
+}
+
+/// checks whether vouchers could be redeemed against a payment channel in the
+/// state of a tipset. Vouchers merging lanes are not supported.
+pub(in crate::rpc) async fn state_paych_vouchers_check<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((channel, vouchers, key))): Params<
+        LotusJson<(Address, Vec<ApiSignedVoucher>, TipsetKeys)>,
+    >,
+) -> Result<Vec<PaychVoucherCheck>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let (actor, state) = load_paych(&data, &channel, &tipset)?;
+    track_local_channel(
+        &data,
+        &channel,
+        &state,
+        &local_parties(&data, &tipset).await?,
+    )?;
+    let from = data
+        .state_manager
+        .resolve_to_key_addr(&state.from, &tipset)
+        .await?;
+    let channel_id = data
+        .state_manager
+        .lookup_id(&channel, &tipset)?
+        .context("payment channel ID not found")?;
+    let balance = TokenAmount::from(&actor.balance);
+    let lanes = state.lane_states(data.state_manager.blockstore())?;
+
+    let mut checks = Vec::with_capacity(vouchers.len());
+    for voucher in vouchers {
+        let voucher = SignedVoucher::from(voucher);
+        let error = data
+            .state_manager
+            .lookup_id(&voucher.channel_addr, &tipset)
+            .map_err(anyhow::Error::from)
+            .and_then(|id| {
+                ensure!(
+                    id == Some(channel_id),
+                    "voucher is for the channel {}",
+                    voucher.channel_addr
+                );
+                check_voucher(&voucher, &from, &balance, &lanes)
+            })
+            .err()
+            .map(|e| e.to_string());
+        checks.push(PaychVoucherCheck {
+            lane: voucher.lane,
+            nonce: voucher.nonce,
+            error,
+        });
+    }
+    Ok(checks)
+}
+
+/// Checks a voucher of a channel owned by `from`, as in Lotus.
+fn check_voucher(
+    voucher: &SignedVoucher,
+    from: &Address,
+    balance: &TokenAmount,
+    lanes: &[(u64, LaneState)],
+) -> anyhow::Result<()> {
+    let signature = voucher
+        .signature
+        .as_ref()
+        .context("voucher is not signed")?;
+    signature
+        .verify(&voucher.signing_bytes()?, from)
+        .map_err(|e| anyhow!("invalid voucher signature: {e}"))?;
+
+    if let Some((_, lane)) = lanes.iter().find(|(id, _)| *id == voucher.lane) {
+        ensure!(
+            voucher.nonce > lane.nonce,
+            "voucher nonce {} is not above the nonce {} of lane {}",
+            voucher.nonce,
+            lane.nonce,
+            voucher.lane
+        );
+        ensure!(
+            voucher.amount > lane.redeemed,
+            "voucher amount {} is not above the amount {} redeemed on lane {}",
+            voucher.amount,
+            lane.redeemed,
+            voucher.lane
+        );
+    }
+    ensure!(
+        voucher.merges.is_empty(),
+        "vouchers merging lanes are not supported"
+    );
+
+    let total_redeemed = lanes
+        .iter()
+        .filter(|(id, _)| *id != voucher.lane)
+        .fold(voucher.amount.clone(), |total, (_, lane)| {
+            total + &lane.redeemed
+        });
+    ensure!(
+        total_redeemed <= *balance,
+        "total redeemed {total_redeemed} would exceed the channel balance {balance}"
+    );
+    Ok(())
+}
+
+fn load_paych<DB: Blockstore>(
+    data: &RPCState<DB>,
+    channel: &Address,
+    tipset: &Tipset,
+) -> anyhow::Result<(ActorState, paych::State)> {
+    let actor = data
+        .state_manager
+        .get_actor(channel, *tipset.parent_state())?
+        .with_context(|| format!("payment channel {channel} not found"))?;
+    ensure!(
+        builtin_actor(data, tipset, &actor.code)? == BuiltinActor::PaymentChannel,
+        "{channel} is not a payment channel"
+    );
+    let state = paych::State::load(data.state_manager.blockstore(), &actor)?;
+    Ok((actor, state))
+}
+
+/// Payment channels tracked by the node.
+fn tracked_channels(settings: &dyn SettingsStore) -> anyhow::Result<Vec<Address>> {
+    Ok(settings
+        .read_obj::<LotusJson<Vec<Address>>>(PAYCH_CHANNELS_KEY)?
+        .map(LotusJson::into_inner)
+        .unwrap_or_default())
+}
+
+/// Tracks a payment channel, if one of its parties is in the wallet of the
+/// node.
+fn track_local_channel<DB: Blockstore>(
+    data: &RPCState<DB>,
+    channel: &Address,
+    state: &paych::State,
+    parties: &HashMap<Address, Address>,
+) -> anyhow::Result<()> {
+    if !parties.contains_key(&state.from) && !parties.contains_key(&state.to) {
+        return Ok(());
+    }
+    let settings = data.chain_store.settings();
+    let mut channels = tracked_channels(&*settings)?;
+    if !channels.contains(channel) {
+        channels.push(*channel);
+        settings.write_obj(PAYCH_CHANNELS_KEY, &LotusJson(channels))?;
+    }
+    Ok(())
+}
+
+/// Wallet addresses of the node, by the ID address of their actor. The keys
+/// without an actor are left out.
+async fn local_parties<DB: Blockstore + Send + Sync + 'static>(
+    data: &RPCState<DB>,
+    tipset: &Tipset,
+) -> anyhow::Result<HashMap<Address, Address>> {
+    let keystore = data.keystore.read().await;
+    let mut parties = HashMap::new();
+    for address in crate::key_management::list_addrs(&keystore)? {
+        if let Some(id) = data.state_manager.lookup_id(&address, tipset)? {
+            parties.insert(id, address);
+        }
+    }
+    Ok(parties)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_management::{generate_key, sign};
+    use crate::shim::crypto::SignatureType;
+
+    #[test]
+    fn voucher_checks() {
+        let key = generate_key(SignatureType::Secp256k1).unwrap();
+        let mut voucher = SignedVoucher {
+            channel_addr: Address::new_id(1000),
+            time_lock_min: 0,
+            time_lock_max: 0,
+            secret_pre_image: vec![],
+            extra: None,
+            lane: 1,
+            nonce: 2,
+            amount: TokenAmount::from_atto(60),
+            min_settle_height: 0,
+            merges: vec![],
+            signature: None,
+        };
+        let lanes = [
+            (
+                0,
+                LaneState {
+                    redeemed: TokenAmount::from_atto(30),
+                    nonce: 5,
+                },
+            ),
+            (
+                1,
+                LaneState {
+                    redeemed: TokenAmount::from_atto(50),
+                    nonce: 1,
+                },
+            ),
+        ];
+        let balance = TokenAmount::from_atto(100);
+        let check = |voucher: &SignedVoucher| {
+            check_voucher(voucher, &key.address, &balance, &lanes).map_err(|e| e.to_string())
+        };
+
+        assert!(check(&voucher).is_err(), "not signed");
+        let signing_bytes = voucher.signing_bytes().unwrap();
+        voucher.signature = Some(
+            sign(
+                SignatureType::Secp256k1,
+                key.key_info.private_key(),
+                &signing_bytes,
+            )
+            .unwrap(),
+        );
+        check(&voucher).unwrap();
+
+        let tampered = SignedVoucher {
+            nonce: 1,
+            ..voucher.clone()
+        };
+        assert!(check(&tampered).is_err(), "changed after signing");
+
+        let mut too_much = SignedVoucher {
+            amount: TokenAmount::from_atto(80),
+            ..voucher
+        };
+        let signing_bytes = too_much.signing_bytes().unwrap();
+        too_much.signature = Some(
+            sign(
+                SignatureType::Secp256k1,
+                key.key_info.private_key(),
+                &signing_bytes,
+            )
+            .unwrap(),
+        );
+        let error = check(&too_much).unwrap_err();
+        assert!(error.contains("exceed the channel balance"), "{error}");
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Subscriptions of the WebSocket clients. Notifications are sent on channels,
+//! as in Lotus: the response to a subscription is the ID of a channel, on
+//! which `xrpc.ch.val` notifications are sent until `xrpc.ch.close`. Clients
+//! end a subscription with `xrpc.cancel`, passing the ID of the request.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::blocks::Tipset;
+use crate::chain::{ChainStore, HeadChange};
+use crate::rpc_api::chain_api::{ApiHeadChange, HeadChangeType};
+use ahash::HashMap;
+use fvm_ipld_blockstore::Blockstore;
+use parking_lot::Mutex;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::AbortHandle;
+use tracing::warn;
+
+pub const XRPC_CANCEL: &str = "xrpc.cancel";
+pub const XRPC_CHANNEL_VALUE: &str = "xrpc.ch.val";
+pub const XRPC_CHANNEL_CLOSE: &str = "xrpc.ch.close";
+
+/// Batches of head changes buffered for each subscriber. Slower subscribers
+/// are dropped.
+const HEAD_CHANGES_CAP: usize = 16;
+
+/// Longest reorganization notified tipset by tipset. Beyond, e.g. when the
+/// node catches up with the network, only the new head is applied.
+const MAX_REORG_DEPTH: usize = 900;
+
+pub type HeadChanges = Arc<Vec<ApiHeadChange>>;
+
+/// Broadcasts the head changes of the [`ChainStore`] to the `ChainNotify`
+/// subscribers. Reverts are found by comparing consecutive heads.
+pub struct ChainNotifyHub {
+    head: Mutex<Arc<Tipset>>,
+    sender: broadcast::Sender<HeadChanges>,
+}
+
+impl ChainNotifyHub {
+    pub fn new(head: Arc<Tipset>) -> Self {
+        let (sender, _) = broadcast::channel(HEAD_CHANGES_CAP);
+        Self {
+            head: Mutex::new(head),
+            sender,
+        }
+    }
+
+    /// Returns the current head, and the receiver of the changes from it on.
+    pub fn subscribe(&self) -> (Arc<Tipset>, broadcast::Receiver<HeadChanges>) {
+        let head = self.head.lock();
+        (Arc::clone(&head), self.sender.subscribe())
+    }
+
+    /// Forwards the head changes of the chain store to the subscribers.
+    pub async fn run<DB: Blockstore>(&self, chain_store: Arc<ChainStore<DB>>) {
+        let mut head_changes = chain_store.publisher().subscribe();
+        loop {
+            let new_head = match head_changes.recv().await {
+                Ok(HeadChange::Apply(tipset)) => tipset,
+                // The changes that were missed are found from the latest head
+                Err(RecvError::Lagged(_)) => chain_store.heaviest_tipset(),
+                Err(RecvError::Closed) => return,
+            };
+            let old_head = Arc::clone(&self.head.lock());
+            let changes = chain_store
+                .chain_index
+                .path(old_head, Arc::clone(&new_head), Some(MAX_REORG_DEPTH))
+                .map(|(reverts, applies)| head_changes_of_path(reverts, applies))
+                .unwrap_or_else(|e| {
+                    warn!("Only applying the new head: {e:#}");
+                    vec![ApiHeadChange {
+                        r#type: HeadChangeType::Apply,
+                        val: (*new_head).clone(),
+                    }]
+                });
+            let mut head = self.head.lock();
+            *head = new_head;
+            if !changes.is_empty() {
+                // There may be no subscribers
+                let _ = self.sender.send(Arc::new(changes));
+            }
+        }
+    }
+}
+
+/// Head changes of a path between tipsets, see [`ChainIndex::path`].
+///
+/// [`ChainIndex::path`]: crate::chain::index::ChainIndex::path
+pub fn head_changes_of_path(
+    reverts: Vec<Arc<Tipset>>,
+    applies: Vec<Arc<Tipset>>,
+) -> Vec<ApiHeadChange> {
+    let reverts = reverts.into_iter().map(|tipset| ApiHeadChange {
+        r#type: HeadChangeType::Revert,
+        val: (*tipset).clone(),
+    });
+    let applies = applies.into_iter().map(|tipset| ApiHeadChange {
+        r#type: HeadChangeType::Apply,
+        val: (*tipset).clone(),
+    });
+    reverts.chain(applies).collect()
+}
+
+/// Subscriptions of a WebSocket connection, by request ID.
+#[derive(Default)]
+pub struct Subscriptions {
+    next_channel_id: AtomicU64,
+    channels: Mutex<HashMap<String, (u64, AbortHandle)>>,
+}
+
+impl Subscriptions {
+    pub fn next_channel_id(&self) -> u64 {
+        self.next_channel_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn insert(&self, request_id: String, channel_id: u64, task: AbortHandle) {
+        self.channels.lock().insert(request_id, (channel_id, task));
+    }
+
+    /// Cancels a subscription, returns the ID of its channel.
+    pub fn cancel(&self, request_id: &str) -> Option<u64> {
+        let (channel_id, task) = self.channels.lock().remove(request_id)?;
+        task.abort();
+        Some(channel_id)
+    }
+
+    /// Cancels the subscriptions of a closed connection.
+    pub fn cancel_all(&self) {
+        for (_, (_, task)) in self.channels.lock().drain() {
+            task.abort();
+        }
+    }
+}
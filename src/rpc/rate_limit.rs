@@ -0,0 +1,174 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::{net::IpAddr, sync::Arc, time::Instant};
+
+use crate::cli_shared::cli::RpcRateLimitConfig;
+use ahash::HashMap;
+use http::StatusCode;
+use parking_lot::Mutex;
+
+/// Number of buckets above which the full ones are dropped, they are
+/// equivalent to missing ones.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Token bucket refilled continuously, holding up to one second of requests.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(rate: u32, now: Instant) -> Self {
+        Self {
+            tokens: rate as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, rate: u32, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate as f64).min(rate as f64);
+        self.updated = now;
+    }
+
+    fn is_full(&self, rate: u32, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens + elapsed * rate as f64 >= rate as f64
+    }
+}
+
+/// Rate limits of the RPC requests, by client IP and by method.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RpcRateLimitConfig,
+    /// Buckets keyed by client and method, `None` for the per client limit.
+    buckets: Mutex<HashMap<(IpAddr, Option<String>), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RpcRateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Default::default(),
+        }
+    }
+
+    /// Takes a token from the buckets of the client for the method. Nothing is
+    /// taken if one of them is empty.
+    fn try_acquire(&self, ip: IpAddr, method: &str, now: Instant) -> bool {
+        let limits = [
+            self.config.per_ip.map(|rate| (None, rate)),
+            self.config
+                .per_method
+                .get(method)
+                .map(|rate| (Some(method.to_owned()), *rate)),
+        ];
+        if limits.iter().all(Option::is_none) {
+            return true;
+        }
+
+        let mut buckets = self.buckets.lock();
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|(_, method), bucket| {
+                let rate = match method {
+                    None => self.config.per_ip,
+                    Some(method) => self.config.per_method.get(method).copied(),
+                };
+                rate.map_or(false, |rate| !bucket.is_full(rate, now))
+            });
+        }
+
+        let mut acquired = vec![];
+        for (method, rate) in limits.into_iter().flatten() {
+            let bucket = buckets
+                .entry((ip, method.clone()))
+                .or_insert_with(|| Bucket::new(rate, now));
+            bucket.refill(rate, now);
+            if bucket.tokens < 1.0 {
+                // Give back the tokens taken from the other buckets
+                for method in acquired {
+                    if let Some(bucket) = buckets.get_mut(&(ip, method)) {
+                        bucket.tokens += 1.0;
+                    }
+                }
+                return false;
+            }
+            bucket.tokens -= 1.0;
+            acquired.push(method);
+        }
+        true
+    }
+}
+
+/// Rate limits of a client.
+#[derive(Debug, Clone)]
+pub struct ClientRateLimit {
+    pub limiter: Arc<RateLimiter>,
+    pub ip: IpAddr,
+}
+
+impl ClientRateLimit {
+    pub fn check(&self, method: &str) -> Result<(), (StatusCode, String)> {
+        match self.limiter.try_acquire(self.ip, method, Instant::now()) {
+            true => Ok(()),
+            false => Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                format!("Rate limit exceeded for {method}"),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const METHOD: &str = "Filecoin.ChainHead";
+
+    fn limiter(per_ip: Option<u32>, per_method: Option<u32>) -> RateLimiter {
+        RateLimiter::new(RpcRateLimitConfig {
+            per_ip,
+            per_method: HashMap::from_iter(per_method.map(|rate| (METHOD.to_owned(), rate))),
+        })
+    }
+
+    #[test]
+    fn unlimited() {
+        let limiter = limiter(None, None);
+        let now = Instant::now();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        assert!((0..1000).all(|_| limiter.try_acquire(ip, METHOD, now)));
+        assert!(limiter.buckets.lock().is_empty());
+    }
+
+    #[test]
+    fn per_ip() {
+        let limiter = limiter(Some(2), None);
+        let now = Instant::now();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        assert!(limiter.try_acquire(ip, METHOD, now));
+        assert!(limiter.try_acquire(ip, "Filecoin.Version", now));
+        assert!(!limiter.try_acquire(ip, METHOD, now));
+        // Other clients have their own bucket
+        assert!(limiter.try_acquire(IpAddr::from([127, 0, 0, 2]), METHOD, now));
+        // Refilled at the configured rate
+        assert!(limiter.try_acquire(ip, METHOD, now + Duration::from_millis(500)));
+        assert!(!limiter.try_acquire(ip, METHOD, now + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn per_method() {
+        let limiter = limiter(Some(3), Some(1));
+        let now = Instant::now();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        assert!(limiter.try_acquire(ip, METHOD, now));
+        assert!(!limiter.try_acquire(ip, METHOD, now));
+        // The rejected request didn't count against the client limit
+        assert!(limiter.try_acquire(ip, "Filecoin.Version", now));
+        assert!(limiter.try_acquire(ip, "Filecoin.Version", now));
+        assert!(!limiter.try_acquire(ip, "Filecoin.Version", now));
+    }
+}
@@ -1,19 +1,63 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::{net::SocketAddr, sync::Arc};
+
 use crate::rpc_api::data_types::JsonRpcServerState;
-use axum::response::IntoResponse;
+use axum::{extract::ConnectInfo, response::IntoResponse, Extension};
 use http::{HeaderMap, StatusCode};
 use jsonrpc_v2::RequestObject as JsonRpcRequestObject;
 
-use crate::rpc::rpc_util::{call_rpc_str, check_permissions, get_auth_header, is_streaming_method};
+use crate::rpc::rate_limit::{ClientRateLimit, RateLimiter};
+use crate::rpc::rpc_util::{
+    call_rpc_batch, call_rpc_str, check_permissions, get_auth_header, get_error_str,
+    is_streaming_method, MaxBatchSize, INVALID_REQUEST,
+};
 
 pub async fn rpc_http_handler(
     headers: HeaderMap,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
     axum::extract::State(rpc_server): axum::extract::State<JsonRpcServerState>,
-    axum::Json(rpc_call): axum::Json<JsonRpcRequestObject>,
+    Extension(max_batch_size): Extension<MaxBatchSize>,
+    Extension(limiter): Extension<Arc<RateLimiter>>,
+    axum::Json(body): axum::Json<serde_json::Value>,
 ) -> impl IntoResponse {
     let response_headers = [("content-type", "application/json-rpc;charset=utf-8")];
+    let rate_limit = ClientRateLimit {
+        limiter,
+        ip: client.ip(),
+    };
+    let rpc_call: JsonRpcRequestObject = match body {
+        serde_json::Value::Array(requests) => {
+            return match call_rpc_batch(
+                rpc_server,
+                get_auth_header(headers),
+                rate_limit,
+                requests,
+                max_batch_size,
+            )
+            .await
+            {
+                Ok(result) => (StatusCode::OK, response_headers, result),
+                Err(err) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    response_headers,
+                    err.to_string(),
+                ),
+            };
+        }
+        body => match serde_json::from_value(body) {
+            Ok(rpc_call) => rpc_call,
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    response_headers,
+                    get_error_str(INVALID_REQUEST, err.to_string()),
+                )
+            }
+        },
+    };
+
     if let Err((code, msg)) = check_permissions(
         rpc_server.clone(),
         rpc_call.method_ref(),
@@ -23,6 +67,9 @@ pub async fn rpc_http_handler(
     {
         return (code, response_headers, msg);
     }
+    if let Err((code, msg)) = rate_limit.check(rpc_call.method_ref()) {
+        return (code, response_headers, msg);
+    }
 
     if is_streaming_method(rpc_call.method_ref()) {
         return (
@@ -32,7 +79,7 @@ pub async fn rpc_http_handler(
         );
     }
 
-    match call_rpc_str(rpc_server.clone(), rpc_call).await {
+    match call_rpc_str(rpc_server.clone(), rpc_call, client.ip()).await {
         Ok(result) => (StatusCode::OK, response_headers, result),
         Err(err) => (
             StatusCode::INTERNAL_SERVER_ERROR,
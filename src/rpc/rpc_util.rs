@@ -1,10 +1,30 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use crate::rpc_api::{auth_api::*, check_access, data_types::JsonRpcServerState, ACCESS_MAP};
+use std::{
+    net::IpAddr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+use crate::rpc::metrics::{self, values};
+use crate::rpc::rate_limit::ClientRateLimit;
+use crate::rpc_api::{
+    actor_events_api::SUBSCRIBE_ACTOR_EVENTS_RAW, auth_api::*, chain_api::CHAIN_NOTIFY,
+    check_access, data_types::JsonRpcServerState, mpool_api::MPOOL_SUB, ACCESS_MAP,
+};
 use http::{HeaderMap, HeaderValue, StatusCode};
 use serde::de::DeserializeOwned;
-use tracing::{debug, error};
+use serde_json::json;
+use tracing::{debug, debug_span, error, Instrument};
+
+pub const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const LIMIT_EXCEEDED: i64 = -32005;
+
+/// Maximum number of requests in a JSON-RPC batch.
+#[derive(Debug, Clone, Copy)]
+pub struct MaxBatchSize(pub usize);
 
 pub fn get_error_obj(code: i64, message: String) -> jsonrpc_v2::Error {
     debug!(
@@ -33,7 +53,7 @@ pub fn get_error_str(code: i64, message: String) -> String {
     }
 }
 
-const STREAMING_METHODS: [&str; 0] = [];
+const STREAMING_METHODS: [&str; 3] = [CHAIN_NOTIFY, SUBSCRIBE_ACTOR_EVENTS_RAW, MPOOL_SUB];
 
 pub fn is_streaming_method(method_name: &str) -> bool {
     STREAMING_METHODS.contains(&method_name)
@@ -84,15 +104,155 @@ pub fn get_auth_header(headers: HeaderMap) -> Option<HeaderValue> {
     headers.get("Authorization").cloned()
 }
 
+/// Identifier of the next request handled by [`handle_rpc`], to tell apart
+/// the logs of concurrent requests.
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Calls an RPC method on behalf of `client`. The call is logged, with its
+/// parameters size, duration and outcome, and recorded in the RPC metrics.
+pub async fn handle_rpc(
+    rpc_server: &JsonRpcServerState,
+    rpc_request: jsonrpc_v2::RequestObject,
+    client: IpAddr,
+) -> jsonrpc_v2::ResponseObjects {
+    let request_id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+    let method = rpc_request.method_ref().to_owned();
+    let params_size = serde_json::to_value(&rpc_request)
+        .ok()
+        .and_then(|request| request.get("params").map(|params| params.to_string().len()))
+        .unwrap_or_default();
+
+    let start = Instant::now();
+    let response = rpc_server
+        .handle(rpc_request)
+        .instrument(debug_span!("rpc_request", id = request_id, %method, %client))
+        .await;
+    let elapsed = start.elapsed();
+
+    let outcome = match &response {
+        jsonrpc_v2::ResponseObjects::One(jsonrpc_v2::ResponseObject::Error { .. }) => values::ERROR,
+        _ => values::OK,
+    };
+    debug!(
+        "RPC request {request_id} from {client}: {method} with {params_size} bytes of params, {outcome} in {elapsed:?}"
+    );
+    metrics::RPC_METHOD_TIME
+        .with_label_values(&[&method])
+        .observe(elapsed.as_secs_f64());
+    metrics::RPC_METHOD_PARAMS_SIZE
+        .with_label_values(&[&method])
+        .observe(params_size as f64);
+    metrics::RPC_METHOD_CALLS
+        .with_label_values(&[&method, outcome])
+        .inc();
+    response
+}
+
 // Calls an RPC method and returns the full response as a string.
+#[tracing::instrument(skip_all, fields(method = rpc_request.method_ref()))]
 pub async fn call_rpc_str(
     rpc_server: JsonRpcServerState,
     rpc_request: jsonrpc_v2::RequestObject,
+    client: IpAddr,
 ) -> anyhow::Result<String> {
-    let rpc_subscription_response = rpc_server.handle(rpc_request).await;
+    let rpc_subscription_response = handle_rpc(&rpc_server, rpc_request, client).await;
     Ok(serde_json::to_string(&rpc_subscription_response)?)
 }
 
+/// Calls the requests of a batch concurrently and returns their responses as
+/// a JSON array. Notifications get no response, as per the JSON-RPC
+/// specification, so a batch of notifications only gets an empty string. A
+/// batch that is empty or too large gets a single error.
+pub async fn call_rpc_batch(
+    rpc_server: JsonRpcServerState,
+    authorization_header: Option<HeaderValue>,
+    rate_limit: ClientRateLimit,
+    requests: Vec<serde_json::Value>,
+    MaxBatchSize(max_batch_size): MaxBatchSize,
+) -> anyhow::Result<String> {
+    if requests.is_empty() {
+        return Ok(get_error_str(INVALID_REQUEST, "Empty batch".into()));
+    }
+    if requests.len() > max_batch_size {
+        return Ok(get_error_str(
+            INVALID_REQUEST,
+            format!(
+                "Batch of {} requests exceeds the limit of {max_batch_size}",
+                requests.len()
+            ),
+        ));
+    }
+
+    let responses = futures::future::join_all(requests.into_iter().map(|request| {
+        call_batched_rpc(
+            rpc_server.clone(),
+            authorization_header.clone(),
+            &rate_limit,
+            request,
+        )
+    }))
+    .await;
+    let responses: Vec<_> = responses.into_iter().flatten().collect();
+    if responses.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(serde_json::to_string(&responses)?)
+}
+
+async fn call_batched_rpc(
+    rpc_server: JsonRpcServerState,
+    authorization_header: Option<HeaderValue>,
+    rate_limit: &ClientRateLimit,
+    request: serde_json::Value,
+) -> Option<serde_json::Value> {
+    // Requests that aren't even objects are answered with a null ID
+    let id = match request.is_object() {
+        true => request.get("id").cloned(),
+        false => Some(serde_json::Value::Null),
+    };
+    let error = |code: i64, message: String| {
+        // Failed notifications are not reported either
+        id.as_ref().map(|id| {
+            json!({ "jsonrpc": "2.0", "error": { "code": code, "message": message }, "id": id })
+        })
+    };
+
+    // The request object borrows its version string, so it can't be
+    // deserialized from a `Value`
+    let request: jsonrpc_v2::RequestObject = match serde_json::from_str(&request.to_string()) {
+        Ok(request) => request,
+        Err(e) => return error(INVALID_REQUEST, e.to_string()),
+    };
+    if is_streaming_method(request.method_ref()) {
+        return error(
+            INVALID_REQUEST,
+            "Streaming methods cannot be batched".into(),
+        );
+    }
+    if let Err((code, message)) = check_permissions(
+        rpc_server.clone(),
+        request.method_ref(),
+        authorization_header,
+    )
+    .await
+    {
+        let code = if code == StatusCode::NOT_FOUND {
+            METHOD_NOT_FOUND
+        } else {
+            INVALID_REQUEST
+        };
+        return error(code, message);
+    }
+    if let Err((_, message)) = rate_limit.check(request.method_ref()) {
+        return error(LIMIT_EXCEEDED, message);
+    }
+
+    match handle_rpc(&rpc_server, request, rate_limit.ip).await {
+        jsonrpc_v2::ResponseObjects::Empty => None,
+        response => serde_json::to_value(response).ok(),
+    }
+}
+
 // Returns both the RPC response string and the result value in a tuple.
 pub async fn call_rpc<T>(
     rpc_server: JsonRpcServerState,
@@ -144,3 +304,105 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::rate_limit::RateLimiter;
+    use crate::rpc_api::common_api::VERSION;
+    use std::sync::Arc;
+
+    async fn version() -> Result<String, jsonrpc_v2::Error> {
+        Ok("test".into())
+    }
+
+    fn server() -> JsonRpcServerState {
+        Arc::new(
+            jsonrpc_v2::Server::new()
+                .with_method(VERSION, version)
+                .finish_unwrapped(),
+        )
+    }
+
+    async fn batch_str(requests: serde_json::Value, max_batch_size: usize) -> String {
+        let serde_json::Value::Array(requests) = requests else {
+            panic!("not a batch");
+        };
+        let rate_limit = ClientRateLimit {
+            limiter: Arc::new(RateLimiter::new(Default::default())),
+            ip: [127, 0, 0, 1].into(),
+        };
+        call_rpc_batch(
+            server(),
+            None,
+            rate_limit,
+            requests,
+            MaxBatchSize(max_batch_size),
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn batch(requests: serde_json::Value, max_batch_size: usize) -> serde_json::Value {
+        serde_json::from_str(&batch_str(requests, max_batch_size).await).unwrap()
+    }
+
+    #[tokio::test]
+    async fn batch_responses() {
+        let response = batch(
+            json!([
+                { "jsonrpc": "2.0", "method": VERSION, "id": 1 },
+                { "jsonrpc": "2.0", "method": VERSION },
+                { "jsonrpc": "2.0", "method": "Filecoin.Unknown", "id": 2 },
+                1,
+            ]),
+            10,
+        )
+        .await;
+        let responses = response.as_array().unwrap();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["result"], "test");
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[1]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[2]["error"]["code"], INVALID_REQUEST);
+        assert_eq!(responses[2]["id"], serde_json::Value::Null);
+
+        // A batch of notifications gets nothing back, not an empty array
+        let response = batch_str(
+            json!([
+                { "jsonrpc": "2.0", "method": VERSION },
+                { "jsonrpc": "2.0", "method": "Filecoin.Unknown" },
+            ]),
+            10,
+        )
+        .await;
+        assert_eq!(response, "");
+    }
+
+    #[tokio::test]
+    async fn calls_are_recorded() {
+        let calls = || {
+            metrics::RPC_METHOD_CALLS
+                .with_label_values(&[VERSION, values::OK])
+                .get()
+        };
+        let before = calls();
+        let request = jsonrpc_v2::RequestObject::request()
+            .with_method(VERSION)
+            .with_id(1)
+            .finish();
+        handle_rpc(&server(), request, [127, 0, 0, 1].into()).await;
+        assert!(calls() > before);
+    }
+
+    #[tokio::test]
+    async fn batch_limits() {
+        let response = batch(json!([]), 10).await;
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+
+        let request = json!({ "jsonrpc": "2.0", "method": VERSION, "id": 1 });
+        let response = batch(json!([request, request]), 1).await;
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+    }
+}
@@ -1,30 +1,52 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use crate::rpc_api::data_types::JsonRpcServerState;
+use crate::message_pool::MpoolUpdate;
+use crate::rpc_api::{
+    actor_events_api::{ActorEventFilter, SUBSCRIBE_ACTOR_EVENTS_RAW},
+    chain_api::{ApiHeadChange, HeadChangeType},
+    data_types::JsonRpcServerState,
+    mpool_api::{ApiMpoolUpdate, MPOOL_SUB},
+};
 use axum::{
     extract::{
         ws::{Message, WebSocket},
-        WebSocketUpgrade,
+        ConnectInfo, Extension, WebSocketUpgrade,
     },
     response::IntoResponse,
 };
 use crossbeam::atomic::AtomicCell;
 use futures::{stream::SplitSink, SinkExt, StreamExt};
 use http::{HeaderMap, HeaderValue};
-use tokio::sync::RwLock;
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    RwLock,
+};
 use tracing::{debug, error, info, warn};
 
-use crate::rpc::rpc_util::{call_rpc_str, check_permissions, get_auth_header, get_error_str};
+use crate::rpc::actor_events_api::{head_change_events, ActorEventSource};
+use crate::rpc::pubsub::{
+    ChainNotifyHub, Subscriptions, XRPC_CANCEL, XRPC_CHANNEL_CLOSE, XRPC_CHANNEL_VALUE,
+};
+use crate::rpc::rate_limit::{ClientRateLimit, RateLimiter};
+use crate::rpc::rpc_util::{
+    call_rpc_batch, call_rpc_str, check_permissions, get_auth_header, get_error_str,
+    is_streaming_method, MaxBatchSize,
+};
+
+type WsSender = Arc<RwLock<SplitSink<WebSocket, Message>>>;
 
 async fn rpc_ws_task(
     authorization_header: Option<HeaderValue>,
     rpc_call: jsonrpc_v2::RequestObject,
     rpc_server: JsonRpcServerState,
+    rate_limit: ClientRateLimit,
     _is_socket_active: Arc<AtomicCell<bool>>,
-    ws_sender: Arc<RwLock<SplitSink<WebSocket, Message>>>,
+    ws_sender: WsSender,
 ) -> anyhow::Result<()> {
     let call_method = rpc_call.method_ref();
     let _call_id = rpc_call.id_ref();
@@ -32,9 +54,12 @@ async fn rpc_ws_task(
     check_permissions(rpc_server.clone(), call_method, authorization_header)
         .await
         .map_err(|(_, e)| anyhow::Error::msg(e))?;
+    rate_limit
+        .check(call_method)
+        .map_err(|(_, e)| anyhow::Error::msg(e))?;
 
     info!("RPC WS called method: {}", call_method);
-    let response = call_rpc_str(rpc_server.clone(), rpc_call).await?;
+    let response = call_rpc_str(rpc_server.clone(), rpc_call, rate_limit.ip).await?;
     ws_sender
         .write()
         .await
@@ -44,26 +69,228 @@ async fn rpc_ws_task(
     Ok(())
 }
 
+/// Subscribes to the head changes, to the actor events matching a filter, or to
+/// the changes of the message pool. They are sent on a channel until the
+/// subscription is cancelled.
+#[allow(clippy::too_many_arguments)]
+async fn rpc_ws_subscribe(
+    authorization_header: Option<HeaderValue>,
+    rpc_call: jsonrpc_v2::RequestObject,
+    request_text: &str,
+    rpc_server: JsonRpcServerState,
+    rate_limit: ClientRateLimit,
+    chain_notify: Arc<ChainNotifyHub>,
+    event_source: Arc<dyn ActorEventSource>,
+    mpool_updates: broadcast::Sender<MpoolUpdate>,
+    subscriptions: Arc<Subscriptions>,
+    ws_sender: WsSender,
+) -> anyhow::Result<()> {
+    check_permissions(rpc_server, rpc_call.method_ref(), authorization_header)
+        .await
+        .map_err(|(_, e)| anyhow::Error::msg(e))?;
+    rate_limit
+        .check(rpc_call.method_ref())
+        .map_err(|(_, e)| anyhow::Error::msg(e))?;
+
+    info!("RPC WS subscription: {}", rpc_call.method_ref());
+    let event_filter = if rpc_call.method_ref() == SUBSCRIBE_ACTOR_EVENTS_RAW {
+        Some(event_source.event_filter(&subscription_filter(request_text)?)?)
+    } else {
+        None
+    };
+    let channel_id = subscriptions.next_channel_id();
+    let (head, mut head_changes) = chain_notify.subscribe();
+    let mpool_updates = (rpc_call.method_ref() == MPOOL_SUB).then(|| mpool_updates.subscribe());
+    let response = json!({ "jsonrpc": "2.0", "result": channel_id, "id": rpc_call.id_ref() });
+    ws_send(&ws_sender, response).await?;
+
+    let task_ws_sender = ws_sender.clone();
+    if let Some(updates) = mpool_updates {
+        let task = tokio::task::spawn(send_mpool_updates(task_ws_sender, channel_id, updates));
+        let request_id = serde_json::to_string(&rpc_call.id_ref())?;
+        subscriptions.insert(request_id, channel_id, task.abort_handle());
+        return Ok(());
+    }
+    let task = tokio::task::spawn(async move {
+        let mut result = match &event_filter {
+            Some(_) => Ok(()),
+            None => {
+                let current = [ApiHeadChange {
+                    r#type: HeadChangeType::Current,
+                    val: (*head).clone(),
+                }];
+                ws_send_channel_value(&task_ws_sender, channel_id, &current).await
+            }
+        };
+        while result.is_ok() {
+            let changes = match head_changes.recv().await {
+                Ok(changes) => changes,
+                Err(RecvError::Lagged(_)) => {
+                    warn!("Closing the channel {channel_id} of a slow client");
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let Some(event_filter) = &event_filter else {
+                result = ws_send_channel_value(&task_ws_sender, channel_id, &*changes).await;
+                continue;
+            };
+            match head_change_events(&*event_source, &changes, event_filter) {
+                Ok(events) => {
+                    for event in events {
+                        result = ws_send_channel_value(&task_ws_sender, channel_id, &event).await;
+                        if result.is_err() {
+                            break;
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to load the events of channel {channel_id}: {e:#}"),
+            }
+        }
+        if result.is_ok() {
+            let close =
+                json!({ "jsonrpc": "2.0", "method": XRPC_CHANNEL_CLOSE, "params": [channel_id] });
+            let _ = ws_send(&task_ws_sender, close).await;
+        }
+    });
+    let request_id = serde_json::to_string(&rpc_call.id_ref())?;
+    subscriptions.insert(request_id, channel_id, task.abort_handle());
+    Ok(())
+}
+
+/// Sends the changes of the message pool on a channel.
+async fn send_mpool_updates(
+    ws_sender: WsSender,
+    channel_id: u64,
+    mut updates: broadcast::Receiver<MpoolUpdate>,
+) {
+    loop {
+        let update = match updates.recv().await {
+            Ok(update) => ApiMpoolUpdate::from(update),
+            Err(RecvError::Lagged(_)) => {
+                warn!("Closing the channel {channel_id} of a slow client");
+                break;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        if ws_send_channel_value(&ws_sender, channel_id, &update)
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+    let close = json!({ "jsonrpc": "2.0", "method": XRPC_CHANNEL_CLOSE, "params": [channel_id] });
+    let _ = ws_send(&ws_sender, close).await;
+}
+
+/// Filter of an actor events subscription, which is optional.
+fn subscription_filter(request_text: &str) -> anyhow::Result<ActorEventFilter> {
+    #[derive(serde::Deserialize)]
+    struct SubscribeRequest {
+        #[serde(default)]
+        params: Vec<serde_json::Value>,
+    }
+
+    let SubscribeRequest { params } = serde_json::from_str(request_text)?;
+    match params.into_iter().next() {
+        Some(filter) if !filter.is_null() => Ok(serde_json::from_value(filter)?),
+        _ => Ok(ActorEventFilter::default()),
+    }
+}
+
+/// Cancels the subscription made by the request whose ID is the parameter.
+async fn rpc_ws_cancel(
+    request_text: &str,
+    subscriptions: &Subscriptions,
+    ws_sender: &WsSender,
+) -> anyhow::Result<()> {
+    #[derive(serde::Deserialize)]
+    struct CancelRequest {
+        params: (serde_json::Value,),
+    }
+
+    let CancelRequest {
+        params: (request_id,),
+    } = serde_json::from_str(request_text)?;
+    if let Some(channel_id) = subscriptions.cancel(&request_id.to_string()) {
+        let close =
+            json!({ "jsonrpc": "2.0", "method": XRPC_CHANNEL_CLOSE, "params": [channel_id] });
+        ws_send(ws_sender, close).await?;
+    }
+    Ok(())
+}
+
+async fn ws_send_channel_value(
+    ws_sender: &WsSender,
+    channel_id: u64,
+    value: &(impl Serialize + ?Sized),
+) -> anyhow::Result<()> {
+    let notification = json!({
+        "jsonrpc": "2.0",
+        "method": XRPC_CHANNEL_VALUE,
+        "params": [channel_id, value],
+    });
+    ws_send(ws_sender, notification).await
+}
+
+async fn ws_send(ws_sender: &WsSender, value: serde_json::Value) -> anyhow::Result<()> {
+    ws_sender
+        .write()
+        .await
+        .send(Message::Text(value.to_string()))
+        .await?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn rpc_ws_handler(
     headers: HeaderMap,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
     axum::extract::State(rpc_server): axum::extract::State<JsonRpcServerState>,
+    Extension(chain_notify): Extension<Arc<ChainNotifyHub>>,
+    Extension(event_source): Extension<Arc<dyn ActorEventSource>>,
+    Extension(mpool_updates): Extension<broadcast::Sender<MpoolUpdate>>,
+    Extension(max_batch_size): Extension<MaxBatchSize>,
+    Extension(limiter): Extension<Arc<RateLimiter>>,
     ws: WebSocketUpgrade,
 ) -> impl IntoResponse {
     let authorization_header = get_auth_header(headers);
-    ws.on_upgrade(move |socket| async {
-        rpc_ws_handler_inner(socket, authorization_header, rpc_server).await
+    let rate_limit = ClientRateLimit {
+        limiter,
+        ip: client.ip(),
+    };
+    ws.on_upgrade(move |socket| async move {
+        rpc_ws_handler_inner(
+            socket,
+            authorization_header,
+            rpc_server,
+            chain_notify,
+            event_source,
+            mpool_updates,
+            max_batch_size,
+            rate_limit,
+        )
+        .await
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn rpc_ws_handler_inner(
     socket: WebSocket,
     authorization_header: Option<HeaderValue>,
     rpc_server: JsonRpcServerState,
+    chain_notify: Arc<ChainNotifyHub>,
+    event_source: Arc<dyn ActorEventSource>,
+    mpool_updates: broadcast::Sender<MpoolUpdate>,
+    max_batch_size: MaxBatchSize,
+    rate_limit: ClientRateLimit,
 ) {
     info!("Accepted WS connection!");
     let (sender, mut receiver) = socket.split();
     let ws_sender = Arc::new(RwLock::new(sender));
     let socket_active = Arc::new(AtomicCell::new(true));
+    let subscriptions = Arc::new(Subscriptions::default());
     while let Some(Ok(message)) = receiver.next().await {
         debug!("Received new WS RPC message: {:?}", message);
         if let Message::Text(request_text) = message {
@@ -74,15 +301,85 @@ async fn rpc_ws_handler_inner(
                 let task_rpc_server = rpc_server.clone();
                 let task_socket_active = socket_active.clone();
                 let task_ws_sender = ws_sender.clone();
+                let task_rate_limit = rate_limit.clone();
+                if let Ok(requests) = serde_json::from_str::<Vec<serde_json::Value>>(&request_text)
+                {
+                    tokio::task::spawn(async move {
+                        let response = match call_rpc_batch(
+                            task_rpc_server,
+                            authorization_header,
+                            task_rate_limit,
+                            requests,
+                            max_batch_size,
+                        )
+                        .await
+                        {
+                            // Nothing is sent back for a batch of notifications
+                            Ok(response) if response.is_empty() => return,
+                            Ok(response) => response,
+                            Err(e) => get_error_str(3, format!("WS RPC batch error: {e}")),
+                        };
+                        if let Err(e) = task_ws_sender
+                            .write()
+                            .await
+                            .send(Message::Text(response))
+                            .await
+                        {
+                            warn!("{e}");
+                        }
+                    });
+                    continue;
+                }
                 match serde_json::from_str(&request_text)
                     as Result<jsonrpc_v2::RequestObject, serde_json::Error>
                 {
+                    Ok(rpc_call) if rpc_call.method_ref() == XRPC_CANCEL => {
+                        if let Err(e) =
+                            rpc_ws_cancel(&request_text, &subscriptions, &task_ws_sender).await
+                        {
+                            warn!("Failed to cancel WS subscription: {e}");
+                        }
+                    }
+                    Ok(rpc_call) if is_streaming_method(rpc_call.method_ref()) => {
+                        let chain_notify = chain_notify.clone();
+                        let event_source = event_source.clone();
+                        let mpool_updates = mpool_updates.clone();
+                        let subscriptions = subscriptions.clone();
+                        tokio::task::spawn(async move {
+                            if let Err(e) = rpc_ws_subscribe(
+                                authorization_header,
+                                rpc_call,
+                                &request_text,
+                                task_rpc_server,
+                                task_rate_limit,
+                                chain_notify,
+                                event_source,
+                                mpool_updates,
+                                subscriptions,
+                                task_ws_sender.clone(),
+                            )
+                            .await
+                            {
+                                let msg = format!("WS RPC subscription error: {e}");
+                                error!("{}", msg);
+                                if let Err(e) = task_ws_sender
+                                    .write()
+                                    .await
+                                    .send(Message::Text(get_error_str(3, msg)))
+                                    .await
+                                {
+                                    warn!("{e}");
+                                }
+                            }
+                        });
+                    }
                     Ok(rpc_call) => {
                         tokio::task::spawn(async move {
                             match rpc_ws_task(
                                 authorization_header,
                                 rpc_call,
                                 task_rpc_server,
+                                task_rate_limit,
                                 task_socket_active,
                                 task_ws_sender.clone(),
                             )
@@ -121,4 +418,5 @@ async fn rpc_ws_handler_inner(
         }
     }
     socket_active.store(false);
+    subscriptions.cancel_all();
 }
@@ -2,30 +2,57 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 #![allow(clippy::unused_async)]
 
-use crate::blocks::TipsetKeys;
+use crate::blocks::{Tipset, TipsetKeys};
 use crate::cid_collections::CidHashSet;
+use crate::interpreter::{execution_trace, state_overrides::ActorOverride, VMTrace};
 use crate::ipld::json::IpldJson;
 use crate::libp2p::NetworkMessage;
+use crate::libp2p_bitswap::BitswapSession;
 use crate::lotus_json::LotusJson;
-use crate::rpc_api::data_types::{MarketDeal, MessageLookup, RPCState};
+use crate::rpc_api::data_types::{
+    ActorEntry, ActorsPage, ApiAllocation, ApiClaim, ApiSectorExpiration,
+    ApiSectorPreCommitOnChainInfo, BaseFeeParams, MarketDeal, MarketDealsPage, MessageLookup,
+    MinerPartition, MsigTransaction, NetworkParams, NetworkVersionRange, RPCState,
+    SectorOnChainInfo,
+};
 use crate::shim::{
-    address::Address, executor::Receipt, message::Message, state_tree::ActorState,
+    actor_methods,
+    address::Address,
+    clock::ChainEpoch,
+    datacap,
+    deal::DealID,
+    econ::TokenAmount,
+    executor::Receipt,
+    machine::{ActorVersion, BuiltinActor, BuiltinActorManifest},
+    market,
+    message::{Message, MethodNum},
+    multisig,
+    sector::SectorNumber,
+    state_tree::{self, ActorState},
+    verifreg,
     version::NetworkVersion,
 };
-use crate::state_manager::{InvocResult, MarketBalance};
+use crate::state_manager::{
+    chain_rand::draw_randomness_from_digest, BlockRewards, CirculatingSupply, ComputeStateOutput,
+    InvocResult, MarketBalance, StateManager, LOOKBACK_NO_LIMIT,
+};
 use crate::utils::db::car_stream::{CarBlock, CarWriter};
 use ahash::{HashMap, HashMapExt};
 use anyhow::Context as _;
 use cid::Cid;
-use fil_actor_interface::market;
+use fil_actor_interface::miner;
+use fil_actor_system_state::v10::State as SystemState;
+use fil_actors_shared::fvm_ipld_bitfield::BitField;
 use futures::StreamExt;
 use fvm_ipld_blockstore::Blockstore;
-use fvm_ipld_encoding::{CborStore, DAG_CBOR};
+use fvm_ipld_encoding::{CborStore, RawBytes, DAG_CBOR};
 use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
 use libipld_core::ipld::Ipld;
+use num_bigint::BigInt;
 use parking_lot::Mutex;
+use serde::{ser::Error as _, ser::SerializeMap as _, Serialize, Serializer};
 use std::path::PathBuf;
-use std::{sync::Arc, time::Duration};
+use std::{ops::ControlFlow, sync::Arc, time::Duration};
 use tokio::task::JoinSet;
 
 /// runs the given message and returns its result without any persisted changes.
@@ -41,26 +68,111 @@ pub(in crate::rpc) async fn state_call<DB: Blockstore + Send + Sync + 'static>(
     Ok(state_manager.call(&mut message, Some(tipset))?)
 }
 
+/// runs the given message with the balance, nonce or state of some actors
+/// overridden, and returns its result without any persisted changes.
+pub(in crate::rpc) async fn state_call_with_overrides<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((mut message, overrides, key))): Params<
+        LotusJson<(Message, Vec<ActorOverride>, TipsetKeys)>,
+    >,
+) -> Result<InvocResult, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    Ok(data
+        .state_manager
+        .call_with_overrides(&mut message, &overrides, Some(tipset))?)
+}
+
 /// returns the result of executing the indicated message, assuming it was
-/// executed in the indicated tipset.
+/// executed in the indicated tipset, with its execution trace.
 pub(in crate::rpc) async fn state_replay<DB: Blockstore + Send + Sync + 'static>(
     data: Data<RPCState<DB>>,
     Params(LotusJson((cid, key))): Params<LotusJson<(Cid, TipsetKeys)>>,
 ) -> Result<InvocResult, JsonRpcError> {
-    let state_manager = &data.state_manager;
     let tipset = data
         .state_manager
         .chain_store()
         .load_required_tipset(&key)?;
-    let (msg, ret) = state_manager.replay(&tipset, cid).await?;
+    replay(&data.state_manager, &tipset, cid).await
+}
+
+/// like `StateReplay`, but the tipset key may be empty, in which case the
+/// message is replayed in the tipset that included it.
+pub(in crate::rpc) async fn state_replay_ex<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((cid, key))): Params<LotusJson<(Cid, TipsetKeys)>>,
+) -> Result<InvocResult, JsonRpcError> {
+    let state_manager = &data.state_manager;
+    let tipset = if key.cids.is_empty() {
+        let head = state_manager.chain_store().heaviest_tipset();
+        let (executed_in, ..) = state_manager
+            .search_for_message(head, cid, LOOKBACK_NO_LIMIT, false)?
+            .with_context(|| format!("message {cid} was not found on chain"))?;
+        state_manager
+            .chain_store()
+            .load_required_tipset(executed_in.parents())?
+    } else {
+        state_manager.chain_store().load_required_tipset(&key)?
+    };
+    replay(state_manager, &tipset, cid).await
+}
 
+async fn replay<DB: Blockstore + Send + Sync + 'static>(
+    state_manager: &Arc<StateManager<DB>>,
+    tipset: &Arc<Tipset>,
+    cid: Cid,
+) -> Result<InvocResult, JsonRpcError> {
+    let (msg, ret) = state_manager.replay(tipset, cid).await?;
     Ok(InvocResult {
+        msg_cid: cid,
         msg,
         msg_rct: Some(ret.msg_receipt()),
         error: ret.failure_info(),
+        execution_trace: execution_trace::json(ret.exec_trace())?,
     })
 }
 
+/// computes the state of the indicated tipset, then applies the given messages
+/// on top of it at the given epoch, and returns the resulting state root with
+/// the results of all the messages.
+pub(in crate::rpc) async fn state_compute<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((epoch, messages, key))): Params<
+        LotusJson<(ChainEpoch, Vec<Message>, TipsetKeys)>,
+    >,
+) -> Result<ComputeStateOutput, JsonRpcError> {
+    let state_manager = &data.state_manager;
+    let tipset = state_manager.chain_store().load_required_tipset(&key)?;
+    Ok(state_manager
+        .compute_state(epoch, messages, tipset, VMTrace::NotTraced)
+        .await?)
+}
+
+/// like `StateCompute`, but the results include the execution traces of the
+/// messages.
+pub(in crate::rpc) async fn state_compute_traced<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((epoch, messages, key))): Params<
+        LotusJson<(ChainEpoch, Vec<Message>, TipsetKeys)>,
+    >,
+) -> Result<ComputeStateOutput, JsonRpcError> {
+    let state_manager = &data.state_manager;
+    let tipset = state_manager.chain_store().load_required_tipset(&key)?;
+    Ok(state_manager
+        .compute_state(epoch, messages, tipset, VMTrace::Traced)
+        .await?)
+}
+
+/// breaks down the rewards, gas burns and penalties of the indicated tipset
+/// by block
+pub(in crate::rpc) async fn state_tipset_rewards<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((key,))): Params<LotusJson<(TipsetKeys,)>>,
+) -> Result<Vec<BlockRewards>, JsonRpcError> {
+    let state_manager = &data.state_manager;
+    let tipset = state_manager.chain_store().load_required_tipset(&key)?;
+    Ok(state_manager.tipset_rewards(&tipset).await?)
+}
+
 /// gets network name from state manager
 pub(in crate::rpc) async fn state_network_name<DB: Blockstore>(
     data: Data<RPCState<DB>>,
@@ -81,6 +193,99 @@ pub(in crate::rpc) async fn state_get_network_version<DB: Blockstore>(
     Ok(data.state_manager.get_network_version(ts.epoch()))
 }
 
+/// returns the consensus parameters of the network at the heaviest tipset
+pub(in crate::rpc) async fn state_get_network_params<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> Result<NetworkParams, JsonRpcError> {
+    let state_manager = &data.state_manager;
+    let heaviest_tipset = state_manager.chain_store().heaviest_tipset();
+    let network_name = state_manager.get_network_name(heaviest_tipset.parent_state())?;
+    let params = crate::shim::params::NetworkParams::at_epoch(
+        state_manager.chain_config(),
+        heaviest_tipset.epoch(),
+    );
+    Ok(NetworkParams::new(network_name, &params))
+}
+
+/// returns the parameters of the computation of the base fee at the heaviest
+/// tipset
+pub(in crate::rpc) async fn state_base_fee_params<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> Result<BaseFeeParams, JsonRpcError> {
+    let state_manager = &data.state_manager;
+    let heaviest_tipset = state_manager.chain_store().heaviest_tipset();
+    let params = crate::shim::params::NetworkParams::at_epoch(
+        state_manager.chain_config(),
+        heaviest_tipset.epoch(),
+    );
+    Ok(BaseFeeParams::new(&params))
+}
+
+/// returns the network versions of the epochs from the first to the second
+/// parameter, included, as ranges of epochs. Epochs after the head are
+/// supported, with the upgrades scheduled by the node.
+pub(in crate::rpc) async fn state_network_versions<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((from, to))): Params<LotusJson<(ChainEpoch, ChainEpoch)>>,
+) -> Result<Vec<NetworkVersionRange>, JsonRpcError> {
+    if from > to {
+        return Err(format!("the range of epochs {from}..={to} is empty").into());
+    }
+    let ranges = crate::shim::params::network_versions(data.state_manager.chain_config(), from, to);
+    Ok(ranges
+        .into_iter()
+        .map(
+            |(from_epoch, to_epoch, network_version)| NetworkVersionRange {
+                from_epoch,
+                to_epoch,
+                network_version,
+            },
+        )
+        .collect())
+}
+
+/// returns the beacon randomness of an epoch, as seen from a tipset, drawn
+/// with the given personalization tag and entropy
+#[allow(clippy::type_complexity)]
+pub(in crate::rpc) async fn state_get_randomness_from_beacon<DB>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((personalization, rand_epoch, entropy, key))): Params<
+        LotusJson<(i64, ChainEpoch, Vec<u8>, TipsetKeys)>,
+    >,
+) -> Result<LotusJson<Vec<u8>>, JsonRpcError>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let digest = data
+        .state_manager
+        .chain_rand(tipset)
+        .beacon_randomness_digest(rand_epoch)?;
+    let randomness = draw_randomness_from_digest(&digest, personalization, rand_epoch, &entropy)?;
+    Ok(LotusJson(randomness.to_vec()))
+}
+
+/// returns the ticket randomness of an epoch, as seen from a tipset, drawn
+/// with the given personalization tag and entropy
+#[allow(clippy::type_complexity)]
+pub(in crate::rpc) async fn state_get_randomness_from_tickets<DB>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((personalization, rand_epoch, entropy, key))): Params<
+        LotusJson<(i64, ChainEpoch, Vec<u8>, TipsetKeys)>,
+    >,
+) -> Result<LotusJson<Vec<u8>>, JsonRpcError>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let digest = data
+        .state_manager
+        .chain_rand(tipset)
+        .chain_randomness_digest(rand_epoch)?;
+    let randomness = draw_randomness_from_digest(&digest, personalization, rand_epoch, &entropy)?;
+    Ok(LotusJson(randomness.to_vec()))
+}
+
 pub(crate) async fn state_get_actor<DB: Blockstore>(
     data: Data<RPCState<DB>>,
     Params(LotusJson((addr, tsk))): Params<LotusJson<(Address, TipsetKeys)>>,
@@ -90,6 +295,460 @@ pub(crate) async fn state_get_actor<DB: Blockstore>(
     state.map(Into::into).map_err(|e| e.into())
 }
 
+/// returns the portion of the balance of the given multisig wallet that can be
+/// spent at the indicated tipset
+pub(in crate::rpc) async fn msig_get_available_balance<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, key))): Params<LotusJson<(Address, TipsetKeys)>>,
+) -> Result<LotusJson<TokenAmount>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let (actor, state) = load_multisig(&data, &address, &tipset)?;
+    let locked = multisig::locked_balance(&state, tipset.epoch());
+    Ok(LotusJson(TokenAmount::from(&actor.balance) - &locked))
+}
+
+/// returns the amount of the given multisig wallet that vested between the two
+/// indicated tipsets
+pub(in crate::rpc) async fn msig_get_vested<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, start_key, end_key))): Params<
+        LotusJson<(Address, TipsetKeys, TipsetKeys)>,
+    >,
+) -> Result<LotusJson<TokenAmount>, JsonRpcError> {
+    let start = data.chain_store.load_required_tipset(&start_key)?;
+    let end = data.chain_store.load_required_tipset(&end_key)?;
+    if start.epoch() > end.epoch() {
+        return Err(format!(
+            "the start epoch {} is after the end epoch {}",
+            start.epoch(),
+            end.epoch()
+        )
+        .into());
+    }
+    let (_, state) = load_multisig(&data, &address, &end)?;
+    Ok(LotusJson(
+        multisig::locked_balance(&state, start.epoch())
+            - &multisig::locked_balance(&state, end.epoch()),
+    ))
+}
+
+/// returns the transactions of the given multisig wallet that wait for
+/// approvals at the indicated tipset
+pub(in crate::rpc) async fn msig_get_pending<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, key))): Params<LotusJson<(Address, TipsetKeys)>>,
+) -> Result<Vec<MsigTransaction>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let (_, state) = load_multisig(&data, &address, &tipset)?;
+    let transactions = multisig::pending_transactions(&state, data.state_manager.blockstore())?;
+    Ok(transactions
+        .into_iter()
+        .map(|(id, transaction)| MsigTransaction {
+            id,
+            to: transaction.to,
+            value: transaction.value,
+            method: transaction.method,
+            params: transaction.params,
+            approved: transaction.approved,
+        })
+        .collect())
+}
+
+fn load_multisig<DB: Blockstore>(
+    data: &RPCState<DB>,
+    address: &Address,
+    tipset: &Tipset,
+) -> anyhow::Result<(ActorState, multisig::State)> {
+    let actor = data
+        .state_manager
+        .get_actor(address, *tipset.parent_state())?
+        .with_context(|| format!("actor {address} not found"))?;
+    let state = multisig::load(data.state_manager.blockstore(), &actor)
+        .with_context(|| format!("actor {address} is not a multisig wallet"))?;
+    Ok((actor, state))
+}
+
+/// returns info about the given miner's sectors. If the filter bitfield is nil,
+/// all sectors are included.
+pub(in crate::rpc) async fn state_miner_sectors<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, filter, key))): Params<
+        LotusJson<(Address, Option<BitField>, TipsetKeys)>,
+    >,
+) -> Result<Vec<SectorOnChainInfo>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let state = load_miner_sectors(&data, &address, &tipset)?;
+    let sectors = state.sectors(data.state_manager.blockstore(), filter.as_ref())?;
+    Ok(sectors.into_iter().map(From::from).collect())
+}
+
+/// returns info about sectors that a given miner is actively proving.
+pub(in crate::rpc) async fn state_miner_active_sectors<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, key))): Params<LotusJson<(Address, TipsetKeys)>>,
+) -> Result<Vec<SectorOnChainInfo>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let state = load_miner(&data, &address, &tipset)?;
+    let store = data.state_manager.blockstore();
+    let mut active_sectors = BitField::new();
+    state.for_each_deadline(
+        &data.state_manager.chain_config().policy,
+        store,
+        |_, deadline| {
+            deadline.for_each(store, |_, partition: miner::Partition| {
+                active_sectors |= &partition.active_sectors();
+                Ok(())
+            })
+        },
+    )?;
+    let sectors =
+        load_miner_sectors(&data, &address, &tipset)?.sectors(store, Some(&active_sectors))?;
+    Ok(sectors.into_iter().map(From::from).collect())
+}
+
+/// returns all partitions in the specified deadline
+pub(in crate::rpc) async fn state_miner_partitions<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, deadline_index, key))): Params<
+        LotusJson<(Address, u64, TipsetKeys)>,
+    >,
+) -> Result<Vec<MinerPartition>, JsonRpcError> {
+    let policy = &data.state_manager.chain_config().policy;
+    if deadline_index >= policy.wpost_period_deadlines {
+        return Err(format!("invalid deadline index {deadline_index}").into());
+    }
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let state = load_miner(&data, &address, &tipset)?;
+    let store = data.state_manager.blockstore();
+    let mut partitions = vec![];
+    state.for_each_deadline(policy, store, |index, deadline| {
+        if index == deadline_index {
+            deadline.for_each(store, |_, partition: miner::Partition| {
+                partitions.push(MinerPartition::from(&partition));
+                Ok(())
+            })?;
+        }
+        Ok(())
+    })?;
+    Ok(partitions)
+}
+
+/// returns the on-chain info for the specified miner's sector. Returns null in
+/// case the sector info isn't found.
+pub(in crate::rpc) async fn state_sector_get_info<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, sector_number, key))): Params<
+        LotusJson<(Address, SectorNumber, TipsetKeys)>,
+    >,
+) -> Result<Option<SectorOnChainInfo>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let state = load_miner_sectors(&data, &address, &tipset)?;
+    let sector = state.sector(data.state_manager.blockstore(), sector_number)?;
+    Ok(sector.map(From::from))
+}
+
+/// returns the PreCommit info for the specified miner's sector. Returns null in
+/// case the sector isn't pre-committed.
+pub(in crate::rpc) async fn state_sector_pre_commit_info<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, sector_number, key))): Params<
+        LotusJson<(Address, SectorNumber, TipsetKeys)>,
+    >,
+) -> Result<Option<ApiSectorPreCommitOnChainInfo>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let state = load_miner_sectors(&data, &address, &tipset)?;
+    let pre_commit = state.pre_committed_sector(data.state_manager.blockstore(), sector_number)?;
+    Ok(pre_commit.map(From::from))
+}
+
+/// returns epoch at which given sector will expire
+pub(in crate::rpc) async fn state_sector_expiration<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, sector_number, key))): Params<
+        LotusJson<(Address, SectorNumber, TipsetKeys)>,
+    >,
+) -> Result<ApiSectorExpiration, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let state = load_miner_sectors(&data, &address, &tipset)?;
+    let expiration = state
+        .sector_expiration(data.state_manager.blockstore(), sector_number)?
+        .with_context(|| format!("sector {sector_number} not found in any partition"))?;
+    Ok(expiration.into())
+}
+
+fn load_miner_sectors<DB: Blockstore>(
+    data: &RPCState<DB>,
+    address: &Address,
+    tipset: &Tipset,
+) -> anyhow::Result<crate::shim::miner::State> {
+    let actor = data
+        .state_manager
+        .get_actor(address, *tipset.parent_state())?
+        .with_context(|| format!("actor {address} not found"))?;
+    crate::shim::miner::State::load(data.state_manager.blockstore(), &actor)
+        .with_context(|| format!("actor {address} is not a miner"))
+}
+
+fn load_miner<DB: Blockstore>(
+    data: &RPCState<DB>,
+    address: &Address,
+    tipset: &Tipset,
+) -> anyhow::Result<miner::State> {
+    let actor = data
+        .state_manager
+        .get_actor(address, *tipset.parent_state())?
+        .with_context(|| format!("actor {address} not found"))?;
+    miner::State::load(data.state_manager.blockstore(), actor.code, actor.state)
+        .with_context(|| format!("actor {address} is not a miner"))
+}
+
+/// returns the data cap for the given address. Returns nil if there is no
+/// entry in the data cap table for the address.
+pub(in crate::rpc) async fn state_verified_client_status<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, key))): Params<LotusJson<(Address, TipsetKeys)>>,
+) -> Result<LotusJson<Option<BigInt>>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let id = lookup_id(&data, &address, &tipset)?;
+    let store = data.state_manager.blockstore();
+    let data_cap = match load_verifreg(&data, &tipset)? {
+        state @ verifreg::State::V8(_) => state.verified_client_data_cap(store, &id)?,
+        _ => {
+            let actor = data
+                .state_manager
+                .get_actor(&Address::DATACAP_TOKEN_ACTOR, *tipset.parent_state())?
+                .context("datacap actor not found")?;
+            let state = datacap::load(store, &actor)?;
+            datacap::verified_client_data_cap(&state, store, id.id()?)?
+        }
+    };
+    Ok(LotusJson(data_cap))
+}
+
+/// returns the data cap for the given verifier address. Returns nil if there
+/// is no entry in the data cap table for the address.
+pub(in crate::rpc) async fn state_verifier_status<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, key))): Params<LotusJson<(Address, TipsetKeys)>>,
+) -> Result<LotusJson<Option<BigInt>>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let id = lookup_id(&data, &address, &tipset)?;
+    let state = load_verifreg(&data, &tipset)?;
+    Ok(LotusJson(
+        state.verifier_data_cap(data.state_manager.blockstore(), &id)?,
+    ))
+}
+
+/// returns the allocation for a given address and allocation ID
+pub(in crate::rpc) async fn state_get_allocation<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((client, allocation_id, key))): Params<
+        LotusJson<(Address, verifreg::AllocationID, TipsetKeys)>,
+    >,
+) -> Result<Option<ApiAllocation>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let client = lookup_id(&data, &client, &tipset)?.id()?;
+    let state = load_verifreg(&data, &tipset)?;
+    let allocation = state.allocation(data.state_manager.blockstore(), client, allocation_id)?;
+    Ok(allocation.map(From::from))
+}
+
+/// returns the claim for a given address and claim ID
+pub(in crate::rpc) async fn state_get_claim<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((provider, claim_id, key))): Params<
+        LotusJson<(Address, verifreg::ClaimID, TipsetKeys)>,
+    >,
+) -> Result<Option<ApiClaim>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let provider = lookup_id(&data, &provider, &tipset)?.id()?;
+    let state = load_verifreg(&data, &tipset)?;
+    let claim = state.claim(data.state_manager.blockstore(), provider, claim_id)?;
+    Ok(claim.map(From::from))
+}
+
+/// returns the exact circulating supply of Filecoin at the given tipset,
+/// summed over the balances of all the actors
+pub(in crate::rpc) async fn state_circulating_supply<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((key,))): Params<LotusJson<(TipsetKeys,)>>,
+) -> Result<LotusJson<TokenAmount>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    Ok(LotusJson(
+        data.state_manager.get_state_circulating_supply(&tipset)?,
+    ))
+}
+
+/// returns an approximation of the circulating supply of Filecoin at the
+/// given tipset, as used by the VM, with its breakdown
+pub(in crate::rpc) async fn state_vm_circulating_supply_internal<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((key,))): Params<LotusJson<(TipsetKeys,)>>,
+) -> Result<LotusJson<CirculatingSupply>, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    Ok(LotusJson(
+        data.state_manager
+            .get_vm_circulating_supply_detailed(&tipset)?,
+    ))
+}
+
+fn lookup_id<DB: Blockstore + Send + Sync + 'static>(
+    data: &RPCState<DB>,
+    address: &Address,
+    tipset: &Tipset,
+) -> anyhow::Result<Address> {
+    data.state_manager
+        .lookup_id(address, tipset)?
+        .with_context(|| format!("actor {address} not found"))
+}
+
+fn load_verifreg<DB: Blockstore>(
+    data: &RPCState<DB>,
+    tipset: &Tipset,
+) -> anyhow::Result<verifreg::State> {
+    let actor = data
+        .state_manager
+        .get_actor(&Address::VERIFIED_REGISTRY_ACTOR, *tipset.parent_state())?
+        .context("verified registry actor not found")?;
+    verifreg::State::load(data.state_manager.blockstore(), &actor)
+}
+
+/// decodes the CBOR parameters of a call to the given method of the given actor
+/// to JSON
+pub(in crate::rpc) async fn state_decode_params<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((address, method, params, key))): Params<
+        LotusJson<(Address, MethodNum, RawBytes, TipsetKeys)>,
+    >,
+) -> Result<serde_json::Value, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    let actor = data
+        .state_manager
+        .get_actor(&address, *tipset.parent_state())?
+        .with_context(|| format!("actor {address} not found"))?;
+    let builtin = builtin_actor(&data, &tipset, &actor.code)?;
+    let version = actor_version(&data, &tipset)?;
+    Ok(actor_methods::decode_params(
+        builtin, version, method, &params,
+    )?)
+}
+
+/// encodes the JSON parameters of a call to the given method of the actor with
+/// the given code to CBOR
+pub(in crate::rpc) async fn state_encode_params<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((code, method, params))): Params<
+        LotusJson<(Cid, MethodNum, serde_json::Value)>,
+    >,
+) -> Result<LotusJson<RawBytes>, JsonRpcError> {
+    let head = data.chain_store.heaviest_tipset();
+    let builtin = builtin_actor(&data, &head, &code)?;
+    let version = actor_version(&data, &head)?;
+    Ok(LotusJson(actor_methods::encode_params(
+        builtin, version, method, params,
+    )?))
+}
+
+/// returns the name of the builtin actor with the given code, in the manifest of
+/// the actors of the given tipset
+pub(in crate::rpc) async fn state_builtin_actor<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((code, key))): Params<LotusJson<(Cid, TipsetKeys)>>,
+) -> Result<String, JsonRpcError> {
+    let tipset = data.chain_store.load_required_tipset(&key)?;
+    Ok(builtin_actor(&data, &tipset, &code)?.name().to_string())
+}
+
+/// Returns at most `limit` actors of the state of the given tipset, starting
+/// from the actor of address `start`, and the address of the actor to start the
+/// next page from, if any. With `code`, only the actors with that code are
+/// listed. The state tree is walked without holding all its actors in memory.
+#[allow(clippy::type_complexity)]
+pub(in crate::rpc) async fn state_list_actors_page<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((tsk, start, limit, code))): Params<
+        LotusJson<(TipsetKeys, Option<Address>, u64, Option<Cid>)>,
+    >,
+) -> Result<ActorsPage, JsonRpcError> {
+    let ts = data.chain_store.load_required_tipset(&tsk)?;
+    Ok(actors_page(
+        data.state_manager.blockstore(),
+        ts.parent_state(),
+        start.as_ref(),
+        limit,
+        code,
+    )?)
+}
+
+/// Page of the actors of the state tree `root`, see [`state_list_actors_page`].
+fn actors_page(
+    store: &impl Blockstore,
+    root: &Cid,
+    start: Option<&Address>,
+    limit: u64,
+    code: Option<Cid>,
+) -> anyhow::Result<ActorsPage> {
+    // An empty page would point to its own start as the next one
+    anyhow::ensure!(limit > 0, "the page limit must be positive");
+    let limit = limit.min(MAX_ACTORS_PAGE) as usize;
+    let mut actors = vec![];
+    let next = state_tree::for_each_actor_from(store, root, start, |address, actor| {
+        if code.is_some_and(|code| actor.code != code) {
+            return Ok(ControlFlow::Continue(()));
+        }
+        if actors.len() == limit {
+            return Ok(ControlFlow::Break(()));
+        }
+        actors.push(ActorEntry { address, actor });
+        Ok(ControlFlow::Continue(()))
+    })?;
+    Ok(ActorsPage { actors, next })
+}
+
+/// Largest page of `StateListActorsPage`.
+const MAX_ACTORS_PAGE: u64 = 10_000;
+
+/// Finds the builtin actor of `code` in the manifest of the state of `tipset`.
+pub(in crate::rpc) fn builtin_actor<DB: Blockstore>(
+    data: &RPCState<DB>,
+    tipset: &Tipset,
+    code: &Cid,
+) -> anyhow::Result<BuiltinActor> {
+    builtin_actors_manifest(data, tipset)?
+        .builtin_actor_of(code)
+        .with_context(|| format!("{code} is not the code of a builtin actor"))
+}
+
+/// Returns the version of the builtin actors deployed at `tipset`.
+fn actor_version<DB: Blockstore>(
+    data: &RPCState<DB>,
+    tipset: &Tipset,
+) -> anyhow::Result<ActorVersion> {
+    let network_version = data.state_manager.get_network_version(tipset.epoch());
+    ActorVersion::for_network_version(network_version).with_context(|| {
+        format!(
+            "no builtin actor methods at network version {}",
+            *network_version
+        )
+    })
+}
+
+/// Loads the manifest of the builtin actors of the state of `tipset`.
+fn builtin_actors_manifest<DB: Blockstore>(
+    data: &RPCState<DB>,
+    tipset: &Tipset,
+) -> anyhow::Result<BuiltinActorManifest> {
+    let store = data.state_manager.blockstore();
+    let system = data
+        .state_manager
+        .get_actor(&Address::SYSTEM_ACTOR, *tipset.parent_state())?
+        .context("system actor not found")?;
+    let system_state = store
+        .get_cbor::<SystemState>(&system.state)?
+        .context("system actor state not found")?;
+    BuiltinActorManifest::load_v1_actor_list(store, &system_state.builtin_actors)
+}
+
 /// looks up the Escrow and Locked balances of the given address in the Storage
 /// Market
 pub(in crate::rpc) async fn state_market_balance<DB: Blockstore + Send + Sync + 'static>(
@@ -105,38 +764,104 @@ pub(in crate::rpc) async fn state_market_balance<DB: Blockstore + Send + Sync +
         .map_err(|e| e.into())
 }
 
+/// returns all the deals of the Storage Market, keyed by ID
 pub(in crate::rpc) async fn state_market_deals<DB: Blockstore>(
     data: Data<RPCState<DB>>,
     Params(LotusJson((tsk,))): Params<LotusJson<(TipsetKeys,)>>,
-) -> Result<HashMap<String, MarketDeal>, JsonRpcError> {
+) -> Result<MarketDeals<DB>, JsonRpcError> {
+    let ts = data.chain_store.load_required_tipset(&tsk)?;
+    Ok(MarketDeals {
+        state: load_market(&data, &ts)?,
+        store: data.state_manager.blockstore_owned(),
+    })
+}
+
+/// Deals of the Storage Market, serialized as they are read from the store so
+/// that they are never all held in memory.
+pub(in crate::rpc) struct MarketDeals<DB> {
+    store: Arc<DB>,
+    state: market::State,
+}
+
+impl<DB: Blockstore> Serialize for MarketDeals<DB> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let store = &*self.store;
+        let mut map = serializer.serialize_map(None)?;
+        let mut serialize_error = None;
+        market::for_each_proposal(&self.state, store, 0, None, |deal_id, proposal| {
+            let state = market::deal_state(&self.state, store, deal_id)?
+                .unwrap_or(market::UNSTARTED_DEAL_STATE);
+            if let Err(e) =
+                map.serialize_entry(&deal_id.to_string(), &MarketDeal { proposal, state })
+            {
+                serialize_error = Some(e);
+                anyhow::bail!("couldn't serialize deal {deal_id}");
+            }
+            Ok(())
+        })
+        .map_err(|e| {
+            serialize_error
+                .take()
+                .unwrap_or_else(|| S::Error::custom(e))
+        })?;
+        map.end()
+    }
+}
+
+/// returns at most `limit` deals of the Storage Market, in the order of their
+/// IDs from `start` on, and the ID to start the next page from, if any.
+pub(in crate::rpc) async fn state_market_deals_page<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((tsk, start, limit))): Params<LotusJson<(TipsetKeys, DealID, u64)>>,
+) -> Result<MarketDealsPage, JsonRpcError> {
     let ts = data.chain_store.load_required_tipset(&tsk)?;
+    let store = data.state_manager.blockstore();
+    let market_state = load_market(&data, &ts)?;
+
+    let mut deals = HashMap::new();
+    let limit = limit.min(MAX_MARKET_DEALS_PAGE);
+    let next = market::for_each_proposal(
+        &market_state,
+        store,
+        start,
+        Some(limit),
+        |deal_id, proposal| {
+            let state = market::deal_state(&market_state, store, deal_id)?
+                .unwrap_or(market::UNSTARTED_DEAL_STATE);
+            deals.insert(deal_id.to_string(), MarketDeal { proposal, state });
+            Ok(())
+        },
+    )?;
+    Ok(MarketDealsPage { deals, next })
+}
+
+/// returns information about the indicated deal
+pub(in crate::rpc) async fn state_market_storage_deal<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((deal_id, tsk))): Params<LotusJson<(DealID, TipsetKeys)>>,
+) -> Result<MarketDeal, JsonRpcError> {
+    let ts = data.chain_store.load_required_tipset(&tsk)?;
+    let store = data.state_manager.blockstore();
+    let market_state = load_market(&data, &ts)?;
+    let proposal = market::proposal(&market_state, store, deal_id)?
+        .with_context(|| format!("deal {deal_id} not found"))?;
+    let state =
+        market::deal_state(&market_state, store, deal_id)?.unwrap_or(market::UNSTARTED_DEAL_STATE);
+    Ok(MarketDeal { proposal, state })
+}
+
+/// Largest page of `StateMarketDealsPage`.
+const MAX_MARKET_DEALS_PAGE: u64 = 10_000;
+
+fn load_market<DB: Blockstore>(
+    data: &RPCState<DB>,
+    tipset: &Tipset,
+) -> anyhow::Result<market::State> {
     let actor = data
         .state_manager
-        .get_actor(&Address::MARKET_ACTOR, *ts.parent_state())?
-        .ok_or("Market actor address could not be resolved")?;
-    let market_state =
-        market::State::load(data.state_manager.blockstore(), actor.code, actor.state)?;
-
-    let da = market_state.proposals(data.state_manager.blockstore())?;
-    let sa = market_state.states(data.state_manager.blockstore())?;
-
-    let mut out = HashMap::new();
-    da.for_each(|deal_id, d| {
-        let s = sa.get(deal_id)?.unwrap_or(market::DealState {
-            sector_start_epoch: -1,
-            last_updated_epoch: -1,
-            slash_epoch: -1,
-        });
-        out.insert(
-            deal_id.to_string(),
-            MarketDeal {
-                proposal: d,
-                state: s,
-            },
-        );
-        Ok(())
-    })?;
-    Ok(out)
+        .get_actor(&Address::MARKET_ACTOR, *tipset.parent_state())?
+        .context("Market actor address could not be resolved")?;
+    market::load(data.state_manager.blockstore(), &actor)
 }
 
 /// returns the message receipt for the given message
@@ -154,16 +879,41 @@ pub(in crate::rpc) async fn state_get_receipt<DB: Blockstore + Send + Sync + 'st
         .map(|s| s.into())
         .map_err(|e| e.into())
 }
-/// looks back in the chain for a message. If not found, it blocks until the
-/// message arrives on chain, and gets to the indicated confidence depth.
+/// looks back in the chain, from the indicated tipset, for the tipset
+/// executing a message, at most `lookback_limit` epochs back unless it is `-1`.
+/// If `allow_replaced`, a message with the same call and sequence but
+/// different gas parameters is found instead, and its CID returned.
+pub(in crate::rpc) async fn state_search_msg<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((key, cid, lookback_limit, allow_replaced))): Params<
+        LotusJson<(TipsetKeys, Cid, ChainEpoch, bool)>,
+    >,
+) -> Result<Option<MessageLookup>, JsonRpcError> {
+    let state_manager = &data.state_manager;
+    let tipset = state_manager.chain_store().load_required_tipset(&key)?;
+    let found = state_manager.search_for_message(tipset, cid, lookback_limit, allow_replaced)?;
+    Ok(found
+        .map(|(tipset, receipt, cid)| message_lookup(&tipset, receipt, cid))
+        .transpose()?)
+}
+
+/// looks back in the chain for a message, like `StateSearchMsg`. If not found,
+/// it blocks until the message arrives on chain, and gets to the indicated
+/// confidence depth.
 pub(in crate::rpc) async fn state_wait_msg<DB: Blockstore + Send + Sync + 'static>(
     data: Data<RPCState<DB>>,
-    Params(LotusJson((cid, confidence))): Params<LotusJson<(Cid, i64)>>,
+    Params(LotusJson((cid, confidence, lookback_limit, allow_replaced))): Params<
+        LotusJson<(Cid, i64, ChainEpoch, bool)>,
+    >,
 ) -> Result<MessageLookup, JsonRpcError> {
     let state_manager = &data.state_manager;
-    let (tipset, receipt) = state_manager.wait_for_message(cid, confidence).await?;
-    let tipset = tipset.ok_or("wait for msg returned empty tuple")?;
-    let receipt = receipt.ok_or("wait for msg returned empty receipt")?;
+    let (tipset, receipt, cid) = state_manager
+        .wait_for_message(cid, confidence, lookback_limit, allow_replaced)
+        .await?;
+    Ok(message_lookup(&tipset, receipt, cid)?)
+}
+
+fn message_lookup(tipset: &Tipset, receipt: Receipt, cid: Cid) -> anyhow::Result<MessageLookup> {
     let ipld: Ipld = if receipt.return_data().bytes().is_empty() {
         Ipld::Null
     } else {
@@ -250,6 +1000,7 @@ pub(in crate::rpc) async fn state_fetch_root<DB: Blockstore + Sync + Send + 'sta
     // more than 1000 elements (even when walking tens of millions of nodes).
     let dfs = Arc::new(Mutex::new(vec![Ipld::Link(root_cid)]));
     let mut to_be_fetched = vec![];
+    let session = BitswapSession::new();
 
     // Loop until: No more items in `dfs` AND no running worker tasks.
     loop {
@@ -295,11 +1046,13 @@ pub(in crate::rpc) async fn state_fetch_root<DB: Blockstore + Sync + Send + 'sta
                     let db = db.clone();
                     let dfs_vec = Arc::clone(&dfs);
                     let car_tx = car_tx.clone();
+                    let session = session.clone();
                     move || {
                         let (tx, rx) = flume::bounded(1);
                         network_send.send(NetworkMessage::BitswapRequest {
                             cid,
                             response_channel: tx,
+                            session: Some(session),
                         })?;
                         // Bitswap requests do not fail. They are just ignored if no-one has
                         // the requested data. Here we arbitrary decide to only wait for
@@ -349,3 +1102,79 @@ pub(in crate::rpc) async fn state_fetch_root<DB: Blockstore + Sync + Send + 'sta
 fn lock_pop<T>(mutex: &Mutex<Vec<T>>) -> Option<T> {
     mutex.lock().pop()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+    use crate::shim::{
+        econ::TokenAmount,
+        state_tree::{ActorState, StateTree, StateTreeVersion},
+    };
+    use num_traits::Zero as _;
+
+    #[test]
+    fn actors_pages() {
+        let store = Arc::new(MemoryDB::default());
+        let mut state = StateTree::new(store.clone(), StateTreeVersion::V5).unwrap();
+        for id in 0..5 {
+            let actor = ActorState::new(
+                Cid::default(),
+                Cid::default(),
+                TokenAmount::zero(),
+                id,
+                None,
+            );
+            state.set_actor(&Address::new_id(id), actor).unwrap();
+        }
+        let root = state.flush().unwrap();
+
+        let mut addresses = vec![];
+        let mut start = None;
+        loop {
+            let page = actors_page(&*store, &root, start.as_ref(), 2, None).unwrap();
+            assert!(!page.actors.is_empty());
+            addresses.extend(page.actors.into_iter().map(|entry| entry.address));
+            start = page.next;
+            if start.is_none() {
+                break;
+            }
+        }
+        assert_eq!(addresses.len(), 5);
+        assert!(actors_page(&*store, &root, None, 0, None).is_err());
+    }
+
+    #[test]
+    fn market_deals_are_streamed() {
+        use fil_actor_market_state::v12::{DealProposal, Label, State};
+
+        let store = Arc::new(MemoryDB::default());
+        let mut state = State::new(&*store).unwrap();
+        let proposal = DealProposal {
+            piece_cid: Cid::default(),
+            piece_size: fvm_shared4::piece::PaddedPieceSize(2048),
+            verified_deal: false,
+            client: Address::new_id(1000).into(),
+            provider: Address::new_id(1001).into(),
+            label: Label::String("deal".into()),
+            start_epoch: 10,
+            end_epoch: 20,
+            storage_price_per_epoch: TokenAmount::zero().into(),
+            provider_collateral: TokenAmount::zero().into(),
+            client_collateral: TokenAmount::zero().into(),
+        };
+        state
+            .put_deal_proposals(&*store, &[(0, proposal.clone()), (3, proposal)])
+            .unwrap();
+        let deals = MarketDeals {
+            store,
+            state: market::State::V12(state),
+        };
+
+        let json = serde_json::to_value(deals).unwrap();
+        let deals = json.as_object().unwrap();
+        assert_eq!(deals.keys().collect::<Vec<_>>(), ["0", "3"]);
+        assert_eq!(deals["3"]["Proposal"]["Label"], "deal");
+        assert_eq!(deals["3"]["State"]["SectorStartEpoch"], -1);
+    }
+}
@@ -2,13 +2,21 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 #![allow(clippy::unused_async)]
 
-use crate::chain_sync::SyncState;
+use std::sync::Arc;
+
+use crate::blocks::{Block, FullTipset, GossipBlock, Tipset};
+use crate::chain::persist_objects;
+use crate::chain_sync::{SyncState, TipsetValidator};
+use crate::libp2p::{NetworkMessage, Topic, PUBSUB_BLOCK_STR};
 use crate::lotus_json::LotusJson;
 use crate::rpc_api::data_types::{RPCState, RPCSyncState};
+use anyhow::{anyhow, Context as _};
 use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{to_vec, CborStore as _};
 use jsonrpc_v2::{Data, Error as JsonRpcError, Params};
 use parking_lot::RwLock;
+use serde::de::DeserializeOwned;
 
 /// Checks if a given block is marked as bad.
 pub(in crate::rpc) async fn sync_check_bad<DB: Blockstore>(
@@ -28,6 +36,23 @@ pub(in crate::rpc) async fn sync_mark_bad<DB: Blockstore>(
     Ok(())
 }
 
+/// Unmarks a bad block, so that it can be synced again.
+pub(in crate::rpc) async fn sync_unmark_bad<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((cid,))): Params<LotusJson<(Cid,)>>,
+) -> Result<(), JsonRpcError> {
+    data.bad_blocks.remove(&cid);
+    Ok(())
+}
+
+/// Unmarks all the bad blocks, so that they can be synced again.
+pub(in crate::rpc) async fn sync_unmark_all_bad<DB: Blockstore>(
+    data: Data<RPCState<DB>>,
+) -> Result<(), JsonRpcError> {
+    data.bad_blocks.clear();
+    Ok(())
+}
+
 async fn clone_state(state: &RwLock<SyncState>) -> SyncState {
     state.read().clone()
 }
@@ -40,6 +65,71 @@ pub(in crate::rpc) async fn sync_state<DB: Blockstore>(
     Ok(RPCSyncState { active_syncs })
 }
 
+/// submits a newly mined block, whose messages must be in the message pool
+/// or the database, to the synchronizer of the node and gossips it to the
+/// network once validated.
+pub(in crate::rpc) async fn sync_submit_block<DB: Blockstore + Send + Sync + 'static>(
+    data: Data<RPCState<DB>>,
+    Params(LotusJson((block,))): Params<LotusJson<(GossipBlock,)>>,
+) -> Result<(), JsonRpcError> {
+    Ok(submit_block(&data, block).await?)
+}
+
+async fn submit_block<DB: Blockstore + Send + Sync + 'static>(
+    data: &RPCState<DB>,
+    gossip: GossipBlock,
+) -> anyhow::Result<()> {
+    let db = data.chain_store.blockstore();
+    let block = Block {
+        header: gossip.header.clone(),
+        bls_messages: load_messages(db, &gossip.bls_messages)?,
+        secp_messages: load_messages(db, &gossip.secpk_messages)?,
+    };
+    let tipset = FullTipset::from(block);
+
+    let genesis = Arc::new(Tipset::from(data.chain_store.genesis()));
+    let block_delay = data.state_manager.chain_config().block_delay_secs as u64;
+    TipsetValidator(&tipset)
+        .validate(
+            Arc::clone(&data.chain_store),
+            Arc::clone(&data.bad_blocks),
+            genesis,
+            block_delay,
+        )
+        .map_err(|e| anyhow!("invalid block: {e}"))?;
+    for block in tipset.blocks() {
+        persist_objects(db, &[block.header()])?;
+        persist_objects(db, block.bls_msgs())?;
+        persist_objects(db, block.secp_msgs())?;
+    }
+
+    // Gossipsub doesn't deliver the messages published by the node to itself
+    data.network_send
+        .send_async(NetworkMessage::PubsubMessage {
+            topic: Topic::new(format!("{PUBSUB_BLOCK_STR}/{}", data.network_name)),
+            message: to_vec(&gossip)?,
+        })
+        .await
+        .context("network receiver dropped")?;
+    data.tipset_sink
+        .send_async(Arc::new(tipset.into_tipset()))
+        .await
+        .context("tipset processor dropped")?;
+    Ok(())
+}
+
+fn load_messages<T: DeserializeOwned>(
+    db: &impl Blockstore,
+    cids: &[Cid],
+) -> anyhow::Result<Vec<T>> {
+    cids.iter()
+        .map(|cid| {
+            db.get_cbor(cid)?
+                .with_context(|| format!("message {cid} of the block not found"))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -121,19 +211,23 @@ mod tests {
         };
         let start_time = chrono::Utc::now();
         let (gc_event_tx, _) = flume::unbounded();
+        let (tipset_sink, _) = flume::unbounded();
 
         let state = Arc::new(RPCState {
             state_manager,
             keystore: Arc::new(RwLock::new(KeyStore::new(KeyStoreConfig::Memory).unwrap())),
+            token_registry: Default::default(),
             mpool: Arc::new(pool),
             bad_blocks: Default::default(),
             sync_state: Arc::new(parking_lot::RwLock::new(Default::default())),
             network_send,
+            tipset_sink,
             network_name: TEST_NET_NAME.to_owned(),
             start_time,
             chain_store: cs_for_chain.clone(),
             beacon,
             gc_event_tx,
+            db_directory: Default::default(),
         });
         (state, network_rx)
     }
@@ -158,10 +252,31 @@ mod tests {
                 .await
                 .is_ok()
         );
-        match sync_check_bad(Data(state), Params(LotusJson((cid,)))).await {
+        match sync_check_bad(Data(state.clone()), Params(LotusJson((cid,)))).await {
             Ok(reason) => assert_eq!(reason, "Marked bad manually through RPC API"),
             Err(e) => std::panic::panic_any(e),
         }
+
+        // Unmark it, one block and then all of them
+        assert!(
+            sync_unmark_bad(Data(state.clone()), Params(LotusJson((cid,))))
+                .await
+                .is_ok()
+        );
+        match sync_check_bad(Data(state.clone()), Params(LotusJson((cid,)))).await {
+            Ok(reason) => assert_eq!(reason, ""),
+            Err(e) => std::panic::panic_any(e),
+        }
+        assert!(
+            sync_mark_bad(Data(state.clone()), Params(LotusJson((cid,))))
+                .await
+                .is_ok()
+        );
+        assert!(sync_unmark_all_bad(Data(state.clone())).await.is_ok());
+        match sync_check_bad(Data(state), Params(LotusJson((cid,)))).await {
+            Ok(reason) => assert_eq!(reason, ""),
+            Err(e) => std::panic::panic_any(e),
+        }
     }
 
     #[tokio::test]
@@ -187,4 +302,18 @@ mod tests {
             Err(e) => std::panic::panic_any(e),
         }
     }
+
+    #[tokio::test]
+    async fn submit_block_with_unknown_messages() {
+        let (state, network_rx) = state_setup();
+
+        let block = GossipBlock {
+            header: state.chain_store.genesis().clone(),
+            bls_messages: vec![Cid::default()],
+            secpk_messages: vec![],
+        };
+        let error = submit_block(&state, block).await.unwrap_err();
+        assert!(error.to_string().contains("not found"), "{error}");
+        assert!(network_rx.is_empty());
+    }
 }
@@ -1,10 +1,13 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
+use crate::auth::TokenRegistry;
 use crate::beacon::BeaconSchedule;
-use crate::blocks::TipsetKeys;
+use crate::blocks::{Tipset, TipsetKeys};
 use crate::chain::ChainStore;
 use crate::chain_sync::{BadBlockCache, SyncState};
 use crate::ipld::json::IpldJson;
@@ -23,17 +26,21 @@ use crate::shim::{
     message::Message,
     sector::{RegisteredSealProof, SectorNumber},
     state_tree::ActorState,
+    verifreg,
+    version::NetworkVersion,
 };
 use crate::state_manager::StateManager;
-use ahash::HashSet;
+use ahash::{HashMap, HashSet};
 use chrono::Utc;
 use cid::Cid;
 use fil_actor_interface::{
     market::{DealProposal, DealState},
-    miner::MinerPower,
+    miner::{self, MinerPower},
     power::Claim,
 };
+use fil_actors_shared::fvm_ipld_bitfield::BitField;
 use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::RawBytes;
 use jsonrpc_v2::{MapRouter as JsonRpcMapRouter, Server as JsonRpcServer};
 use libipld_core::ipld::Ipld;
 use num_bigint::BigInt;
@@ -49,16 +56,23 @@ where
     DB: Blockstore,
 {
     pub keystore: Arc<RwLock<KeyStore>>,
+    /// Tokens issued through RPC, which can be listed and revoked.
+    pub token_registry: Arc<SyncRwLock<TokenRegistry>>,
     pub chain_store: Arc<ChainStore<DB>>,
     pub state_manager: Arc<StateManager<DB>>,
     pub mpool: Arc<MessagePool<MpoolRpcProvider<DB>>>,
     pub bad_blocks: Arc<BadBlockCache>,
     pub sync_state: Arc<SyncRwLock<SyncState>>,
     pub network_send: flume::Sender<NetworkMessage>,
+    /// Tipsets to hand to the synchronizer, e.g. the blocks submitted through
+    /// RPC.
+    pub tipset_sink: flume::Sender<Arc<Tipset>>,
     pub network_name: String,
     pub start_time: chrono::DateTime<Utc>,
     pub beacon: Arc<BeaconSchedule>,
     pub gc_event_tx: flume::Sender<flume::Sender<anyhow::Result<()>>>,
+    /// Directory of the database, whose size is reported through RPC.
+    pub db_directory: PathBuf,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,7 +101,7 @@ pub struct BlockMessages {
 #[serde(rename_all = "PascalCase")]
 pub struct MessageSendSpec {
     #[serde(with = "crate::lotus_json")]
-    max_fee: TokenAmount,
+    pub max_fee: TokenAmount,
 }
 
 lotus_json_with_self!(MessageSendSpec);
@@ -99,6 +113,55 @@ pub struct MarketDeal {
     pub state: DealState,
 }
 
+/// A page of the deals of the Storage Market, keyed by ID.
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MarketDealsPage {
+    pub deals: HashMap<String, MarketDeal>,
+    /// The deal ID that the next page starts from, if there are more deals.
+    pub next: Option<DealID>,
+}
+
+/// A page of the actors of a state tree, in the order of the tree.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ActorsPage {
+    pub actors: Vec<ActorEntry>,
+    /// The address of the actor that the next page starts from, if there are
+    /// more actors.
+    #[serde(with = "crate::lotus_json")]
+    pub next: Option<Address>,
+}
+
+lotus_json_with_self!(ActorsPage);
+
+/// An actor of a state tree, with its address.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ActorEntry {
+    #[serde(with = "crate::lotus_json")]
+    pub address: Address,
+    #[serde(with = "crate::lotus_json")]
+    pub actor: ActorState,
+}
+
+/// A transaction of a multisig wallet, waiting for approvals.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MsigTransaction {
+    #[serde(rename = "ID")]
+    pub id: i64,
+    #[serde(with = "crate::lotus_json")]
+    pub to: Address,
+    #[serde(with = "crate::lotus_json")]
+    pub value: TokenAmount,
+    pub method: u64,
+    #[serde(with = "crate::lotus_json")]
+    pub params: RawBytes,
+    #[serde(with = "crate::lotus_json")]
+    pub approved: Vec<Address>,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct MessageLookup {
@@ -218,37 +281,40 @@ impl HasLotusJson for MinerPower {
     }
 }
 
+/// OpenRPC document describing the RPC methods
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DiscoverResult {
-    info: DiscoverInfo,
-    methods: Vec<DiscoverMethod>,
-    openrpc: String,
+    pub info: DiscoverInfo,
+    pub methods: Vec<DiscoverMethod>,
+    pub openrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DiscoverMethod {
-    deprecated: bool,
-    description: String,
-    external_docs: DiscoverDocs,
-    name: String,
-    param_structure: String,
-    params: Value,
-    // Missing 'result' field. Tracking issue:
-    // https://github.com/ChainSafe/forest/issues/3585
-    summary: String,
+    pub deprecated: bool,
+    pub description: String,
+    pub external_docs: DiscoverDocs,
+    pub name: String,
+    pub param_structure: String,
+    pub params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    pub summary: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DiscoverDocs {
-    description: String,
-    url: String,
+    pub description: String,
+    pub url: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DiscoverInfo {
-    title: String,
-    version: String,
+    pub title: String,
+    pub version: String,
 }
 
 lotus_json_with_self!(DiscoverResult, DiscoverMethod, DiscoverDocs, DiscoverInfo);
@@ -370,3 +436,262 @@ pub struct SectorOnChainInfo {
 }
 
 lotus_json_with_self!(SectorOnChainInfo);
+
+impl From<crate::shim::miner::SectorOnChainInfo> for SectorOnChainInfo {
+    fn from(info: crate::shim::miner::SectorOnChainInfo) -> Self {
+        SectorOnChainInfo {
+            sector_number: info.sector_number,
+            seal_proof: info.seal_proof,
+            sealed_cid: info.sealed_cid,
+            deal_ids: info.deal_ids,
+            activation: info.activation,
+            expiration: info.expiration,
+            deal_weight: info.deal_weight,
+            verified_deal_weight: info.verified_deal_weight,
+            initial_pledge: info.initial_pledge,
+            expected_day_reward: info.expected_day_reward,
+            expected_storage_pledge: info.expected_storage_pledge,
+            replaced_sector_age: info.replaced_sector_age,
+            replaced_day_reward: info.replaced_day_reward,
+            sector_key_cid: info.sector_key_cid,
+            simple_qa_power: info.simple_qa_power,
+        }
+    }
+}
+
+/// Parameters of a pre-committed sector, in the layout of Lotus'
+/// `StateSectorPreCommitInfo`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiSectorPreCommitInfo {
+    pub seal_proof: RegisteredSealProof,
+    pub sector_number: SectorNumber,
+    #[serde(with = "crate::lotus_json")]
+    #[serde(rename = "SealedCID")]
+    pub sealed_cid: Cid,
+    pub seal_rand_epoch: ChainEpoch,
+    #[serde(rename = "DealIDs")]
+    #[serde(with = "crate::lotus_json")]
+    pub deal_ids: Vec<DealID>,
+    pub expiration: ChainEpoch,
+    #[serde(with = "crate::lotus_json")]
+    pub unsealed_cid: Option<Cid>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiSectorPreCommitOnChainInfo {
+    pub info: ApiSectorPreCommitInfo,
+    #[serde(with = "crate::lotus_json")]
+    pub pre_commit_deposit: TokenAmount,
+    pub pre_commit_epoch: ChainEpoch,
+}
+
+impl From<crate::shim::miner::SectorPreCommitOnChainInfo> for ApiSectorPreCommitOnChainInfo {
+    fn from(pre_commit: crate::shim::miner::SectorPreCommitOnChainInfo) -> Self {
+        let info = pre_commit.info;
+        ApiSectorPreCommitOnChainInfo {
+            info: ApiSectorPreCommitInfo {
+                seal_proof: info.seal_proof,
+                sector_number: info.sector_number,
+                sealed_cid: info.sealed_cid,
+                seal_rand_epoch: info.seal_rand_epoch,
+                deal_ids: info.deal_ids,
+                expiration: info.expiration,
+                unsealed_cid: info.unsealed_cid,
+            },
+            pre_commit_deposit: pre_commit.pre_commit_deposit,
+            pre_commit_epoch: pre_commit.pre_commit_epoch,
+        }
+    }
+}
+
+/// Expiration epochs of a sector, in the layout of Lotus'
+/// `StateSectorExpiration`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiSectorExpiration {
+    pub on_time: ChainEpoch,
+    pub early: ChainEpoch,
+}
+
+impl From<crate::shim::miner::SectorExpiration> for ApiSectorExpiration {
+    fn from(expiration: crate::shim::miner::SectorExpiration) -> Self {
+        ApiSectorExpiration {
+            on_time: expiration.on_time,
+            early: expiration.early,
+        }
+    }
+}
+
+/// Data cap allocated by a client to a provider, in the layout of Lotus'
+/// `StateGetAllocation`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiAllocation {
+    pub client: u64,
+    pub provider: u64,
+    #[serde(with = "crate::lotus_json")]
+    pub data: Cid,
+    pub size: u64,
+    pub term_min: ChainEpoch,
+    pub term_max: ChainEpoch,
+    pub expiration: ChainEpoch,
+}
+
+impl From<verifreg::Allocation> for ApiAllocation {
+    fn from(allocation: verifreg::Allocation) -> Self {
+        ApiAllocation {
+            client: allocation.client,
+            provider: allocation.provider,
+            data: allocation.data,
+            size: allocation.size,
+            term_min: allocation.term_min,
+            term_max: allocation.term_max,
+            expiration: allocation.expiration,
+        }
+    }
+}
+
+/// Allocation claimed by a provider, in the layout of Lotus' `StateGetClaim`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiClaim {
+    pub provider: u64,
+    pub client: u64,
+    #[serde(with = "crate::lotus_json")]
+    pub data: Cid,
+    pub size: u64,
+    pub term_min: ChainEpoch,
+    pub term_max: ChainEpoch,
+    pub term_start: ChainEpoch,
+    pub sector: u64,
+}
+
+impl From<verifreg::Claim> for ApiClaim {
+    fn from(claim: verifreg::Claim) -> Self {
+        ApiClaim {
+            provider: claim.provider,
+            client: claim.client,
+            data: claim.data,
+            size: claim.size,
+            term_min: claim.term_min,
+            term_max: claim.term_max,
+            term_start: claim.term_start,
+            sector: claim.sector,
+        }
+    }
+}
+
+/// Sectors of a partition of a miner deadline, in the layout of Lotus'
+/// `StateMinerPartitions`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MinerPartition {
+    #[serde(with = "crate::lotus_json")]
+    pub all_sectors: BitField,
+    #[serde(with = "crate::lotus_json")]
+    pub faulty_sectors: BitField,
+    #[serde(with = "crate::lotus_json")]
+    pub recovering_sectors: BitField,
+    #[serde(with = "crate::lotus_json")]
+    pub live_sectors: BitField,
+    #[serde(with = "crate::lotus_json")]
+    pub active_sectors: BitField,
+}
+
+impl From<&miner::Partition<'_>> for MinerPartition {
+    fn from(partition: &miner::Partition) -> Self {
+        let recovering_sectors = match partition {
+            miner::Partition::V8(p) => &p.recoveries,
+            miner::Partition::V9(p) => &p.recoveries,
+            miner::Partition::V10(p) => &p.recoveries,
+            miner::Partition::V11(p) => &p.recoveries,
+            miner::Partition::V12(p) => &p.recoveries,
+        };
+        MinerPartition {
+            all_sectors: partition.all_sectors().clone(),
+            faulty_sectors: partition.faulty_sectors().clone(),
+            recovering_sectors: recovering_sectors.clone(),
+            live_sectors: partition.live_sectors(),
+            active_sectors: partition.active_sectors(),
+        }
+    }
+}
+
+/// Consensus parameters of the network. The fields follow Lotus'
+/// `StateGetNetworkParams`, but the upgrades are keyed by Forest's height
+/// names, which do not all match the Lotus ones.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+pub struct NetworkParams {
+    pub network_name: String,
+    pub block_delay_secs: u64,
+    #[serde(with = "crate::lotus_json")]
+    pub consensus_miner_min_power: BigInt,
+    pub supported_proof_types: Vec<RegisteredSealProof>,
+    pub pre_commit_challenge_delay: ChainEpoch,
+    /// Epochs of the network upgrades, keyed by `Upgrade<Height>Height`.
+    pub fork_upgrade_params: BTreeMap<String, ChainEpoch>,
+    #[serde(rename = "Eip155ChainID")]
+    pub eip155_chain_id: u32,
+}
+
+lotus_json_with_self!(NetworkParams);
+
+/// Parameters of the computation of the base fee.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+pub struct BaseFeeParams {
+    pub block_gas_limit: u64,
+    pub block_gas_target: u64,
+    #[serde(with = "crate::lotus_json")]
+    pub minimum_base_fee: TokenAmount,
+    pub base_fee_max_change_denom: i64,
+}
+
+lotus_json_with_self!(BaseFeeParams);
+
+impl BaseFeeParams {
+    pub fn new(params: &crate::shim::params::NetworkParams) -> Self {
+        Self {
+            block_gas_limit: params.block_gas_limit,
+            block_gas_target: params.block_gas_target,
+            minimum_base_fee: params.minimum_base_fee.clone(),
+            base_fee_max_change_denom: params.base_fee_max_change_denom,
+        }
+    }
+}
+
+/// Epochs `from_epoch..=to_epoch` of a network version.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+pub struct NetworkVersionRange {
+    pub from_epoch: ChainEpoch,
+    pub to_epoch: ChainEpoch,
+    pub network_version: NetworkVersion,
+}
+
+lotus_json_with_self!(NetworkVersionRange);
+
+impl NetworkParams {
+    pub fn new(network_name: String, params: &crate::shim::params::NetworkParams) -> Self {
+        Self {
+            network_name,
+            block_delay_secs: params.block_delay_secs.into(),
+            consensus_miner_min_power: params.consensus_miner_min_power.clone(),
+            supported_proof_types: params
+                .sector_sizes
+                .iter()
+                .map(|size| RegisteredSealProof::from_sector_size(*size, params.network_version))
+                .collect(),
+            pre_commit_challenge_delay: params.pre_commit_challenge_delay,
+            fork_upgrade_params: params
+                .upgrades
+                .iter()
+                .map(|upgrade| (format!("Upgrade{:?}Height", upgrade.height), upgrade.epoch))
+                .collect(),
+            eip155_chain_id: params.eth_chain_id,
+        }
+    }
+}
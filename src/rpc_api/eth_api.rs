@@ -0,0 +1,468 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Ethereum JSON-RPC API, for the FEVM tooling.
+//!
+//! Quantities, addresses, hashes and byte strings are `0x`-prefixed hex
+//! strings, see <https://ethereum.org/en/developers/docs/apis/json-rpc/#hex-encoding>.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::shim::address::{Address, Payload};
+use anyhow::Context as _;
+use cid::multihash::{Code, Multihash};
+use cid::Cid;
+use fvm_ipld_encoding::DAG_CBOR;
+use num::{BigInt, Num as _};
+use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+pub const ETH_ACCOUNTS: &str = "eth_accounts";
+pub const ETH_BLOCK_NUMBER: &str = "eth_blockNumber";
+pub const ETH_CHAIN_ID: &str = "eth_chainId";
+pub const ETH_NET_VERSION: &str = "net_version";
+pub const ETH_GAS_PRICE: &str = "eth_gasPrice";
+pub const ETH_GET_BALANCE: &str = "eth_getBalance";
+pub const ETH_GET_TRANSACTION_COUNT: &str = "eth_getTransactionCount";
+pub const ETH_GET_BLOCK_BY_NUMBER: &str = "eth_getBlockByNumber";
+pub const ETH_GET_BLOCK_BY_HASH: &str = "eth_getBlockByHash";
+pub const ETH_GET_TRANSACTION_BY_HASH: &str = "eth_getTransactionByHash";
+pub const ETH_GET_TRANSACTION_RECEIPT: &str = "eth_getTransactionReceipt";
+pub const ETH_GET_CODE: &str = "eth_getCode";
+pub const ETH_GET_STORAGE_AT: &str = "eth_getStorageAt";
+pub const ETH_CALL: &str = "eth_call";
+pub const ETH_ESTIMATE_GAS: &str = "eth_estimateGas";
+
+/// Namespace of the addresses managed by the Ethereum Address Manager actor,
+/// i.e. `f410` addresses.
+pub const EAM_NAMESPACE: u64 = 10;
+
+/// Method of the EVM actor invoking the contract code, `InvokeEVM` as in
+/// FRC-0042.
+pub const EVM_INVOKE_CONTRACT_METHOD: u64 = 3844450837;
+
+/// Method of the Ethereum Address Manager actor creating a contract from an
+/// Ethereum transaction, `CreateExternal`.
+pub const EAM_CREATE_EXTERNAL_METHOD: u64 = 4;
+
+/// Method of the EVM actor reading a storage slot of the contract.
+pub const EVM_GET_STORAGE_AT_METHOD: u64 = 5;
+
+/// Type of the transactions, they are all EIP-1559 ones.
+pub const EIP_1559_TX_TYPE: u64 = 2;
+
+/// Number of epochs behind the head of the `safe` block, as in Lotus.
+pub const SAFE_EPOCH_DELAY: i64 = 30;
+
+fn strip_hex_prefix(s: &str) -> anyhow::Result<&str> {
+    s.strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .with_context(|| format!("{s} is missing the 0x prefix"))
+}
+
+fn decode_hex<const N: usize>(s: &str) -> anyhow::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    hex::decode_to_slice(strip_hex_prefix(s)?, &mut bytes)
+        .with_context(|| format!("{s} isn't {N} hex-encoded bytes"))?;
+    Ok(bytes)
+}
+
+/// Unsigned quantity, such as a block number or a nonce.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
+pub struct EthUint64(pub u64);
+
+impl fmt::Display for EthUint64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
+impl FromStr for EthUint64 {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let n = u64::from_str_radix(strip_hex_prefix(s)?, 16)
+            .with_context(|| format!("{s} isn't a hex-encoded quantity"))?;
+        Ok(Self(n))
+    }
+}
+
+/// Arbitrary precision quantity, such as a balance in attoFIL.
+#[derive(Debug, Default, Clone, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
+pub struct EthBigInt(pub BigInt);
+
+impl fmt::Display for EthBigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
+impl FromStr for EthBigInt {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let n = BigInt::from_str_radix(strip_hex_prefix(s)?, 16)
+            .with_context(|| format!("{s} isn't a hex-encoded quantity"))?;
+        Ok(Self(n))
+    }
+}
+
+/// Unformatted byte string, such as the input of a call.
+#[derive(Debug, Default, Clone, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
+pub struct EthBytes(pub Vec<u8>);
+
+impl fmt::Display for EthBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(&self.0))
+    }
+}
+
+impl FromStr for EthBytes {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex::decode(strip_hex_prefix(s)?)
+            .with_context(|| format!("{s} isn't a hex-encoded byte string"))?;
+        Ok(Self(bytes))
+    }
+}
+
+/// 32-byte hash of a block or of a transaction. Those of the tipsets and of
+/// the messages are the digests of their CIDs, except for the messages signed
+/// with a delegated key, see [`crate::eth`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
+pub struct EthHash(pub [u8; 32]);
+
+impl EthHash {
+    pub fn from_cid(cid: &Cid) -> Self {
+        let mut hash = Self::default();
+        let digest = cid.hash().digest();
+        let len = digest.len().min(hash.0.len());
+        hash.0[..len].copy_from_slice(&digest[..len]);
+        hash
+    }
+
+    /// The CID of a DAG-CBOR object with this `Blake2b-256` digest, i.e. of a
+    /// Filecoin message.
+    pub fn to_cid(self) -> Cid {
+        let multihash = Multihash::wrap(Code::Blake2b256.into(), &self.0)
+            .expect("a 32-byte digest must fit in a multihash");
+        Cid::new_v1(DAG_CBOR, multihash)
+    }
+}
+
+impl fmt::Display for EthHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+impl FromStr for EthHash {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(decode_hex(s)?))
+    }
+}
+
+/// 20-byte Ethereum address. Actors without an `f410` address are given a
+/// masked ID address: `0xff`, zeros, then their ID in big-endian.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
+pub struct EthAddress(pub [u8; 20]);
+
+impl EthAddress {
+    const MASKED_ID_PREFIX: [u8; 12] = [0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    pub fn from_actor_id(id: u64) -> Self {
+        let mut addr = Self::default();
+        addr.0[..12].copy_from_slice(&Self::MASKED_ID_PREFIX);
+        addr.0[12..].copy_from_slice(&id.to_be_bytes());
+        addr
+    }
+
+    fn as_actor_id(&self) -> Option<u64> {
+        let (prefix, id) = self.0.split_at(12);
+        if prefix != Self::MASKED_ID_PREFIX {
+            return None;
+        }
+        Some(u64::from_be_bytes(id.try_into().ok()?))
+    }
+
+    /// Converts an ID or `f410` address, other addresses have to be resolved
+    /// to an ID first.
+    pub fn from_filecoin_address(addr: &Address) -> anyhow::Result<Self> {
+        match addr.payload() {
+            Payload::ID(id) => Ok(Self::from_actor_id(*id)),
+            Payload::Delegated(delegated) if delegated.namespace() == EAM_NAMESPACE => {
+                let subaddress: [u8; 20] = delegated
+                    .subaddress()
+                    .try_into()
+                    .with_context(|| format!("{addr} isn't a valid Ethereum address"))?;
+                Ok(Self(subaddress))
+            }
+            _ => anyhow::bail!("{addr} has no Ethereum equivalent"),
+        }
+    }
+
+    pub fn to_filecoin_address(self) -> anyhow::Result<Address> {
+        match self.as_actor_id() {
+            Some(id) => Ok(Address::new_id(id)),
+            None => Ok(Address::new_delegated(EAM_NAMESPACE, &self.0)?),
+        }
+    }
+}
+
+impl fmt::Display for EthAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+impl FromStr for EthAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(decode_hex(s)?))
+    }
+}
+
+/// Block parameter of the state queries. The `safe` block is
+/// [`SAFE_EPOCH_DELAY`] epochs behind the head, the `finalized` one the chain
+/// finality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
+pub enum BlockNumberOrPredefined {
+    Earliest,
+    Latest,
+    Pending,
+    Safe,
+    Finalized,
+    Number(EthUint64),
+}
+
+impl fmt::Display for BlockNumberOrPredefined {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Earliest => write!(f, "earliest"),
+            Self::Latest => write!(f, "latest"),
+            Self::Pending => write!(f, "pending"),
+            Self::Safe => write!(f, "safe"),
+            Self::Finalized => write!(f, "finalized"),
+            Self::Number(n) => n.fmt(f),
+        }
+    }
+}
+
+impl FromStr for BlockNumberOrPredefined {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "earliest" => Ok(Self::Earliest),
+            "latest" => Ok(Self::Latest),
+            "pending" => Ok(Self::Pending),
+            "safe" => Ok(Self::Safe),
+            "finalized" => Ok(Self::Finalized),
+            _ => Ok(Self::Number(s.parse()?)),
+        }
+    }
+}
+
+/// Transaction, built from a Filecoin message. Only the messages signed with a
+/// delegated key have a signature, the `v`, `r` and `s` of the others are
+/// zero as in Lotus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthTx {
+    pub hash: EthHash,
+    pub nonce: EthUint64,
+    /// Unset until the transaction is included in a block
+    pub block_hash: Option<EthHash>,
+    pub block_number: Option<EthUint64>,
+    pub transaction_index: Option<EthUint64>,
+    pub from: EthAddress,
+    /// Unset for the creation of a contract
+    pub to: Option<EthAddress>,
+    pub value: EthBigInt,
+    pub r#type: EthUint64,
+    pub input: EthBytes,
+    pub gas: EthUint64,
+    pub max_fee_per_gas: EthBigInt,
+    pub max_priority_fee_per_gas: EthBigInt,
+    pub chain_id: EthUint64,
+    pub v: EthBigInt,
+    pub r: EthBigInt,
+    pub s: EthBigInt,
+}
+
+/// Transactions of a block, either their hashes or the full transactions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EthBlockTransactions {
+    Hashes(Vec<EthHash>),
+    Full(Vec<EthTx>),
+}
+
+/// Block, built from a tipset. The state root and the gas used are those of
+/// the execution of the tipset. The fields without a Filecoin equivalent have
+/// the values of an empty Ethereum block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthBlock {
+    pub hash: EthHash,
+    pub parent_hash: EthHash,
+    pub sha3_uncles: EthHash,
+    pub miner: EthAddress,
+    pub state_root: EthHash,
+    pub transactions_root: EthHash,
+    pub receipts_root: EthHash,
+    pub logs_bloom: EthBytes,
+    pub difficulty: EthUint64,
+    pub total_difficulty: EthUint64,
+    pub number: EthUint64,
+    pub gas_limit: EthUint64,
+    pub gas_used: EthUint64,
+    pub timestamp: EthUint64,
+    pub extra_data: EthBytes,
+    pub mix_hash: EthHash,
+    pub nonce: EthBytes,
+    pub base_fee_per_gas: EthBigInt,
+    pub size: EthUint64,
+    pub transactions: EthBlockTransactions,
+    pub uncles: Vec<EthHash>,
+}
+
+/// Parameters of `eth_estimateGas`, the block defaults to the latest one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EthEstimateGasParams {
+    AtBlock(EthCallMessage, BlockNumberOrPredefined),
+    Latest((EthCallMessage,)),
+}
+
+/// Receipt of an executed transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthTxReceipt {
+    pub transaction_hash: EthHash,
+    pub transaction_index: EthUint64,
+    pub block_hash: EthHash,
+    pub block_number: EthUint64,
+    pub from: EthAddress,
+    pub to: Option<EthAddress>,
+    /// `0x1` on success, `0x0` on failure
+    pub status: EthUint64,
+    /// Set for the successful creation of a contract
+    pub contract_address: Option<EthAddress>,
+    pub cumulative_gas_used: EthUint64,
+    pub gas_used: EthUint64,
+    pub effective_gas_price: EthBigInt,
+    pub logs_bloom: EthBytes,
+    pub logs: Vec<EthLog>,
+    pub r#type: EthUint64,
+}
+
+/// Event emitted by a contract.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthLog {
+    pub address: EthAddress,
+    pub data: EthBytes,
+    pub topics: Vec<EthHash>,
+    pub removed: bool,
+    pub log_index: EthUint64,
+    pub transaction_index: EthUint64,
+    pub transaction_hash: EthHash,
+    pub block_hash: EthHash,
+    pub block_number: EthUint64,
+}
+
+/// Message of `eth_call` and `eth_estimateGas`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EthCallMessage {
+    pub from: Option<EthAddress>,
+    pub to: Option<EthAddress>,
+    pub gas: Option<EthUint64>,
+    pub gas_price: Option<EthBigInt>,
+    pub value: Option<EthBigInt>,
+    #[serde(alias = "input")]
+    pub data: Option<EthBytes>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantities_are_hex_encoded() {
+        assert_eq!(EthUint64(0).to_string(), "0x0");
+        assert_eq!(EthUint64(314159).to_string(), "0x4cb2f");
+        assert_eq!("0x4cb2f".parse::<EthUint64>().unwrap(), EthUint64(314159));
+        assert!("4cb2f".parse::<EthUint64>().is_err());
+        let balance = EthBigInt(BigInt::from(10).pow(20));
+        assert_eq!(balance.to_string(), "0x56bc75e2d63100000");
+        assert_eq!(balance.to_string().parse::<EthBigInt>().unwrap(), balance);
+    }
+
+    #[test]
+    fn masked_id_addresses_round_trip() {
+        let addr = EthAddress::from_filecoin_address(&Address::new_id(1024)).unwrap();
+        assert_eq!(
+            addr.to_string(),
+            "0xff00000000000000000000000000000000000400"
+        );
+        assert_eq!(addr.to_filecoin_address().unwrap(), Address::new_id(1024));
+    }
+
+    #[test]
+    fn delegated_addresses_round_trip() {
+        let addr: EthAddress = "0xd4c5fb16488aa48081296299d54b0c648c9333da"
+            .parse()
+            .unwrap();
+        let filecoin_addr = addr.to_filecoin_address().unwrap();
+        assert_eq!(
+            filecoin_addr,
+            Address::new_delegated(EAM_NAMESPACE, &addr.0).unwrap()
+        );
+        assert_eq!(
+            EthAddress::from_filecoin_address(&filecoin_addr).unwrap(),
+            addr
+        );
+        assert!(EthAddress::from_filecoin_address(&Address::new_actor(b"actor")).is_err());
+    }
+
+    #[test]
+    fn block_parameter_of_gas_estimations_is_optional() {
+        let params: EthEstimateGasParams = serde_json::from_str(r#"[{}]"#).unwrap();
+        assert!(matches!(params, EthEstimateGasParams::Latest(_)));
+        let params: EthEstimateGasParams = serde_json::from_str(r#"[{}, "safe"]"#).unwrap();
+        assert!(matches!(
+            params,
+            EthEstimateGasParams::AtBlock(_, BlockNumberOrPredefined::Safe)
+        ));
+    }
+
+    #[test]
+    fn hashes_map_to_message_cids() {
+        let hash = EthHash([7; 32]);
+        assert_eq!(EthHash::from_cid(&hash.to_cid()), hash);
+    }
+
+    #[test]
+    fn block_parameters() {
+        assert_eq!(
+            "latest".parse::<BlockNumberOrPredefined>().unwrap(),
+            BlockNumberOrPredefined::Latest
+        );
+        assert_eq!(
+            "0x10".parse::<BlockNumberOrPredefined>().unwrap(),
+            BlockNumberOrPredefined::Number(EthUint64(16))
+        );
+        assert_eq!(
+            "finalized".parse::<BlockNumberOrPredefined>().unwrap(),
+            BlockNumberOrPredefined::Finalized
+        );
+        assert!("unsafe".parse::<BlockNumberOrPredefined>().is_err());
+    }
+}
@@ -13,6 +13,8 @@ use ahash::{HashMap, HashMapExt};
 use once_cell::sync::Lazy;
 
 pub mod data_types;
+pub mod eth_api;
+pub mod openrpc;
 
 /// Access levels to be checked against JWT claims
 pub enum Access {
@@ -30,6 +32,8 @@ pub static ACCESS_MAP: Lazy<HashMap<&str, Access>> = Lazy::new(|| {
     // Auth API
     access.insert(auth_api::AUTH_NEW, Access::Admin);
     access.insert(auth_api::AUTH_VERIFY, Access::Read);
+    access.insert(auth_api::AUTH_LIST, Access::Admin);
+    access.insert(auth_api::AUTH_REVOKE, Access::Admin);
 
     // Beacon API
     access.insert(beacon_api::BEACON_GET_ENTRY, Access::Read);
@@ -48,16 +52,25 @@ pub static ACCESS_MAP: Lazy<HashMap<&str, Access>> = Lazy::new(|| {
     access.insert(chain_api::CHAIN_SET_HEAD, Access::Admin);
     access.insert(chain_api::CHAIN_GET_MIN_BASE_FEE, Access::Admin);
     access.insert(chain_api::CHAIN_GET_MESSAGES_IN_TIPSET, Access::Read);
+    access.insert(chain_api::CHAIN_NOTIFY, Access::Read);
+    access.insert(chain_api::CHAIN_GET_PATH, Access::Read);
 
     // Message Pool API
     access.insert(mpool_api::MPOOL_PENDING, Access::Read);
     access.insert(mpool_api::MPOOL_PUSH, Access::Write);
     access.insert(mpool_api::MPOOL_PUSH_MESSAGE, Access::Sign);
+    access.insert(mpool_api::MPOOL_PUSH_UNTRUSTED, Access::Write);
+    access.insert(mpool_api::MPOOL_BATCH_PUSH, Access::Write);
+    access.insert(mpool_api::MPOOL_BATCH_PUSH_UNTRUSTED, Access::Write);
+    access.insert(mpool_api::MPOOL_SUB, Access::Read);
 
     // Sync API
     access.insert(sync_api::SYNC_CHECK_BAD, Access::Read);
     access.insert(sync_api::SYNC_MARK_BAD, Access::Admin);
+    access.insert(sync_api::SYNC_UNMARK_BAD, Access::Admin);
+    access.insert(sync_api::SYNC_UNMARK_ALL_BAD, Access::Admin);
     access.insert(sync_api::SYNC_STATE, Access::Read);
+    access.insert(sync_api::SYNC_SUBMIT_BLOCK, Access::Write);
 
     // Wallet API
     access.insert(wallet_api::WALLET_BALANCE, Access::Write);
@@ -75,15 +88,50 @@ pub static ACCESS_MAP: Lazy<HashMap<&str, Access>> = Lazy::new(|| {
 
     // State API
     access.insert(state_api::STATE_CALL, Access::Read);
+    access.insert(state_api::STATE_CALL_WITH_OVERRIDES, Access::Read);
     access.insert(state_api::STATE_REPLAY, Access::Read);
+    access.insert(state_api::STATE_REPLAY_EX, Access::Read);
+    access.insert(state_api::STATE_COMPUTE, Access::Read);
+    access.insert(state_api::STATE_COMPUTE_TRACED, Access::Read);
+    access.insert(state_api::MSIG_GET_AVAILABLE_BALANCE, Access::Read);
+    access.insert(state_api::MSIG_GET_VESTED, Access::Read);
+    access.insert(state_api::MSIG_GET_PENDING, Access::Read);
     access.insert(state_api::STATE_GET_ACTOR, Access::Read);
     access.insert(state_api::STATE_MARKET_BALANCE, Access::Read);
     access.insert(state_api::STATE_MARKET_DEALS, Access::Read);
+    access.insert(state_api::STATE_MARKET_DEALS_PAGE, Access::Read);
+    access.insert(state_api::STATE_MARKET_STORAGE_DEAL, Access::Read);
     access.insert(state_api::STATE_GET_RECEIPT, Access::Read);
     access.insert(state_api::STATE_WAIT_MSG, Access::Read);
+    access.insert(state_api::STATE_SEARCH_MSG, Access::Read);
     access.insert(state_api::STATE_NETWORK_NAME, Access::Read);
     access.insert(state_api::STATE_NETWORK_VERSION, Access::Read);
+    access.insert(state_api::STATE_GET_NETWORK_PARAMS, Access::Read);
+    access.insert(state_api::STATE_NETWORK_VERSIONS, Access::Read);
+    access.insert(state_api::STATE_BASE_FEE_PARAMS, Access::Read);
     access.insert(state_api::STATE_FETCH_ROOT, Access::Read);
+    access.insert(state_api::STATE_GET_RANDOMNESS_FROM_BEACON, Access::Read);
+    access.insert(state_api::STATE_GET_RANDOMNESS_FROM_TICKETS, Access::Read);
+    access.insert(state_api::STATE_TIPSET_REWARDS, Access::Read);
+    access.insert(state_api::STATE_MINER_ACTIVE_SECTORS, Access::Read);
+    access.insert(state_api::STATE_MINER_SECTORS, Access::Read);
+    access.insert(state_api::STATE_SECTOR_GET_INFO, Access::Read);
+    access.insert(state_api::STATE_SECTOR_PRE_COMMIT_INFO, Access::Read);
+    access.insert(state_api::STATE_SECTOR_EXPIRATION, Access::Read);
+    access.insert(state_api::STATE_MINER_PARTITIONS, Access::Read);
+    access.insert(state_api::STATE_DECODE_PARAMS, Access::Read);
+    access.insert(state_api::STATE_ENCODE_PARAMS, Access::Read);
+    access.insert(state_api::STATE_BUILTIN_ACTOR, Access::Read);
+    access.insert(state_api::STATE_LIST_ACTORS_PAGE, Access::Read);
+    access.insert(state_api::STATE_VERIFIED_CLIENT_STATUS, Access::Read);
+    access.insert(state_api::STATE_VERIFIER_STATUS, Access::Read);
+    access.insert(state_api::STATE_GET_ALLOCATION, Access::Read);
+    access.insert(state_api::STATE_GET_CLAIM, Access::Read);
+    access.insert(state_api::STATE_CIRCULATING_SUPPLY, Access::Read);
+    access.insert(
+        state_api::STATE_VM_CIRCULATING_SUPPLY_INTERNAL,
+        Access::Read,
+    );
 
     // Gas API
     access.insert(gas_api::GAS_ESTIMATE_GAS_LIMIT, Access::Read);
@@ -93,6 +141,8 @@ pub static ACCESS_MAP: Lazy<HashMap<&str, Access>> = Lazy::new(|| {
 
     // Common API
     access.insert(common_api::VERSION, Access::Read);
+    access.insert(common_api::DISCOVER, Access::Read);
+    access.insert(common_api::RPC_DISCOVER, Access::Read);
     access.insert(common_api::SHUTDOWN, Access::Admin);
     access.insert(common_api::START_TIME, Access::Read);
 
@@ -102,15 +152,65 @@ pub static ACCESS_MAP: Lazy<HashMap<&str, Access>> = Lazy::new(|| {
     access.insert(net_api::NET_INFO, Access::Read);
     access.insert(net_api::NET_CONNECT, Access::Write);
     access.insert(net_api::NET_DISCONNECT, Access::Write);
+    access.insert(net_api::NET_REACHABILITY, Access::Read);
+    access.insert(net_api::NET_PUBSUB_SCORES, Access::Read);
+    access.insert(net_api::NET_PEER_INFO, Access::Read);
+    access.insert(net_api::NET_AGENT_VERSION, Access::Read);
+    access.insert(net_api::NET_BAN_PEER, Access::Admin);
+    access.insert(net_api::NET_UNBAN_PEER, Access::Admin);
+    access.insert(net_api::NET_PROTECT_ADD, Access::Admin);
+    access.insert(net_api::NET_PROTECT_REMOVE, Access::Admin);
+    access.insert(net_api::NET_PROTECT_LIST, Access::Read);
+    access.insert(net_api::NET_FIND_PEER, Access::Read);
 
     // DB API
     access.insert(db_api::DB_GC, Access::Write);
+    access.insert(db_api::DB_SIZE, Access::Read);
 
     // Progress API
     access.insert(progress_api::GET_PROGRESS, Access::Read);
     // Node API
     access.insert(node_api::NODE_STATUS, Access::Read);
 
+    // F3 API
+    access.insert(f3_api::F3_GET_MANIFEST, Access::Read);
+    access.insert(f3_api::F3_GET_POWER_TABLE, Access::Read);
+    access.insert(f3_api::F3_SIGN, Access::Sign);
+    access.insert(f3_api::F3_GET_CERTIFICATE, Access::Read);
+    access.insert(f3_api::F3_GET_LATEST_CERTIFICATE, Access::Read);
+    access.insert(f3_api::F3_PUT_CERTIFICATE, Access::Admin);
+
+    // Log API
+    access.insert(log_api::LOG_LIST, Access::Read);
+    access.insert(log_api::LOG_SET_LEVEL, Access::Admin);
+    access.insert(log_api::LOG_RESET, Access::Admin);
+
+    // Payment channel API
+    access.insert(paych_api::PAYCH_LIST, Access::Read);
+    access.insert(paych_api::PAYCH_STATUS, Access::Read);
+    access.insert(paych_api::STATE_PAYCH_VOUCHERS_CHECK, Access::Read);
+
+    // Actor events API
+    access.insert(actor_events_api::GET_ACTOR_EVENTS_RAW, Access::Read);
+    access.insert(actor_events_api::SUBSCRIBE_ACTOR_EVENTS_RAW, Access::Read);
+
+    // Eth API
+    access.insert(eth_api::ETH_ACCOUNTS, Access::Read);
+    access.insert(eth_api::ETH_BLOCK_NUMBER, Access::Read);
+    access.insert(eth_api::ETH_CHAIN_ID, Access::Read);
+    access.insert(eth_api::ETH_NET_VERSION, Access::Read);
+    access.insert(eth_api::ETH_GAS_PRICE, Access::Read);
+    access.insert(eth_api::ETH_GET_BALANCE, Access::Read);
+    access.insert(eth_api::ETH_GET_TRANSACTION_COUNT, Access::Read);
+    access.insert(eth_api::ETH_GET_BLOCK_BY_NUMBER, Access::Read);
+    access.insert(eth_api::ETH_GET_BLOCK_BY_HASH, Access::Read);
+    access.insert(eth_api::ETH_GET_TRANSACTION_BY_HASH, Access::Read);
+    access.insert(eth_api::ETH_GET_TRANSACTION_RECEIPT, Access::Read);
+    access.insert(eth_api::ETH_GET_CODE, Access::Read);
+    access.insert(eth_api::ETH_GET_STORAGE_AT, Access::Read);
+    access.insert(eth_api::ETH_CALL, Access::Read);
+    access.insert(eth_api::ETH_ESTIMATE_GAS, Access::Read);
+
     access
 });
 
@@ -145,6 +245,8 @@ pub mod auth_api {
     lotus_json_with_self!(AuthNewParams);
 
     pub const AUTH_VERIFY: &str = "Filecoin.AuthVerify";
+    pub const AUTH_LIST: &str = "Forest.AuthList";
+    pub const AUTH_REVOKE: &str = "Forest.AuthRevoke";
 }
 
 /// Beacon API
@@ -156,7 +258,7 @@ pub mod beacon_api {
 pub mod chain_api {
     use std::path::PathBuf;
 
-    use crate::blocks::TipsetKeys;
+    use crate::blocks::{Tipset, TipsetKeys};
     use crate::lotus_json::lotus_json_with_self;
     use crate::shim::clock::ChainEpoch;
     use serde::{Deserialize, Serialize};
@@ -192,20 +294,82 @@ pub mod chain_api {
     pub const CHAIN_GET_MIN_BASE_FEE: &str = "Filecoin.ChainGetMinBaseFee";
     pub const CHAIN_GET_MESSAGES_IN_TIPSET: &str = "Filecoin.ChainGetMessagesInTipset";
     pub const CHAIN_GET_PARENT_MESSAGES: &str = "Filecoin.ChainGetParentMessages";
+
+    /// Streams the head changes, only over WebSocket. The first notification
+    /// is the current head.
+    pub const CHAIN_NOTIFY: &str = "Filecoin.ChainNotify";
+    pub const CHAIN_GET_PATH: &str = "Filecoin.ChainGetPath";
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum HeadChangeType {
+        Current,
+        Apply,
+        Revert,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct ApiHeadChange {
+        pub r#type: HeadChangeType,
+        #[serde(with = "crate::lotus_json")]
+        pub val: Tipset,
+    }
 }
 
 /// Message Pool API
 pub mod mpool_api {
+    use fvm_ipld_encoding::repr::{Deserialize_repr, Serialize_repr};
+    use serde::{Deserialize, Serialize};
+
+    use crate::message::SignedMessage;
+    use crate::message_pool::MpoolUpdate;
+
     pub const MPOOL_PENDING: &str = "Filecoin.MpoolPending";
     pub const MPOOL_PUSH: &str = "Filecoin.MpoolPush";
     pub const MPOOL_PUSH_MESSAGE: &str = "Filecoin.MpoolPushMessage";
+    pub const MPOOL_PUSH_UNTRUSTED: &str = "Filecoin.MpoolPushUntrusted";
+    pub const MPOOL_BATCH_PUSH: &str = "Filecoin.MpoolBatchPush";
+    pub const MPOOL_BATCH_PUSH_UNTRUSTED: &str = "Filecoin.MpoolBatchPushUntrusted";
+
+    /// Streams the messages added to and removed from the pool, only over
+    /// WebSocket.
+    pub const MPOOL_SUB: &str = "Filecoin.MpoolSub";
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+    #[repr(u8)]
+    pub enum MpoolChange {
+        Add = 0,
+        Remove = 1,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct ApiMpoolUpdate {
+        pub r#type: MpoolChange,
+        #[serde(with = "crate::lotus_json")]
+        pub message: SignedMessage,
+    }
+
+    impl From<MpoolUpdate> for ApiMpoolUpdate {
+        fn from(update: MpoolUpdate) -> Self {
+            let (r#type, message) = match update {
+                MpoolUpdate::Add(message) => (MpoolChange::Add, message),
+                MpoolUpdate::Remove(message) => (MpoolChange::Remove, message),
+            };
+            ApiMpoolUpdate { r#type, message }
+        }
+    }
 }
 
 /// Sync API
 pub mod sync_api {
     pub const SYNC_CHECK_BAD: &str = "Filecoin.SyncCheckBad";
     pub const SYNC_MARK_BAD: &str = "Filecoin.SyncMarkBad";
+    pub const SYNC_UNMARK_BAD: &str = "Filecoin.SyncUnmarkBad";
+    pub const SYNC_UNMARK_ALL_BAD: &str = "Filecoin.SyncUnmarkAllBad";
     pub const SYNC_STATE: &str = "Filecoin.SyncState";
+    pub const SYNC_SUBMIT_BLOCK: &str = "Filecoin.SyncSubmitBlock";
 }
 
 /// Wallet API
@@ -226,19 +390,56 @@ pub mod wallet_api {
 /// State API
 pub mod state_api {
     pub const STATE_CALL: &str = "Filecoin.StateCall";
+    /// [`STATE_CALL`] with actor state overrides, not in Lotus.
+    pub const STATE_CALL_WITH_OVERRIDES: &str = "Forest.StateCallWithOverrides";
     pub const STATE_REPLAY: &str = "Filecoin.StateReplay";
+    /// [`STATE_REPLAY`] with an optional tipset, not in Lotus.
+    pub const STATE_REPLAY_EX: &str = "Forest.StateReplayEx";
+    pub const STATE_COMPUTE: &str = "Filecoin.StateCompute";
+    /// [`STATE_COMPUTE`] with the execution traces of the messages, not in Lotus.
+    pub const STATE_COMPUTE_TRACED: &str = "Forest.StateComputeTraced";
+    pub const MSIG_GET_AVAILABLE_BALANCE: &str = "Filecoin.MsigGetAvailableBalance";
+    pub const MSIG_GET_VESTED: &str = "Filecoin.MsigGetVested";
+    pub const MSIG_GET_PENDING: &str = "Filecoin.MsigGetPending";
     pub const STATE_NETWORK_NAME: &str = "Filecoin.StateNetworkName";
     pub const STATE_NETWORK_VERSION: &str = "Filecoin.StateNetworkVersion";
+    pub const STATE_GET_NETWORK_PARAMS: &str = "Filecoin.StateGetNetworkParams";
+    /// Network versions of a range of epochs, not in Lotus.
+    pub const STATE_NETWORK_VERSIONS: &str = "Forest.StateNetworkVersions";
+    /// Parameters of the computation of the base fee, not in Lotus.
+    pub const STATE_BASE_FEE_PARAMS: &str = "Forest.StateBaseFeeParams";
     pub const STATE_GET_ACTOR: &str = "Filecoin.StateGetActor";
     pub const STATE_MARKET_BALANCE: &str = "Filecoin.StateMarketBalance";
     pub const STATE_MARKET_DEALS: &str = "Filecoin.StateMarketDeals";
+    pub const STATE_MARKET_DEALS_PAGE: &str = "Forest.StateMarketDealsPage";
+    pub const STATE_MARKET_STORAGE_DEAL: &str = "Filecoin.StateMarketStorageDeal";
     pub const STATE_GET_RECEIPT: &str = "Filecoin.StateGetReceipt";
     pub const STATE_WAIT_MSG: &str = "Filecoin.StateWaitMsg";
+    pub const STATE_SEARCH_MSG: &str = "Filecoin.StateSearchMsg";
     pub const STATE_FETCH_ROOT: &str = "Filecoin.StateFetchRoot";
+    /// Rewards and burns of a tipset by block, not in Lotus.
+    pub const STATE_TIPSET_REWARDS: &str = "Forest.StateTipsetRewards";
     pub const STATE_MINOR_POWER: &str = "Filecoin.StateMinerPower";
     pub const STATE_GET_RANDOMNESS_FROM_BEACON: &str = "Filecoin.StateGetRandomnessFromBeacon";
+    pub const STATE_GET_RANDOMNESS_FROM_TICKETS: &str = "Filecoin.StateGetRandomnessFromTickets";
     pub const STATE_READ_STATE: &str = "Filecoin.StateReadState";
     pub const STATE_MINER_ACTIVE_SECTORS: &str = "Filecoin.StateMinerActiveSectors";
+    pub const STATE_MINER_SECTORS: &str = "Filecoin.StateMinerSectors";
+    pub const STATE_MINER_PARTITIONS: &str = "Filecoin.StateMinerPartitions";
+    pub const STATE_SECTOR_GET_INFO: &str = "Filecoin.StateSectorGetInfo";
+    pub const STATE_SECTOR_PRE_COMMIT_INFO: &str = "Filecoin.StateSectorPreCommitInfo";
+    pub const STATE_SECTOR_EXPIRATION: &str = "Filecoin.StateSectorExpiration";
+    pub const STATE_DECODE_PARAMS: &str = "Filecoin.StateDecodeParams";
+    pub const STATE_ENCODE_PARAMS: &str = "Filecoin.StateEncodeParams";
+    pub const STATE_BUILTIN_ACTOR: &str = "Forest.StateBuiltinActor";
+    pub const STATE_LIST_ACTORS_PAGE: &str = "Forest.StateListActorsPage";
+    pub const STATE_VERIFIED_CLIENT_STATUS: &str = "Filecoin.StateVerifiedClientStatus";
+    pub const STATE_VERIFIER_STATUS: &str = "Filecoin.StateVerifierStatus";
+    pub const STATE_GET_ALLOCATION: &str = "Filecoin.StateGetAllocation";
+    pub const STATE_GET_CLAIM: &str = "Filecoin.StateGetClaim";
+    pub const STATE_CIRCULATING_SUPPLY: &str = "Filecoin.StateCirculatingSupply";
+    pub const STATE_VM_CIRCULATING_SUPPLY_INTERNAL: &str =
+        "Filecoin.StateVMCirculatingSupplyInternal";
 }
 
 /// Gas API
@@ -255,6 +456,7 @@ pub mod common_api {
     pub const SHUTDOWN: &str = "Filecoin.Shutdown";
     pub const START_TIME: &str = "Filecoin.StartTime";
     pub const DISCOVER: &str = "Filecoin.Discover";
+    pub const RPC_DISCOVER: &str = "rpc.discover";
     pub const SESSION: &str = "Filecoin.Session";
 }
 
@@ -297,11 +499,91 @@ pub mod net_api {
 
     pub const NET_CONNECT: &str = "Filecoin.NetConnect";
     pub const NET_DISCONNECT: &str = "Filecoin.NetDisconnect";
+
+    pub const NET_REACHABILITY: &str = "Filecoin.NetReachability";
+
+    /// Reachability of the node from the public internet
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum NatReachability {
+        /// Not determined yet, e.g. because too few peers were asked
+        Unknown,
+        Public,
+        /// Behind a NAT, only reachable through relays
+        Private,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct NetReachabilityResult {
+        pub reachability: NatReachability,
+        pub public_addrs: Vec<crate::libp2p::Multiaddr>,
+    }
+    lotus_json_with_self!(NetReachabilityResult);
+
+    pub const NET_PUBSUB_SCORES: &str = "Filecoin.NetPubsubScores";
+
+    /// Gossipsub score of a peer, in the format of Lotus. Only the total score
+    /// is available.
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct PubsubScore {
+        #[serde(rename = "ID")]
+        pub id: String,
+        pub score: PeerScoreSnapshot,
+    }
+    lotus_json_with_self!(PubsubScore);
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct PeerScoreSnapshot {
+        pub score: f64,
+    }
+
+    pub const NET_PEER_INFO: &str = "Filecoin.NetPeerInfo";
+    pub const NET_AGENT_VERSION: &str = "Filecoin.NetAgentVersion";
+
+    /// Whether a connection was opened by the remote peer or by the node
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum ConnectionDirection {
+        Inbound,
+        Outbound,
+    }
+
+    /// Details of a connected peer. Agent and protocols are empty until the
+    /// peer has been identified.
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct ExtendedPeerInfo {
+        #[serde(rename = "ID")]
+        pub id: String,
+        pub agent: String,
+        pub addrs: Vec<String>,
+        pub protocols: Vec<String>,
+        /// Direction of the first connection with the peer
+        pub direction: ConnectionDirection,
+        /// Round-trip time of the latest ping, in nanoseconds
+        pub latency: Option<u64>,
+        pub protected: bool,
+    }
+    lotus_json_with_self!(ExtendedPeerInfo);
+
+    /// Bans a peer, for a number of seconds or until unbanned
+    pub const NET_BAN_PEER: &str = "Filecoin.NetBanPeer";
+    pub const NET_UNBAN_PEER: &str = "Filecoin.NetUnbanPeer";
+
+    /// Protected peers are never banned
+    pub const NET_PROTECT_ADD: &str = "Filecoin.NetProtectAdd";
+    pub const NET_PROTECT_REMOVE: &str = "Filecoin.NetProtectRemove";
+    pub const NET_PROTECT_LIST: &str = "Filecoin.NetProtectList";
+
+    /// Looks up the addresses of a peer in the DHT
+    pub const NET_FIND_PEER: &str = "Filecoin.NetFindPeer";
 }
 
 /// DB API
 pub mod db_api {
     pub const DB_GC: &str = "Filecoin.DatabaseGarbageCollection";
+    pub const DB_SIZE: &str = "Forest.DbSize";
 }
 
 /// Progress API
@@ -326,34 +608,288 @@ pub mod node_api {
     pub const NODE_STATUS: &str = "Filecoin.NodeStatus";
     pub type NodeStatusResult = NodeStatus;
 
+    use std::collections::BTreeMap;
+
     use serde::{Deserialize, Serialize};
 
     use crate::lotus_json::lotus_json_with_self;
 
     #[derive(Debug, Serialize, Deserialize, Default)]
+    #[serde(rename_all = "PascalCase")]
     pub struct NodeSyncStatus {
         pub epoch: u64,
         pub behind: u64,
     }
 
     #[derive(Debug, Serialize, Deserialize, Default)]
+    #[serde(rename_all = "PascalCase")]
     pub struct NodePeerStatus {
         pub peers_to_publish_msgs: u32,
         pub peers_to_publish_blocks: u32,
+        /// Number of connected peers supporting each protocol, not in Lotus.
+        #[serde(default)]
+        pub peers_by_protocol: BTreeMap<String, u32>,
     }
 
     #[derive(Debug, Serialize, Deserialize, Default)]
+    #[serde(rename_all = "PascalCase")]
     pub struct NodeChainStatus {
         pub blocks_per_tipset_last_100: f64,
         pub blocks_per_tipset_last_finality: f64,
     }
 
+    #[derive(Debug, Serialize, Deserialize, Default)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct NodeMpoolStatus {
+        pub pending_messages: u64,
+        pub pending_senders: u64,
+        pub local_messages: u64,
+        /// Number of messages above which the pool is pruned.
+        pub size_limit: u64,
+    }
+
     #[derive(Debug, Deserialize, Default, Serialize)]
+    #[serde(rename_all = "PascalCase")]
     pub struct NodeStatus {
         pub sync_status: NodeSyncStatus,
         pub peer_status: NodePeerStatus,
         pub chain_status: NodeChainStatus,
+        /// Message pool status, not in Lotus.
+        #[serde(default)]
+        pub mpool_status: NodeMpoolStatus,
     }
 
     lotus_json_with_self!(NodeStatus);
 }
+
+/// F3 API
+pub mod f3_api {
+    pub const F3_GET_MANIFEST: &str = "Filecoin.F3GetManifest";
+    pub const F3_GET_POWER_TABLE: &str = "Filecoin.F3GetPowerTable";
+    pub const F3_SIGN: &str = "Filecoin.F3Sign";
+    pub const F3_GET_CERTIFICATE: &str = "Filecoin.F3GetCertificate";
+    pub const F3_GET_LATEST_CERTIFICATE: &str = "Filecoin.F3GetLatestCertificate";
+    pub const F3_PUT_CERTIFICATE: &str = "Filecoin.F3PutCertificate";
+}
+
+/// Log API
+pub mod log_api {
+    /// Log filter directives in effect, not in Lotus which lists its logging
+    /// subsystems instead.
+    pub const LOG_LIST: &str = "Forest.LogList";
+    pub type LogListResult = Vec<String>;
+
+    /// Sets the level of a target of the log filter, not in Lotus which sets
+    /// the level of a logging subsystem instead.
+    pub const LOG_SET_LEVEL: &str = "Forest.LogSetLevel";
+    pub type LogSetLevelParams = (String, String);
+
+    /// Restores the log filter the node was started with, not in Lotus.
+    pub const LOG_RESET: &str = "Forest.LogReset";
+}
+
+/// Actor events API
+pub mod actor_events_api {
+    use std::collections::BTreeMap;
+
+    use cid::Cid;
+    use serde::{Deserialize, Serialize};
+
+    use crate::blocks::TipsetKeys;
+    use crate::lotus_json::lotus_json_with_self;
+    use crate::shim::{address::Address, clock::ChainEpoch};
+
+    pub const GET_ACTOR_EVENTS_RAW: &str = "Filecoin.GetActorEventsRaw";
+    /// Streams the events matching a filter, only over WebSocket, from the
+    /// current head on.
+    pub const SUBSCRIBE_ACTOR_EVENTS_RAW: &str = "Filecoin.SubscribeActorEventsRaw";
+
+    /// Value of an event entry to match, encoded with `codec`.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct ActorEventBlock {
+        pub codec: u64,
+        #[serde(with = "crate::lotus_json")]
+        pub value: Vec<u8>,
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ActorEventFilter {
+        /// Emitters of the events, any if empty.
+        #[serde(with = "crate::lotus_json", default)]
+        pub addresses: Vec<Address>,
+        /// Entries that the events must have, by key. Any of the values of a
+        /// key matches.
+        #[serde(default)]
+        pub fields: BTreeMap<String, Vec<ActorEventBlock>>,
+        /// First epoch of the events, the one of the latest executed tipset
+        /// if unset.
+        #[serde(default)]
+        pub from_height: Option<ChainEpoch>,
+        /// Last epoch of the events, the one of the latest executed tipset
+        /// if unset.
+        #[serde(default)]
+        pub to_height: Option<ChainEpoch>,
+        /// Only the events of the messages of this tipset, exclusive with the
+        /// heights.
+        #[serde(with = "crate::lotus_json", default)]
+        pub tipset_key: Option<TipsetKeys>,
+    }
+
+    lotus_json_with_self!(ActorEventFilter);
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct ApiEventEntry {
+        pub flags: u64,
+        pub key: String,
+        pub codec: u64,
+        #[serde(with = "crate::lotus_json")]
+        pub value: Vec<u8>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ActorEvent {
+        pub entries: Vec<ApiEventEntry>,
+        /// Delegated address of the emitter if it has one, its ID address
+        /// otherwise.
+        #[serde(with = "crate::lotus_json")]
+        pub emitter: Address,
+        /// Whether the tipset of the message was reverted.
+        pub reverted: bool,
+        pub height: ChainEpoch,
+        #[serde(with = "crate::lotus_json")]
+        pub tipset_key: TipsetKeys,
+        #[serde(with = "crate::lotus_json")]
+        pub msg_cid: Cid,
+    }
+
+    lotus_json_with_self!(ActorEvent);
+}
+
+/// Payment channel API
+pub mod paych_api {
+    use fvm_ipld_encoding::{
+        repr::{Deserialize_repr, Serialize_repr},
+        RawBytes,
+    };
+    use serde::{Deserialize, Serialize};
+
+    use crate::lotus_json::lotus_json_with_self;
+    use crate::shim::{
+        address::Address,
+        clock::ChainEpoch,
+        crypto::Signature,
+        econ::TokenAmount,
+        message::MethodNum,
+        paych::{Merge, ModVerifyParams, SignedVoucher},
+    };
+
+    pub const PAYCH_LIST: &str = "Filecoin.PaychList";
+    pub const PAYCH_STATUS: &str = "Filecoin.PaychStatus";
+    /// Checks vouchers against the state of their channel, not in Lotus.
+    pub const STATE_PAYCH_VOUCHERS_CHECK: &str = "Forest.StatePaychVouchersCheck";
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+    #[repr(u8)]
+    pub enum PaychDirection {
+        Inbound = 1,
+        Outbound = 2,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct PaychStatus {
+        /// Wallet address of the local party of the channel.
+        #[serde(with = "crate::lotus_json")]
+        pub control_addr: Address,
+        pub direction: PaychDirection,
+    }
+
+    lotus_json_with_self!(PaychStatus);
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct ApiModVerifyParams {
+        #[serde(with = "crate::lotus_json")]
+        pub actor: Address,
+        pub method: MethodNum,
+        #[serde(with = "crate::lotus_json")]
+        pub data: RawBytes,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct ApiMerge {
+        pub lane: u64,
+        pub nonce: u64,
+    }
+
+    lotus_json_with_self!(ApiMerge);
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct ApiSignedVoucher {
+        #[serde(with = "crate::lotus_json")]
+        pub channel_addr: Address,
+        pub time_lock_min: ChainEpoch,
+        pub time_lock_max: ChainEpoch,
+        #[serde(with = "crate::lotus_json", default)]
+        pub secret_hash: Vec<u8>,
+        #[serde(default)]
+        pub extra: Option<ApiModVerifyParams>,
+        pub lane: u64,
+        pub nonce: u64,
+        #[serde(with = "crate::lotus_json")]
+        pub amount: TokenAmount,
+        pub min_settle_height: ChainEpoch,
+        #[serde(with = "crate::lotus_json", default)]
+        pub merges: Vec<ApiMerge>,
+        #[serde(with = "crate::lotus_json", default)]
+        pub signature: Option<Signature>,
+    }
+
+    lotus_json_with_self!(ApiSignedVoucher);
+
+    impl From<ApiSignedVoucher> for SignedVoucher {
+        fn from(voucher: ApiSignedVoucher) -> Self {
+            SignedVoucher {
+                channel_addr: voucher.channel_addr,
+                time_lock_min: voucher.time_lock_min,
+                time_lock_max: voucher.time_lock_max,
+                secret_pre_image: voucher.secret_hash,
+                extra: voucher.extra.map(|extra| ModVerifyParams {
+                    actor: extra.actor,
+                    method: extra.method,
+                    data: extra.data,
+                }),
+                lane: voucher.lane,
+                nonce: voucher.nonce,
+                amount: voucher.amount,
+                min_settle_height: voucher.min_settle_height,
+                merges: voucher
+                    .merges
+                    .into_iter()
+                    .map(|merge| Merge {
+                        lane: merge.lane,
+                        nonce: merge.nonce,
+                    })
+                    .collect(),
+                signature: voucher.signature,
+            }
+        }
+    }
+
+    /// Result of the check of a voucher.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct PaychVoucherCheck {
+        pub lane: u64,
+        pub nonce: u64,
+        /// Why the voucher can't be redeemed, `null` if it's valid.
+        pub error: Option<String>,
+    }
+
+    lotus_json_with_self!(PaychVoucherCheck);
+}
@@ -0,0 +1,169 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! [OpenRPC](https://spec.open-rpc.org) document of the RPC methods, served by
+//! `rpc.discover` and printed by `forest-tool api openrpc`.
+//!
+//! The methods are the ones of the [`ACCESS_MAP`], described by their name and
+//! permission. The methods listed in [`method_schemas`] also describe their
+//! parameters and result with [JSON schemas](schemars), the schemas of their
+//! lotus JSON representation.
+
+use cid::Cid;
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    schema::Schema,
+};
+use serde_json::{json, Value};
+
+use super::{
+    chain_api,
+    data_types::{DiscoverDocs, DiscoverInfo, DiscoverMethod, DiscoverResult},
+    state_api, wallet_api, Access, ACCESS_MAP,
+};
+use crate::lotus_json::LotusJson;
+use crate::shim::address::Address;
+
+const OPENRPC_VERSION: &str = "1.2.6";
+const LOTUS_API_DOCS: &str =
+    "https://github.com/filecoin-project/lotus/blob/master/documentation/en/api-v0-methods.md";
+/// Where the schemas shared by several methods are referenced from.
+const SCHEMAS_PATH: &str = "#/components/schemas/";
+
+/// Declares the parameters, by position, and the result of methods, with the
+/// types their handlers (de)serialize.
+macro_rules! method_schemas {
+    ($($method:path => ($($param:ident: $param_ty:ty),*) -> $result_ty:ty;)*) => {
+        /// Schemas of the parameters and of the result of the method `name`,
+        /// if they are known.
+        fn method_schemas(name: &str, gen: &mut SchemaGenerator) -> Option<(Value, Value)> {
+            match name {
+                $(
+                    $method => Some((
+                        json!([$(
+                            content_descriptor(
+                                stringify!($param),
+                                gen.subschema_for::<$param_ty>(),
+                            )
+                        ),*]),
+                        content_descriptor("result", gen.subschema_for::<$result_ty>()),
+                    )),
+                )*
+                _ => None,
+            }
+        }
+    };
+}
+
+method_schemas! {
+    chain_api::CHAIN_HAS_OBJ => (cid: LotusJson<Cid>) -> bool;
+    chain_api::CHAIN_READ_OBJ => (cid: LotusJson<Cid>) -> String;
+    state_api::STATE_NETWORK_NAME => () -> String;
+    wallet_api::WALLET_BALANCE => (address: String) -> String;
+    wallet_api::WALLET_DEFAULT_ADDRESS => () -> Option<String>;
+    wallet_api::WALLET_DELETE => (address: String) -> ();
+    wallet_api::WALLET_HAS => (address: String) -> bool;
+    wallet_api::WALLET_LIST => () -> LotusJson<Vec<Address>>;
+    wallet_api::WALLET_SET_DEFAULT => (address: LotusJson<Address>) -> ();
+}
+
+/// Builds the OpenRPC document of the RPC methods, sorted by name.
+pub fn document(forest_version: &str) -> DiscoverResult {
+    let mut gen = SchemaSettings::draft07()
+        .with(|settings| settings.definitions_path = SCHEMAS_PATH.into())
+        .into_generator();
+    let mut methods: Vec<_> = ACCESS_MAP
+        .iter()
+        .map(|(name, access)| method(name, access, &mut gen))
+        .collect();
+    methods.sort_by(|a, b| a.name.cmp(&b.name));
+    let schemas = gen.take_definitions();
+    DiscoverResult {
+        info: DiscoverInfo {
+            title: "Forest RPC API".into(),
+            version: forest_version.into(),
+        },
+        methods,
+        openrpc: OPENRPC_VERSION.into(),
+        components: (!schemas.is_empty()).then(|| json!({ "schemas": schemas })),
+    }
+}
+
+fn content_descriptor(name: &str, schema: Schema) -> Value {
+    json!({ "name": name, "required": true, "schema": schema })
+}
+
+fn method(name: &str, access: &Access, gen: &mut SchemaGenerator) -> DiscoverMethod {
+    let permission = match access {
+        Access::Admin => "admin",
+        Access::Sign => "sign",
+        Access::Write => "write",
+        Access::Read => "read",
+    };
+    let (params, result) = method_schemas(name, gen)
+        .unwrap_or_else(|| (json!([]), json!({ "name": "result", "schema": {} })));
+    DiscoverMethod {
+        deprecated: false,
+        description: format!("Requires the `{permission}` permission."),
+        external_docs: DiscoverDocs {
+            description: "Lotus API reference".into(),
+            url: LOTUS_API_DOCS.into(),
+        },
+        name: name.into(),
+        param_structure: "by-position".into(),
+        params,
+        result: Some(result),
+        summary: String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc_api::{chain_api::CHAIN_HEAD, common_api::RPC_DISCOVER};
+    use wallet_api::{WALLET_BALANCE, WALLET_LIST};
+
+    #[test]
+    fn document_lists_the_methods() {
+        let document = document("0.0.0");
+        assert_eq!(document.methods.len(), ACCESS_MAP.len());
+        assert!(document
+            .methods
+            .windows(2)
+            .all(|pair| pair[0].name < pair[1].name));
+        for name in [CHAIN_HEAD, RPC_DISCOVER] {
+            assert!(document.methods.iter().any(|method| method.name == name));
+        }
+
+        let json = serde_json::to_value(&document).unwrap();
+        assert_eq!(json["openrpc"], OPENRPC_VERSION);
+        assert_eq!(json["methods"][0]["paramStructure"], "by-position");
+    }
+
+    #[test]
+    fn document_has_schemas() {
+        let json = serde_json::to_value(document("0.0.0")).unwrap();
+        let method = |name| {
+            json["methods"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|method| method["name"] == name)
+                .unwrap()
+                .clone()
+        };
+
+        let balance = method(WALLET_BALANCE);
+        assert_eq!(balance["params"][0]["name"], "address");
+        assert_eq!(balance["params"][0]["schema"]["type"], "string");
+        assert_eq!(balance["result"]["schema"]["type"], "string");
+
+        // A list of addresses, `null` when empty
+        let list = method(WALLET_LIST);
+        assert_eq!(list["params"], json!([]));
+        assert_eq!(list["result"]["schema"]["type"], json!(["array", "null"]));
+
+        // Unknown schemas are left empty
+        assert_eq!(method(CHAIN_HEAD)["result"]["schema"], json!({}));
+    }
+}
@@ -1,6 +1,7 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use crate::auth::TokenInfo;
 use crate::rpc_api::auth_api::*;
 use chrono::Duration;
 
@@ -19,4 +20,20 @@ impl ApiInfo {
     pub fn auth_new_req(perms: Vec<String>, token_exp: Duration) -> RpcRequest<Vec<u8>> {
         RpcRequest::new(AUTH_NEW, AuthNewParams { perms, token_exp })
     }
+
+    pub async fn auth_list(&self) -> Result<Vec<TokenInfo>, JsonRpcError> {
+        self.call(Self::auth_list_req()).await
+    }
+
+    pub fn auth_list_req() -> RpcRequest<Vec<TokenInfo>> {
+        RpcRequest::new(AUTH_LIST, ())
+    }
+
+    pub async fn auth_revoke(&self, id: String) -> Result<bool, JsonRpcError> {
+        self.call(Self::auth_revoke_req(id)).await
+    }
+
+    pub fn auth_revoke_req(id: String) -> RpcRequest<bool> {
+        RpcRequest::new(AUTH_REVOKE, (id,))
+    }
 }
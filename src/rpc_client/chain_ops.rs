@@ -9,6 +9,7 @@ use crate::{
     shim::clock::ChainEpoch,
 };
 use cid::Cid;
+use futures::stream::BoxStream;
 
 use super::{ApiInfo, JsonRpcError, RpcRequest};
 
@@ -21,6 +22,16 @@ impl ApiInfo {
         RpcRequest::new(CHAIN_HEAD, ())
     }
 
+    pub async fn chain_notify(
+        &self,
+    ) -> anyhow::Result<BoxStream<'static, anyhow::Result<Vec<ApiHeadChange>>>> {
+        self.subscribe(Self::chain_notify_req()).await
+    }
+
+    pub fn chain_notify_req() -> RpcRequest<Vec<ApiHeadChange>> {
+        RpcRequest::new(CHAIN_NOTIFY, ())
+    }
+
     pub async fn chain_get_block(&self, cid: Cid) -> Result<BlockHeader, JsonRpcError> {
         self.call(Self::chain_get_block_req(cid)).await
     }
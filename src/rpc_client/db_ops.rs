@@ -13,4 +13,12 @@ impl ApiInfo {
     pub fn db_gc_req() -> RpcRequest<()> {
         RpcRequest::new(DB_GC, ())
     }
+
+    pub async fn db_size(&self) -> Result<u64, JsonRpcError> {
+        self.call(Self::db_size_req()).await
+    }
+
+    pub fn db_size_req() -> RpcRequest<u64> {
+        RpcRequest::new(DB_SIZE, ())
+    }
 }
@@ -0,0 +1,30 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::{
+    blocks::TipsetKeys,
+    rpc_api::{data_types::MessageSendSpec, gas_api::*},
+    shim::message::Message,
+};
+
+use super::{ApiInfo, JsonRpcError, RpcRequest};
+
+impl ApiInfo {
+    pub async fn gas_estimate_message_gas(
+        &self,
+        message: Message,
+        specs: Option<MessageSendSpec>,
+        tsk: TipsetKeys,
+    ) -> Result<Message, JsonRpcError> {
+        self.call(Self::gas_estimate_message_gas_req(message, specs, tsk))
+            .await
+    }
+
+    pub fn gas_estimate_message_gas_req(
+        message: Message,
+        specs: Option<MessageSendSpec>,
+        tsk: TipsetKeys,
+    ) -> RpcRequest<Message> {
+        RpcRequest::new(GAS_ESTIMATE_MESSAGE_GAS, (message, specs, tsk))
+    }
+}
@@ -0,0 +1,32 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::rpc_api::log_api::*;
+
+use super::{ApiInfo, JsonRpcError, RpcRequest};
+
+impl ApiInfo {
+    pub async fn log_list(&self) -> Result<LogListResult, JsonRpcError> {
+        self.call(Self::log_list_req()).await
+    }
+
+    pub fn log_list_req() -> RpcRequest<LogListResult> {
+        RpcRequest::new(LOG_LIST, ())
+    }
+
+    pub async fn log_set_level(&self, target: String, level: String) -> Result<(), JsonRpcError> {
+        self.call(Self::log_set_level_req(target, level)).await
+    }
+
+    pub fn log_set_level_req(target: String, level: String) -> RpcRequest<()> {
+        RpcRequest::new(LOG_SET_LEVEL, (target, level))
+    }
+
+    pub async fn log_reset(&self) -> Result<(), JsonRpcError> {
+        self.call(Self::log_reset_req()).await
+    }
+
+    pub fn log_reset_req() -> RpcRequest<()> {
+        RpcRequest::new(LOG_RESET, ())
+    }
+}
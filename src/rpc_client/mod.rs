@@ -5,6 +5,8 @@ pub mod auth_ops;
 pub mod chain_ops;
 pub mod common_ops;
 pub mod db_ops;
+pub mod gas_ops;
+pub mod log_ops;
 pub mod mpool_ops;
 pub mod net_ops;
 pub mod node_ops;
@@ -21,10 +23,15 @@ use std::str::FromStr;
 
 use crate::libp2p::{Multiaddr, Protocol};
 use crate::lotus_json::HasLotusJson;
+use crate::rpc::XRPC_CHANNEL_VALUE;
 use crate::utils::net::global_http_client;
+use anyhow::Context as _;
+use futures::stream::{BoxStream, StreamExt as _};
+use futures::SinkExt as _;
 use http::StatusCode;
 use jsonrpc_v2::{Id, RequestObject, V2};
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize};
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest as _, Message};
 use tracing::debug;
 
 pub const API_INFO_KEY: &str = "FULLNODE_API_INFO";
@@ -35,8 +42,8 @@ pub const DEFAULT_PROTOCOL: &str = "http";
 pub const RPC_ENDPOINT: &str = "rpc/v0";
 
 pub use self::{
-    auth_ops::*, chain_ops::*, common_ops::*, mpool_ops::*, net_ops::*, state_ops::*, sync_ops::*,
-    wallet_ops::*,
+    auth_ops::*, chain_ops::*, common_ops::*, gas_ops::*, mpool_ops::*, net_ops::*, state_ops::*,
+    sync_ops::*, wallet_ops::*,
 };
 
 #[derive(Clone, Debug)]
@@ -119,6 +126,43 @@ impl ApiInfo {
             JsonRpcResponse::Error { error, .. } => Err(error),
         }
     }
+
+    /// Subscribes to a streaming method, like `Filecoin.ChainNotify`, over
+    /// WebSocket, and returns the values sent on the channel of the
+    /// subscription.
+    pub async fn subscribe<T: DeserializeOwned + Send + 'static>(
+        &self,
+        req: RpcRequest<T>,
+    ) -> anyhow::Result<BoxStream<'static, anyhow::Result<T>>> {
+        let api_url = multiaddress_to_url(&self.multiaddr).replacen("http", "ws", 1);
+        debug!("Using JSON-RPC v2 WS URL: {}", api_url);
+
+        let mut request = api_url.into_client_request()?;
+        if let Some(token) = &self.token {
+            request
+                .headers_mut()
+                .insert(http::header::AUTHORIZATION, token.parse()?);
+        }
+        let (mut socket, _) = tokio_tungstenite::connect_async(request).await?;
+        let rpc_req = RequestObject::request()
+            .with_method(req.method_name)
+            .with_params(req.params)
+            .with_id(0)
+            .finish();
+        socket
+            .send(Message::Text(serde_json::to_string(&rpc_req)?))
+            .await?;
+
+        Ok(socket
+            .filter_map(|message| async move {
+                match message {
+                    Ok(Message::Text(text)) => channel_value(&text).transpose(),
+                    Ok(_) => None,
+                    Err(e) => Some(Err(e.into())),
+                }
+            })
+            .boxed())
+    }
 }
 
 /// Error object in a response
@@ -203,6 +247,22 @@ struct Url {
     host: String,
 }
 
+/// Extracts the value of a channel notification of a subscription, skipping the
+/// response to the subscription request.
+fn channel_value<T: DeserializeOwned>(text: &str) -> anyhow::Result<Option<T>> {
+    let mut message: serde_json::Value = serde_json::from_str(text)?;
+    if let Some(error) = message.get("error") {
+        anyhow::bail!("subscription failed: {error}");
+    }
+    if message["method"] != XRPC_CHANNEL_VALUE {
+        return Ok(None);
+    }
+    let value = message["params"][1].take();
+    Ok(Some(
+        serde_json::from_value(value).context("invalid channel value")?,
+    ))
+}
+
 /// Parses a multi-address into a URL
 fn multiaddress_to_url(multiaddr: &Multiaddr) -> String {
     // Fold Multiaddress into a Url struct
@@ -290,3 +350,20 @@ impl<T> RpcRequest<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_values() {
+        let response = r#"{"jsonrpc":"2.0","result":1,"id":0}"#;
+        assert_eq!(channel_value::<u64>(response).unwrap(), None);
+
+        let notification = r#"{"jsonrpc":"2.0","method":"xrpc.ch.val","params":[1,42]}"#;
+        assert_eq!(channel_value::<u64>(notification).unwrap(), Some(42));
+
+        let error = r#"{"jsonrpc":"2.0","error":{"code":1,"message":"boom"},"id":0}"#;
+        assert!(channel_value::<u64>(error).is_err());
+    }
+}
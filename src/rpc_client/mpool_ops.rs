@@ -27,6 +27,14 @@ impl ApiInfo {
         RpcRequest::new(MPOOL_PUSH_MESSAGE, (message, specs))
     }
 
+    pub async fn mpool_push(&self, message: SignedMessage) -> Result<Cid, JsonRpcError> {
+        self.call(Self::mpool_push_req(message)).await
+    }
+
+    pub fn mpool_push_req(message: SignedMessage) -> RpcRequest<Cid> {
+        RpcRequest::new(MPOOL_PUSH, (message,))
+    }
+
     pub async fn mpool_pending(&self, cids: Vec<Cid>) -> Result<Vec<SignedMessage>, JsonRpcError> {
         self.call(Self::mpool_pending_req(cids)).await
     }
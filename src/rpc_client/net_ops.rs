@@ -45,4 +45,88 @@ impl ApiInfo {
     pub fn net_disconnect_req(peer: String) -> RpcRequest<()> {
         RpcRequest::new(NET_DISCONNECT, (peer,))
     }
+
+    pub async fn net_reachability(&self) -> Result<NetReachabilityResult, JsonRpcError> {
+        self.call(Self::net_reachability_req()).await
+    }
+
+    pub fn net_reachability_req() -> RpcRequest<NetReachabilityResult> {
+        RpcRequest::new(NET_REACHABILITY, ())
+    }
+
+    pub async fn net_pubsub_scores(&self) -> Result<Vec<PubsubScore>, JsonRpcError> {
+        self.call(Self::net_pubsub_scores_req()).await
+    }
+
+    pub fn net_pubsub_scores_req() -> RpcRequest<Vec<PubsubScore>> {
+        RpcRequest::new(NET_PUBSUB_SCORES, ())
+    }
+
+    pub async fn net_peer_info(&self, peer: String) -> Result<ExtendedPeerInfo, JsonRpcError> {
+        self.call(Self::net_peer_info_req(peer)).await
+    }
+
+    pub fn net_peer_info_req(peer: String) -> RpcRequest<ExtendedPeerInfo> {
+        RpcRequest::new(NET_PEER_INFO, (peer,))
+    }
+
+    pub async fn net_agent_version(&self, peer: String) -> Result<String, JsonRpcError> {
+        self.call(Self::net_agent_version_req(peer)).await
+    }
+
+    pub fn net_agent_version_req(peer: String) -> RpcRequest<String> {
+        RpcRequest::new(NET_AGENT_VERSION, (peer,))
+    }
+
+    pub async fn net_ban_peer(
+        &self,
+        peer: String,
+        duration_secs: Option<u64>,
+    ) -> Result<(), JsonRpcError> {
+        self.call(Self::net_ban_peer_req(peer, duration_secs)).await
+    }
+
+    pub fn net_ban_peer_req(peer: String, duration_secs: Option<u64>) -> RpcRequest<()> {
+        RpcRequest::new(NET_BAN_PEER, (peer, duration_secs))
+    }
+
+    pub async fn net_unban_peer(&self, peer: String) -> Result<bool, JsonRpcError> {
+        self.call(Self::net_unban_peer_req(peer)).await
+    }
+
+    pub fn net_unban_peer_req(peer: String) -> RpcRequest<bool> {
+        RpcRequest::new(NET_UNBAN_PEER, (peer,))
+    }
+
+    pub async fn net_protect_add(&self, peers: Vec<String>) -> Result<(), JsonRpcError> {
+        self.call(Self::net_protect_add_req(peers)).await
+    }
+
+    pub fn net_protect_add_req(peers: Vec<String>) -> RpcRequest<()> {
+        RpcRequest::new(NET_PROTECT_ADD, (peers,))
+    }
+
+    pub async fn net_protect_remove(&self, peers: Vec<String>) -> Result<(), JsonRpcError> {
+        self.call(Self::net_protect_remove_req(peers)).await
+    }
+
+    pub fn net_protect_remove_req(peers: Vec<String>) -> RpcRequest<()> {
+        RpcRequest::new(NET_PROTECT_REMOVE, (peers,))
+    }
+
+    pub async fn net_protect_list(&self) -> Result<Vec<String>, JsonRpcError> {
+        self.call(Self::net_protect_list_req()).await
+    }
+
+    pub fn net_protect_list_req() -> RpcRequest<Vec<String>> {
+        RpcRequest::new(NET_PROTECT_LIST, ())
+    }
+
+    pub async fn net_find_peer(&self, peer: String) -> Result<AddrInfo, JsonRpcError> {
+        self.call(Self::net_find_peer_req(peer)).await
+    }
+
+    pub fn net_find_peer_req(peer: String) -> RpcRequest<AddrInfo> {
+        RpcRequest::new(NET_FIND_PEER, (peer,))
+    }
 }
@@ -6,10 +6,14 @@ use std::path::PathBuf;
 use crate::{
     blocks::TipsetKeys,
     rpc_api::{
-        data_types::{ApiActorState, SectorOnChainInfo},
+        data_types::{ActorsPage, ApiActorState, NetworkParams, SectorOnChainInfo},
         state_api::*,
     },
-    shim::{address::Address, clock::ChainEpoch, state_tree::ActorState},
+    shim::{
+        address::Address, clock::ChainEpoch, message::Message, state_tree::ActorState,
+        version::NetworkVersion,
+    },
+    state_manager::{BlockRewards, ComputeStateOutput},
 };
 use cid::Cid;
 use fil_actor_interface::miner::MinerPower;
@@ -45,6 +49,53 @@ impl ApiInfo {
         RpcRequest::new(STATE_FETCH_ROOT, (root, opt_path))
     }
 
+    pub async fn state_compute(
+        &self,
+        epoch: ChainEpoch,
+        messages: Vec<Message>,
+        tsk: TipsetKeys,
+    ) -> Result<ComputeStateOutput, JsonRpcError> {
+        self.call(Self::state_compute_req(epoch, messages, tsk))
+            .await
+    }
+
+    pub fn state_compute_req(
+        epoch: ChainEpoch,
+        messages: Vec<Message>,
+        tsk: TipsetKeys,
+    ) -> RpcRequest<ComputeStateOutput> {
+        RpcRequest::new(STATE_COMPUTE, (epoch, messages, tsk))
+    }
+
+    pub async fn state_compute_traced(
+        &self,
+        epoch: ChainEpoch,
+        messages: Vec<Message>,
+        tsk: TipsetKeys,
+    ) -> Result<ComputeStateOutput, JsonRpcError> {
+        self.call(Self::state_compute_traced_req(epoch, messages, tsk))
+            .await
+    }
+
+    pub fn state_compute_traced_req(
+        epoch: ChainEpoch,
+        messages: Vec<Message>,
+        tsk: TipsetKeys,
+    ) -> RpcRequest<ComputeStateOutput> {
+        RpcRequest::new(STATE_COMPUTE_TRACED, (epoch, messages, tsk))
+    }
+
+    pub async fn state_tipset_rewards(
+        &self,
+        tsk: TipsetKeys,
+    ) -> Result<Vec<BlockRewards>, JsonRpcError> {
+        self.call(Self::state_tipset_rewards_req(tsk)).await
+    }
+
+    pub fn state_tipset_rewards_req(tsk: TipsetKeys) -> RpcRequest<Vec<BlockRewards>> {
+        RpcRequest::new(STATE_TIPSET_REWARDS, (tsk,))
+    }
+
     pub async fn state_network_name(&self) -> Result<String, JsonRpcError> {
         self.call(Self::state_network_name_req()).await
     }
@@ -53,6 +104,57 @@ impl ApiInfo {
         RpcRequest::new(STATE_NETWORK_NAME, ())
     }
 
+    pub async fn state_get_network_params(&self) -> Result<NetworkParams, JsonRpcError> {
+        self.call(Self::state_get_network_params_req()).await
+    }
+
+    pub fn state_get_network_params_req() -> RpcRequest<NetworkParams> {
+        RpcRequest::new(STATE_GET_NETWORK_PARAMS, ())
+    }
+
+    pub async fn state_builtin_actor(
+        &self,
+        code: Cid,
+        tsk: TipsetKeys,
+    ) -> Result<String, JsonRpcError> {
+        self.call(Self::state_builtin_actor_req(code, tsk)).await
+    }
+
+    pub fn state_builtin_actor_req(code: Cid, tsk: TipsetKeys) -> RpcRequest<String> {
+        RpcRequest::new(STATE_BUILTIN_ACTOR, (code, tsk))
+    }
+
+    pub async fn state_network_version(
+        &self,
+        tsk: TipsetKeys,
+    ) -> Result<NetworkVersion, JsonRpcError> {
+        self.call(Self::state_network_version_req(tsk)).await
+    }
+
+    pub fn state_network_version_req(tsk: TipsetKeys) -> RpcRequest<NetworkVersion> {
+        RpcRequest::new(STATE_NETWORK_VERSION, (tsk,))
+    }
+
+    pub async fn state_list_actors_page(
+        &self,
+        tsk: TipsetKeys,
+        start: Option<Address>,
+        limit: u64,
+        code: Option<Cid>,
+    ) -> Result<ActorsPage, JsonRpcError> {
+        self.call(Self::state_list_actors_page_req(tsk, start, limit, code))
+            .await
+    }
+
+    pub fn state_list_actors_page_req(
+        tsk: TipsetKeys,
+        start: Option<Address>,
+        limit: u64,
+        code: Option<Cid>,
+    ) -> RpcRequest<ActorsPage> {
+        RpcRequest::new(STATE_LIST_ACTORS_PAGE, (tsk, start, limit, code))
+    }
+
     pub fn state_miner_power(miner: Address, tsk: TipsetKeys) -> RpcRequest<MinerPower> {
         RpcRequest::new(STATE_MINOR_POWER, (miner, tsk))
     }
@@ -69,6 +171,18 @@ impl ApiInfo {
         )
     }
 
+    pub fn state_get_randomness_from_tickets_req(
+        tsk: TipsetKeys,
+        personalization: DomainSeparationTag,
+        rand_epoch: ChainEpoch,
+        entropy: Vec<u8>,
+    ) -> RpcRequest<Vec<u8>> {
+        RpcRequest::new(
+            STATE_GET_RANDOMNESS_FROM_TICKETS,
+            (personalization as u32, rand_epoch, entropy, tsk),
+        )
+    }
+
     pub fn state_read_state_req(actor: Address, tsk: TipsetKeys) -> RpcRequest<ApiActorState> {
         RpcRequest::new(STATE_READ_STATE, (actor, tsk))
     }
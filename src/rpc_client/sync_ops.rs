@@ -23,6 +23,22 @@ impl ApiInfo {
         RpcRequest::new(SYNC_MARK_BAD, (cid,))
     }
 
+    pub async fn sync_unmark_bad(&self, cid: Cid) -> Result<(), JsonRpcError> {
+        self.call(Self::sync_unmark_bad_req(cid)).await
+    }
+
+    pub fn sync_unmark_bad_req(cid: Cid) -> RpcRequest<()> {
+        RpcRequest::new(SYNC_UNMARK_BAD, (cid,))
+    }
+
+    pub async fn sync_unmark_all_bad(&self) -> Result<(), JsonRpcError> {
+        self.call(Self::sync_unmark_all_bad_req()).await
+    }
+
+    pub fn sync_unmark_all_bad_req() -> RpcRequest<()> {
+        RpcRequest::new(SYNC_UNMARK_ALL_BAD, ())
+    }
+
     pub async fn sync_status(&self) -> Result<RPCSyncState, JsonRpcError> {
         self.call(Self::sync_status_req()).await
     }
@@ -0,0 +1,1074 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Names and parameter layouts of the methods of the builtin actors, for each
+//! actor version.
+//!
+//! Parameters are tuple-encoded CBOR, which carries no field names. They are
+//! decoded as IPLD, and the fields of the lists are named with the layouts
+//! below, any field past a layout is keyed by its index. When the actor crates
+//! export the parameters of a method, the CBOR is also checked against them,
+//! so that parameters of the wrong shape are rejected instead of being
+//! mislabelled.
+
+use crate::ipld::{
+    json::{IpldJson, IpldJsonRef},
+    Ipld,
+};
+use anyhow::Context as _;
+use fvm_ipld_encoding::RawBytes;
+use serde::de::DeserializeOwned;
+
+use super::address::Address;
+use super::machine::{ActorVersion, BuiltinActor};
+use super::message::MethodNum;
+
+/// Checks that CBOR parameters decode to some type.
+type ParamsCheck = fn(&[u8]) -> anyhow::Result<()>;
+
+/// A method of a builtin actor.
+#[derive(Debug)]
+pub struct Method {
+    pub number: MethodNum,
+    pub name: &'static str,
+    /// Names of the leading fields of the parameters, empty if they aren't a
+    /// tuple.
+    pub params: &'static [&'static str],
+    /// Checks that CBOR parameters decode to the parameters of the method, if
+    /// their type is known.
+    check: Option<ParamsCheck>,
+}
+
+impl Method {
+    fn check(&self, params: &[u8]) -> anyhow::Result<()> {
+        match self.check {
+            Some(check) => {
+                check(params).with_context(|| format!("invalid parameters for {}", self.name))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// A method whose parameters have no known type.
+const fn m(number: MethodNum, name: &'static str, params: &'static [&'static str]) -> Method {
+    Method {
+        number,
+        name,
+        params,
+        check: None,
+    }
+}
+
+/// A method whose parameters are checked with [`check`].
+const fn t(
+    number: MethodNum,
+    name: &'static str,
+    params: &'static [&'static str],
+    check: ParamsCheck,
+) -> Method {
+    Method {
+        number,
+        name,
+        params,
+        check: Some(check),
+    }
+}
+
+fn check<P: DeserializeOwned>(params: &[u8]) -> anyhow::Result<()> {
+    fvm_ipld_encoding::from_slice::<P>(params)?;
+    Ok(())
+}
+
+/// Every actor can receive funds.
+static SEND: Method = m(0, "Send", &[]);
+
+/// Returns the method `number` of `actor` at `version`, if known.
+pub fn method(
+    actor: BuiltinActor,
+    version: ActorVersion,
+    number: MethodNum,
+) -> Option<&'static Method> {
+    if number == SEND.number {
+        return Some(&SEND);
+    }
+    methods(actor, version)
+        .iter()
+        .find(|method| method.number == number)
+}
+
+/// Returns the known methods of `actor` at `version`, sorted by number.
+pub fn methods(actor: BuiltinActor, version: ActorVersion) -> &'static [Method] {
+    use ActorVersion::*;
+    match (actor, version) {
+        (BuiltinActor::System, _) => SYSTEM,
+        (BuiltinActor::Init, V8 | V9) => INIT_V8,
+        (BuiltinActor::Init, V10) => INIT_V10,
+        (BuiltinActor::Init, V11) => INIT_V11,
+        (BuiltinActor::Init, V12) => INIT_V12,
+        (BuiltinActor::Cron, V8 | V9 | V10) => CRON_V8,
+        (BuiltinActor::Cron, V11) => CRON_V11,
+        (BuiltinActor::Cron, V12) => CRON_V12,
+        (BuiltinActor::Account, V8) => ACCOUNT_V8,
+        (BuiltinActor::Account, V9) => ACCOUNT_V9,
+        (BuiltinActor::Account, V10) => ACCOUNT_V10,
+        (BuiltinActor::Account, V11) => ACCOUNT_V11,
+        (BuiltinActor::Account, V12) => ACCOUNT_V12,
+        (BuiltinActor::Power, V8) => POWER_V8,
+        (BuiltinActor::Power, V9) => POWER_V9,
+        (BuiltinActor::Power, V10) => POWER_V10,
+        (BuiltinActor::Power, V11) => POWER_V11,
+        (BuiltinActor::Power, V12) => POWER_V12,
+        (BuiltinActor::Miner, V8) => MINER_V8,
+        (BuiltinActor::Miner, V9) => MINER_V9,
+        (BuiltinActor::Miner, V10) => MINER_V10,
+        (BuiltinActor::Miner, V11) => MINER_V11,
+        (BuiltinActor::Miner, V12) => MINER_V12,
+        (BuiltinActor::Market, V8) => MARKET_V8,
+        (BuiltinActor::Market, V9) => MARKET_V9,
+        (BuiltinActor::Market, V10) => MARKET_V10,
+        (BuiltinActor::Market, V11) => MARKET_V11,
+        (BuiltinActor::Market, V12) => MARKET_V12,
+        (BuiltinActor::PaymentChannel, _) => PAYMENT_CHANNEL,
+        (BuiltinActor::Multisig, V8) => MULTISIG_V8,
+        (BuiltinActor::Multisig, V9) => MULTISIG_V9,
+        (BuiltinActor::Multisig, V10) => MULTISIG_V10,
+        (BuiltinActor::Multisig, V11) => MULTISIG_V11,
+        (BuiltinActor::Multisig, V12) => MULTISIG_V12,
+        (BuiltinActor::Reward, V8) => REWARD_V8,
+        (BuiltinActor::Reward, V9) => REWARD_V9,
+        (BuiltinActor::Reward, V10) => REWARD_V10,
+        (BuiltinActor::Reward, V11) => REWARD_V11,
+        (BuiltinActor::Reward, V12) => REWARD_V12,
+        (BuiltinActor::VerifiedRegistry, V8) => VERIFIED_REGISTRY_V8,
+        (BuiltinActor::VerifiedRegistry, V9) => VERIFIED_REGISTRY_V9,
+        (BuiltinActor::VerifiedRegistry, V10) => VERIFIED_REGISTRY_V10,
+        (BuiltinActor::VerifiedRegistry, V11) => VERIFIED_REGISTRY_V11,
+        (BuiltinActor::VerifiedRegistry, V12) => VERIFIED_REGISTRY_V12,
+        (BuiltinActor::DataCap, V9) => DATACAP_V9,
+        // The EVM and the EAM were introduced with the v10 actors
+        (BuiltinActor::EVM, V10 | V11 | V12) => EVM,
+        (BuiltinActor::EAM, V10 | V11 | V12) => EAM,
+        (BuiltinActor::DataCap, _)
+        | (BuiltinActor::EVM, _)
+        | (BuiltinActor::EAM, _)
+        | (BuiltinActor::Placeholder, _)
+        | (BuiltinActor::EthAccount, _) => &[],
+    }
+}
+
+/// Decodes the CBOR parameters of a call to the method `number` of `actor` at
+/// `version` to JSON, naming the fields when the layout is known.
+pub fn decode_params(
+    actor: BuiltinActor,
+    version: ActorVersion,
+    number: MethodNum,
+    params: &RawBytes,
+) -> anyhow::Result<serde_json::Value> {
+    if params.bytes().is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+    let method = method(actor, version, number);
+    if let Some(method) = method {
+        method.check(params.bytes())?;
+    }
+    let ipld: Ipld =
+        fvm_ipld_encoding::from_slice(params.bytes()).context("invalid CBOR parameters")?;
+    let fields = method
+        .map(|method| method.params)
+        .filter(|fields| !fields.is_empty());
+    match (fields, ipld) {
+        (Some(names), Ipld::List(values)) => {
+            let mut object = serde_json::Map::new();
+            for (index, value) in values.iter().enumerate() {
+                object.insert(
+                    field_name(names, index),
+                    serde_json::to_value(IpldJsonRef(value))?,
+                );
+            }
+            Ok(serde_json::Value::Object(object))
+        }
+        (_, ipld) => Ok(serde_json::to_value(IpldJson(ipld))?),
+    }
+}
+
+/// Encodes JSON parameters, as returned by [`decode_params`], to CBOR.
+pub fn encode_params(
+    actor: BuiltinActor,
+    version: ActorVersion,
+    number: MethodNum,
+    params: serde_json::Value,
+) -> anyhow::Result<RawBytes> {
+    let method = method(actor, version, number);
+    let fields = method
+        .map(|method| method.params)
+        .filter(|fields| !fields.is_empty());
+    let ipld = match (fields, params) {
+        (_, serde_json::Value::Null) => return Ok(RawBytes::default()),
+        (Some(names), serde_json::Value::Object(mut object)) => {
+            let mut values = vec![];
+            while let Some(value) = object.remove(&field_name(names, values.len())) {
+                values.push(serde_json::from_value::<IpldJson>(value)?.0);
+            }
+            if let Some(name) = object.keys().next() {
+                anyhow::bail!("unexpected field {name}");
+            }
+            Ipld::List(values)
+        }
+        (_, params) => serde_json::from_value::<IpldJson>(params)?.0,
+    };
+    let bytes = fvm_ipld_encoding::to_vec(&ipld)?;
+    if let Some(method) = method {
+        method.check(&bytes)?;
+    }
+    Ok(RawBytes::new(bytes))
+}
+
+fn field_name(names: &[&str], index: usize) -> String {
+    names
+        .get(index)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| index.to_string())
+}
+
+static SYSTEM: &[Method] = &[m(1, "Constructor", &[])];
+
+macro_rules! init_methods {
+    ($v:ident $(, $extra:expr)* $(,)?) => {
+        &[
+            t(
+                1,
+                "Constructor",
+                &["NetworkName"],
+                check::<fil_actor_init_state::$v::ConstructorParams>,
+            ),
+            t(
+                2,
+                "Exec",
+                &["CodeCID", "ConstructorParams"],
+                check::<fil_actor_init_state::$v::ExecParams>,
+            ),
+            $($extra,)*
+        ]
+    };
+}
+
+/// The parameters of the v9 init actor aren't exported, they didn't change
+/// since v8.
+static INIT_V8: &[Method] = init_methods!(v8);
+static INIT_V10: &[Method] = init_methods!(
+    v10,
+    t(
+        3,
+        "Exec4",
+        &["CodeCID", "ConstructorParams", "SubAddress"],
+        check::<fil_actor_init_state::v10::Exec4Params>,
+    ),
+);
+static INIT_V11: &[Method] = init_methods!(
+    v11,
+    t(
+        3,
+        "Exec4",
+        &["CodeCID", "ConstructorParams", "SubAddress"],
+        check::<fil_actor_init_state::v11::Exec4Params>,
+    ),
+);
+static INIT_V12: &[Method] = init_methods!(
+    v12,
+    t(
+        3,
+        "Exec4",
+        &["CodeCID", "ConstructorParams", "SubAddress"],
+        check::<fil_actor_init_state::v12::Exec4Params>,
+    ),
+);
+
+/// The parameters of the constructor are only exported since v11.
+static CRON_V8: &[Method] = &[m(1, "Constructor", &["Entries"]), m(2, "EpochTick", &[])];
+static CRON_V11: &[Method] = &[
+    t(
+        1,
+        "Constructor",
+        &["Entries"],
+        check::<fil_actor_cron_state::v11::ConstructorParams>,
+    ),
+    m(2, "EpochTick", &[]),
+];
+static CRON_V12: &[Method] = &[
+    t(
+        1,
+        "Constructor",
+        &["Entries"],
+        check::<fil_actor_cron_state::v12::ConstructorParams>,
+    ),
+    m(2, "EpochTick", &[]),
+];
+
+/// `AuthenticateMessage` was added in v9, and is only exported under its
+/// FRC-42 number since v10.
+const AUTHENTICATE_MESSAGE_EXPORTED: MethodNum = 2643134072;
+
+static ACCOUNT_V8: &[Method] = &[
+    t(1, "Constructor", &[], check::<Address>),
+    m(2, "PubkeyAddress", &[]),
+];
+static ACCOUNT_V9: &[Method] = &[
+    t(1, "Constructor", &[], check::<Address>),
+    m(2, "PubkeyAddress", &[]),
+    t(
+        3,
+        "AuthenticateMessage",
+        &["Signature", "Message"],
+        check::<fil_actor_account_state::v9::AuthenticateMessageParams>,
+    ),
+];
+static ACCOUNT_V10: &[Method] = &[
+    t(1, "Constructor", &[], check::<Address>),
+    m(2, "PubkeyAddress", &[]),
+    t(
+        AUTHENTICATE_MESSAGE_EXPORTED,
+        "AuthenticateMessage",
+        &["Signature", "Message"],
+        check::<fil_actor_account_state::v10::AuthenticateMessageParams>,
+    ),
+];
+static ACCOUNT_V11: &[Method] = &[
+    t(
+        1,
+        "Constructor",
+        &[],
+        check::<fil_actor_account_state::v11::ConstructorParams>,
+    ),
+    m(2, "PubkeyAddress", &[]),
+    t(
+        AUTHENTICATE_MESSAGE_EXPORTED,
+        "AuthenticateMessage",
+        &["Signature", "Message"],
+        check::<fil_actor_account_state::v11::AuthenticateMessageParams>,
+    ),
+];
+static ACCOUNT_V12: &[Method] = &[
+    t(
+        1,
+        "Constructor",
+        &[],
+        check::<fil_actor_account_state::v12::ConstructorParams>,
+    ),
+    m(2, "PubkeyAddress", &[]),
+    t(
+        AUTHENTICATE_MESSAGE_EXPORTED,
+        "AuthenticateMessage",
+        &["Signature", "Message"],
+        check::<fil_actor_account_state::v12::AuthenticateMessageParams>,
+    ),
+];
+
+macro_rules! power_methods {
+    ($v:ident) => {
+        &[
+            m(1, "Constructor", &[]),
+            t(
+                2,
+                "CreateMiner",
+                &[
+                    "Owner",
+                    "Worker",
+                    "WindowPoStProofType",
+                    "Peer",
+                    "Multiaddrs",
+                ],
+                check::<fil_actor_power_state::$v::CreateMinerParams>,
+            ),
+            t(
+                3,
+                "UpdateClaimedPower",
+                &["RawByteDelta", "QualityAdjustedDelta"],
+                check::<fil_actor_power_state::$v::UpdateClaimedPowerParams>,
+            ),
+            t(
+                4,
+                "EnrollCronEvent",
+                &["EventEpoch", "Payload"],
+                check::<fil_actor_power_state::$v::EnrollCronEventParams>,
+            ),
+            m(5, "OnEpochTickEnd", &[]),
+            m(6, "UpdatePledgeTotal", &[]),
+            m(8, "SubmitPoRepForBulkVerify", &[]),
+            m(9, "CurrentTotalPower", &[]),
+        ]
+    };
+}
+
+static POWER_V8: &[Method] = power_methods!(v8);
+static POWER_V9: &[Method] = power_methods!(v9);
+static POWER_V10: &[Method] = power_methods!(v10);
+static POWER_V11: &[Method] = power_methods!(v11);
+static POWER_V12: &[Method] = power_methods!(v12);
+
+/// Methods 1 to 27, shared by all versions. `$confirm_worker` is the name of
+/// method 21, and `$owner` the parameters of method 23.
+macro_rules! miner_methods {
+    ($v:ident, $confirm_worker:expr, $owner:ty $(, $extra:expr)* $(,)?) => {
+        &[
+            t(
+                1,
+                "Constructor",
+                &[
+                    "OwnerAddr",
+                    "WorkerAddr",
+                    "ControlAddrs",
+                    "WindowPoStProofType",
+                    "PeerId",
+                    "Multiaddrs",
+                ],
+                check::<fil_actor_miner_state::$v::MinerConstructorParams>,
+            ),
+            m(2, "ControlAddresses", &[]),
+            t(
+                3,
+                "ChangeWorkerAddress",
+                &["NewWorker", "NewControlAddrs"],
+                check::<fil_actor_miner_state::$v::ChangeWorkerAddressParams>,
+            ),
+            t(
+                4,
+                "ChangePeerID",
+                &["NewID"],
+                check::<fil_actor_miner_state::$v::ChangePeerIDParams>,
+            ),
+            t(
+                5,
+                "SubmitWindowedPoSt",
+                &[
+                    "Deadline",
+                    "Partitions",
+                    "Proofs",
+                    "ChainCommitEpoch",
+                    "ChainCommitRand",
+                ],
+                check::<fil_actor_miner_state::$v::SubmitWindowedPoStParams>,
+            ),
+            t(
+                6,
+                "PreCommitSector",
+                &[
+                    "SealProof",
+                    "SectorNumber",
+                    "SealedCID",
+                    "SealRandEpoch",
+                    "DealIDs",
+                    "Expiration",
+                ],
+                check::<fil_actor_miner_state::$v::PreCommitSectorParams>,
+            ),
+            t(
+                7,
+                "ProveCommitSector",
+                &["SectorNumber", "Proof"],
+                check::<fil_actor_miner_state::$v::ProveCommitSectorParams>,
+            ),
+            t(
+                8,
+                "ExtendSectorExpiration",
+                &["Extensions"],
+                check::<fil_actor_miner_state::$v::ExtendSectorExpirationParams>,
+            ),
+            t(
+                9,
+                "TerminateSectors",
+                &["Terminations"],
+                check::<fil_actor_miner_state::$v::TerminateSectorsParams>,
+            ),
+            t(
+                10,
+                "DeclareFaults",
+                &["Faults"],
+                check::<fil_actor_miner_state::$v::DeclareFaultsParams>,
+            ),
+            t(
+                11,
+                "DeclareFaultsRecovered",
+                &["Recoveries"],
+                check::<fil_actor_miner_state::$v::DeclareFaultsRecoveredParams>,
+            ),
+            t(
+                12,
+                "OnDeferredCronEvent",
+                &[],
+                check::<fil_actor_miner_state::$v::DeferredCronEventParams>,
+            ),
+            t(
+                13,
+                "CheckSectorProven",
+                &["SectorNumber"],
+                check::<fil_actor_miner_state::$v::CheckSectorProvenParams>,
+            ),
+            t(
+                14,
+                "ApplyRewards",
+                &["Reward", "Penalty"],
+                check::<fil_actor_miner_state::$v::ApplyRewardParams>,
+            ),
+            t(
+                15,
+                "ReportConsensusFault",
+                &["BlockHeader1", "BlockHeader2", "BlockHeaderExtra"],
+                check::<fil_actor_miner_state::$v::ReportConsensusFaultParams>,
+            ),
+            t(
+                16,
+                "WithdrawBalance",
+                &["AmountRequested"],
+                check::<fil_actor_miner_state::$v::WithdrawBalanceParams>,
+            ),
+            t(
+                17,
+                "ConfirmSectorProofsValid",
+                &[],
+                check::<fil_actor_miner_state::$v::ConfirmSectorProofsParams>,
+            ),
+            t(
+                18,
+                "ChangeMultiaddrs",
+                &["NewMultiaddrs"],
+                check::<fil_actor_miner_state::$v::ChangeMultiaddrsParams>,
+            ),
+            t(
+                19,
+                "CompactPartitions",
+                &["Deadline", "Partitions"],
+                check::<fil_actor_miner_state::$v::CompactPartitionsParams>,
+            ),
+            t(
+                20,
+                "CompactSectorNumbers",
+                &["MaskSectorNumbers"],
+                check::<fil_actor_miner_state::$v::CompactSectorNumbersParams>,
+            ),
+            m(21, $confirm_worker, &[]),
+            m(22, "RepayDebt", &[]),
+            t(23, "ChangeOwnerAddress", &[], check::<$owner>),
+            t(
+                24,
+                "DisputeWindowedPoSt",
+                &["Deadline", "PoStIndex"],
+                check::<fil_actor_miner_state::$v::DisputeWindowedPoStParams>,
+            ),
+            t(
+                25,
+                "PreCommitSectorBatch",
+                &["Sectors"],
+                check::<fil_actor_miner_state::$v::PreCommitSectorBatchParams>,
+            ),
+            t(
+                26,
+                "ProveCommitAggregate",
+                &["SectorNumbers", "AggregateProof"],
+                check::<fil_actor_miner_state::$v::ProveCommitAggregateParams>,
+            ),
+            t(
+                27,
+                "ProveReplicaUpdates",
+                &["Updates"],
+                check::<fil_actor_miner_state::$v::ProveReplicaUpdatesParams>,
+            ),
+            $($extra,)*
+        ]
+    };
+}
+
+/// Methods 28 to 32, added in v9.
+macro_rules! miner_methods_v9 {
+    ($v:ident, $confirm_worker:expr, $owner:ty $(, $extra:expr)* $(,)?) => {
+        miner_methods!(
+            $v,
+            $confirm_worker,
+            $owner,
+            t(
+                28,
+                "PreCommitSectorBatch2",
+                &["Sectors"],
+                check::<fil_actor_miner_state::$v::PreCommitSectorBatchParams2>,
+            ),
+            t(
+                29,
+                "ProveReplicaUpdates2",
+                &["Updates"],
+                check::<fil_actor_miner_state::$v::ProveReplicaUpdatesParams2>,
+            ),
+            t(
+                30,
+                "ChangeBeneficiary",
+                &["NewBeneficiary", "NewQuota", "NewExpiration"],
+                check::<fil_actor_miner_state::$v::ChangeBeneficiaryParams>,
+            ),
+            m(31, "GetBeneficiary", &[]),
+            t(
+                32,
+                "ExtendSectorExpiration2",
+                &["Extensions"],
+                check::<fil_actor_miner_state::$v::ExtendSectorExpiration2Params>,
+            ),
+            $($extra,)*
+        )
+    };
+}
+
+static MINER_V8: &[Method] = miner_methods!(v8, "ConfirmUpdateWorkerKey", Address);
+static MINER_V9: &[Method] = miner_methods_v9!(v9, "ConfirmUpdateWorkerKey", Address);
+static MINER_V10: &[Method] = miner_methods_v9!(v10, "ConfirmChangeWorkerAddress", Address);
+static MINER_V11: &[Method] = miner_methods_v9!(
+    v11,
+    "ConfirmChangeWorkerAddress",
+    fil_actor_miner_state::v11::ChangeOwnerAddressParams
+);
+static MINER_V12: &[Method] = miner_methods_v9!(
+    v12,
+    "ConfirmChangeWorkerAddress",
+    fil_actor_miner_state::v12::ChangeOwnerAddressParams,
+    t(
+        33,
+        "MovePartitions",
+        &["OrigDeadline", "DestDeadline", "Partitions"],
+        check::<fil_actor_miner_state::v12::MovePartitionsParams>,
+    ),
+);
+
+/// The parameters of the v8 market actor aren't exported.
+static MARKET_V8: &[Method] = &[
+    m(1, "Constructor", &[]),
+    m(2, "AddBalance", &[]),
+    m(3, "WithdrawBalance", &["ProviderOrClientAddress", "Amount"]),
+    m(4, "PublishStorageDeals", &["Deals"]),
+    m(5, "VerifyDealsForActivation", &["Sectors"]),
+    m(6, "ActivateDeals", &["DealIDs", "SectorExpiry"]),
+    m(7, "OnMinerSectorsTerminate", &["Epoch", "DealIDs"]),
+    m(8, "ComputeDataCommitment", &["Inputs"]),
+    m(9, "CronTick", &[]),
+];
+
+/// Methods 1 to 5, and 7 and 9, shared by all versions.
+macro_rules! market_methods {
+    ($v:ident, $activate:expr $(, $compute_data_commitment:expr)? $(,)?) => {
+        &[
+            m(1, "Constructor", &[]),
+            t(2, "AddBalance", &[], check::<Address>),
+            t(
+                3,
+                "WithdrawBalance",
+                &["ProviderOrClientAddress", "Amount"],
+                check::<fil_actor_market_state::$v::WithdrawBalanceParams>,
+            ),
+            t(
+                4,
+                "PublishStorageDeals",
+                &["Deals"],
+                check::<fil_actor_market_state::$v::PublishStorageDealsParams>,
+            ),
+            t(
+                5,
+                "VerifyDealsForActivation",
+                &["Sectors"],
+                check::<fil_actor_market_state::$v::VerifyDealsForActivationParams>,
+            ),
+            $activate,
+            t(
+                7,
+                "OnMinerSectorsTerminate",
+                &["Epoch", "DealIDs"],
+                check::<fil_actor_market_state::$v::OnMinerSectorsTerminateParams>,
+            ),
+            $($compute_data_commitment,)?
+            m(9, "CronTick", &[]),
+        ]
+    };
+}
+
+/// `ActivateDeals` and `ComputeDataCommitment`, up to v11.
+macro_rules! market_methods_v9 {
+    ($v:ident) => {
+        market_methods!(
+            $v,
+            t(
+                6,
+                "ActivateDeals",
+                &["DealIDs", "SectorExpiry"],
+                check::<fil_actor_market_state::$v::ActivateDealsParams>,
+            ),
+            t(
+                8,
+                "ComputeDataCommitment",
+                &["Inputs"],
+                check::<fil_actor_market_state::$v::ComputeDataCommitmentParams>,
+            ),
+        )
+    };
+}
+
+static MARKET_V9: &[Method] = market_methods_v9!(v9);
+static MARKET_V10: &[Method] = market_methods_v9!(v10);
+static MARKET_V11: &[Method] = market_methods_v9!(v11);
+static MARKET_V12: &[Method] = market_methods!(
+    v12,
+    t(
+        6,
+        "BatchActivateDeals",
+        &["Sectors", "ComputeCID"],
+        check::<fil_actor_market_state::v12::BatchActivateDealsParams>,
+    ),
+);
+
+/// There is no crate with the types of the payment channel actor.
+static PAYMENT_CHANNEL: &[Method] = &[
+    m(1, "Constructor", &["From", "To"]),
+    m(2, "UpdateChannelState", &["Sv", "Secret"]),
+    m(3, "Settle", &[]),
+    m(4, "Collect", &[]),
+];
+
+macro_rules! multisig_methods {
+    ($v:ident) => {
+        &[
+            t(
+                1,
+                "Constructor",
+                &[
+                    "Signers",
+                    "NumApprovalsThreshold",
+                    "UnlockDuration",
+                    "StartEpoch",
+                ],
+                check::<fil_actor_multisig_state::$v::ConstructorParams>,
+            ),
+            t(
+                2,
+                "Propose",
+                &["To", "Value", "Method", "Params"],
+                check::<fil_actor_multisig_state::$v::ProposeParams>,
+            ),
+            t(
+                3,
+                "Approve",
+                &["ID", "ProposalHash"],
+                check::<fil_actor_multisig_state::$v::TxnIDParams>,
+            ),
+            t(
+                4,
+                "Cancel",
+                &["ID", "ProposalHash"],
+                check::<fil_actor_multisig_state::$v::TxnIDParams>,
+            ),
+            t(
+                5,
+                "AddSigner",
+                &["Signer", "Increase"],
+                check::<fil_actor_multisig_state::$v::AddSignerParams>,
+            ),
+            t(
+                6,
+                "RemoveSigner",
+                &["Signer", "Decrease"],
+                check::<fil_actor_multisig_state::$v::RemoveSignerParams>,
+            ),
+            t(
+                7,
+                "SwapSigner",
+                &["From", "To"],
+                check::<fil_actor_multisig_state::$v::SwapSignerParams>,
+            ),
+            t(
+                8,
+                "ChangeNumApprovalsThreshold",
+                &["NewThreshold"],
+                check::<fil_actor_multisig_state::$v::ChangeNumApprovalsThresholdParams>,
+            ),
+            t(
+                9,
+                "LockBalance",
+                &["StartEpoch", "UnlockDuration", "Amount"],
+                check::<fil_actor_multisig_state::$v::LockBalanceParams>,
+            ),
+        ]
+    };
+}
+
+static MULTISIG_V8: &[Method] = multisig_methods!(v8);
+static MULTISIG_V9: &[Method] = multisig_methods!(v9);
+static MULTISIG_V10: &[Method] = multisig_methods!(v10);
+static MULTISIG_V11: &[Method] = multisig_methods!(v11);
+static MULTISIG_V12: &[Method] = multisig_methods!(v12);
+
+macro_rules! reward_methods {
+    ($v:ident) => {
+        &[
+            m(1, "Constructor", &[]),
+            t(
+                2,
+                "AwardBlockReward",
+                &["Miner", "Penalty", "GasReward", "WinCount"],
+                check::<fil_actor_reward_state::$v::AwardBlockRewardParams>,
+            ),
+            m(3, "ThisEpochReward", &[]),
+            m(4, "UpdateNetworkKPI", &[]),
+        ]
+    };
+}
+
+static REWARD_V8: &[Method] = reward_methods!(v8);
+static REWARD_V9: &[Method] = reward_methods!(v9);
+static REWARD_V10: &[Method] = reward_methods!(v10);
+static REWARD_V11: &[Method] = reward_methods!(v11);
+static REWARD_V12: &[Method] = reward_methods!(v12);
+
+/// Methods 1 to 4 and 7, shared by all versions. `$add_client` is the
+/// parameters of method 4.
+macro_rules! verified_registry_methods {
+    ($v:ident, $add_client:ident $(, $extra:expr)* $(,)?) => {
+        &[
+            m(1, "Constructor", &[]),
+            t(
+                2,
+                "AddVerifier",
+                &["Address", "Allowance"],
+                check::<fil_actor_verifreg_state::$v::AddVerifierParams>,
+            ),
+            t(3, "RemoveVerifier", &[], check::<Address>),
+            t(
+                4,
+                "AddVerifiedClient",
+                &["Address", "Allowance"],
+                check::<fil_actor_verifreg_state::$v::$add_client>,
+            ),
+            $($extra,)*
+        ]
+    };
+}
+
+/// The data cap was moved to its own actor in v9, along with allocations and
+/// claims.
+macro_rules! verified_registry_methods_v9 {
+    ($v:ident, $add_client:ident) => {
+        verified_registry_methods!(
+            $v,
+            $add_client,
+            t(
+                7,
+                "RemoveVerifiedClientDataCap",
+                &[
+                    "VerifiedClientToRemove",
+                    "DataCapAmountToRemove",
+                    "VerifierRequest1",
+                    "VerifierRequest2",
+                ],
+                check::<fil_actor_verifreg_state::$v::RemoveDataCapParams>,
+            ),
+            t(
+                8,
+                "RemoveExpiredAllocations",
+                &["Client", "AllocationIds"],
+                check::<fil_actor_verifreg_state::$v::RemoveExpiredAllocationsParams>,
+            ),
+            t(
+                9,
+                "ClaimAllocations",
+                &["Sectors", "AllOrNothing"],
+                check::<fil_actor_verifreg_state::$v::ClaimAllocationsParams>,
+            ),
+            t(
+                10,
+                "GetClaims",
+                &["Provider", "ClaimIds"],
+                check::<fil_actor_verifreg_state::$v::GetClaimsParams>,
+            ),
+            t(
+                11,
+                "ExtendClaimTerms",
+                &["Terms"],
+                check::<fil_actor_verifreg_state::$v::ExtendClaimTermsParams>,
+            ),
+            t(
+                12,
+                "RemoveExpiredClaims",
+                &["Provider", "ClaimIds"],
+                check::<fil_actor_verifreg_state::$v::RemoveExpiredClaimsParams>,
+            ),
+        )
+    };
+}
+
+static VERIFIED_REGISTRY_V8: &[Method] = verified_registry_methods!(
+    v8,
+    AddVerifierClientParams,
+    t(
+        5,
+        "UseBytes",
+        &["Address", "DealSize"],
+        check::<fil_actor_verifreg_state::v8::UseBytesParams>,
+    ),
+    t(
+        6,
+        "RestoreBytes",
+        &["Address", "DealSize"],
+        check::<fil_actor_verifreg_state::v8::RestoreBytesParams>,
+    ),
+    t(
+        7,
+        "RemoveVerifiedClientDataCap",
+        &[
+            "VerifiedClientToRemove",
+            "DataCapAmountToRemove",
+            "VerifierRequest1",
+            "VerifierRequest2",
+        ],
+        check::<fil_actor_verifreg_state::v8::RemoveDataCapParams>,
+    ),
+);
+static VERIFIED_REGISTRY_V9: &[Method] = verified_registry_methods_v9!(v9, AddVerifierClientParams);
+static VERIFIED_REGISTRY_V10: &[Method] =
+    verified_registry_methods_v9!(v10, AddVerifiedClientParams);
+static VERIFIED_REGISTRY_V11: &[Method] =
+    verified_registry_methods_v9!(v11, AddVerifiedClientParams);
+static VERIFIED_REGISTRY_V12: &[Method] =
+    verified_registry_methods_v9!(v12, AddVerifiedClientParams);
+
+/// Only v9 numbers its methods sequentially, later versions export them under
+/// their FRC-42 numbers.
+static DATACAP_V9: &[Method] = &[
+    m(1, "Constructor", &[]),
+    t(
+        2,
+        "Mint",
+        &["To", "Amount", "Operators"],
+        check::<fil_actor_datacap_state::v9::MintParams>,
+    ),
+    t(
+        3,
+        "Destroy",
+        &["Owner", "Amount"],
+        check::<fil_actor_datacap_state::v9::DestroyParams>,
+    ),
+];
+
+/// There is no crate with the types of the EVM and EAM actors.
+static EVM: &[Method] = &[
+    m(1, "Constructor", &["Creator", "Initcode"]),
+    m(2, "Resurrect", &["Creator", "Initcode"]),
+    m(3, "GetBytecode", &[]),
+    m(4, "GetBytecodeHash", &[]),
+    m(5, "GetStorageAt", &["StorageKey"]),
+    m(
+        6,
+        "InvokeContractDelegate",
+        &["Code", "Input", "Caller", "Value"],
+    ),
+    m(3844450837, "InvokeContract", &[]),
+];
+
+static EAM: &[Method] = &[
+    m(1, "Constructor", &[]),
+    m(2, "Create", &["Initcode", "Nonce"]),
+    m(3, "Create2", &["Initcode", "Salt"]),
+    m(4, "CreateExternal", &[]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const VERSIONS: [ActorVersion; 5] = [
+        ActorVersion::V8,
+        ActorVersion::V9,
+        ActorVersion::V10,
+        ActorVersion::V11,
+        ActorVersion::V12,
+    ];
+
+    #[test]
+    fn methods_are_sorted() {
+        for actor in [
+            BuiltinActor::Account,
+            BuiltinActor::Miner,
+            BuiltinActor::Market,
+            BuiltinActor::Multisig,
+            BuiltinActor::VerifiedRegistry,
+            BuiltinActor::EVM,
+        ] {
+            for version in VERSIONS {
+                assert!(methods(actor, version)
+                    .windows(2)
+                    .all(|pair| pair[0].number < pair[1].number));
+            }
+        }
+        assert_eq!(
+            method(BuiltinActor::DataCap, ActorVersion::V12, 0)
+                .unwrap()
+                .name,
+            "Send"
+        );
+    }
+
+    #[test]
+    fn methods_change_across_versions() {
+        let name = |version, number| {
+            method(BuiltinActor::Market, version, number).map(|method| method.name)
+        };
+        assert_eq!(name(ActorVersion::V11, 6), Some("ActivateDeals"));
+        assert_eq!(name(ActorVersion::V12, 6), Some("BatchActivateDeals"));
+        assert_eq!(name(ActorVersion::V12, 8), None);
+        assert!(method(BuiltinActor::Miner, ActorVersion::V8, 28).is_none());
+        assert!(method(BuiltinActor::Miner, ActorVersion::V12, 33).is_some());
+    }
+
+    #[test]
+    fn named_params_roundtrip() {
+        let params =
+            RawBytes::new(fvm_ipld_encoding::to_vec(&(7_i64, RawBytes::new(vec![1, 2]))).unwrap());
+        let decoded = decode_params(BuiltinActor::Multisig, ActorVersion::V12, 3, &params).unwrap();
+        assert_eq!(decoded.as_object().unwrap().len(), 2);
+        assert!(decoded.get("ID").is_some() && decoded.get("ProposalHash").is_some());
+        assert_eq!(
+            encode_params(BuiltinActor::Multisig, ActorVersion::V12, 3, decoded).unwrap(),
+            params
+        );
+    }
+
+    #[test]
+    fn params_are_checked() {
+        // A trailing field doesn't fit the parameters of `Approve`
+        let params = RawBytes::new(
+            fvm_ipld_encoding::to_vec(&(7_i64, RawBytes::new(vec![1, 2]), 3_u64)).unwrap(),
+        );
+        assert!(decode_params(BuiltinActor::Multisig, ActorVersion::V12, 3, &params).is_err());
+        assert!(encode_params(
+            BuiltinActor::Multisig,
+            ActorVersion::V12,
+            3,
+            json!({ "ID": "a" })
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn unnamed_params_roundtrip() {
+        let params = RawBytes::new(fvm_ipld_encoding::to_vec(&vec![1_u64, 2]).unwrap());
+        let decoded = decode_params(BuiltinActor::Cron, ActorVersion::V8, 2, &params).unwrap();
+        assert!(decoded.is_array());
+        assert_eq!(
+            encode_params(BuiltinActor::Cron, ActorVersion::V8, 2, decoded).unwrap(),
+            params
+        );
+        assert_eq!(
+            encode_params(BuiltinActor::Market, ActorVersion::V12, 2, json!(null)).unwrap(),
+            RawBytes::default()
+        );
+        assert!(encode_params(
+            BuiltinActor::Multisig,
+            ActorVersion::V12,
+            3,
+            json!({ "Foo": null })
+        )
+        .is_err());
+    }
+}
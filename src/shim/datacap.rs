@@ -0,0 +1,47 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Read-only access to the state of the datacap actor, added in actors v9,
+//! which holds the data cap of the verified clients as a fungible token. On
+//! top of the versioned states of [`fil_actor_interface::datacap`].
+
+use fil_actors_shared::frc46_token::{TokenState, DEFAULT_HAMT_BIT_WIDTH};
+use fil_actors_shared::fvm_ipld_hamt::Hamt;
+use fvm_ipld_blockstore::Blockstore;
+use num::BigInt;
+
+pub use fil_actor_interface::datacap::State;
+
+use super::econ::TokenAmount;
+use super::state_tree::ActorState;
+use super::verifreg::{id_key, ActorID};
+
+/// Token units per byte of data cap.
+const TOKEN_PRECISION: u64 = 1_000_000_000_000_000_000;
+
+/// Loads the state of the datacap actor, fails if the actor is of another
+/// kind.
+pub fn load(store: &impl Blockstore, actor: &ActorState) -> anyhow::Result<State> {
+    State::load(store, actor.code, actor.state)
+}
+
+/// Data cap of a verified client, in bytes.
+pub fn verified_client_data_cap(
+    state: &State,
+    store: &impl Blockstore,
+    client: ActorID,
+) -> anyhow::Result<Option<BigInt>> {
+    let token: &TokenState = match state {
+        State::V9(st) => &st.token,
+        State::V10(st) => &st.token,
+        State::V11(st) => &st.token,
+        State::V12(st) => &st.token,
+    };
+    // The bit width of the token isn't exposed, the datacap actor always
+    // creates its token with the default one
+    let balances: Hamt<_, TokenAmount> =
+        Hamt::load_with_bit_width(&token.balances, store, DEFAULT_HAMT_BIT_WIDTH)?;
+    Ok(balances
+        .get(&id_key(client))?
+        .map(|balance| balance.atto() / TOKEN_PRECISION))
+}
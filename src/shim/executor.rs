@@ -10,7 +10,9 @@ use fvm4::executor::ApplyRet as ApplyRet_v4;
 use fvm_ipld_encoding::RawBytes;
 use fvm_shared2::receipt::Receipt as Receipt_v2;
 use fvm_shared3::error::ExitCode;
+use fvm_shared3::event::StampedEvent as StampedEvent_v3;
 pub use fvm_shared3::receipt::Receipt as Receipt_v3;
+use fvm_shared4::event::StampedEvent as StampedEvent_v4;
 use fvm_shared4::receipt::Receipt as Receipt_v4;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -103,6 +105,92 @@ impl ApplyRet {
             ApplyRet::V4(v4) => v4.exec_trace.iter().cloned().map(Into::into).collect(),
         }
     }
+
+    /// Events emitted by the actors, since FVM v3.
+    pub fn events(&self) -> Vec<StampedEvent> {
+        match self {
+            ApplyRet::V2(_) => vec![],
+            ApplyRet::V3(v3) => v3.events.iter().cloned().map(StampedEvent::V3).collect(),
+            ApplyRet::V4(v4) => v4.events.iter().cloned().map(StampedEvent::V4).collect(),
+        }
+    }
+}
+
+/// Bit width of the AMT of events whose root is in the receipts.
+pub const EVENTS_AMT_BITWIDTH: u32 = 5;
+
+/// An event emitted by an actor, as defined by FIP-0049.
+#[derive(PartialEq, Clone, Debug)]
+pub enum StampedEvent {
+    V3(StampedEvent_v3),
+    V4(StampedEvent_v4),
+}
+
+/// Entry of an event, a key and a value encoded with `codec`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct EventEntry {
+    pub flags: u64,
+    pub key: String,
+    pub codec: u64,
+    pub value: Vec<u8>,
+}
+
+impl Serialize for StampedEvent {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            StampedEvent::V3(v3) => v3.serialize(serializer),
+            StampedEvent::V4(v4) => v4.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for StampedEvent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        StampedEvent_v4::deserialize(deserializer).map(StampedEvent::V4)
+    }
+}
+
+impl StampedEvent {
+    /// ID of the actor that emitted the event.
+    pub fn emitter(&self) -> u64 {
+        match self {
+            StampedEvent::V3(v3) => v3.emitter,
+            StampedEvent::V4(v4) => v4.emitter,
+        }
+    }
+
+    pub fn entries(&self) -> Vec<EventEntry> {
+        match self {
+            StampedEvent::V3(v3) => v3
+                .event
+                .entries
+                .iter()
+                .map(|entry| EventEntry {
+                    flags: entry.flags.bits(),
+                    key: entry.key.clone(),
+                    codec: entry.codec,
+                    value: entry.value.clone(),
+                })
+                .collect(),
+            StampedEvent::V4(v4) => v4
+                .event
+                .entries
+                .iter()
+                .map(|entry| EventEntry {
+                    flags: entry.flags.bits(),
+                    key: entry.key.clone(),
+                    codec: entry.codec,
+                    value: entry.value.clone(),
+                })
+                .collect(),
+        }
+    }
 }
 
 #[derive(PartialEq, Clone, Debug)]
@@ -17,7 +17,7 @@ pub use fvm4::gas::{
     Gas as GasV4, GasCharge as GasChargeV4, GasDuration as GasDurationV4, PriceList as PriceListV4,
 };
 
-use crate::shim::version::NetworkVersion;
+use crate::shim::{machine::FvmVersion, version::NetworkVersion};
 
 #[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default)]
 pub struct Gas(Gas_latest);
@@ -56,6 +56,10 @@ impl Gas {
     pub fn round_up(&self) -> u64 {
         self.0.round_up()
     }
+
+    pub fn as_milligas(&self) -> u64 {
+        self.0.as_milligas()
+    }
 }
 
 impl From<GasV2> for Gas {
@@ -227,11 +231,9 @@ impl From<&'static PriceListV4> for PriceList {
 }
 
 pub fn price_list_by_network_version(network_version: NetworkVersion) -> PriceList {
-    if network_version < NetworkVersion::V18 {
-        price_list_by_network_version_v2(network_version.into()).into()
-    } else if network_version < NetworkVersion::V21 {
-        price_list_by_network_version_v3(network_version.into()).into()
-    } else {
-        price_list_by_network_version_v4(network_version.into()).into()
+    match FvmVersion::for_network_version(network_version) {
+        FvmVersion::V2 => price_list_by_network_version_v2(network_version.into()).into(),
+        FvmVersion::V3 => price_list_by_network_version_v3(network_version.into()).into(),
+        FvmVersion::V4 => price_list_by_network_version_v4(network_version.into()).into(),
     }
 }
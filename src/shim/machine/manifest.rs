@@ -99,6 +99,19 @@ impl BuiltinActorManifest {
     pub fn builtin_actors(&self) -> impl ExactSizeIterator<Item = (BuiltinActor, Cid)> + '_ {
         self.builtin2cid.iter().map(|(k, v)| (*k, *v)) // std::iter::Copied doesn't play well with the tuple here
     }
+    /// The builtin actor with the given name in the actor bundles, e.g.
+    /// `storageminer`
+    pub fn builtin_actor_named(name: &str) -> Option<BuiltinActor> {
+        ALL_BUILTINS
+            .iter()
+            .copied()
+            .find(|builtin| builtin.name() == name)
+    }
+    /// The builtin actor whose code is `code`, if it is in this manifest
+    pub fn builtin_actor_of(&self, code: &Cid) -> Option<BuiltinActor> {
+        self.builtin_actors()
+            .find_map(|(builtin, cid)| (cid == *code).then_some(builtin))
+    }
 }
 
 // https://github.com/ChainSafe/fil-actor-states/issues/171
@@ -1,12 +1,68 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use super::version::NetworkVersion;
 use fvm2::machine::MultiEngine as MultiEngine_v2;
 use fvm3::engine::MultiEngine as MultiEngine_v3;
 use fvm4::engine::MultiEngine as MultiEngine_v4;
 pub use manifest::{BuiltinActor, BuiltinActorManifest};
 mod manifest;
 
+/// Major version of the FVM. Every network version is executed by exactly one
+/// of them, so that historical epochs replay on the FVM they were produced
+/// with while new network versions run on the latest one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FvmVersion {
+    V2,
+    V3,
+    V4,
+}
+
+impl FvmVersion {
+    /// Returns the FVM that executes `network_version`.
+    pub fn for_network_version(network_version: NetworkVersion) -> Self {
+        if network_version >= NetworkVersion::V21 {
+            FvmVersion::V4
+        } else if network_version >= NetworkVersion::V18 {
+            FvmVersion::V3
+        } else {
+            FvmVersion::V2
+        }
+    }
+}
+
+/// Major version of the builtin actors, which fixes the methods they export
+/// and the layout of their parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ActorVersion {
+    V8,
+    V9,
+    V10,
+    V11,
+    V12,
+}
+
+impl ActorVersion {
+    /// Returns the actors deployed at `network_version`, `None` before the
+    /// first actor bundle with a manifest (v8).
+    pub fn for_network_version(network_version: NetworkVersion) -> Option<Self> {
+        Some(if network_version >= NetworkVersion::V21 {
+            ActorVersion::V12
+        } else if network_version >= NetworkVersion::V19 {
+            ActorVersion::V11
+        } else if network_version >= NetworkVersion::V18 {
+            ActorVersion::V10
+        } else if network_version >= NetworkVersion::V17 {
+            ActorVersion::V9
+        } else if network_version >= NetworkVersion::V16 {
+            ActorVersion::V8
+        } else {
+            return None;
+        })
+    }
+}
+
+/// Wasm engines of all the supported FVM versions.
 pub struct MultiEngine {
     pub v2: MultiEngine_v2,
     pub v3: MultiEngine_v3,
@@ -29,3 +85,25 @@ impl MultiEngine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fvm_version_for_network_version() {
+        for (network_version, fvm_version) in [
+            (NetworkVersion::V0, FvmVersion::V2),
+            (NetworkVersion::V17, FvmVersion::V2),
+            (NetworkVersion::V18, FvmVersion::V3),
+            (NetworkVersion::V20, FvmVersion::V3),
+            (NetworkVersion::V21, FvmVersion::V4),
+            (NetworkVersion::V23, FvmVersion::V4),
+        ] {
+            assert_eq!(
+                FvmVersion::for_network_version(network_version),
+                fvm_version
+            );
+        }
+    }
+}
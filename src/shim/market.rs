@@ -0,0 +1,180 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Read-only access to the deals of the storage market actor, on top of the
+//! versioned states of [`fil_actor_interface::market`].
+
+use base64::{prelude::BASE64_STANDARD, Engine as _};
+use fil_actor_interface::market::{
+    is_v10_market_cid, is_v11_market_cid, is_v12_market_cid, is_v8_market_cid, is_v9_market_cid,
+};
+use fvm_ipld_blockstore::Blockstore;
+
+pub use fil_actor_interface::market::{DealProposal, DealState, State};
+
+use super::address::Address;
+use super::deal::DealID;
+use super::econ::TokenAmount;
+use super::state_tree::ActorState;
+
+/// State of the deals that are not activated yet.
+pub const UNSTARTED_DEAL_STATE: DealState = DealState {
+    sector_start_epoch: -1,
+    last_updated_epoch: -1,
+    slash_epoch: -1,
+};
+
+/// Loads the state of the market actor. Only the states of actors v8 onwards
+/// can be read.
+pub fn load(store: &impl Blockstore, actor: &ActorState) -> anyhow::Result<State> {
+    let supported = [
+        is_v8_market_cid,
+        is_v9_market_cid,
+        is_v10_market_cid,
+        is_v11_market_cid,
+        is_v12_market_cid,
+    ];
+    anyhow::ensure!(
+        supported
+            .iter()
+            .any(|is_supported| is_supported(&actor.code)),
+        "unsupported market actor code {}, only the market states of actors v8 to v12 can be read",
+        actor.code
+    );
+    State::load(store, actor.code, actor.state)
+}
+
+/// Converts a deal proposal of the actors `$version`.
+macro_rules! convert_proposal {
+    ($proposal:expr, $version:ident) => {{
+        let proposal = $proposal;
+        DealProposal {
+            piece_cid: proposal.piece_cid,
+            piece_size: fvm_shared2::piece::PaddedPieceSize(proposal.piece_size.0),
+            verified_deal: proposal.verified_deal,
+            client: Address::from(&proposal.client).into(),
+            provider: Address::from(&proposal.provider).into(),
+            // Like Lotus, labels that aren't strings are shown in base64
+            label: match &proposal.label {
+                fil_actor_market_state::$version::Label::String(label) => label.clone(),
+                fil_actor_market_state::$version::Label::Bytes(label) => {
+                    BASE64_STANDARD.encode(label)
+                }
+            },
+            start_epoch: proposal.start_epoch,
+            end_epoch: proposal.end_epoch,
+            storage_price_per_epoch: TokenAmount::from(&proposal.storage_price_per_epoch).into(),
+            provider_collateral: TokenAmount::from(&proposal.provider_collateral).into(),
+            client_collateral: TokenAmount::from(&proposal.client_collateral).into(),
+        }
+    }};
+}
+
+/// Loads the array of the `$field` of the deals of the actors `$version`.
+macro_rules! load_deal_array {
+    ($state:expr, $field:ident, $type:ident, $version:ident, $store:expr) => {
+        fil_actors_shared::$version::Array::<fil_actor_market_state::$version::$type, _>::load(
+            &$state.$field,
+            $store,
+        )?
+    };
+}
+
+/// Returns the proposal of the deal `id`, if any.
+pub fn proposal(
+    state: &State,
+    store: &impl Blockstore,
+    id: DealID,
+) -> anyhow::Result<Option<DealProposal>> {
+    macro_rules! get_proposal {
+        ($state:expr, $version:ident) => {
+            load_deal_array!($state, proposals, DealProposal, $version, store)
+                .get(id)?
+                .map(|proposal| convert_proposal!(proposal, $version))
+        };
+    }
+    Ok(match state {
+        State::V8(st) => get_proposal!(st, v8),
+        State::V9(st) => get_proposal!(st, v9),
+        State::V10(st) => get_proposal!(st, v10),
+        State::V11(st) => get_proposal!(st, v11),
+        State::V12(st) => get_proposal!(st, v12),
+    })
+}
+
+/// Returns the state of the deal `id`, `None` until the deal is activated.
+pub fn deal_state(
+    state: &State,
+    store: &impl Blockstore,
+    id: DealID,
+) -> anyhow::Result<Option<DealState>> {
+    macro_rules! get_state {
+        ($state:expr, $version:ident) => {
+            load_deal_array!($state, states, DealState, $version, store)
+                .get(id)?
+                .map(|state| DealState {
+                    sector_start_epoch: state.sector_start_epoch,
+                    last_updated_epoch: state.last_updated_epoch,
+                    slash_epoch: state.slash_epoch,
+                })
+        };
+    }
+    Ok(match state {
+        State::V8(st) => get_state!(st, v8),
+        State::V9(st) => get_state!(st, v9),
+        State::V10(st) => get_state!(st, v10),
+        State::V11(st) => get_state!(st, v11),
+        State::V12(st) => get_state!(st, v12),
+    })
+}
+
+/// Calls `f` on the proposals of at most `limit` deals, in the order of their
+/// IDs from `start` on. Returns the ID of the next deal, if any, to resume
+/// from.
+pub fn for_each_proposal(
+    state: &State,
+    store: &impl Blockstore,
+    start: DealID,
+    limit: Option<u64>,
+    mut f: impl FnMut(DealID, DealProposal) -> anyhow::Result<()>,
+) -> anyhow::Result<Option<DealID>> {
+    macro_rules! for_each {
+        ($state:expr, $version:ident) => {{
+            let (_, next) = load_deal_array!($state, proposals, DealProposal, $version, store)
+                .for_each_ranged(Some(start), limit, |id, proposal| {
+                    f(id, convert_proposal!(proposal, $version))
+                })?;
+            next
+        }};
+    }
+    Ok(match state {
+        State::V8(st) => for_each!(st, v8),
+        State::V9(st) => for_each!(st, v9),
+        State::V10(st) => for_each!(st, v10),
+        State::V11(st) => for_each!(st, v11),
+        State::V12(st) => for_each!(st, v12),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+    use cid::{
+        multihash::{Code, MultihashDigest as _},
+        Cid,
+    };
+    use num_traits::Zero as _;
+
+    #[test]
+    fn old_market_states_are_refused() {
+        // Code of the market actor of actors v7
+        let code = Cid::new_v1(
+            fvm_ipld_encoding::IPLD_RAW,
+            Code::Identity.digest(b"fil/7/storagemarket"),
+        );
+        let actor = ActorState::new(code, Cid::default(), TokenAmount::zero(), 0, None);
+        let error = load(&MemoryDB::default(), &actor).unwrap_err();
+        assert!(error.to_string().contains("actors v8 to v12"));
+    }
+}
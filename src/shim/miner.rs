@@ -0,0 +1,479 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Read-only access to the sectors of the miner actor, on top of the
+//! versioned states of [`fil_actor_miner_state`]. The sector and pre-commit
+//! records changed over time: every layout of those is read into a single set
+//! of types.
+
+use anyhow::Context as _;
+use cid::Cid;
+use fil_actor_interface::miner::{
+    is_v10_miner_cid, is_v11_miner_cid, is_v12_miner_cid, is_v8_miner_cid, is_v9_miner_cid,
+};
+use fil_actor_miner_state::{v10, v11, v12, v8, v9};
+use fil_actors_shared::fvm_ipld_amt::Amt;
+use fil_actors_shared::fvm_ipld_bitfield::BitField;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore as _;
+use num::BigInt;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::clock::ChainEpoch;
+use super::deal::DealID;
+use super::econ::TokenAmount;
+use super::sector::{RegisteredSealProof, SectorNumber};
+use super::state_tree::ActorState;
+
+/// State of the miner actor.
+pub enum State {
+    V8(v8::State),
+    V9(v9::State),
+    V10(v10::State),
+    V11(v11::State),
+    V12(v12::State),
+}
+
+/// A proven sector, in the layout of the latest actors. Older sectors have
+/// their power base epoch at their activation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectorOnChainInfo {
+    pub sector_number: SectorNumber,
+    pub seal_proof: RegisteredSealProof,
+    pub sealed_cid: Cid,
+    pub deal_ids: Vec<DealID>,
+    pub activation: ChainEpoch,
+    pub expiration: ChainEpoch,
+    pub deal_weight: BigInt,
+    pub verified_deal_weight: BigInt,
+    pub initial_pledge: TokenAmount,
+    pub expected_day_reward: TokenAmount,
+    pub expected_storage_pledge: TokenAmount,
+    /// Age of the replaced sector, up to actors v11
+    pub replaced_sector_age: ChainEpoch,
+    /// Epoch the power of the sector is computed from, since actors v12
+    pub power_base_epoch: ChainEpoch,
+    pub replaced_day_reward: TokenAmount,
+    pub sector_key_cid: Option<Cid>,
+    pub simple_qa_power: bool,
+}
+
+/// Parameters of a pre-committed sector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectorPreCommitInfo {
+    pub seal_proof: RegisteredSealProof,
+    pub sector_number: SectorNumber,
+    pub sealed_cid: Cid,
+    pub seal_rand_epoch: ChainEpoch,
+    pub deal_ids: Vec<DealID>,
+    pub expiration: ChainEpoch,
+    /// `CommD`, since actors v9
+    pub unsealed_cid: Option<Cid>,
+}
+
+/// A sector pre-committed, but not proven yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectorPreCommitOnChainInfo {
+    pub info: SectorPreCommitInfo,
+    pub pre_commit_deposit: TokenAmount,
+    pub pre_commit_epoch: ChainEpoch,
+}
+
+/// Epochs a sector expires at, on time or early if it's faulty.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SectorExpiration {
+    pub on_time: ChainEpoch,
+    pub early: ChainEpoch,
+}
+
+impl State {
+    /// Loads the state of a miner actor, in the layout of the actor version of
+    /// its code. Fails if the actor is of another kind.
+    pub fn load(store: &impl Blockstore, actor: &ActorState) -> anyhow::Result<Self> {
+        let code = &actor.code;
+        let not_found = || format!("miner state {} not found", actor.state);
+        if is_v8_miner_cid(code) {
+            return Ok(State::V8(
+                store.get_cbor(&actor.state)?.with_context(not_found)?,
+            ));
+        }
+        if is_v9_miner_cid(code) {
+            return Ok(State::V9(
+                store.get_cbor(&actor.state)?.with_context(not_found)?,
+            ));
+        }
+        if is_v10_miner_cid(code) {
+            return Ok(State::V10(
+                store.get_cbor(&actor.state)?.with_context(not_found)?,
+            ));
+        }
+        if is_v11_miner_cid(code) {
+            return Ok(State::V11(
+                store.get_cbor(&actor.state)?.with_context(not_found)?,
+            ));
+        }
+        if is_v12_miner_cid(code) {
+            return Ok(State::V12(
+                store.get_cbor(&actor.state)?.with_context(not_found)?,
+            ));
+        }
+        anyhow::bail!("unknown miner actor code {code}")
+    }
+
+    /// Balance of the miner that isn't locked by pre-commit deposits, vesting
+    /// funds or pledges, minus its fee debt. Fails if the locked funds exceed
+    /// the balance.
+    pub fn available_balance(&self, balance: &TokenAmount) -> anyhow::Result<TokenAmount> {
+        Ok(match self {
+            State::V8(st) => st.get_available_balance(&balance.into())?.into(),
+            State::V9(st) => st.get_available_balance(&balance.into())?.into(),
+            State::V10(st) => st.get_available_balance(&balance.into())?.into(),
+            State::V11(st) => st.get_available_balance(&balance.into())?.into(),
+            State::V12(st) => st.get_available_balance(&balance.into())?.into(),
+        })
+    }
+
+    /// Returns the proven sector `number`, if any.
+    pub fn sector(
+        &self,
+        store: &impl Blockstore,
+        number: SectorNumber,
+    ) -> anyhow::Result<Option<SectorOnChainInfo>> {
+        Ok(match self {
+            State::V8(st) => st.get_sector(store, number)?.map(From::from),
+            State::V9(st) => st.get_sector(store, number)?.map(From::from),
+            State::V10(st) => st.get_sector(store, number)?.map(From::from),
+            State::V11(st) => st.get_sector(store, number)?.map(From::from),
+            State::V12(st) => st.get_sector(store, number)?.map(From::from),
+        })
+    }
+
+    /// Returns the proven sectors, only those in `filter` if any. Like Lotus,
+    /// the sectors of `filter` that aren't proven are skipped.
+    pub fn sectors(
+        &self,
+        store: &impl Blockstore,
+        filter: Option<&BitField>,
+    ) -> anyhow::Result<Vec<SectorOnChainInfo>> {
+        macro_rules! collect_sectors {
+            ($state:expr) => {{
+                let mut out = vec![];
+                match filter {
+                    Some(filter) => {
+                        for number in filter.iter() {
+                            out.extend($state.get_sector(store, number)?.map(From::from));
+                        }
+                    }
+                    None => $state.for_each_sector(store, |sector| {
+                        out.push(sector.clone().into());
+                        Ok(())
+                    })?,
+                }
+                out
+            }};
+        }
+        Ok(match self {
+            State::V8(st) => collect_sectors!(st),
+            State::V9(st) => collect_sectors!(st),
+            State::V10(st) => collect_sectors!(st),
+            State::V11(st) => collect_sectors!(st),
+            State::V12(st) => collect_sectors!(st),
+        })
+    }
+
+    /// Returns the pre-committed sector `number`, if any.
+    pub fn pre_committed_sector(
+        &self,
+        store: &impl Blockstore,
+        number: SectorNumber,
+    ) -> anyhow::Result<Option<SectorPreCommitOnChainInfo>> {
+        Ok(match self {
+            State::V8(st) => st.get_precommitted_sector(store, number)?.map(From::from),
+            State::V9(st) => st.get_precommitted_sector(store, number)?.map(From::from),
+            State::V10(st) => st.get_precommitted_sector(store, number)?.map(From::from),
+            State::V11(st) => st.get_precommitted_sector(store, number)?.map(From::from),
+            State::V12(st) => st.get_precommitted_sector(store, number)?.map(From::from),
+        })
+    }
+
+    /// Returns the expiration epochs of sector `number`, from the expiration
+    /// queue of its partition. Fails if the sector is terminated, and returns
+    /// `None` if the sector isn't in any partition.
+    pub fn sector_expiration(
+        &self,
+        store: &impl Blockstore,
+        number: SectorNumber,
+    ) -> anyhow::Result<Option<SectorExpiration>> {
+        macro_rules! find_expiration {
+            ($state:expr, $version:ident) => {{
+                let deadlines = $state.load_deadlines(store)?;
+                let mut found = None;
+                for deadline in &deadlines.due {
+                    let deadline: $version::Deadline = store
+                        .get_cbor(deadline)?
+                        .with_context(|| format!("miner deadline {deadline} not found"))?;
+                    deadline
+                        .partitions_amt(store)?
+                        .for_each_while(|_, partition| {
+                            found = partition_expiration::<$version::ExpirationSet>(
+                                store,
+                                &partition.sectors,
+                                &partition.terminated,
+                                &partition.expirations_epochs,
+                                number,
+                            )
+                            .transpose();
+                            Ok(found.is_none())
+                        })?;
+                    if found.is_some() {
+                        break;
+                    }
+                }
+                found.transpose()
+            }};
+        }
+        match self {
+            State::V8(st) => find_expiration!(st, v8),
+            State::V9(st) => find_expiration!(st, v9),
+            State::V10(st) => find_expiration!(st, v10),
+            State::V11(st) => find_expiration!(st, v11),
+            State::V12(st) => find_expiration!(st, v12),
+        }
+    }
+}
+
+/// Sectors of an expiration set, in any actor version.
+trait ExpirationSectors: Serialize + DeserializeOwned {
+    fn on_time_sectors(&self) -> &BitField;
+    fn early_sectors(&self) -> &BitField;
+}
+
+macro_rules! impl_expiration_sectors {
+    ($($version:ident),*) => {
+        $(
+            impl ExpirationSectors for $version::ExpirationSet {
+                fn on_time_sectors(&self) -> &BitField {
+                    &self.on_time_sectors
+                }
+
+                fn early_sectors(&self) -> &BitField {
+                    &self.early_sectors
+                }
+            }
+        )*
+    };
+}
+
+impl_expiration_sectors!(v8, v9, v10, v11, v12);
+
+/// Looks the sector up in a partition, given by its sectors, its terminated
+/// sectors and its expiration queue. `None` if the sector isn't there.
+fn partition_expiration<E: ExpirationSectors>(
+    store: &impl Blockstore,
+    sectors: &BitField,
+    terminated: &BitField,
+    expirations: &Cid,
+    number: SectorNumber,
+) -> anyhow::Result<Option<SectorExpiration>> {
+    if !sectors.get(number) {
+        return Ok(None);
+    }
+    anyhow::ensure!(!terminated.get(number), "sector {number} is terminated");
+    let queue = Amt::<E, _>::load(expirations, store)?;
+    let mut expiration = SectorExpiration::default();
+    queue.for_each_while(|epoch, set| {
+        if set.early_sectors().get(number) {
+            expiration.early = epoch as ChainEpoch;
+        }
+        if set.on_time_sectors().get(number) {
+            expiration.on_time = epoch as ChainEpoch;
+            return Ok(false);
+        }
+        Ok(true)
+    })?;
+    anyhow::ensure!(
+        expiration.on_time != 0,
+        "sector {number} not found in the expiration queue"
+    );
+    Ok(Some(expiration))
+}
+
+impl From<v12::SectorOnChainInfo> for SectorOnChainInfo {
+    fn from(info: v12::SectorOnChainInfo) -> Self {
+        SectorOnChainInfo {
+            sector_number: info.sector_number,
+            seal_proof: info.seal_proof.into(),
+            sealed_cid: info.sealed_cid,
+            deal_ids: info.deal_ids,
+            activation: info.activation,
+            expiration: info.expiration,
+            deal_weight: info.deal_weight,
+            verified_deal_weight: info.verified_deal_weight,
+            initial_pledge: info.initial_pledge.into(),
+            expected_day_reward: info.expected_day_reward.into(),
+            expected_storage_pledge: info.expected_storage_pledge.into(),
+            replaced_sector_age: 0,
+            power_base_epoch: info.power_base_epoch,
+            replaced_day_reward: info.replaced_day_reward.into(),
+            sector_key_cid: info.sector_key_cid,
+            simple_qa_power: info
+                .flags
+                .contains(v12::SectorOnChainInfoFlags::SIMPLE_QA_POWER),
+        }
+    }
+}
+
+/// Sectors of actors v9 to v11 share the same layout.
+macro_rules! impl_sector_from {
+    ($($version:ident),*) => {
+        $(
+            impl From<$version::SectorOnChainInfo> for SectorOnChainInfo {
+                fn from(info: $version::SectorOnChainInfo) -> Self {
+                    SectorOnChainInfo {
+                        sector_number: info.sector_number,
+                        seal_proof: info.seal_proof.into(),
+                        sealed_cid: info.sealed_cid,
+                        deal_ids: info.deal_ids,
+                        activation: info.activation,
+                        expiration: info.expiration,
+                        deal_weight: info.deal_weight,
+                        verified_deal_weight: info.verified_deal_weight,
+                        initial_pledge: info.initial_pledge.into(),
+                        expected_day_reward: info.expected_day_reward.into(),
+                        expected_storage_pledge: info.expected_storage_pledge.into(),
+                        replaced_sector_age: info.replaced_sector_age,
+                        power_base_epoch: info.activation,
+                        replaced_day_reward: info.replaced_day_reward.into(),
+                        sector_key_cid: info.sector_key_cid,
+                        simple_qa_power: info.simple_qa_power,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_sector_from!(v9, v10, v11);
+
+impl From<v8::SectorOnChainInfo> for SectorOnChainInfo {
+    fn from(info: v8::SectorOnChainInfo) -> Self {
+        SectorOnChainInfo {
+            sector_number: info.sector_number,
+            seal_proof: info.seal_proof.into(),
+            sealed_cid: info.sealed_cid,
+            deal_ids: info.deal_ids,
+            activation: info.activation,
+            expiration: info.expiration,
+            deal_weight: info.deal_weight,
+            verified_deal_weight: info.verified_deal_weight,
+            initial_pledge: info.initial_pledge.into(),
+            expected_day_reward: info.expected_day_reward.into(),
+            expected_storage_pledge: info.expected_storage_pledge.into(),
+            replaced_sector_age: info.replaced_sector_age,
+            power_base_epoch: info.activation,
+            replaced_day_reward: info.replaced_day_reward.into(),
+            sector_key_cid: info.sector_key_cid,
+            simple_qa_power: false,
+        }
+    }
+}
+
+/// Pre-commits of actors v9 on share the same layout.
+macro_rules! impl_pre_commit_from {
+    ($($version:ident),*) => {
+        $(
+            impl From<$version::SectorPreCommitOnChainInfo> for SectorPreCommitOnChainInfo {
+                fn from(pre_commit: $version::SectorPreCommitOnChainInfo) -> Self {
+                    let info = pre_commit.info;
+                    SectorPreCommitOnChainInfo {
+                        info: SectorPreCommitInfo {
+                            seal_proof: info.seal_proof.into(),
+                            sector_number: info.sector_number,
+                            sealed_cid: info.sealed_cid,
+                            seal_rand_epoch: info.seal_rand_epoch,
+                            deal_ids: info.deal_ids,
+                            expiration: info.expiration,
+                            unsealed_cid: info.unsealed_cid.0,
+                        },
+                        pre_commit_deposit: pre_commit.pre_commit_deposit.into(),
+                        pre_commit_epoch: pre_commit.pre_commit_epoch,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_pre_commit_from!(v9, v10, v11, v12);
+
+impl From<v8::SectorPreCommitOnChainInfo> for SectorPreCommitOnChainInfo {
+    fn from(pre_commit: v8::SectorPreCommitOnChainInfo) -> Self {
+        let info = pre_commit.info;
+        SectorPreCommitOnChainInfo {
+            info: SectorPreCommitInfo {
+                seal_proof: info.seal_proof.into(),
+                sector_number: info.sector_number,
+                sealed_cid: info.sealed_cid,
+                seal_rand_epoch: info.seal_rand_epoch,
+                deal_ids: info.deal_ids,
+                expiration: info.expiration,
+                unsealed_cid: None,
+            },
+            pre_commit_deposit: pre_commit.pre_commit_deposit.into(),
+            pre_commit_epoch: pre_commit.pre_commit_epoch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+
+    fn bit_field(sectors: &[u64]) -> BitField {
+        BitField::try_from_bits(sectors.iter().copied()).unwrap()
+    }
+
+    fn expiration_queue(store: &MemoryDB) -> Cid {
+        let mut queue = Amt::new(store);
+        let mut early = v12::ExpirationSet::empty();
+        early.early_sectors = bit_field(&[2]);
+        queue.set(100, early).unwrap();
+        let mut on_time = v12::ExpirationSet::empty();
+        on_time.on_time_sectors = bit_field(&[1, 2]);
+        queue.set(200, on_time).unwrap();
+        queue.flush().unwrap()
+    }
+
+    #[test]
+    fn expiration_from_queue() {
+        let store = MemoryDB::default();
+        let queue = expiration_queue(&store);
+        let expiration = |number| {
+            partition_expiration::<v12::ExpirationSet>(
+                &store,
+                &bit_field(&[1, 2, 3]),
+                &bit_field(&[3]),
+                &queue,
+                number,
+            )
+        };
+
+        assert_eq!(
+            expiration(1).unwrap(),
+            Some(SectorExpiration {
+                on_time: 200,
+                early: 0
+            })
+        );
+        assert_eq!(
+            expiration(2).unwrap(),
+            Some(SectorExpiration {
+                on_time: 200,
+                early: 100
+            })
+        );
+        assert!(expiration(3).is_err());
+        assert_eq!(expiration(4).unwrap(), None);
+    }
+}
@@ -1,10 +1,12 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+pub mod actor_methods;
 pub mod address;
 pub mod bigint;
 pub mod clock;
 pub mod crypto;
+pub mod datacap;
 pub mod deal;
 pub mod econ;
 pub mod error;
@@ -13,13 +15,19 @@ pub mod externs;
 pub mod gas;
 pub mod kernel;
 pub mod machine;
+pub mod market;
 pub mod message;
+pub mod miner;
+pub mod multisig;
+pub mod params;
+pub mod paych;
 pub mod piece;
 pub mod randomness;
 pub mod sector;
 pub mod state_tree;
 pub mod state_tree_v0;
 pub mod trace;
+pub mod verifreg;
 pub mod version;
 
 pub mod fvm_shared_latest {
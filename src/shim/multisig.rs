@@ -0,0 +1,132 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Read-only access to the state of the multisig actor, on top of the
+//! versioned states of [`fil_actor_interface::multisig`].
+
+use fil_actors_shared::fvm_ipld_hamt::BytesKey;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::RawBytes;
+use integer_encoding::VarInt as _;
+
+pub use fil_actor_interface::multisig::State;
+
+use super::address::Address;
+use super::clock::ChainEpoch;
+use super::econ::TokenAmount;
+use super::state_tree::ActorState;
+
+/// A proposed transaction, waiting for the approval of enough signers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub to: Address,
+    pub value: TokenAmount,
+    pub method: u64,
+    pub params: RawBytes,
+    pub approved: Vec<Address>,
+}
+
+/// Loads the state of a multisig actor, fails if the actor is of another
+/// kind.
+pub fn load(store: &impl Blockstore, actor: &ActorState) -> anyhow::Result<State> {
+    State::load(store, actor.code, actor.state)
+}
+
+/// Balance still locked by the vesting schedule at `epoch`.
+pub fn locked_balance(state: &State, epoch: ChainEpoch) -> TokenAmount {
+    match state {
+        State::V8(st) => st.amount_locked(epoch - st.start_epoch).into(),
+        State::V9(st) => st.amount_locked(epoch - st.start_epoch).into(),
+        State::V10(st) => st.amount_locked(epoch - st.start_epoch).into(),
+        State::V11(st) => st.amount_locked(epoch - st.start_epoch).into(),
+        State::V12(st) => st.amount_locked(epoch - st.start_epoch).into(),
+    }
+}
+
+/// Returns the pending transactions, with their IDs.
+pub fn pending_transactions(
+    state: &State,
+    store: &impl Blockstore,
+) -> anyhow::Result<Vec<(i64, Transaction)>> {
+    macro_rules! collect_transactions {
+        ($state:expr, $version:ident, $state_version:ident) => {{
+            let pending = fil_actors_shared::$version::make_map_with_root_and_bitwidth::<
+                _,
+                fil_actor_multisig_state::$state_version::Transaction,
+            >(
+                &$state.pending_txs,
+                store,
+                fil_actors_shared::$version::builtin::HAMT_BIT_WIDTH,
+            )?;
+            let mut out = Vec::new();
+            pending.for_each(|key: &BytesKey, transaction| {
+                out.push((
+                    parse_tx_id(key)?,
+                    Transaction {
+                        to: (&transaction.to).into(),
+                        value: (&transaction.value).into(),
+                        method: transaction.method,
+                        params: transaction.params.clone(),
+                        approved: transaction.approved.iter().map(From::from).collect(),
+                    },
+                ));
+                Ok(())
+            })?;
+            out
+        }};
+    }
+    Ok(match state {
+        State::V8(st) => collect_transactions!(st, v8, v8),
+        State::V9(st) => collect_transactions!(st, v9, v9),
+        State::V10(st) => collect_transactions!(st, v10, v10),
+        State::V11(st) => collect_transactions!(st, v11, v11),
+        // The `v12` variant holds the `v11` state, the layout didn't change
+        State::V12(st) => collect_transactions!(st, v12, v11),
+    })
+}
+
+/// Transaction IDs are keyed by their signed varint encoding.
+fn parse_tx_id(key: &[u8]) -> anyhow::Result<i64> {
+    match i64::decode_var(key) {
+        Some((id, len)) if len == key.len() => Ok(id),
+        _ => anyhow::bail!("invalid transaction ID key {key:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cid::Cid;
+    use num_traits::Zero as _;
+
+    fn state(initial_balance: u64, start_epoch: ChainEpoch, unlock_duration: ChainEpoch) -> State {
+        State::V11(fil_actor_multisig_state::v11::State {
+            signers: vec![],
+            num_approvals_threshold: 1,
+            next_tx_id: fil_actor_multisig_state::v11::TxnID(0),
+            initial_balance: TokenAmount::from_atto(initial_balance).into(),
+            start_epoch,
+            unlock_duration,
+            pending_txs: Cid::default(),
+        })
+    }
+
+    #[test]
+    fn vesting_schedule() {
+        let state = state(100, 10, 3);
+        assert_eq!(locked_balance(&state, 0), TokenAmount::from_atto(100));
+        assert_eq!(locked_balance(&state, 10), TokenAmount::from_atto(100));
+        // 100 * 2 / 3, rounded up
+        assert_eq!(locked_balance(&state, 11), TokenAmount::from_atto(67));
+        assert_eq!(locked_balance(&state, 12), TokenAmount::from_atto(34));
+        assert!(locked_balance(&state, 13).is_zero());
+    }
+
+    #[test]
+    fn tx_id_keys() {
+        for id in [0, 1, -1, 300, i64::MAX] {
+            assert_eq!(parse_tx_id(&id.encode_var_vec()).unwrap(), id);
+        }
+        assert!(parse_tx_id(&[]).is_err());
+    }
+}
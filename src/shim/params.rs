@@ -0,0 +1,183 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Consensus parameters of a network, as they apply at a given network
+//! version.
+
+use crate::chain::{BASE_FEE_MAX_CHANGE_DENOM, BLOCK_GAS_TARGET, MINIMUM_BASE_FEE};
+use crate::networks::{sort_by_epoch, ChainConfig, Height};
+use crate::shim::clock::ChainEpoch;
+use crate::shim::econ::{TokenAmount, BLOCK_GAS_LIMIT};
+use crate::shim::machine::FvmVersion;
+use crate::shim::sector::{SectorSize, StoragePower};
+use crate::shim::version::NetworkVersion;
+
+/// A scheduled network upgrade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Upgrade {
+    pub height: Height,
+    pub epoch: ChainEpoch,
+    pub network_version: NetworkVersion,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkParams {
+    pub network_version: NetworkVersion,
+    pub fvm_version: FvmVersion,
+    pub block_delay_secs: u32,
+    pub propagation_delay_secs: u32,
+    pub block_gas_limit: u64,
+    pub block_gas_target: u64,
+    /// Lowest base fee, which it never falls below.
+    pub minimum_base_fee: TokenAmount,
+    /// The base fee changes by at most `1 / base_fee_max_change_denom` from an
+    /// epoch to the next.
+    pub base_fee_max_change_denom: i64,
+    pub consensus_miner_min_power: StoragePower,
+    pub pre_commit_challenge_delay: ChainEpoch,
+    /// Sizes of the sectors that can be pre-committed, in ascending order.
+    pub sector_sizes: Vec<SectorSize>,
+    pub eth_chain_id: u32,
+    /// All upgrades of the network, in the order they are scheduled.
+    pub upgrades: Vec<Upgrade>,
+}
+
+impl NetworkParams {
+    /// Parameters of the network described by `config` at `network_version`.
+    pub fn new(config: &ChainConfig, network_version: NetworkVersion) -> Self {
+        let policy = &config.policy;
+        let mut sector_sizes = policy
+            .valid_pre_commit_proof_type
+            .iter()
+            .filter_map(|proof| proof.sector_size().ok())
+            .map(SectorSize::from)
+            .collect::<Vec<_>>();
+        sector_sizes.sort_by_key(|size| *size as u64);
+        sector_sizes.dedup();
+
+        NetworkParams {
+            network_version,
+            fvm_version: FvmVersion::for_network_version(network_version),
+            block_delay_secs: config.block_delay_secs,
+            propagation_delay_secs: config.propagation_delay_secs,
+            block_gas_limit: BLOCK_GAS_LIMIT,
+            block_gas_target: BLOCK_GAS_TARGET,
+            minimum_base_fee: TokenAmount::from_atto(MINIMUM_BASE_FEE),
+            base_fee_max_change_denom: BASE_FEE_MAX_CHANGE_DENOM,
+            consensus_miner_min_power: policy.minimum_consensus_power.clone(),
+            pre_commit_challenge_delay: policy.pre_commit_challenge_delay,
+            sector_sizes,
+            eth_chain_id: config.eth_chain_id,
+            upgrades: upgrade_schedule(config),
+        }
+    }
+
+    /// Parameters of the network described by `config` at `epoch`.
+    pub fn at_epoch(config: &ChainConfig, epoch: ChainEpoch) -> Self {
+        Self::new(config, config.network_version(epoch))
+    }
+}
+
+/// Network versions of the epochs `from..=to`, as `(first epoch, last epoch,
+/// version)` ranges in ascending order.
+pub fn network_versions(
+    config: &ChainConfig,
+    from: ChainEpoch,
+    to: ChainEpoch,
+) -> Vec<(ChainEpoch, ChainEpoch, NetworkVersion)> {
+    let mut ranges = vec![];
+    if from > to {
+        return ranges;
+    }
+    let mut start = from;
+    let mut network_version = config.network_version(from);
+    // The new version of an upgrade applies from the epoch after it
+    for upgrade in upgrade_schedule(config) {
+        let first = upgrade.epoch.saturating_add(1);
+        if first <= start || first > to {
+            continue;
+        }
+        let next = config.network_version(first);
+        if next != network_version {
+            ranges.push((start, first - 1, network_version));
+            start = first;
+            network_version = next;
+        }
+    }
+    ranges.push((start, to, network_version));
+    ranges
+}
+
+/// All upgrades of the network described by `config`, ordered by epoch.
+pub fn upgrade_schedule(config: &ChainConfig) -> Vec<Upgrade> {
+    sort_by_epoch(&config.height_infos)
+        .into_iter()
+        .map(|info| Upgrade {
+            height: info.height,
+            epoch: info.epoch,
+            network_version: info.height.into(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mainnet_params() {
+        let config = ChainConfig::mainnet();
+        let params = NetworkParams::new(&config, NetworkVersion::V21);
+        assert_eq!(params.fvm_version, FvmVersion::V4);
+        assert_eq!(params.block_delay_secs, 30);
+        assert_eq!(
+            params.sector_sizes,
+            vec![SectorSize::_32GiB, SectorSize::_64GiB]
+        );
+        assert_eq!(
+            params
+                .upgrades
+                .iter()
+                .find(|upgrade| upgrade.network_version == NetworkVersion::V21)
+                .map(|upgrade| upgrade.epoch),
+            Some(config.epoch(Height::Watermelon))
+        );
+        assert!(params
+            .upgrades
+            .windows(2)
+            .all(|pair| pair[0].epoch <= pair[1].epoch));
+    }
+
+    #[test]
+    fn mainnet_network_versions() {
+        let config = ChainConfig::mainnet();
+        let watermelon = config.epoch(Height::Watermelon);
+        assert_eq!(
+            network_versions(&config, watermelon - 10, watermelon + 10),
+            vec![
+                (watermelon - 10, watermelon, NetworkVersion::V20),
+                (watermelon + 1, watermelon + 10, NetworkVersion::V21),
+            ]
+        );
+        assert_eq!(
+            network_versions(&config, watermelon + 1, watermelon + 1),
+            vec![(watermelon + 1, watermelon + 1, NetworkVersion::V21)]
+        );
+        assert!(network_versions(&config, 1, 0).is_empty());
+
+        let all = network_versions(&config, 0, watermelon + 1);
+        assert_eq!(all.first().map(|range| range.0), Some(0));
+        assert!(all
+            .windows(2)
+            .all(|pair| pair[0].1 + 1 == pair[1].0 && pair[0].2 != pair[1].2));
+    }
+
+    #[test]
+    fn devnet_sector_sizes() {
+        let params = NetworkParams::at_epoch(&ChainConfig::devnet(), 0);
+        assert_eq!(
+            params.sector_sizes,
+            vec![SectorSize::_2KiB, SectorSize::_8MiB]
+        );
+    }
+}
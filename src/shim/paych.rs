@@ -0,0 +1,136 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Read-only access to the state of the payment channel actor, and the
+//! vouchers redeemed against it. Their layout is the same in all the actor
+//! versions supported by Forest.
+
+use cid::Cid;
+use fil_actors_shared::fvm_ipld_amt::Amt;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{strict_bytes, tuple::*, CborStore as _, RawBytes};
+
+use super::address::Address;
+use super::clock::ChainEpoch;
+use super::crypto::Signature;
+use super::econ::TokenAmount;
+use super::message::MethodNum;
+use super::state_tree::ActorState;
+
+/// State of the payment channel actor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct State {
+    /// Channel owner, who has funded the actor.
+    pub from: Address,
+    /// Recipient of the payments from this channel.
+    pub to: Address,
+    /// Amount successfully redeemed through the payment channel, paid out on
+    /// `collect`.
+    pub to_send: TokenAmount,
+    /// Height at which the channel can be collected, zero until it's settling.
+    pub settling_at: ChainEpoch,
+    /// Height before which the channel `to_send` cannot be collected.
+    pub min_settle_height: ChainEpoch,
+    /// AMT of the [`LaneState`]s, by lane ID.
+    pub lane_states: Cid,
+}
+
+/// Amount redeemed, and nonce of the last voucher redeemed, on a lane.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct LaneState {
+    pub redeemed: TokenAmount,
+    pub nonce: u64,
+}
+
+/// Method that the voucher recipient must call successfully for the voucher
+/// to be redeemable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct ModVerifyParams {
+    pub actor: Address,
+    pub method: MethodNum,
+    pub data: RawBytes,
+}
+
+/// Lane of a voucher to merge with the lane of the voucher.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct Merge {
+    pub lane: u64,
+    pub nonce: u64,
+}
+
+/// Voucher signed by the channel owner, redeemable by the recipient.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize_tuple, Deserialize_tuple)]
+pub struct SignedVoucher {
+    pub channel_addr: Address,
+    pub time_lock_min: ChainEpoch,
+    /// Epoch after which the voucher can't be redeemed, ignored if zero.
+    pub time_lock_max: ChainEpoch,
+    #[serde(with = "strict_bytes")]
+    pub secret_pre_image: Vec<u8>,
+    pub extra: Option<ModVerifyParams>,
+    pub lane: u64,
+    pub nonce: u64,
+    pub amount: TokenAmount,
+    pub min_settle_height: ChainEpoch,
+    pub merges: Vec<Merge>,
+    pub signature: Option<Signature>,
+}
+
+impl SignedVoucher {
+    /// Bytes signed by the channel owner: the voucher without its signature.
+    pub fn signing_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let unsigned = SignedVoucher {
+            signature: None,
+            ..self.clone()
+        };
+        Ok(fvm_ipld_encoding::to_vec(&unsigned)?)
+    }
+}
+
+impl State {
+    /// Loads the state of a payment channel actor. The code of the actor must
+    /// have been checked by the caller.
+    pub fn load(store: &impl Blockstore, actor: &ActorState) -> anyhow::Result<Self> {
+        store
+            .get_cbor(&actor.state)?
+            .ok_or_else(|| anyhow::anyhow!("payment channel state {} not found", actor.state))
+    }
+
+    /// Returns the states of the lanes on which vouchers were redeemed, by
+    /// lane ID.
+    pub fn lane_states(&self, store: &impl Blockstore) -> anyhow::Result<Vec<(u64, LaneState)>> {
+        let lanes = Amt::<LaneState, _>::load(&self.lane_states, store)?;
+        let mut states = vec![];
+        lanes.for_each(|lane, state| {
+            states.push((lane, state.clone()));
+            Ok(())
+        })?;
+        Ok(states)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signing_bytes_ignore_the_signature() {
+        let mut voucher = SignedVoucher {
+            channel_addr: Address::new_id(1000),
+            time_lock_min: 0,
+            time_lock_max: 0,
+            secret_pre_image: vec![],
+            extra: None,
+            lane: 1,
+            nonce: 2,
+            amount: TokenAmount::from_atto(100),
+            min_settle_height: 0,
+            merges: vec![],
+            signature: None,
+        };
+        let unsigned = voucher.signing_bytes().unwrap();
+        voucher.signature = Some(Signature::new_secp256k1(vec![0; 65]));
+        assert_eq!(voucher.signing_bytes().unwrap(), unsigned);
+        assert_ne!(fvm_ipld_encoding::to_vec(&voucher).unwrap(), unsigned);
+    }
+}
@@ -1,26 +1,29 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::{
-    ops::{Deref, DerefMut},
+    ops::{ControlFlow, Deref, DerefMut},
     sync::Arc,
 };
 
 use anyhow::{anyhow, bail, Context as _};
 use cid::Cid;
+use fil_actors_shared::fvm_ipld_hamt::{BytesKey, Hamt};
 pub use fvm2::state_tree::{ActorState as ActorStateV2, StateTree as StateTreeV2};
 pub use fvm3::state_tree::{ActorState as ActorStateV3, StateTree as StateTreeV3};
 pub use fvm4::state_tree::{ActorState as ActorStateV4, StateTree as StateTreeV4};
 pub use fvm4::state_tree::{ActorState as ActorState_latest, StateTree as StateTree_latest};
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::repr::{Deserialize_repr, Serialize_repr};
+use fvm_ipld_encoding::CborStore as _;
 use fvm_shared2::state::StateTreeVersion as StateTreeVersionV2;
 pub use fvm_shared3::state::StateRoot;
 use fvm_shared3::state::StateTreeVersion as StateTreeVersionV3;
 pub use fvm_shared3::ActorID;
 use fvm_shared4::state::StateTreeVersion as StateTreeVersionV4;
+use fvm_shared4::HAMT_BIT_WIDTH;
 use num::FromPrimitive;
 use num_derive::FromPrimitive;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::shim::{address::Address, econ::TokenAmount};
 
@@ -293,6 +296,58 @@ where
     }
 }
 
+/// Calls `f` on the actors of the state tree of root `root`, in the order of
+/// the tree, from the actor of address `start` on, until `f` breaks. Returns the
+/// address of the actor `f` broke on, to resume the walk from without going
+/// through the actors before it again.
+pub fn for_each_actor_from<S: Blockstore>(
+    store: &S,
+    root: &Cid,
+    start: Option<&Address>,
+    f: impl FnMut(Address, ActorState) -> anyhow::Result<ControlFlow<()>>,
+) -> anyhow::Result<Option<Address>> {
+    let StateRoot {
+        version, actors, ..
+    } = store
+        .get_cbor(root)?
+        .with_context(|| format!("state root {root} not found"))?;
+    match version {
+        StateTreeVersionV3::V0 | StateTreeVersionV3::V1 => {
+            bail!("walking state trees of version {version:?} is not supported")
+        }
+        StateTreeVersionV3::V2 | StateTreeVersionV3::V3 | StateTreeVersionV3::V4 => {
+            walk_actors::<_, ActorStateV2>(store, &actors, start, f)
+        }
+        StateTreeVersionV3::V5 => walk_actors::<_, ActorStateV4>(store, &actors, start, f),
+    }
+}
+
+/// Walks the HAMT of the actors, whose layout is `V`.
+fn walk_actors<S: Blockstore, V>(
+    store: &S,
+    actors: &Cid,
+    start: Option<&Address>,
+    mut f: impl FnMut(Address, ActorState) -> anyhow::Result<ControlFlow<()>>,
+) -> anyhow::Result<Option<Address>>
+where
+    V: Serialize + DeserializeOwned,
+    for<'a> ActorState: From<&'a V>,
+{
+    let hamt = Hamt::<_, V>::load_with_bit_width(actors, store, HAMT_BIT_WIDTH)?;
+    let entries = match start {
+        Some(start) => hamt.iter_from(&BytesKey(start.to_bytes()))?,
+        None => hamt.iter(),
+    };
+    for entry in entries {
+        let (key, actor) = entry?;
+        let address = Address::from_bytes(key)?;
+        if f(address, actor.into())?.is_break() {
+            return Ok(Some(address));
+        }
+    }
+    Ok(None)
+}
+
 /// `Newtype` to wrap different versions of `fvm::state_tree::ActorState`
 ///
 /// # Examples
@@ -456,12 +511,16 @@ impl From<ActorState> for ActorStateV4 {
 
 #[cfg(test)]
 mod tests {
-    use super::StateTree;
+    use super::{for_each_actor_from, ActorState, StateTree, StateTreeVersion};
     use crate::blocks::BlockHeader;
     use crate::db::car::AnyCar;
+    use crate::db::MemoryDB;
     use crate::networks::{calibnet, mainnet};
+    use crate::shim::{address::Address, econ::TokenAmount};
     use cid::Cid;
     use fil_actor_interface::init::{self, State};
+    use num_traits::Zero as _;
+    use std::ops::ControlFlow;
     use std::sync::Arc;
 
     // refactored from `StateManager::get_network_name`
@@ -487,6 +546,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn walk_actors_in_pages() {
+        let store = Arc::new(MemoryDB::default());
+        let mut state = StateTree::new(store.clone(), StateTreeVersion::V5).unwrap();
+        for id in 0..10 {
+            let actor = ActorState::new(
+                Cid::default(),
+                Cid::default(),
+                TokenAmount::zero(),
+                id,
+                None,
+            );
+            state.set_actor(&Address::new_id(id), actor).unwrap();
+        }
+        let root = &state.flush().unwrap();
+        let mut all = vec![];
+        state
+            .for_each(|address, _| {
+                all.push(address);
+                Ok(())
+            })
+            .unwrap();
+
+        let mut paged = vec![];
+        let mut start = None;
+        loop {
+            let mut page = vec![];
+            start = for_each_actor_from(&*store, root, start.as_ref(), |address, _| {
+                if page.len() == 3 {
+                    return Ok(ControlFlow::Break(()));
+                }
+                page.push(address);
+                Ok(ControlFlow::Continue(()))
+            })
+            .unwrap();
+            paged.extend(page);
+            if start.is_none() {
+                break;
+            }
+        }
+        assert_eq!(all.len(), 10);
+        assert_eq!(paged, all);
+    }
+
     #[test]
     fn mainnet_network_name() {
         // Yes, the name of `mainnet` in the genesis block really is `testnetnet`.
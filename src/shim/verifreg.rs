@@ -0,0 +1,259 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Read-only access to the state of the verified registry actor, on top of
+//! the versioned states of [`fil_actor_verifreg_state`]. Actors v9 moved the
+//! data cap of the verified clients to the [datacap](super::datacap) actor,
+//! and added the allocations and claims of data cap.
+
+use anyhow::Context as _;
+use cid::Cid;
+use fil_actor_interface::verifreg::{
+    is_v10_verifreg_cid, is_v11_verifreg_cid, is_v12_verifreg_cid, is_v8_verifreg_cid,
+    is_v9_verifreg_cid,
+};
+use fil_actors_shared::v8::{builtin::HAMT_BIT_WIDTH, make_map_with_root_and_bitwidth};
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore as _;
+use integer_encoding::VarInt as _;
+
+use super::address::Address;
+use super::bigint::BigInt;
+use super::clock::ChainEpoch;
+use super::state_tree::ActorState;
+
+pub type ActorID = u64;
+pub type AllocationID = u64;
+pub type ClaimID = u64;
+
+/// State of the verified registry actor.
+pub enum State {
+    V8(fil_actor_verifreg_state::v8::State),
+    V9(fil_actor_verifreg_state::v9::State),
+    V10(fil_actor_verifreg_state::v10::State),
+    V11(fil_actor_verifreg_state::v11::State),
+    V12(fil_actor_verifreg_state::v12::State),
+}
+
+/// Data cap allocated by a client to a provider, for a piece.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation {
+    pub client: ActorID,
+    pub provider: ActorID,
+    pub data: Cid,
+    pub size: u64,
+    pub term_min: ChainEpoch,
+    pub term_max: ChainEpoch,
+    pub expiration: ChainEpoch,
+}
+
+/// Allocation claimed by a provider, with the sector that stores the piece.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Claim {
+    pub provider: ActorID,
+    pub client: ActorID,
+    pub data: Cid,
+    pub size: u64,
+    pub term_min: ChainEpoch,
+    pub term_max: ChainEpoch,
+    pub term_start: ChainEpoch,
+    pub sector: u64,
+}
+
+impl State {
+    /// Loads the state of the verified registry actor, in the layout of the
+    /// actor version of its code.
+    pub fn load(store: &impl Blockstore, actor: &ActorState) -> anyhow::Result<Self> {
+        let code = &actor.code;
+        let not_found = || format!("verified registry state {} not found", actor.state);
+        if is_v8_verifreg_cid(code) {
+            return Ok(State::V8(
+                store.get_cbor(&actor.state)?.with_context(not_found)?,
+            ));
+        }
+        if is_v9_verifreg_cid(code) {
+            return Ok(State::V9(
+                store.get_cbor(&actor.state)?.with_context(not_found)?,
+            ));
+        }
+        if is_v10_verifreg_cid(code) {
+            return Ok(State::V10(
+                store.get_cbor(&actor.state)?.with_context(not_found)?,
+            ));
+        }
+        if is_v11_verifreg_cid(code) {
+            return Ok(State::V11(
+                store.get_cbor(&actor.state)?.with_context(not_found)?,
+            ));
+        }
+        if is_v12_verifreg_cid(code) {
+            return Ok(State::V12(
+                store.get_cbor(&actor.state)?.with_context(not_found)?,
+            ));
+        }
+        anyhow::bail!("unknown verified registry actor code {code}")
+    }
+
+    /// Data cap of a verifier, given by its ID address.
+    pub fn verifier_data_cap(
+        &self,
+        store: &impl Blockstore,
+        verifier: &Address,
+    ) -> anyhow::Result<Option<num::BigInt>> {
+        Ok(match self {
+            State::V8(st) => {
+                let verifiers = make_map_with_root_and_bitwidth::<_, BigInt>(
+                    &st.verifiers,
+                    store,
+                    HAMT_BIT_WIDTH,
+                )?;
+                verifiers
+                    .get(&verifier.to_bytes())?
+                    .map(|data_cap| (**data_cap).clone())
+            }
+            State::V9(st) => st.get_verifier_cap(store, &(*verifier).into())?,
+            State::V10(st) => st.get_verifier_cap(store, &(*verifier).into())?,
+            State::V11(st) => st.get_verifier_cap(store, &(*verifier).into())?,
+            State::V12(st) => st.get_verifier_cap(store, &(*verifier).into())?,
+        })
+    }
+
+    /// Data cap of a verified client, given by its ID address. Only tracked by
+    /// this actor up to actors v8, `None` afterwards.
+    pub fn verified_client_data_cap(
+        &self,
+        store: &impl Blockstore,
+        client: &Address,
+    ) -> anyhow::Result<Option<num::BigInt>> {
+        match self {
+            State::V8(st) => {
+                let clients = make_map_with_root_and_bitwidth::<_, BigInt>(
+                    &st.verified_clients,
+                    store,
+                    HAMT_BIT_WIDTH,
+                )?;
+                Ok(clients
+                    .get(&client.to_bytes())?
+                    .map(|data_cap| (**data_cap).clone()))
+            }
+            State::V9(_) | State::V10(_) | State::V11(_) | State::V12(_) => Ok(None),
+        }
+    }
+
+    /// Allocation `id` of `client`. Allocations were added in actors v9.
+    pub fn allocation(
+        &self,
+        store: &impl Blockstore,
+        client: ActorID,
+        id: AllocationID,
+    ) -> anyhow::Result<Option<Allocation>> {
+        macro_rules! get_allocation {
+            ($state:expr, $version:ident) => {{
+                let mut allocations = $state.load_allocs(store)?;
+                fil_actor_verifreg_state::$version::state::get_allocation(
+                    &mut allocations,
+                    client,
+                    id,
+                )?
+                .map(|allocation| Allocation {
+                    client: allocation.client,
+                    provider: allocation.provider,
+                    data: allocation.data,
+                    size: allocation.size.0,
+                    term_min: allocation.term_min,
+                    term_max: allocation.term_max,
+                    expiration: allocation.expiration,
+                })
+            }};
+        }
+        Ok(match self {
+            State::V8(_) => None,
+            State::V9(st) => get_allocation!(st, v9),
+            State::V10(st) => get_allocation!(st, v10),
+            State::V11(st) => get_allocation!(st, v11),
+            State::V12(st) => get_allocation!(st, v12),
+        })
+    }
+
+    /// Claim `id` of `provider`. Claims were added in actors v9.
+    pub fn claim(
+        &self,
+        store: &impl Blockstore,
+        provider: ActorID,
+        id: ClaimID,
+    ) -> anyhow::Result<Option<Claim>> {
+        macro_rules! get_claim {
+            ($state:expr, $version:ident) => {{
+                let mut claims = $state.load_claims(store)?;
+                fil_actor_verifreg_state::$version::state::get_claim(&mut claims, provider, id)?
+                    .map(|claim| Claim {
+                        provider: claim.provider,
+                        client: claim.client,
+                        data: claim.data,
+                        size: claim.size.0,
+                        term_min: claim.term_min,
+                        term_max: claim.term_max,
+                        term_start: claim.term_start,
+                        sector: claim.sector,
+                    })
+            }};
+        }
+        Ok(match self {
+            State::V8(_) => None,
+            State::V9(st) => get_claim!(st, v9),
+            State::V10(st) => get_claim!(st, v10),
+            State::V11(st) => get_claim!(st, v11),
+            State::V12(st) => get_claim!(st, v12),
+        })
+    }
+}
+
+/// IDs are keyed by their unsigned varint encoding.
+pub(super) fn id_key(id: u64) -> Vec<u8> {
+    id.encode_var_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+    use fvm_shared4::piece::PaddedPieceSize;
+
+    #[test]
+    fn allocations_and_claims() {
+        let store = MemoryDB::default();
+        let mut state =
+            fil_actor_verifreg_state::v12::State::new(&store, Address::new_id(80).into()).unwrap();
+        let allocation = fil_actor_verifreg_state::v12::Allocation {
+            client: 1001,
+            provider: 1000,
+            data: Cid::default(),
+            size: PaddedPieceSize(2048),
+            term_min: 10,
+            term_max: 20,
+            expiration: 30,
+        };
+        let ids = state
+            .insert_allocations(&store, 1001, vec![allocation])
+            .unwrap();
+        let claim = fil_actor_verifreg_state::v12::Claim {
+            provider: 1000,
+            client: 1001,
+            data: Cid::default(),
+            size: PaddedPieceSize(2048),
+            term_min: 10,
+            term_max: 20,
+            term_start: 5,
+            sector: 7,
+        };
+        state.put_claims(&store, vec![(3, claim)]).unwrap();
+        let state = State::V12(state);
+
+        let allocation = state.allocation(&store, 1001, ids[0]).unwrap().unwrap();
+        assert_eq!((allocation.provider, allocation.size), (1000, 2048));
+        assert_eq!(state.allocation(&store, 1000, ids[0]).unwrap(), None);
+        assert_eq!(state.claim(&store, 1000, 3).unwrap().unwrap().sector, 7);
+        assert_eq!(state.claim(&store, 1000, 4).unwrap(), None);
+        assert_eq!(state.claim(&store, 1001, 3).unwrap(), None);
+    }
+}
@@ -52,8 +52,12 @@ impl NetworkVersion {
     pub const V19: Self = Self(NetworkVersion_latest::new(19));
     pub const V20: Self = Self(NetworkVersion_latest::new(20));
     pub const V21: Self = Self(NetworkVersion_latest::new(21));
+    pub const V22: Self = Self(NetworkVersion_latest::new(22));
+    pub const V23: Self = Self(NetworkVersion_latest::new(23));
 }
 
+crate::lotus_json::lotus_json_with_self!(NetworkVersion);
+
 impl Deref for NetworkVersion {
     type Target = NetworkVersion_latest;
     fn deref(&self) -> &Self::Target {
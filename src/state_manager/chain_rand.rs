@@ -9,6 +9,7 @@ use crate::chain::index::{ChainIndex, ResolveNullTipset};
 use crate::networks::ChainConfig;
 use crate::shim::clock::ChainEpoch;
 use crate::shim::externs::Rand;
+use crate::shim::version::NetworkVersion;
 use crate::utils::encoding::blake2b_256;
 use anyhow::{bail, Context as _};
 use blake2b_simd::Params;
@@ -85,6 +86,26 @@ where
         ))
     }
 
+    /// Digest of the ticket randomness at `round`, drawn as in the network
+    /// version of `round`.
+    pub fn chain_randomness_digest(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+        let lookback = self.chain_config.network_version(round) < NetworkVersion::V13;
+        self.get_chain_randomness(round, lookback)
+    }
+
+    /// Digest of the beacon randomness at `round`, drawn as in the network
+    /// version of `round`.
+    pub fn beacon_randomness_digest(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]> {
+        let network_version = self.chain_config.network_version(round);
+        if network_version >= NetworkVersion::V14 {
+            self.get_beacon_randomness_v3(round)
+        } else if network_version == NetworkVersion::V13 {
+            self.get_beacon_randomness_v2(round)
+        } else {
+            self.get_beacon_randomness(round, true)
+        }
+    }
+
     /// network version 13 onward
     pub fn get_chain_randomness_v2(&self, round: ChainEpoch) -> anyhow::Result<[u8; 32]> {
         self.get_chain_randomness(round, false)
@@ -186,11 +207,21 @@ pub fn draw_randomness(
     pers: i64,
     round: ChainEpoch,
     entropy: &[u8],
+) -> anyhow::Result<[u8; 32]> {
+    draw_randomness_from_digest(&digest(rbase), pers, round, entropy)
+}
+
+/// Computes a pseudo random 32 byte `Vec` from the digest of the randomness
+/// base, e.g. [`ChainRand::beacon_randomness_digest`].
+pub fn draw_randomness_from_digest(
+    rbase_digest: &[u8; 32],
+    pers: i64,
+    round: ChainEpoch,
+    entropy: &[u8],
 ) -> anyhow::Result<[u8; 32]> {
     let mut state = Params::new().hash_length(32).to_state();
     state.write_i64::<BigEndian>(pers)?;
-    let vrf_digest = digest(rbase);
-    state.write_all(&vrf_digest)?;
+    state.write_all(rbase_digest)?;
     state.write_i64::<BigEndian>(round)?;
     state.write_all(entropy)?;
     let mut ret = [0u8; 32];
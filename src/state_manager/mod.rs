@@ -4,16 +4,22 @@
 pub mod chain_rand;
 mod errors;
 mod metrics;
+mod rewards;
 mod utils;
 use crate::chain_sync::SyncConfig;
-use crate::interpreter::{MessageCallbackCtx, VMTrace};
-use crate::state_migration::run_state_migrations;
+use crate::interpreter::state_overrides::{
+    apply_state_overrides, ActorOverride, OverlayBlockstore,
+};
+use crate::interpreter::trace_sink::{MessageTrace, TraceSink};
+use crate::interpreter::{execution_trace, MessageCallbackCtx, VMTrace};
+use crate::state_migration::{run_state_migrations, run_state_pre_migrations};
 use anyhow::{bail, Context as _};
 use fil_actor_interface::init::{self, State};
 use rayon::prelude::ParallelBridge;
 pub use utils::is_valid_for_sending;
 mod vm_circ_supply;
 pub use self::errors::*;
+pub use self::rewards::BlockRewards;
 use crate::beacon::BeaconSchedule;
 use crate::blocks::{Tipset, TipsetKeys};
 use crate::chain::{
@@ -21,25 +27,25 @@ use crate::chain::{
     ChainStore, HeadChange,
 };
 use crate::interpreter::{resolve_to_key_addr, ExecutionContext, VM};
-use crate::interpreter::{BlockMessages, CalledAt};
+use crate::interpreter::{BlockMessages, CalledAt, ReplayedMessage};
+use crate::lotus_json::lotus_json_with_self;
 use crate::message::{ChainMessage, Message as MessageTrait};
 use crate::networks::ChainConfig;
 use crate::shim::clock::ChainEpoch;
 use crate::shim::{
     address::{Address, Payload, Protocol, BLS_PUB_LEN},
     econ::TokenAmount,
-    executor::{ApplyRet, Receipt},
+    executor::{ApplyRet, Receipt, StampedEvent, EVENTS_AMT_BITWIDTH},
     message::Message,
     state_tree::{ActorState, StateTree},
     version::NetworkVersion,
 };
-use ahash::{HashMap, HashMapExt};
 use chain_rand::ChainRand;
 use cid::Cid;
 use fil_actor_interface::*;
 use fil_actors_shared::fvm_ipld_amt::Amtv0 as Amt;
 use fil_actors_shared::v10::runtime::Policy;
-use futures::{channel::oneshot, select, FutureExt};
+use futures::{select, FutureExt};
 use fvm_ipld_blockstore::Blockstore;
 use itertools::Itertools as _;
 use lru::LruCache;
@@ -50,11 +56,13 @@ use parking_lot::Mutex as SyncMutex;
 use serde::{Deserialize, Serialize};
 use std::ops::RangeInclusive;
 use std::{num::NonZeroUsize, sync::Arc};
-use tokio::sync::{broadcast::error::RecvError, Mutex as TokioMutex, RwLock};
+use tokio::sync::{broadcast::error::RecvError, Mutex as TokioMutex};
 use tracing::{debug, error, info, instrument, trace, warn};
+pub use vm_circ_supply::CirculatingSupply;
 use vm_circ_supply::GenesisInfo;
 
 const DEFAULT_TIPSET_CACHE_SIZE: NonZeroUsize = nonzero!(1024usize);
+const DEFAULT_ACTOR_CACHE_SIZE: NonZeroUsize = nonzero!(4096usize);
 
 /// Intermediary for retrieving state objects and updating actor states.
 type CidPair = (Cid, Cid);
@@ -173,16 +181,32 @@ impl TipsetStateCache {
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct InvocResult {
+    #[serde(with = "crate::lotus_json")]
+    pub msg_cid: Cid,
     #[serde(with = "crate::lotus_json")]
     pub msg: Message,
     #[serde(with = "crate::lotus_json")]
     pub msg_rct: Option<Receipt>,
     pub error: Option<String>,
+    /// Lotus-style `ExecutionTrace` of the message, if it was traced.
+    pub execution_trace: Option<serde_json::Value>,
 }
 
 /// An alias Result that represents an `InvocResult` and an Error.
 type StateCallResult = Result<InvocResult, Error>;
 
+/// State root computed by [`StateManager::compute_state`], with the results
+/// of the messages applied.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ComputeStateOutput {
+    #[serde(with = "crate::lotus_json")]
+    pub root: Cid,
+    pub trace: Vec<InvocResult>,
+}
+
+lotus_json_with_self!(ComputeStateOutput);
+
 /// External format for returning market balance from state.
 #[derive(Default, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
@@ -201,6 +225,9 @@ pub struct StateManager<DB> {
 
     /// This is a cache which indexes tipsets to their calculated state.
     cache: TipsetStateCache,
+    /// Recently read actors, keyed by state root. State trees are immutable,
+    /// so entries never go stale.
+    actor_cache: SyncMutex<LruCache<(Cid, Address), Option<ActorState>>>,
     // Beacon can be cheaply crated from the `chain_config`. The only reason we
     // store it here is because it has a look-up cache.
     beacon: Arc<crate::beacon::BeaconSchedule>,
@@ -212,6 +239,9 @@ pub struct StateManager<DB> {
 #[allow(clippy::type_complexity)]
 pub const NO_CALLBACK: Option<fn(&MessageCallbackCtx) -> anyhow::Result<()>> = None;
 
+/// Lookback limit of the message searches that look back up to genesis.
+pub const LOOKBACK_NO_LIMIT: ChainEpoch = -1;
+
 impl<DB> StateManager<DB>
 where
     DB: Blockstore,
@@ -222,11 +252,14 @@ where
         sync_config: Arc<SyncConfig>,
     ) -> Result<Self, anyhow::Error> {
         let genesis = cs.genesis();
-        let beacon = Arc::new(chain_config.get_beacon_schedule(genesis.timestamp()));
+        let beacon = Arc::new(
+            chain_config.get_beacon_schedule_with_cache(genesis.timestamp(), cs.settings()),
+        );
 
         Ok(Self {
             cs,
             cache: TipsetStateCache::new(),
+            actor_cache: SyncMutex::new(LruCache::new(DEFAULT_ACTOR_CACHE_SIZE)),
             beacon,
             chain_config,
             sync_config,
@@ -251,10 +284,47 @@ where
         &self.sync_config
     }
 
+    /// Returns the breakdown of the circulating supply used by the VM at a
+    /// tipset.
+    pub fn get_vm_circulating_supply_detailed(
+        &self,
+        tipset: &Tipset,
+    ) -> anyhow::Result<CirculatingSupply> {
+        GenesisInfo::from_chain_config(self.chain_config()).get_circulating_supply_detailed(
+            tipset.epoch(),
+            &self.blockstore_owned(),
+            tipset.parent_state(),
+        )
+    }
+
+    /// Returns the circulating supply at a tipset, summed over the balances of
+    /// all the actors. Slower than the estimation used by the VM.
+    pub fn get_state_circulating_supply(&self, tipset: &Tipset) -> anyhow::Result<TokenAmount> {
+        vm_circ_supply::get_state_circulating_supply(
+            tipset.epoch(),
+            self.get_network_version(tipset.epoch()),
+            &self.blockstore_owned(),
+            tipset.parent_state(),
+        )
+    }
+
     /// Gets actor from given [`Cid`], if it exists.
     pub fn get_actor(&self, addr: &Address, state_cid: Cid) -> anyhow::Result<Option<ActorState>> {
+        let key = (state_cid, *addr);
+        if let Some(actor) = self.actor_cache.lock().get(&key) {
+            crate::metrics::LRU_CACHE_HIT
+                .with_label_values(&[crate::metrics::values::STATE_MANAGER_ACTOR])
+                .inc();
+            return Ok(actor.clone());
+        }
+
         let state = StateTree::new_from_root(self.blockstore_owned(), &state_cid)?;
-        state.get_actor(addr)
+        let actor = state.get_actor(addr)?;
+        self.actor_cache.lock().put(key, actor.clone());
+        crate::metrics::LRU_CACHE_MISS
+            .with_label_values(&[crate::metrics::values::STATE_MANAGER_ACTOR])
+            .inc();
+        Ok(actor)
     }
 
     /// Returns a reference to the state manager's [`Blockstore`].
@@ -369,20 +439,26 @@ where
             .await
     }
 
-    #[instrument(skip(self, rand))]
-    fn call_raw(
+    #[instrument(skip(self, rand, chain_index))]
+    fn call_raw<S>(
         self: &Arc<Self>,
         msg: &mut Message,
         rand: ChainRand<DB>,
         tipset: &Arc<Tipset>,
-    ) -> StateCallResult {
+        chain_index: Arc<ChainIndex<Arc<S>>>,
+        state_root: Cid,
+    ) -> StateCallResult
+    where
+        S: Blockstore + Send + Sync + 'static,
+    {
         let bstate = tipset.parent_state();
         let bheight = tipset.epoch();
         let genesis_info = GenesisInfo::from_chain_config(self.chain_config());
+        let state_tree = StateTree::new_from_root(Arc::clone(&chain_index.db), &state_root)?;
         let mut vm = VM::new(
             ExecutionContext {
                 heaviest_tipset: Arc::clone(tipset),
-                state_tree_root: *bstate,
+                state_tree_root: state_root,
                 epoch: bheight,
                 rand: Box::new(rand),
                 base_fee: TokenAmount::zero(),
@@ -392,7 +468,7 @@ where
                     bstate,
                 )?,
                 chain_config: self.chain_config().clone(),
-                chain_index: Arc::clone(&self.chain_store().chain_index),
+                chain_index,
                 timestamp: tipset.min_timestamp(),
             },
             &self.engine,
@@ -403,8 +479,8 @@ where
             msg.gas_limit = 10000000000;
         }
 
-        let actor = self
-            .get_actor(&msg.from, *bstate)?
+        let actor = state_tree
+            .get_actor(&msg.from)?
             .ok_or_else(|| Error::Other("Could not get actor".to_string()))?;
         msg.sequence = actor.sequence;
         let apply_ret = vm.apply_implicit_message(msg)?;
@@ -419,9 +495,11 @@ where
         }
 
         Ok(InvocResult {
+            msg_cid: msg.cid().map_err(|e| Error::Other(e.to_string()))?,
             msg: msg.clone(),
             msg_rct: Some(apply_ret.msg_receipt()),
             error: apply_ret.failure_info(),
+            execution_trace: None,
         })
     }
 
@@ -434,7 +512,24 @@ where
     ) -> StateCallResult {
         let ts = tipset.unwrap_or_else(|| self.cs.heaviest_tipset());
         let chain_rand = self.chain_rand(Arc::clone(&ts));
-        self.call_raw(message, chain_rand, &ts)
+        let chain_index = Arc::clone(&self.chain_store().chain_index);
+        self.call_raw(message, chain_rand, &ts, chain_index, *ts.parent_state())
+    }
+
+    /// Same as [`StateManager::call`], with the state of some actors
+    /// overridden. The overridden state is kept in memory.
+    pub fn call_with_overrides(
+        self: &Arc<Self>,
+        message: &mut Message,
+        overrides: &[ActorOverride],
+        tipset: Option<Arc<Tipset>>,
+    ) -> StateCallResult {
+        let ts = tipset.unwrap_or_else(|| self.cs.heaviest_tipset());
+        let chain_rand = self.chain_rand(Arc::clone(&ts));
+        let store = Arc::new(OverlayBlockstore::new(self.blockstore_owned()));
+        let state_root = apply_state_overrides(&store, *ts.parent_state(), overrides)?;
+        let chain_index = Arc::new(ChainIndex::new(store));
+        self.call_raw(message, chain_rand, &ts, chain_index, state_root)
     }
 
     /// Computes message on the given [Tipset] state, after applying other
@@ -488,14 +583,17 @@ where
         let ret = vm.apply_message(message)?;
 
         Ok(InvocResult {
+            msg_cid: message.cid().map_err(|e| Error::Other(e.to_string()))?,
             msg: message.message().clone(),
             msg_rct: Some(ret.msg_receipt()),
             error: ret.failure_info(),
+            execution_trace: None,
         })
     }
 
     /// Replays the given message and returns the result of executing the
     /// indicated message, assuming it was executed in the indicated tipset.
+    /// The message is traced, see [`ApplyRet::exec_trace`].
     pub async fn replay(
         self: &Arc<Self>,
         ts: &Arc<Tipset>,
@@ -522,7 +620,7 @@ where
             }
         };
         let result = self
-            .compute_tipset_state(Arc::clone(ts), Some(callback), VMTrace::NotTraced)
+            .compute_tipset_state(Arc::clone(ts), Some(callback), VMTrace::Traced)
             .await;
 
         if let Err(error_message) = result {
@@ -631,6 +729,87 @@ where
         .await?
     }
 
+    /// Computes the state of the tipset, runs the migrations of the epochs up
+    /// to `epoch`, then applies the extra messages at `epoch`, like Lotus'
+    /// `StateCompute`. The results include those of the implicit messages, with
+    /// their execution traces if `enable_tracing` asks for them.
+    pub async fn compute_state(
+        self: &Arc<Self>,
+        epoch: ChainEpoch,
+        messages: Vec<Message>,
+        tipset: Arc<Tipset>,
+        enable_tracing: VMTrace,
+    ) -> Result<ComputeStateOutput, Error> {
+        if epoch < tipset.epoch() {
+            return Err(Error::Other(format!(
+                "cannot compute the state at epoch {epoch}, before the tipset at epoch {}",
+                tipset.epoch()
+            )));
+        }
+        let trace = Arc::new(SyncMutex::new(vec![]));
+        let callback = {
+            let trace = Arc::clone(&trace);
+            move |ctx: &MessageCallbackCtx| {
+                trace.lock().push(InvocResult {
+                    msg_cid: ctx.cid,
+                    msg: ctx.message.message().clone(),
+                    msg_rct: Some(ctx.apply_ret.msg_receipt()),
+                    error: ctx.apply_ret.failure_info(),
+                    execution_trace: execution_trace::json(ctx.apply_ret.exec_trace())?,
+                });
+                Ok(())
+            }
+        };
+        let (root, _) = self
+            .compute_tipset_state(Arc::clone(&tipset), Some(callback), enable_tracing)
+            .await?;
+        let mut trace = std::mem::take(&mut *trace.lock());
+
+        let this = Arc::clone(self);
+        tokio::task::spawn_blocking(move || -> Result<_, Error> {
+            let db = this.blockstore_owned();
+            let mut root = root;
+            for epoch_i in tipset.epoch()..epoch {
+                if let Some(new_root) =
+                    run_state_migrations(epoch_i, this.chain_config(), &db, &root)?
+                {
+                    root = new_root;
+                }
+            }
+            if !messages.is_empty() {
+                let genesis_info = GenesisInfo::from_chain_config(this.chain_config());
+                let mut vm = VM::new(
+                    ExecutionContext {
+                        heaviest_tipset: Arc::clone(&tipset),
+                        state_tree_root: root,
+                        epoch,
+                        rand: Box::new(this.chain_rand(Arc::clone(&tipset))),
+                        base_fee: tipset.min_ticket_block().parent_base_fee().clone(),
+                        circ_supply: genesis_info.get_circulating_supply(epoch, &db, &root)?,
+                        chain_config: Arc::clone(this.chain_config()),
+                        chain_index: Arc::clone(&this.chain_store().chain_index),
+                        timestamp: tipset.min_timestamp(),
+                    },
+                    &this.engine,
+                    enable_tracing,
+                )?;
+                for msg in messages {
+                    let ret = vm.apply_message(&ChainMessage::Unsigned(msg.clone()))?;
+                    trace.push(InvocResult {
+                        msg_cid: msg.cid().map_err(|e| Error::Other(e.to_string()))?,
+                        msg,
+                        msg_rct: Some(ret.msg_receipt()),
+                        error: ret.failure_info(),
+                        execution_trace: execution_trace::json(ret.exec_trace())?,
+                    });
+                }
+                root = vm.flush()?;
+            }
+            Ok(ComputeStateOutput { root, trace })
+        })
+        .await?
+    }
+
     /// Blocking version of `compute_tipset_state`
     #[tracing::instrument(skip_all)]
     pub fn compute_tipset_state_blocking(
@@ -651,17 +830,20 @@ where
         )?)
     }
 
-    /// Check if tipset had executed the message, by loading the receipt based
-    /// on the index of the message in the block.
+    /// Checks if the tipset executed the message, or a replacement of it if
+    /// `allow_replaced`, by loading the receipt based on the index of the
+    /// message in the parent tipset. Returns the receipt with the CID of the
+    /// executed message.
     fn tipset_executed_message(
         &self,
         tipset: &Tipset,
-        msg_cid: Cid,
-        (message_from_address, message_sequence): (&Address, &u64),
-    ) -> Result<Option<Receipt>, Error> {
+        message: &ChainMessage,
+        allow_replaced: bool,
+    ) -> Result<Option<(Receipt, Cid)>, Error> {
         if tipset.epoch() == 0 {
             return Ok(None);
         }
+        let msg_cid = message.cid().map_err(|e| Error::Other(e.to_string()))?;
         // Load parent state.
         let pts = self
             .cs
@@ -671,62 +853,60 @@ where
             .cs
             .messages_for_tipset(&pts)
             .map_err(|err| Error::Other(err.to_string()))?;
-        messages
-            .iter()
-            .enumerate()
-            // reverse iteration intentional
-            .rev()
-            .filter(|(_, s)| {
-                &s.from() == message_from_address
-            })
-            .filter_map(|(index, s)| {
-                if s.sequence() == *message_sequence {
-                    if s.cid().map(|s|
-                        s == msg_cid
-                    ).unwrap_or_default() {
-                        // When message Cid has been found, get receipt at index.
-                        let rct = crate::chain::get_parent_reciept(
-                            self.blockstore(),
-                            tipset.blocks().first().unwrap(),
-                            index,
-                        )
-                            .map_err(|err| {
-                                Error::Other(err.to_string())
-                            });
-                        return Some(
-                           rct
-                        );
-                    }
-                    let error_msg = format!("found message with equal nonce as the one we are looking for (F:{:} n {:}, TS: `Error Converting message to Cid` n{:})", msg_cid, message_sequence, s.sequence());
-                    return Some(Err(Error::Other(error_msg)))
-                }
-                if s.sequence() < *message_sequence {
-                    return Some(Ok(None));
-                }
-
-                None
-            })
-            .next()
-            .unwrap_or(Ok(None))
+        // reverse iteration intentional: the messages of a sender are ordered
+        // by sequence
+        for (index, executed) in messages.iter().enumerate().rev() {
+            if executed.from() != message.from() || executed.sequence() > message.sequence() {
+                continue;
+            }
+            if executed.sequence() < message.sequence() {
+                return Ok(None);
+            }
+            let executed_cid = executed.cid().map_err(|e| Error::Other(e.to_string()))?;
+            if executed_cid != msg_cid
+                && !(allow_replaced && is_same_call(executed.message(), message.message()))
+            {
+                warn!(
+                    "message {executed_cid} has the sequence of {msg_cid} but does not replace it"
+                );
+                return Ok(None);
+            }
+            // When message Cid has been found, get receipt at index.
+            let receipt = crate::chain::get_parent_reciept(
+                self.blockstore(),
+                tipset.blocks().first().unwrap(),
+                index,
+            )
+            .map_err(|err| Error::Other(err.to_string()))?;
+            return Ok(receipt.map(|receipt| (receipt, executed_cid)));
+        }
+        Ok(None)
     }
 
-    fn check_search(
+    /// Looks backwards from the parent of `current` for the tipset executing
+    /// the message, at most `lookback_limit` epochs back, unless it is
+    /// [`LOOKBACK_NO_LIMIT`].
+    fn search_back_for_message(
         &self,
         mut current: Arc<Tipset>,
-        (message_from_address, message_cid, message_sequence): (&Address, &Cid, &u64),
-    ) -> Result<Option<(Arc<Tipset>, Receipt)>, Error> {
+        message: &ChainMessage,
+        lookback_limit: ChainEpoch,
+        allow_replaced: bool,
+    ) -> Result<Option<(Arc<Tipset>, Receipt, Cid)>, Error> {
+        let limit_epoch =
+            (lookback_limit != LOOKBACK_NO_LIMIT).then(|| current.epoch() - lookback_limit);
         loop {
-            if current.epoch() == 0 {
+            if current.epoch() == 0 || limit_epoch.is_some_and(|limit| current.epoch() <= limit) {
                 return Ok(None);
             }
             let state = StateTree::new_from_root(self.blockstore_owned(), current.parent_state())
                 .map_err(|e| Error::State(e.to_string()))?;
 
             if let Some(actor_state) = state
-                .get_actor(message_from_address)
+                .get_actor(&message.from())
                 .map_err(|e| Error::State(e.to_string()))?
             {
-                if actor_state.sequence == 0 || actor_state.sequence < *message_sequence {
+                if actor_state.sequence == 0 || actor_state.sequence < message.sequence() {
                     return Ok(None);
                 }
             }
@@ -739,180 +919,146 @@ where
                         "failed to load tipset during msg wait searchback: {err:}"
                     ))
                 })?;
-            let r = self.tipset_executed_message(
-                &tipset,
-                *message_cid,
-                (message_from_address, message_sequence),
-            )?;
-
-            if let Some(receipt) = r {
-                return Ok(Some((tipset, receipt)));
+            if let Some((receipt, cid)) =
+                self.tipset_executed_message(&tipset, message, allow_replaced)?
+            {
+                return Ok(Some((tipset, receipt, cid)));
             }
             current = tipset;
         }
     }
 
-    fn search_back_for_message(
+    /// Looks backwards from `current` for the tipset executing the message,
+    /// i.e. the child of the tipset including it, at most `lookback_limit`
+    /// epochs back, unless it is [`LOOKBACK_NO_LIMIT`]. Returns it with the
+    /// receipt and the CID of the executed message, which differs from
+    /// `msg_cid` if the message was replaced and `allow_replaced`.
+    pub fn search_for_message(
         &self,
         current: Arc<Tipset>,
-        params: (&Address, &Cid, &u64),
-    ) -> Result<Option<(Arc<Tipset>, Receipt)>, Error> {
-        self.check_search(current, params)
+        msg_cid: Cid,
+        lookback_limit: ChainEpoch,
+        allow_replaced: bool,
+    ) -> Result<Option<(Arc<Tipset>, Receipt, Cid)>, Error> {
+        let message = crate::chain::get_chain_message(self.blockstore(), &msg_cid)
+            .map_err(|e| Error::Other(e.to_string()))?;
+        if let Some((receipt, cid)) =
+            self.tipset_executed_message(&current, &message, allow_replaced)?
+        {
+            return Ok(Some((current, receipt, cid)));
+        }
+        self.search_back_for_message(current, &message, lookback_limit, allow_replaced)
     }
+
     /// Returns a message receipt from a given tipset and message CID.
     pub fn get_receipt(&self, tipset: Arc<Tipset>, msg: Cid) -> Result<Receipt, Error> {
-        let m = crate::chain::get_chain_message(self.blockstore(), &msg)
-            .map_err(|e| Error::Other(e.to_string()))?;
-        let message_var = (&m.from(), &m.sequence());
-        let message_receipt = self.tipset_executed_message(&tipset, msg, message_var)?;
-
-        if let Some(receipt) = message_receipt {
-            return Ok(receipt);
-        }
-        let cid = m
-            .cid()
-            .map_err(|e| Error::Other(format!("Could not convert message to cid {e:?}")))?;
-        let message_var = (&m.from(), &cid, &m.sequence());
-        let maybe_tuple = self.search_back_for_message(tipset, message_var)?;
-        let message_receipt = maybe_tuple
+        let (_, receipt, _) = self
+            .search_for_message(tipset, msg, LOOKBACK_NO_LIMIT, false)?
             .ok_or_else(|| {
                 Error::Other("Could not get receipt from search back message".to_string())
-            })?
-            .1;
-        Ok(message_receipt)
+            })?;
+        Ok(receipt)
     }
 
     /// `WaitForMessage` blocks until a message appears on chain. It looks
-    /// backwards in the chain to see if this has already happened. It
-    /// guarantees that the message has been on chain for at least
-    /// confidence epochs without being reverted before returning.
+    /// backwards in the chain, at most `lookback_limit` epochs, to see if this
+    /// has already happened. It guarantees that the message has been on chain
+    /// for at least confidence epochs without being reverted before
+    /// returning. Returns the tipset executing the message, its receipt, and
+    /// the CID of the executed message, which differs from `msg_cid` if the
+    /// message was replaced and `allow_replaced`.
     pub async fn wait_for_message(
         self: &Arc<Self>,
         msg_cid: Cid,
         confidence: i64,
-    ) -> Result<(Option<Arc<Tipset>>, Option<Receipt>), Error> {
-        let mut subscriber = self.cs.publisher().subscribe();
-        let (sender, mut receiver) = oneshot::channel::<()>();
+        lookback_limit: ChainEpoch,
+        allow_replaced: bool,
+    ) -> Result<(Arc<Tipset>, Receipt, Cid), Error> {
+        // Subscribe first, so that no head change is missed
+        let mut head_changes = self.cs.publisher().subscribe();
         let message = crate::chain::get_chain_message(self.blockstore(), &msg_cid)
             .map_err(|err| Error::Other(format!("failed to load message {err:}")))?;
 
-        let message_var = (&message.from(), &message.sequence());
-        let current_tipset = self.cs.heaviest_tipset();
-        let maybe_message_reciept =
-            self.tipset_executed_message(&current_tipset, msg_cid, message_var)?;
-        if let Some(r) = maybe_message_reciept {
-            return Ok((Some(current_tipset.clone()), Some(r)));
+        let head = self.cs.heaviest_tipset();
+        let mut candidate = None;
+        if let Some((receipt, cid)) =
+            self.tipset_executed_message(&head, &message, allow_replaced)?
+        {
+            if confidence == 0 {
+                return Ok((head, receipt, cid));
+            }
+            candidate = Some((Arc::clone(&head), receipt, cid));
         }
 
-        let mut candidate_tipset: Option<Arc<Tipset>> = None;
-        let mut candidate_receipt: Option<Receipt> = None;
-
-        let sm_cloned = Arc::clone(self);
-        let cid = message
-            .cid()
-            .map_err(|e| Error::Other(format!("Could not get cid from message {e:?}")))?;
-
-        let cid_for_task = cid;
-        let address_for_task = message.from();
-        let sequence_for_task = message.sequence();
-        let height_of_head = current_tipset.epoch();
-        let task = tokio::task::spawn(async move {
-            let back_tuple = sm_cloned.search_back_for_message(
-                current_tipset,
-                (&address_for_task, &cid_for_task, &sequence_for_task),
-            )?;
-            sender
-                .send(())
-                .map_err(|e| Error::Other(format!("Could not send to channel {e:?}")))?;
-            Ok::<_, Error>(back_tuple)
-        });
-
-        let reverts: Arc<RwLock<HashMap<TipsetKeys, bool>>> = Arc::new(RwLock::new(HashMap::new()));
-        let block_revert = reverts.clone();
-        let sm_cloned = Arc::clone(self);
-
-        // Wait for message to be included in head change.
-        let mut subscriber_poll = tokio::task::spawn(async move {
-            loop {
-                match subscriber.recv().await {
-                    Ok(subscriber) => match subscriber {
-                        HeadChange::Apply(tipset) => {
-                            if candidate_tipset
-                                .as_ref()
-                                .map(|s| tipset.epoch() >= s.epoch() + confidence)
-                                .unwrap_or_default()
-                            {
-                                return Ok((candidate_tipset, candidate_receipt));
-                            }
-                            let poll_receiver = receiver.try_recv();
-                            if let Ok(Some(_)) = poll_receiver {
-                                block_revert
-                                    .write()
-                                    .await
-                                    .insert(tipset.key().to_owned(), true);
-                            }
-
-                            let message_var = (&message.from(), &message.sequence());
-                            let maybe_receipt =
-                                sm_cloned.tipset_executed_message(&tipset, msg_cid, message_var)?;
-                            if let Some(receipt) = maybe_receipt {
-                                if confidence == 0 {
-                                    return Ok((Some(tipset), Some(receipt)));
-                                }
-                                candidate_tipset = Some(tipset);
-                                candidate_receipt = Some(receipt)
-                            }
+        // Search backwards for message, while watching the new heads.
+        let mut search_back = {
+            let this = Arc::clone(self);
+            let message = message.clone();
+            let head = Arc::clone(&head);
+            tokio::task::spawn_blocking(move || {
+                this.search_back_for_message(head, &message, lookback_limit, allow_replaced)
+            })
+            .fuse()
+        };
+        let mut head = head;
+        loop {
+            select! {
+                found = search_back => {
+                    if let (Some(found), None) = (found??, &candidate) {
+                        if self.is_on_chain(&found.0, Arc::clone(&head))? {
+                            candidate = Some(found);
                         }
-                    },
-                    Err(RecvError::Lagged(i)) => {
-                        warn!(
-                            "wait for message head change subscriber lagged, skipped {} events",
-                            i
-                        );
                     }
-                    Err(RecvError::Closed) => break,
                 }
-            }
-            Ok((None, None))
-        })
-        .fuse();
-
-        // Search backwards for message.
-        let mut search_back_poll = tokio::task::spawn(async move {
-            let back_tuple = task.await.map_err(|e| {
-                Error::Other(format!("Could not search backwards for message {e}"))
-            })??;
-            if let Some((back_tipset, back_receipt)) = back_tuple {
-                let should_revert = *reverts
-                    .read()
-                    .await
-                    .get(back_tipset.key())
-                    .unwrap_or(&false);
-                let larger_height_of_head = height_of_head >= back_tipset.epoch() + confidence;
-                if !should_revert && larger_height_of_head {
-                    return Ok::<_, Error>((Some(back_tipset), Some(back_receipt)));
+                head_change = head_changes.recv().fuse() => {
+                    head = match head_change {
+                        Ok(HeadChange::Apply(tipset)) => tipset,
+                        Err(RecvError::Lagged(i)) => {
+                            warn!(
+                                "wait for message head change subscriber lagged, skipped {i} events"
+                            );
+                            self.cs.heaviest_tipset()
+                        }
+                        Err(RecvError::Closed) => {
+                            return Err(Error::Other("the chain store was closed".into()))
+                        }
+                    };
+                    // The head changes are not published for reverted tipsets
+                    if let Some((candidate_tipset, ..)) = &candidate {
+                        if !self.is_on_chain(candidate_tipset, Arc::clone(&head))? {
+                            candidate = None;
+                        }
+                    }
+                    if candidate.is_none() {
+                        if let Some((receipt, cid)) =
+                            self.tipset_executed_message(&head, &message, allow_replaced)?
+                        {
+                            candidate = Some((Arc::clone(&head), receipt, cid));
+                        }
+                    }
                 }
-                return Ok((None, None));
             }
-            Ok((None, None))
-        })
-        .fuse();
-
-        // Await on first future to finish.
-        loop {
-            select! {
-                res = subscriber_poll => {
-                    return res?
-                }
-                res = search_back_poll => {
-                    if let Ok((Some(ts), Some(rct))) = res? {
-                        return Ok((Some(ts), Some(rct)));
-                    }
+            if let Some((candidate_tipset, receipt, cid)) = &candidate {
+                if head.epoch() >= candidate_tipset.epoch() + confidence {
+                    return Ok((Arc::clone(candidate_tipset), receipt.clone(), *cid));
                 }
             }
         }
     }
 
+    /// Whether `tipset` is on the chain whose head is `head`.
+    fn is_on_chain(&self, tipset: &Tipset, head: Arc<Tipset>) -> Result<bool, Error> {
+        if tipset.epoch() > head.epoch() {
+            return Ok(false);
+        }
+        let at_epoch = self
+            .cs
+            .chain_index
+            .tipset_by_height(tipset.epoch(), head, ResolveNullTipset::TakeOlder)
+            .map_err(|e| Error::Other(e.to_string()))?;
+        Ok(at_epoch.key() == tipset.key())
+    }
+
     /// Returns a BLS public key from provided address
     pub fn get_bls_public_key(
         db: &Arc<DB>,
@@ -1085,10 +1231,12 @@ where
             self.beacon_schedule(),
             &self.engine,
             tipsets,
+            None,
         )
     }
 
-    fn chain_rand(&self, tipset: Arc<Tipset>) -> ChainRand<DB> {
+    /// Randomness of the chain as seen from `tipset`.
+    pub fn chain_rand(&self, tipset: Arc<Tipset>) -> ChainRand<DB> {
         ChainRand::new(
             self.chain_config.clone(),
             tipset,
@@ -1098,6 +1246,18 @@ where
     }
 }
 
+/// Whether the messages make the same call, in which case one can replace the
+/// other with different gas parameters.
+fn is_same_call(a: &Message, b: &Message) -> bool {
+    a.version == b.version
+        && a.from == b.from
+        && a.to == b.to
+        && a.sequence == b.sequence
+        && a.value == b.value
+        && a.method_num == b.method_num
+        && a.params == b.params
+}
+
 pub fn validate_tipsets<DB, T>(
     genesis_timestamp: u64,
     chain_index: Arc<ChainIndex<Arc<DB>>>,
@@ -1105,6 +1265,7 @@ pub fn validate_tipsets<DB, T>(
     beacon: Arc<BeaconSchedule>,
     engine: &crate::shim::machine::MultiEngine,
     tipsets: T,
+    trace_sink: Option<&dyn TraceSink>,
 ) -> anyhow::Result<()>
 where
     DB: Blockstore + Send + Sync + 'static,
@@ -1116,6 +1277,10 @@ where
         .par_bridge()
         .try_for_each(|(child, parent)| {
             info!(height = parent.epoch(), "compute parent state");
+            let epoch = parent.epoch();
+            let callback = trace_sink.map(|sink| {
+                move |ctx: &MessageCallbackCtx| sink.record(MessageTrace::new(epoch, ctx))
+            });
             let (actual_state, actual_receipt) = apply_block_messages(
                 genesis_timestamp,
                 chain_index.clone(),
@@ -1123,8 +1288,11 @@ where
                 beacon.clone(),
                 engine,
                 parent,
-                NO_CALLBACK,
-                VMTrace::NotTraced,
+                callback,
+                match trace_sink {
+                    Some(_) => VMTrace::Traced,
+                    None => VMTrace::NotTraced,
+                },
             )
             .context("couldn't compute tipset state")?;
             let expected_receipt = child.min_ticket_block().message_receipts();
@@ -1241,7 +1409,8 @@ where
     // 2. run 'cron' for any null-tipsets between the current tipset and our parent tipset
     // 3. run migrations
     // 4. execute block messages
-    // 5. write the state-tree to the DB and return the CID
+    // 5. write the events to the DB
+    // 6. write the state-tree to the DB and return the CID
 
     // step 1: special case for genesis block
     if tipset.epoch() == 0 {
@@ -1255,6 +1424,69 @@ where
 
     let _timer = metrics::APPLY_BLOCKS_TIME.start_timer();
 
+    let (mut vm, block_messages) = prepare_tipset_vm(
+        genesis_timestamp,
+        Arc::clone(&chain_index),
+        chain_config,
+        beacon,
+        engine,
+        Arc::clone(&tipset),
+        callback.as_mut(),
+        enable_tracing,
+    )?;
+    let epoch = tipset.epoch();
+
+    // step 4: apply tipset messages
+    let (receipts, events) = vm.apply_block_messages(&block_messages, epoch, callback)?;
+
+    // step 5: store the events, that the FVM doesn't write out, under the
+    // event roots of the receipts
+    for (receipt, events) in receipts.iter().zip(events) {
+        if let Some(events_root) = receipt.events_root() {
+            store_events(&chain_index.db, &events_root, events)?;
+        }
+    }
+
+    // step 6: construct receipt root from receipts and flush the state-tree
+    let receipt_root = Amt::new_from_iter(&chain_index.db, receipts)?;
+    let state_root = vm.flush()?;
+
+    Ok((state_root, receipt_root))
+}
+
+/// Stores the events of a message, that the FVM doesn't write out, under the
+/// events root of its receipt. Fails if the events don't match that root.
+fn store_events(
+    db: &impl Blockstore,
+    events_root: &Cid,
+    events: Vec<StampedEvent>,
+) -> anyhow::Result<()> {
+    let mut amt = fil_actors_shared::fvm_ipld_amt::Amt::new_with_bit_width(db, EVENTS_AMT_BITWIDTH);
+    amt.batch_set(events)?;
+    let root = amt.flush()?;
+    anyhow::ensure!(
+        root == *events_root,
+        "stored events root {root} differs from the receipt one {events_root}"
+    );
+    Ok(())
+}
+
+/// Runs the cron of the null rounds before `tipset` and any state migration,
+/// and returns a VM ready to apply the messages of `tipset`.
+#[allow(clippy::too_many_arguments)]
+fn prepare_tipset_vm<DB>(
+    genesis_timestamp: u64,
+    chain_index: Arc<ChainIndex<Arc<DB>>>,
+    chain_config: Arc<ChainConfig>,
+    beacon: Arc<BeaconSchedule>,
+    engine: &crate::shim::machine::MultiEngine,
+    tipset: Arc<Tipset>,
+    mut callback: Option<impl FnMut(&MessageCallbackCtx) -> anyhow::Result<()>>,
+    enable_tracing: VMTrace,
+) -> anyhow::Result<(VM<DB>, Vec<BlockMessages>)>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
     let rand = ChainRand::new(
         Arc::clone(&chain_config),
         Arc::clone(&tipset),
@@ -1307,19 +1539,280 @@ where
         {
             parent_state = new_state;
         }
+        run_state_pre_migrations(epoch_i, &chain_config, &chain_index.db, &parent_state);
     }
 
     let block_messages = BlockMessages::for_tipset(&chain_index.db, &tipset)
         .map_err(|e| Error::Other(e.to_string()))?;
 
-    let mut vm = create_vm(parent_state, epoch, tipset.min_timestamp())?;
+    let vm = create_vm(parent_state, epoch, tipset.min_timestamp())?;
+    Ok((vm, block_messages))
+}
 
-    // step 4: apply tipset messages
-    let receipts = vm.apply_block_messages(&block_messages, epoch, callback)?;
+/// Re-executes the messages of `tipset` up to the one matching `message`, and
+/// returns it along with the state roots right before and after it.
+#[allow(clippy::too_many_arguments)]
+pub fn replay_message<DB>(
+    genesis_timestamp: u64,
+    chain_index: Arc<ChainIndex<Arc<DB>>>,
+    chain_config: Arc<ChainConfig>,
+    beacon: Arc<BeaconSchedule>,
+    engine: &crate::shim::machine::MultiEngine,
+    tipset: Arc<Tipset>,
+    message: impl Fn(&ChainMessage) -> bool,
+    enable_tracing: VMTrace,
+) -> anyhow::Result<ReplayedMessage>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    // The genesis tipset has no messages.
+    anyhow::ensure!(tipset.epoch() > 0, "message not found in the tipset");
+    let (mut vm, block_messages) = prepare_tipset_vm(
+        genesis_timestamp,
+        chain_index,
+        chain_config,
+        beacon,
+        engine,
+        Arc::clone(&tipset),
+        NO_CALLBACK,
+        enable_tracing,
+    )?;
+    vm.apply_block_messages_until(&block_messages, tipset.epoch(), message)
+}
 
-    // step 5: construct receipt root from receipts and flush the state-tree
-    let receipt_root = Amt::new_from_iter(&chain_index.db, receipts)?;
-    let state_root = vm.flush()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{BlockHeader, TxMeta};
+    use crate::chain::persist_objects;
+    use crate::db::MemoryDB;
+    use crate::shim::{econ::TokenAmount, state_tree::StateTreeVersion};
+    use crate::utils::{cid::CidCborExt as _, db::CborStoreExt as _};
 
-    Ok((state_root, receipt_root))
+    const SENDER: u64 = 100;
+
+    struct TestChain {
+        state_manager: Arc<StateManager<MemoryDB>>,
+        genesis: Arc<Tipset>,
+    }
+
+    impl TestChain {
+        /// A chain whose state holds the sender of the test messages, which
+        /// already sent its first message.
+        fn new() -> Self {
+            let db = Arc::new(MemoryDB::default());
+            let mut state = StateTree::new(db.clone(), StateTreeVersion::V5).unwrap();
+            state
+                .set_actor(
+                    &Address::new_id(SENDER),
+                    ActorState::new(Cid::default(), Cid::default(), TokenAmount::zero(), 1, None),
+                )
+                .unwrap();
+            let state_root = state.flush().unwrap();
+            let genesis = BlockHeader::builder()
+                .miner_address(Address::new_id(0))
+                .timestamp(7777)
+                .state_root(state_root)
+                .messages(message_root(&db, &[]))
+                .message_receipts(Amt::<Receipt, _>::new_from_iter(&db, []).unwrap())
+                .build()
+                .unwrap();
+            persist_objects(&db, &[genesis.clone()]).unwrap();
+            let chain_config = Arc::new(ChainConfig::default());
+            let chain_store = Arc::new(
+                ChainStore::new(db.clone(), db, chain_config.clone(), genesis.clone()).unwrap(),
+            );
+            let state_manager =
+                Arc::new(StateManager::new(chain_store, chain_config, Default::default()).unwrap());
+            Self {
+                state_manager,
+                genesis: Arc::new(Tipset::from(genesis)),
+            }
+        }
+
+        /// Adds a child of `parent` mined by `miner`, which includes `messages`
+        /// and has the `receipts` of the messages of `parent`.
+        fn child(
+            &self,
+            parent: &Tipset,
+            miner: u64,
+            messages: &[Message],
+            receipts: &[Receipt],
+        ) -> Arc<Tipset> {
+            let db = self.state_manager.blockstore();
+            let header = BlockHeader::builder()
+                .miner_address(Address::new_id(miner))
+                .parents(parent.key().clone())
+                .epoch(parent.epoch() + 1)
+                .state_root(*parent.parent_state())
+                .messages(message_root(db, messages))
+                .message_receipts(Amt::new_from_iter(db, receipts.iter().cloned()).unwrap())
+                .build()
+                .unwrap();
+            persist_objects(db, &[header.clone()]).unwrap();
+            Arc::new(Tipset::from(header))
+        }
+    }
+
+    fn message_root(db: &MemoryDB, messages: &[Message]) -> Cid {
+        let cids: Vec<_> = messages
+            .iter()
+            .map(|message| db.put_cbor_default(message).unwrap())
+            .collect();
+        let meta = TxMeta {
+            bls_message_root: Amt::new_from_iter(db, cids).unwrap(),
+            secp_message_root: Amt::<Cid, _>::new_from_iter(db, []).unwrap(),
+        };
+        db.put_cbor_default(&meta).unwrap()
+    }
+
+    fn message(gas_limit: u64) -> Message {
+        Message {
+            from: Address::new_id(SENDER),
+            to: Address::new_id(SENDER + 1),
+            gas_limit,
+            ..Default::default()
+        }
+    }
+
+    fn receipt(exit_code: u32) -> Receipt {
+        Receipt::V2(fvm_shared2::receipt::Receipt {
+            exit_code: fvm_shared2::error::ExitCode::new(exit_code),
+            return_data: Default::default(),
+            gas_used: 10,
+        })
+    }
+
+    #[test]
+    fn search_for_message_within_lookback_limit() {
+        let chain = TestChain::new();
+        let message = message(1000);
+        let cid = Cid::from_cbor_blake2b256(&message).unwrap();
+        let ts1 = chain.child(&chain.genesis, 1, &[message], &[]);
+        let ts2 = chain.child(&ts1, 1, &[], &[receipt(0)]);
+        let ts3 = chain.child(&ts2, 1, &[], &[]);
+        let ts4 = chain.child(&ts3, 1, &[], &[]);
+        let sm = &chain.state_manager;
+
+        // Executed by the tipset the search starts from
+        let (tipset, found, executed) = sm
+            .search_for_message(ts2.clone(), cid, LOOKBACK_NO_LIMIT, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tipset.key(), ts2.key());
+        assert_eq!(found, receipt(0));
+        assert_eq!(executed, cid);
+
+        // Executed two epochs before the head
+        let search = |limit| {
+            sm.search_for_message(ts4.clone(), cid, limit, false)
+                .unwrap()
+        };
+        assert!(search(1).is_none());
+        assert_eq!(search(2).unwrap().0.key(), ts2.key());
+        assert_eq!(search(LOOKBACK_NO_LIMIT).unwrap().0.key(), ts2.key());
+    }
+
+    #[test]
+    fn search_for_replaced_message() {
+        let chain = TestChain::new();
+        let original = message(1000);
+        let replacement = message(2000);
+        let db = chain.state_manager.blockstore();
+        let cid = db.put_cbor_default(&original).unwrap();
+        let ts1 = chain.child(&chain.genesis, 1, &[replacement.clone()], &[]);
+        let ts2 = chain.child(&ts1, 1, &[], &[receipt(0)]);
+        let sm = &chain.state_manager;
+
+        assert!(sm
+            .search_for_message(ts2.clone(), cid, LOOKBACK_NO_LIMIT, false)
+            .unwrap()
+            .is_none());
+        let (tipset, _, executed) = sm
+            .search_for_message(ts2.clone(), cid, LOOKBACK_NO_LIMIT, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(tipset.key(), ts2.key());
+        assert_eq!(executed, Cid::from_cbor_blake2b256(&replacement).unwrap());
+    }
+
+    #[tokio::test]
+    async fn wait_for_message_follows_reorgs() {
+        let chain = TestChain::new();
+        let message = message(1000);
+        let cid = Cid::from_cbor_blake2b256(&message).unwrap();
+        let a1 = chain.child(&chain.genesis, 1, &[message.clone()], &[]);
+        let a2 = chain.child(&a1, 1, &[], &[receipt(0)]);
+        let b1 = chain.child(&chain.genesis, 2, &[message], &[]);
+        let b2 = chain.child(&b1, 2, &[], &[receipt(1)]);
+        let b3 = chain.child(&b2, 2, &[], &[]);
+        let chain_store = chain.state_manager.chain_store().clone();
+        chain_store.set_heaviest_tipset(a1).unwrap();
+
+        let state_manager = chain.state_manager.clone();
+        let wait = tokio::spawn(async move {
+            state_manager
+                .wait_for_message(cid, 1, LOOKBACK_NO_LIMIT, false)
+                .await
+        });
+        while chain_store.publisher().receiver_count() == 0 {
+            tokio::task::yield_now().await;
+        }
+        // Executed on the first fork, which is reverted before reaching the
+        // confidence
+        chain_store.set_heaviest_tipset(a2).unwrap();
+        chain_store.set_heaviest_tipset(b2.clone()).unwrap();
+        chain_store.set_heaviest_tipset(b3).unwrap();
+
+        let (tipset, found, executed) = wait.await.unwrap().unwrap();
+        assert_eq!(tipset.key(), b2.key());
+        assert_eq!(found, receipt(1));
+        assert_eq!(executed, cid);
+    }
+
+    #[test]
+    fn store_and_load_events() {
+        use fvm_shared4::event::{ActorEvent, Entry, Flags, StampedEvent as StampedEvent_v4};
+
+        let event = |emitter, value| {
+            StampedEvent::V4(StampedEvent_v4::new(
+                emitter,
+                ActorEvent {
+                    entries: vec![Entry {
+                        flags: Flags::FLAG_INDEXED_ALL,
+                        key: "t1".into(),
+                        codec: 0x55,
+                        value: vec![value],
+                    }],
+                },
+            ))
+        };
+        let events = vec![event(1000, 1), event(1001, 2)];
+        // The root computed by the FVM
+        let events_root = {
+            let mut amt = fil_actors_shared::fvm_ipld_amt::Amt::new_with_bit_width(
+                MemoryDB::default(),
+                EVENTS_AMT_BITWIDTH,
+            );
+            amt.batch_set(events.clone()).unwrap();
+            amt.flush().unwrap()
+        };
+
+        let db = MemoryDB::default();
+        store_events(&db, &events_root, events.clone()).unwrap();
+        let stored =
+            fil_actors_shared::fvm_ipld_amt::Amt::<StampedEvent, _>::load(&events_root, &db)
+                .unwrap();
+        let mut loaded = vec![];
+        stored
+            .for_each(|_, event| {
+                loaded.push(event.clone());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(loaded, events);
+
+        // Events that don't match the receipt are an error
+        assert!(store_events(&db, &events_root, events[..1].to_vec()).is_err());
+    }
 }
@@ -0,0 +1,161 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Per-block accounting of the token flows caused by applying a tipset.
+
+use std::sync::Arc;
+
+use super::{Error, StateManager};
+use crate::blocks::Tipset;
+use crate::interpreter::{CalledAt, MessageCallbackCtx, VMTrace};
+use crate::lotus_json::lotus_json_with_self;
+use crate::shim::{address::Address, econ::TokenAmount, executor::ApplyRet, trace::ExecutionEvent};
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use serde::{Deserialize, Serialize};
+
+/// Rewards and burns of the messages a block contributed to its tipset.
+/// Messages included in several blocks of a tipset are only accounted to the
+/// first of them, as they are only executed once.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BlockRewards {
+    #[serde(with = "crate::lotus_json")]
+    pub block: Cid,
+    #[serde(with = "crate::lotus_json")]
+    pub miner: Address,
+    pub win_count: i64,
+    /// Reward paid to the miner for winning the block, not counting the
+    /// miner tips.
+    #[serde(with = "crate::lotus_json")]
+    pub block_reward: TokenAmount,
+    /// Number of messages executed on behalf of this block.
+    pub messages: u64,
+    /// Gas premiums paid to the miner.
+    #[serde(with = "crate::lotus_json")]
+    pub miner_tip: TokenAmount,
+    #[serde(with = "crate::lotus_json")]
+    pub base_fee_burn: TokenAmount,
+    #[serde(with = "crate::lotus_json")]
+    pub over_estimation_burn: TokenAmount,
+    /// Penalties charged to the miner for including invalid messages.
+    #[serde(with = "crate::lotus_json")]
+    pub penalty: TokenAmount,
+}
+
+lotus_json_with_self!(BlockRewards);
+
+impl BlockRewards {
+    fn add(&mut self, ret: &ApplyRet) {
+        self.messages += 1;
+        self.miner_tip += ret.miner_tip();
+        self.base_fee_burn += ret.base_fee_burn();
+        self.over_estimation_burn += ret.over_estimation_burn();
+        self.penalty += ret.penalty();
+    }
+
+    /// Everything paid to the miner: the block reward and the miner tips.
+    pub fn total_reward(&self) -> TokenAmount {
+        &self.block_reward + &self.miner_tip
+    }
+
+    /// Everything sent to the burnt funds actor: gas burns and penalties.
+    pub fn total_burn(&self) -> TokenAmount {
+        &self.base_fee_burn + &self.over_estimation_burn + &self.penalty
+    }
+}
+
+impl<DB> StateManager<DB>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    /// Re-executes `tipset` and breaks down its rewards and the burns of its
+    /// messages by block, in the order of [`Tipset::blocks`].
+    pub async fn tipset_rewards(
+        self: &Arc<Self>,
+        tipset: &Arc<Tipset>,
+    ) -> Result<Vec<BlockRewards>, Error> {
+        // The VM applies a block's messages, then its reward message, so the
+        // n-th reward message closes the tally of the n-th block.
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut tally = BlockRewards::default();
+        let callback = move |ctx: &MessageCallbackCtx| {
+            match ctx.at {
+                CalledAt::Applied => tally.add(ctx.apply_ret),
+                CalledAt::Reward => {
+                    tally.block_reward = paid_reward(ctx.apply_ret) - &tally.miner_tip;
+                    tx.send(std::mem::take(&mut tally))?
+                }
+                CalledAt::Cron => {}
+            }
+            Ok(())
+        };
+        // The paid rewards are only found in the execution traces.
+        self.compute_tipset_state(Arc::clone(tipset), Some(callback), VMTrace::Traced)
+            .await?;
+
+        Ok(tipset
+            .blocks()
+            .iter()
+            .zip(rx.try_iter())
+            .map(|(header, tally)| BlockRewards {
+                block: *header.cid(),
+                miner: *header.miner_address(),
+                win_count: header
+                    .election_proof()
+                    .as_ref()
+                    .map(|proof| proof.win_count)
+                    .unwrap_or_default(),
+                ..tally
+            })
+            .collect())
+    }
+}
+
+// The reward actor pays the block reward and the miner tips of a block in a
+// single `ApplyRewards` call to the miner.
+fn paid_reward(ret: &ApplyRet) -> TokenAmount {
+    ret.exec_trace()
+        .into_iter()
+        .find_map(|event| match event {
+            ExecutionEvent::Call(call)
+                if Address::new_id(call.from) == Address::REWARD_ACTOR
+                    && call.method_num
+                        == fil_actor_miner_state::v12::Method::ApplyRewards as u64 =>
+            {
+                Some(call.value)
+            }
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::Zero;
+
+    #[test]
+    fn total_reward() {
+        let rewards = BlockRewards {
+            block_reward: TokenAmount::from_atto(10),
+            miner_tip: TokenAmount::from_atto(1),
+            penalty: TokenAmount::from_atto(100),
+            ..Default::default()
+        };
+        assert_eq!(rewards.total_reward(), TokenAmount::from_atto(11));
+    }
+
+    #[test]
+    fn total_burn() {
+        let rewards = BlockRewards {
+            miner_tip: TokenAmount::from_atto(1),
+            base_fee_burn: TokenAmount::from_atto(10),
+            over_estimation_burn: TokenAmount::from_atto(100),
+            penalty: TokenAmount::from_atto(1000),
+            ..Default::default()
+        };
+        assert_eq!(rewards.total_burn(), TokenAmount::from_atto(1110));
+        assert!(BlockRewards::default().total_burn().is_zero());
+    }
+}
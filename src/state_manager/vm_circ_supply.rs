@@ -8,16 +8,23 @@ use crate::networks::{ChainConfig, Height};
 use crate::shim::{
     address::Address,
     clock::{ChainEpoch, EPOCHS_IN_DAY},
-    econ::TokenAmount,
+    econ::{TokenAmount, TOTAL_FILECOIN},
+    machine::{BuiltinActor, BuiltinActorManifest},
+    miner, multisig,
     state_tree::{ActorState, StateTree},
+    version::NetworkVersion,
 };
 use anyhow::Context as _;
 use cid::Cid;
 use fil_actor_interface::{market, power, reward};
+use fil_actor_system_state::v10::State as SystemState;
 use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::CborStore as _;
 use num_traits::Zero;
 
 const EPOCHS_IN_YEAR: ChainEpoch = 365 * EPOCHS_IN_DAY;
+/// Holds the funds of the token sale, which don't circulate.
+const SAFT_ACTOR: Address = Address::new_id(122);
 const PRE_CALICO_VESTING: [(ChainEpoch, usize); 5] = [
     (183 * EPOCHS_IN_DAY, 82_717_041),
     (EPOCHS_IN_YEAR, 22_421_712),
@@ -34,6 +41,18 @@ const CALICO_VESTING: [(ChainEpoch, usize); 6] = [
     (6 * EPOCHS_IN_YEAR, 100_000_000 + 300_000_000 + 9_805_053),
 ];
 
+/// Breakdown of the circulating supply used by the VM.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+pub struct CirculatingSupply {
+    pub fil_vested: TokenAmount,
+    pub fil_mined: TokenAmount,
+    pub fil_burnt: TokenAmount,
+    pub fil_locked: TokenAmount,
+    pub fil_circulating: TokenAmount,
+    pub fil_reserve_disbursed: TokenAmount,
+}
+
 /// Genesis information used when calculating circulating supply.
 #[derive(Default, Clone)]
 pub(in crate::state_manager) struct GenesisInfo {
@@ -71,6 +90,17 @@ impl GenesisInfo {
         db: &Arc<DB>,
         root: &Cid,
     ) -> Result<TokenAmount, anyhow::Error> {
+        Ok(self
+            .get_circulating_supply_detailed(height, db, root)?
+            .fil_circulating)
+    }
+
+    pub fn get_circulating_supply_detailed<DB: Blockstore>(
+        &self,
+        height: ChainEpoch,
+        db: &Arc<DB>,
+        root: &Cid,
+    ) -> Result<CirculatingSupply, anyhow::Error> {
         let state_tree = StateTree::new_from_root(Arc::clone(db), root)?;
         let fil_vested = get_fil_vested(self, height);
         let fil_mined = get_fil_mined(&state_tree)?;
@@ -86,10 +116,109 @@ impl GenesisInfo {
             TokenAmount::default(),
         );
 
-        Ok(fil_circulating)
+        Ok(CirculatingSupply {
+            fil_vested,
+            fil_mined,
+            fil_burnt,
+            fil_locked,
+            fil_circulating,
+            fil_reserve_disbursed: fil_reserve_distributed,
+        })
     }
 }
 
+/// Sums the circulating balances of all the actors, as opposed to the
+/// estimation used by the VM. The balances of the other actors must add up to
+/// the total supply.
+pub(in crate::state_manager) fn get_state_circulating_supply<DB: Blockstore>(
+    height: ChainEpoch,
+    network_version: NetworkVersion,
+    db: &Arc<DB>,
+    root: &Cid,
+) -> anyhow::Result<TokenAmount> {
+    let store: &DB = db;
+    let state_tree = StateTree::new_from_root(Arc::clone(db), root)?;
+    let system = get_actor_state(&state_tree, &Address::SYSTEM_ACTOR)?;
+    let system_state: SystemState = store
+        .get_cbor(&system.state)?
+        .context("system actor state not found")?;
+    let manifest = BuiltinActorManifest::load_v1_actor_list(store, &system_state.builtin_actors)
+        .context("the circulating supply is only computed from actors v8 on")?;
+
+    let mut circulating = TokenAmount::zero();
+    let mut not_circulating = TokenAmount::zero();
+    state_tree.for_each(|address, actor| {
+        if actor.balance.is_zero() {
+            return Ok(());
+        }
+        let balance = TokenAmount::from(&actor.balance);
+        if [
+            Address::INIT_ACTOR,
+            Address::REWARD_ACTOR,
+            Address::VERIFIED_REGISTRY_ACTOR,
+            Address::POWER_ACTOR,
+            Address::SYSTEM_ACTOR,
+            Address::CRON_ACTOR,
+            Address::BURNT_FUNDS_ACTOR,
+            SAFT_ACTOR,
+            Address::RESERVE_ACTOR,
+            Address::ETHEREUM_ACCOUNT_MANAGER_ACTOR,
+        ]
+        .contains(&address)
+        {
+            not_circulating += balance;
+            return Ok(());
+        }
+        if address == Address::MARKET_ACTOR {
+            if network_version >= NetworkVersion::V23 {
+                circulating += balance;
+            } else {
+                let state = market::State::load(store, actor.code, actor.state)?;
+                let locked: TokenAmount = state.total_locked().into();
+                circulating += balance - &locked;
+                not_circulating += locked;
+            }
+            return Ok(());
+        }
+        match manifest.builtin_actor_of(&actor.code) {
+            Some(
+                BuiltinActor::Account
+                | BuiltinActor::PaymentChannel
+                | BuiltinActor::EthAccount
+                | BuiltinActor::EVM
+                | BuiltinActor::Placeholder,
+            ) => circulating += balance,
+            Some(BuiltinActor::Miner) => match miner::State::load(store, actor)
+                .and_then(|state| state.available_balance(&balance))
+            {
+                Ok(available) => {
+                    not_circulating += balance - &available;
+                    circulating += available;
+                }
+                // The balance of a broken miner, lower than its locked funds,
+                // is considered locked
+                Err(_) => not_circulating += balance,
+            },
+            Some(BuiltinActor::Multisig) => {
+                let locked = multisig::locked_balance(&multisig::load(store, actor)?, height);
+                let available = balance.clone() - &locked;
+                circulating += TokenAmount::max(available, TokenAmount::zero());
+                not_circulating += TokenAmount::min(balance, locked);
+            }
+            _ => anyhow::bail!("unexpected actor {address}"),
+        }
+        Ok(())
+    })?;
+
+    let total = &circulating + &not_circulating;
+    anyhow::ensure!(
+        total == *TOTAL_FILECOIN,
+        "total filecoin didn't add to the expected amount: {total} != {}",
+        *TOTAL_FILECOIN
+    );
+    Ok(circulating)
+}
+
 /// Vesting schedule info. These states are lazily filled, to avoid doing until
 /// needed to calculate circulating supply.
 #[derive(Default, Clone)]
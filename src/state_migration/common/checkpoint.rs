@@ -0,0 +1,203 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Progress reporting and checkpointing of state tree migrations.
+//!
+//! Actors are migrated in parallel, so jobs complete out of order. A
+//! checkpoint records the partially migrated output tree together with the
+//! number of leading actors (in input iteration order) that are known to be
+//! migrated. Actors past that prefix may be migrated again after a resume,
+//! which is harmless as migrations are deterministic.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::shim::clock::ChainEpoch;
+use cid::Cid;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+/// How often progress is logged.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+/// How often a checkpoint is written.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+static CHECKPOINT_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Sets the directory where migration checkpoints are stored. Checkpointing
+/// is disabled until this is called.
+pub fn set_checkpoint_dir(dir: PathBuf) {
+    let _ = CHECKPOINT_DIR.set(dir);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(in crate::state_migration) struct Checkpoint {
+    /// Root of the state tree being migrated.
+    #[serde(with = "crate::lotus_json")]
+    pub input: Cid,
+    pub prior_epoch: ChainEpoch,
+    /// Root of the partially migrated state tree.
+    #[serde(with = "crate::lotus_json")]
+    pub output: Cid,
+    /// Number of leading input actors which have been migrated.
+    pub migrated: u64,
+}
+
+impl Checkpoint {
+    fn path(dir: &Path, input: &Cid, prior_epoch: ChainEpoch) -> PathBuf {
+        dir.join(format!("{prior_epoch}-{input}.json"))
+    }
+
+    /// Loads the checkpoint of an interrupted migration of `input`, if any.
+    pub fn load(input: &Cid, prior_epoch: ChainEpoch) -> Option<Self> {
+        let path = Self::path(CHECKPOINT_DIR.get()?, input, prior_epoch);
+        let checkpoint = std::fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Self>(&bytes).ok())?;
+        (checkpoint.input == *input && checkpoint.prior_epoch == prior_epoch).then_some(checkpoint)
+    }
+
+    /// Atomically replaces the stored checkpoint.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(dir) = CHECKPOINT_DIR.get() else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(dir)?;
+        let path = Self::path(dir, &self.input, self.prior_epoch);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Removes the checkpoint of a completed migration.
+    pub fn remove(input: &Cid, prior_epoch: ChainEpoch) {
+        if let Some(dir) = CHECKPOINT_DIR.get() {
+            let _ = std::fs::remove_file(Self::path(dir, input, prior_epoch));
+        }
+    }
+}
+
+/// Tracks completed migration jobs.
+pub(in crate::state_migration) struct MigrationProgress {
+    /// Number of jobs, known once all the input actors have been walked.
+    total: Option<u64>,
+    /// Jobs skipped because of a resumed checkpoint.
+    resumed: u64,
+    /// Length of the prefix of completed jobs.
+    prefix: u64,
+    /// Completed jobs past the prefix.
+    completed: BTreeSet<u64>,
+    start: Instant,
+    last_report: Instant,
+    last_checkpoint: Instant,
+}
+
+impl MigrationProgress {
+    pub fn new(resumed: u64) -> Self {
+        let now = Instant::now();
+        Self {
+            total: None,
+            resumed,
+            prefix: resumed,
+            completed: BTreeSet::new(),
+            start: now,
+            last_report: now,
+            last_checkpoint: now,
+        }
+    }
+
+    pub fn set_total(&mut self, total: u64) {
+        self.total = Some(total);
+    }
+
+    /// Marks the job at `index` (in input iteration order) as completed.
+    pub fn complete(&mut self, index: u64) {
+        self.completed.insert(index);
+        while self.completed.remove(&self.prefix) {
+            self.prefix += 1;
+        }
+    }
+
+    /// Number of jobs known to have completed, in input iteration order.
+    pub fn prefix(&self) -> u64 {
+        self.prefix
+    }
+
+    pub fn done(&self) -> u64 {
+        self.prefix + self.completed.len() as u64
+    }
+
+    /// Estimated time to completion, based on the rate of this run.
+    pub fn eta(&self) -> Option<Duration> {
+        let done = self.done().checked_sub(self.resumed).filter(|&n| n > 0)?;
+        let remaining = self.total?.saturating_sub(self.done());
+        Some(self.start.elapsed().mul_f64(remaining as f64 / done as f64))
+    }
+
+    /// Logs the progress if it hasn't been logged recently.
+    pub fn report(&mut self) {
+        if self.last_report.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        self.last_report = Instant::now();
+        let eta = self
+            .eta()
+            .map(|eta| humantime::format_duration(Duration::from_secs(eta.as_secs())).to_string())
+            .unwrap_or_else(|| "unknown".into());
+        match self.total {
+            Some(total) => tracing::info!(
+                "Migrated {}/{total} actors ({:.1}%), ETA: {eta}",
+                self.done(),
+                self.done() as f64 * 100.0 / total.max(1) as f64,
+            ),
+            None => tracing::info!("Migrated {} actors", self.done()),
+        }
+    }
+
+    /// Returns `true` if a checkpoint is due, and resets the timer.
+    pub fn checkpoint_due(&mut self) -> bool {
+        if CHECKPOINT_DIR.get().is_none() || self.last_checkpoint.elapsed() < CHECKPOINT_INTERVAL {
+            return false;
+        }
+        self.last_checkpoint = Instant::now();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_prefix() {
+        let mut progress = MigrationProgress::new(2);
+        assert_eq!(progress.prefix(), 2);
+        progress.complete(4);
+        progress.complete(3);
+        assert_eq!(progress.prefix(), 2);
+        assert_eq!(progress.done(), 4);
+        progress.complete(2);
+        assert_eq!(progress.prefix(), 5);
+        assert_eq!(progress.done(), 5);
+        assert!(progress.eta().is_none());
+        progress.set_total(10);
+        assert!(progress.eta().is_some());
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let checkpoint = Checkpoint {
+            input: Cid::default(),
+            prior_epoch: 42,
+            output: Cid::default(),
+            migrated: 7,
+        };
+        let json = serde_json::to_vec(&checkpoint).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Checkpoint>(&json).unwrap(),
+            checkpoint
+        );
+    }
+}
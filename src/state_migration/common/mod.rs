@@ -4,13 +4,17 @@
 //! Common code that's shared across all migration code.
 //! Each network upgrade / state migration code lives in their own module.
 
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
 use crate::shim::{address::Address, clock::ChainEpoch, econ::TokenAmount, state_tree::StateTree};
 use ahash::HashMap;
 use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
 
+pub(in crate::state_migration) mod checkpoint;
 mod macros;
 mod migration_job;
 pub(in crate::state_migration) mod migrators;
@@ -22,12 +26,34 @@ pub(in crate::state_migration) use state_migration::StateMigration;
 pub(in crate::state_migration) type Migrator<BS> = Arc<dyn ActorMigration<BS> + Send + Sync>;
 
 /// Cache of existing CID to CID migrations for an actor.
+///
+/// A migration using the cache stops early once the cache is cancelled, which
+/// is how pre-migrations that run too close to the upgrade are stopped.
 #[derive(Clone, Default)]
-pub(in crate::state_migration) struct MigrationCache {
+pub struct MigrationCache {
     cache: Arc<RwLock<HashMap<String, Cid>>>,
+    cancelled: Arc<AtomicBool>,
 }
 
 impl MigrationCache {
+    /// Stops the migrations using this cache.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns a cache sharing the entries of this one, which isn't cancelled
+    /// along with it.
+    pub fn detach(&self) -> Self {
+        Self {
+            cache: self.cache.clone(),
+            cancelled: Default::default(),
+        }
+    }
+
     pub fn get(&self, key: &str) -> Option<Cid> {
         self.cache.read().get(key).cloned()
     }
@@ -1,7 +1,10 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
 use crate::cid_collections::CidHashMap;
 use crate::shim::{clock::ChainEpoch, state_tree::StateTree};
@@ -9,6 +12,7 @@ use crate::state_migration::common::MigrationCache;
 use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
 
+use super::checkpoint::{Checkpoint, MigrationProgress};
 use super::PostMigrationCheckArc;
 use super::{verifier::MigrationVerifier, Migrator, PostMigratorArc};
 use crate::state_migration::common::migration_job::{MigrationJob, MigrationJobOutput};
@@ -64,12 +68,19 @@ impl<BS: Blockstore> StateMigration<BS> {
 }
 
 impl<BS: Blockstore + Send + Sync> StateMigration<BS> {
+    /// Migrates `actors_in` into `actors_out`. Migrators may look up and store
+    /// intermediate results in `cache`, which can be pre-populated by running
+    /// the same migration on an earlier state.
+    ///
+    /// Progress is checkpointed periodically, and an interrupted migration of
+    /// the same state resumes from its last checkpoint.
     pub(in crate::state_migration) fn migrate_state_tree(
         &self,
         store: &Arc<BS>,
         prior_epoch: ChainEpoch,
-        actors_in: StateTree<BS>,
+        mut actors_in: StateTree<BS>,
         mut actors_out: StateTree<BS>,
+        cache: &MigrationCache,
     ) -> anyhow::Result<Cid> {
         // Checks if the migration specification is correct
         if let Some(verifier) = &self.verifier {
@@ -79,10 +90,25 @@ impl<BS: Blockstore + Send + Sync> StateMigration<BS> {
         // we need at least 3 threads for the migration to work
         let threads = num_cpus::get().max(3);
         let chan_size = threads / 2;
-        let cache = MigrationCache::default();
 
         tracing::info!("Using {threads} threads for migration and channel size of {chan_size}",);
 
+        let input = actors_in.flush()?;
+        let resumed = match Checkpoint::load(&input, prior_epoch) {
+            Some(checkpoint) => {
+                tracing::info!(
+                    "Resuming migration from checkpoint, {} actors already migrated",
+                    checkpoint.migrated
+                );
+                actors_out = StateTree::new_from_root(store.clone(), &checkpoint.output)?;
+                checkpoint.migrated
+            }
+            None => 0,
+        };
+        let mut progress = MigrationProgress::new(resumed);
+        // Counted while the input actors are walked, to avoid another walk.
+        let total = &AtomicU64::new(0);
+
         let pool = rayon::ThreadPoolBuilder::new()
             .thread_name(|id| format!("state migration thread: {id}"))
             .num_threads(threads)
@@ -93,18 +119,25 @@ impl<BS: Blockstore + Send + Sync> StateMigration<BS> {
 
         pool.scope(|s| {
             s.spawn(move |_| {
-                actors_in
-                    .for_each(|addr, state| {
+                let mut index = 0;
+                let walk = actors_in.for_each(|addr, state| {
+                    anyhow::ensure!(!cache.is_cancelled(), "migration cancelled");
+                    if index >= resumed {
                         state_tx
-                            .send((addr, state.clone()))
+                            .send((index, addr, state.clone()))
                             .expect("failed sending actor state through channel");
-                        Ok(())
-                    })
-                    .expect("Failed iterating over actor state");
+                    }
+                    index += 1;
+                    Ok(())
+                });
+                if !cache.is_cancelled() {
+                    walk.expect("Failed iterating over actor state");
+                    total.store(index, Ordering::Relaxed);
+                }
             });
 
             s.spawn(move |scope| {
-                while let Ok((address, state)) = state_rx.recv() {
+                while let Ok((index, address, state)) = state_rx.recv() {
                     let job_tx = job_tx.clone();
                     let migrator = self.migrations.get(&state.code).cloned().unwrap_or_else(|| panic!("migration failed with state code: {}", state.code));
                     let cache_clone = cache.clone();
@@ -121,7 +154,7 @@ impl<BS: Blockstore + Send + Sync> StateMigration<BS> {
                             )
                         });
 
-                        job_tx.send(job_output).unwrap_or_else(|_| {
+                        job_tx.send((index, job_output)).unwrap_or_else(|_| {
                             panic!("failed sending job output for address: {address}")
                         });
                     });
@@ -129,7 +162,7 @@ impl<BS: Blockstore + Send + Sync> StateMigration<BS> {
                 drop(job_tx);
             });
 
-            while let Ok(job_output) = job_rx.recv() {
+            while let Ok((index, job_output)) = job_rx.recv() {
                 if let Some(MigrationJobOutput {
                     address,
                     actor_state,
@@ -142,9 +175,31 @@ impl<BS: Blockstore + Send + Sync> StateMigration<BS> {
                             )
                         });
                 }
+                progress.complete(index);
+                match total.load(Ordering::Relaxed) {
+                    0 => {}
+                    total => progress.set_total(total),
+                }
+                progress.report();
+                if progress.checkpoint_due() {
+                    let checkpoint = actors_out.flush().and_then(|output| {
+                        Checkpoint {
+                            input,
+                            prior_epoch,
+                            output,
+                            migrated: progress.prefix(),
+                        }
+                        .save()
+                    });
+                    if let Err(e) = checkpoint {
+                        tracing::warn!("Failed to checkpoint migration: {e}");
+                    }
+                }
             }
         });
 
+        anyhow::ensure!(!cache.is_cancelled(), "migration cancelled");
+
         // execute post migration actions, e.g., create new actors
         for post_migrator in self.post_migrators.iter() {
             post_migrator.post_migrate_state(store, &mut actors_out)?;
@@ -155,6 +210,101 @@ impl<BS: Blockstore + Send + Sync> StateMigration<BS> {
             post_migration_check.post_migrate_check(store, &actors_out)?;
         }
 
+        Checkpoint::remove(&input, prior_epoch);
+
         actors_out.flush()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+    use crate::shim::{
+        address::Address,
+        econ::TokenAmount,
+        state_tree::{ActorState, StateTreeVersion},
+    };
+    use crate::state_migration::common::{checkpoint::set_checkpoint_dir, migrators::nil_migrator};
+    use cid::multihash::{Code, MultihashDigest};
+    use fvm_ipld_encoding::IPLD_RAW;
+
+    fn code(name: &str) -> Cid {
+        Cid::new_v1(IPLD_RAW, Code::Identity.digest(name.as_bytes()))
+    }
+
+    fn actor(code: Cid, balance: u64) -> ActorState {
+        ActorState::new(
+            code,
+            Cid::default(),
+            TokenAmount::from_atto(balance),
+            0,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_resume_interrupted_migration() {
+        set_checkpoint_dir(tempfile::tempdir().unwrap().into_path());
+        let store = Arc::new(MemoryDB::default());
+        let (old_code, new_code) = (code("old"), code("new"));
+        let mut migration = StateMigration::new(None);
+        migration.add_migrator(old_code, nil_migrator(new_code));
+
+        let mut actors_in = StateTree::new(store.clone(), StateTreeVersion::V5).unwrap();
+        for id in 100..120 {
+            actors_in
+                .set_actor(&Address::new_id(id), actor(old_code, id))
+                .unwrap();
+        }
+        let input = actors_in.flush().unwrap();
+        let migrate = || {
+            migration
+                .migrate_state_tree(
+                    &store,
+                    42,
+                    StateTree::new_from_root(store.clone(), &input).unwrap(),
+                    StateTree::new(store.clone(), StateTreeVersion::V5).unwrap(),
+                    &MigrationCache::default(),
+                )
+                .unwrap()
+        };
+        let migrated = migrate();
+
+        // Pretend a migration was interrupted after the first 5 actors, which
+        // the checkpoint marks with a distinct balance.
+        let mut order = vec![];
+        actors_in
+            .for_each(|address, _| {
+                order.push(address);
+                Ok(())
+            })
+            .unwrap();
+        let mut partial = StateTree::new(store.clone(), StateTreeVersion::V5).unwrap();
+        for address in &order[..5] {
+            partial.set_actor(address, actor(new_code, 1)).unwrap();
+        }
+        Checkpoint {
+            input,
+            prior_epoch: 42,
+            output: partial.flush().unwrap(),
+            migrated: 5,
+        }
+        .save()
+        .unwrap();
+
+        let resumed = StateTree::new_from_root(store.clone(), &migrate()).unwrap();
+        let migrated = StateTree::new_from_root(store.clone(), &migrated).unwrap();
+        for (i, address) in order.iter().enumerate() {
+            let actor = resumed.get_actor(address).unwrap().unwrap();
+            assert_eq!(actor.code, new_code);
+            if i < 5 {
+                assert_eq!(actor.balance, TokenAmount::from_atto(1).into());
+            } else {
+                assert_eq!(actor, migrated.get_actor(address).unwrap().unwrap());
+            }
+        }
+        // The checkpoint is removed once the migration completes.
+        assert!(Checkpoint::load(&input, 42).is_none());
+    }
+}
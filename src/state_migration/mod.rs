@@ -10,9 +10,13 @@ use crate::networks::{ChainConfig, Height, NetworkChain};
 use crate::shim::clock::ChainEpoch;
 use crate::shim::state_tree::StateRoot;
 use crate::utils::misc::reveal_three_trees;
+use ahash::{HashMap, HashSet};
+use anyhow::Context as _;
 use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::CborStore;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 
 pub(in crate::state_migration) mod common;
 mod nv17;
@@ -21,16 +25,32 @@ mod nv19;
 mod nv21;
 mod nv21fix;
 mod type_migrations;
+mod verification;
 
-type RunMigration<DB> = fn(&ChainConfig, &Arc<DB>, &Cid, ChainEpoch) -> anyhow::Result<Cid>;
+pub use common::checkpoint::set_checkpoint_dir;
+use common::MigrationCache;
 
-/// Run state migrations
-pub fn run_state_migrations<DB>(
-    epoch: ChainEpoch,
-    chain_config: &Arc<ChainConfig>,
-    db: &Arc<DB>,
-    parent_state: &Cid,
-) -> anyhow::Result<Option<Cid>>
+type RunMigration<DB> =
+    fn(&ChainConfig, &Arc<DB>, &Cid, ChainEpoch, &MigrationCache) -> anyhow::Result<Cid>;
+
+/// Pre-migrations start this many epochs before the upgrade.
+const PRE_MIGRATION_START_WITHIN: ChainEpoch = 240;
+/// Pre-migrations are not started this close to the upgrade, as they would not
+/// finish in time to be useful.
+const PRE_MIGRATION_DONT_START_WITHIN: ChainEpoch = 60;
+/// Pre-migrations still running this close to the upgrade are stopped, so
+/// that they don't compete with the migration at the upgrade for resources.
+const PRE_MIGRATION_STOP_WITHIN: ChainEpoch = 10;
+/// Set to `1` to disable pre-migrations.
+const DISABLE_PRE_MIGRATIONS_ENV: &str = "FOREST_DISABLE_PRE_MIGRATIONS";
+
+/// Caches filled by pre-migrations, by upgrade epoch.
+static PRE_MIGRATION_CACHES: Lazy<Mutex<HashMap<ChainEpoch, MigrationCache>>> =
+    Lazy::new(Default::default);
+/// Upgrade epochs whose pre-migration is running or has finished.
+static PRE_MIGRATIONS_STARTED: Lazy<Mutex<HashSet<ChainEpoch>>> = Lazy::new(Default::default);
+
+fn migration_schedule<DB>(chain_config: &ChainConfig) -> Vec<(Height, RunMigration<DB>)>
 where
     DB: Blockstore + Send + Sync,
 {
@@ -45,6 +65,32 @@ where
         mappings.push((Height::WatermelonFix, nv21fix::run_migration::<DB>));
     }
 
+    mappings
+}
+
+/// Whether a state migration runs at `epoch`. Such migrations are too
+/// expensive to run when simulating messages, e.g. to estimate gas.
+pub fn has_state_migration<DB>(epoch: ChainEpoch, chain_config: &ChainConfig) -> bool
+where
+    DB: Blockstore + Send + Sync,
+{
+    migration_schedule::<DB>(chain_config)
+        .into_iter()
+        .any(|(height, _)| chain_config.epoch(height) == epoch)
+}
+
+/// Run state migrations
+pub fn run_state_migrations<DB>(
+    epoch: ChainEpoch,
+    chain_config: &Arc<ChainConfig>,
+    db: &Arc<DB>,
+    parent_state: &Cid,
+) -> anyhow::Result<Option<Cid>>
+where
+    DB: Blockstore + Send + Sync,
+{
+    let mappings = migration_schedule::<DB>(chain_config);
+
     // Make sure bundle is defined.
     static BUNDLE_CHECKED: AtomicBool = AtomicBool::new(false);
     if !BUNDLE_CHECKED.load(atomic::Ordering::Relaxed) {
@@ -66,7 +112,16 @@ where
         if epoch == chain_config.epoch(height) {
             tracing::info!("Running {height} migration at epoch {epoch}");
             let start_time = std::time::Instant::now();
-            let new_state = migrate(chain_config, db, parent_state, epoch)?;
+            // A pre-migration still running is cancelled, but what it has
+            // cached so far is used.
+            let cache = match PRE_MIGRATION_CACHES.lock().remove(&epoch) {
+                Some(cache) => {
+                    cache.cancel();
+                    cache.detach()
+                }
+                None => MigrationCache::default(),
+            };
+            let new_state = migrate(chain_config, db, parent_state, epoch, &cache)?;
             let elapsed = start_time.elapsed().as_secs_f32();
             // `new_state_actors` is the Go state migration output, log for comparision
             let new_state_actors = db
@@ -76,6 +131,10 @@ where
                 .map(|sr| format!("{}", sr.actors))
                 .unwrap_or_default();
             if new_state != *parent_state {
+                if verification::verification_enabled() {
+                    verification::verify_migration(db, parent_state, &new_state)
+                        .with_context(|| format!("{height} migration verification failed"))?;
+                }
                 reveal_three_trees();
                 tracing::info!("State migration at height {height}(epoch {epoch}) was successful, Previous state: {parent_state}, new state: {new_state}, new state actors: {new_state_actors}. Took: {elapsed}s.");
             } else {
@@ -89,5 +148,101 @@ where
     Ok(None)
 }
 
+/// Starts the pre-migration of an upcoming upgrade in the background, if
+/// `epoch` is within its pre-migration window. A pre-migration runs the
+/// upgrade's migration on `parent_state` and keeps the intermediate results
+/// (e.g. migrated sector arrays) so that the migration at the upgrade height
+/// only has to process what changed since.
+pub fn run_state_pre_migrations<DB>(
+    epoch: ChainEpoch,
+    chain_config: &Arc<ChainConfig>,
+    db: &Arc<DB>,
+    parent_state: &Cid,
+) where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    if std::env::var(DISABLE_PRE_MIGRATIONS_ENV) == Ok("1".to_owned()) {
+        return;
+    }
+
+    for (height, migrate) in migration_schedule::<DB>(chain_config) {
+        let upgrade_epoch = chain_config.epoch(height);
+        if is_pre_migration_stop_window(epoch, upgrade_epoch) {
+            if let Some(cache) = PRE_MIGRATION_CACHES.lock().get(&upgrade_epoch) {
+                if !cache.is_cancelled() {
+                    tracing::info!("Stopping {height} pre-migration at epoch {epoch}");
+                    cache.cancel();
+                }
+            }
+        }
+        if is_pre_migration_window(epoch, upgrade_epoch) {
+            start_pre_migration(
+                height,
+                upgrade_epoch,
+                migrate,
+                epoch,
+                chain_config,
+                db,
+                parent_state,
+            );
+        }
+    }
+}
+
+/// Runs `migrate` on `parent_state` in the background, unless a pre-migration
+/// for `upgrade_epoch` is running or has finished. A failed pre-migration is
+/// retried at the next epoch of the window, reusing what the failed run cached.
+fn start_pre_migration<DB>(
+    height: Height,
+    upgrade_epoch: ChainEpoch,
+    migrate: RunMigration<DB>,
+    epoch: ChainEpoch,
+    chain_config: &Arc<ChainConfig>,
+    db: &Arc<DB>,
+    parent_state: &Cid,
+) -> Option<std::thread::JoinHandle<()>>
+where
+    DB: Blockstore + Send + Sync + 'static,
+{
+    if !PRE_MIGRATIONS_STARTED.lock().insert(upgrade_epoch) {
+        return None;
+    }
+
+    let cache = PRE_MIGRATION_CACHES
+        .lock()
+        .entry(upgrade_epoch)
+        .or_default()
+        .clone();
+
+    tracing::info!(
+        "Starting {height} pre-migration at epoch {epoch}, {} epochs before the upgrade",
+        upgrade_epoch - epoch
+    );
+    let (chain_config, db, parent_state) = (chain_config.clone(), db.clone(), *parent_state);
+    Some(std::thread::spawn(move || {
+        let start_time = std::time::Instant::now();
+        // The resulting state is discarded; only the cache is kept.
+        match migrate(&chain_config, &db, &parent_state, epoch, &cache) {
+            Ok(_) => tracing::info!(
+                "{height} pre-migration finished. Took: {}s.",
+                start_time.elapsed().as_secs_f32()
+            ),
+            Err(e) => {
+                tracing::warn!("{height} pre-migration failed: {e}");
+                PRE_MIGRATIONS_STARTED.lock().remove(&upgrade_epoch);
+            }
+        }
+    }))
+}
+
+fn is_pre_migration_window(epoch: ChainEpoch, upgrade_epoch: ChainEpoch) -> bool {
+    epoch >= upgrade_epoch - PRE_MIGRATION_START_WITHIN
+        && epoch < upgrade_epoch - PRE_MIGRATION_DONT_START_WITHIN
+}
+
+fn is_pre_migration_stop_window(epoch: ChainEpoch, upgrade_epoch: ChainEpoch) -> bool {
+    epoch >= upgrade_epoch - PRE_MIGRATION_STOP_WITHIN && epoch < upgrade_epoch
+}
+
 #[cfg(test)]
 mod tests;
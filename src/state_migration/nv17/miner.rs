@@ -51,7 +51,9 @@ where
     let policy = match chain {
         NetworkChain::Mainnet => fil_actors_shared::v8::runtime::Policy::mainnet(),
         NetworkChain::Calibnet => fil_actors_shared::v8::runtime::Policy::calibnet(),
-        NetworkChain::Devnet(_) => unimplemented!("Policy::devnet"),
+        NetworkChain::Butterflynet | NetworkChain::Devnet(_) => {
+            unimplemented!("Policy::devnet")
+        }
     };
     let empty_deadlines_v8 =
         fil_actor_miner_state::v8::Deadlines::new(&policy, empty_deadline_v8_cid);
@@ -63,7 +65,9 @@ where
     let policy = match chain {
         NetworkChain::Mainnet => fil_actors_shared::v9::runtime::Policy::mainnet(),
         NetworkChain::Calibnet => fil_actors_shared::v9::runtime::Policy::calibnet(),
-        NetworkChain::Devnet(_) => unimplemented!("Policy::devnet"),
+        NetworkChain::Butterflynet | NetworkChain::Devnet(_) => {
+            unimplemented!("Policy::devnet")
+        }
     };
     let empty_deadlines_v9 =
         fil_actor_miner_state::v9::Deadlines::new(&policy, empty_deadline_v9_cid);
@@ -257,7 +261,9 @@ impl MinerMigrator {
             let policy = match &self.chain {
                 NetworkChain::Mainnet => fil_actors_shared::v9::runtime::Policy::mainnet(),
                 NetworkChain::Calibnet => fil_actors_shared::v9::runtime::Policy::calibnet(),
-                NetworkChain::Devnet(_) => unimplemented!("Policy::devnet"),
+                NetworkChain::Butterflynet | NetworkChain::Devnet(_) => {
+                    unimplemented!("Policy::devnet")
+                }
             };
             let mut out_deadlines =
                 fil_actor_miner_state::v9::Deadlines::new(&policy, self.empty_deadline_v9_cid);
@@ -616,15 +622,27 @@ mod tests {
         if let Some(bundle) = &mut chain_config.height_infos[Height::Shark as usize].bundle {
             *bundle = new_manifest_cid;
         }
-        let new_state_cid =
-            super::super::run_migration(&chain_config, &store, &tree_root, 200).unwrap();
+        let new_state_cid = super::super::run_migration(
+            &chain_config,
+            &store,
+            &tree_root,
+            200,
+            &crate::state_migration::common::MigrationCache::default(),
+        )
+        .unwrap();
         let actors_out_state_root: StateRoot = store.get_cbor(&new_state_cid).unwrap().unwrap();
         assert_eq!(
             actors_out_state_root.actors.to_string(),
             "bafy2bzacedgtk3lnnyfxnzc32etqaj3zvi7ar7nxq2jtxd2qr36ftbsjoycqu"
         );
-        let new_state_cid2 =
-            super::super::run_migration(&chain_config, &store, &tree_root, 200).unwrap();
+        let new_state_cid2 = super::super::run_migration(
+            &chain_config,
+            &store,
+            &tree_root,
+            200,
+            &crate::state_migration::common::MigrationCache::default(),
+        )
+        .unwrap();
         assert_eq!(new_state_cid, new_state_cid2);
     }
 
@@ -649,8 +667,14 @@ mod tests {
         if let Some(bundle) = &mut chain_config.height_infos[Height::Shark as usize].bundle {
             *bundle = new_manifest_cid;
         }
-        let new_state_cid =
-            super::super::run_migration(&chain_config, &store, &state_tree_old_root, 200).unwrap();
+        let new_state_cid = super::super::run_migration(
+            &chain_config,
+            &store,
+            &state_tree_old_root,
+            200,
+            &crate::state_migration::common::MigrationCache::default(),
+        )
+        .unwrap();
         let actors_out_state_root: StateRoot = store.get_cbor(&new_state_cid).unwrap().unwrap();
         assert_eq!(
             actors_out_state_root.actors.to_string(),
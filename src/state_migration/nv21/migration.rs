@@ -18,7 +18,7 @@ use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::CborStore;
 
 use super::{miner, system, verifier::Verifier, SystemStateOld};
-use crate::state_migration::common::{migrators::nil_migrator, StateMigration};
+use crate::state_migration::common::{migrators::nil_migrator, MigrationCache, StateMigration};
 
 impl<BS: Blockstore> StateMigration<BS> {
     pub fn add_nv21_migrations(
@@ -56,6 +56,9 @@ impl<BS: Blockstore> StateMigration<BS> {
                 fil_actors_shared::v11::runtime::Policy::calibnet(),
                 fil_actors_shared::v12::runtime::Policy::calibnet(),
             ),
+            NetworkChain::Butterflynet => {
+                anyhow::bail!("butterflynet is launched after the nv21 upgrade")
+            }
             NetworkChain::Devnet(_) => {
                 let mut policy_old = fil_actors_shared::v11::runtime::Policy::mainnet();
                 policy_old.minimum_consensus_power = 2048.into();
@@ -101,6 +104,7 @@ pub fn run_migration<DB>(
     blockstore: &Arc<DB>,
     state: &Cid,
     epoch: ChainEpoch,
+    cache: &MigrationCache,
 ) -> anyhow::Result<Cid>
 where
     DB: Blockstore + Send + Sync,
@@ -126,7 +130,8 @@ where
 
     let actors_in = StateTree::new_from_root(blockstore.clone(), state)?;
     let actors_out = StateTree::new(blockstore.clone(), StateTreeVersion::V5)?;
-    let new_state = migration.migrate_state_tree(blockstore, epoch, actors_in, actors_out)?;
+    let new_state =
+        migration.migrate_state_tree(blockstore, epoch, actors_in, actors_out, cache)?;
 
     Ok(new_state)
 }
@@ -368,11 +368,23 @@ mod tests {
         if let Some(bundle) = &mut chain_config.height_infos[Height::Watermelon as usize].bundle {
             *bundle = new_manifest_cid;
         }
-        let new_state_cid =
-            super::super::run_migration(&chain_config, &store, &tree_root, 200).unwrap();
+        let new_state_cid = super::super::run_migration(
+            &chain_config,
+            &store,
+            &tree_root,
+            200,
+            &crate::state_migration::common::MigrationCache::default(),
+        )
+        .unwrap();
 
-        let new_state_cid2 =
-            super::super::run_migration(&chain_config, &store, &tree_root, 200).unwrap();
+        let new_state_cid2 = super::super::run_migration(
+            &chain_config,
+            &store,
+            &tree_root,
+            200,
+            &crate::state_migration::common::MigrationCache::default(),
+        )
+        .unwrap();
 
         assert_eq!(new_state_cid, new_state_cid2);
 
@@ -69,6 +69,73 @@ async fn test_nv19_state_migration_calibnet() {
     .await
 }
 
+#[test]
+fn test_pre_migration_window() {
+    use super::is_pre_migration_window;
+
+    assert!(!is_pre_migration_window(999, 1240));
+    assert!(is_pre_migration_window(1000, 1240));
+    assert!(is_pre_migration_window(1179, 1240));
+    assert!(!is_pre_migration_window(1180, 1240));
+    assert!(!is_pre_migration_window(1240, 1240));
+}
+
+#[test]
+fn test_pre_migration_stop_window() {
+    use super::is_pre_migration_stop_window;
+
+    assert!(!is_pre_migration_stop_window(1229, 1240));
+    assert!(is_pre_migration_stop_window(1230, 1240));
+    assert!(is_pre_migration_stop_window(1239, 1240));
+    assert!(!is_pre_migration_stop_window(1240, 1240));
+}
+
+#[test]
+fn test_failed_pre_migration_is_retried() {
+    use super::{common::MigrationCache, start_pre_migration};
+    use crate::db::MemoryDB;
+    use crate::shim::clock::ChainEpoch;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Fails the first time, after caching some of its work.
+    fn flaky_migration(
+        _: &ChainConfig,
+        _: &Arc<MemoryDB>,
+        _: &Cid,
+        _: ChainEpoch,
+        cache: &MigrationCache,
+    ) -> anyhow::Result<Cid> {
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        if RUNS.fetch_add(1, Ordering::SeqCst) == 0 {
+            cache.insert("partial".into(), Cid::default());
+            anyhow::bail!("interrupted");
+        }
+        anyhow::ensure!(cache.get("partial").is_some(), "the cache was lost");
+        Ok(Cid::default())
+    }
+
+    // An upgrade epoch no other test uses, as pre-migrations are tracked
+    // globally.
+    let upgrade_epoch = 7_000_240;
+    let chain_config = Arc::new(ChainConfig::calibnet());
+    let db = Arc::new(MemoryDB::default());
+    let start = || {
+        start_pre_migration(
+            Height::Watermelon,
+            upgrade_epoch,
+            flaky_migration,
+            upgrade_epoch - 200,
+            &chain_config,
+            &db,
+            &Cid::default(),
+        )
+    };
+
+    start().expect("first run").join().unwrap();
+    start().expect("retry after the failure").join().unwrap();
+    assert!(start().is_none(), "the pre-migration has succeeded");
+}
+
 async fn test_state_migration(
     height: Height,
     network: NetworkChain,
@@ -114,7 +181,11 @@ async fn test_state_migration(
     load_actor_bundles(&store, &network).await.unwrap();
 
     let chain_config = Arc::new(ChainConfig::from_chain(&network));
-    let height_info = &chain_config.height_infos[height as usize];
+    let height_info = chain_config
+        .height_infos
+        .iter()
+        .find(|info| info.height == height)
+        .unwrap();
 
     let state_root: StateRoot = store.get_cbor(&old_state).unwrap().unwrap();
     println!("Actor root (for Go test): {}", state_root.actors);
@@ -0,0 +1,206 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Invariant checks between the state trees before and after a migration.
+//! They are independent of the upgrade, unlike the post migration checks of
+//! the individual migrations.
+
+use std::sync::Arc;
+
+use crate::shim::{address::Address, state_tree::StateTree};
+use ahash::HashMap;
+use anyhow::Context as _;
+use cid::Cid;
+use fil_actors_shared::v12::make_map_with_root_and_bitwidth;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{tuple::*, CborStore as _};
+use fvm_shared4::econ::TokenAmount;
+use libipld_core::ipld::Ipld;
+use num_traits::Zero as _;
+
+/// Set to `1` to verify every migration before its state root is used. A
+/// migration violating an invariant fails, so its state root is never used.
+pub(super) const VERIFY_MIGRATIONS_ENV: &str = "FOREST_VERIFY_MIGRATIONS";
+
+pub(super) fn verification_enabled() -> bool {
+    std::env::var(VERIFY_MIGRATIONS_ENV) == Ok("1".to_owned())
+}
+
+/// Subset of the `datacap` actor state that has kept its layout since `v9`.
+#[derive(Serialize_tuple, Deserialize_tuple)]
+struct DataCapState {
+    governor: Ipld,
+    token: TokenState,
+}
+
+#[derive(Serialize_tuple, Deserialize_tuple)]
+struct TokenState {
+    supply: TokenAmount,
+    balances: Cid,
+    allowances: Cid,
+    hamt_bit_width: u32,
+}
+
+/// Checks that a migration from `state_pre` to `state_post`:
+/// - keeps every actor, with its balance and sequence,
+/// - conserves the total balance,
+/// - keeps the `datacap` supply, which also matches the sum of its balances.
+///
+/// All violations are reported in the returned error, which fails the
+/// migration.
+pub(super) fn verify_migration<DB: Blockstore>(
+    db: &Arc<DB>,
+    state_pre: &Cid,
+    state_post: &Cid,
+) -> anyhow::Result<()> {
+    let actors_pre = StateTree::new_from_root(db.clone(), state_pre)?;
+    let actors_post = StateTree::new_from_root(db.clone(), state_post)?;
+
+    let mut violations = vec![];
+
+    let mut pre = HashMap::default();
+    actors_pre.for_each(|address, actor| {
+        pre.insert(address, (actor.balance.clone(), actor.sequence));
+        Ok(())
+    })?;
+
+    let (mut balance_pre, mut balance_post) = (TokenAmount::zero(), TokenAmount::zero());
+    for (balance, _) in pre.values() {
+        balance_pre += balance.clone();
+    }
+    let mut count_post = 0;
+    actors_post.for_each(|address, actor| {
+        count_post += 1;
+        balance_post += actor.balance.clone();
+        if let Some((balance, sequence)) = pre.remove(&address) {
+            if balance != actor.balance {
+                violations.push(format!(
+                    "actor {address} balance changed: {balance} -> {}",
+                    actor.balance
+                ));
+            }
+            if sequence != actor.sequence {
+                violations.push(format!(
+                    "actor {address} sequence changed: {sequence} -> {}",
+                    actor.sequence
+                ));
+            }
+        }
+        Ok(())
+    })?;
+
+    for address in pre.keys() {
+        violations.push(format!("actor {address} was removed"));
+    }
+    if balance_pre != balance_post {
+        violations.push(format!(
+            "total balance changed: {balance_pre} -> {balance_post}"
+        ));
+    }
+
+    let supply_pre = datacap_supply(db, &actors_pre)?;
+    let supply_post = datacap_supply(db, &actors_post)?;
+    match (&supply_pre, &supply_post) {
+        (Some(pre), Some(post)) if pre != post => {
+            violations.push(format!("datacap supply changed: {pre} -> {post}"));
+        }
+        (Some(_), None) => violations.push("datacap actor state is missing".into()),
+        _ => {}
+    }
+    if let Some(post) = &supply_post {
+        let balances = datacap_balances(db, &actors_post)?;
+        if &balances != post {
+            violations.push(format!(
+                "datacap balances sum to {balances}, but the supply is {post}"
+            ));
+        }
+    }
+
+    anyhow::ensure!(
+        violations.is_empty(),
+        "migration to {state_post} violates {} invariant(s):\n{}",
+        violations.len(),
+        violations.join("\n")
+    );
+    tracing::info!(
+        "Verified migration from {state_pre} to {state_post}: {count_post} actors, total balance {balance_post}"
+    );
+    Ok(())
+}
+
+fn datacap_state<DB: Blockstore>(
+    db: &Arc<DB>,
+    actors: &StateTree<DB>,
+) -> anyhow::Result<Option<DataCapState>> {
+    let Some(actor) = actors.get_actor(&Address::DATACAP_TOKEN_ACTOR)? else {
+        return Ok(None);
+    };
+    let state = db
+        .get_cbor(&actor.state)?
+        .context("datacap actor state not found")?;
+    Ok(Some(state))
+}
+
+fn datacap_supply<DB: Blockstore>(
+    db: &Arc<DB>,
+    actors: &StateTree<DB>,
+) -> anyhow::Result<Option<TokenAmount>> {
+    Ok(datacap_state(db, actors)?.map(|state| state.token.supply))
+}
+
+fn datacap_balances<DB: Blockstore>(
+    db: &Arc<DB>,
+    actors: &StateTree<DB>,
+) -> anyhow::Result<TokenAmount> {
+    let mut total = TokenAmount::zero();
+    if let Some(state) = datacap_state(db, actors)? {
+        let balances = make_map_with_root_and_bitwidth::<_, TokenAmount>(
+            &state.token.balances,
+            db,
+            state.token.hamt_bit_width,
+        )?;
+        balances.for_each(|_, balance| {
+            total += balance.clone();
+            Ok(())
+        })?;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+    use crate::shim::state_tree::{ActorState, StateTreeVersion};
+
+    fn state(db: &Arc<MemoryDB>, actors: &[(u64, u64)]) -> Cid {
+        let mut tree = StateTree::new(db.clone(), StateTreeVersion::V5).unwrap();
+        for &(id, balance) in actors {
+            let actor = ActorState::new(
+                Cid::default(),
+                Cid::default(),
+                TokenAmount::from_atto(balance).into(),
+                0,
+                None,
+            );
+            tree.set_actor(&Address::new_id(id), actor).unwrap();
+        }
+        tree.flush().unwrap()
+    }
+
+    #[test]
+    fn test_verify_migration() {
+        let db = Arc::new(MemoryDB::default());
+        let pre = state(&db, &[(100, 10), (101, 20)]);
+        verify_migration(&db, &pre, &pre).unwrap();
+
+        let post = state(&db, &[(100, 10), (101, 21)]);
+        let err = verify_migration(&db, &pre, &post).unwrap_err().to_string();
+        assert!(err.contains("actor f0101 balance changed"), "{err}");
+        assert!(err.contains("total balance changed"), "{err}");
+
+        let post = state(&db, &[(100, 10)]);
+        let err = verify_migration(&db, &pre, &post).unwrap_err().to_string();
+        assert!(err.contains("actor f0101 was removed"), "{err}");
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Machine-readable renderings of a state diff.
+
+use std::io::Write;
+
+use crate::ipld::json::IpldJsonRef;
+use crate::shim::{address::Address, state_tree::ActorState};
+use fvm_ipld_blockstore::Blockstore;
+use libipld_core::ipld::Ipld;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::structural::Change;
+use super::{actor_to_resolved, ActorDiff};
+
+/// Output format of [`super::print_state_diff`].
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, strum::EnumString, strum::Display, clap::ValueEnum,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum StateDiffFormat {
+    /// Colored, human-readable diff.
+    #[default]
+    Text,
+    /// A single [RFC 6902](https://datatracker.ietf.org/doc/html/rfc6902)
+    /// document that turns the expected state into the computed state. Paths
+    /// start with the actor address.
+    JsonPatch,
+    /// One JSON object per changed value.
+    Jsonl,
+}
+
+/// One changed value. Serialized as-is for [`StateDiffFormat::Jsonl`], and
+/// reduced to `op`, `path` and `value` for [`StateDiffFormat::JsonPatch`].
+#[derive(Serialize)]
+struct Record {
+    address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actor: Option<&'static str>,
+    op: &'static str,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    before: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct PatchOperation {
+    op: &'static str,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<Value>,
+}
+
+impl From<Record> for PatchOperation {
+    fn from(record: Record) -> Self {
+        PatchOperation {
+            op: record.op,
+            path: format!("/{}{}", record.address, record.path),
+            value: record.after,
+        }
+    }
+}
+
+fn ipld_value(ipld: &Ipld) -> anyhow::Result<Value> {
+    Ok(serde_json::to_value(IpldJsonRef(ipld))?)
+}
+
+fn actor_value(
+    bs: &impl Blockstore,
+    actor: &ActorState,
+    depth: Option<u64>,
+) -> anyhow::Result<Value> {
+    Ok(serde_json::to_value(actor_to_resolved(bs, actor, depth))?)
+}
+
+fn records(
+    bs: &impl Blockstore,
+    diffs: &[ActorDiff],
+    depth: Option<u64>,
+) -> anyhow::Result<Vec<Record>> {
+    let address = |address: &Address| address.to_string();
+    let mut records = vec![];
+    for diff in diffs {
+        match diff {
+            ActorDiff::Added { address: a, actor } => records.push(Record {
+                address: address(a),
+                actor: None,
+                op: "add",
+                path: String::new(),
+                before: None,
+                after: Some(actor_value(bs, actor, depth)?),
+            }),
+            ActorDiff::Removed { address: a, actor } => records.push(Record {
+                address: address(a),
+                actor: None,
+                op: "remove",
+                path: String::new(),
+                before: Some(actor_value(bs, actor, depth)?),
+                after: None,
+            }),
+            ActorDiff::Changed {
+                address: a,
+                kind,
+                changes,
+            } => {
+                for change in changes {
+                    let (op, path, before, after) = match change {
+                        Change::Added { path, value } => ("add", path, None, Some(value)),
+                        Change::Removed { path, value } => ("remove", path, Some(value), None),
+                        Change::Modified {
+                            path,
+                            before,
+                            after,
+                        } => ("replace", path, Some(before), Some(after)),
+                    };
+                    records.push(Record {
+                        address: address(a),
+                        actor: *kind,
+                        op,
+                        path: path.to_json_pointer(),
+                        before: before.map(ipld_value).transpose()?,
+                        after: after.map(ipld_value).transpose()?,
+                    });
+                }
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Writes `diffs` in one of the machine-readable formats.
+pub(super) fn write_diffs(
+    bs: &impl Blockstore,
+    diffs: &[ActorDiff],
+    depth: Option<u64>,
+    format: StateDiffFormat,
+    mut writer: impl Write,
+) -> anyhow::Result<()> {
+    let records = records(bs, diffs, depth)?;
+    match format {
+        StateDiffFormat::Text => anyhow::bail!("text diffs are not machine-readable"),
+        StateDiffFormat::JsonPatch => {
+            let patch = records
+                .into_iter()
+                .map(PatchOperation::from)
+                .collect::<Vec<_>>();
+            serde_json::to_writer_pretty(&mut writer, &patch)?;
+            writeln!(writer)?;
+        }
+        StateDiffFormat::Jsonl => {
+            for record in records {
+                serde_json::to_writer(&mut writer, &record)?;
+                writeln!(writer)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+    use crate::statediff::structural::{ChangePath, Segment};
+
+    fn changed() -> Vec<ActorDiff> {
+        vec![ActorDiff::Changed {
+            address: Address::new_id(1234),
+            kind: Some("miner"),
+            changes: vec![Change::Modified {
+                path: ChangePath(vec![
+                    Segment::Field("state".into()),
+                    Segment::Field("sectors".into()),
+                    Segment::Key("123".into()),
+                    Segment::Field("expiration".into()),
+                ]),
+                before: Ipld::Integer(100),
+                after: Ipld::Integer(200),
+            }],
+        }]
+    }
+
+    fn render(format: StateDiffFormat) -> String {
+        let mut out = vec![];
+        write_diffs(&MemoryDB::default(), &changed(), None, format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn json_patch() {
+        let patch: Value = serde_json::from_str(&render(StateDiffFormat::JsonPatch)).unwrap();
+        assert_eq!(
+            patch,
+            serde_json::json!([{
+                "op": "replace",
+                "path": "/f01234/state/sectors/123/expiration",
+                "value": { "/": { "int": "200" } },
+            }])
+        );
+    }
+
+    #[test]
+    fn jsonl() {
+        let line: Value = serde_json::from_str(render(StateDiffFormat::Jsonl).trim()).unwrap();
+        assert_eq!(
+            line,
+            serde_json::json!({
+                "address": "f01234",
+                "actor": "miner",
+                "op": "replace",
+                "path": "/state/sectors/123/expiration",
+                "before": { "/": { "int": "100" } },
+                "after": { "/": { "int": "200" } },
+            })
+        );
+    }
+}
@@ -1,7 +1,12 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+mod format;
 mod resolve;
+mod schema;
+mod structural;
+
+pub use format::StateDiffFormat;
 
 use std::{
     fmt::Write as FmtWrite,
@@ -15,6 +20,7 @@ use crate::shim::{
     state_tree::{ActorState, StateTree},
 };
 use ahash::HashMap;
+use anyhow::Context as _;
 use cid::Cid;
 use colored::*;
 use fil_actor_interface::{
@@ -28,6 +34,7 @@ use libipld_core::ipld::Ipld;
 use resolve::resolve_cids_recursive;
 use serde::{Deserialize, Serialize};
 use similar::{ChangeTag, TextDiff};
+use structural::{diff_actor_states, Change};
 
 #[derive(Serialize, Deserialize)]
 struct ActorStateResolved {
@@ -67,61 +74,139 @@ fn root_to_state_map<BS: Blockstore>(
     Ok(actors)
 }
 
+/// Difference between the expected and the computed version of an actor.
+enum ActorDiff {
+    Added {
+        address: Address,
+        actor: ActorState,
+    },
+    Removed {
+        address: Address,
+        actor: ActorState,
+    },
+    Changed {
+        address: Address,
+        kind: Option<&'static str>,
+        changes: Vec<Change>,
+    },
+}
+
 /// Tries to resolve state tree actors, if all data exists in store.
 /// The actors HAMT is hard to parse in a diff, so this attempts to remedy this.
-/// This function will only print the actors that are added, removed, or changed
-/// so it can be used on large state trees.
-fn try_print_actor_states<BS: Blockstore>(
+/// This function will only return the actors that are added, removed, or
+/// changed so it can be used on large state trees.
+fn try_diff_actor_states<BS: Blockstore>(
     bs: &Arc<BS>,
     root: &Cid,
     expected_root: &Cid,
     depth: Option<u64>,
-) -> Result<(), anyhow::Error> {
+) -> Result<Vec<ActorDiff>, anyhow::Error> {
     // For now, resolving to a map, because we need to use go implementation's
     // inefficient caching this would probably be faster in most cases.
     let mut e_state = root_to_state_map(bs, expected_root)?;
+    let mut diffs = vec![];
 
     // Compare state with expected
     let state_tree = StateTree::new_from_root(bs.clone(), root)?;
 
-    state_tree.for_each(|addr: Address, actor| {
-        let calc_pp = pp_actor_state(bs, actor, depth)?;
-
-        if let Some(other) = e_state.remove(&addr) {
+    state_tree.for_each(|address: Address, actor| {
+        if let Some(other) = e_state.remove(&address) {
             if &other != actor {
-                let comma = ",";
-                let expected_pp = pp_actor_state(bs, &other, depth)?;
-                let expected = expected_pp
-                    .split(comma)
-                    .map(|s| s.trim_start_matches('\n'))
-                    .collect::<Vec<&str>>();
-                let calculated = calc_pp
-                    .split(comma)
-                    .map(|s| s.trim_start_matches('\n'))
-                    .collect::<Vec<&str>>();
-                let diffs = TextDiff::from_slices(&expected, &calculated);
-                let stdout = stdout();
-                let mut handle = stdout.lock();
-                writeln!(handle, "Address {addr} changed: ")?;
-                print_diffs(&mut handle, diffs)?;
+                let kind = actor_kind(bs, actor);
+                let changes = diff_actor_states(bs, kind, &other, actor, depth)?;
+                diffs.push(ActorDiff::Changed {
+                    address,
+                    kind,
+                    changes,
+                });
             }
         } else {
-            // Added actor, print out the json format actor state.
-            println!("{}", format!("+ Address {addr}:\n{calc_pp}").green());
+            diffs.push(ActorDiff::Added {
+                address,
+                actor: actor.clone(),
+            });
         }
 
         Ok(())
     })?;
 
-    // Print all addresses that no longer have actor state
-    for (addr, state) in e_state.into_iter() {
-        let expected_json = serde_json::to_string_pretty(&actor_to_resolved(bs, &state, depth))?;
-        println!("{}", format!("- Address {addr}:\n{expected_json}").red())
-    }
+    // All addresses that no longer have actor state
+    diffs.extend(
+        e_state
+            .into_iter()
+            .map(|(address, actor)| ActorDiff::Removed { address, actor }),
+    );
 
+    Ok(diffs)
+}
+
+fn print_actor_diffs(
+    bs: &impl Blockstore,
+    diffs: &[ActorDiff],
+    depth: Option<u64>,
+) -> Result<(), anyhow::Error> {
+    for diff in diffs {
+        match diff {
+            ActorDiff::Changed {
+                address,
+                kind,
+                changes,
+            } => {
+                let stdout = stdout();
+                let mut handle = stdout.lock();
+                writeln!(
+                    handle,
+                    "Address {address} ({}) changed: ",
+                    kind.unwrap_or("unknown actor")
+                )?;
+                print_changes(&mut handle, changes)?;
+            }
+            ActorDiff::Added { address, actor } => {
+                // Added actor, print out the json format actor state.
+                let calc_pp = pp_actor_state(bs, actor, depth)?;
+                println!("{}", format!("+ Address {address}:\n{calc_pp}").green());
+            }
+            ActorDiff::Removed { address, actor } => {
+                let expected_json =
+                    serde_json::to_string_pretty(&actor_to_resolved(bs, actor, depth))?;
+                println!("{}", format!("- Address {address}:\n{expected_json}").red())
+            }
+        }
+    }
     Ok(())
 }
 
+/// Name of the builtin actor whose state is stored in `actor_state`, if it
+/// can be identified.
+fn actor_kind(bs: &impl Blockstore, actor_state: &ActorState) -> Option<&'static str> {
+    let (code, state) = (actor_state.code, actor_state.state);
+    if MinerState::load(bs, code, state).is_ok() {
+        Some("miner")
+    } else if CronState::load(bs, code, state).is_ok() {
+        Some("cron")
+    } else if AccountState::load(bs, code, state).is_ok() {
+        Some("account")
+    } else if PowerState::load(bs, code, state).is_ok() {
+        Some("power")
+    } else if InitState::load(bs, code, state).is_ok() {
+        Some("init")
+    } else if RewardState::load(bs, code, state).is_ok() {
+        Some("reward")
+    } else if SystemState::load(bs, code, state).is_ok() {
+        Some("system")
+    } else if MultiSigState::load(bs, code, state).is_ok() {
+        Some("multisig")
+    } else if MarketState::load(bs, code, state).is_ok() {
+        Some("market")
+    } else if DatacapState::load(bs, code, state).is_ok() {
+        Some("datacap")
+    } else if EvmState::load(bs, code, state).is_ok() {
+        Some("evm")
+    } else {
+        None
+    }
+}
+
 fn pp_actor_state(
     bs: &impl Blockstore,
     actor_state: &ActorState,
@@ -194,30 +279,52 @@ fn print_diffs(handle: &mut impl Write, diffs: TextDiff<str>) -> std::io::Result
     Ok(())
 }
 
+fn print_changes(handle: &mut impl Write, changes: &[Change]) -> std::io::Result<()> {
+    for change in changes {
+        match change {
+            Change::Added { .. } => writeln!(handle, "{}", format!("+ {change}").green())?,
+            Change::Removed { .. } => writeln!(handle, "{}", format!("- {change}").red())?,
+            Change::Modified { .. } => writeln!(handle, "{}", format!("~ {change}").yellow())?,
+        }
+    }
+    Ok(())
+}
+
 /// Prints a diff of the resolved state tree.
-/// If the actor's HAMT cannot be loaded, base IPLD resolution is given.
+/// If the actor's HAMT cannot be loaded, base IPLD resolution is given for
+/// [`StateDiffFormat::Text`]. The machine-readable formats require the actor
+/// states to be resolvable.
 pub fn print_state_diff<BS>(
     bs: &Arc<BS>,
     root: &Cid,
     expected_root: &Cid,
     depth: Option<u64>,
+    format: StateDiffFormat,
 ) -> Result<(), anyhow::Error>
 where
     BS: Blockstore,
 {
-    if let Err(e) = try_print_actor_states(bs, root, expected_root, depth) {
-        println!("Could not resolve actor states: {e}\nUsing default resolution:");
-        let expected = resolve_cids_recursive(bs, expected_root, depth)?;
-        let actual = resolve_cids_recursive(bs, root, depth)?;
+    let diffs = try_diff_actor_states(bs, root, expected_root, depth);
+    if format != StateDiffFormat::Text {
+        let diffs = diffs.context("Could not resolve actor states")?;
+        return format::write_diffs(bs, &diffs, depth, format, stdout().lock());
+    }
+    match diffs {
+        Ok(diffs) => print_actor_diffs(bs, &diffs, depth)?,
+        Err(e) => {
+            println!("Could not resolve actor states: {e}\nUsing default resolution:");
+            let expected = resolve_cids_recursive(bs, expected_root, depth)?;
+            let actual = resolve_cids_recursive(bs, root, depth)?;
 
-        let expected_json = serde_json::to_string_pretty(&IpldJsonRef(&expected))?;
-        let actual_json = serde_json::to_string_pretty(&IpldJsonRef(&actual))?;
+            let expected_json = serde_json::to_string_pretty(&IpldJsonRef(&expected))?;
+            let actual_json = serde_json::to_string_pretty(&IpldJsonRef(&actual))?;
 
-        let diffs = TextDiff::from_lines(&expected_json, &actual_json);
+            let diffs = TextDiff::from_lines(&expected_json, &actual_json);
 
-        let stdout = stdout();
-        let mut handle = stdout.lock();
-        print_diffs(&mut handle, diffs)?
+            let stdout = stdout();
+            let mut handle = stdout.lock();
+            print_diffs(&mut handle, diffs)?
+        }
     }
 
     Ok(())
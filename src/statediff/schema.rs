@@ -0,0 +1,231 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Field names for the tuple-encoded states of the builtin actors.
+//!
+//! Actor states are serialized as CBOR lists, so without a schema a change is
+//! only reported by field index. Layouts differ between actor versions, so
+//! the lists below only name the leading fields which have kept their
+//! position across versions; anything past them is reported by index.
+
+use super::structural::KeyKind;
+
+/// Layout of a tuple-encoded structure or of a collection.
+pub struct Schema {
+    /// Names of the leading fields.
+    pub fields: &'static [&'static str],
+    /// Schemas of the fields, by name.
+    pub children: &'static [(&'static str, &'static Schema)],
+    /// How keys are rendered if this is a HAMT.
+    pub key: KeyKind,
+    /// Schema of the values if this is a HAMT or an AMT.
+    pub element: Option<&'static Schema>,
+}
+
+impl Schema {
+    const fn collection(key: KeyKind, element: Option<&'static Schema>) -> Self {
+        Schema {
+            fields: &[],
+            children: &[],
+            key,
+            element,
+        }
+    }
+
+    /// Name and schema of the field at `index`.
+    pub fn field(&self, index: usize) -> (Option<&'static str>, Option<&'static Schema>) {
+        let Some(name) = self.fields.get(index).copied() else {
+            return (None, None);
+        };
+        let child = self
+            .children
+            .iter()
+            .find_map(|(n, schema)| (*n == name).then_some(*schema));
+        (Some(name), child)
+    }
+}
+
+/// Returns the schema of the state of the builtin actor `kind`.
+pub fn actor_state(kind: &str) -> Option<&'static Schema> {
+    match kind {
+        "account" => Some(&ACCOUNT),
+        "init" => Some(&INIT),
+        "miner" => Some(&MINER),
+        "power" => Some(&POWER),
+        "market" => Some(&MARKET),
+        "multisig" => Some(&MULTISIG),
+        _ => None,
+    }
+}
+
+static ACCOUNT: Schema = Schema {
+    fields: &["address"],
+    children: &[],
+    key: KeyKind::Any,
+    element: None,
+};
+
+static INIT: Schema = Schema {
+    fields: &["address_map", "next_id", "network_name"],
+    children: &[("address_map", &ADDRESS_MAP)],
+    key: KeyKind::Any,
+    element: None,
+};
+
+static ADDRESS_MAP: Schema = Schema::collection(KeyKind::Address, None);
+
+static MINER: Schema = Schema {
+    fields: &[
+        "info",
+        "pre_commit_deposits",
+        "locked_funds",
+        "vesting_funds",
+        "fee_debt",
+        "initial_pledge",
+        "pre_committed_sectors",
+        "pre_committed_sectors_cleanup",
+        "allocated_sectors",
+        "sectors",
+        "proving_period_start",
+        "current_deadline",
+        "deadlines",
+    ],
+    children: &[
+        ("info", &MINER_INFO),
+        ("pre_committed_sectors", &PRE_COMMITTED_SECTORS),
+        ("sectors", &SECTORS),
+    ],
+    key: KeyKind::Any,
+    element: None,
+};
+
+static MINER_INFO: Schema = Schema {
+    fields: &[
+        "owner",
+        "worker",
+        "control_addresses",
+        "pending_worker_key",
+        "peer_id",
+        "multi_address",
+        "window_post_proof_type",
+        "sector_size",
+        "window_post_partition_sectors",
+        "consensus_fault_elapsed",
+        "pending_owner_address",
+    ],
+    children: &[],
+    key: KeyKind::Any,
+    element: None,
+};
+
+static PRE_COMMITTED_SECTORS: Schema = Schema::collection(KeyKind::Uint, None);
+
+static SECTORS: Schema = Schema::collection(KeyKind::Uint, Some(&SECTOR_ON_CHAIN_INFO));
+
+static SECTOR_ON_CHAIN_INFO: Schema = Schema {
+    fields: &[
+        "sector_number",
+        "seal_proof",
+        "sealed_cid",
+        "deal_ids",
+        "activation",
+        "expiration",
+        "deal_weight",
+        "verified_deal_weight",
+        "initial_pledge",
+        "expected_day_reward",
+        "expected_storage_pledge",
+    ],
+    children: &[],
+    key: KeyKind::Any,
+    element: None,
+};
+
+static POWER: Schema = Schema {
+    fields: &[
+        "total_raw_byte_power",
+        "total_bytes_committed",
+        "total_quality_adj_power",
+        "total_qa_bytes_committed",
+        "total_pledge_collateral",
+        "this_epoch_raw_byte_power",
+        "this_epoch_quality_adj_power",
+        "this_epoch_pledge_collateral",
+        "this_epoch_qa_power_smoothed",
+        "miner_count",
+        "miner_above_min_power_count",
+        "cron_event_queue",
+        "first_cron_epoch",
+        "claims",
+    ],
+    children: &[("claims", &CLAIMS)],
+    key: KeyKind::Any,
+    element: None,
+};
+
+static CLAIMS: Schema = Schema::collection(KeyKind::Address, Some(&CLAIM));
+
+static CLAIM: Schema = Schema {
+    fields: &[
+        "window_post_proof_type",
+        "raw_byte_power",
+        "quality_adj_power",
+    ],
+    children: &[],
+    key: KeyKind::Any,
+    element: None,
+};
+
+static MARKET: Schema = Schema {
+    fields: &[
+        "proposals",
+        "states",
+        "pending_proposals",
+        "escrow_table",
+        "locked_table",
+        "next_id",
+        "deal_ops_by_epoch",
+        "last_cron",
+        "total_client_locked_collateral",
+        "total_provider_locked_collateral",
+        "total_client_storage_fee",
+    ],
+    children: &[
+        ("proposals", &DEAL_IDS),
+        ("states", &DEAL_STATES),
+        ("escrow_table", &BALANCE_TABLE),
+        ("locked_table", &BALANCE_TABLE),
+    ],
+    key: KeyKind::Any,
+    element: None,
+};
+
+static DEAL_IDS: Schema = Schema::collection(KeyKind::Uint, None);
+
+static DEAL_STATES: Schema = Schema::collection(KeyKind::Uint, Some(&DEAL_STATE));
+
+static DEAL_STATE: Schema = Schema {
+    fields: &["sector_start_epoch", "last_updated_epoch", "slash_epoch"],
+    children: &[],
+    key: KeyKind::Any,
+    element: None,
+};
+
+static BALANCE_TABLE: Schema = Schema::collection(KeyKind::Address, None);
+
+static MULTISIG: Schema = Schema {
+    fields: &[
+        "signers",
+        "num_approvals_threshold",
+        "next_tx_id",
+        "initial_balance",
+        "start_epoch",
+        "unlock_duration",
+        "pending_txs",
+    ],
+    children: &[("pending_txs", &PENDING_TXS)],
+    key: KeyKind::Any,
+    element: None,
+};
+
+static PENDING_TXS: Schema = Schema::collection(KeyKind::Any, None);
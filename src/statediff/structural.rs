@@ -0,0 +1,687 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Structural diffing of actor states.
+//!
+//! Rather than comparing the textual representation of two states, the trees
+//! are walked side by side and only the leaves that actually differ are
+//! reported. Links that point to [HAMT](fil_actors_shared::fvm_ipld_hamt) or
+//! [AMT](fil_actors_shared::fvm_ipld_amt) roots are recognized by their shape
+//! and descended in lock-step, so unchanged sub-trees are skipped and changes
+//! are reported per key (e.g. `sectors[123].expiration`) instead of as two
+//! opaque root CIDs.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::ipld::json::IpldJsonRef;
+use crate::shim::{address::Address, state_tree::ActorState};
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{CborStore, DAG_CBOR};
+use itertools::Itertools as _;
+use libipld_core::ipld::Ipld;
+
+use super::schema::{self, Schema};
+
+/// Bit-width of the version 0 AMT used by actors v0 to v2.
+const AMT_V0_BIT_WIDTH: u32 = 3;
+
+/// One step in the path from the actor to a changed value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// A named (or, if the layout is unknown, numbered) field of a structure.
+    Field(String),
+    /// A key in a HAMT, AMT or IPLD map.
+    Key(String),
+}
+
+/// Path from the actor to a changed value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangePath(pub Vec<Segment>);
+
+impl ChangePath {
+    fn field(&self, name: impl Into<String>) -> Self {
+        let mut path = self.clone();
+        path.0.push(Segment::Field(name.into()));
+        path
+    }
+
+    fn key(&self, key: impl Into<String>) -> Self {
+        let mut path = self.clone();
+        path.0.push(Segment::Key(key.into()));
+        path
+    }
+
+    /// Renders the path as an [RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901)
+    /// JSON pointer.
+    pub fn to_json_pointer(&self) -> String {
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                Segment::Field(s) | Segment::Key(s) => {
+                    format!("/{}", s.replace('~', "~0").replace('/', "~1"))
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for ChangePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                Segment::Field(name) if i == 0 => write!(f, "{name}")?,
+                Segment::Field(name) => write!(f, ".{name}")?,
+                Segment::Key(key) => write!(f, "[{key}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single difference between two actor states.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added {
+        path: ChangePath,
+        value: Ipld,
+    },
+    Removed {
+        path: ChangePath,
+        value: Ipld,
+    },
+    Modified {
+        path: ChangePath,
+        before: Ipld,
+        after: Ipld,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "{path} added: {}", render(value)),
+            Change::Removed { path, value } => write!(f, "{path} removed: {}", render(value)),
+            Change::Modified {
+                path,
+                before,
+                after,
+            } => write!(f, "{path} changed: {} -> {}", render(before), render(after)),
+        }
+    }
+}
+
+/// Compact, single-line rendering of an IPLD value.
+fn render(ipld: &Ipld) -> String {
+    match ipld {
+        Ipld::Integer(i) => i.to_string(),
+        Ipld::String(s) => format!("{s:?}"),
+        Ipld::Link(cid) => cid.to_string(),
+        other => serde_json::to_string(&IpldJsonRef(other)).unwrap_or_else(|_| "?".into()),
+    }
+}
+
+/// Structurally compares two versions of the same actor and returns the list
+/// of changed leaves. `kind` is the builtin actor name used to pick the field
+/// names of its state (see [`super::actor_kind`]).
+pub fn diff_actor_states(
+    bs: &impl Blockstore,
+    kind: Option<&str>,
+    before: &ActorState,
+    after: &ActorState,
+    depth: Option<u64>,
+) -> anyhow::Result<Vec<Change>> {
+    let mut changes = vec![];
+    let root = ChangePath::default();
+    if before.code != after.code {
+        changes.push(Change::Modified {
+            path: root.field("code"),
+            before: Ipld::Link(before.code),
+            after: Ipld::Link(after.code),
+        });
+    }
+    if before.sequence != after.sequence {
+        changes.push(Change::Modified {
+            path: root.field("nonce"),
+            before: Ipld::Integer(before.sequence.into()),
+            after: Ipld::Integer(after.sequence.into()),
+        });
+    }
+    if before.balance != after.balance {
+        changes.push(Change::Modified {
+            path: root.field("balance"),
+            before: Ipld::String(before.balance.to_string()),
+            after: Ipld::String(after.balance.to_string()),
+        });
+    }
+    if before.state != after.state {
+        let mut differ = Differ {
+            bs,
+            changes: vec![],
+        };
+        let schema = kind.and_then(schema::actor_state);
+        differ.diff(
+            &root.field("state"),
+            &Ipld::Link(before.state),
+            &Ipld::Link(after.state),
+            schema,
+            depth,
+        )?;
+        changes.extend(differ.changes);
+    }
+    Ok(changes)
+}
+
+struct Differ<'a, BS> {
+    bs: &'a BS,
+    changes: Vec<Change>,
+}
+
+impl<'a, BS: Blockstore> Differ<'a, BS> {
+    fn diff(
+        &mut self,
+        path: &ChangePath,
+        before: &Ipld,
+        after: &Ipld,
+        schema: Option<&'static Schema>,
+        mut depth: Option<u64>,
+    ) -> anyhow::Result<()> {
+        if before == after {
+            return Ok(());
+        }
+        if let Some(dep) = depth.as_mut() {
+            if *dep == 0 {
+                return self.modified(path, before, after);
+            }
+            *dep -= 1;
+        }
+        match (before, after) {
+            (Ipld::Link(a), Ipld::Link(b)) => {
+                let (Some(a_node), Some(b_node)) = (self.load(a)?, self.load(b)?) else {
+                    return self.modified(path, before, after);
+                };
+                if let (Some(a_hamt), Some(b_hamt)) =
+                    (HamtNode::parse(&a_node), HamtNode::parse(&b_node))
+                {
+                    let mut entries = BTreeMap::new();
+                    self.diff_hamt(&a_hamt, &b_hamt, &mut entries)?;
+                    let key_kind = schema.map(|s| s.key).unwrap_or_default();
+                    for (key, (a, b)) in entries {
+                        self.entry(&path.key(key_kind.render(&key)), a, b, schema, depth)?;
+                    }
+                    Ok(())
+                } else if let (Some(a_amt), Some(b_amt)) =
+                    (AmtRoot::parse(&a_node), AmtRoot::parse(&b_node))
+                {
+                    let mut entries = BTreeMap::new();
+                    self.diff_amt(&a_amt, &b_amt, &mut entries)?;
+                    for (index, (a, b)) in entries {
+                        self.entry(&path.key(index.to_string()), a, b, schema, depth)?;
+                    }
+                    Ok(())
+                } else {
+                    // A plain link to a sub-structure: keep the schema, since
+                    // state fields such as the miner's `info` are stored
+                    // out-of-line.
+                    self.diff(path, &a_node, &b_node, schema, depth)
+                }
+            }
+            (Ipld::List(a), Ipld::List(b)) if a.len() == b.len() => {
+                for (i, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                    let (name, child) = schema.map(|s| s.field(i)).unwrap_or((None, None));
+                    let path = match name {
+                        Some(name) => path.field(name),
+                        None => path.field(i.to_string()),
+                    };
+                    self.diff(&path, a, b, child, depth)?;
+                }
+                Ok(())
+            }
+            (Ipld::Map(a), Ipld::Map(b)) => {
+                for (key, a_value) in a {
+                    match b.get(key) {
+                        Some(b_value) => {
+                            self.diff(&path.key(key), a_value, b_value, None, depth)?
+                        }
+                        None => self.changes.push(Change::Removed {
+                            path: path.key(key),
+                            value: a_value.clone(),
+                        }),
+                    }
+                }
+                for (key, b_value) in b {
+                    if !a.contains_key(key) {
+                        self.changes.push(Change::Added {
+                            path: path.key(key),
+                            value: b_value.clone(),
+                        });
+                    }
+                }
+                Ok(())
+            }
+            _ => self.modified(path, before, after),
+        }
+    }
+
+    /// Reports a collection entry, descending into it when it exists on both
+    /// sides. Entries use the element schema of the collection.
+    fn entry(
+        &mut self,
+        path: &ChangePath,
+        before: Option<Ipld>,
+        after: Option<Ipld>,
+        schema: Option<&'static Schema>,
+        depth: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let element = schema.and_then(|s| s.element);
+        match (before, after) {
+            (Some(before), Some(after)) => self.diff(path, &before, &after, element, depth),
+            (Some(value), None) => {
+                self.changes.push(Change::Removed {
+                    path: path.clone(),
+                    value,
+                });
+                Ok(())
+            }
+            (None, Some(value)) => {
+                self.changes.push(Change::Added {
+                    path: path.clone(),
+                    value,
+                });
+                Ok(())
+            }
+            (None, None) => Ok(()),
+        }
+    }
+
+    fn modified(&mut self, path: &ChangePath, before: &Ipld, after: &Ipld) -> anyhow::Result<()> {
+        self.changes.push(Change::Modified {
+            path: path.clone(),
+            before: before.clone(),
+            after: after.clone(),
+        });
+        Ok(())
+    }
+
+    fn load(&self, cid: &Cid) -> anyhow::Result<Option<Ipld>> {
+        if cid.codec() != DAG_CBOR {
+            return Ok(None);
+        }
+        self.bs.get_cbor(cid)
+    }
+
+    fn load_with<T>(&self, cid: &Cid, parse: impl FnOnce(&Ipld) -> Option<T>) -> anyhow::Result<T> {
+        let node = self
+            .load(cid)?
+            .ok_or_else(|| anyhow::anyhow!("missing collection node {cid}"))?;
+        parse(&node).ok_or_else(|| anyhow::anyhow!("malformed collection node {cid}"))
+    }
+
+    /// Walks two HAMT nodes slot by slot, skipping shared sub-trees, and
+    /// collects `key -> (before, after)` for every key that differs.
+    fn diff_hamt(
+        &self,
+        a: &HamtNode,
+        b: &HamtNode,
+        out: &mut BTreeMap<Vec<u8>, (Option<Ipld>, Option<Ipld>)>,
+    ) -> anyhow::Result<()> {
+        let slots = a.pointers.keys().chain(b.pointers.keys()).copied();
+        for slot in slots.unique() {
+            match (a.pointers.get(&slot), b.pointers.get(&slot)) {
+                (Some(HamtPointer::Link(a)), Some(HamtPointer::Link(b))) => {
+                    if a != b {
+                        let a = self.load_with(a, HamtNode::parse)?;
+                        let b = self.load_with(b, HamtNode::parse)?;
+                        self.diff_hamt(&a, &b, out)?;
+                    }
+                }
+                (a, b) => {
+                    for (key, value) in self.hamt_entries(a)? {
+                        out.entry(key).or_default().0 = Some(value);
+                    }
+                    for (key, value) in self.hamt_entries(b)? {
+                        out.entry(key).or_default().1 = Some(value);
+                    }
+                }
+            }
+        }
+        out.retain(|_, (a, b)| a != b);
+        Ok(())
+    }
+
+    fn hamt_entries(&self, pointer: Option<&HamtPointer>) -> anyhow::Result<Vec<(Vec<u8>, Ipld)>> {
+        match pointer {
+            None => Ok(vec![]),
+            Some(HamtPointer::Bucket(entries)) => Ok(entries.clone()),
+            Some(HamtPointer::Link(cid)) => {
+                let node = self.load_with(cid, HamtNode::parse)?;
+                let mut entries = vec![];
+                for pointer in node.pointers.values() {
+                    entries.extend(self.hamt_entries(Some(pointer))?);
+                }
+                Ok(entries)
+            }
+        }
+    }
+
+    /// Walks two AMTs in lock-step. AMTs of different shapes (bit-width or
+    /// height) are flattened and compared index by index.
+    fn diff_amt(
+        &self,
+        a: &AmtRoot,
+        b: &AmtRoot,
+        out: &mut BTreeMap<u64, (Option<Ipld>, Option<Ipld>)>,
+    ) -> anyhow::Result<()> {
+        if a.bit_width == b.bit_width && a.height == b.height {
+            self.diff_amt_nodes(a.bit_width, a.height, 0, Some(&a.node), Some(&b.node), out)?;
+        } else {
+            for (index, value) in self.amt_entries(a.bit_width, a.height, 0, &a.node)? {
+                out.entry(index).or_default().0 = Some(value);
+            }
+            for (index, value) in self.amt_entries(b.bit_width, b.height, 0, &b.node)? {
+                out.entry(index).or_default().1 = Some(value);
+            }
+        }
+        out.retain(|_, (a, b)| a != b);
+        Ok(())
+    }
+
+    fn diff_amt_nodes(
+        &self,
+        bit_width: u32,
+        height: u32,
+        offset: u64,
+        a: Option<&AmtNode>,
+        b: Option<&AmtNode>,
+        out: &mut BTreeMap<u64, (Option<Ipld>, Option<Ipld>)>,
+    ) -> anyhow::Result<()> {
+        let width = 1u64 << bit_width;
+        if height == 0 {
+            for slot in 0..width {
+                let a = a.and_then(|n| n.get(slot)).cloned();
+                let b = b.and_then(|n| n.get(slot)).cloned();
+                if a != b {
+                    out.insert(offset + slot, (a, b));
+                }
+            }
+            return Ok(());
+        }
+        let stride = width.pow(height);
+        for slot in 0..width {
+            let a_link = a.and_then(|n| n.get(slot)).and_then(as_link);
+            let b_link = b.and_then(|n| n.get(slot)).and_then(as_link);
+            if a_link == b_link {
+                continue;
+            }
+            let a_child = match a_link {
+                Some(cid) => Some(self.load_with(&cid, AmtNode::parse)?),
+                None => None,
+            };
+            let b_child = match b_link {
+                Some(cid) => Some(self.load_with(&cid, AmtNode::parse)?),
+                None => None,
+            };
+            self.diff_amt_nodes(
+                bit_width,
+                height - 1,
+                offset + slot * stride,
+                a_child.as_ref(),
+                b_child.as_ref(),
+                out,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn amt_entries(
+        &self,
+        bit_width: u32,
+        height: u32,
+        offset: u64,
+        node: &AmtNode,
+    ) -> anyhow::Result<Vec<(u64, Ipld)>> {
+        let mut out = BTreeMap::new();
+        self.diff_amt_nodes(bit_width, height, offset, None, Some(node), &mut out)?;
+        Ok(out
+            .into_iter()
+            .filter_map(|(index, (_, value))| Some((index, value?)))
+            .collect())
+    }
+}
+
+fn as_link(ipld: &Ipld) -> Option<Cid> {
+    match ipld {
+        Ipld::Link(cid) => Some(*cid),
+        _ => None,
+    }
+}
+
+enum HamtPointer {
+    Link(Cid),
+    Bucket(Vec<(Vec<u8>, Ipld)>),
+}
+
+/// A HAMT node, keyed by slot index.
+struct HamtNode {
+    pointers: BTreeMap<u32, HamtPointer>,
+}
+
+impl HamtNode {
+    /// Recognizes `[bitfield, [pointer, ...]]`. The bitfield is a big-endian
+    /// integer with one bit set for each occupied slot.
+    fn parse(ipld: &Ipld) -> Option<Self> {
+        let Ipld::List(fields) = ipld else {
+            return None;
+        };
+        let [Ipld::Bytes(bitfield), Ipld::List(pointers)] = fields.as_slice() else {
+            return None;
+        };
+        let slots = bitfield
+            .iter()
+            .rev()
+            .enumerate()
+            .flat_map(|(byte, bits)| {
+                (0..8)
+                    .filter(move |bit| bits & (1 << bit) != 0)
+                    .map(move |bit| byte as u32 * 8 + bit)
+            })
+            .collect::<Vec<_>>();
+        if slots.len() != pointers.len() {
+            return None;
+        }
+        let pointers = slots
+            .into_iter()
+            .zip(pointers.iter())
+            .map(|(slot, pointer)| Some((slot, HamtPointer::parse(pointer)?)))
+            .collect::<Option<_>>()?;
+        Some(Self { pointers })
+    }
+}
+
+impl HamtPointer {
+    fn parse(ipld: &Ipld) -> Option<Self> {
+        match ipld {
+            Ipld::Link(cid) => Some(Self::Link(*cid)),
+            Ipld::List(entries) => entries
+                .iter()
+                .map(|kv| match kv {
+                    Ipld::List(kv) => match kv.as_slice() {
+                        [Ipld::Bytes(key), value] => Some((key.clone(), value.clone())),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect::<Option<_>>()
+                .map(Self::Bucket),
+            // Version 0 HAMTs (actors v0 to v2) encode pointers as a union map.
+            Ipld::Map(union) if union.len() == 1 => match union.iter().next()? {
+                (key, value) if key == "0" => {
+                    Self::parse(value).filter(|p| matches!(p, Self::Link(_)))
+                }
+                (key, value) if key == "1" => {
+                    Self::parse(value).filter(|p| matches!(p, Self::Bucket(_)))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// An AMT node. `bitmap` has one bit per slot, least significant bit first.
+struct AmtNode {
+    bitmap: Vec<u8>,
+    /// Links for interior nodes, values for leaves.
+    items: Vec<Ipld>,
+}
+
+impl AmtNode {
+    fn parse(ipld: &Ipld) -> Option<Self> {
+        let Ipld::List(fields) = ipld else {
+            return None;
+        };
+        let [Ipld::Bytes(bitmap), Ipld::List(links), Ipld::List(values)] = fields.as_slice() else {
+            return None;
+        };
+        let set = bitmap
+            .iter()
+            .map(|b| b.count_ones() as usize)
+            .sum::<usize>();
+        let items = match (links.is_empty(), values.is_empty()) {
+            (true, _) => values,
+            (false, true) if links.iter().all(|l| matches!(l, Ipld::Link(_))) => links,
+            _ => return None,
+        };
+        (set == items.len()).then(|| Self {
+            bitmap: bitmap.clone(),
+            items: items.clone(),
+        })
+    }
+
+    fn get(&self, slot: u64) -> Option<&Ipld> {
+        let (byte, bit) = ((slot / 8) as usize, slot % 8);
+        if self.bitmap.get(byte)? & (1 << bit) == 0 {
+            return None;
+        }
+        let before = self.bitmap[..byte]
+            .iter()
+            .map(|b| b.count_ones() as usize)
+            .sum::<usize>()
+            + (self.bitmap[byte] & ((1u8 << bit) - 1)).count_ones() as usize;
+        self.items.get(before)
+    }
+}
+
+struct AmtRoot {
+    bit_width: u32,
+    height: u32,
+    node: AmtNode,
+}
+
+impl AmtRoot {
+    /// Recognizes `[bit_width, height, count, node]` (actors v3 onwards) and
+    /// `[height, count, node]` (actors v0 to v2).
+    fn parse(ipld: &Ipld) -> Option<Self> {
+        let Ipld::List(fields) = ipld else {
+            return None;
+        };
+        let (bit_width, height, node) = match fields.as_slice() {
+            [Ipld::Integer(bit_width), Ipld::Integer(height), Ipld::Integer(_), node] => {
+                (u32::try_from(*bit_width).ok()?, height, node)
+            }
+            [Ipld::Integer(height), Ipld::Integer(_), node] => (AMT_V0_BIT_WIDTH, height, node),
+            _ => return None,
+        };
+        if !(1..=18).contains(&bit_width) {
+            return None;
+        }
+        Some(Self {
+            bit_width,
+            height: u32::try_from(*height).ok()?,
+            node: AmtNode::parse(node)?,
+        })
+    }
+}
+
+/// How the raw bytes of HAMT keys are rendered in change paths.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    /// Guess: addresses, then varint integers, then hex.
+    #[default]
+    Any,
+    Address,
+    Uint,
+}
+
+impl KeyKind {
+    pub fn render(self, key: &[u8]) -> String {
+        let address = || Address::from_bytes(key).ok().map(|a| a.to_string());
+        let uint = || match unsigned_varint::decode::u64(key) {
+            Ok((n, [])) => Some(n.to_string()),
+            _ => None,
+        };
+        match self {
+            KeyKind::Any => address().or_else(uint),
+            KeyKind::Address => address(),
+            KeyKind::Uint => uint(),
+        }
+        .unwrap_or_else(|| format!("0x{}", hex::encode(key)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryDB;
+    use crate::shim::econ::TokenAmount;
+    use crate::utils::db::CborStoreExt;
+    use fil_actors_shared::fvm_ipld_amt::Amt;
+    use fil_actors_shared::fvm_ipld_hamt::{BytesKey, Hamt};
+
+    fn actor(state: Cid) -> ActorState {
+        ActorState::new(Cid::default(), state, TokenAmount::from_atto(0), 0, None)
+    }
+
+    #[test]
+    fn amt_changes_are_reported_per_index() {
+        let db = MemoryDB::default();
+        let mut amt = Amt::<u64, _>::new(&db);
+        for i in 0..100 {
+            amt.set(i, i).unwrap();
+        }
+        let before = amt.flush().unwrap();
+        amt.set(42, 1000).unwrap();
+        amt.delete(7).unwrap();
+        let after = amt.flush().unwrap();
+
+        let before = actor(db.put_cbor_default(&(before,)).unwrap());
+        let after = actor(db.put_cbor_default(&(after,)).unwrap());
+        let changes = diff_actor_states(&db, None, &before, &after, None).unwrap();
+        let rendered = changes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            rendered,
+            vec!["state.0[7] removed: 7", "state.0[42] changed: 42 -> 1000"]
+        );
+    }
+
+    #[test]
+    fn hamt_changes_are_reported_per_key() {
+        let db = MemoryDB::default();
+        let mut hamt = Hamt::<_, u64>::new_with_bit_width(&db, fvm_shared4::HAMT_BIT_WIDTH);
+        for i in 0..100u64 {
+            hamt.set(BytesKey(Address::new_id(i).to_bytes()), i)
+                .unwrap();
+        }
+        let before = hamt.flush().unwrap();
+        hamt.set(BytesKey(Address::new_id(1000).to_bytes()), 1)
+            .unwrap();
+        let after = hamt.flush().unwrap();
+
+        let before = actor(db.put_cbor_default(&(before,)).unwrap());
+        let after = actor(db.put_cbor_default(&(after,)).unwrap());
+        let changes = diff_actor_states(&db, None, &before, &after, None).unwrap();
+        let rendered = changes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(rendered, vec!["state.0[f01000] added: 1"]);
+    }
+}
@@ -31,6 +31,8 @@ where
                 Subcommand::DB(cmd) => cmd.run().await,
                 Subcommand::Car(cmd) => cmd.run().await,
                 Subcommand::Api(cmd) => cmd.run().await,
+                Subcommand::Shed(cmd) => cmd.run().await,
+                Subcommand::Genesis(cmd) => cmd.run().await,
             }
         })
 }
@@ -13,8 +13,10 @@ use crate::blocks::Tipset;
 use crate::blocks::TipsetKeys;
 use crate::db::car::ManyCar;
 use crate::lotus_json::HasLotusJson;
+use crate::rpc_api::openrpc;
 use crate::rpc_client::{ApiInfo, JsonRpcError, RpcRequest};
 use crate::shim::address::Address;
+use crate::utils::version::FOREST_VERSION_STRING;
 
 #[derive(Debug, Subcommand)]
 pub enum ApiCommands {
@@ -36,6 +38,8 @@ pub enum ApiCommands {
         #[arg(long)]
         fail_fast: bool,
     },
+    /// Print the OpenRPC document describing the RPC methods of Forest
+    Openrpc,
 }
 
 impl ApiCommands {
@@ -48,6 +52,10 @@ impl ApiCommands {
                 filter,
                 fail_fast,
             } => compare_apis(forest, lotus, snapshot_files, filter, fail_fast).await?,
+            Self::Openrpc => {
+                let document = openrpc::document(FOREST_VERSION_STRING.as_str());
+                println!("{}", serde_json::to_string_pretty(&document)?);
+            }
         }
         Ok(())
     }
@@ -246,6 +254,8 @@ fn state_tests(shared_tipset: &Tipset) -> Vec<RpcTest> {
     let shared_block = shared_tipset.min_ticket_block();
     vec![
         RpcTest::identity(ApiInfo::state_network_name_req()),
+        // Upgrade names differ from Lotus, so only the call itself is compared.
+        RpcTest::basic(ApiInfo::state_get_network_params_req()),
         RpcTest::identity(ApiInfo::state_get_actor_req(
             Address::SYSTEM_ACTOR,
             shared_tipset.key().clone(),
@@ -256,6 +266,12 @@ fn state_tests(shared_tipset: &Tipset) -> Vec<RpcTest> {
             shared_tipset.epoch(),
             "dead beef".as_bytes().to_vec(),
         )),
+        RpcTest::identity(ApiInfo::state_get_randomness_from_tickets_req(
+            shared_tipset.key().clone(),
+            DomainSeparationTag::ElectionProofProduction,
+            shared_tipset.epoch(),
+            "dead beef".as_bytes().to_vec(),
+        )),
         RpcTest::identity(ApiInfo::state_read_state_req(
             Address::SYSTEM_ACTOR,
             shared_tipset.key().clone(),
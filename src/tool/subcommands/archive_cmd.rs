@@ -43,6 +43,7 @@ use crate::shim::clock::{ChainEpoch, EPOCHS_IN_DAY, EPOCH_DURATION_SECONDS};
 use crate::shim::fvm_shared_latest::address::Network;
 use crate::shim::machine::MultiEngine;
 use crate::state_manager::{apply_block_messages, NO_CALLBACK};
+use crate::statediff::StateDiffFormat;
 use anyhow::{bail, Context as _};
 use chrono::NaiveDateTime;
 use clap::Subcommand;
@@ -52,7 +53,7 @@ use fvm_ipld_blockstore::Blockstore;
 use indicatif::ProgressIterator;
 use itertools::Itertools;
 use sha2::Sha256;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::io::{AsyncWriteExt, BufWriter};
 use tracing::info;
@@ -91,6 +92,23 @@ pub enum ArchiveCommands {
         #[arg(long, default_value_t = false)]
         force: bool,
     },
+    /// Write the state tree at a given epoch to `<output_path>`, without any
+    /// messages or older block headers
+    ExportState {
+        /// Snapshot input paths. Supports `.car`, `.car.zst`, and `.forest.car.zst`.
+        #[arg(required = true)]
+        snapshot_files: Vec<PathBuf>,
+        /// Output filename or directory. Defaults to
+        /// `./forest_state_{chain}_height_{epoch}.forest.car.zst`.
+        #[arg(short, long, default_value = ".", verbatim_doc_comment)]
+        output_path: PathBuf,
+        /// Epoch of the exported state. Defaults to the heaviest tipset.
+        #[arg(short, long)]
+        epoch: Option<ChainEpoch>,
+        /// Overwrite output file without prompting.
+        #[arg(long, default_value_t = false)]
+        force: bool,
+    },
     /// Print block headers at 30 day interval for a snapshot file
     Checkpoints {
         /// Path to snapshot file.
@@ -124,6 +142,9 @@ pub enum ArchiveCommands {
         // shown as different branch IDs.
         #[arg(long)]
         depth: Option<u64>,
+        /// Output format of the diff.
+        #[arg(long, value_enum, default_value_t = StateDiffFormat::default())]
+        format: StateDiffFormat,
     },
 }
 
@@ -160,6 +181,12 @@ impl ArchiveCommands {
                 )
                 .await
             }
+            Self::ExportState {
+                snapshot_files,
+                output_path,
+                epoch,
+                force,
+            } => export_state(snapshot_files, output_path, epoch, force).await,
             Self::Checkpoints {
                 snapshot_files: snapshot,
             } => print_checkpoints(snapshot),
@@ -172,7 +199,8 @@ impl ArchiveCommands {
                 snapshot_files,
                 epoch,
                 depth,
-            } => show_tipset_diff(snapshot_files, epoch, depth).await,
+                format,
+            } => show_tipset_diff(snapshot_files, epoch, depth, format).await,
         }
     }
 }
@@ -341,6 +369,23 @@ fn build_output_path(
     }
 }
 
+// Asks for confirmation before overwriting an existing file, unless `force`
+// is set.
+fn may_overwrite(output_path: &Path, force: bool) -> bool {
+    if force || !output_path.exists() {
+        return true;
+    }
+    Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "{} will be overwritten. Continue?",
+            output_path.to_string_lossy()
+        ))
+        .default(false)
+        .interact()
+        // e.g not a tty (or some other error), so haven't got permission.
+        .unwrap_or(false)
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn do_export(
     store: impl Blockstore + Send + Sync + 'static,
@@ -396,19 +441,8 @@ async fn do_export(
     let output_path =
         build_output_path(network.to_string(), genesis.timestamp(), epoch, output_path);
 
-    if !force && output_path.exists() {
-        let have_permission = Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt(format!(
-                "{} will be overwritten. Continue?",
-                output_path.to_string_lossy()
-            ))
-            .default(false)
-            .interact()
-            // e.g not a tty (or some other error), so haven't got permission.
-            .unwrap_or(false);
-        if !have_permission {
-            return Ok(());
-        }
+    if !may_overwrite(&output_path, force) {
+        return Ok(());
     }
 
     let writer = tokio::fs::File::create(&output_path)
@@ -437,6 +471,58 @@ async fn do_export(
     Ok(())
 }
 
+async fn export_state(
+    snapshot_files: Vec<PathBuf>,
+    output_path: PathBuf,
+    epoch: Option<ChainEpoch>,
+    force: bool,
+) -> anyhow::Result<()> {
+    let store = Arc::new(ManyCar::try_from(snapshot_files)?);
+    let heaviest = store.heaviest_tipset()?;
+    let genesis = heaviest.genesis(&store)?;
+    let network = NetworkChain::from_genesis_or_devnet_placeholder(genesis.cid());
+
+    let ts = ChainIndex::new(&store)
+        .tipset_by_height(
+            epoch.unwrap_or(heaviest.epoch()),
+            Arc::new(heaviest),
+            ResolveNullTipset::TakeOlder,
+        )
+        .context("unable to get a tipset at given height")?;
+
+    let output_path = match output_path.is_dir() {
+        true => output_path.join(format!(
+            "forest_state_{network}_height_{}.forest.car.zst",
+            ts.epoch()
+        )),
+        false => output_path,
+    };
+    if !may_overwrite(&output_path, force) {
+        return Ok(());
+    }
+
+    let writer = tokio::fs::File::create(&output_path)
+        .await
+        .with_context(|| format!("unable to create '{}'", output_path.display()))?;
+    info!(
+        "exporting state at epoch {} (state root {}) to {}",
+        ts.epoch(),
+        ts.parent_state(),
+        output_path.display()
+    );
+
+    let pb = indicatif::ProgressBar::new_spinner().with_style(
+        indicatif::ProgressStyle::with_template(
+            "{spinner} exported {total_bytes} with {binary_bytes_per_sec} in {elapsed}",
+        )
+        .expect("indicatif template must be valid"),
+    );
+    pb.enable_steady_tick(std::time::Duration::from_secs_f32(0.1));
+    let writer = pb.wrap_async_write(writer);
+
+    crate::chain::export_state(store, &ts, writer).await
+}
+
 // TODO(lemmih): https://github.com/ChainSafe/forest/issues/3347
 //               Testing with diff snapshots can be significantly improved
 /// Merge a set of snapshots (diff snapshots or lite snapshots). The output
@@ -492,11 +578,12 @@ async fn merge_snapshots(
 /// Compute the tree of actor states for a given epoch and compare it to the
 /// expected result (as encoded in the blockchain). Differences are printed
 /// using the diff format (red for the blockchain state, green for the computed
-/// state).
+/// state), or in one of the machine-readable [`StateDiffFormat`]s.
 async fn show_tipset_diff(
     snapshot_files: Vec<PathBuf>,
     epoch: ChainEpoch,
     depth: Option<u64>,
+    format: StateDiffFormat,
 ) -> anyhow::Result<()> {
     use colored::*;
 
@@ -545,7 +632,21 @@ async fn show_tipset_diff(
         VMTrace::NotTraced,
     )?;
 
-    if child_tipset.parent_state() != &state_root {
+    if format != StateDiffFormat::Text {
+        // Keep stdout parseable: no headers, and an empty diff when the
+        // states match.
+        if child_tipset.parent_state() != &state_root {
+            crate::statediff::print_state_diff(
+                &store,
+                &state_root,
+                child_tipset.parent_state(),
+                depth,
+                format,
+            )?;
+        } else if format == StateDiffFormat::JsonPatch {
+            println!("[]");
+        }
+    } else if child_tipset.parent_state() != &state_root {
         println!(
             "{}",
             format!("- Expected state hash: {}", child_tipset.parent_state()).red()
@@ -560,6 +661,7 @@ async fn show_tipset_diff(
             &state_root,
             child_tipset.parent_state(),
             depth,
+            format,
         )?;
     } else {
         println!("Computed state matches expected state.");
@@ -610,6 +712,23 @@ mod tests {
         CarStream::new(BufReader::new(file)).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn export_state_at_genesis() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("genesis.car");
+        std::fs::write(&input, calibnet::DEFAULT_GENESIS).unwrap();
+        let output = dir.path().join("state.forest.car.zst");
+        export_state(vec![input], output.clone(), Some(0), false)
+            .await
+            .unwrap();
+
+        let genesis = AnyCar::try_from(calibnet::DEFAULT_GENESIS).unwrap();
+        let exported = AnyCar::try_from(output.as_path()).unwrap();
+        let ts = exported.heaviest_tipset().unwrap();
+        assert_eq!(ts, genesis.heaviest_tipset().unwrap());
+        assert!(exported.has(ts.parent_state()).unwrap());
+    }
+
     #[test]
     fn archive_info_calibnet() {
         let info = ArchiveInfo::from_store_with(
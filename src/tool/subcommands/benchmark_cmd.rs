@@ -1,22 +1,35 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use crate::blocks::Tipset;
 use crate::chain::{
     index::{ChainIndex, ResolveNullTipset},
     ChainEpochDelta,
 };
 use crate::db::car::forest::DEFAULT_FOREST_CAR_FRAME_SIZE;
 use crate::db::car::ManyCar;
+use crate::interpreter::{MessageCallbackCtx, VMTrace};
 use crate::ipld::{stream_chain, stream_graph, unordered_stream_graph};
+use crate::message::Message as _;
+use crate::networks::{ChainConfig, NetworkChain};
+use crate::shim::address::{Address, CurrentNetwork};
 use crate::shim::clock::ChainEpoch;
+use crate::shim::fvm_shared_latest::address::Network;
+use crate::shim::machine::{BuiltinActorManifest, MultiEngine};
+use crate::shim::state_tree::StateTree;
+use crate::shim::trace::ExecutionEvent;
+use crate::state_manager::apply_block_messages;
 use crate::utils::db::car_stream::{CarBlock, CarStream};
 use crate::utils::encoding::extract_cids;
 use crate::utils::stream::par_buffer;
+use ahash::HashMap;
 use anyhow::Context as _;
 use cid::Cid;
 use clap::Subcommand;
+use fil_actor_system_state::v10::State as SystemState;
 use futures::{StreamExt, TryStreamExt};
-use fvm_ipld_encoding::DAG_CBOR;
+use fvm_ipld_encoding::{CborStore as _, DAG_CBOR};
+use fvm_shared4::MethodNum;
 use indicatif::{ProgressBar, ProgressStyle};
 use itertools::Itertools;
 use std::ops::Deref;
@@ -78,6 +91,21 @@ pub enum BenchmarkCommands {
         #[arg(short, long, default_value_t = 2000)]
         depth: ChainEpochDelta,
     },
+    /// Re-execute a range of tipsets and aggregate gas usage by actor and method
+    GasProfile {
+        /// Snapshot input files (`.car.`, `.car.zst`, `.forest.car.zst`)
+        #[arg(required = true)]
+        snapshot_files: Vec<PathBuf>,
+        /// Last epoch of the profiled range. Defaults to the heaviest tipset.
+        #[arg(short, long)]
+        epoch: Option<ChainEpoch>,
+        /// Number of tipsets to execute, going back from `--epoch`
+        #[arg(short, long, default_value_t = 10)]
+        tipsets: usize,
+        /// Number of entries to print, ordered by gas used
+        #[arg(long, default_value_t = 30)]
+        top: usize,
+    },
 }
 
 impl BenchmarkCommands {
@@ -111,6 +139,12 @@ impl BenchmarkCommands {
                 benchmark_exporting(snapshot_files, compression_level, frame_size, epoch, depth)
                     .await
             }
+            Self::GasProfile {
+                snapshot_files,
+                epoch,
+                tipsets,
+                top,
+            } => benchmark_gas_profile(snapshot_files, epoch, tipsets, top),
         }
     }
 }
@@ -256,6 +290,171 @@ async fn benchmark_exporting(
     Ok(())
 }
 
+/// Gas used by the calls to one method of one actor.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct GasUsage {
+    calls: u64,
+    milligas: u64,
+}
+
+impl GasUsage {
+    fn add(&mut self, other: &GasUsage) {
+        self.calls += other.calls;
+        self.milligas += other.milligas;
+    }
+}
+
+// Walks the execution trace of a message and attributes every gas charge to
+// the innermost call it happened in. Charges outside of any call (like the
+// message inclusion cost) are attributed to the message itself.
+fn gas_by_call(
+    message: (Address, MethodNum),
+    trace: Vec<ExecutionEvent>,
+) -> HashMap<(Address, MethodNum), GasUsage> {
+    let mut usage: HashMap<(Address, MethodNum), GasUsage> = HashMap::default();
+    let mut stack = vec![];
+    for event in trace {
+        match event {
+            ExecutionEvent::GasCharge(charge) => {
+                let callee = stack.last().copied().unwrap_or(message);
+                usage.entry(callee).or_default().milligas += charge.total().as_milligas();
+            }
+            ExecutionEvent::Call(call) => {
+                let callee = (call.to, call.method_num);
+                usage.entry(callee).or_default().calls += 1;
+                stack.push(callee);
+            }
+            ExecutionEvent::CallReturn(_)
+            | ExecutionEvent::CallAbort(_)
+            | ExecutionEvent::CallError(_) => {
+                stack.pop();
+            }
+            ExecutionEvent::Log(_) | ExecutionEvent::Unknown(_) => {}
+        }
+    }
+    usage
+}
+
+// Re-execute the messages of a range of tipsets with tracing enabled, and
+// report which actor methods consume the most gas.
+fn benchmark_gas_profile(
+    input: Vec<PathBuf>,
+    epoch: Option<ChainEpoch>,
+    tipsets: usize,
+    top: usize,
+) -> anyhow::Result<()> {
+    let store = Arc::new(open_store(input)?);
+    let heaviest = store.heaviest_tipset()?;
+    let genesis = heaviest.genesis(&store)?;
+    let network = NetworkChain::from_genesis_or_devnet_placeholder(genesis.cid());
+    let chain_config = Arc::new(ChainConfig::from_chain(&network));
+    if chain_config.is_testnet() {
+        CurrentNetwork::set_global(Network::Testnet);
+    }
+    let timestamp = genesis.timestamp();
+    let beacon = Arc::new(chain_config.get_beacon_schedule(timestamp));
+    let chain_index = Arc::new(ChainIndex::new(Arc::clone(&store)));
+    let head = chain_index.tipset_by_height(
+        epoch.unwrap_or(heaviest.epoch()),
+        Arc::new(heaviest),
+        ResolveNullTipset::TakeOlder,
+    )?;
+
+    // The genesis tipset has no parent state to execute on.
+    let range = head
+        .deref()
+        .clone()
+        .chain(&store)
+        .take_while(|ts| ts.epoch() > 0)
+        .take(tipsets)
+        .map(Arc::new)
+        .collect::<Vec<_>>();
+
+    let pb = ProgressBar::new(range.len() as u64).with_style(
+        ProgressStyle::with_template("{bar} {pos}/{len} tipsets, eta {eta}")
+            .expect("indicatif template must be valid"),
+    );
+    let mut profile: HashMap<(Cid, MethodNum), GasUsage> = HashMap::default();
+    for tipset in range.into_iter().rev() {
+        let state = StateTree::new_from_root(Arc::clone(&store), tipset.parent_state())?;
+        let mut traces = vec![];
+        apply_block_messages(
+            timestamp,
+            Arc::clone(&chain_index),
+            Arc::clone(&chain_config),
+            Arc::clone(&beacon),
+            &MultiEngine::default(),
+            Arc::clone(&tipset),
+            Some(|ctx: &MessageCallbackCtx| {
+                let message = ctx.message.message();
+                traces.push((
+                    (message.to(), message.method_num()),
+                    ctx.apply_ret.exec_trace(),
+                ));
+                anyhow::Ok(())
+            }),
+            VMTrace::Traced,
+        )
+        .with_context(|| format!("couldn't execute tipset at epoch {}", tipset.epoch()))?;
+
+        for (message, trace) in traces {
+            for ((address, method), usage) in gas_by_call(message, trace) {
+                // Actors created during the tipset are missing from the parent
+                // state, and are reported under the default CID.
+                let code = state
+                    .get_actor(&address)?
+                    .map(|actor| actor.code)
+                    .unwrap_or_default();
+                profile.entry((code, method)).or_default().add(&usage);
+            }
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    let names = actor_names(&store, &head)?;
+    let total = profile.values().map(|usage| usage.milligas).sum::<u64>();
+    println!(
+        "{:<12} {:>10} {:>10} {:>18} {:>7}",
+        "actor", "method", "calls", "gas", "share"
+    );
+    for ((code, method), usage) in profile
+        .into_iter()
+        .sorted_by(|(_, a), (_, b)| b.milligas.cmp(&a.milligas))
+        .take(top)
+    {
+        let actor = names
+            .get(&code)
+            .cloned()
+            .unwrap_or_else(|| code.to_string());
+        println!(
+            "{:<12} {:>10} {:>10} {:>18} {:>6.2}%",
+            actor,
+            method,
+            usage.calls,
+            usage.milligas / 1000,
+            usage.milligas as f64 * 100.0 / total.max(1) as f64
+        );
+    }
+    Ok(())
+}
+
+// Names of the builtin actors deployed at the state of `tipset`, by code CID.
+fn actor_names(store: &Arc<ManyCar>, tipset: &Tipset) -> anyhow::Result<HashMap<Cid, String>> {
+    let state = StateTree::new_from_root(Arc::clone(store), tipset.parent_state())?;
+    let system = state
+        .get_actor(&Address::SYSTEM_ACTOR)?
+        .context("system actor not found")?;
+    let system_state = store
+        .get_cbor::<SystemState>(&system.state)?
+        .context("system actor state not found")?;
+    let manifest = BuiltinActorManifest::load_v1_actor_list(store, &system_state.builtin_actors)?;
+    Ok(manifest
+        .builtin_actors()
+        .map(|(actor, code)| (code, format!("{actor:?}").to_lowercase()))
+        .collect())
+}
+
 // Sink with attached progress indicator
 fn indicatif_sink(task: &'static str) -> impl AsyncWrite {
     let sink = tokio::io::sink();
@@ -288,3 +487,74 @@ fn open_store(input: Vec<PathBuf>) -> anyhow::Result<ManyCar> {
 
     Ok(store)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shim::trace::{Call, CallReturn};
+    use fvm3::gas::{Gas as GasV3, GasCharge as GasChargeV3};
+    use fvm_ipld_encoding::RawBytes;
+    use itertools::Either;
+
+    fn charge(milligas: u64) -> ExecutionEvent {
+        ExecutionEvent::GasCharge(
+            GasChargeV3::new(
+                "test",
+                GasV3::from_milligas(milligas),
+                GasV3::from_milligas(0),
+            )
+            .into(),
+        )
+    }
+
+    fn call(to: u64, method_num: MethodNum) -> ExecutionEvent {
+        ExecutionEvent::Call(Call {
+            from: 100,
+            to: Address::new_id(to),
+            method_num,
+            params: Either::Left(RawBytes::default()),
+            value: Default::default(),
+            gas_limit: None,
+            read_only: None,
+        })
+    }
+
+    fn ret() -> ExecutionEvent {
+        ExecutionEvent::CallReturn(CallReturn {
+            exit_code: None,
+            data: Either::Left(RawBytes::default()),
+        })
+    }
+
+    #[test]
+    fn gas_is_attributed_to_innermost_call() {
+        let message = (Address::new_id(1), 2);
+        let trace = vec![
+            charge(5),
+            call(1, 2),
+            charge(10),
+            call(3, 4),
+            charge(20),
+            ret(),
+            charge(1),
+            call(3, 4),
+            ret(),
+            ret(),
+        ];
+        let usage = gas_by_call(message, trace);
+        assert_eq!(
+            usage[&(Address::new_id(1), 2)],
+            GasUsage {
+                calls: 1,
+                milligas: 16
+            }
+        );
+        assert_eq!(
+            usage[&(Address::new_id(3), 4)],
+            GasUsage {
+                calls: 2,
+                milligas: 20
+            }
+        );
+    }
+}
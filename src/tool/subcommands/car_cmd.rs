@@ -1,28 +1,37 @@
 // Copyright 2019-2023 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::collections::VecDeque;
 use std::path::{Path, PathBuf};
 
+use anyhow::bail;
+use cid::Cid;
 use clap::Subcommand;
 use futures::{StreamExt, TryStreamExt};
 use fvm_ipld_blockstore::Blockstore;
 use indicatif::{ProgressBar, ProgressStyle};
 use itertools::Itertools;
+use tempfile::TempPath;
 use tokio::{
     fs::File,
     io::{AsyncWriteExt, BufReader},
 };
 
-use crate::db::car::ForestCar;
+use crate::cid_collections::CidHashSet;
+use crate::db::car::{AnyCar, ForestCar, PlainCar};
 use crate::utils::db::{
-    car_stream::CarStream,
+    car_stream::{CarBlock, CarStream},
     car_util::{dedup_block_stream, merge_car_streams},
 };
+use crate::utils::encoding::extract_cids;
+use crate::utils::io::EitherMmapOrRandomAccessFile;
 
 #[derive(Debug, Subcommand)]
 pub enum CarCommands {
-    /// Concatenate two or more CAR files into a single archive
-    Concat {
+    /// Merge two or more CAR files into a single archive, writing each block
+    /// once, with the roots of all the inputs
+    #[command(alias = "concat")]
+    Merge {
         /// A list of CAR file paths. A CAR file can be a plain CAR, a zstd compressed CAR
         /// or a `.forest.car.zst` file
         car_files: Vec<PathBuf>,
@@ -41,44 +50,181 @@ pub enum CarCommands {
         /// Skip verifying the integrity of the on-disk index
         #[arg(long)]
         ignore_forest_index: bool,
+        /// Report the links to blocks missing from the archive without failing,
+        /// e.g. for the old state of a lite snapshot
+        #[arg(long)]
+        ignore_missing_links: bool,
+        /// Blocks known to be absent from the archive. The links to them are
+        /// not reported as missing.
+        #[arg(long = "allow-missing", value_name = "CID")]
+        allowed_missing: Vec<Cid>,
+    },
+    /// Write the blocks reachable from some CIDs of a CAR archive, e.g. the
+    /// state root of an actor or the header of a tipset, to a new archive
+    Extract {
+        /// CAR archive. Supported extensions: `.car`, `.car.zst`, `.forest.car.zst`
+        car_file: PathBuf,
+        /// CIDs to extract the sub-DAGs of, which become the roots of the output
+        #[arg(long = "root", value_name = "CID", required = true)]
+        roots: Vec<Cid>,
+        /// The output `.forest.car.zst` file path
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Maximum number of links followed from the roots
+        #[arg(long)]
+        depth: Option<u64>,
+        /// Only extract the blocks with these codecs besides the roots:
+        /// `dag-cbor`, `raw` or a multicodec code. Defaults to all. The links
+        /// of the blocks left out are still followed.
+        #[arg(long = "codec", value_parser = parse_codec)]
+        codecs: Vec<u64>,
     },
 }
 
 impl CarCommands {
     pub async fn run(self) -> anyhow::Result<()> {
         match self {
-            Self::Concat { car_files, output } => {
-                let car_streams: Vec<_> = futures::stream::iter(car_files)
-                    .then(tokio::fs::File::open)
-                    .map_ok(tokio::io::BufReader::new)
-                    .and_then(CarStream::new)
-                    .try_collect()
-                    .await?;
-
-                let all_roots = car_streams
-                    .iter()
-                    .flat_map(|it| it.header.roots.iter())
-                    .unique()
-                    .cloned()
-                    .collect::<Vec<_>>();
-
-                let frames = crate::db::car::forest::Encoder::compress_stream_default(
-                    dedup_block_stream(merge_car_streams(car_streams)).map_err(anyhow::Error::from),
+            Self::Merge { car_files, output } => {
+                let stats = merge(&car_files, &output).await?;
+                println!(
+                    "Merged {} archives: {} blocks written, {} duplicates skipped, {} root(s)",
+                    car_files.len(),
+                    stats.written,
+                    stats.read - stats.written,
+                    stats.roots
                 );
-                let mut writer = tokio::io::BufWriter::new(tokio::fs::File::create(&output).await?);
-                crate::db::car::forest::Encoder::write(&mut writer, all_roots, frames).await?;
-                writer.flush().await?;
             }
             Self::Validate {
                 car_file,
                 ignore_block_validity,
                 ignore_forest_index,
-            } => validate(&car_file, ignore_block_validity, ignore_forest_index).await?,
+                ignore_missing_links,
+                allowed_missing,
+            } => {
+                let counts =
+                    validate(&car_file, ignore_block_validity, ignore_forest_index).await?;
+                println!(
+                    "{} blocks, {} duplicate(s)",
+                    counts.blocks.len(),
+                    counts.duplicates
+                );
+                let (roots, roots_len) = (counts.roots.clone(), counts.roots.len());
+                let dag = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+                    let (store, _decompressed) = open_indexed(&car_file)?;
+                    walk_dag(
+                        &store,
+                        &roots,
+                        counts.blocks,
+                        &allowed_missing.into_iter().collect(),
+                    )
+                })
+                .await??;
+                println!(
+                    "{} blocks reachable from {} root(s), {} unreachable",
+                    dag.reachable,
+                    roots_len,
+                    dag.unreachable
+                );
+                if dag.unreachable > 0 {
+                    println!("Unreachable blocks include:");
+                    for cid in &dag.first_unreachable {
+                        println!("  {cid}");
+                    }
+                }
+                if dag.missing > 0 {
+                    println!("{} linked block(s) missing, including:", dag.missing);
+                    for cid in &dag.first_missing {
+                        println!("  {cid}");
+                    }
+                    if !ignore_missing_links {
+                        bail!("{} linked blocks are missing from the archive", dag.missing);
+                    }
+                }
+            }
+            Self::Extract {
+                car_file,
+                roots,
+                output,
+                depth,
+                codecs,
+            } => {
+                let store = AnyCar::try_from(car_file.as_path())?;
+                for root in &roots {
+                    anyhow::ensure!(store.has(root)?, "{root} is not in {}", car_file.display());
+                }
+                let mut sub_dag = SubDag::new(&store, &roots, depth, codecs);
+                let frames = crate::db::car::forest::Encoder::compress_stream_default(
+                    futures::stream::iter(&mut sub_dag),
+                );
+                let mut writer = tokio::io::BufWriter::new(tokio::fs::File::create(&output).await?);
+                crate::db::car::forest::Encoder::write(&mut writer, roots, frames).await?;
+                writer.flush().await?;
+                println!("{} blocks extracted", sub_dag.extracted);
+                if sub_dag.missing > 0 {
+                    println!(
+                        "{} linked blocks are missing from the input, and were skipped",
+                        sub_dag.missing
+                    );
+                }
+            }
         }
         Ok(())
     }
 }
 
+/// Number of blocks and roots of a [`merge`].
+struct MergeStats {
+    /// Number of blocks read from the inputs
+    read: u64,
+    /// Number of distinct blocks written to the output
+    written: u64,
+    roots: usize,
+}
+
+/// Writes the distinct blocks of `car_files` to a `.forest.car.zst` archive
+/// with a fresh index, whose roots are the union of the roots of the inputs.
+async fn merge(car_files: &[PathBuf], output: &Path) -> anyhow::Result<MergeStats> {
+    let car_streams: Vec<_> = futures::stream::iter(car_files)
+        .then(tokio::fs::File::open)
+        .map_ok(tokio::io::BufReader::new)
+        .and_then(CarStream::new)
+        .try_collect()
+        .await?;
+
+    let all_roots = car_streams
+        .iter()
+        .flat_map(|it| it.header.roots.iter())
+        .unique()
+        .cloned()
+        .collect::<Vec<_>>();
+    let roots = all_roots.len();
+
+    let (mut read, mut written) = (0, 0);
+    let blocks = merge_car_streams(car_streams).inspect_ok(|_| read += 1);
+    let frames = crate::db::car::forest::Encoder::compress_stream_default(
+        dedup_block_stream(blocks)
+            .inspect_ok(|_| written += 1)
+            .map_err(anyhow::Error::from),
+    );
+    let mut writer = tokio::io::BufWriter::new(tokio::fs::File::create(output).await?);
+    crate::db::car::forest::Encoder::write(&mut writer, all_roots, frames).await?;
+    writer.flush().await?;
+    Ok(MergeStats {
+        read,
+        written,
+        roots,
+    })
+}
+
+/// Blocks of an archive, as streamed by [`validate`].
+struct BlockCounts {
+    roots: Vec<Cid>,
+    /// Distinct blocks
+    blocks: CidHashSet,
+    /// Number of blocks that are repeated
+    duplicates: u64,
+}
+
 /// At present, three properties are checked:
 /// - The CAR file is syntactically valid and all blocks can be streamed.
 /// - Each block CID is checked against the hash of the block.
@@ -87,13 +233,13 @@ impl CarCommands {
 /// Properties related to Filecoin are not checked. For those, see `forest-tool
 /// snapshot validate`.
 ///
-/// We do not check for duplicate blocks. Whether duplicate blocks are allowed or
-/// not is vague in the specification.
+/// Duplicate blocks are counted but not rejected. Whether duplicate blocks are
+/// allowed or not is vague in the specification.
 async fn validate(
     car_file: &Path,
     ignore_block_validity: bool,
     ignore_forest_index: bool,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<BlockCounts> {
     let optional_db = if !ignore_forest_index {
         Some(ForestCar::try_from(car_file)?)
     } else {
@@ -107,26 +253,228 @@ async fn validate(
     let file = BufReader::new(pb.wrap_async_read(file));
 
     let mut stream = CarStream::new(file).await?;
+    let roots = stream.header.roots.clone();
+    let mut seen = CidHashSet::new();
+    let mut duplicates = 0;
     while let Some(block) = stream.try_next().await? {
         if !ignore_block_validity && !block.valid() {
             anyhow::ensure!(block.valid(), "CID/Block mismatch for block: {}", block.cid);
         }
+        if !seen.insert(block.cid) {
+            duplicates += 1;
+        }
         if let Some(ref db) = optional_db {
             anyhow::ensure!(db.get(&block.cid).ok().flatten() == Some(block.data));
         }
     }
-    Ok(())
+    Ok(BlockCounts {
+        roots,
+        blocks: seen,
+        duplicates,
+    })
+}
+
+/// Opens `car_file` with an index of the positions of its blocks, without
+/// loading it into memory. A plain `.car.zst` archive is decompressed into a
+/// temporary `.car` file next to it first, which is removed when the returned
+/// path is dropped.
+fn open_indexed(
+    car_file: &Path,
+) -> anyhow::Result<(AnyCar<EitherMmapOrRandomAccessFile>, Option<TempPath>)> {
+    let reader = EitherMmapOrRandomAccessFile::open(car_file)?;
+    if ForestCar::is_valid(&reader) {
+        return Ok((ForestCar::new(reader)?.into(), None));
+    }
+    if let Ok(car) = PlainCar::new(reader) {
+        return Ok((car.into(), None));
+    }
+    println!("Decompressing {} to index it", car_file.display());
+    let mut decompressed = tempfile::Builder::new()
+        .suffix(".car")
+        .tempfile_in(car_file.parent().unwrap_or(Path::new(".")))?;
+    zstd::stream::copy_decode(std::fs::File::open(car_file)?, &mut decompressed)?;
+    let decompressed = decompressed.into_temp_path();
+    let car = PlainCar::new(EitherMmapOrRandomAccessFile::open(&decompressed)?)?;
+    Ok((car.into(), Some(decompressed)))
+}
+
+/// Result of a walk of the DAG of an archive, by [`walk_dag`].
+#[derive(Debug, PartialEq)]
+struct DagReport {
+    /// Number of blocks of the archive reachable from the roots
+    reachable: u64,
+    /// Number of linked blocks missing from the archive
+    missing: u64,
+    /// The first few of the missing blocks
+    first_missing: Vec<Cid>,
+    /// Number of blocks of the archive not reachable from the roots
+    unreachable: u64,
+    /// The first few of the unreachable blocks
+    first_unreachable: Vec<Cid>,
+}
+
+/// Number of missing or unreachable blocks listed in a [`DagReport`].
+const MAX_REPORTED_BLOCKS: usize = 10;
+
+/// Walks the DAG of `store` from `roots`, following the links of the
+/// `DAG_CBOR` blocks, and counts the blocks reached, the linked blocks that
+/// are missing and not `allowed_missing`, and the `blocks` of the archive that
+/// aren't reached. Links with other codecs, e.g. to sector commitments, and
+/// identity CIDs don't point to stored blocks.
+fn walk_dag(
+    store: &impl Blockstore,
+    roots: &[Cid],
+    blocks: CidHashSet,
+    allowed_missing: &CidHashSet,
+) -> anyhow::Result<DagReport> {
+    let mut report = DagReport {
+        reachable: 0,
+        missing: 0,
+        first_missing: vec![],
+        unreachable: 0,
+        first_unreachable: vec![],
+    };
+    let mut walked = CidHashSet::new();
+    let mut stack = roots.to_vec();
+    while let Some(cid) = stack.pop() {
+        if !is_stored(&cid) || !walked.insert(cid) {
+            continue;
+        }
+        match store.get(&cid)? {
+            Some(data) => {
+                report.reachable += 1;
+                if cid.codec() == fvm_ipld_encoding::DAG_CBOR {
+                    stack.extend(extract_cids(&data)?);
+                }
+            }
+            None if allowed_missing.contains(&cid) => {}
+            None => {
+                report.missing += 1;
+                if report.first_missing.len() < MAX_REPORTED_BLOCKS {
+                    report.first_missing.push(cid);
+                }
+            }
+        }
+    }
+    for cid in blocks {
+        if !walked.contains(&cid) {
+            report.unreachable += 1;
+            if report.first_unreachable.len() < MAX_REPORTED_BLOCKS {
+                report.first_unreachable.push(cid);
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Whether `cid` points to a block that is stored in an archive. Links with
+/// other codecs than `DAG_CBOR` and raw, e.g. to sector commitments, and
+/// identity CIDs, whose data is the digest itself, don't.
+fn is_stored(cid: &Cid) -> bool {
+    matches!(
+        cid.codec(),
+        fvm_ipld_encoding::DAG_CBOR | crate::shim::crypto::IPLD_RAW
+    ) && cid.hash().code() != IDENTITY
+}
+
+/// Multihash code of the identity hash, whose digest is the data itself.
+const IDENTITY: u64 = 0x00;
+
+/// Iterates over the roots and the blocks reachable from them, breadth first,
+/// at most `max_depth` links away from them and with one of `codecs`, if any.
+/// Walking breadth first visits each block at its shortest distance from the
+/// roots. The linked blocks missing from the store are skipped.
+struct SubDag<'a, S> {
+    store: &'a S,
+    /// Blocks left to visit, with their distance from the roots
+    queue: VecDeque<(Cid, u64)>,
+    seen: CidHashSet,
+    max_depth: Option<u64>,
+    codecs: Vec<u64>,
+    extracted: u64,
+    missing: u64,
+}
+
+impl<'a, S: Blockstore> SubDag<'a, S> {
+    fn new(store: &'a S, roots: &[Cid], max_depth: Option<u64>, codecs: Vec<u64>) -> Self {
+        Self {
+            store,
+            queue: roots.iter().map(|root| (*root, 0)).collect(),
+            seen: CidHashSet::new(),
+            max_depth,
+            codecs,
+            extracted: 0,
+            missing: 0,
+        }
+    }
+
+    /// Whether the block is written, the roots always are so that the output
+    /// contains them.
+    fn is_selected(&self, cid: &Cid, depth: u64) -> bool {
+        depth == 0 || self.codecs.is_empty() || self.codecs.contains(&cid.codec())
+    }
+
+    /// Queues the links of the block, and returns it if it's selected.
+    fn visit(&mut self, cid: Cid, depth: u64) -> anyhow::Result<Option<CarBlock>> {
+        let Some(data) = self.store.get(&cid)? else {
+            self.missing += 1;
+            return Ok(None);
+        };
+        if cid.codec() == fvm_ipld_encoding::DAG_CBOR
+            && self.max_depth.map_or(true, |max_depth| depth < max_depth)
+        {
+            let links = extract_cids(&data)?;
+            self.queue
+                .extend(links.into_iter().map(|link| (link, depth + 1)));
+        }
+        if !self.is_selected(&cid, depth) {
+            return Ok(None);
+        }
+        self.extracted += 1;
+        Ok(Some(CarBlock { cid, data }))
+    }
+}
+
+impl<S: Blockstore> Iterator for SubDag<'_, S> {
+    type Item = anyhow::Result<CarBlock>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((cid, depth)) = self.queue.pop_front() {
+            if !is_stored(&cid) || !self.seen.insert(cid) {
+                continue;
+            }
+            match self.visit(cid, depth) {
+                Ok(None) => continue,
+                result => return result.transpose(),
+            }
+        }
+        None
+    }
+}
+
+/// Parses a codec name, `dag-cbor` or `raw`, or a multicodec code, in decimal
+/// or in hex with a `0x` prefix.
+fn parse_codec(codec: &str) -> anyhow::Result<u64> {
+    Ok(match codec {
+        "dag-cbor" => fvm_ipld_encoding::DAG_CBOR,
+        "raw" => crate::shim::crypto::IPLD_RAW,
+        _ => match codec.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16)?,
+            None => codec.parse()?,
+        },
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use super::validate;
+    use super::{merge, open_indexed, parse_codec, validate, walk_dag, DagReport, SubDag};
     use crate::db::car::forest;
     use crate::networks::{calibnet, mainnet};
-    use crate::utils::db::car_stream::CarBlock;
+    use crate::utils::db::car_stream::{CarBlock, CarStream};
     use cid::multihash::{Code, MultihashDigest};
     use cid::Cid;
     use futures::{stream::iter, StreamExt, TryStreamExt};
+    use fvm_ipld_blockstore::Blockstore;
     use std::io::Write;
     use tempfile::{Builder, TempPath};
     use tokio::io::AsyncWriteExt;
@@ -166,6 +514,26 @@ mod tests {
             .is_ok());
     }
 
+    #[tokio::test]
+    async fn plain_car_zst_is_indexed() {
+        let mut temp_path = Builder::new().suffix(".car.zst").tempfile().unwrap();
+        temp_path
+            .write_all(&zstd::encode_all(calibnet::DEFAULT_GENESIS, 0).unwrap())
+            .unwrap();
+        let temp_path = temp_path.into_temp_path();
+
+        let counts = validate(&temp_path, false, true).await.unwrap();
+        let (store, decompressed) = open_indexed(&temp_path).unwrap();
+        let decompressed = decompressed.unwrap();
+        let path = decompressed.to_path_buf();
+        assert!(path.exists());
+        for root in &counts.roots {
+            assert!(store.has(root).unwrap());
+        }
+        drop((store, decompressed));
+        assert!(!path.exists());
+    }
+
     fn valid_block(msg: &str) -> CarBlock {
         let data = msg.as_bytes().to_vec();
         CarBlock {
@@ -241,4 +609,132 @@ mod tests {
         // Ignoring index validity should make the test pass.
         assert!(validate(&temp_path, false, true).await.is_ok());
     }
+
+    fn cbor_block(links: &[Cid]) -> CarBlock {
+        let data = fvm_ipld_encoding::to_vec(&links).unwrap();
+        CarBlock {
+            cid: Cid::new_v1(fvm_ipld_encoding::DAG_CBOR, Code::Blake2b256.digest(&data)),
+            data,
+        }
+    }
+
+    #[test]
+    fn walk_dag_reports_missing_blocks() {
+        let leaf = cbor_block(&[]);
+        let missing = cbor_block(&[Cid::default()]);
+        let allowed = cbor_block(&[leaf.cid, leaf.cid]);
+        let unreachable = valid_block("not linked from the root");
+        // Sector commitments are not blocks
+        let commitment = Cid::new_v1(0xf101, Code::Identity.digest(&[]));
+        let root = cbor_block(&[leaf.cid, missing.cid, allowed.cid, commitment]);
+
+        let store = crate::db::MemoryDB::default();
+        for block in [&leaf, &unreachable, &root] {
+            store.put_keyed(&block.cid, &block.data).unwrap();
+        }
+
+        let blocks = [leaf.cid, unreachable.cid, root.cid].into_iter().collect();
+        let report = walk_dag(
+            &store,
+            &[root.cid],
+            blocks,
+            &[allowed.cid].into_iter().collect(),
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            DagReport {
+                reachable: 2,
+                missing: 1,
+                first_missing: vec![missing.cid],
+                unreachable: 1,
+                first_unreachable: vec![unreachable.cid],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn merge_overlapping_archives() {
+        let (a, b, c) = (valid_block("a"), valid_block("b"), valid_block("c"));
+        let first = create_car_file_with_roots(vec![a.clone(), b.clone()], vec![a.cid]).await;
+        let second = create_car_file_with_roots(vec![b.clone(), c.clone()], vec![c.cid]).await;
+        let output = Builder::new().tempfile().unwrap().into_temp_path();
+
+        let stats = merge(&[first.to_path_buf(), second.to_path_buf()], &output)
+            .await
+            .unwrap();
+        assert_eq!((stats.read, stats.written, stats.roots), (4, 3, 2));
+
+        let file = tokio::io::BufReader::new(tokio::fs::File::open(&output).await.unwrap());
+        let stream = CarStream::new(file).await.unwrap();
+        assert_eq!(stream.header.roots, vec![a.cid, c.cid]);
+        let blocks: Vec<_> = stream.try_collect().await.unwrap();
+        assert_eq!(blocks, vec![a, b, c]);
+        assert!(validate(&output, false, false).await.is_ok());
+    }
+
+    async fn create_car_file_with_roots(car_blocks: Vec<CarBlock>, roots: Vec<Cid>) -> TempPath {
+        let temp_path = Builder::new().tempfile().unwrap().into_temp_path();
+        let mut writer = tokio::fs::File::create(&temp_path).await.unwrap();
+        let frames = forest::Encoder::compress_stream_default(iter(car_blocks).map(Ok));
+        forest::Encoder::write(&mut writer, roots, frames)
+            .await
+            .unwrap();
+        writer.flush().await.unwrap();
+        temp_path
+    }
+
+    #[test]
+    fn extract_sub_dag() {
+        let raw = CarBlock {
+            cid: Cid::new_v1(
+                crate::shim::crypto::IPLD_RAW,
+                Code::Blake2b256.digest(b"raw"),
+            ),
+            data: b"raw".to_vec(),
+        };
+        let leaf = cbor_block(&[]);
+        let grandchild = cbor_block(&[leaf.cid]);
+        let child = cbor_block(&[grandchild.cid, raw.cid]);
+        let root = cbor_block(&[child.cid, grandchild.cid]);
+        let store = crate::db::MemoryDB::default();
+        for block in [&raw, &leaf, &grandchild, &child, &root] {
+            store.put_keyed(&block.cid, &block.data).unwrap();
+        }
+
+        let extract = |depth, codecs| -> Vec<Cid> {
+            SubDag::new(&store, &[root.cid], depth, codecs)
+                .map(|block| block.unwrap().cid)
+                .collect()
+        };
+        let all = vec![root.cid, child.cid, grandchild.cid, raw.cid, leaf.cid];
+        assert_eq!(extract(None, vec![]), all);
+        assert_eq!(extract(Some(0), vec![]), vec![root.cid]);
+        assert_eq!(
+            extract(Some(1), vec![]),
+            vec![root.cid, child.cid, grandchild.cid]
+        );
+        // The grandchild is linked from the root too, so its leaf is 2 links
+        // away from it
+        assert_eq!(extract(Some(2), vec![]), all);
+        assert_eq!(
+            extract(None, vec![fvm_ipld_encoding::DAG_CBOR]),
+            vec![root.cid, child.cid, grandchild.cid, leaf.cid]
+        );
+        // The root is kept, and the raw block is found through the blocks
+        // left out
+        assert_eq!(
+            extract(None, vec![crate::shim::crypto::IPLD_RAW]),
+            vec![root.cid, raw.cid]
+        );
+    }
+
+    #[test]
+    fn codecs() {
+        assert_eq!(parse_codec("dag-cbor").unwrap(), 0x71);
+        assert_eq!(parse_codec("raw").unwrap(), 0x55);
+        assert_eq!(parse_codec("0x71").unwrap(), 0x71);
+        assert_eq!(parse_codec("85").unwrap(), 0x55);
+        assert!(parse_codec("json").is_err());
+    }
 }
@@ -0,0 +1,1068 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+//! Devnet genesis generation. A genesis is described by a JSON template in the
+//! format of `lotus-seed genesis`, so templates can be shared with Lotus:
+//!
+//! ```text
+//! forest-tool genesis new genesis.json --account t1...=1000
+//! forest-tool genesis set-signers genesis.json --threshold 1 --signers t1...
+//! forest-tool genesis car genesis.json --bundle builtin-actors.car --output devgen.car
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::utils::db::CborStoreExt as _;
+use anyhow::{bail, ensure, Context as _};
+use cid::Cid;
+use clap::Subcommand;
+use fil_actor_market_state::v12::{
+    balance_table::BalanceTable, DealProposal, DealState, Label, NO_ALLOCATION_ID,
+};
+use fil_actor_miner_state::v12::{
+    CollisionPolicy, CronEventPayload, MinerInfo, PowerPair, SectorOnChainInfo,
+    SectorOnChainInfoFlags, State as MinerState, CRON_EVENT_PROVING_DEADLINE,
+};
+use fil_actor_power_state::v12::{
+    consensus_miner_min_power, epoch_key, set_claim, Claim, CronEvent, CRON_QUEUE_AMT_BITWIDTH,
+    CRON_QUEUE_HAMT_BITWIDTH,
+};
+use fil_actors_shared::fvm_ipld_amt::Amtv0 as Amt;
+use fil_actors_shared::fvm_ipld_bitfield::BitField;
+use fil_actors_shared::v12::runtime::Policy;
+use fil_actors_shared::v12::{builtin::HAMT_BIT_WIDTH, make_empty_map, Multimap};
+use futures::{SinkExt as _, StreamExt as _};
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::RawBytes;
+use fvm_shared4::clock::EPOCH_UNDEFINED;
+use fvm_shared4::deal::DealID;
+use fvm_shared4::piece::PaddedPieceSize;
+use fvm_shared4::sector::{RegisteredSealProof, StoragePower as DealWeight};
+use fvm_shared4::smooth::FilterEstimate;
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt as _;
+
+use crate::beacon::BeaconEntry;
+use crate::blocks::{BlockHeader, ElectionProof, Ticket, Tipset, VRFProof};
+use crate::chain_sync::TipsetValidator;
+use crate::db::MemoryDB;
+use crate::shim::address::{Address, Protocol};
+use crate::shim::clock::ChainEpoch;
+use crate::shim::econ::{TokenAmount, TOTAL_FILECOIN};
+use crate::shim::machine::{BuiltinActor, BuiltinActorManifest};
+use crate::shim::message::Message;
+use crate::shim::state_tree::{ActorID, ActorState, StateTree, StateTreeVersion};
+use crate::utils::db::car_stream::CarWriter;
+use crate::utils::db::car_util::load_car;
+use crate::utils::encoding::blake2b_256;
+
+/// Balance of the reward actor, which pays out block rewards.
+const INITIAL_REWARD_BALANCE: u64 = 1_100_000_000;
+/// Base fee of the genesis block, in attoFIL.
+const INITIAL_BASE_FEE: u64 = 100_000_000;
+/// Address of the actor holding the root key of the verified registry.
+const ROOT_VERIFIER_ID: u64 = 80;
+/// First ID of the pre-sealed miners.
+const MINER_START: u64 = 1000;
+
+const SECTOR_SIZE_2KIB: u64 = 2 << 10;
+const SECTOR_SIZE_8MIB: u64 = 8 << 20;
+const SECTOR_SIZE_512MIB: u64 = 512 << 20;
+const SECTOR_SIZE_32GIB: u64 = 32 << 30;
+const SECTOR_SIZE_64GIB: u64 = 64 << 30;
+
+#[derive(Debug, Subcommand)]
+pub enum GenesisCommands {
+    /// Create a genesis template
+    New {
+        /// Path of the template to write
+        template: PathBuf,
+        /// Name of the network. Defaults to a random `localnet-` name
+        #[arg(long)]
+        network_name: Option<String>,
+        /// Timestamp of the genesis block. Defaults to now
+        #[arg(long)]
+        timestamp: Option<u64>,
+        /// Pre-funded accounts, as `<address>=<balance in FIL>`
+        #[arg(long = "account")]
+        accounts: Vec<String>,
+    },
+    /// Make the root key of the verified registry a multisig of the given
+    /// signers
+    SetSigners {
+        /// Path of the template to update
+        template: PathBuf,
+        /// Number of signatures required to approve a transaction
+        #[arg(long, default_value_t = 1)]
+        threshold: u64,
+        /// Key addresses of the signers
+        #[arg(long, required = true, num_args = 1..)]
+        signers: Vec<Address>,
+    },
+    /// Build the genesis CAR described by a template
+    Car {
+        /// Path of the template
+        template: PathBuf,
+        /// Actor bundle CAR to create the genesis actors from
+        #[arg(long)]
+        bundle: PathBuf,
+        /// Path of the genesis CAR to write
+        #[arg(short, long, default_value = "devgen.car")]
+        output: PathBuf,
+    },
+}
+
+impl GenesisCommands {
+    pub async fn run(self) -> anyhow::Result<()> {
+        match self {
+            Self::New {
+                template,
+                network_name,
+                timestamp,
+                accounts,
+            } => {
+                let accounts = accounts
+                    .iter()
+                    .map(|account| parse_account(account))
+                    .collect::<anyhow::Result<_>>()?;
+                let network_name =
+                    network_name.unwrap_or_else(|| format!("localnet-{}", uuid::Uuid::new_v4()));
+                let timestamp = timestamp.unwrap_or_else(|| chrono::Utc::now().timestamp() as u64);
+                Template {
+                    network_name,
+                    timestamp,
+                    accounts,
+                    ..Default::default()
+                }
+                .save(&template)
+            }
+            Self::SetSigners {
+                template: path,
+                threshold,
+                signers,
+            } => {
+                ensure!(
+                    threshold > 0 && threshold as usize <= signers.len(),
+                    "the threshold must be between 1 and the number of signers"
+                );
+                let mut template = Template::load(&path)?;
+                template.verifreg_root_key = GenesisActor {
+                    kind: ActorType::Multisig,
+                    balance: template.verifreg_root_key.balance,
+                    meta: serde_json::to_value(MultisigMeta {
+                        signers,
+                        threshold,
+                        vesting_duration: 0,
+                        vesting_start: 0,
+                    })?,
+                };
+                template.save(&path)
+            }
+            Self::Car {
+                template,
+                bundle,
+                output,
+            } => {
+                let template = Template::load(&template)?;
+                let db = Arc::new(MemoryDB::default());
+                let header = load_car(
+                    db.as_ref(),
+                    tokio::io::BufReader::new(tokio::fs::File::open(&bundle).await?),
+                )
+                .await
+                .with_context(|| format!("couldn't load actor bundle {}", bundle.display()))?;
+                ensure!(
+                    header.roots.len() == 1,
+                    "expected a single manifest in the actor bundle"
+                );
+                let genesis = make_genesis(&db, &template, &header.roots[0])?;
+
+                let mut writer = tokio::io::BufWriter::new(tokio::fs::File::create(&output).await?);
+                crate::ipld::stream_chain(&db, std::iter::once(Tipset::from(&genesis)), -1)
+                    .forward(
+                        CarWriter::new_carv1(vec![*genesis.cid()], &mut writer)?
+                            .sink_map_err(anyhow::Error::from),
+                    )
+                    .await?;
+                writer.flush().await?;
+                println!("Wrote genesis {} to {}", genesis.cid(), output.display());
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Genesis template, as written by `lotus-seed genesis new`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Template {
+    pub network_name: String,
+    pub timestamp: u64,
+    pub accounts: Vec<GenesisActor>,
+    /// Pre-sealed miners, as added by `lotus-seed genesis add-miner`.
+    pub miners: Vec<GenesisMiner>,
+    pub verifreg_root_key: GenesisActor,
+    pub remainder_account: GenesisActor,
+}
+
+impl Template {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("couldn't read genesis template {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("invalid genesis template {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActorType {
+    #[default]
+    Account,
+    Multisig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GenesisActor {
+    #[serde(rename = "Type")]
+    pub kind: ActorType,
+    #[serde(with = "crate::lotus_json")]
+    pub balance: TokenAmount,
+    /// [`AccountMeta`] or [`MultisigMeta`], depending on the type.
+    pub meta: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AccountMeta {
+    #[serde(with = "crate::lotus_json")]
+    pub owner: Address,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MultisigMeta {
+    #[serde(with = "crate::lotus_json")]
+    pub signers: Vec<Address>,
+    pub threshold: u64,
+    pub vesting_duration: ChainEpoch,
+    pub vesting_start: ChainEpoch,
+}
+
+/// Miner with pre-sealed sectors, as written by `lotus-seed pre-seal`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GenesisMiner {
+    /// ID the miner is expected to get, miners are created from `t01000` on.
+    #[serde(rename = "ID", with = "crate::lotus_json")]
+    pub id: Address,
+    #[serde(with = "crate::lotus_json")]
+    pub owner: Address,
+    #[serde(with = "crate::lotus_json")]
+    pub worker: Address,
+    /// Base58 peer ID of the miner, if any.
+    #[serde(default)]
+    pub peer_id: String,
+    /// Balance of the miner in the storage market.
+    #[serde(with = "crate::lotus_json")]
+    pub market_balance: TokenAmount,
+    /// Balance of the miner actor.
+    #[serde(with = "crate::lotus_json")]
+    pub power_balance: TokenAmount,
+    pub sector_size: u64,
+    pub sectors: Vec<PreSeal>,
+}
+
+/// Sector sealed ahead of genesis, with the deal storing its piece.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PreSeal {
+    #[serde(rename = "CommR", with = "crate::lotus_json")]
+    pub comm_r: Cid,
+    #[serde(rename = "CommD", with = "crate::lotus_json")]
+    pub comm_d: Cid,
+    #[serde(rename = "SectorID")]
+    pub sector_id: u64,
+    pub deal: PreSealDeal,
+    /// Registered seal proof of the sector.
+    pub proof_type: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PreSealDeal {
+    #[serde(rename = "PieceCID", with = "crate::lotus_json")]
+    pub piece_cid: Cid,
+    pub piece_size: u64,
+    pub verified_deal: bool,
+    #[serde(with = "crate::lotus_json")]
+    pub client: Address,
+    #[serde(with = "crate::lotus_json")]
+    pub provider: Address,
+    pub label: String,
+    pub start_epoch: ChainEpoch,
+    pub end_epoch: ChainEpoch,
+    #[serde(with = "crate::lotus_json")]
+    pub storage_price_per_epoch: TokenAmount,
+    #[serde(with = "crate::lotus_json")]
+    pub provider_collateral: TokenAmount,
+    #[serde(with = "crate::lotus_json")]
+    pub client_collateral: TokenAmount,
+}
+
+/// Parses `<address>=<balance in FIL>`.
+fn parse_account(s: &str) -> anyhow::Result<GenesisActor> {
+    let (address, balance) = s
+        .split_once('=')
+        .with_context(|| format!("expected <address>=<balance>, got {s}"))?;
+    let owner: Address = address.parse()?;
+    let balance: u64 = balance
+        .parse()
+        .with_context(|| format!("invalid balance {balance}"))?;
+    Ok(GenesisActor {
+        kind: ActorType::Account,
+        balance: TokenAmount::from_whole(balance),
+        meta: serde_json::to_value(AccountMeta { owner })?,
+    })
+}
+
+/// Creates the genesis state described by `template` with the actors of the
+/// bundle `manifest_cid`, and returns the genesis block. Everything is written
+/// to `db`.
+fn make_genesis<DB: Blockstore>(
+    db: &Arc<DB>,
+    template: &Template,
+    manifest_cid: &Cid,
+) -> anyhow::Result<BlockHeader> {
+    let manifest = BuiltinActorManifest::load_manifest(db.as_ref(), manifest_cid)?;
+    let mut tree = StateTree::new(db.clone(), StateTreeVersion::V5)?;
+    let set_actor = |tree: &mut StateTree<DB>,
+                     address: &Address,
+                     actor: BuiltinActor,
+                     state: Cid,
+                     balance: TokenAmount|
+     -> anyhow::Result<()> {
+        tree.set_actor(
+            address,
+            ActorState::new(manifest.get(actor)?, state, balance, 0, None),
+        )
+    };
+
+    let system_state = fil_actor_system_state::v12::State {
+        builtin_actors: manifest.source_cid(),
+    };
+    set_actor(
+        &mut tree,
+        &Address::SYSTEM_ACTOR,
+        BuiltinActor::System,
+        db.put_cbor_default(&system_state)?,
+        TokenAmount::zero(),
+    )?;
+
+    let mut init_state =
+        fil_actor_init_state::v12::State::new(db.as_ref(), template.network_name.clone())?;
+
+    let reward_balance = TokenAmount::from_whole(INITIAL_REWARD_BALANCE);
+
+    let cron_state = fil_actor_cron_state::v12::State {
+        entries: vec![
+            fil_actor_cron_state::v12::Entry {
+                receiver: Address::POWER_ACTOR.into(),
+                method_num: fil_actor_interface::power::Method::OnEpochTickEnd as u64,
+            },
+            fil_actor_cron_state::v12::Entry {
+                receiver: Address::MARKET_ACTOR.into(),
+                method_num: fil_actor_interface::market::Method::CronTick as u64,
+            },
+        ],
+    };
+    set_actor(
+        &mut tree,
+        &Address::CRON_ACTOR,
+        BuiltinActor::Cron,
+        db.put_cbor_default(&cron_state)?,
+        TokenAmount::zero(),
+    )?;
+
+    let verifreg_root = Address::new_id(ROOT_VERIFIER_ID);
+    let verifreg_state =
+        fil_actor_verifreg_state::v12::State::new(db.as_ref(), verifreg_root.into())?;
+    set_actor(
+        &mut tree,
+        &Address::VERIFIED_REGISTRY_ACTOR,
+        BuiltinActor::VerifiedRegistry,
+        db.put_cbor_default(&verifreg_state)?,
+        TokenAmount::zero(),
+    )?;
+
+    let datacap_state = fil_actor_datacap_state::v12::State {
+        governor: Address::VERIFIED_REGISTRY_ACTOR.into(),
+        token: fil_actors_shared::frc46_token::TokenState::new_with_bit_width(
+            db.as_ref(),
+            HAMT_BIT_WIDTH,
+        )?,
+    };
+    set_actor(
+        &mut tree,
+        &Address::DATACAP_TOKEN_ACTOR,
+        BuiltinActor::DataCap,
+        db.put_cbor_default(&datacap_state)?,
+        TokenAmount::zero(),
+    )?;
+
+    // The Ethereum Address Manager is stateless.
+    set_actor(
+        &mut tree,
+        &Address::ETHEREUM_ACCOUNT_MANAGER_ACTOR,
+        BuiltinActor::EAM,
+        db.put_cbor_default(&Vec::<()>::new())?,
+        TokenAmount::zero(),
+    )?;
+
+    let burnt_funds_state = fil_actor_account_state::v12::State {
+        address: Address::BURNT_FUNDS_ACTOR.into(),
+    };
+    set_actor(
+        &mut tree,
+        &Address::BURNT_FUNDS_ACTOR,
+        BuiltinActor::Account,
+        db.put_cbor_default(&burnt_funds_state)?,
+        TokenAmount::zero(),
+    )?;
+
+    // Creates an account actor for a key address, or returns the existing one.
+    let account_id = |tree: &mut StateTree<DB>,
+                      init_state: &mut fil_actor_init_state::v12::State,
+                      owner: &Address,
+                      balance: TokenAmount|
+     -> anyhow::Result<Address> {
+        if let Some(id) = init_state
+            .resolve_address(db.as_ref(), &owner.into())
+            .map_err(|e| anyhow::anyhow!("{e}"))?
+        {
+            let id = Address::from(id);
+            let mut actor = tree.get_actor(&id)?.context("missing account actor")?;
+            actor.balance = (TokenAmount::from(&actor.balance) + balance).into();
+            tree.set_actor(&id, actor)?;
+            return Ok(id);
+        }
+        let (id, _) = init_state
+            .map_addresses_to_id(db.as_ref(), &owner.into(), None)
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        let id = Address::new_id(id);
+        let state = fil_actor_account_state::v12::State {
+            address: owner.into(),
+        };
+        set_actor(
+            tree,
+            &id,
+            BuiltinActor::Account,
+            db.put_cbor_default(&state)?,
+            balance,
+        )?;
+        Ok(id)
+    };
+
+    let mut allocated = reward_balance.clone();
+    for account in &template.accounts {
+        ensure!(
+            account.kind == ActorType::Account,
+            "only account actors can be pre-funded"
+        );
+        let meta: AccountMeta = serde_json::from_value(account.meta.clone())?;
+        ensure!(
+            meta.owner.protocol() != Protocol::ID,
+            "account {} must be a key address",
+            meta.owner
+        );
+        account_id(
+            &mut tree,
+            &mut init_state,
+            &meta.owner,
+            account.balance.clone(),
+        )?;
+        allocated += account.balance.clone();
+    }
+
+    let root_key = &template.verifreg_root_key;
+    let root_key_state = match root_key.kind {
+        ActorType::Account => {
+            // Without an owner, nobody holds the root key.
+            let owner = match &root_key.meta {
+                serde_json::Value::Null => verifreg_root,
+                meta => serde_json::from_value::<AccountMeta>(meta.clone())?.owner,
+            };
+            (
+                BuiltinActor::Account,
+                db.put_cbor_default(&fil_actor_account_state::v12::State {
+                    address: owner.into(),
+                })?,
+            )
+        }
+        ActorType::Multisig => {
+            let meta: MultisigMeta = serde_json::from_value(root_key.meta.clone())?;
+            let signers = meta
+                .signers
+                .iter()
+                .map(|signer| {
+                    account_id(&mut tree, &mut init_state, signer, TokenAmount::zero())
+                        .map(Into::into)
+                })
+                .collect::<anyhow::Result<_>>()?;
+            let pending_txs = make_empty_map::<_, ()>(db.as_ref(), HAMT_BIT_WIDTH).flush()?;
+            (
+                BuiltinActor::Multisig,
+                db.put_cbor_default(&fil_actor_multisig_state::v12::State {
+                    signers,
+                    num_approvals_threshold: meta.threshold,
+                    next_tx_id: fil_actor_multisig_state::v12::TxnID(0),
+                    initial_balance: TokenAmount::zero().into(),
+                    start_epoch: meta.vesting_start,
+                    unlock_duration: meta.vesting_duration,
+                    pending_txs,
+                })?,
+            )
+        }
+    };
+    set_actor(
+        &mut tree,
+        &verifreg_root,
+        root_key_state.0,
+        root_key_state.1,
+        root_key.balance.clone(),
+    )?;
+    allocated += root_key.balance.clone();
+
+    // Pre-sealed miners get IDs from `MINER_START` on, like in Lotus, so the
+    // accounts they use are created first.
+    for miner in &template.miners {
+        let clients = miner.sectors.iter().map(|sector| &sector.deal.client);
+        for address in [&miner.owner, &miner.worker].into_iter().chain(clients) {
+            ensure!(
+                address.protocol() != Protocol::ID,
+                "{address} must be a key address"
+            );
+            account_id(&mut tree, &mut init_state, address, TokenAmount::zero())?;
+        }
+    }
+    ensure!(
+        init_state.next_id <= MINER_START,
+        "the template has too many accounts to create miners from t0{MINER_START} on"
+    );
+    init_state.next_id = MINER_START;
+    let resolve = |init_state: &fil_actor_init_state::v12::State,
+                   address: &Address|
+     -> anyhow::Result<ActorID> {
+        let id = init_state
+            .resolve_address(db.as_ref(), &address.into())
+            .map_err(|e| anyhow::anyhow!("{e}"))?
+            .with_context(|| format!("{address} has no actor"))?;
+        Ok(Address::from(id).id()?)
+    };
+
+    let policy = Policy::devnet();
+    let mut power_state = fil_actor_power_state::v12::State::new(db.as_ref())?;
+    let mut market_state = fil_actor_market_state::v12::State::new(db.as_ref())?;
+    let mut market_balance = TokenAmount::zero();
+    for (i, miner) in template.miners.iter().enumerate() {
+        let robust_address =
+            Address::new_actor(&[miner.owner.to_bytes(), i.to_be_bytes().to_vec()].concat());
+        let (id, _) = init_state
+            .map_addresses_to_id(db.as_ref(), &robust_address.into(), None)
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        ensure!(
+            Address::new_id(id) == miner.id,
+            "miner {} would be created as t0{id}, miners must be listed in ID order",
+            miner.id
+        );
+
+        // Clients are funded with what their deals lock, whereas providers
+        // lock their collateral out of their market balance.
+        let mut escrow =
+            BalanceTable::from_root(db.as_ref(), &market_state.escrow_table, "escrow table")?;
+        let mut locked =
+            BalanceTable::from_root(db.as_ref(), &market_state.locked_table, "locked table")?;
+        escrow.add(&miner.id.into(), &miner.market_balance.clone().into())?;
+        market_balance += miner.market_balance.clone();
+        let mut deals = Vec::with_capacity(miner.sectors.len());
+        for sector in &miner.sectors {
+            let deal = &sector.deal;
+            ensure!(
+                !deal.verified_deal,
+                "verified pre-sealed deals are not supported"
+            );
+            ensure!(
+                deal.provider == miner.id,
+                "the deal of sector {} is not with miner {}",
+                sector.sector_id,
+                miner.id
+            );
+            let proposal = DealProposal {
+                piece_cid: deal.piece_cid,
+                piece_size: PaddedPieceSize(deal.piece_size),
+                verified_deal: false,
+                client: Address::new_id(resolve(&init_state, &deal.client)?).into(),
+                provider: miner.id.into(),
+                label: Label::String(deal.label.clone()),
+                start_epoch: deal.start_epoch,
+                end_epoch: deal.end_epoch,
+                storage_price_per_epoch: deal.storage_price_per_epoch.clone().into(),
+                provider_collateral: deal.provider_collateral.clone().into(),
+                client_collateral: deal.client_collateral.clone().into(),
+            };
+            let client_funds = proposal.client_balance_requirement();
+            escrow.add(&proposal.client, &client_funds)?;
+            locked.add(&proposal.client, &client_funds)?;
+            locked.add(&proposal.provider, &proposal.provider_collateral)?;
+            market_balance += TokenAmount::from(client_funds);
+            market_state.total_client_locked_collateral += &proposal.client_collateral;
+            market_state.total_provider_locked_collateral += &proposal.provider_collateral;
+            market_state.total_client_storage_fee += proposal.total_storage_fee();
+            deals.push((market_state.next_id, proposal));
+            market_state.next_id += 1;
+        }
+        ensure!(
+            locked.get(&miner.id.into())? <= escrow.get(&miner.id.into())?,
+            "the market balance of miner {} does not cover the collateral of its deals",
+            miner.id
+        );
+        market_state.escrow_table = escrow.root()?;
+        market_state.locked_table = locked.root()?;
+        market_state.put_deal_proposals(db.as_ref(), &deals)?;
+        let deal_states = deals
+            .iter()
+            .map(|(deal_id, _)| {
+                let state = DealState {
+                    sector_start_epoch: 0,
+                    last_updated_epoch: EPOCH_UNDEFINED,
+                    slash_epoch: EPOCH_UNDEFINED,
+                    verified_claim: NO_ALLOCATION_ID,
+                };
+                (*deal_id, state)
+            })
+            .collect::<Vec<_>>();
+        market_state.put_deal_states(db.as_ref(), &deal_states)?;
+        let deals_by_epoch = deals
+            .iter()
+            .map(|(deal_id, proposal)| (proposal.start_epoch, *deal_id))
+            .collect::<Vec<_>>();
+        market_state.put_deals_by_epoch(db.as_ref(), &deals_by_epoch)?;
+
+        let owner = resolve(&init_state, &miner.owner)?;
+        let worker = resolve(&init_state, &miner.worker)?;
+        let (miner_state, claim) =
+            make_miner_state(db.as_ref(), &policy, id, owner, worker, miner, &deals)?;
+        let mut claims = power_state.load_claims(db.as_ref())?;
+        set_claim(&mut claims, &miner.id.into(), claim.clone())?;
+        power_state.save_claims(&mut claims)?;
+        power_state.miner_count += 1;
+        power_state.total_bytes_committed += &claim.raw_byte_power;
+        power_state.total_qa_bytes_committed += &claim.quality_adj_power;
+        if claim.raw_byte_power >= consensus_miner_min_power(&policy, claim.window_post_proof_type)?
+        {
+            power_state.miner_above_min_power_count += 1;
+            power_state.total_raw_byte_power += &claim.raw_byte_power;
+            power_state.total_quality_adj_power += &claim.quality_adj_power;
+        }
+        if miner_state.deadline_cron_active {
+            let mut cron_queue = Multimap::from_root(
+                db.as_ref(),
+                &power_state.cron_event_queue,
+                CRON_QUEUE_HAMT_BITWIDTH,
+                CRON_QUEUE_AMT_BITWIDTH,
+            )?;
+            cron_queue.add(
+                epoch_key(miner_state.deadline_info(&policy, 0).last()),
+                CronEvent {
+                    miner_addr: miner.id.into(),
+                    callback_payload: RawBytes::serialize(CronEventPayload {
+                        event_type: CRON_EVENT_PROVING_DEADLINE,
+                    })?,
+                },
+            )?;
+            power_state.cron_event_queue = cron_queue.root()?;
+        }
+        set_actor(
+            &mut tree,
+            &miner.id,
+            BuiltinActor::Miner,
+            db.put_cbor_default(&miner_state)?,
+            miner.power_balance.clone(),
+        )?;
+        allocated += miner.power_balance.clone();
+    }
+    if !template.miners.is_empty() {
+        let (raw_power, qa_power) = power_state.current_total_power();
+        power_state.this_epoch_raw_byte_power = raw_power;
+        power_state.this_epoch_quality_adj_power = qa_power.clone();
+        power_state.this_epoch_qa_power_smoothed = FilterEstimate::new(qa_power, Zero::zero());
+    }
+
+    let reward_state =
+        fil_actor_reward_state::v12::State::new(power_state.this_epoch_quality_adj_power.clone());
+    set_actor(
+        &mut tree,
+        &Address::REWARD_ACTOR,
+        BuiltinActor::Reward,
+        db.put_cbor_default(&reward_state)?,
+        reward_balance,
+    )?;
+    set_actor(
+        &mut tree,
+        &Address::POWER_ACTOR,
+        BuiltinActor::Power,
+        db.put_cbor_default(&power_state)?,
+        TokenAmount::zero(),
+    )?;
+    set_actor(
+        &mut tree,
+        &Address::MARKET_ACTOR,
+        BuiltinActor::Market,
+        db.put_cbor_default(&market_state)?,
+        market_balance.clone(),
+    )?;
+    allocated += market_balance;
+
+    // Whatever was not allocated is held by the reserve.
+    ensure!(
+        allocated <= *TOTAL_FILECOIN,
+        "the template allocates more than the total supply of FIL"
+    );
+    let reserve_state = fil_actor_account_state::v12::State {
+        address: Address::RESERVE_ACTOR.into(),
+    };
+    set_actor(
+        &mut tree,
+        &Address::RESERVE_ACTOR,
+        BuiltinActor::Account,
+        db.put_cbor_default(&reserve_state)?,
+        TOTAL_FILECOIN.clone() - &allocated,
+    )?;
+
+    set_actor(
+        &mut tree,
+        &Address::INIT_ACTOR,
+        BuiltinActor::Init,
+        db.put_cbor_default(&init_state)?,
+        TokenAmount::zero(),
+    )?;
+    let state_root = tree.flush()?;
+
+    let messages = TipsetValidator::compute_msg_root(db.as_ref(), &[] as &[Message], &[])
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    let message_receipts = Amt::<(), _>::new(db.as_ref()).flush()?;
+    let genesis = BlockHeader::builder()
+        .miner_address(Address::SYSTEM_ACTOR)
+        .ticket(Some(Ticket::new(VRFProof::new(
+            b"vrf proof0000000vrf proof0000000".to_vec(),
+        ))))
+        .election_proof(Some(ElectionProof::default()))
+        .beacon_entries(vec![BeaconEntry::new(0, vec![0; 32])])
+        .state_root(state_root)
+        .messages(messages)
+        .message_receipts(message_receipts)
+        .timestamp(template.timestamp)
+        .parent_base_fee(TokenAmount::from_atto(INITIAL_BASE_FEE))
+        .build()?;
+    db.put_cbor_default(&genesis)?;
+    Ok(genesis)
+}
+
+/// Creates the state of a pre-sealed miner whose sectors store `deals`, and
+/// returns it with the power the miner claims. The sectors are proven from
+/// genesis on and carry no pledge.
+fn make_miner_state<BS: Blockstore>(
+    db: &BS,
+    policy: &Policy,
+    id: ActorID,
+    owner: ActorID,
+    worker: ActorID,
+    miner: &GenesisMiner,
+    deals: &[(DealID, DealProposal)],
+) -> anyhow::Result<(MinerState, Claim)> {
+    let seal_proof = match miner.sector_size {
+        SECTOR_SIZE_2KIB => RegisteredSealProof::StackedDRG2KiBV1P1,
+        SECTOR_SIZE_8MIB => RegisteredSealProof::StackedDRG8MiBV1P1,
+        SECTOR_SIZE_512MIB => RegisteredSealProof::StackedDRG512MiBV1P1,
+        SECTOR_SIZE_32GIB => RegisteredSealProof::StackedDRG32GiBV1P1,
+        SECTOR_SIZE_64GIB => RegisteredSealProof::StackedDRG64GiBV1P1,
+        size => bail!("unsupported sector size {size}"),
+    };
+    let peer_id = match miner.peer_id.as_str() {
+        "" => Vec::new(),
+        peer_id => peer_id.parse::<libp2p::PeerId>()?.to_bytes(),
+    };
+    let info = MinerInfo::new(
+        owner,
+        worker,
+        Vec::new(),
+        peer_id,
+        Vec::new(),
+        seal_proof
+            .registered_window_post_proof()
+            .map_err(anyhow::Error::msg)?,
+    )?;
+
+    // Like the miner actor, spread the proving periods of the miners.
+    let digest =
+        blake2b_256(&[Address::new_id(id).to_bytes(), 0_u64.to_be_bytes().to_vec()].concat());
+    let offset = (u64::from_be_bytes(digest[..8].try_into()?) % policy.wpost_proving_period as u64)
+        as ChainEpoch;
+    let period_start = match offset {
+        0 => 0,
+        offset => offset - policy.wpost_proving_period,
+    };
+    let deadline_idx = (-period_start / policy.wpost_challenge_window) as u64;
+    let mut state = MinerState::new(
+        policy,
+        db,
+        db.put_cbor_default(&info)?,
+        period_start,
+        deadline_idx,
+    )?;
+
+    // Sectors last as long as the longest deal of the miner.
+    let expiration = deals
+        .iter()
+        .map(|(_, deal)| deal.end_epoch)
+        .max()
+        .unwrap_or_default();
+    let mut sectors = Vec::with_capacity(miner.sectors.len());
+    for (sector, (deal_id, deal)) in miner.sectors.iter().zip(deals) {
+        let proof = RegisteredSealProof::from(sector.proof_type);
+        ensure!(
+            proof.sector_size().map_err(anyhow::Error::msg)? as u64 == miner.sector_size,
+            "sector {} of miner {} is not {} bytes",
+            sector.sector_id,
+            miner.id,
+            miner.sector_size
+        );
+        sectors.push(SectorOnChainInfo {
+            sector_number: sector.sector_id,
+            seal_proof: proof,
+            sealed_cid: sector.comm_r,
+            deal_ids: vec![*deal_id],
+            activation: 0,
+            expiration,
+            deal_weight: DealWeight::from(deal.piece_size.0) * deal.end_epoch,
+            verified_deal_weight: DealWeight::zero(),
+            initial_pledge: Zero::zero(),
+            expected_day_reward: Zero::zero(),
+            expected_storage_pledge: Zero::zero(),
+            power_base_epoch: 0,
+            replaced_day_reward: Zero::zero(),
+            sector_key_cid: None,
+            flags: SectorOnChainInfoFlags::SIMPLE_QA_POWER,
+        });
+    }
+    let sector_numbers = BitField::try_from_bits(sectors.iter().map(|s| s.sector_number))?;
+    ensure!(
+        sector_numbers.len() == sectors.len() as u64,
+        "miner {} has duplicate sector numbers",
+        miner.id
+    );
+    state.allocate_sector_numbers(db, &sector_numbers, CollisionPolicy::DenyCollisions)?;
+    state.put_sectors(db, sectors.clone())?;
+    state.assign_sectors_to_deadlines(
+        policy,
+        db,
+        0,
+        sectors,
+        info.window_post_partition_sectors,
+        info.sector_size,
+    )?;
+
+    // Sectors are assigned unproven, prove them so that the miner has power
+    // at genesis.
+    let mut power = PowerPair::zero();
+    let mut deadlines = state.load_deadlines(db)?;
+    for deadline_idx in 0..policy.wpost_period_deadlines {
+        let mut deadline = deadlines.load_deadline(db, deadline_idx)?;
+        let mut partitions = deadline.partitions_amt(db)?;
+        for partition_idx in 0..partitions.count() {
+            let mut partition = partitions
+                .get(partition_idx)?
+                .context("missing partition")?
+                .clone();
+            power += &partition.activate_unproven();
+            partitions.set(partition_idx, partition)?;
+        }
+        deadline.partitions = partitions.flush()?;
+        deadlines.update_deadline(policy, db, deadline_idx, &deadline)?;
+    }
+    state.save_deadlines(db, deadlines)?;
+    state.deadline_cron_active = !miner.sectors.is_empty();
+
+    let claim = Claim {
+        window_post_proof_type: info.window_post_proof_type,
+        raw_byte_power: power.raw,
+        quality_adj_power: power.qa,
+    };
+    Ok((state, claim))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cid::multihash::MultihashDigest as _;
+    use fvm_ipld_encoding::{CborStore as _, IPLD_RAW};
+
+    fn make_test_manifest<BS: Blockstore>(store: &BS) -> Cid {
+        let actors = [
+            "account",
+            "cron",
+            "init",
+            "storagemarket",
+            "storageminer",
+            "multisig",
+            "paymentchannel",
+            "storagepower",
+            "reward",
+            "system",
+            "verifiedregistry",
+            "datacap",
+            "eam",
+        ]
+        .into_iter()
+        .map(|name| {
+            let hash = cid::multihash::Code::Identity.digest(format!("fil/12/{name}").as_bytes());
+            (name, Cid::new_v1(IPLD_RAW, hash))
+        })
+        .collect::<Vec<_>>();
+        store
+            .put_cbor_default(&(1, store.put_cbor_default(&actors).unwrap()))
+            .unwrap()
+    }
+
+    #[test]
+    fn template_roundtrip() {
+        let mut template = Template {
+            network_name: "localnet".into(),
+            accounts: vec![
+                parse_account("t1d2xrzcslx7xlbbylc5c3d5lvandqw4iwl6epxba=1000").unwrap(),
+            ],
+            ..Default::default()
+        };
+        template.verifreg_root_key.meta = serde_json::to_value(MultisigMeta {
+            signers: vec!["t1d2xrzcslx7xlbbylc5c3d5lvandqw4iwl6epxba".parse().unwrap()],
+            threshold: 1,
+            vesting_duration: 0,
+            vesting_start: 0,
+        })
+        .unwrap();
+        let json = serde_json::to_string(&template).unwrap();
+        assert!(json.contains("\"NetworkName\":\"localnet\""));
+        assert_eq!(serde_json::from_str::<Template>(&json).unwrap(), template);
+        assert!(parse_account("t1d2xrzcslx7xlbbylc5c3d5lvandqw4iwl6epxba").is_err());
+    }
+
+    #[test]
+    fn genesis_allocates_all_funds() {
+        let db = Arc::new(MemoryDB::default());
+        let manifest = make_test_manifest(db.as_ref());
+        let owner: Address = "t1d2xrzcslx7xlbbylc5c3d5lvandqw4iwl6epxba".parse().unwrap();
+        let template = Template {
+            network_name: "localnet".into(),
+            accounts: vec![parse_account(&format!("{owner}=1000")).unwrap()],
+            verifreg_root_key: GenesisActor {
+                kind: ActorType::Multisig,
+                balance: TokenAmount::zero(),
+                meta: serde_json::to_value(MultisigMeta {
+                    signers: vec![owner],
+                    threshold: 1,
+                    vesting_duration: 0,
+                    vesting_start: 0,
+                })
+                .unwrap(),
+            },
+            ..Default::default()
+        };
+        let genesis = make_genesis(&db, &template, &manifest).unwrap();
+        assert_eq!(genesis.epoch(), 0);
+
+        let tree = StateTree::new_from_root(db.clone(), genesis.state_root()).unwrap();
+        let account = tree.get_actor(&owner).unwrap().unwrap();
+        assert_eq!(
+            TokenAmount::from(&account.balance),
+            TokenAmount::from_whole(1000)
+        );
+        let mut total = TokenAmount::zero();
+        tree.for_each(|_, actor| {
+            total += TokenAmount::from(&actor.balance);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(total, *TOTAL_FILECOIN);
+    }
+
+    #[test]
+    fn genesis_with_pre_sealed_miner() {
+        let db = Arc::new(MemoryDB::default());
+        let manifest = make_test_manifest(db.as_ref());
+        let worker: Address = "t1d2xrzcslx7xlbbylc5c3d5lvandqw4iwl6epxba".parse().unwrap();
+        let miner_id = Address::new_id(MINER_START);
+        let deal = PreSealDeal {
+            piece_cid: Cid::default(),
+            piece_size: SECTOR_SIZE_2KIB,
+            verified_deal: false,
+            client: worker,
+            provider: miner_id,
+            label: "0".into(),
+            start_epoch: 0,
+            end_epoch: 10_000,
+            storage_price_per_epoch: TokenAmount::zero(),
+            provider_collateral: TokenAmount::zero(),
+            client_collateral: TokenAmount::zero(),
+        };
+        let template = Template {
+            network_name: "localnet".into(),
+            accounts: vec![parse_account(&format!("{worker}=1000")).unwrap()],
+            miners: vec![GenesisMiner {
+                id: miner_id,
+                owner: worker,
+                worker,
+                peer_id: String::new(),
+                market_balance: TokenAmount::zero(),
+                power_balance: TokenAmount::zero(),
+                sector_size: SECTOR_SIZE_2KIB,
+                sectors: (0..2)
+                    .map(|sector_id| PreSeal {
+                        comm_r: Cid::default(),
+                        comm_d: Cid::default(),
+                        sector_id,
+                        deal: deal.clone(),
+                        proof_type: i64::from(RegisteredSealProof::StackedDRG2KiBV1P1),
+                    })
+                    .collect(),
+            }],
+            ..Default::default()
+        };
+        let genesis = make_genesis(&db, &template, &manifest).unwrap();
+
+        let tree = StateTree::new_from_root(db.clone(), genesis.state_root()).unwrap();
+        let miner = tree.get_actor(&miner_id).unwrap().unwrap();
+        let miner_state: MinerState = db.get_cbor(&miner.state).unwrap().unwrap();
+        assert!(miner_state.deadline_cron_active);
+        let power = tree.get_actor(&Address::POWER_ACTOR).unwrap().unwrap();
+        let power_state: fil_actor_power_state::v12::State =
+            db.get_cbor(&power.state).unwrap().unwrap();
+        let claim = power_state
+            .get_claim(db.as_ref(), &miner_id.into())
+            .unwrap()
+            .unwrap();
+        assert_eq!(claim.raw_byte_power, (2 * SECTOR_SIZE_2KIB).into());
+        assert_eq!(power_state.this_epoch_raw_byte_power, claim.raw_byte_power);
+        let market = tree.get_actor(&Address::MARKET_ACTOR).unwrap().unwrap();
+        let market_state: fil_actor_market_state::v12::State =
+            db.get_cbor(&market.state).unwrap().unwrap();
+        assert_eq!(market_state.next_id, 2);
+    }
+}
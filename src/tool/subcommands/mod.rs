@@ -7,6 +7,8 @@ pub mod benchmark_cmd;
 pub mod car_cmd;
 pub mod db_cmd;
 pub mod fetch_params_cmd;
+pub mod genesis_cmd;
+pub mod shed_cmd;
 pub mod snapshot_cmd;
 pub mod state_migration_cmd;
 
@@ -60,6 +62,14 @@ pub enum Subcommand {
     /// API tooling
     #[command(subcommand)]
     Api(api_cmd::ApiCommands),
+
+    /// Miscellaneous debugging tools
+    #[command(subcommand)]
+    Shed(shed_cmd::ShedCommands),
+
+    /// Generate devnet genesis files
+    #[command(subcommand)]
+    Genesis(genesis_cmd::GenesisCommands),
 }
 
 fn read_config(
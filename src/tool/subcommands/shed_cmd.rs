@@ -0,0 +1,514 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::blocks::Tipset;
+use crate::chain::index::{ChainIndex, ResolveNullTipset};
+use crate::chain::{messages_for_tipset, ChainEpochDelta};
+use crate::db::car::ManyCar;
+use crate::interpreter::{resolve_to_key_addr, ReplayedMessage, VMTrace};
+use crate::ipld::json::IpldJsonRef;
+use crate::message::{ChainMessage, Message as _};
+use crate::networks::{ChainConfig, NetworkChain};
+use crate::shim::address::{Address, CurrentNetwork};
+use crate::shim::clock::ChainEpoch;
+use crate::shim::error::ExitCode;
+use crate::shim::executor::ApplyRet;
+use crate::shim::fvm_shared_latest::address::Network;
+use crate::shim::kernel::SyscallError;
+use crate::shim::machine::MultiEngine;
+use crate::shim::state_tree::{ActorID, StateTree};
+use crate::shim::trace::{Call, CallReturn, ExecutionEvent};
+use crate::state_manager::replay_message;
+use ahash::HashMap;
+use anyhow::{bail, Context as _};
+use cid::Cid;
+use clap::Subcommand;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::{ipld_block::IpldBlock, CborStore as _, RawBytes, DAG_CBOR};
+use itertools::Either;
+use libipld_core::ipld::Ipld;
+
+#[derive(Debug, Subcommand)]
+pub enum ShedCommands {
+    /// Re-execute a message with tracing enabled and step through its call
+    /// tree interactively
+    Replay {
+        /// CID of the message, signed or unsigned
+        message: Cid,
+        /// Snapshot input files (`.car.`, `.car.zst`, `.forest.car.zst`)
+        #[arg(long, required = true)]
+        snapshot_files: Vec<PathBuf>,
+        /// Epoch from which to search backwards for the message. Defaults to
+        /// the heaviest tipset.
+        #[arg(long)]
+        epoch: Option<ChainEpoch>,
+        /// Number of epochs to search for the message
+        #[arg(long, default_value_t = 2000)]
+        lookback: ChainEpochDelta,
+    },
+}
+
+impl ShedCommands {
+    pub async fn run(self) -> anyhow::Result<()> {
+        match self {
+            Self::Replay {
+                message,
+                snapshot_files,
+                epoch,
+                lookback,
+            } => replay(message, snapshot_files, epoch, lookback),
+        }
+    }
+}
+
+fn replay(
+    message: Cid,
+    snapshot_files: Vec<PathBuf>,
+    epoch: Option<ChainEpoch>,
+    lookback: ChainEpochDelta,
+) -> anyhow::Result<()> {
+    let store =
+        Arc::new(ManyCar::try_from(snapshot_files).context("couldn't read input CAR file")?);
+    let heaviest = store.heaviest_tipset()?;
+    let genesis = heaviest.genesis(&store)?;
+    let network = NetworkChain::from_genesis_or_devnet_placeholder(genesis.cid());
+    let chain_config = Arc::new(ChainConfig::from_chain(&network));
+    if chain_config.is_testnet() {
+        CurrentNetwork::set_global(Network::Testnet);
+    }
+    let timestamp = genesis.timestamp();
+    let chain_index = Arc::new(ChainIndex::new(Arc::clone(&store)));
+    let head = chain_index.tipset_by_height(
+        epoch.unwrap_or(heaviest.epoch()),
+        Arc::new(heaviest),
+        ResolveNullTipset::TakeOlder,
+    )?;
+
+    let tipset = find_inclusion_tipset(&store, &head, &message, lookback)?;
+    println!("Message {message} is included at epoch {}", tipset.epoch());
+
+    let replayed = replay_message(
+        timestamp,
+        Arc::clone(&chain_index),
+        Arc::clone(&chain_config),
+        Arc::new(chain_config.get_beacon_schedule(timestamp)),
+        &MultiEngine::default(),
+        Arc::new(tipset),
+        |chain_message| is_message(chain_message, &message),
+        VMTrace::Traced,
+    )?;
+
+    let mut debugger = Debugger::new(Arc::clone(&store), replayed)?;
+    let mut stdout = std::io::stdout();
+    debugger.execute("receipt", &mut stdout)?;
+    debugger.execute("tree", &mut stdout)?;
+
+    let mut editor = rustyline::DefaultEditor::new()?;
+    while let Ok(line) = editor.readline("replay> ") {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        match debugger.execute(line, &mut stdout) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => println!("error: {e:#}"),
+        }
+    }
+    Ok(())
+}
+
+fn is_message(chain_message: &ChainMessage, cid: &Cid) -> bool {
+    chain_message.cid().ok().as_ref() == Some(cid)
+        || chain_message.message().cid().ok().as_ref() == Some(cid)
+}
+
+// Walks back from `head` looking for the tipset whose blocks include
+// `message`. The message is executed on top of that tipset's parent state.
+fn find_inclusion_tipset(
+    store: &Arc<ManyCar>,
+    head: &Tipset,
+    message: &Cid,
+    lookback: ChainEpochDelta,
+) -> anyhow::Result<Tipset> {
+    let until = head.epoch() - lookback;
+    for tipset in head.clone().chain(store) {
+        if tipset.epoch() < until || tipset.epoch() == 0 {
+            break;
+        }
+        let messages = messages_for_tipset(Arc::clone(store), &tipset)?;
+        if messages.iter().any(|m| is_message(m, message)) {
+            return Ok(tipset);
+        }
+    }
+    bail!(
+        "message {message} not found in the {lookback} epochs before {}",
+        head.epoch()
+    )
+}
+
+/// How a call in the trace ended.
+#[derive(Debug, Clone)]
+enum Outcome {
+    Return(CallReturn),
+    Abort(ExitCode),
+    Error(SyscallError),
+}
+
+/// A call in the execution trace, with the gas charged directly to it.
+#[derive(Debug, Clone)]
+struct Frame {
+    call: Call,
+    milligas: u64,
+    outcome: Option<Outcome>,
+    sub_calls: Vec<Frame>,
+}
+
+impl Frame {
+    // Builds the call trees of a trace. Well-formed traces have a single
+    // root.
+    fn parse(trace: Vec<ExecutionEvent>) -> Vec<Frame> {
+        let mut roots = vec![];
+        let mut stack: Vec<Frame> = vec![];
+        for event in trace {
+            let outcome = match event {
+                ExecutionEvent::GasCharge(charge) => {
+                    if let Some(frame) = stack.last_mut() {
+                        frame.milligas += charge.total().as_milligas();
+                    }
+                    continue;
+                }
+                ExecutionEvent::Call(call) => {
+                    stack.push(Frame {
+                        call,
+                        milligas: 0,
+                        outcome: None,
+                        sub_calls: vec![],
+                    });
+                    continue;
+                }
+                ExecutionEvent::CallReturn(ret) => Outcome::Return(ret),
+                ExecutionEvent::CallAbort(code) => Outcome::Abort(code),
+                ExecutionEvent::CallError(e) => Outcome::Error(e),
+                ExecutionEvent::Log(_) | ExecutionEvent::Unknown(_) => continue,
+            };
+            if let Some(mut frame) = stack.pop() {
+                frame.outcome = Some(outcome);
+                match stack.last_mut() {
+                    Some(parent) => parent.sub_calls.push(frame),
+                    None => roots.push(frame),
+                }
+            }
+        }
+        // Calls which never returned, e.g. if execution ran out of gas.
+        while let Some(frame) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.sub_calls.push(frame),
+                None => roots.push(frame),
+            }
+        }
+        roots
+    }
+
+    fn summary(&self, from: &Address) -> String {
+        let outcome = match &self.outcome {
+            Some(Outcome::Return(CallReturn { exit_code, .. })) => {
+                format!("exit {}", exit_code.map(|c| c.value()).unwrap_or_default())
+            }
+            Some(Outcome::Abort(code)) => format!("abort {}", code.value()),
+            Some(Outcome::Error(e)) => format!("error: {e}"),
+            None => "no return".into(),
+        };
+        format!(
+            "{} -> {} method {} ({}, {} gas)",
+            from,
+            self.call.to,
+            self.call.method_num,
+            outcome,
+            self.milligas / 1000
+        )
+    }
+}
+
+/// Navigates the call tree of a replayed message.
+struct Debugger<DB> {
+    db: Arc<DB>,
+    message: ChainMessage,
+    apply_ret: ApplyRet,
+    pre_state: Cid,
+    post_state: Cid,
+    roots: Vec<Frame>,
+    /// Robust addresses of the callers in the trace, by actor ID.
+    callers: HashMap<ActorID, Address>,
+    /// Indexes from the roots down to the selected call.
+    path: Vec<usize>,
+}
+
+const HELP: &str = "\
+commands:
+  tree                  print the call tree, marking the selected call
+  down <n> | d <n>      select the n-th sub-call of the selected call
+  up | u                select the caller of the selected call
+  info | i              print the selected call
+  params | p            dump the parameters of the selected call
+  return | r            dump the return value of the selected call
+  state [address] [pre|post]
+                        dump an actor state before or after the message
+                        (defaults to the callee, after the message)
+  receipt               print the message receipt
+  help | h              print this help
+  quit | q              exit";
+
+impl<DB: Blockstore> Debugger<DB> {
+    fn new(
+        db: Arc<DB>,
+        ReplayedMessage {
+            message,
+            apply_ret,
+            pre_state,
+            post_state,
+        }: ReplayedMessage,
+    ) -> anyhow::Result<Self> {
+        let roots = Frame::parse(apply_ret.exec_trace());
+        if roots.is_empty() {
+            bail!("the execution trace is empty");
+        }
+        // Callers may be created by the message itself, so resolve them in
+        // the state after it.
+        let tree = StateTree::new_from_root(Arc::clone(&db), &post_state)?;
+        let mut callers = HashMap::default();
+        let mut frames: Vec<&Frame> = roots.iter().collect();
+        while let Some(frame) = frames.pop() {
+            callers.entry(frame.call.from).or_insert_with(|| {
+                let id = Address::new_id(frame.call.from);
+                resolve_to_key_addr(&tree, &db, &id).unwrap_or(id)
+            });
+            frames.extend(&frame.sub_calls);
+        }
+        Ok(Self {
+            db,
+            message,
+            apply_ret,
+            pre_state,
+            post_state,
+            roots,
+            callers,
+            path: vec![0],
+        })
+    }
+
+    fn summary(&self, frame: &Frame) -> String {
+        let id = Address::new_id(frame.call.from);
+        frame.summary(self.callers.get(&frame.call.from).unwrap_or(&id))
+    }
+
+    fn selected(&self) -> &Frame {
+        let mut frame = &self.roots[self.path[0]];
+        for &i in &self.path[1..] {
+            frame = &frame.sub_calls[i];
+        }
+        frame
+    }
+
+    /// Runs a single command. Returns `false` once the user quits.
+    fn execute(&mut self, line: &str, out: &mut impl Write) -> anyhow::Result<bool> {
+        let mut words = line.split_whitespace();
+        match words.next().unwrap_or_default() {
+            "tree" | "t" => {
+                for (i, root) in self.roots.iter().enumerate() {
+                    self.print_tree(root, &mut vec![i], out)?;
+                }
+            }
+            "down" | "d" => {
+                let n = words.next().context("missing sub-call index")?.parse()?;
+                let count = self.selected().sub_calls.len();
+                if n >= count {
+                    bail!("the selected call has {count} sub-call(s)");
+                }
+                self.path.push(n);
+                writeln!(out, "{}", self.summary(self.selected()))?;
+            }
+            "up" | "u" => {
+                if self.path.len() == 1 {
+                    bail!("already at the top-level call");
+                }
+                self.path.pop();
+                writeln!(out, "{}", self.summary(self.selected()))?;
+            }
+            "info" | "i" => {
+                let frame = self.selected();
+                writeln!(out, "{}", self.summary(frame))?;
+                writeln!(out, "value: {}", frame.call.value)?;
+                if let Some(gas_limit) = frame.call.gas_limit {
+                    writeln!(out, "gas limit: {gas_limit}")?;
+                }
+                writeln!(out, "sub-calls: {}", frame.sub_calls.len())?;
+            }
+            "params" | "p" => {
+                let params = self.selected().call.params.clone();
+                dump_payload(params, out)?;
+            }
+            "return" | "r" => match self.selected().outcome.clone() {
+                Some(Outcome::Return(ret)) => dump_payload(ret.data, out)?,
+                Some(Outcome::Abort(code)) => writeln!(out, "aborted with {}", code.value())?,
+                Some(Outcome::Error(e)) => writeln!(out, "{e}")?,
+                None => writeln!(out, "the call did not return")?,
+            },
+            "state" | "s" => {
+                let mut address = self.selected().call.to;
+                let mut root = self.post_state;
+                for word in words {
+                    match word {
+                        "pre" => root = self.pre_state,
+                        "post" => root = self.post_state,
+                        _ => address = word.parse()?,
+                    }
+                }
+                let tree = StateTree::new_from_root(Arc::clone(&self.db), &root)?;
+                let actor = tree
+                    .get_actor(&address)?
+                    .with_context(|| format!("actor {address} not found in state {root}"))?;
+                writeln!(out, "code: {}", actor.code)?;
+                writeln!(out, "balance: {}", actor.balance)?;
+                writeln!(out, "sequence: {}", actor.sequence)?;
+                let state = self
+                    .db
+                    .get_cbor::<Ipld>(&actor.state)?
+                    .context("actor state not found")?;
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::to_string_pretty(&IpldJsonRef(&state))?
+                )?;
+            }
+            "receipt" => {
+                let message = self.message.message();
+                writeln!(
+                    out,
+                    "{} -> {} method {}: exit {}, {} gas used",
+                    message.from(),
+                    message.to(),
+                    message.method_num(),
+                    self.apply_ret.msg_receipt().exit_code().value(),
+                    self.apply_ret.msg_receipt().gas_used()
+                )?;
+                if let Some(info) = self.apply_ret.failure_info() {
+                    writeln!(out, "failure: {info}")?;
+                }
+            }
+            "help" | "h" => writeln!(out, "{HELP}")?,
+            "quit" | "q" | "exit" => return Ok(false),
+            other => bail!("unknown command `{other}`, try `help`"),
+        }
+        Ok(true)
+    }
+
+    fn print_tree(
+        &self,
+        frame: &Frame,
+        path: &mut Vec<usize>,
+        out: &mut impl Write,
+    ) -> anyhow::Result<()> {
+        let marker = if *path == self.path { "*" } else { " " };
+        let index = path.last().copied().unwrap_or_default();
+        writeln!(
+            out,
+            "{marker}{}[{index}] {}",
+            "  ".repeat(path.len() - 1),
+            self.summary(frame)
+        )?;
+        for (i, sub_call) in frame.sub_calls.iter().enumerate() {
+            path.push(i);
+            self.print_tree(sub_call, path, out)?;
+            path.pop();
+        }
+        Ok(())
+    }
+}
+
+// Prints send parameters or return values, decoding them if they are CBOR.
+fn dump_payload(
+    payload: Either<RawBytes, Option<IpldBlock>>,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    let (codec, data) = match payload {
+        Either::Left(bytes) => (DAG_CBOR, Vec::from(bytes)),
+        Either::Right(Some(IpldBlock { codec, data })) => (codec, data),
+        Either::Right(None) => {
+            writeln!(out, "(none)")?;
+            return Ok(());
+        }
+    };
+    if data.is_empty() {
+        writeln!(out, "(empty)")?;
+        return Ok(());
+    }
+    match fvm_ipld_encoding::from_slice::<Ipld>(&data) {
+        Ok(ipld) if codec == DAG_CBOR || codec == fvm_ipld_encoding::CBOR => writeln!(
+            out,
+            "{}",
+            serde_json::to_string_pretty(&IpldJsonRef(&ipld))?
+        )?,
+        _ => writeln!(out, "codec {codec:#x}: 0x{}", hex::encode(&data))?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(to: u64) -> ExecutionEvent {
+        ExecutionEvent::Call(Call {
+            from: 100,
+            to: Address::new_id(to),
+            method_num: 2,
+            params: Either::Left(RawBytes::default()),
+            value: Default::default(),
+            gas_limit: None,
+            read_only: None,
+        })
+    }
+
+    fn ret() -> ExecutionEvent {
+        ExecutionEvent::CallReturn(CallReturn {
+            exit_code: None,
+            data: Either::Left(RawBytes::default()),
+        })
+    }
+
+    #[test]
+    fn parse_call_tree() {
+        let roots = Frame::parse(vec![
+            call(1),
+            call(2),
+            ret(),
+            call(3),
+            call(4),
+            ExecutionEvent::CallAbort(ExitCode::from(16)),
+            ret(),
+            ret(),
+        ]);
+        assert_eq!(roots.len(), 1);
+        let root = &roots[0];
+        assert_eq!(root.call.to, Address::new_id(1));
+        assert_eq!(root.sub_calls.len(), 2);
+        assert_eq!(root.sub_calls[1].call.to, Address::new_id(3));
+        let aborted = &root.sub_calls[1].sub_calls[0];
+        assert_eq!(aborted.call.to, Address::new_id(4));
+        assert!(matches!(aborted.outcome, Some(Outcome::Abort(code)) if code.value() == 16));
+    }
+
+    #[test]
+    fn unfinished_calls_are_kept() {
+        let roots = Frame::parse(vec![call(1), call(2)]);
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].sub_calls.len(), 1);
+        assert!(roots[0].sub_calls[0].outcome.is_none());
+    }
+}
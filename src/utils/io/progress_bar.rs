@@ -94,12 +94,6 @@ impl ProgressBar {
         }
     }
 
-    pub fn finish_println(&self, s: &str) {
-        if self.display {
-            self.inner.lock().finish_println(s);
-        }
-    }
-
     /// Sets the visibility of progress bars (globally).
     pub fn set_progress_bars_visibility(visibility: ProgressBarVisibility) {
         *PROGRESS_BAR_VISIBILITY.write() = visibility;
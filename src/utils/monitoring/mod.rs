@@ -2,4 +2,6 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 mod mem_tracker;
+mod otlp;
 pub use mem_tracker::*;
+pub use otlp::*;
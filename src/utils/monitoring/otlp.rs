@@ -0,0 +1,25 @@
+// Copyright 2019-2023 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig as _;
+use opentelemetry_sdk::trace::TracerProvider;
+
+/// Builds a tracer provider that exports spans in batches to the OTLP
+/// collector at `endpoint` (gRPC, e.g. `http://localhost:4317`). Must be
+/// called within a tokio runtime, which runs the export task.
+pub fn otlp_tracer_provider(endpoint: &str, service_name: &str) -> anyhow::Result<TracerProvider> {
+    let exporter = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(endpoint)
+        .build_span_exporter()?;
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_config(opentelemetry_sdk::trace::config().with_resource(
+            opentelemetry_sdk::Resource::new([KeyValue::new(
+                "service.name",
+                service_name.to_owned(),
+            )]),
+        ))
+        .build())
+}
@@ -15,7 +15,8 @@ where
     ArgT: Into<OsString> + Clone,
 {
     // Capture Cli inputs
-    let Cli { opts, cmd } = Cli::parse_from(args);
+    let Cli { opts, output, cmd } = Cli::parse_from(args);
+    output.set_global();
 
     let api = ApiInfo::from_env()?.set_token(opts.token.clone());
 
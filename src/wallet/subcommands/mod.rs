@@ -4,6 +4,7 @@
 pub mod wallet_cmd;
 
 use crate::cli_shared::cli::{CliRpcOpts, HELP_MESSAGE};
+use crate::cli_shared::output::OutputFormat;
 use crate::utils::version::FOREST_VERSION_STRING;
 use clap::Parser;
 
@@ -15,6 +16,10 @@ pub struct Cli {
     #[clap(flatten)]
     pub opts: CliRpcOpts,
 
+    /// Format of the printed results
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
     #[command(subcommand)]
     pub cmd: wallet_cmd::WalletCommands,
 }
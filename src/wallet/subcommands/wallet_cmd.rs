@@ -6,19 +6,24 @@ use std::{
     str::{self, FromStr},
 };
 
+use crate::cli_shared::output::{self, OutputFormat};
 use crate::lotus_json::LotusJson;
+use crate::rpc_api::chain_api::HeadChangeType;
 use crate::shim::{
-    address::{Protocol, StrictAddress},
+    address::{Address, Protocol, StrictAddress},
+    clock::ChainEpoch,
     crypto::{Signature, SignatureType},
     econ::TokenAmount,
 };
 use crate::utils::io::read_file_to_string;
 use crate::{key_management::KeyInfo, rpc_client::ApiInfo};
-use anyhow::Context as _;
+use anyhow::{bail, Context as _};
 use base64::{prelude::BASE64_STANDARD, Engine};
 use clap::{arg, Subcommand};
 use dialoguer::{theme::ColorfulTheme, Password};
-use num::BigInt;
+use futures::StreamExt as _;
+use num::{BigInt, Signed as _, Zero as _};
+use serde::Serialize;
 
 use crate::cli::humantoken::TokenAmountPretty as _;
 
@@ -34,6 +39,16 @@ pub enum WalletCommands {
     Balance {
         /// The address of the account to check
         address: String,
+        /// Keep running, and print the balance again each time a new head
+        /// changes it
+        #[arg(long)]
+        watch: bool,
+        /// Shell command to run on each change, with the
+        /// `FOREST_WALLET_ADDRESS`, `FOREST_WALLET_EPOCH`,
+        /// `FOREST_WALLET_BALANCE` and `FOREST_WALLET_DELTA` environment
+        /// variables set, the amounts in attoFIL
+        #[arg(long, requires = "watch")]
+        notify_cmd: Option<String>,
     },
     /// Get the default address of the wallet
     Default,
@@ -108,37 +123,38 @@ impl WalletCommands {
                 };
 
                 let response = api.wallet_new(signature_type).await?;
-                println!("{response}");
-                Ok(())
+                output::print(&response, |address| println!("{address}"))
             }
-            Self::Balance { address } => {
+            Self::Balance {
+                address,
+                watch: true,
+                notify_cmd,
+            } => watch_balance(&api, address, notify_cmd.as_deref()).await,
+            Self::Balance { address, .. } => {
                 let response = api.wallet_balance(address.to_string()).await?;
-                println!("{response}");
-                Ok(())
+                output::print(&response, |balance| println!("{balance}"))
             }
             Self::Default => {
-                let response = api
-                    .wallet_default_address()
-                    .await?
-                    .unwrap_or_else(|| "No default wallet address set".to_string());
-                println!("{response}");
-                Ok(())
+                // `--output json` prints `null` when no default is set
+                let response = api.wallet_default_address().await?;
+                output::print(&response, |address| match address {
+                    Some(address) => println!("{address}"),
+                    None => println!("No default wallet address set"),
+                })
             }
             Self::Export { address } => {
                 let response = api.wallet_export(address.to_string()).await?;
 
                 let encoded_key = serde_json::to_string(&LotusJson(response))?;
-                println!("{}", hex::encode(encoded_key));
-                Ok(())
+                output::print(&hex::encode(encoded_key), |key| println!("{key}"))
             }
             Self::Has { key } => {
                 let response = api.wallet_has(key.to_string()).await?;
-                println!("{response}");
-                Ok(())
+                output::print(&response, |has| println!("{has}"))
             }
             Self::Delete { address } => {
                 api.wallet_delete(address.to_string()).await?;
-                println!("deleted {address}.");
+                output::status(format!("deleted {address}."));
                 Ok(())
             }
             Self::Import { path } => {
@@ -166,8 +182,7 @@ impl WalletCommands {
 
                 let key = api.wallet_import(vec![key]).await?;
 
-                println!("{key}");
-                Ok(())
+                output::print(&key, |key| println!("{key}"))
             }
             Self::List {
                 no_round,
@@ -177,37 +192,41 @@ impl WalletCommands {
 
                 let default = api.wallet_default_address().await?;
 
-                let (title_address, title_default_mark, title_balance) =
-                    ("Address", "Default", "Balance");
-                println!("{title_address:41} {title_default_mark:7} {title_balance}");
-
+                let mut entries = Vec::with_capacity(response.len());
                 for address in response {
-                    let addr = address.to_string();
-                    let default_address_mark = if default.as_ref() == Some(&addr) {
-                        "X"
-                    } else {
-                        ""
-                    };
-
-                    let balance_string = api.wallet_balance(addr.clone()).await?;
-
-                    let balance_token_amount =
-                        TokenAmount::from_atto(balance_string.parse::<BigInt>()?);
-
-                    let balance_string = match (no_round, no_abbrev) {
-                        // no_round, absolute
-                        (true, true) => format!("{:#}", balance_token_amount.pretty()),
-                        // no_round, relative
-                        (true, false) => format!("{}", balance_token_amount.pretty()),
-                        // round, absolute
-                        (false, true) => format!("{:#.4}", balance_token_amount.pretty()),
-                        // round, relative
-                        (false, false) => format!("{:.4}", balance_token_amount.pretty()),
-                    };
-
-                    println!("{addr:41}  {default_address_mark:7}  {balance_string}");
+                    let address = address.to_string();
+                    let balance = api.wallet_balance(address.clone()).await?;
+                    entries.push(WalletEntry {
+                        default: default.as_ref() == Some(&address),
+                        address,
+                        balance: TokenAmount::from_atto(balance.parse::<BigInt>()?),
+                    });
                 }
-                Ok(())
+
+                output::print(&entries, |entries| {
+                    let (title_address, title_default_mark, title_balance) =
+                        ("Address", "Default", "Balance");
+                    println!("{title_address:41} {title_default_mark:7} {title_balance}");
+
+                    for entry in entries.iter() {
+                        let default_address_mark = if entry.default { "X" } else { "" };
+                        let balance = entry.balance.pretty();
+                        let balance_string = match (no_round, no_abbrev) {
+                            // no_round, absolute
+                            (true, true) => format!("{balance:#}"),
+                            // no_round, relative
+                            (true, false) => format!("{balance}"),
+                            // round, absolute
+                            (false, true) => format!("{balance:#.4}"),
+                            // round, relative
+                            (false, false) => format!("{balance:.4}"),
+                        };
+                        println!(
+                            "{:41}  {default_address_mark:7}  {balance_string}",
+                            entry.address
+                        );
+                    }
+                })
             }
             Self::SetDefault { key } => {
                 let StrictAddress(key) = StrictAddress::from_str(key)
@@ -224,8 +243,9 @@ impl WalletCommands {
                 let message = BASE64_STANDARD.encode(message);
 
                 let response = api.wallet_sign(address, message.into_bytes()).await?;
-                println!("{}", hex::encode(response.bytes()));
-                Ok(())
+                output::print(&hex::encode(response.bytes()), |signature| {
+                    println!("{signature}")
+                })
             }
             Self::Verify {
                 message,
@@ -245,9 +265,171 @@ impl WalletCommands {
 
                 let response = api.wallet_verify(address, msg, signature).await?;
 
-                println!("{response}");
-                Ok(())
+                output::print(&response, |valid| println!("{valid}"))
             }
         }
     }
 }
+
+/// Address of the wallet, as printed by `forest-wallet list`.
+#[derive(Debug, Serialize)]
+struct WalletEntry {
+    address: String,
+    default: bool,
+    #[serde(with = "crate::lotus_json")]
+    balance: TokenAmount,
+}
+
+/// Balance of an address at an epoch, as printed by `forest-wallet balance
+/// --watch`. The delta is absent for the first balance.
+#[derive(Debug, Serialize)]
+struct BalanceChange {
+    epoch: ChainEpoch,
+    #[serde(with = "crate::lotus_json")]
+    balance: TokenAmount,
+    #[serde(with = "crate::lotus_json", skip_serializing_if = "Option::is_none")]
+    delta: Option<TokenAmount>,
+}
+
+impl BalanceChange {
+    fn text(&self) -> String {
+        match &self.delta {
+            None => format!("Epoch {}: {}", self.epoch, self.balance.pretty()),
+            Some(delta) => format!(
+                "Epoch {}: {} ({})",
+                self.epoch,
+                self.balance.pretty(),
+                format_delta(delta)
+            ),
+        }
+    }
+}
+
+/// Prints the balance of `address` at the current head, then again with the
+/// difference each time a new head changes it. `--output json` prints one JSON
+/// object per line.
+async fn watch_balance(
+    api: &ApiInfo,
+    address: &str,
+    notify_cmd: Option<&str>,
+) -> anyhow::Result<()> {
+    let StrictAddress(address) =
+        StrictAddress::from_str(address).with_context(|| format!("Invalid address: {address}"))?;
+    let mut balance = None;
+    // The first notification is the current head
+    let mut head_changes = api.chain_notify().await?;
+    while let Some(changes) = head_changes.next().await {
+        for change in changes? {
+            // The tipsets applied after a revert report the new balance
+            if change.r#type == HeadChangeType::Revert {
+                continue;
+            }
+            let epoch = change.val.epoch();
+            let current = api
+                .state_get_actor(address, change.val.key().clone())
+                .await?
+                .map(|actor| TokenAmount::from(&actor.balance))
+                .unwrap_or_else(TokenAmount::zero);
+            let delta = match &balance {
+                None => None,
+                Some(previous) if previous != &current => Some(current.clone() - previous),
+                // Unchanged
+                Some(_) => continue,
+            };
+            let change = BalanceChange {
+                epoch,
+                balance: current.clone(),
+                delta,
+            };
+            match OutputFormat::get() {
+                OutputFormat::Text => println!("{}", change.text()),
+                OutputFormat::Json => println!("{}", serde_json::to_string(&change)?),
+            }
+            if let (Some(delta), Some(notify_cmd)) = (&change.delta, notify_cmd) {
+                if let Err(e) = run_notify_cmd(notify_cmd, &address, epoch, &current, delta).await {
+                    eprintln!("{e:#}");
+                }
+            }
+            balance = Some(current);
+        }
+    }
+    bail!("the node closed the subscription")
+}
+
+/// Formats a balance change with its sign, e.g. `+1 FIL`.
+fn format_delta(delta: &TokenAmount) -> String {
+    let sign = if delta.is_negative() { '-' } else { '+' };
+    let magnitude = TokenAmount::from_atto(delta.atto().abs());
+    format!("{sign}{}", magnitude.pretty())
+}
+
+async fn run_notify_cmd(
+    notify_cmd: &str,
+    address: &Address,
+    epoch: ChainEpoch,
+    balance: &TokenAmount,
+    delta: &TokenAmount,
+) -> anyhow::Result<()> {
+    let status = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(notify_cmd)
+        .env("FOREST_WALLET_ADDRESS", address.to_string())
+        .env("FOREST_WALLET_EPOCH", epoch.to_string())
+        .env("FOREST_WALLET_BALANCE", balance.atto().to_string())
+        .env("FOREST_WALLET_DELTA", delta.atto().to_string())
+        .status()
+        .await
+        .with_context(|| format!("could not run {notify_cmd}"))?;
+    anyhow::ensure!(status.success(), "{notify_cmd} failed: {status}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delta_sign() {
+        assert_eq!(format_delta(&TokenAmount::from_whole(1)), "+1 FIL");
+        assert_eq!(format_delta(&TokenAmount::from_whole(-1)), "-1 FIL");
+        assert_eq!(format_delta(&TokenAmount::zero()), "+0 FIL");
+    }
+
+    #[test]
+    fn json_output() {
+        let entry = WalletEntry {
+            address: "t01234".into(),
+            default: true,
+            balance: TokenAmount::from_whole(1),
+        };
+        assert_eq!(
+            serde_json::to_value(entry).unwrap(),
+            serde_json::json!({
+                "address": "t01234",
+                "default": true,
+                "balance": "1000000000000000000",
+            })
+        );
+
+        let first = BalanceChange {
+            epoch: 10,
+            balance: TokenAmount::from_atto(5),
+            delta: None,
+        };
+        assert_eq!(first.text(), "Epoch 10: 5 attoFIL");
+        assert_eq!(
+            serde_json::to_string(&first).unwrap(),
+            r#"{"epoch":10,"balance":"5"}"#
+        );
+        let change = BalanceChange {
+            epoch: 11,
+            balance: TokenAmount::from_atto(2),
+            delta: Some(TokenAmount::from_atto(-3)),
+        };
+        assert_eq!(change.text(), "Epoch 11: 2 attoFIL (-3 attoFIL)");
+        assert_eq!(
+            serde_json::to_string(&change).unwrap(),
+            r#"{"epoch":11,"balance":"2","delta":"-3"}"#
+        );
+    }
+}